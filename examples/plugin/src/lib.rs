@@ -0,0 +1,55 @@
+//! Pavlov (Win-Stay, Lose-Shift) as a native strategy plugin for actoripd
+//!
+//! Actions are 0 (cooperate) and 1 (defect), or -1 before the first round.
+
+use std::ffi::c_void;
+
+const COOPERATE: i32 = 0;
+const DEFECT: i32 = 1;
+
+/// Per-prisoner state; Pavlov only needs the last round, but a plugin may
+/// keep as much as it likes
+struct Pavlov {
+    rounds: u32,
+}
+
+#[no_mangle]
+pub extern "C" fn actoripd_abi_version() -> u32 {
+    1
+}
+
+#[no_mangle]
+pub extern "C" fn actoripd_new(_seed: u64) -> *mut c_void {
+    Box::into_raw(Box::new(Pavlov { rounds: 0 })) as *mut c_void
+}
+
+/// # Safety
+///
+/// `instance` must come from `actoripd_new` and not yet be freed.
+#[no_mangle]
+pub unsafe extern "C" fn actoripd_choose(
+    instance: *mut c_void,
+    _round: u32,
+    own: i32,
+    opponent: i32,
+) -> i32 {
+    let pavlov = &mut *(instance as *mut Pavlov);
+    pavlov.rounds += 1;
+    match (own, opponent) {
+        // start by cooperating
+        (-1, _) => COOPERATE,
+        // stay after a reward or temptation, i.e. when the opponent cooperated
+        (own, COOPERATE) => own,
+        // shift after a punishment or sucker payoff
+        (COOPERATE, _) => DEFECT,
+        _ => COOPERATE,
+    }
+}
+
+/// # Safety
+///
+/// `instance` must come from `actoripd_new` and not yet be freed.
+#[no_mangle]
+pub unsafe extern "C" fn actoripd_free(instance: *mut c_void) {
+    drop(Box::from_raw(instance as *mut Pavlov));
+}
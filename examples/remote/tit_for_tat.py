@@ -0,0 +1,29 @@
+#!/usr/bin/env python3
+"""Tit-for-Tat as a remote strategy.
+
+Run it, then name it in a config or on the command line:
+
+    python3 examples/remote/tit_for_tat.py 7878
+    cargo run -- --blue remote:localhost:7878 --red pavlov run
+"""
+
+import json
+import socketserver
+import sys
+
+
+class TitForTat(socketserver.StreamRequestHandler):
+    def handle(self):
+        greeting = json.loads(self.rfile.readline())
+        if greeting.get("protocol") != 1:
+            return
+        for line in self.rfile:
+            query = json.loads(line)
+            action = query["opponent"] or "C"
+            self.wfile.write(f"{action}\n".encode())
+
+
+if __name__ == "__main__":
+    port = int(sys.argv[1]) if len(sys.argv) > 1 else 7878
+    with socketserver.ThreadingTCPServer(("localhost", port), TitForTat) as server:
+        server.serve_forever()
@@ -0,0 +1,35 @@
+//! Tit-for-Two-Tats as a WebAssembly strategy for actoripd
+//!
+//! The host calls `reset` between matches and `choose` once per round.
+//! Actions are 0 (cooperate) and 1 (defect), or -1 before the first round.
+
+use std::sync::atomic::{AtomicI32, Ordering};
+
+const COOPERATE: i32 = 0;
+const DEFECT: i32 = 1;
+
+/// The opponent's defections in a row, up to last round
+static DEFECTIONS: AtomicI32 = AtomicI32::new(0);
+
+#[no_mangle]
+pub extern "C" fn init(_seed: i64) {}
+
+#[no_mangle]
+pub extern "C" fn reset() {
+    DEFECTIONS.store(0, Ordering::Relaxed);
+}
+
+#[no_mangle]
+pub extern "C" fn choose(_round: i32, _own: i32, opponent: i32) -> i32 {
+    let defections = if opponent == DEFECT {
+        DEFECTIONS.fetch_add(1, Ordering::Relaxed) + 1
+    } else {
+        DEFECTIONS.store(0, Ordering::Relaxed);
+        0
+    };
+    if defections >= 2 {
+        DEFECT
+    } else {
+        COOPERATE
+    }
+}
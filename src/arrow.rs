@@ -0,0 +1,294 @@
+//! Round, match, and sweep tables as Arrow IPC files
+//!
+//! The files are in the Arrow IPC file format, which polars and pandas can
+//! memory-map without parsing, so a notebook can load the rounds of a large
+//! run at once:
+//!
+//! ```python
+//! rounds = polars.read_ipc("rounds.arrow", memory_map=True)
+//! matches = polars.read_ipc("matches.arrow")
+//! rounds.join(matches, on="match")
+//! ```
+//!
+//! - [`write_matches`]: one row per match, numbered from 0 in the order of
+//!   the record, with both players, the rounds played, and each side's
+//!   score and cooperations.
+//! - [`write_rounds`]: one row per round of every match, with the match's
+//!   number, the round from 0, what each side played, whether noise flipped
+//!   it, and what each side was paid.
+//! - [`write_sweep`]: the rows of [`SweepRecord::to_csv`], one column per
+//!   axis and one per field of the standings.
+//!
+//! Actions are written as their letters, `C` and `D` and so on.
+
+use arrow_array::{ArrayRef, BooleanArray, Float64Array, RecordBatch, StringArray, UInt64Array};
+use arrow_ipc::writer::FileWriter;
+use arrow_schema::ArrowError;
+use std::fs::File;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::schema;
+use crate::sweep::SweepRecord;
+use crate::tournament::{Standing, TournamentRecord};
+use crate::transcript::RoundRecord;
+
+fn counts(values: impl Iterator<Item = usize>) -> ArrayRef {
+    Arc::new(UInt64Array::from_iter_values(values.map(|n| n as u64)))
+}
+
+fn numbers(values: impl Iterator<Item = f64>) -> ArrayRef {
+    Arc::new(Float64Array::from_iter_values(values))
+}
+
+fn texts<'a>(values: impl Iterator<Item = &'a str>) -> ArrayRef {
+    Arc::new(StringArray::from_iter_values(values))
+}
+
+fn flags(values: impl Iterator<Item = bool>) -> ArrayRef {
+    Arc::new(BooleanArray::from(values.collect::<Vec<_>>()))
+}
+
+/// Write the named `columns` to `path` as one record batch
+fn write(path: &Path, columns: Vec<(String, ArrayRef)>) -> Result<(), ArrowError> {
+    let batch = RecordBatch::try_from_iter(columns)?;
+    let file = File::create(path)?;
+    let mut writer = FileWriter::try_new(file, &batch.schema())?;
+    writer.write(&batch)?;
+    writer.finish()
+}
+
+fn named(columns: Vec<(&str, ArrayRef)>) -> Vec<(String, ArrayRef)> {
+    columns
+        .into_iter()
+        .map(|(name, column)| (name.to_owned(), column))
+        .collect()
+}
+
+/// Write one row per match of `record` to `path`
+pub fn write_matches(record: &TournamentRecord, path: &Path) -> Result<(), ArrowError> {
+    let matches = &record.matches;
+    write(
+        path,
+        named(vec![
+            ("match", counts(0..matches.len())),
+            ("blue", texts(matches.iter().map(|m| m.blue.name.as_str()))),
+            ("red", texts(matches.iter().map(|m| m.red.name.as_str()))),
+            ("rounds", counts(matches.iter().map(|m| m.rounds))),
+            ("blue_score", numbers(matches.iter().map(|m| m.blue.score))),
+            ("red_score", numbers(matches.iter().map(|m| m.red.score))),
+            (
+                "blue_cooperations",
+                counts(matches.iter().map(|m| m.blue.cooperations)),
+            ),
+            (
+                "red_cooperations",
+                counts(matches.iter().map(|m| m.red.cooperations)),
+            ),
+        ]),
+    )
+}
+
+/// Write one row per round of every match of `record` to `path`
+pub fn write_rounds(record: &TournamentRecord, path: &Path) -> Result<(), ArrowError> {
+    let rounds: Vec<(usize, &RoundRecord)> = record
+        .matches
+        .iter()
+        .enumerate()
+        .flat_map(|(i, result)| result.transcript.iter().map(move |round| (i, round)))
+        .collect();
+    let letters = |side: fn(&RoundRecord) -> char| -> Vec<String> {
+        rounds
+            .iter()
+            .map(|(_, round)| side(round).to_string())
+            .collect()
+    };
+    let (blue, red) = (
+        letters(|round| round.blue.executed.letter()),
+        letters(|round| round.red.executed.letter()),
+    );
+    write(
+        path,
+        named(vec![
+            ("match", counts(rounds.iter().map(|&(i, _)| i))),
+            ("round", counts(rounds.iter().map(|(_, r)| r.round))),
+            ("blue_action", texts(blue.iter().map(String::as_str))),
+            ("red_action", texts(red.iter().map(String::as_str))),
+            (
+                "blue_flipped",
+                flags(rounds.iter().map(|(_, r)| r.blue.executed != r.blue.chosen)),
+            ),
+            (
+                "red_flipped",
+                flags(rounds.iter().map(|(_, r)| r.red.executed != r.red.chosen)),
+            ),
+            (
+                "blue_payoff",
+                numbers(rounds.iter().map(|(_, r)| r.blue.outcome.amount)),
+            ),
+            (
+                "red_payoff",
+                numbers(rounds.iter().map(|(_, r)| r.red.outcome.amount)),
+            ),
+        ]),
+    )
+}
+
+/// Write the standings at every point of `record` to `path`, as
+/// [`SweepRecord::to_csv`] does
+pub fn write_sweep(record: &SweepRecord, path: &Path) -> Result<(), ArrowError> {
+    // each point's values, and each standing there with its rank from 1
+    let rows: Vec<(&[f64], usize, &Standing)> = record
+        .points
+        .iter()
+        .flat_map(|point| {
+            let values = point.values.as_slice();
+            point
+                .standings
+                .iter()
+                .enumerate()
+                .map(move |(i, standing)| (values, i + 1, standing))
+        })
+        .collect();
+    let mut columns = vec![(
+        schema::VERSION_FIELD.to_owned(),
+        counts(rows.iter().map(|_| schema::SCHEMA_VERSION as usize)),
+    )];
+    for (k, axis) in record.axes.iter().enumerate() {
+        columns.push((
+            axis.setting.to_string(),
+            numbers(rows.iter().map(|(values, _, _)| values[k])),
+        ));
+    }
+    columns.extend(named(vec![
+        ("rank", counts(rows.iter().map(|&(_, rank, _)| rank))),
+        ("name", texts(rows.iter().map(|(_, _, s)| s.name.as_str()))),
+        (
+            "strategy",
+            texts(rows.iter().map(|(_, _, s)| s.strategy.as_str())),
+        ),
+        ("matches", counts(rows.iter().map(|(_, _, s)| s.matches))),
+        ("rounds", counts(rows.iter().map(|(_, _, s)| s.rounds))),
+        ("score", numbers(rows.iter().map(|(_, _, s)| s.score))),
+        (
+            "average_score",
+            numbers(rows.iter().map(|(_, _, s)| s.average_score)),
+        ),
+        (
+            "average_match_score",
+            numbers(rows.iter().map(|(_, _, s)| s.average_match_score)),
+        ),
+        (
+            "cooperation_rate",
+            numbers(rows.iter().map(|(_, _, s)| s.cooperation_rate)),
+        ),
+    ]));
+    write(path, columns)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{ParticipantConfig, SimConfig};
+    use crate::registry::StrategyRegistry;
+    use crate::sweep::Sweep;
+    use crate::tournament;
+    use arrow_array::cast::AsArray;
+    use arrow_array::types::{Float64Type, UInt64Type};
+    use arrow_ipc::reader::FileReader;
+
+    fn config() -> SimConfig {
+        SimConfig {
+            iterations: 3,
+            participants: vec![
+                ParticipantConfig::new("alld", "always-defect"),
+                ParticipantConfig::new("allc", "always-cooperate"),
+            ],
+            ..SimConfig::default()
+        }
+    }
+
+    fn read(path: &Path) -> RecordBatch {
+        let reader = FileReader::try_new(File::open(path).unwrap(), None).unwrap();
+        let batches: Vec<RecordBatch> = reader.map(|batch| batch.unwrap()).collect();
+        assert_eq!(batches.len(), 1);
+        batches.into_iter().next().unwrap()
+    }
+
+    fn temp(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("actoripd-{}-{}.arrow", name, std::process::id()))
+    }
+
+    #[actix_rt::test]
+    async fn matches_and_rounds_read_back() {
+        let config = config();
+        let registry = StrategyRegistry::new();
+        let reports = tournament::round_robin(&config, &registry, 1, None).await;
+        let record = TournamentRecord::new(&config, 1, reports);
+
+        let path = temp("matches");
+        write_matches(&record, &path).unwrap();
+        let matches = read(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(matches.num_rows(), 1);
+        assert_eq!(
+            matches
+                .column_by_name("blue")
+                .unwrap()
+                .as_string::<i32>()
+                .value(0),
+            "alld"
+        );
+        let score = matches.column_by_name("blue_score").unwrap();
+        assert_eq!(score.as_primitive::<Float64Type>().value(0), 12.0);
+
+        let path = temp("rounds");
+        write_rounds(&record, &path).unwrap();
+        let rounds = read(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(rounds.num_rows(), 3);
+        let round = rounds
+            .column_by_name("round")
+            .unwrap()
+            .as_primitive::<UInt64Type>();
+        assert_eq!(round.values().to_vec(), vec![0, 1, 2]);
+        let red = rounds
+            .column_by_name("red_action")
+            .unwrap()
+            .as_string::<i32>();
+        assert_eq!(red.value(2), "C");
+        let paid = rounds.column_by_name("red_payoff").unwrap();
+        assert_eq!(paid.as_primitive::<Float64Type>().value(0), 1.0);
+        assert!(!rounds
+            .column_by_name("blue_flipped")
+            .unwrap()
+            .as_boolean()
+            .value(0));
+    }
+
+    #[actix_rt::test]
+    async fn sweeps_have_a_column_per_axis() {
+        let registry = StrategyRegistry::new();
+        let axes = vec!["noise=0,0.1".parse().unwrap()];
+        let sweep = Sweep::new(&config(), axes, &registry).unwrap();
+        let record = sweep.run(&registry, 1).await;
+
+        let path = temp("sweep");
+        write_sweep(&record, &path).unwrap();
+        let batch = read(&path);
+        std::fs::remove_file(&path).unwrap();
+        let names: Vec<&str> = batch
+            .schema_ref()
+            .fields()
+            .iter()
+            .map(|field| field.name().as_str())
+            .collect();
+        assert_eq!(names[..4], ["schema_version", "noise", "rank", "name"]);
+        assert_eq!(batch.num_rows(), 4);
+        let noise = batch
+            .column_by_name("noise")
+            .unwrap()
+            .as_primitive::<Float64Type>();
+        assert_eq!(noise.values().to_vec(), vec![0.0, 0.0, 0.1, 0.1]);
+    }
+}
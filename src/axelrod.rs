@@ -0,0 +1,245 @@
+//! Tournaments in the interactions CSV of the Axelrod Python library
+//!
+//! [`interactions_csv`] writes a tournament the way `axelrod.Tournament`
+//! writes one with `play(filename=...)`: two rows per match, one from each
+//! player's side, with the library's header. Its `ResultSet` can then read
+//! the file, given the number of players and repetitions, so the
+//! notebooks and plots written for that library work on actoripd's
+//! results:
+//!
+//! ```python
+//! results = axelrod.ResultSet("interactions.csv", players, repetitions)
+//! ```
+//!
+//! Players are indexed in config order, and a participant's twin, in
+//! self-play, shares its index, as a player does when the library matches
+//! it against itself. `Actions` spells each side's executed moves with `C`
+//! and `D`. The library has no move for abstaining, so a tournament in
+//! which anyone abstained, as in the loner game, isn't exported.
+
+use std::collections::{BTreeMap, HashMap};
+
+use crate::config::twin_name;
+use crate::game::Action;
+use crate::tournament::TournamentRecord;
+
+const HEADER: &str = "Interaction index,Player index,Opponent index,Repetition,\
+Player name,Opponent name,Actions,Score,Score difference,Turns,Score per turn,\
+Score difference per turn,Win,Initial cooperation,Cooperation count,CC count,\
+CD count,DC count,DD count,CC to C count,CD to C count,DC to C count,\
+DD to C count,Good partner";
+
+/// The library's letter for `action`, which has none for abstaining or
+/// any action beyond defecting
+fn letter(action: Action) -> Option<char> {
+    match action {
+        Action::COOPERATE => Some('C'),
+        Action::DEFECT => Some('D'),
+        _ => None,
+    }
+}
+
+/// A field that may need quoting, such as a name with a comma
+fn field(s: &str) -> String {
+    if s.contains([',', '"', '\n']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_owned()
+    }
+}
+
+/// One side of a match: its own moves and its opponent's, round by round
+struct Side<'a> {
+    index: usize,
+    name: &'a str,
+    score: f64,
+    own: &'a [Action],
+    opponent: &'a [Action],
+}
+
+impl Side<'_> {
+    fn cooperations(&self) -> usize {
+        self.own.iter().filter(|&&a| a == Action::COOPERATE).count()
+    }
+
+    /// Rounds in which this side played `own` and the opponent `opponent`
+    fn state(&self, own: Action, opponent: Action) -> usize {
+        self.own
+            .iter()
+            .zip(self.opponent)
+            .filter(|&(&a, &b)| a == own && b == opponent)
+            .count()
+    }
+
+    /// Times this side cooperated in the round after playing `own` against
+    /// `opponent`
+    fn state_to_cooperation(&self, own: Action, opponent: Action) -> usize {
+        self.own
+            .windows(2)
+            .zip(self.opponent)
+            .filter(|&(moves, &b)| {
+                moves[0] == own && b == opponent && moves[1] == Action::COOPERATE
+            })
+            .count()
+    }
+}
+
+/// Add `side`'s row for a match, or say in which round it abstained
+fn row(
+    csv: &mut String,
+    interaction: usize,
+    repetition: usize,
+    side: &Side,
+    other: &Side,
+) -> Result<(), String> {
+    let turns = side.own.len();
+    let difference = side.score - other.score;
+    let per_turn = |x: f64| if turns == 0 { 0.0 } else { x / turns as f64 };
+    let actions: String = side
+        .own
+        .iter()
+        .enumerate()
+        .map(|(round, &action)| {
+            letter(action).ok_or_else(|| {
+                format!(
+                    "{} abstained in round {} of match {}, which the Axelrod library can't represent",
+                    side.name,
+                    round + 1,
+                    interaction
+                )
+            })
+        })
+        .collect::<Result<_, _>>()?;
+    let (c, d) = (Action::COOPERATE, Action::DEFECT);
+    let fields = [
+        interaction.to_string(),
+        side.index.to_string(),
+        other.index.to_string(),
+        repetition.to_string(),
+        field(side.name),
+        field(other.name),
+        actions,
+        side.score.to_string(),
+        difference.to_string(),
+        turns.to_string(),
+        per_turn(side.score).to_string(),
+        per_turn(difference).to_string(),
+        ((side.score > other.score) as u8).to_string(),
+        ((side.own.first() == Some(&c)) as u8).to_string(),
+        side.cooperations().to_string(),
+        side.state(c, c).to_string(),
+        side.state(c, d).to_string(),
+        side.state(d, c).to_string(),
+        side.state(d, d).to_string(),
+        side.state_to_cooperation(c, c).to_string(),
+        side.state_to_cooperation(c, d).to_string(),
+        side.state_to_cooperation(d, c).to_string(),
+        side.state_to_cooperation(d, d).to_string(),
+        ((side.cooperations() >= other.cooperations()) as u8).to_string(),
+    ];
+    csv.push_str(&fields.join(","));
+    csv.push('\n');
+    Ok(())
+}
+
+/// Every finished match of `record` as the library's interactions CSV
+///
+/// A pairing's repetitions are numbered from 0 in the order they were
+/// played. Fails if any side abstained in any round.
+pub fn interactions_csv(record: &TournamentRecord) -> Result<String, String> {
+    let participants = &record.config.participants;
+    let mut indexes: HashMap<String, usize> = HashMap::new();
+    for (i, participant) in participants.iter().enumerate() {
+        indexes.insert(participant.name.clone(), i);
+        indexes.insert(twin_name(&participant.name), i);
+    }
+    let index = |name: &str| *indexes.get(name).expect("a participant's match");
+
+    let mut csv = String::from(HEADER);
+    csv.push('\n');
+    let mut repetitions: BTreeMap<(usize, usize), usize> = BTreeMap::new();
+    for (interaction, result) in record.matches.iter().enumerate() {
+        let blue_moves: Vec<Action> = result.transcript.iter().map(|r| r.blue.executed).collect();
+        let red_moves: Vec<Action> = result.transcript.iter().map(|r| r.red.executed).collect();
+        let blue = Side {
+            index: index(&result.blue.name),
+            name: &result.blue.name,
+            score: result.blue.score,
+            own: &blue_moves,
+            opponent: &red_moves,
+        };
+        let red = Side {
+            index: index(&result.red.name),
+            name: &result.red.name,
+            score: result.red.score,
+            own: &red_moves,
+            opponent: &blue_moves,
+        };
+        let pairing = (blue.index.min(red.index), blue.index.max(red.index));
+        let repetition = repetitions.entry(pairing).or_insert(0);
+        row(&mut csv, interaction, *repetition, &blue, &red)?;
+        row(&mut csv, interaction, *repetition, &red, &blue)?;
+        *repetition += 1;
+    }
+    Ok(csv)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{ParticipantConfig, SimConfig};
+    use crate::referee::MatchResult;
+
+    fn record(matches: Vec<MatchResult>) -> TournamentRecord {
+        TournamentRecord {
+            config: SimConfig {
+                participants: vec![
+                    ParticipantConfig::new("tft", "tit-for-tat"),
+                    ParticipantConfig::new("alld", "always-defect"),
+                ],
+                ..SimConfig::default()
+            },
+            seed: 0,
+            matches,
+            aborted: Vec::new(),
+            standings: Vec::new(),
+            disqualified: Vec::new(),
+            ratings: Vec::new(),
+            pairings: Vec::new(),
+            placings: Vec::new(),
+            memories: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn a_match_is_two_rows_under_the_library_header() {
+        let csv = interactions_csv(&record(vec![MatchResult::played(
+            ("tft", "CDDC"),
+            ("alld", "DDCC"),
+        )]))
+        .unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], HEADER);
+        assert_eq!(lines.len(), 3);
+        // tft: CD, DD, DC, CC; cooperated after DC only
+        assert_eq!(
+            lines[1],
+            "0,0,1,0,tft,alld,CDDC,10,0,4,2.5,0,0,1,2,1,1,1,1,0,0,1,0,1"
+        );
+        // alld: DC, DD, CD, CC; cooperated after DD and CD
+        assert_eq!(
+            lines[2],
+            "0,1,0,0,alld,tft,DDCC,10,0,4,2.5,0,0,0,2,1,1,1,1,0,1,0,1,1"
+        );
+    }
+
+    #[test]
+    fn abstentions_are_refused() {
+        let error = interactions_csv(&record(vec![MatchResult::played(
+            ("tft", "CC"),
+            ("alld", "CA"),
+        )]))
+        .unwrap_err();
+        assert!(error.contains("alld abstained in round 2"), "{}", error);
+    }
+}
@@ -0,0 +1,213 @@
+//! A standard workload for measuring the messaging between actors
+//!
+//! [`run`] plays long matches between two strategies, by default a million
+//! rounds of `tit-for-tat` against `pavlov`, and times every round: each
+//! match's referee publishes its rounds to a [`Stopwatch`] of its own,
+//! which notes when each arrives. Since the default strategies look at
+//! the history, every round takes a fresh question to each prisoner and an
+//! answer back, so the timings follow the cost of a message round trip
+//! more than that of the strategies. The workload is the same from run to
+//! run, so a drop in throughput between builds points at a regression.
+//!
+//! Publishing the rounds costs a little throughput of its own, the same in
+//! every run.
+
+use actix::prelude::*;
+use futures::future::join_all;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use serde::Serialize;
+use std::time::{Duration, Instant};
+
+use crate::config::{ConfigError, ParticipantConfig, SimConfig};
+use crate::events::{self, GameEvent};
+use crate::registry::StrategyRegistry;
+use crate::tournament::{self, MatchSeeds};
+
+/// What to play
+#[derive(Debug, Clone)]
+pub struct BenchSettings {
+    pub blue: String,
+    pub red: String,
+
+    /// Rounds in each match
+    pub rounds: usize,
+
+    /// Matches played at once
+    pub matches: usize,
+    pub seed: u64,
+}
+
+impl Default for BenchSettings {
+    fn default() -> BenchSettings {
+        BenchSettings {
+            blue: "tit-for-tat".to_owned(),
+            red: "pavlov".to_owned(),
+            rounds: 1_000_000,
+            matches: 1,
+            seed: 0,
+        }
+    }
+}
+
+/// How long rounds took, in microseconds
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Latency {
+    pub p50: f64,
+    pub p90: f64,
+    pub p99: f64,
+    pub p999: f64,
+    pub max: f64,
+}
+
+impl Latency {
+    /// Percentiles of `laps`, which must not be empty
+    fn new(laps: &mut [Duration]) -> Latency {
+        laps.sort_unstable();
+        let at = |q: f64| {
+            let i = ((q * laps.len() as f64).ceil() as usize).clamp(1, laps.len()) - 1;
+            laps[i].as_secs_f64() * 1e6
+        };
+        Latency {
+            p50: at(0.5),
+            p90: at(0.9),
+            p99: at(0.99),
+            p999: at(0.999),
+            max: at(1.0),
+        }
+    }
+}
+
+/// What a benchmark measured
+#[derive(Debug, Clone, Serialize)]
+pub struct BenchRecord {
+    pub blue: String,
+    pub red: String,
+    pub matches: usize,
+
+    /// Rounds played in all, over every match
+    pub rounds: usize,
+    pub seconds: f64,
+    pub rounds_per_second: f64,
+
+    /// From one round's arrival at the stopwatch to the next's, the first
+    /// round timed from the start of the benchmark
+    pub latency: Latency,
+}
+
+/// Notes when each round of a match is published
+pub struct Stopwatch {
+    last: Instant,
+    laps: Vec<Duration>,
+}
+
+impl Stopwatch {
+    fn new(start: Instant, rounds: usize) -> Stopwatch {
+        Stopwatch {
+            last: start,
+            laps: Vec::with_capacity(rounds),
+        }
+    }
+}
+
+impl Actor for Stopwatch {
+    type Context = Context<Self>;
+}
+
+impl Handler<GameEvent> for Stopwatch {
+    type Result = ();
+
+    fn handle(&mut self, event: GameEvent, _ctx: &mut Context<Self>) {
+        if let GameEvent::RoundPlayed { .. } = event {
+            let now = Instant::now();
+            self.laps.push(now - self.last);
+            self.last = now;
+        }
+    }
+}
+
+/// Hand over the laps timed so far
+struct Laps;
+
+impl Message for Laps {
+    type Result = Vec<Duration>;
+}
+
+impl Handler<Laps> for Stopwatch {
+    type Result = MessageResult<Laps>;
+
+    fn handle(&mut self, _msg: Laps, _ctx: &mut Context<Self>) -> Self::Result {
+        MessageResult(std::mem::take(&mut self.laps))
+    }
+}
+
+/// Play the benchmark's matches and measure them
+///
+/// Fails if `registry` doesn't know the strategies, or if there is no
+/// match or no round to play.
+pub async fn run(
+    settings: &BenchSettings,
+    registry: &StrategyRegistry,
+) -> Result<BenchRecord, ConfigError> {
+    let config = SimConfig {
+        iterations: settings.rounds,
+        participants: vec![
+            ParticipantConfig::new("blue", &settings.blue),
+            ParticipantConfig::new("red", &settings.red),
+        ],
+        ..SimConfig::default()
+    };
+    config.validate(registry)?;
+    if settings.matches == 0 {
+        return Err(ConfigError::Invalid(
+            "a benchmark needs at least one match".to_owned(),
+        ));
+    }
+
+    let mut seeds = StdRng::seed_from_u64(settings.seed);
+    let (blue, red) = (&config.participants[0], &config.participants[1]);
+    let start = Instant::now();
+    let mut stopwatches = Vec::with_capacity(settings.matches);
+    let mut matches = Vec::with_capacity(settings.matches);
+    for _ in 0..settings.matches {
+        let stopwatch = Stopwatch::new(start, settings.rounds).start();
+        // the referee publishes to whatever bus is set when it is made
+        events::publish_to(Some(stopwatch.clone().recipient()));
+        matches.push(tournament::start_match(
+            &config,
+            registry,
+            blue,
+            red,
+            MatchSeeds::draw(&mut seeds),
+            None,
+            &tournament::Shared::default(),
+        ));
+        stopwatches.push(stopwatch);
+    }
+    events::publish_to(None);
+    let reports = join_all(matches).await;
+    let seconds = start.elapsed().as_secs_f64();
+
+    let mut laps = Vec::new();
+    for stopwatch in &stopwatches {
+        // queued after every round, so the laps are complete
+        laps.extend(stopwatch.send(Laps).await.unwrap_or_default());
+    }
+    if laps.is_empty() {
+        return Err(ConfigError::Invalid("no rounds were played".to_owned()));
+    }
+    let rounds = reports
+        .iter()
+        .filter_map(|report| report.result.as_ref().ok())
+        .map(|result| result.rounds)
+        .sum::<usize>();
+    Ok(BenchRecord {
+        blue: settings.blue.clone(),
+        red: settings.red.clone(),
+        matches: settings.matches,
+        rounds,
+        seconds,
+        rounds_per_second: rounds as f64 / seconds,
+        latency: Latency::new(&mut laps),
+    })
+}
@@ -0,0 +1,141 @@
+//! A shared heartbeat that matches play their rounds to
+//!
+//! A [`Clock`] [installed](install) on the thread the matches start from
+//! ticks at a fixed interval, and before every round a referee waits for
+//! the next tick. Every match that is ready plays its round on the same
+//! tick, so a tournament's matches advance one round per tick in lockstep,
+//! and someone watching the events sees them move together, round by
+//! round, at a pace they can follow.
+//!
+//! A match whose round takes longer than the interval, such as one waiting
+//! on a human player, plays its next round on the first tick after it is
+//! ready, and falls behind the others rather than holding them up. Ticks
+//! only change when rounds are played, so the same seeds give the same
+//! results with or without a clock.
+
+use actix::prelude::*;
+use futures::channel::oneshot;
+use std::cell::RefCell;
+use std::time::Duration;
+
+thread_local! {
+    static INSTALLED: RefCell<Option<Addr<Clock>>> = const { RefCell::new(None) };
+}
+
+/// Play the rounds of the matches started from this thread to `clock`, or
+/// as fast as they go with `None`
+pub fn install(clock: Option<Addr<Clock>>) {
+    INSTALLED.with(|installed| *installed.borrow_mut() = clock);
+}
+
+/// What the matches started from this thread play to, if anything
+pub(crate) fn installed() -> Option<Addr<Clock>> {
+    INSTALLED.with(|installed| installed.borrow().clone())
+}
+
+/// Ticks every `interval`, letting every waiting match play a round
+pub struct Clock {
+    interval: Duration,
+
+    /// Ticks so far
+    ticks: u64,
+    waiting: Vec<oneshot::Sender<u64>>,
+}
+
+impl Clock {
+    pub fn new(interval: Duration) -> Clock {
+        Clock {
+            interval,
+            ticks: 0,
+            waiting: Vec::new(),
+        }
+    }
+
+    fn tick(&mut self) {
+        self.ticks += 1;
+        for waiting in self.waiting.drain(..) {
+            let _ = waiting.send(self.ticks);
+        }
+    }
+}
+
+impl Actor for Clock {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Context<Self>) {
+        ctx.run_interval(self.interval, |act, _ctx| act.tick());
+    }
+}
+
+/// Wait for the next tick, answered with its number from 1
+pub struct NextTick;
+
+impl Message for NextTick {
+    type Result = Result<u64, oneshot::Canceled>;
+}
+
+impl Handler<NextTick> for Clock {
+    type Result = ResponseFuture<Result<u64, oneshot::Canceled>>;
+
+    fn handle(&mut self, _msg: NextTick, _ctx: &mut Context<Self>) -> Self::Result {
+        let (tx, rx) = oneshot::channel();
+        self.waiting.push(tx);
+        Box::pin(rx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{ParticipantConfig, SimConfig};
+    use crate::game::Score;
+    use crate::registry::StrategyRegistry;
+    use crate::tournament;
+    use futures::future::join;
+    use std::time::Instant;
+
+    #[actix_rt::test]
+    async fn everyone_waiting_goes_on_the_same_tick() {
+        let clock = Clock::new(Duration::from_millis(10)).start();
+        let (first, second) = join(clock.send(NextTick), clock.send(NextTick)).await;
+        let (first, second) = (first.unwrap().unwrap(), second.unwrap().unwrap());
+        assert_eq!(first, second);
+        let next = clock.send(NextTick).await.unwrap().unwrap();
+        assert_eq!(next, first + 1);
+    }
+
+    #[actix_rt::test]
+    async fn matches_play_a_round_a_tick() {
+        let config = SimConfig {
+            iterations: 5,
+            noise: 0.1,
+            participants: ["tit-for-tat", "random", "pavlov"]
+                .iter()
+                .map(|&name| ParticipantConfig::new(name, name))
+                .collect(),
+            ..SimConfig::default()
+        };
+        let registry = StrategyRegistry::new();
+        let scores = |reports: Vec<tournament::MatchReport>| -> Vec<(Score, Score)> {
+            reports
+                .into_iter()
+                .map(|report| {
+                    let result = report.result.unwrap();
+                    (result.blue.score, result.red.score)
+                })
+                .collect()
+        };
+        let free = scores(tournament::round_robin(&config, &registry, 5, None).await);
+
+        let interval = Duration::from_millis(20);
+        install(Some(Clock::new(interval).start()));
+        let started = Instant::now();
+        let ticked = scores(tournament::round_robin(&config, &registry, 5, None).await);
+        install(None);
+        assert_eq!(ticked, free);
+        // the three matches play their five rounds side by side
+        let elapsed = started.elapsed();
+        assert!(elapsed >= interval * 5, "{:?}", elapsed);
+        assert!(elapsed < interval * 12, "{:?}", elapsed);
+    }
+}
@@ -0,0 +1,408 @@
+//! `analyze` and `describe`: characterizing one strategy, how stable
+//! standings are, or which seeds turn a tournament a given way
+
+use actix::prelude::*;
+use clap::{Args, Subcommand};
+use std::path::PathBuf;
+use tracing::warn;
+
+use crate::config::ParticipantConfig;
+use crate::evolution;
+use crate::exploit;
+use crate::fingerprint;
+use crate::interrupt;
+use crate::profile;
+use crate::registry::Parameters;
+use crate::registry::StrategyRegistry;
+use crate::report;
+use crate::schema;
+use crate::search::{self, Outcome};
+use crate::sweep::{Axis, Robustness, Sweep};
+
+use super::{
+    choose_seed, fail, load_config, setup, start_system, strategy_name, Format, GlobalArgs,
+};
+
+#[derive(Subcommand)]
+pub enum Analysis {
+    /// Play a strategy against Ashlock's grid of probes and print its
+    /// fingerprint as CSV
+    Fingerprint(FingerprintArgs),
+
+    /// Estimate how often a few invaders of one strategy take over a
+    /// population of another
+    Invade(InvadeArgs),
+
+    /// Replay a tournament at a range of noise levels and print each
+    /// participant's rank at every level
+    Robustness(RobustnessArgs),
+
+    /// Find the memory-one strategy that scores most against a strategy,
+    /// and how far it exploits it
+    Exploit(ExploitArgs),
+
+    /// Scan seeds for tournaments that turn out a given way
+    Seeds(SeedsArgs),
+}
+
+#[derive(Args)]
+pub struct DescribeArgs {
+    /// The strategy to describe: a participant in the config, or else a
+    /// strategy with its defaults
+    strategy: String,
+
+    /// TOML file declaring payoffs and any parameterized participants
+    #[arg(long)]
+    config: Option<PathBuf>,
+
+    /// Rounds to play against each opponent of the panel
+    #[arg(long, default_value_t = 20)]
+    rounds: usize,
+}
+
+#[derive(Args)]
+pub struct FingerprintArgs {
+    /// The strategy to fingerprint
+    #[arg(long)]
+    strategy: String,
+
+    /// TOML file declaring payoffs and iterations; its participants are ignored
+    #[arg(long)]
+    config: Option<PathBuf>,
+
+    /// Spacing of the probe grid, from 0 to 1 on both axes
+    #[arg(long, default_value_t = 0.05)]
+    step: f64,
+
+    /// Also draw the fingerprint as a heat map in this PNG file
+    #[arg(long)]
+    png: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct InvadeArgs {
+    /// TOML file declaring payoffs, iterations, and any parameterized participants
+    #[arg(long)]
+    config: Option<PathBuf>,
+
+    /// The resident: a participant in the config, or else a strategy with its defaults
+    #[arg(long)]
+    resident: String,
+
+    /// The invader, named the same way
+    #[arg(long)]
+    invader: String,
+
+    /// Invaders at the start, among the residents
+    #[arg(long, default_value_t = 1)]
+    invaders: usize,
+
+    /// Individuals in the population
+    #[arg(long, default_value_t = 20)]
+    population: usize,
+
+    /// How many times to run the process to fixation
+    #[arg(long, default_value_t = 1000)]
+    repetitions: usize,
+
+    /// Give up on a repetition that hasn't fixed after this many birth-death steps
+    #[arg(long, default_value_t = 100_000)]
+    max_steps: usize,
+}
+
+#[derive(Args)]
+pub struct RobustnessArgs {
+    /// TOML file declaring payoffs, iterations, and participants
+    #[arg(long)]
+    config: Option<PathBuf>,
+
+    /// The noise levels to play at, as a list or start:stop:step
+    #[arg(long, default_value = "0:0.2:0.02")]
+    noise: String,
+}
+
+#[derive(Args)]
+pub struct ExploitArgs {
+    /// TOML file declaring payoffs, iterations, noise, and participants
+    #[arg(long)]
+    config: Option<PathBuf>,
+
+    /// The strategy to exploit: a participant in the config, or else a
+    /// strategy with its defaults; every participant if not given
+    #[arg(long)]
+    strategy: Option<String>,
+
+    /// How many of the best responses to print for a single strategy
+    #[arg(long, default_value_t = 5)]
+    top: usize,
+}
+
+#[derive(Args)]
+pub struct SeedsArgs {
+    /// TOML file declaring payoffs, iterations, and participants
+    #[arg(long)]
+    config: Option<PathBuf>,
+
+    /// An outcome every seed found must show, e.g. "random beats tft",
+    /// "tft wins", or "cooperation below 0.2 after 80"; repeat for several
+    #[arg(long = "where", value_name = "OUTCOME", required = true)]
+    outcomes: Vec<Outcome>,
+
+    /// The first seed to try
+    #[arg(long, default_value_t = 0)]
+    from: u64,
+
+    /// How many seeds to try at most
+    #[arg(long, default_value_t = 1000)]
+    count: u64,
+
+    /// Stop once this many seeds are found
+    #[arg(long, default_value_t = 10)]
+    limit: usize,
+}
+
+/// Play a strategy against the panel of scripted opponents and print how it
+/// answered them
+pub fn describe(global: &GlobalArgs, args: &DescribeArgs) {
+    let registry = StrategyRegistry::new();
+    let config = load_config(global, args.config.as_ref());
+    let (strategy, parameters) = match config.participants.iter().find(|p| p.name == args.strategy)
+    {
+        Some(participant) => (participant.strategy.clone(), participant.parameters.clone()),
+        None => (strategy_name(&registry, &args.strategy), Parameters::new()),
+    };
+    let seed = choose_seed(global, &config);
+    let profile = profile::profile(
+        &registry,
+        &strategy,
+        &parameters,
+        config.payoffs(),
+        args.rounds,
+        seed,
+    )
+    .unwrap_or_else(|e| fail(e));
+    if !global.quiet {
+        match global.format {
+            Format::Text => print!("{}", report::profile_table(&profile)),
+            Format::Json => println!("{}", schema::to_string_pretty(&profile)),
+        }
+    }
+}
+
+/// Print a strategy's fingerprint against Ashlock's probes as CSV
+pub fn fingerprint(global: &GlobalArgs, args: &FingerprintArgs) {
+    if !(args.step > 0.0 && args.step <= 1.0) {
+        fail(format!("step {} must be above 0 and at most 1", args.step));
+    }
+    let registry = StrategyRegistry::new();
+    let mut config = load_config(global, args.config.as_ref());
+    let subject = ParticipantConfig::new("subject", &args.strategy);
+    config.participants = vec![
+        subject.clone(),
+        ParticipantConfig::new("probe", fingerprint::PROBE),
+    ];
+    if let Err(e) = config.validate(&registry) {
+        fail(e);
+    }
+    let seed = choose_seed(global, &config);
+    let (quiet, format, step, png) = (global.quiet, global.format, args.step, args.png.clone());
+
+    let system = start_system(global);
+
+    let execution = async move {
+        let fingerprint = fingerprint::fingerprint(&config, &registry, &subject, step, seed).await;
+        if let Some(path) = &png {
+            if let Err(e) = fingerprint.write_png(path) {
+                warn!(path = %path.display(), error = %e, "unable to write fingerprint");
+            }
+        }
+        if !quiet {
+            match format {
+                Format::Text => print!("{}", fingerprint.to_csv()),
+                Format::Json => println!("{}", schema::to_string_pretty(&fingerprint)),
+            }
+        }
+
+        System::current().stop();
+    };
+    Arbiter::spawn(execution);
+
+    system.run().unwrap();
+}
+
+/// Estimate how often a few invaders take over a resident population
+pub fn invade(global: &GlobalArgs, args: &InvadeArgs) {
+    let quiet = global.quiet;
+    let format = global.format;
+
+    if args.invaders == 0 || args.invaders >= args.population {
+        fail("invaders must be at least 1 and fewer than the population");
+    }
+    if args.resident == args.invader {
+        fail("the resident and the invader must differ");
+    }
+    let registry = StrategyRegistry::new();
+    let mut config = load_config(global, args.config.as_ref());
+    let participant = |name: &str| {
+        config
+            .participants
+            .iter()
+            .find(|p| p.name == name)
+            .cloned()
+            .unwrap_or_else(|| ParticipantConfig::new(name, name))
+    };
+    config.participants = vec![participant(&args.resident), participant(&args.invader)];
+    if let Err(e) = config.validate(&registry) {
+        fail(e);
+    }
+    let seed = choose_seed(global, &config);
+    if !quiet && format == Format::Text {
+        println!("seed: {}", seed);
+    }
+    let (population, invaders) = (args.population, args.invaders);
+    let (repetitions, max_steps) = (args.repetitions, args.max_steps);
+
+    let system = start_system(global);
+
+    let execution = async move {
+        let record = evolution::invasion(
+            &config,
+            &registry,
+            seed,
+            population,
+            invaders,
+            repetitions,
+            max_steps,
+        )
+        .await;
+        if !quiet {
+            match format {
+                Format::Text => print!("{}", report::invasion_table(&record)),
+                Format::Json => println!("{}", schema::to_string_pretty(&record)),
+            }
+        }
+
+        System::current().stop();
+    };
+    Arbiter::spawn(execution);
+
+    system.run().unwrap();
+}
+
+/// Rank the participants again at each of a range of noise levels
+pub fn robustness(global: &GlobalArgs, args: &RobustnessArgs) {
+    let quiet = global.quiet;
+    let format = global.format;
+
+    let registry = StrategyRegistry::new();
+    let (config, seed) = setup(global, args.config.as_ref(), &registry);
+    let axis = format!("noise={}", args.noise)
+        .parse::<Axis>()
+        .unwrap_or_else(|e| fail(e));
+    let sweep = Sweep::new(&config, vec![axis], &registry).unwrap_or_else(|e| fail(e));
+
+    let system = start_system(global);
+
+    let execution = async move {
+        let robustness = Robustness::new(&sweep.run(&registry, seed).await);
+        if !quiet {
+            match format {
+                Format::Text => print!("\n{}", report::robustness_table(&robustness)),
+                Format::Json => println!("{}", schema::to_string_pretty(&robustness)),
+            }
+        }
+
+        System::current().stop();
+    };
+    Arbiter::spawn(execution);
+
+    system.run().unwrap();
+}
+
+/// Find and print the memory-one strategy that exploits a strategy most
+pub fn exploit(global: &GlobalArgs, args: &ExploitArgs) {
+    let quiet = global.quiet;
+    let format = global.format;
+
+    let registry = StrategyRegistry::new();
+    let config = load_config(global, args.config.as_ref());
+    let targets = match &args.strategy {
+        Some(name) => vec![config
+            .participants
+            .iter()
+            .find(|p| &p.name == name)
+            .cloned()
+            .unwrap_or_else(|| ParticipantConfig::new(name, name))],
+        None => config.participants.clone(),
+    };
+    let seed = choose_seed(global, &config);
+    if !quiet && format == Format::Text {
+        println!("seed: {}", seed);
+    }
+    let top = args.top;
+
+    let system = start_system(global);
+
+    let execution = async move {
+        let mut records = Vec::new();
+        for target in &targets {
+            if interrupt::interrupted() {
+                break;
+            }
+            match exploit::exploit(&config, &registry, target, seed).await {
+                Ok(record) => records.push(record),
+                Err(e) => fail(e),
+            }
+        }
+        if !quiet {
+            match format {
+                Format::Text => {
+                    if let [record] = records.as_slice() {
+                        print!("\n{}", report::responses_table(record, top));
+                    }
+                    print!("\n{}", report::exploit_table(&records));
+                }
+                Format::Json => println!("{}", schema::to_string_pretty(&records)),
+            }
+        }
+
+        System::current().stop();
+    };
+    Arbiter::spawn(execution);
+
+    system.run().unwrap();
+}
+
+/// Play the tournament under seed after seed and print those where it
+/// showed every outcome asked for
+pub fn seeds(global: &GlobalArgs, args: &SeedsArgs) {
+    let quiet = global.quiet;
+    let format = global.format;
+
+    let registry = StrategyRegistry::new();
+    let config = load_config(global, args.config.as_ref());
+    config.validate(&registry).unwrap_or_else(|e| fail(e));
+    for outcome in &args.outcomes {
+        outcome.check(&config).unwrap_or_else(|e| fail(e));
+    }
+    let outcomes = args.outcomes.clone();
+    let seeds = args.from..args.from.saturating_add(args.count);
+    let limit = args.limit;
+
+    let system = start_system(global);
+
+    let execution = async move {
+        let found = search::search(&config, &registry, &outcomes, seeds, limit).await;
+        if !quiet {
+            match format {
+                Format::Text => print!("{}", report::seed_search(&found)),
+                Format::Json => println!("{}", schema::to_string_pretty(&found)),
+            }
+        }
+
+        System::current().stop();
+    };
+    Arbiter::spawn(execution);
+
+    system.run().unwrap();
+}
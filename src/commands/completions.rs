@@ -0,0 +1,51 @@
+//! `completions`: shell completion scripts generated from the command line
+
+use clap::builder::PossibleValuesParser;
+use clap::{Args, Command};
+use clap_complete::Shell;
+use std::io;
+
+use crate::game::Game;
+use crate::registry::StrategyRegistry;
+
+#[derive(Args)]
+pub struct CompletionsArgs {
+    /// The shell to complete in
+    shell: Shell,
+}
+
+/// Offer `values` wherever `command` or its subcommands take the argument
+/// `id`
+///
+/// Only the completions are told the values: a strategy can also be a
+/// combinator or a script, which the parser must go on accepting.
+fn with_values(mut command: Command, id: &str, values: &[String]) -> Command {
+    if command.get_arguments().any(|arg| arg.get_id() == id) {
+        command = command.mut_arg(id, |arg| {
+            arg.value_parser(PossibleValuesParser::new(values.to_vec()))
+        });
+    }
+    let subcommands: Vec<String> = command
+        .get_subcommands()
+        .map(|subcommand| subcommand.get_name().to_owned())
+        .collect();
+    for name in subcommands {
+        command = command.mut_subcommand(name, |subcommand| with_values(subcommand, id, values));
+    }
+    command
+}
+
+/// Print the completion script for `command` in the chosen shell, naming
+/// the registered strategies and the games
+pub fn completions(command: Command, args: &CompletionsArgs) {
+    let registry = StrategyRegistry::new();
+    let strategies: Vec<String> = registry.names().map(str::to_owned).collect();
+    let games: Vec<String> = Game::ALL
+        .iter()
+        .map(|game| game.name().to_owned())
+        .collect();
+    let command = with_values(command, "strategy", &strategies);
+    let mut command = with_values(command, "game", &games);
+    let name = command.get_name().to_owned();
+    clap_complete::generate(args.shell, &mut command, name, &mut io::stdout());
+}
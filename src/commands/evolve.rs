@@ -0,0 +1,580 @@
+//! `evolve`, `genetic`, `moran`, and `replicator`: populations over
+//! generations
+
+use actix::prelude::*;
+use clap::Args;
+use std::path::PathBuf;
+use tracing::warn;
+
+use crate::control::{self, Control, Controller};
+use crate::events::{self, EventBus, Subscribe};
+use crate::evolution::{self, Checkpointing, Convergence, EvolutionCheckpoint, IslandSettings};
+use crate::genetic::{self, GeneticSettings};
+use crate::population::{self, Population};
+use crate::progress::Unit;
+use crate::registry::StrategyRegistry;
+use crate::report;
+use crate::schema;
+
+use super::{
+    drain, event_log, fail, hide_progress, refuse_human, setup, show_progress, start_system,
+    subscribe_log, Format, GlobalArgs,
+};
+
+#[derive(Args)]
+pub struct EvolveArgs {
+    /// TOML file declaring payoffs, iterations, and the competing strategies
+    #[arg(long)]
+    config: Option<PathBuf>,
+
+    /// How many generations to run
+    #[arg(long, default_value_t = 50)]
+    generations: usize,
+
+    /// Individuals per generation, split evenly across the participants at the start
+    #[arg(long, default_value_t = 20)]
+    population: usize,
+
+    /// Probability that each offspring switches to a different strategy
+    #[arg(long, default_value_t = 0.0)]
+    mutation_rate: f64,
+
+    /// Start from the population in this JSON file, as counts, nodes, or a
+    /// fill, instead of an even split; --population sizes only a fill
+    #[arg(long, conflicts_with_all = ["resume", "islands", "coevolve"])]
+    start: Option<PathBuf>,
+
+    /// Write each generation's headcounts to this directory, as
+    /// generation-0000.json on, each a population to --start from
+    #[arg(long, conflicts_with_all = ["islands", "coevolve"])]
+    snapshots: Option<PathBuf>,
+
+    /// Snapshot only every this many generations, and the survivors
+    #[arg(long, default_value_t = 1, requires = "snapshots")]
+    snapshot_every: usize,
+
+    /// Save the run to this file as it goes, so that it can be resumed;
+    /// defaults to the --resume file
+    #[arg(long)]
+    checkpoint: Option<PathBuf>,
+
+    /// Generations between checkpoints
+    #[arg(long, default_value_t = 10)]
+    checkpoint_every: usize,
+
+    /// Carry on the run saved in this checkpoint, up to --generations in all;
+    /// its config, seed, population, and mutation rate are used
+    #[arg(long, conflicts_with = "config")]
+    resume: Option<PathBuf>,
+
+    /// Evolve this many separate populations of --population each, with
+    /// migration between them
+    #[arg(long, default_value_t = 1, conflicts_with_all = ["checkpoint", "resume"])]
+    islands: usize,
+
+    /// Co-evolve the two populations the participants are split into by
+    /// their `population`, each playing only the other, with --population
+    /// individuals each
+    #[arg(
+        long,
+        conflicts_with_all = ["islands", "checkpoint", "resume", "until_stable", "control"]
+    )]
+    coevolve: bool,
+
+    /// Stop early once no species' share has moved by more than --tolerance
+    /// a generation for this many generations running
+    #[arg(long, conflicts_with = "islands")]
+    until_stable: Option<usize>,
+
+    /// The largest change in a species' share, from one generation to the
+    /// next, that counts as stable
+    #[arg(long, default_value_t = 0.01, requires = "until_stable")]
+    tolerance: f64,
+
+    /// Generations between migrations from each island to the next; 0 for none
+    #[arg(long, default_value_t = 5)]
+    migration_interval: usize,
+
+    /// Individuals each island sends to the next at every migration
+    #[arg(long, default_value_t = 1)]
+    migrants: usize,
+
+    /// Take commands on stdin while running: pause, resume, standings,
+    /// noise, skip, and stop
+    #[arg(long, conflicts_with = "islands")]
+    control: bool,
+
+    /// Write every game event to this file as it happens, one JSON object
+    /// per line
+    #[arg(long, value_name = "FILE", conflicts_with = "islands")]
+    events: Option<PathBuf>,
+
+    /// Chart each species' share of the population in this PNG or SVG file
+    #[cfg(feature = "plots")]
+    #[arg(long)]
+    plot_shares: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct ReplicatorArgs {
+    /// TOML file declaring payoffs, iterations, and the competing strategies
+    #[arg(long)]
+    config: Option<PathBuf>,
+
+    /// How many generations to run, starting from an even split
+    #[arg(long, default_value_t = 50)]
+    generations: usize,
+
+    /// Chart each species' share of the population in this PNG or SVG file
+    #[cfg(feature = "plots")]
+    #[arg(long)]
+    plot_shares: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct MoranArgs {
+    /// TOML file declaring payoffs, iterations, and the competing strategies
+    #[arg(long)]
+    config: Option<PathBuf>,
+
+    /// Individuals in the population, split evenly across the participants at the start
+    #[arg(long, default_value_t = 20)]
+    population: usize,
+
+    /// How many times to run the process to fixation
+    #[arg(long, default_value_t = 100)]
+    repetitions: usize,
+
+    /// Give up on a repetition that hasn't fixed after this many birth-death steps
+    #[arg(long, default_value_t = 100_000)]
+    max_steps: usize,
+}
+
+#[derive(Args)]
+pub struct GeneticArgs {
+    /// TOML file declaring payoffs, iterations, and the strategies every machine also plays
+    #[arg(long)]
+    config: Option<PathBuf>,
+
+    /// How many generations to run
+    #[arg(long, default_value_t = 50)]
+    generations: usize,
+
+    /// Machines per generation
+    #[arg(long, default_value_t = 30)]
+    population: usize,
+
+    /// States per machine
+    #[arg(long, default_value_t = 4)]
+    states: usize,
+
+    /// Probability that each action and transition of a child is redrawn
+    #[arg(long, default_value_t = 0.05)]
+    mutation_rate: f64,
+
+    /// The fittest machines copied unchanged into the next generation
+    #[arg(long, default_value_t = 2)]
+    elite: usize,
+
+    /// How many of the best machines to report
+    #[arg(long, default_value_t = 5)]
+    keep: usize,
+
+    /// Write a TOML config playing the best machines to this file
+    #[arg(long)]
+    save: Option<PathBuf>,
+}
+
+/// Evolve the configured population, on islands or coevolving if asked
+pub fn evolve(global: &GlobalArgs, args: &EvolveArgs) {
+    let quiet = global.quiet;
+    let format = global.format;
+
+    if args.population < 2 {
+        fail("population must be at least 2");
+    }
+    if !(0.0..=1.0).contains(&args.mutation_rate) {
+        fail(format!(
+            "mutation rate {} is not a probability",
+            args.mutation_rate
+        ));
+    }
+    if args.islands == 0 {
+        fail("there must be at least one island");
+    }
+    if args.until_stable == Some(0) {
+        fail("--until-stable needs at least one generation");
+    }
+    if args.tolerance.is_nan() || args.tolerance < 0.0 {
+        fail(format!("tolerance {} is negative", args.tolerance));
+    }
+    let registry = StrategyRegistry::new();
+    if args.islands > 1 {
+        return evolve_islands(global, args, registry);
+    }
+    if args.coevolve {
+        return coevolve(global, args, registry);
+    }
+    let start = match &args.resume {
+        Some(path) => {
+            let state = EvolutionCheckpoint::load(path).unwrap_or_else(|e| fail(e));
+            if let Err(e) = state.config.validate(&registry) {
+                fail(e);
+            }
+            if !quiet && format == Format::Text {
+                println!(
+                    "resuming after generation {} of seed {}",
+                    state.generations.len(),
+                    state.seed
+                );
+            }
+            state
+        }
+        None => {
+            let (config, seed) = setup(global, args.config.as_ref(), &registry);
+            let state =
+                EvolutionCheckpoint::new(&config, seed, args.population, args.mutation_rate);
+            match &args.start {
+                Some(path) => {
+                    let individuals = Population::load(path)
+                        .map_err(|e| e.to_string())
+                        .and_then(|start| start.individuals(&config.participants, args.population))
+                        .unwrap_or_else(|e| fail(format!("{}: {}", path.display(), e)));
+                    if individuals.len() < 2 {
+                        fail(format!(
+                            "{}: the population must be at least 2",
+                            path.display()
+                        ));
+                    }
+                    state.with_population(individuals)
+                }
+                None => state,
+            }
+        }
+    };
+    if args.snapshot_every == 0 {
+        fail("--snapshot-every needs at least one generation");
+    }
+    let (snapshots, stride) = (args.snapshots.clone(), args.snapshot_every);
+    let control = args.control;
+    if control {
+        refuse_human(&start.config);
+    }
+    let generations = args.generations;
+    let remaining = generations.saturating_sub(start.generations.len());
+    let convergence = args.until_stable.map(|generations| Convergence {
+        generations,
+        tolerance: args.tolerance,
+    });
+    let checkpoint = args.checkpoint.clone().or_else(|| args.resume.clone());
+    let every = args.checkpoint_every;
+    #[cfg(feature = "plots")]
+    let plot_shares = args.plot_shares.clone();
+    #[cfg(feature = "webhooks")]
+    let webhooks = global.webhook.clone();
+    let event_log = event_log(args.events.as_ref());
+
+    let system = start_system(global);
+
+    let execution = async move {
+        let checkpointing = checkpoint
+            .as_ref()
+            .map(|path| Checkpointing { path, every });
+        let bus = EventBus::default().start();
+        if control {
+            let steering = Control::default();
+            control::steer(Some(steering.clone()));
+            bus.do_send(Subscribe(Controller::on_stdin(steering).recipient()));
+        }
+        let event_log = subscribe_log(&bus, event_log);
+        if control || event_log.is_some() {
+            events::publish_to(Some(bus.clone().recipient()));
+        }
+        let bar = show_progress(quiet, Some(remaining), Unit::Generations);
+        let record =
+            evolution::resume(&registry, start, generations, convergence, checkpointing).await;
+        hide_progress(bar).await;
+        events::publish_to(None);
+        control::steer(None);
+        drain(&bus, event_log).await;
+        if let Some(dir) = &snapshots {
+            if let Err(e) = population::write_snapshots(&record, dir, stride) {
+                warn!(path = %dir.display(), error = %e, "unable to write snapshots");
+            }
+        }
+        #[cfg(feature = "plots")]
+        if let Some(path) = &plot_shares {
+            if let Err(e) = crate::plots::population_shares(&record, path) {
+                warn!(path = %path.display(), error = %e, "unable to write chart");
+            }
+        }
+        #[cfg(feature = "webhooks")]
+        crate::webhooks::notify(&webhooks, crate::webhooks::Finished::Evolution(&record));
+        if !quiet {
+            match format {
+                Format::Text => print!("{}", report::composition_table(&record)),
+                Format::Json => println!("{}", schema::to_string_pretty(&record)),
+            }
+        }
+
+        System::current().stop();
+    };
+    Arbiter::spawn(execution);
+
+    system.run().unwrap();
+}
+
+fn evolve_islands(global: &GlobalArgs, args: &EvolveArgs, registry: StrategyRegistry) {
+    let quiet = global.quiet;
+    let format = global.format;
+
+    let (config, seed) = setup(global, args.config.as_ref(), &registry);
+    let (population, generations, mutation_rate) =
+        (args.population, args.generations, args.mutation_rate);
+    let settings = IslandSettings {
+        islands: args.islands,
+        migration_interval: args.migration_interval,
+        migrants: args.migrants,
+    };
+    #[cfg(feature = "plots")]
+    let plot_shares = args.plot_shares.clone();
+    #[cfg(feature = "webhooks")]
+    let webhooks = global.webhook.clone();
+
+    let system = start_system(global);
+
+    let execution = async move {
+        let record = evolution::evolve_islands(
+            &config,
+            &registry,
+            seed,
+            population,
+            generations,
+            mutation_rate,
+            settings,
+        )
+        .await;
+        #[cfg(feature = "plots")]
+        if let Some(path) = &plot_shares {
+            if let Err(e) = crate::plots::population_shares(&record.combined(), path) {
+                warn!(path = %path.display(), error = %e, "unable to write chart");
+            }
+        }
+        #[cfg(feature = "webhooks")]
+        crate::webhooks::notify(&webhooks, crate::webhooks::Finished::Islands(&record));
+        if !quiet {
+            match format {
+                Format::Text => {
+                    print!("{}", report::composition_table(&record.combined()));
+                    print!("\n{}", report::island_table(&record));
+                }
+                Format::Json => println!("{}", schema::to_string_pretty(&record)),
+            }
+        }
+
+        System::current().stop();
+    };
+    Arbiter::spawn(execution);
+
+    system.run().unwrap();
+}
+
+fn coevolve(global: &GlobalArgs, args: &EvolveArgs, registry: StrategyRegistry) {
+    let quiet = global.quiet;
+    let format = global.format;
+
+    let (config, seed) = setup(global, args.config.as_ref(), &registry);
+    if let Err(e) = evolution::population_labels(&config.participants) {
+        fail(e);
+    }
+    let (population, generations, mutation_rate) =
+        (args.population, args.generations, args.mutation_rate);
+    #[cfg(feature = "plots")]
+    let plot_shares = args.plot_shares.clone();
+    #[cfg(feature = "webhooks")]
+    let webhooks = global.webhook.clone();
+    let event_log = event_log(args.events.as_ref());
+
+    let system = start_system(global);
+
+    let execution = async move {
+        let bus = EventBus::default().start();
+        let event_log = subscribe_log(&bus, event_log);
+        if event_log.is_some() {
+            events::publish_to(Some(bus.clone().recipient()));
+        }
+        let record = evolution::coevolve(
+            &config,
+            &registry,
+            seed,
+            population,
+            generations,
+            mutation_rate,
+        )
+        .await;
+        events::publish_to(None);
+        drain(&bus, event_log).await;
+        let record = record.unwrap_or_else(|e| fail(e));
+        #[cfg(feature = "plots")]
+        if let Some(path) = &plot_shares {
+            if let Err(e) = crate::plots::population_shares(&record.combined(), path) {
+                warn!(path = %path.display(), error = %e, "unable to write chart");
+            }
+        }
+        #[cfg(feature = "webhooks")]
+        crate::webhooks::notify(&webhooks, crate::webhooks::Finished::Coevolution(&record));
+        if !quiet {
+            match format {
+                Format::Text => {
+                    for (i, label) in record.labels.iter().enumerate() {
+                        if i > 0 {
+                            println!();
+                        }
+                        println!("{}", label);
+                        print!("{}", report::composition_table(&record.population(i)));
+                    }
+                }
+                Format::Json => println!("{}", schema::to_string_pretty(&record)),
+            }
+        }
+
+        System::current().stop();
+    };
+    Arbiter::spawn(execution);
+
+    system.run().unwrap();
+}
+
+/// Evolve finite state machine strategies and print the fittest
+pub fn genetic(global: &GlobalArgs, args: &GeneticArgs) {
+    let quiet = global.quiet;
+    let format = global.format;
+
+    if args.population < 2 {
+        fail("population must be at least 2");
+    }
+    if args.states == 0 {
+        fail("machines need at least one state");
+    }
+    if args.elite > args.population {
+        fail(format!(
+            "elite {} is more than the population of {}",
+            args.elite, args.population
+        ));
+    }
+    if !(0.0..=1.0).contains(&args.mutation_rate) {
+        fail(format!(
+            "mutation rate {} is not a probability",
+            args.mutation_rate
+        ));
+    }
+    let registry = StrategyRegistry::new();
+    let (config, seed) = setup(global, args.config.as_ref(), &registry);
+    let settings = GeneticSettings {
+        population: args.population,
+        generations: args.generations,
+        states: args.states,
+        mutation_rate: args.mutation_rate,
+        elite: args.elite,
+        keep: args.keep,
+    };
+    let save = args.save.clone();
+
+    let system = start_system(global);
+
+    let execution = async move {
+        let record = genetic::evolve_machines(&config, &registry, seed, settings).await;
+        if let Some(path) = &save {
+            let mut best = record.config.clone();
+            best.participants = record
+                .best
+                .iter()
+                .enumerate()
+                .map(|(i, machine)| machine.participant(&format!("evolved-{}", i + 1)))
+                .collect();
+            let written = toml::to_string(&best)
+                .map_err(|e| e.to_string())
+                .and_then(|text| std::fs::write(path, text).map_err(|e| e.to_string()));
+            if let Err(e) = written {
+                warn!(path = %path.display(), error = %e, "unable to save machines");
+            }
+        }
+        if !quiet {
+            match format {
+                Format::Text => print!("{}", report::machine_table(&record)),
+                Format::Json => println!("{}", schema::to_string_pretty(&record)),
+            }
+        }
+
+        System::current().stop();
+    };
+    Arbiter::spawn(execution);
+
+    system.run().unwrap();
+}
+
+/// Estimate each invader's fixation probability in repeated Moran processes
+pub fn moran(global: &GlobalArgs, args: &MoranArgs) {
+    let quiet = global.quiet;
+    let format = global.format;
+
+    if args.population < 2 {
+        fail("population must be at least 2");
+    }
+    let registry = StrategyRegistry::new();
+    let (config, seed) = setup(global, args.config.as_ref(), &registry);
+    let (population, repetitions, max_steps) = (args.population, args.repetitions, args.max_steps);
+
+    let system = start_system(global);
+
+    let execution = async move {
+        let record =
+            evolution::moran(&config, &registry, seed, population, repetitions, max_steps).await;
+        if !quiet {
+            match format {
+                Format::Text => print!("{}", report::fixation_table(&record)),
+                Format::Json => println!("{}", schema::to_string_pretty(&record)),
+            }
+        }
+
+        System::current().stop();
+    };
+    Arbiter::spawn(execution);
+
+    system.run().unwrap();
+}
+
+/// Follow the species' shares under replicator dynamics
+pub fn replicator(global: &GlobalArgs, args: &ReplicatorArgs) {
+    let quiet = global.quiet;
+    let format = global.format;
+
+    let registry = StrategyRegistry::new();
+    let (config, seed) = setup(global, args.config.as_ref(), &registry);
+    let generations = args.generations;
+    #[cfg(feature = "plots")]
+    let plot_shares = args.plot_shares.clone();
+
+    let system = start_system(global);
+
+    let execution = async move {
+        let record = evolution::replicator(&config, &registry, seed, generations).await;
+        #[cfg(feature = "plots")]
+        if let Some(path) = &plot_shares {
+            if let Err(e) = crate::plots::replicator_shares(&record, path) {
+                warn!(path = %path.display(), error = %e, "unable to write chart");
+            }
+        }
+        if !quiet {
+            match format {
+                Format::Text => print!("{}", report::shares_table(&record)),
+                Format::Json => println!("{}", schema::to_string_pretty(&record)),
+            }
+        }
+
+        System::current().stop();
+    };
+    Arbiter::spawn(execution);
+
+    system.run().unwrap();
+}
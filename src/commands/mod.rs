@@ -0,0 +1,351 @@
+//! The command-line subcommands
+//!
+//! The `actoripd` binary parses its arguments into a [`GlobalArgs`] and
+//! one subcommand's arguments, and hands them to that subcommand's
+//! function here, e.g. [`run::run`]. Each loads its config, applies the
+//! command line's overrides, plays, and prints or saves what it found.
+
+use actix::prelude::*;
+use clap::{Args, ValueEnum};
+use rand::Rng;
+use std::io::{self, IsTerminal};
+use std::path::PathBuf;
+use std::process;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tracing::info;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Layer};
+
+use crate::config::{ParticipantConfig, SimConfig};
+use crate::events::{Drain, EventBus, EventLog, Subscribe};
+use crate::game::{Game, PayoffMatrix};
+use crate::interrupt;
+use crate::progress::{self, Progress, ProgressBar, Unit};
+use crate::registry::StrategyRegistry;
+use crate::tournament;
+
+pub mod analyze;
+pub mod completions;
+pub mod evolve;
+pub mod records;
+pub mod run;
+pub mod services;
+pub mod spatial;
+pub mod sweep;
+
+/// The options every subcommand takes
+#[derive(Args)]
+pub struct GlobalArgs {
+    /// Seed for all randomness in the run; a random seed is chosen and reported if omitted
+    #[arg(long, global = true)]
+    seed: Option<u64>,
+
+    /// The 2x2 game to play, overriding the config: prisoners-dilemma, stag-hunt, chicken, or snowdrift
+    #[arg(long, global = true)]
+    game: Option<Game>,
+
+    /// Play a well-known setup instead of a config file
+    #[arg(long, value_enum, global = true)]
+    preset: Option<Preset>,
+
+    /// Payoff values as R,T,P,S, overriding the config, e.g. 3,5,1,0; a fifth, L, allows abstaining;
+    /// or a table's rows, separated by semicolons, for a game of more actions, e.g. 0,-1,1;1,0,-1;-1,1,0
+    #[arg(long, global = true)]
+    payoffs: Option<PayoffMatrix>,
+
+    /// Strategy for a prisoner named blue; with --red, replaces the config's participants
+    #[arg(long, global = true)]
+    blue: Option<String>,
+
+    /// Strategy for a prisoner named red; with --blue, replaces the config's participants
+    #[arg(long, global = true)]
+    red: Option<String>,
+
+    /// Milliseconds each prisoner has to decide, overriding the config
+    #[arg(long, global = true)]
+    decision_timeout: Option<u64>,
+
+    /// Wait this long between rounds, e.g. 250ms or 1s, to watch matches play
+    /// out; overrides the config
+    #[arg(long, global = true, value_parser = parse_delay, value_name = "DURATION")]
+    delay: Option<u64>,
+
+    /// Serve Prometheus metrics at this address, e.g. 0.0.0.0:9100
+    #[cfg(feature = "metrics")]
+    #[arg(long, global = true, value_name = "ADDRESS")]
+    metrics: Option<String>,
+
+    /// Worker threads to spread matches across
+    #[arg(long, global = true, default_value_t = 1)]
+    threads: usize,
+
+    /// Don't print anything to stdout
+    #[arg(long, short, global = true)]
+    quiet: bool,
+
+    /// How results are written to stdout
+    #[arg(long, value_enum, global = true, default_value = "text")]
+    format: Format,
+
+    /// How log lines are written to stderr; RUST_LOG sets which are written
+    #[arg(long, value_enum, global = true, default_value = "text")]
+    log_format: LogFormat,
+
+    /// Export tracing spans to the OTLP/HTTP collector at this address, e.g.
+    /// http://localhost:4318
+    #[cfg(feature = "otel")]
+    #[arg(long, global = true, value_name = "URL")]
+    otlp: Option<String>,
+
+    /// Post a JSON summary, with the standings and a manifest of the run, to
+    /// this URL when a tournament, sweep, or evolution finishes; may be
+    /// given more than once
+    #[cfg(feature = "webhooks")]
+    #[arg(long, global = true, value_name = "URL")]
+    webhook: Vec<String>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Format {
+    /// A summary per match, then the standings
+    Text,
+
+    /// One JSON document with the config, seed, transcripts, and standings
+    Json,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Preset {
+    /// Axelrod's first tournament: payoffs 3,5,1,0, 200 rounds, self-play,
+    /// five repetitions, and the reconstructable entries
+    Axelrod,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum LogFormat {
+    /// One line per event, with the spans it happened in
+    Text,
+
+    /// One JSON object per event, for log collectors
+    Json,
+}
+
+/// What is logged when RUST_LOG isn't set
+const DEFAULT_LOG_FILTER: &str = "warn,actoripd=info";
+
+/// Set while something else, such as the dashboard, is drawing on the terminal
+static LOGS_MUTED: AtomicBool = AtomicBool::new(false);
+
+/// Stop logging, or start again, while something else draws on the terminal
+#[cfg(feature = "tui")]
+fn mute_logs(muted: bool) {
+    LOGS_MUTED.store(muted, Ordering::Relaxed);
+}
+
+/// Log to stderr in the format `global` asks for
+pub fn init_logging(global: &GlobalArgs) {
+    let format = global.log_format;
+    let filter =
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(DEFAULT_LOG_FILTER));
+    let writer = || -> Box<dyn io::Write> {
+        if LOGS_MUTED.load(Ordering::Relaxed) {
+            Box::new(io::sink())
+        } else {
+            progress::clear();
+            Box::new(io::stderr())
+        }
+    };
+    let logger = tracing_subscriber::fmt::layer()
+        .with_ansi(io::stderr().is_terminal())
+        .with_writer(writer);
+    let logger = match format {
+        LogFormat::Text => logger.boxed(),
+        LogFormat::Json => logger.json().boxed(),
+    };
+    let registry = tracing_subscriber::registry().with(filter).with(logger);
+    #[cfg(feature = "otel")]
+    let registry = registry.with(global.otlp.as_deref().map(|endpoint| {
+        crate::telemetry::layer(endpoint)
+            .unwrap_or_else(|e| fail(format!("unable to export to {}: {}", endpoint, e)))
+    }));
+    registry.init();
+}
+
+/// Send what is left to send, such as spans not yet exported, before exiting
+pub fn finish() {
+    #[cfg(feature = "otel")]
+    crate::telemetry::shutdown();
+}
+
+/// Milliseconds from a duration such as `250ms`, `1.5s`, or plain `250`
+fn parse_delay(text: &str) -> Result<u64, String> {
+    let (number, scale) = match text.strip_suffix("ms") {
+        Some(ms) => (ms, 1.0),
+        None => match text.strip_suffix('s') {
+            Some(s) => (s, 1000.0),
+            None => (text, 1.0),
+        },
+    };
+    match number.trim().parse::<f64>() {
+        Ok(n) if n >= 0.0 && n.is_finite() => Ok((n * scale).round() as u64),
+        _ => Err(format!("'{}' is not a duration such as 250ms or 1s", text)),
+    }
+}
+
+fn fail<E: std::fmt::Display>(e: E) -> ! {
+    eprintln!("{}", e);
+    finish();
+    process::exit(1);
+}
+
+/// `name` as the registry knows it, taking `tit_for_tat`, which is easier
+/// to type, for `tit-for-tat`
+fn strategy_name(registry: &StrategyRegistry, name: &str) -> String {
+    if registry.names().any(|known| known == name) {
+        name.to_owned()
+    } else {
+        name.replace('_', "-")
+    }
+}
+
+/// `--control` reads stdin, which a human player needs for its moves
+fn refuse_human(config: &SimConfig) {
+    if let Some(p) = config
+        .participants
+        .iter()
+        .find(|p| p.strategy == crate::human::NAME)
+    {
+        fail(format!(
+            "{} plays from the terminal, so --control can't read it",
+            p.name
+        ));
+    }
+}
+
+/// Load and validate the config, and settle on a seed
+fn setup(
+    global: &GlobalArgs,
+    path: Option<&PathBuf>,
+    registry: &StrategyRegistry,
+) -> (SimConfig, u64) {
+    let config = load_config(global, path);
+    let config = config
+        .validate(registry)
+        .map(|_| config)
+        .unwrap_or_else(|e| fail(e));
+    let seed = choose_seed(global, &config);
+    if !global.quiet && global.format == Format::Text {
+        println!("seed: {}", seed);
+    }
+    (config, seed)
+}
+
+/// The config from `path` or the preset, with the command line's overrides
+fn load_config(global: &GlobalArgs, path: Option<&PathBuf>) -> SimConfig {
+    match (path, global.preset) {
+        (Some(_), Some(_)) => fail("--config and --preset can't be used together"),
+        (Some(path), None) => SimConfig::load(path),
+        (None, Some(Preset::Axelrod)) => Ok(SimConfig::axelrod()),
+        (None, None) => Ok(SimConfig::default()),
+    }
+    .map(|mut config| {
+        if let Some(game) = global.game {
+            config.game = game;
+        }
+        if global.payoffs.is_some() {
+            config.payoffs = global.payoffs;
+        }
+        if global.decision_timeout.is_some() {
+            config.decision_timeout_ms = global.decision_timeout;
+        }
+        if global.delay.is_some() {
+            config.delay_ms = global.delay;
+        }
+        if global.blue.is_some() || global.red.is_some() {
+            let strategy = |s: &Option<String>| s.clone().unwrap_or_else(|| "random".to_owned());
+            config.participants = vec![
+                ParticipantConfig::new("blue", &strategy(&global.blue)),
+                ParticipantConfig::new("red", &strategy(&global.red)),
+            ];
+        }
+        config
+    })
+    .unwrap_or_else(|e| fail(e))
+}
+
+fn choose_seed(global: &GlobalArgs, config: &SimConfig) -> u64 {
+    let seed = global
+        .seed
+        .or(config.seed)
+        .unwrap_or_else(|| rand::thread_rng().gen());
+    info!(seed, "starting");
+    seed
+}
+
+/// Open the file for `--events` before the run starts, so that a bad path
+/// fails at once
+fn event_log(path: Option<&PathBuf>) -> Option<EventLog> {
+    path.map(|path| {
+        EventLog::create(path).unwrap_or_else(|e| {
+            fail(format!(
+                "unable to write events to {}: {}",
+                path.display(),
+                e
+            ))
+        })
+    })
+}
+
+/// Subscribe `log` to `bus`, if there is one
+fn subscribe_log(bus: &Addr<EventBus>, log: Option<EventLog>) -> Option<Addr<EventLog>> {
+    log.map(|log| {
+        let log = log.start();
+        bus.do_send(Subscribe(log.clone().recipient()));
+        log
+    })
+}
+
+/// Draw a bar on stderr for the matches and generations started from this
+/// thread, which will be done at `total` of `unit` if that is known
+///
+/// There is none if `quiet`, or if stderr isn't a terminal.
+fn show_progress(quiet: bool, total: Option<usize>, unit: Unit) -> Option<Addr<ProgressBar>> {
+    if quiet || !io::stderr().is_terminal() {
+        return None;
+    }
+    let progress = Progress::new();
+    progress::watch(Some(progress.clone()));
+    Some(ProgressBar::new(progress, total, unit).start())
+}
+
+/// Take the bar shown by [`show_progress`] off the terminal
+async fn hide_progress(bar: Option<Addr<ProgressBar>>) {
+    progress::watch(None);
+    if let Some(bar) = bar {
+        let _ = bar.send(progress::Close).await;
+    }
+}
+
+/// Wait for `log` to write every event published to `bus` so far
+async fn drain(bus: &Addr<EventBus>, log: Option<Addr<EventLog>>) {
+    if let Some(log) = log {
+        let _ = bus.send(Drain).await;
+        let _ = log.send(Drain).await;
+    }
+}
+
+/// A system whose matches are spread across `--threads` arbiters, and
+/// which stops early on Ctrl-C
+fn start_system(global: &GlobalArgs) -> SystemRunner {
+    let system = System::new("prisoners-dilemma");
+    tournament::use_threads(global.threads);
+    interrupt::watch_ctrl_c();
+    #[cfg(feature = "metrics")]
+    if let Some(address) = &global.metrics {
+        if let Err(e) = crate::metrics::serve(address.as_str()) {
+            fail(format!("unable to serve metrics at {}: {}", address, e));
+        }
+    }
+    system
+}
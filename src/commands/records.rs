@@ -0,0 +1,280 @@
+//! `strategies`, `leaderboard`, `replay`, and `export-experiment` and
+//! `import-experiment`: what the registry and past runs hold
+
+use actix::prelude::*;
+use clap::{Args, Subcommand};
+use std::path::PathBuf;
+use tracing::warn;
+
+use crate::experiment::Experiment;
+use crate::interrupt;
+use crate::leaderboard::{Leaderboard, PROVISIONAL_MATCHES};
+use crate::recording::{self, Recording};
+use crate::registry::{Parameters, StrategyArgs, StrategyRegistry};
+use crate::report;
+use crate::schema;
+use crate::strategy::StrategyInfo;
+
+use super::{fail, setup, start_system, Format, GlobalArgs};
+
+#[derive(Subcommand)]
+pub enum LeaderboardCommand {
+    /// Print each strategy's wins, losses, and points, best first
+    Show(LeaderboardShowArgs),
+}
+
+#[derive(Args)]
+pub struct LeaderboardShowArgs {
+    /// The leaderboard file that `run --leaderboard` keeps
+    #[arg(long, default_value = "leaderboard.json")]
+    file: PathBuf,
+
+    /// Count every match recorded in this SQLite database instead
+    #[cfg(feature = "sqlite")]
+    #[arg(long)]
+    sqlite: Option<PathBuf>,
+
+    /// Rank strategies with fewer matches than this after the rest, as
+    /// provisional
+    #[arg(long, value_name = "MATCHES", default_value_t = PROVISIONAL_MATCHES)]
+    provisional: usize,
+}
+
+#[derive(Args)]
+pub struct ReplayArgs {
+    /// A recording saved by `run --record`
+    recording: PathBuf,
+
+    /// Replay only this match, counting from 1 in the order of the
+    /// recording
+    #[arg(long = "match", value_name = "N")]
+    match_number: Option<usize>,
+
+    /// Print each match's rounds as rows of C and D after its summary
+    #[arg(long)]
+    transcripts: bool,
+
+    /// Take the match up from this round, counting from 1, instead of
+    /// playing it from the start; needs a recording made with
+    /// `--audit-randomness`
+    #[arg(long, value_name = "ROUND", requires = "match_number")]
+    from: Option<usize>,
+}
+
+#[derive(Args)]
+pub struct ExportExperimentArgs {
+    /// TOML file declaring the run to share
+    #[arg(long)]
+    config: Option<PathBuf>,
+
+    /// Where to write the experiment
+    #[arg(long, default_value = "experiment.json")]
+    output: PathBuf,
+}
+
+#[derive(Args)]
+pub struct ImportExperimentArgs {
+    /// An experiment written by `export-experiment`
+    experiment: PathBuf,
+
+    /// The directory to unpack its config and files into
+    #[arg(long, default_value = "experiment")]
+    into: PathBuf,
+}
+
+/// Bundle a config, its seed, and the files its strategies load
+pub fn export_experiment(global: &GlobalArgs, args: &ExportExperimentArgs) {
+    let registry = StrategyRegistry::new();
+    let (config, seed) = setup(global, args.config.as_ref(), &registry);
+    let experiment = Experiment::export(&config, seed).unwrap_or_else(|e| fail(e));
+    experiment.save(&args.output).unwrap_or_else(|e| fail(e));
+    if !global.quiet && global.format == Format::Text {
+        println!(
+            "wrote {} with {} file(s)",
+            args.output.display(),
+            experiment.files.len()
+        );
+    }
+}
+
+/// Unpack an experiment so that running its config plays the same run
+pub fn import_experiment(global: &GlobalArgs, args: &ImportExperimentArgs) {
+    let experiment = Experiment::load(&args.experiment).unwrap_or_else(|e| fail(e));
+    let config = experiment.unpack(&args.into).unwrap_or_else(|e| fail(e));
+    if !global.quiet && global.format == Format::Text {
+        println!(
+            "seed {}: actoripd run --config {}",
+            experiment.seed,
+            config.display()
+        );
+    }
+}
+
+/// List the registered strategies
+pub fn strategies(global: &GlobalArgs) {
+    let registry = StrategyRegistry::new();
+    let payoffs = global
+        .payoffs
+        .unwrap_or_else(|| global.game.unwrap_or_default().default_payoffs());
+    let args = StrategyArgs {
+        parameters: &Parameters::new(),
+        payoffs: &payoffs,
+        seed: 0,
+    };
+    let strategies: Vec<StrategyInfo> = registry
+        .names()
+        .map(|name| registry.describe(name, &args).unwrap_or_else(|e| fail(e)))
+        .collect();
+    if !global.quiet {
+        match global.format {
+            Format::Text => print!("{}", report::strategy_table(&strategies)),
+            Format::Json => println!("{}", schema::to_string_pretty(&strategies)),
+        }
+    }
+}
+
+/// Print the leaderboard kept in a file
+pub fn show_leaderboard(global: &GlobalArgs, args: &LeaderboardShowArgs) {
+    #[cfg(feature = "sqlite")]
+    let leaderboard = match &args.sqlite {
+        Some(path) => crate::store::ResultStore::open(path)
+            .and_then(|store| store.leaderboard())
+            .unwrap_or_else(|e| fail(format!("unable to read {}: {}", path.display(), e))),
+        None => Leaderboard::load(&args.file).unwrap_or_else(|e| fail(e)),
+    };
+    #[cfg(not(feature = "sqlite"))]
+    let leaderboard = Leaderboard::load(&args.file).unwrap_or_else(|e| fail(e));
+
+    if global.quiet {
+        return;
+    }
+    let entries = leaderboard.ranked(args.provisional);
+    match global.format {
+        Format::Text => print!("{}", report::leaderboard_table(&entries)),
+        Format::Json => println!("{}", schema::to_string_pretty(&entries)),
+    }
+}
+
+/// Play a recording's matches again and report where they diverged
+pub fn replay(global: &GlobalArgs, args: &ReplayArgs) {
+    let quiet = global.quiet;
+    let format = global.format;
+
+    let registry = StrategyRegistry::new();
+    let recording = Recording::load(&args.recording).unwrap_or_else(|e| fail(e));
+    if let Err(e) = recording.config.validate(&registry) {
+        fail(e);
+    }
+    let chosen: Vec<usize> = match args.match_number {
+        Some(n) if n == 0 || n > recording.matches.len() => fail(format!(
+            "the recording has {} matches, numbered from 1",
+            recording.matches.len()
+        )),
+        Some(n) => vec![n - 1],
+        None => (0..recording.matches.len()).collect(),
+    };
+    let transcripts = args.transcripts;
+    if let Some(from) = args.from {
+        let recorded = &recording.matches[chosen[0]];
+        let resumed = recording::resume(&recording, recorded, &registry, from)
+            .unwrap_or_else(|e| fail(format!("unable to take the match up: {}", e)));
+        if let Some(round) = resumed.diverged_at {
+            warn!(
+                blue = %recorded.blue.name,
+                red = %recorded.red.name,
+                round,
+                "resumed match diverged from the recording"
+            );
+        }
+        if !quiet {
+            match format {
+                Format::Text => {
+                    print!("{}", report::resumed_summary(&resumed, transcripts));
+                    match resumed.diverged_at {
+                        Some(round) => {
+                            println!("  diverged from the recording at round {}", round)
+                        }
+                        None => println!("  as recorded"),
+                    }
+                }
+                Format::Json => println!("{}", schema::to_string_pretty(&resumed)),
+            }
+        }
+        return;
+    }
+
+    let system = start_system(global);
+
+    let execution = async move {
+        let mut replays = Vec::new();
+        for i in chosen {
+            let recorded = &recording.matches[i];
+            match recording::replay(&recording, recorded, &registry).await {
+                Ok(replay) => {
+                    if let Some(round) = replay.diverged_at {
+                        warn!(
+                            blue = %recorded.blue.name,
+                            red = %recorded.red.name,
+                            round,
+                            "replay diverged from the recording"
+                        );
+                    }
+                    if let Some(round) = replay.draws_diverged_at {
+                        warn!(
+                            blue = %recorded.blue.name,
+                            red = %recorded.red.name,
+                            round,
+                            "replay drew differently from the recording"
+                        );
+                    }
+                    replays.push(replay);
+                }
+                Err(e) => warn!(
+                    blue = %recorded.blue.name,
+                    red = %recorded.red.name,
+                    error = %e,
+                    "replay aborted"
+                ),
+            }
+            if interrupt::interrupted() {
+                break;
+            }
+        }
+        if !quiet {
+            match format {
+                Format::Text => {
+                    for replay in &replays {
+                        print!("{}", report::match_summary(&replay.result));
+                        if transcripts {
+                            print!("{}", report::transcript(&replay.result));
+                        }
+                        match replay.diverged_at {
+                            Some(round) => {
+                                println!("  diverged from the recording at round {}", round)
+                            }
+                            None => println!("  as recorded"),
+                        }
+                        match (&replay.result.draws, replay.draws_diverged_at) {
+                            (_, Some(round)) => {
+                                println!("  drew differently from the recording at round {}", round)
+                            }
+                            (Some(draws), None) if draws.is_empty() => {
+                                println!("  no random draws, as recorded")
+                            }
+                            (Some(draws), None) => {
+                                println!("  all {} random draws as recorded", draws.len())
+                            }
+                            (None, None) => {}
+                        }
+                    }
+                }
+                Format::Json => println!("{}", schema::to_string_pretty(&replays)),
+            }
+        }
+
+        System::current().stop();
+    };
+    Arbiter::spawn(execution);
+
+    system.run().unwrap();
+}
@@ -0,0 +1,829 @@
+//! `run` and `duel`: one tournament of the configured participants, or a
+//! single match between two strategies
+
+use actix::prelude::*;
+use clap::{Args, ValueEnum};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::path::PathBuf;
+use std::time::Duration;
+use tracing::warn;
+
+use crate::axelrod;
+use crate::clock::{self, Clock};
+#[cfg(feature = "sqlite")]
+use crate::config::SimConfig;
+use crate::config::{twin_name, ParticipantConfig};
+use crate::control::{self, Control, Controller};
+use crate::determinism;
+use crate::distributed;
+use crate::events::{self, Drain, EventBus, Subscribe};
+use crate::formats;
+use crate::html;
+#[cfg(feature = "sqlite")]
+use crate::interrupt;
+use crate::leaderboard::Leaderboard;
+use crate::payoff_table::PayoffTable;
+use crate::progress::Unit;
+use crate::rating::Ratings;
+use crate::recording::Recording;
+use crate::registry::StrategyRegistry;
+use crate::report::{self, Reporter, Watcher};
+use crate::scheduler::{self, Scheduler};
+use crate::schema;
+#[cfg(feature = "sqlite")]
+use crate::store::ResultStore;
+use crate::tournament::{self, MatchSeeds, PartialTournament, TournamentRecord};
+
+use super::{
+    choose_seed, drain, event_log, fail, hide_progress, load_config, refuse_human, setup,
+    show_progress, start_system, strategy_name, subscribe_log, Format, GlobalArgs,
+};
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum TournamentFormat {
+    /// Every participant against every other
+    #[default]
+    RoundRobin,
+
+    /// A single-elimination bracket
+    Elimination,
+
+    /// Rounds pairing players on equal points who haven't met
+    Swiss,
+}
+
+/// How much of a tournament is printed
+#[derive(Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Output {
+    /// Only the final standings
+    Summary,
+
+    /// Each match's result as it ends, then the standings and the tables
+    /// that follow them
+    #[default]
+    Standard,
+
+    /// Every round of every match as well
+    Full,
+}
+
+#[derive(Args, Default)]
+pub struct RunArgs {
+    /// TOML file declaring payoffs, iterations, and participants
+    #[arg(long)]
+    config: Option<PathBuf>,
+
+    /// Play every pairing this many times, overriding the config
+    #[arg(long)]
+    repetitions: Option<usize>,
+
+    /// How to pair the participants; elimination and Swiss tournaments are
+    /// seeded by --ratings if given, and otherwise in config order
+    #[arg(long, value_enum, default_value_t)]
+    tournament: TournamentFormat,
+
+    /// Rounds of a Swiss tournament; enough to leave one unbeaten player by
+    /// default
+    #[arg(long)]
+    swiss_rounds: Option<usize>,
+
+    /// Append the results to this SQLite database; a round robin saves each
+    /// match there as it ends, so that a run stopped early can be resumed
+    #[cfg(feature = "sqlite")]
+    #[arg(long)]
+    sqlite: Option<PathBuf>,
+
+    /// Finish the round robin left unfinished in this SQLite database,
+    /// playing only the pairings it hadn't, with its config and seed
+    #[cfg(feature = "sqlite")]
+    #[arg(
+        long,
+        value_name = "DATABASE",
+        conflicts_with_all = ["config", "sqlite", "repetitions", "tournament", "distribute"]
+    )]
+    resume: Option<PathBuf>,
+
+    /// Update the strategies' Elo ratings in this JSON file, creating it if needed
+    #[arg(long)]
+    ratings: Option<PathBuf>,
+
+    /// Add the results to the leaderboard in this JSON file, creating it if needed
+    #[arg(long)]
+    leaderboard: Option<PathBuf>,
+
+    /// Fade the leaderboard's past results, halving their weight every DAYS
+    #[arg(long, value_name = "DAYS", requires = "leaderboard")]
+    half_life: Option<f64>,
+
+    /// Write each participant's points per round against every other as CSV
+    #[arg(long)]
+    matrix: Option<PathBuf>,
+
+    /// Draw the same matrix as a PNG heat map
+    #[arg(long)]
+    heatmap: Option<PathBuf>,
+
+    /// Write a self-contained HTML report of the tournament
+    #[arg(long)]
+    report: Option<PathBuf>,
+
+    /// Write every match as the interactions CSV of the Axelrod Python
+    /// library, for its `ResultSet`
+    #[arg(long)]
+    axelrod: Option<PathBuf>,
+
+    /// Write one row per match as an Arrow IPC file, for polars or pandas
+    #[cfg(feature = "arrow")]
+    #[arg(long, value_name = "FILE")]
+    arrow_matches: Option<PathBuf>,
+
+    /// Write one row per round of every match as an Arrow IPC file
+    #[cfg(feature = "arrow")]
+    #[arg(long, value_name = "FILE")]
+    arrow_rounds: Option<PathBuf>,
+
+    /// How much to print: only the standings, each match's result too, or
+    /// every round as well; JSON leaves out the matches or their rounds
+    /// likewise
+    #[arg(long, value_enum, default_value_t)]
+    output: Output,
+
+    /// Print each match's rounds as rows of C and D after its summary, as
+    /// --output full does
+    #[arg(long)]
+    transcripts: bool,
+
+    /// Save every match's participants, seeds, and transcript to this file,
+    /// for `replay`
+    #[arg(long)]
+    record: Option<PathBuf>,
+
+    /// Log every random draw the strategies make into the match results,
+    /// so `replay` can check a recording against its seeds
+    #[arg(long)]
+    audit_randomness: bool,
+
+    /// Admit only participants that make the same moves when replayed with
+    /// the same seeds
+    #[arg(long)]
+    deterministic_only: bool,
+
+    /// Take commands on stdin while running: pause, resume, standings,
+    /// noise, skip, and stop
+    #[arg(long)]
+    control: bool,
+
+    /// Make the matches take turns at rounds, at most this many rounds at
+    /// once; matches with a human or remote player never wait their turn
+    #[arg(long, value_name = "ROUNDS")]
+    fair: Option<usize>,
+
+    /// Play every match one round per tick of a shared clock, e.g. 1s, so
+    /// that they advance in lockstep
+    #[arg(long, value_parser = super::parse_delay, value_name = "DURATION")]
+    clock: Option<u64>,
+
+    /// Write every game event to this file as it happens, one JSON object
+    /// per line
+    #[arg(long, value_name = "FILE")]
+    events: Option<PathBuf>,
+
+    /// Print how long each player took to answer, and each round to play
+    #[arg(long)]
+    timing: bool,
+
+    /// Print every round as it is played, with what each side played and
+    /// was paid and the running totals, a round every 500ms unless --delay
+    /// says otherwise
+    #[arg(long)]
+    watch: bool,
+
+    /// Listen at this address, e.g. 0.0.0.0:7000, and have the matches
+    /// played by the `worker`s that connect there
+    #[arg(long, value_name = "ADDRESS")]
+    distribute: Option<String>,
+
+    /// Chart each participant's cumulative score in this PNG or SVG file
+    #[cfg(feature = "plots")]
+    #[arg(long)]
+    plot_scores: Option<PathBuf>,
+
+    /// Chart each participant's cooperation round by round in this PNG or SVG file
+    #[cfg(feature = "plots")]
+    #[arg(long)]
+    plot_cooperation: Option<PathBuf>,
+
+    /// Watch the matches live in a terminal dashboard
+    #[cfg(feature = "tui")]
+    #[arg(long)]
+    tui: bool,
+
+    /// Stream game events over WebSockets at this address, e.g. 0.0.0.0:9000,
+    /// waiting for the first spectator before starting, and open a seat for
+    /// each player: participant
+    #[cfg(feature = "websocket")]
+    #[arg(long, value_name = "ADDRESS")]
+    serve_ws: Option<String>,
+}
+
+#[derive(Args)]
+pub struct DuelArgs {
+    /// The blue strategy, e.g. tit-for-tat, or a participant in the config
+    blue: String,
+
+    /// The red strategy, named the same way
+    red: String,
+
+    /// Rounds to play
+    #[arg(long, default_value_t = 200)]
+    rounds: usize,
+
+    /// Probability of flipping each chosen action, overriding the config
+    #[arg(long)]
+    noise: Option<f64>,
+
+    /// TOML file declaring payoffs, termination, and any parameterized
+    /// participants
+    #[arg(long)]
+    config: Option<PathBuf>,
+
+    /// Print every round as it is played, with what each side played and
+    /// was paid and the running totals, a round every 500ms unless --delay
+    /// says otherwise
+    #[arg(long)]
+    watch: bool,
+}
+
+/// How long `--watch` waits between rounds unless `--delay` is given
+const WATCH_DELAY_MS: u64 = 500;
+
+/// Have `bus` print every round it carries, for `--watch`
+fn watch(bus: &Addr<EventBus>) -> Addr<Watcher> {
+    let watcher = Watcher::default().start();
+    bus.do_send(Subscribe(watcher.clone().recipient()));
+    watcher
+}
+
+/// Wait for `watcher` to print every round published to `bus` so far
+async fn drain_watcher(bus: &Addr<EventBus>, watcher: Option<Addr<Watcher>>) {
+    if let Some(watcher) = watcher {
+        let _ = bus.send(Drain).await;
+        let _ = watcher.send(Drain).await;
+    }
+}
+
+/// Play one match between two strategies and print who won
+pub fn duel(global: &GlobalArgs, args: &DuelArgs) {
+    let registry = StrategyRegistry::new();
+    let mut config = load_config(global, args.config.as_ref());
+    let participant = |name: &str| {
+        config
+            .participants
+            .iter()
+            .find(|p| p.name == name)
+            .cloned()
+            .unwrap_or_else(|| {
+                let strategy = strategy_name(&registry, name);
+                ParticipantConfig::new(&strategy, &strategy)
+            })
+    };
+    let blue = participant(&args.blue);
+    let mut red = participant(&args.red);
+    if red.name == blue.name {
+        red.name = twin_name(&red.name);
+    }
+    config.participants = vec![blue, red];
+    config.iterations = args.rounds;
+    if let Some(noise) = args.noise {
+        config.noise = noise;
+    }
+    if args.watch && config.delay_ms.is_none() {
+        config.delay_ms = Some(WATCH_DELAY_MS);
+    }
+    if let Err(e) = config.validate(&registry) {
+        fail(e);
+    }
+    let seed = choose_seed(global, &config);
+    let (quiet, format) = (global.quiet, global.format);
+    let watching = args.watch && !quiet && format == Format::Text;
+
+    let system = start_system(global);
+
+    let execution = async move {
+        let bus = EventBus::default().start();
+        let watcher = if watching {
+            events::publish_to(Some(bus.clone().recipient()));
+            Some(watch(&bus))
+        } else {
+            None
+        };
+        let (blue, red) = (&config.participants[0], &config.participants[1]);
+        let seeds = MatchSeeds::draw(&mut StdRng::seed_from_u64(seed));
+        let report = tournament::start_match(
+            &config,
+            &registry,
+            blue,
+            red,
+            seeds,
+            None,
+            &tournament::Shared::default(),
+        )
+        .await;
+        events::publish_to(None);
+        drain_watcher(&bus, watcher).await;
+        let result = report.result.unwrap_or_else(|e| fail(e));
+        if !quiet {
+            match format {
+                Format::Text => print!("{}", report::duel_summary(&result)),
+                Format::Json => println!("{}", schema::to_string_pretty(&result)),
+            }
+        }
+
+        System::current().stop();
+    };
+    Arbiter::spawn(execution);
+
+    system.run().unwrap();
+}
+
+/// Play the tournament and report, save, and chart its results as asked
+pub fn run(global: &GlobalArgs, args: &RunArgs) {
+    let quiet = global.quiet;
+    let format = global.format;
+
+    if args.repetitions == Some(0) {
+        fail("repetitions must be at least 1");
+    }
+    if args.swiss_rounds == Some(0) {
+        fail("swiss rounds must be at least 1");
+    }
+    let registry = StrategyRegistry::new();
+    #[cfg(feature = "sqlite")]
+    let resumed = args
+        .resume
+        .as_ref()
+        .map(|path| unfinished(global, path, &registry));
+    #[cfg(not(feature = "sqlite"))]
+    let resumed: Option<(i64, PartialTournament)> = None;
+    let (mut config, seed) = match &resumed {
+        Some((_, partial)) => (partial.config.clone(), partial.seed),
+        None => setup(global, args.config.as_ref(), &registry),
+    };
+    if let Some(repetitions) = args.repetitions {
+        config.repetitions = repetitions;
+    }
+    if args.audit_randomness {
+        config.audit_randomness = true;
+    }
+    if args.deterministic_only {
+        config.deterministic_only = true;
+    }
+    let mut system = start_system(global);
+    if config.deterministic_only {
+        let entrants = config.clone();
+        let rejected = system.block_on(async move {
+            determinism::nondeterministic(&entrants, &StrategyRegistry::new(), seed).await
+        });
+        for rejection in &rejected {
+            warn!(participant = %rejection.name, reason = %rejection.reason, "not deterministic, so not admitted");
+        }
+        config
+            .participants
+            .retain(|participant| !rejected.iter().any(|r| r.name == participant.name));
+        if config.participants.len() < 2 {
+            fail("fewer than two deterministic participants are left");
+        }
+    }
+    let tournament_format = args.tournament;
+    let swiss_rounds = args
+        .swiss_rounds
+        .unwrap_or_else(|| formats::swiss_rounds(config.participants.len()));
+
+    #[cfg(feature = "sqlite")]
+    let sqlite_path = args.resume.clone().or_else(|| args.sqlite.clone());
+    #[cfg(feature = "plots")]
+    let plots = (args.plot_scores.clone(), args.plot_cooperation.clone());
+    let (matrix, heatmap) = (args.matrix.clone(), args.heatmap.clone());
+    let html_report = args.report.clone();
+    let axelrod_csv = args.axelrod.clone();
+    #[cfg(feature = "arrow")]
+    let arrow_tables = (args.arrow_matches.clone(), args.arrow_rounds.clone());
+    #[cfg(feature = "webhooks")]
+    let webhooks = global.webhook.clone();
+    let recording = args.record.clone();
+    let output = if args.transcripts {
+        Output::Full
+    } else {
+        args.output
+    };
+    let distribute = args.distribute.clone();
+    if distribute.is_some() {
+        if tournament_format != TournamentFormat::RoundRobin {
+            fail("only round robins can be distributed");
+        }
+        if config.reputation.is_some() {
+            fail("matches played by different workers can't share reputations");
+        }
+        if config.remember_opponents {
+            fail("matches played by different workers can't share memories");
+        }
+    }
+    let control = args.control;
+    let fair = args.fair;
+    let heartbeat = args.clock.map(Duration::from_millis);
+    if heartbeat == Some(Duration::ZERO) {
+        fail("--clock needs to tick at an interval longer than 0");
+    }
+    if fair == Some(0) {
+        fail("--fair needs at least one round at a time");
+    }
+    let timing = args.timing;
+    if args.watch && config.delay_ms.is_none() {
+        config.delay_ms = Some(WATCH_DELAY_MS);
+    }
+    if control {
+        refuse_human(&config);
+    }
+    #[cfg(feature = "tui")]
+    let tui = args.tui;
+    #[cfg(not(feature = "tui"))]
+    let tui = false;
+    // the dashboard shows the rounds itself
+    let watching = args.watch && !(quiet || tui || format != Format::Text);
+    #[cfg(feature = "websocket")]
+    let serve_ws = args.serve_ws.clone();
+    let ratings = args.ratings.clone().map(|path| {
+        let ratings = Ratings::load(&path).unwrap_or_else(|e| fail(e));
+        (path, ratings)
+    });
+    let seeding = formats::seeding(&config, ratings.as_ref().map(|(_, ratings)| ratings));
+    let half_life = args.half_life;
+    let leaderboard = args.leaderboard.clone().map(|path| {
+        let leaderboard = Leaderboard::load(&path).unwrap_or_else(|e| fail(e));
+        (path, leaderboard)
+    });
+
+    let event_log = event_log(args.events.as_ref());
+    // how many matches the other formats play depends on who wins
+    let total = Some(tournament::schedule(&config).1.len())
+        .filter(|_| tournament_format == TournamentFormat::RoundRobin);
+
+    #[cfg(feature = "sqlite")]
+    let journal = match (&sqlite_path, tournament_format, &distribute) {
+        (Some(path), TournamentFormat::RoundRobin, None) => {
+            Some(journal(path, &config, seed, resumed))
+        }
+        _ => None,
+    };
+
+    let execution = async move {
+        let bus = EventBus::default().start();
+        if control {
+            let steering = Control::default();
+            control::steer(Some(steering.clone()));
+            bus.do_send(Subscribe(Controller::on_stdin(steering).recipient()));
+        }
+        if let Some(slots) = fair {
+            scheduler::install(Some(Scheduler::new(slots).start()));
+        }
+        if let Some(interval) = heartbeat {
+            clock::install(Some(Clock::new(interval).start()));
+        }
+        let event_log = subscribe_log(&bus, event_log);
+        #[cfg(feature = "tui")]
+        let dashboard = if tui {
+            // log lines would scribble over the dashboard
+            super::mute_logs(true);
+            let dashboard = crate::tui::Dashboard::new().start();
+            bus.do_send(Subscribe(dashboard.clone().recipient()));
+            Some(dashboard)
+        } else {
+            None
+        };
+        if !(quiet || tui || format != Format::Text || output == Output::Summary) {
+            let reporter = if output == Output::Full {
+                Reporter::default().with_transcripts()
+            } else {
+                Reporter::default()
+            };
+            bus.do_send(Subscribe(reporter.start().recipient()));
+        }
+        let watcher = if watching { Some(watch(&bus)) } else { None };
+        #[cfg(feature = "websocket")]
+        let server = match &serve_ws {
+            Some(address) => {
+                match crate::websocket::serve(address.as_str(), bus.clone(), &config) {
+                    Ok((server, arrival)) => {
+                        tracing::info!("waiting for a spectator to connect");
+                        let _ = arrival.await;
+                        Some(server)
+                    }
+                    Err(e) => fail(format!("unable to serve spectators at {}: {}", address, e)),
+                }
+            }
+            None => None,
+        };
+        events::publish_to(Some(bus.clone().recipient()));
+        // workers play a distributed tournament's matches
+        let bar = if tui || distribute.is_some() {
+            None
+        } else {
+            show_progress(quiet, total, Unit::Matches)
+        };
+        let (reports, placings) = match (tournament_format, &distribute) {
+            (_, Some(address)) => (
+                distributed::round_robin(&config, seed, address)
+                    .await
+                    .unwrap_or_else(|e| {
+                        fail(format!("unable to coordinate at {}: {}", address, e))
+                    }),
+                Vec::new(),
+            ),
+            (TournamentFormat::RoundRobin, None) => {
+                #[cfg(feature = "sqlite")]
+                let reports = match &journal {
+                    Some((store, tournament_id, partial)) => {
+                        let save = |pairing, report: &tournament::MatchReport| {
+                            save_played(store, *tournament_id, pairing, report)
+                        };
+                        tournament::resume(partial, &registry, &save).await
+                    }
+                    None => tournament::round_robin(&config, &registry, seed, None).await,
+                };
+                #[cfg(not(feature = "sqlite"))]
+                let reports = tournament::round_robin(&config, &registry, seed, None).await;
+                (reports, Vec::new())
+            }
+            (TournamentFormat::Elimination, None) => {
+                formats::elimination(&config, &registry, seed, &seeding, None).await
+            }
+            (TournamentFormat::Swiss, None) => {
+                formats::swiss(&config, &registry, seed, &seeding, swiss_rounds, None).await
+            }
+        };
+        hide_progress(bar).await;
+        events::publish_to(None);
+        control::steer(None);
+        scheduler::install(None);
+        clock::install(None);
+        drain(&bus, event_log).await;
+        drain_watcher(&bus, watcher).await;
+        #[cfg(feature = "websocket")]
+        if let Some(server) = server {
+            server.stop(true).await;
+        }
+        #[cfg(feature = "tui")]
+        if let Some(dashboard) = dashboard {
+            let _ = dashboard.send(crate::tui::Close).await;
+            super::mute_logs(false);
+        }
+        if let Some(path) = &recording {
+            if let Err(e) = Recording::new(&config, seed, &reports).save(path) {
+                warn!(path = %path.display(), error = %e, "unable to write recording");
+            }
+        }
+        let mut record = TournamentRecord::new(&config, seed, reports);
+        record.describe_strategies(&registry);
+        if tournament_format != TournamentFormat::RoundRobin {
+            // each pairing is played once, so there is nothing to summarize
+            record.pairings.clear();
+            record.placings = placings;
+        }
+        if let Some((path, mut ratings)) = ratings {
+            record.ratings = ratings.record_tournament(&record);
+            if let Err(e) = ratings.save(&path) {
+                warn!(path = %path.display(), error = %e, "unable to write ratings");
+            }
+        }
+        if let Some((path, mut leaderboard)) = leaderboard {
+            if let Some(days) = half_life {
+                let now = std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map_or(0, |elapsed| elapsed.as_secs());
+                leaderboard.decay(now, (days * 24.0 * 60.0 * 60.0) as u64);
+            }
+            leaderboard.record_tournament(&record);
+            if let Err(e) = leaderboard.save(&path) {
+                warn!(path = %path.display(), error = %e, "unable to write leaderboard");
+            }
+        }
+        for name in &record.disqualified {
+            warn!(participant = %name, "disqualified for going over budget");
+        }
+        for aborted in &record.aborted {
+            warn!(
+                blue = %aborted.blue,
+                red = %aborted.red,
+                error = %aborted.error,
+                "match aborted"
+            );
+        }
+        #[cfg(feature = "webhooks")]
+        crate::webhooks::notify(&webhooks, crate::webhooks::Finished::Tournament(&record));
+
+        #[cfg(feature = "sqlite")]
+        if let Some(path) = &sqlite_path {
+            let written = match journal {
+                Some((_, tournament_id, _)) if interrupt::interrupted() => {
+                    warn!(
+                        path = %path.display(),
+                        tournament = tournament_id,
+                        "tournament left unfinished; --resume finishes it"
+                    );
+                    Ok(())
+                }
+                Some((mut store, tournament_id, _)) => {
+                    store.finish_tournament(tournament_id, &record)
+                }
+                None => ResultStore::open(path)
+                    .and_then(|mut store| store.record_tournament(&record))
+                    .map(|_| ()),
+            };
+            if let Err(e) = written {
+                warn!(path = %path.display(), error = %e, "unable to write results");
+            }
+        }
+
+        if let Some(path) = &html_report {
+            if let Err(e) = std::fs::write(path, html::tournament_report(&record)) {
+                warn!(path = %path.display(), error = %e, "unable to write report");
+            }
+        }
+        if let Some(path) = &axelrod_csv {
+            match axelrod::interactions_csv(&record) {
+                Ok(csv) => {
+                    if let Err(e) = std::fs::write(path, csv) {
+                        warn!(path = %path.display(), error = %e, "unable to write interactions");
+                    }
+                }
+                Err(e) => {
+                    warn!(path = %path.display(), error = %e, "unable to export interactions")
+                }
+            }
+        }
+        #[cfg(feature = "arrow")]
+        {
+            let (matches, rounds) = &arrow_tables;
+            if let Some(path) = matches {
+                if let Err(e) = crate::arrow::write_matches(&record, path) {
+                    warn!(path = %path.display(), error = %e, "unable to write matches");
+                }
+            }
+            if let Some(path) = rounds {
+                if let Err(e) = crate::arrow::write_rounds(&record, path) {
+                    warn!(path = %path.display(), error = %e, "unable to write rounds");
+                }
+            }
+        }
+        if matrix.is_some() || heatmap.is_some() {
+            let table = PayoffTable::new(&record);
+            if let Some(path) = &matrix {
+                if let Err(e) = std::fs::write(path, table.to_csv()) {
+                    warn!(path = %path.display(), error = %e, "unable to write matrix");
+                }
+            }
+            if let Some(path) = &heatmap {
+                if let Err(e) = table.write_png(path) {
+                    warn!(path = %path.display(), error = %e, "unable to write heat map");
+                }
+            }
+        }
+
+        #[cfg(feature = "plots")]
+        {
+            let (scores, cooperation) = &plots;
+            if let Some(path) = scores {
+                if let Err(e) = crate::plots::cumulative_scores(&record, path) {
+                    warn!(path = %path.display(), error = %e, "unable to write chart");
+                }
+            }
+            if let Some(path) = cooperation {
+                if let Err(e) = crate::plots::cooperation(&record, path) {
+                    warn!(path = %path.display(), error = %e, "unable to write chart");
+                }
+            }
+        }
+
+        if !quiet {
+            match format {
+                Format::Text => {
+                    if !record.placings.is_empty() {
+                        print!("\n{}", report::placings_table(&record.placings));
+                    }
+                    print!("\n{}", report::standings_table(&record.standings));
+                    if record.config.ranking.is_some() {
+                        print!("\n{}", report::objectives_table(&record.standings));
+                    }
+                    if output != Output::Summary {
+                        if !record.pairings.is_empty() {
+                            print!("\n{}", report::pairing_table(&record.pairings));
+                        }
+                        if !record.ratings.is_empty() {
+                            print!("\n{}", report::ratings_table(&record.ratings));
+                        }
+                        if timing {
+                            print!("\n{}", report::timing_table(&record.matches));
+                        }
+                    }
+                }
+                Format::Json => {
+                    match output {
+                        Output::Summary => record.matches.clear(),
+                        Output::Standard => {
+                            for result in &mut record.matches {
+                                result.transcript.clear();
+                                result.draws = None;
+                            }
+                        }
+                        Output::Full => (),
+                    }
+                    println!("{}", schema::to_string_pretty(&record))
+                }
+            }
+        }
+
+        System::current().stop();
+    };
+    Arbiter::spawn(execution);
+
+    system.run().unwrap();
+}
+
+/// The round robin left unfinished in the database at `path`, with its id,
+/// checked against `registry`
+#[cfg(feature = "sqlite")]
+fn unfinished(
+    global: &GlobalArgs,
+    path: &PathBuf,
+    registry: &StrategyRegistry,
+) -> (i64, PartialTournament) {
+    let (tournament_id, partial) = ResultStore::open(path)
+        .and_then(|store| store.unfinished())
+        .unwrap_or_else(|e| fail(format!("unable to read {}: {}", path.display(), e)))
+        .unwrap_or_else(|| fail(format!("{} has no unfinished tournament", path.display())));
+    if let Err(e) = partial.config.validate(registry) {
+        fail(e);
+    }
+    if partial.config.reputation.is_some() {
+        fail("matches sharing reputations can't be resumed");
+    }
+    if partial.config.remember_opponents {
+        fail("matches sharing memories can't be resumed");
+    }
+    if !global.quiet && global.format == Format::Text {
+        println!("seed: {}", partial.seed);
+        println!(
+            "resuming tournament {}: {} of {} matches already played",
+            tournament_id,
+            partial.played.len(),
+            tournament::schedule(&partial.config).1.len()
+        );
+    }
+    (tournament_id, partial)
+}
+
+/// The database to save the round robin of `config` to as it is played,
+/// the tournament's id there, and the part of it played already
+#[cfg(feature = "sqlite")]
+fn journal(
+    path: &PathBuf,
+    config: &SimConfig,
+    seed: u64,
+    resumed: Option<(i64, PartialTournament)>,
+) -> (ResultStore, i64, PartialTournament) {
+    let mut store = ResultStore::open(path)
+        .unwrap_or_else(|e| fail(format!("unable to open {}: {}", path.display(), e)));
+    let (tournament_id, partial) = match resumed {
+        Some(resumed) => resumed,
+        None => {
+            let tournament_id = store
+                .begin_tournament(config, seed)
+                .unwrap_or_else(|e| fail(format!("unable to write {}: {}", path.display(), e)));
+            let partial = PartialTournament {
+                config: config.clone(),
+                seed,
+                played: Default::default(),
+            };
+            (tournament_id, partial)
+        }
+    };
+    (store, tournament_id, partial)
+}
+
+/// Save a match of the journaled tournament, unless Ctrl-C cut it short
+#[cfg(feature = "sqlite")]
+fn save_played(
+    store: &ResultStore,
+    tournament_id: i64,
+    pairing: usize,
+    report: &tournament::MatchReport,
+) {
+    let result = match &report.result {
+        Ok(result) if result.stopped_by.as_deref() != Some("interrupted") => result,
+        _ => return,
+    };
+    if let Err(e) = store.record_played(tournament_id, pairing, result) {
+        warn!(pairing, error = %e, "unable to save match");
+    }
+}
@@ -0,0 +1,130 @@
+//! `serve`, `worker`, and `bench`: long-running and timing commands
+
+use actix::prelude::*;
+use clap::Args;
+
+use crate::bench::{self, BenchSettings};
+use crate::distributed;
+use crate::registry::StrategyRegistry;
+use crate::report;
+use crate::schema;
+
+use super::{fail, start_system, Format, GlobalArgs};
+
+#[cfg(feature = "server")]
+#[derive(Args)]
+pub struct ServeArgs {
+    /// Address to listen on
+    #[arg(long, default_value = "127.0.0.1:8080")]
+    address: String,
+}
+
+#[derive(Args)]
+pub struct WorkerArgs {
+    /// The address the coordinator listens at
+    #[arg(long, value_name = "ADDRESS")]
+    coordinator: String,
+
+    /// Matches to play at once
+    #[arg(long, default_value_t = 1)]
+    jobs: usize,
+}
+
+#[derive(Args)]
+pub struct BenchArgs {
+    /// Rounds in each match
+    #[arg(long, default_value_t = 1_000_000)]
+    rounds: usize,
+
+    /// Matches to play at once
+    #[arg(long, default_value_t = 1)]
+    matches: usize,
+
+    /// The blue player's strategy
+    #[arg(long, default_value = "tit-for-tat")]
+    blue: String,
+
+    /// The red player's strategy
+    #[arg(long, default_value = "pavlov")]
+    red: String,
+}
+
+#[cfg(feature = "server")]
+/// Run jobs submitted over the JSON API until stopped
+pub fn serve(global: &GlobalArgs, args: &ServeArgs) {
+    // nobody is at the terminal, and submitted configs mustn't name files
+    let mut registry = StrategyRegistry::new();
+    registry.unregister(crate::human::NAME);
+    registry.clear_loaders();
+
+    let system = start_system(global);
+    if let Err(e) = crate::server::serve(args.address.as_str(), registry) {
+        fail(format!("unable to serve at {}: {}", args.address, e));
+    }
+    system.run().unwrap();
+}
+
+/// Play the matches a coordinator hands out until it has no more
+pub fn worker(global: &GlobalArgs, args: &WorkerArgs) {
+    if args.jobs == 0 {
+        fail("a worker must play at least one match at a time");
+    }
+    // nobody is at this terminal
+    let mut registry = StrategyRegistry::new();
+    registry.unregister(crate::human::NAME);
+    let (address, jobs) = (args.coordinator.clone(), args.jobs);
+    let quiet = global.quiet;
+
+    let system = start_system(global);
+
+    let execution = async move {
+        match distributed::work(&address, &registry, jobs).await {
+            Ok(played) => {
+                if !quiet {
+                    println!("played {} matches for {}", played, address);
+                }
+            }
+            Err(e) => fail(format!("{}: {}", address, e)),
+        }
+
+        System::current().stop();
+    };
+    Arbiter::spawn(execution);
+
+    system.run().unwrap();
+}
+
+/// Time the benchmark workload and print how it went
+pub fn bench(global: &GlobalArgs, args: &BenchArgs) {
+    let quiet = global.quiet;
+    let format = global.format;
+
+    let registry = StrategyRegistry::new();
+    // a fixed seed, so that every run plays the same workload
+    let settings = BenchSettings {
+        blue: args.blue.clone(),
+        red: args.red.clone(),
+        rounds: args.rounds,
+        matches: args.matches,
+        seed: global.seed.unwrap_or(0),
+    };
+
+    let system = start_system(global);
+
+    let execution = async move {
+        let record = bench::run(&settings, &registry)
+            .await
+            .unwrap_or_else(|e| fail(e));
+        if !quiet {
+            match format {
+                Format::Text => print!("{}", report::bench_table(&record)),
+                Format::Json => println!("{}", schema::to_string_pretty(&record)),
+            }
+        }
+
+        System::current().stop();
+    };
+    Arbiter::spawn(execution);
+
+    system.run().unwrap();
+}
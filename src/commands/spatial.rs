@@ -0,0 +1,468 @@
+//! `lattice`, `network`, `compare`, `public-goods`, and `teams`: games with
+//! structure
+
+use actix::prelude::*;
+use clap::{Args, ValueEnum};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+use crate::config::SimConfig;
+use crate::population::Population;
+use crate::public_goods::{self, PublicGoods};
+use crate::registry::StrategyRegistry;
+use crate::report;
+use crate::schema;
+use crate::spatial::{self, Imitation, SpatialRecord};
+use crate::teams::{self, Rotation};
+use crate::topology::{Complete, Graph, Lattice, Topology};
+
+use super::{fail, setup, start_system, Format, GlobalArgs};
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum NetworkModel {
+    /// Watts-Strogatz: a ring lattice with randomly rewired edges
+    SmallWorld,
+
+    /// Barabasi-Albert: preferential attachment
+    ScaleFree,
+}
+
+#[derive(Args)]
+pub struct LatticeArgs {
+    /// TOML file declaring payoffs, iterations, and the competing strategies
+    #[arg(long)]
+    config: Option<PathBuf>,
+
+    /// Cells per row
+    #[arg(long, default_value_t = 10)]
+    width: usize,
+
+    /// Rows in the grid
+    #[arg(long, default_value_t = 10)]
+    height: usize,
+
+    /// How many generations to run
+    #[arg(long, default_value_t = 20)]
+    generations: usize,
+
+    /// Imitate a random neighbour by the Fermi rule at this selection
+    /// temperature, rather than copying the best neighbour
+    #[arg(long)]
+    fermi: Option<f64>,
+
+    /// Start from the population in this JSON file, as counts placed at
+    /// random, nodes, or a fill, such as a snapshot; at random otherwise
+    #[arg(long)]
+    start: Option<PathBuf>,
+
+    /// Write each generation to this directory as numbered JSON snapshots,
+    /// frame-0000.json on, for animating
+    #[arg(long)]
+    snapshots: Option<PathBuf>,
+
+    /// Snapshot only every this many generations, and the last
+    #[arg(long, default_value_t = 1)]
+    snapshot_every: usize,
+
+    /// Draw each snapshotted generation's grid to this directory as
+    /// numbered PNG frames, frame-0000.png on
+    #[cfg(feature = "plots")]
+    #[arg(long)]
+    frames: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct NetworkArgs {
+    /// TOML file declaring payoffs, iterations, and the competing strategies
+    #[arg(long)]
+    config: Option<PathBuf>,
+
+    /// Edge list file, one pair of zero-based node numbers per line
+    #[arg(long, conflicts_with = "model", required_unless_present = "model")]
+    edges: Option<PathBuf>,
+
+    /// Generate a network instead of loading one
+    #[arg(long, value_enum)]
+    model: Option<NetworkModel>,
+
+    /// Nodes in a generated network
+    #[arg(long, default_value_t = 50)]
+    nodes: usize,
+
+    /// Ring neighbours for small-world, or edges per new node for scale-free
+    #[arg(long, default_value_t = 4)]
+    degree: usize,
+
+    /// Probability of rewiring each edge of a small-world network
+    #[arg(long, default_value_t = 0.1)]
+    rewire: f64,
+
+    /// How many generations to run
+    #[arg(long, default_value_t = 20)]
+    generations: usize,
+
+    /// Imitate a random neighbour by the Fermi rule at this selection
+    /// temperature, rather than copying the best neighbour
+    #[arg(long)]
+    fermi: Option<f64>,
+
+    /// Start from the population in this JSON file, as counts placed at
+    /// random, nodes, or a fill, such as a snapshot; at random otherwise
+    #[arg(long)]
+    start: Option<PathBuf>,
+
+    /// Write each generation to this directory as numbered JSON snapshots,
+    /// frame-0000.json on, for animating
+    #[arg(long)]
+    snapshots: Option<PathBuf>,
+
+    /// Snapshot only every this many generations, and the last
+    #[arg(long, default_value_t = 1)]
+    snapshot_every: usize,
+}
+
+#[derive(Args)]
+pub struct CompareArgs {
+    /// TOML file declaring payoffs, iterations, and the competing strategies
+    #[arg(long)]
+    config: Option<PathBuf>,
+
+    /// Cells per row of the lattice
+    #[arg(long, default_value_t = 10)]
+    width: usize,
+
+    /// Rows of the lattice; the other topologies get as many nodes
+    #[arg(long, default_value_t = 10)]
+    height: usize,
+
+    /// Ring neighbours of the small-world network, eight to match the
+    /// lattice's
+    #[arg(long, default_value_t = 8)]
+    degree: usize,
+
+    /// Probability of rewiring each edge of the small-world network
+    #[arg(long, default_value_t = 0.1)]
+    rewire: f64,
+
+    /// How many generations to run on each topology
+    #[arg(long, default_value_t = 20)]
+    generations: usize,
+
+    /// Imitate a random neighbour by the Fermi rule at this selection
+    /// temperature, rather than copying the best neighbour
+    #[arg(long)]
+    fermi: Option<f64>,
+}
+
+#[derive(Args)]
+pub struct PublicGoodsArgs {
+    /// TOML file declaring iterations and the competing strategies
+    #[arg(long)]
+    config: Option<PathBuf>,
+
+    /// Players per group; every participant at once if omitted
+    #[arg(long)]
+    group_size: Option<usize>,
+
+    /// Factor the pot is multiplied by before it is shared, between 1 and the group size
+    #[arg(long)]
+    multiplier: Option<f64>,
+
+    /// What a contributor puts in each round
+    #[arg(long, default_value_t = 1.0)]
+    cost: f64,
+}
+
+/// Play the participants on a grid, saving snapshots if asked
+pub fn lattice(global: &GlobalArgs, args: &LatticeArgs) {
+    let quiet = global.quiet;
+    let format = global.format;
+
+    let lattice = Lattice::new(args.width, args.height)
+        .unwrap_or_else(|| fail("the lattice must be at least 3 cells in each direction"));
+    let imitation = imitation(args.fermi);
+    let registry = StrategyRegistry::new();
+    let (config, seed) = setup(global, args.config.as_ref(), &registry);
+    let start = start(args.start.as_ref(), &config, lattice.nodes(), seed);
+    let generations = args.generations;
+    let (snapshots, stride) = (args.snapshots.clone(), snapshot_stride(args.snapshot_every));
+    #[cfg(feature = "plots")]
+    let frames = args.frames.clone();
+
+    let system = start_system(global);
+
+    let execution = async move {
+        let record = spatial::run_spatial(
+            &config,
+            &registry,
+            seed,
+            &lattice,
+            generations,
+            imitation,
+            start.as_deref(),
+        )
+        .await;
+        if let Some(dir) = &snapshots {
+            write_snapshots(&record, Some(lattice), dir, stride);
+        }
+        #[cfg(feature = "plots")]
+        if let Some(dir) = &frames {
+            if let Err(e) = crate::plots::lattice_frames(&record, lattice, dir, stride) {
+                warn!(path = %dir.display(), error = %e, "unable to draw frames");
+            }
+        }
+        if !quiet {
+            match format {
+                Format::Text => print!("{}", report::lattice_grids(&record, lattice)),
+                Format::Json => println!("{}", schema::to_string_pretty(&record)),
+            }
+        }
+
+        System::current().stop();
+    };
+    Arbiter::spawn(execution);
+
+    system.run().unwrap();
+}
+
+fn imitation(fermi: Option<f64>) -> Imitation {
+    match fermi {
+        None => Imitation::Best,
+        Some(temperature) if temperature > 0.0 => Imitation::Fermi { temperature },
+        Some(_) => fail("--fermi needs a temperature above 0"),
+    }
+}
+
+fn snapshot_stride(every: usize) -> usize {
+    if every == 0 {
+        fail("--snapshot-every needs at least one generation");
+    }
+    every
+}
+
+/// The participant at each node from the `--start` file, if there is one
+fn start(
+    path: Option<&PathBuf>,
+    config: &SimConfig,
+    nodes: usize,
+    seed: u64,
+) -> Option<Vec<usize>> {
+    let path = path?;
+    let mut rng = StdRng::seed_from_u64(seed);
+    Population::load(path)
+        .map_err(|e| e.to_string())
+        .and_then(|start| start.place(&config.participants, nodes, &mut rng))
+        .map(Some)
+        .unwrap_or_else(|e| fail(format!("{}: {}", path.display(), e)))
+}
+
+fn write_snapshots(record: &SpatialRecord, lattice: Option<Lattice>, dir: &Path, stride: usize) {
+    if let Err(e) = spatial::write_snapshots(record, lattice, dir, stride) {
+        warn!(path = %dir.display(), error = %e, "unable to write snapshots");
+    }
+}
+
+/// Play the participants on a loaded or generated network
+pub fn network(global: &GlobalArgs, args: &NetworkArgs) {
+    let quiet = global.quiet;
+    let format = global.format;
+
+    let imitation = imitation(args.fermi);
+    let registry = StrategyRegistry::new();
+    let (config, seed) = setup(global, args.config.as_ref(), &registry);
+    let mut rng = StdRng::seed_from_u64(seed);
+    let graph = match (&args.edges, args.model) {
+        (Some(path), _) => Graph::load(path),
+        (None, Some(NetworkModel::SmallWorld)) => {
+            Graph::small_world(args.nodes, args.degree, args.rewire, &mut rng)
+        }
+        (None, Some(NetworkModel::ScaleFree)) => {
+            Graph::scale_free(args.nodes, args.degree, &mut rng)
+        }
+        (None, None) => unreachable!("clap requires --edges or --model"),
+    }
+    .unwrap_or_else(|e| fail(e));
+    if graph.nodes() < 2 {
+        fail("the network needs at least two nodes");
+    }
+    let start = start(args.start.as_ref(), &config, graph.nodes(), seed);
+    let generations = args.generations;
+    let (snapshots, stride) = (args.snapshots.clone(), snapshot_stride(args.snapshot_every));
+
+    let system = start_system(global);
+
+    let execution = async move {
+        let record = spatial::run_spatial(
+            &config,
+            &registry,
+            seed,
+            &graph,
+            generations,
+            imitation,
+            start.as_deref(),
+        )
+        .await;
+        if let Some(dir) = &snapshots {
+            write_snapshots(&record, None, dir, stride);
+        }
+        if !quiet {
+            match format {
+                Format::Text => print!("{}", report::network_states(&record)),
+                Format::Json => println!("{}", schema::to_string_pretty(&record)),
+            }
+        }
+
+        System::current().stop();
+    };
+    Arbiter::spawn(execution);
+
+    system.run().unwrap();
+}
+
+/// Play the participants well-mixed, on a lattice, and on a small-world
+/// network of as many nodes, and compare how each ends
+pub fn compare(global: &GlobalArgs, args: &CompareArgs) {
+    let quiet = global.quiet;
+    let format = global.format;
+
+    let lattice = Lattice::new(args.width, args.height)
+        .unwrap_or_else(|| fail("the lattice must be at least 3 cells in each direction"));
+    let imitation = imitation(args.fermi);
+    let registry = StrategyRegistry::new();
+    let (config, seed) = setup(global, args.config.as_ref(), &registry);
+    let nodes = lattice.nodes();
+    let network = Graph::small_world(
+        nodes,
+        args.degree,
+        args.rewire,
+        &mut StdRng::seed_from_u64(seed),
+    )
+    .unwrap_or_else(|e| fail(e));
+    let generations = args.generations;
+
+    let system = start_system(global);
+
+    let execution = async move {
+        let topologies: [(&str, &dyn Topology); 3] = [
+            ("well-mixed", &Complete(nodes)),
+            ("lattice", &lattice),
+            ("network", &network),
+        ];
+        let comparison = spatial::compare(
+            &config,
+            &registry,
+            seed,
+            &topologies,
+            generations,
+            imitation,
+        )
+        .await;
+        if !quiet {
+            match format {
+                Format::Text => print!("{}", report::comparison_table(&comparison)),
+                Format::Json => println!("{}", schema::to_string_pretty(&comparison)),
+            }
+        }
+
+        System::current().stop();
+    };
+    Arbiter::spawn(execution);
+
+    system.run().unwrap();
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum TeamRotation {
+    /// The first members meet, then the second, by turns
+    Paired,
+
+    /// Every member meets each opposing member in turn
+    Rotating,
+
+    /// Either member of each team, drawn afresh each round
+    Random,
+}
+
+#[derive(Args)]
+pub struct TeamsArgs {
+    /// TOML file declaring payoffs, iterations, and the competing strategies,
+    /// teamed up two by two in the order listed
+    #[arg(long)]
+    config: Option<PathBuf>,
+
+    /// Which member of each team is seated in each round
+    #[arg(long, value_enum, default_value = "paired")]
+    rotation: TeamRotation,
+}
+
+/// Play a public goods game in every group of the participants
+pub fn public_goods(global: &GlobalArgs, args: &PublicGoodsArgs) {
+    let quiet = global.quiet;
+    let format = global.format;
+
+    let registry = StrategyRegistry::new();
+    let (config, seed) = setup(global, args.config.as_ref(), &registry);
+    let size = args.group_size.unwrap_or(config.participants.len());
+    if size > config.participants.len() {
+        fail(format!(
+            "a group of {} needs at least that many participants",
+            size
+        ));
+    }
+    // halfway between 1 and the group size unless given
+    let multiplier = args.multiplier.unwrap_or((1.0 + size as f64) / 2.0);
+    let game = PublicGoods::new(size, multiplier, args.cost).unwrap_or_else(|e| fail(e));
+
+    let system = start_system(global);
+
+    let execution = async move {
+        let record = public_goods::play_public_goods(&config, &registry, seed, game).await;
+        if !quiet {
+            match format {
+                Format::Text => print!("{}", report::group_standings_table(&record.standings)),
+                Format::Json => println!("{}", schema::to_string_pretty(&record)),
+            }
+        }
+
+        System::current().stop();
+    };
+    Arbiter::spawn(execution);
+
+    system.run().unwrap();
+}
+
+/// Play every team of two participants against every other
+pub fn teams(global: &GlobalArgs, args: &TeamsArgs) {
+    let quiet = global.quiet;
+    let format = global.format;
+
+    let registry = StrategyRegistry::new();
+    let (config, seed) = setup(global, args.config.as_ref(), &registry);
+    if let Err(e) = teams::teams(&config.participants) {
+        fail(e);
+    }
+    let rotation = match args.rotation {
+        TeamRotation::Paired => Rotation::Paired,
+        TeamRotation::Rotating => Rotation::Rotating,
+        TeamRotation::Random => Rotation::Random,
+    };
+
+    let system = start_system(global);
+
+    let execution = async move {
+        let record = teams::play_teams(&config, &registry, seed, rotation).await;
+        if !quiet {
+            match format {
+                Format::Text => print!("{}", report::team_standings_table(&record.standings)),
+                Format::Json => println!("{}", schema::to_string_pretty(&record)),
+            }
+        }
+
+        System::current().stop();
+    };
+    Arbiter::spawn(execution);
+
+    system.run().unwrap();
+}
@@ -0,0 +1,172 @@
+//! `sweep` and `tune`: tournaments over grids of settings
+
+use actix::prelude::*;
+use clap::Args;
+use std::path::PathBuf;
+use tracing::warn;
+
+use crate::registry::StrategyRegistry;
+use crate::report;
+use crate::schema;
+use crate::sweep::{Axis, Sweep};
+use crate::tune::{self, GridAxis, Tuning};
+
+use crate::progress::Unit;
+
+use super::{
+    choose_seed, fail, hide_progress, load_config, show_progress, start_system, Format, GlobalArgs,
+};
+
+#[derive(Args)]
+pub struct SweepArgs {
+    /// TOML file declaring payoffs, iterations, and participants
+    #[arg(long)]
+    config: Option<PathBuf>,
+
+    /// A setting and its values, e.g. noise=0:0.1:0.02, continuation=0.9,0.99,
+    /// or gtft.generosity=0.1,0.2; repeat to sweep a grid
+    #[arg(long, required = true)]
+    vary: Vec<Axis>,
+
+    /// Write the CSV to this file instead of stdout
+    #[arg(long)]
+    output: Option<PathBuf>,
+
+    /// Also write the same rows as an Arrow IPC file, for polars or pandas
+    #[cfg(feature = "arrow")]
+    #[arg(long, value_name = "FILE")]
+    arrow: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct TuneArgs {
+    /// TOML file for the payoffs, match length, noise, and repetitions; its
+    /// participants are the opponents unless --opponent-pool is given
+    #[arg(long)]
+    config: Option<PathBuf>,
+
+    /// The strategy to tune
+    #[arg(long)]
+    strategy: String,
+
+    /// The opponents: classic, a handful of well-known simple strategies,
+    /// or axelrod, the entries of Axelrod's first tournament
+    #[arg(long)]
+    opponent_pool: Option<String>,
+
+    /// Each parameter's values, as parameter=start..stop:step or
+    /// parameter=value, separated by commas, e.g. p_cc=0..1:0.1,p_dd=0..1:0.5
+    #[arg(long, required = true, value_delimiter = ',')]
+    grid: Vec<GridAxis>,
+
+    /// Search the box the grid spans for this many generations instead of
+    /// playing every point of the grid
+    #[arg(long, value_name = "GENERATIONS")]
+    evolve: Option<usize>,
+
+    /// How many points each generation of --evolve plays
+    #[arg(long, default_value_t = 8, requires = "evolve")]
+    population: usize,
+}
+
+/// Print the standings at every point of a grid of settings as CSV
+pub fn sweep(global: &GlobalArgs, args: &SweepArgs) {
+    let quiet = global.quiet;
+    let format = global.format;
+
+    let registry = StrategyRegistry::new();
+    // the seed is only logged, so that stdout is all CSV
+    let config = load_config(global, args.config.as_ref());
+    let seed = choose_seed(global, &config);
+    let sweep = Sweep::new(&config, args.vary.clone(), &registry).unwrap_or_else(|e| fail(e));
+    let output = args.output.clone();
+    #[cfg(feature = "arrow")]
+    let arrow = args.arrow.clone();
+    #[cfg(feature = "webhooks")]
+    let webhooks = global.webhook.clone();
+    let matches = sweep.matches();
+
+    let system = start_system(global);
+
+    let execution = async move {
+        let bar = show_progress(quiet, Some(matches), Unit::Matches);
+        let record = sweep.run(&registry, seed).await;
+        hide_progress(bar).await;
+        if let Some(path) = &output {
+            if let Err(e) = std::fs::write(path, record.to_csv()) {
+                warn!(path = %path.display(), error = %e, "unable to write sweep");
+            }
+        }
+        #[cfg(feature = "arrow")]
+        if let Some(path) = &arrow {
+            if let Err(e) = crate::arrow::write_sweep(&record, path) {
+                warn!(path = %path.display(), error = %e, "unable to write sweep");
+            }
+        }
+        #[cfg(feature = "webhooks")]
+        crate::webhooks::notify(&webhooks, crate::webhooks::Finished::Sweep(&record));
+        if !quiet {
+            match format {
+                Format::Text if output.is_none() => print!("{}", record.to_csv()),
+                Format::Text => (),
+                Format::Json => println!("{}", schema::to_string_pretty(&record)),
+            }
+        }
+
+        System::current().stop();
+    };
+    Arbiter::spawn(execution);
+
+    system.run().unwrap();
+}
+
+/// Search a strategy's parameters for its best response to a pool
+pub fn tune(global: &GlobalArgs, args: &TuneArgs) {
+    let quiet = global.quiet;
+    let format = global.format;
+
+    let registry = StrategyRegistry::new();
+    let config = load_config(global, args.config.as_ref());
+    let pool = match &args.opponent_pool {
+        Some(name) => tune::opponent_pool(name).unwrap_or_else(|| {
+            fail(format!(
+                "unknown opponent pool '{}', expected classic or axelrod",
+                name
+            ))
+        }),
+        None => config.participants.clone(),
+    };
+    if args.population < 2 {
+        fail("the population must be at least 2");
+    }
+    let search = args
+        .evolve
+        .map(|generations| (generations, args.population));
+    let seed = choose_seed(global, &config);
+    let tuning = Tuning::new(&config, &args.strategy, args.grid.clone(), pool, &registry)
+        .unwrap_or_else(|e| fail(e));
+
+    let system = start_system(global);
+
+    let execution = async move {
+        let record = match search {
+            Some((generations, population)) => {
+                tuning
+                    .evolve(&registry, seed, generations, population)
+                    .await
+            }
+            None => tuning.run(&registry, seed).await,
+        };
+        if !quiet {
+            match format {
+                Format::Text => print!("{}", report::tune_table(&record)),
+                Format::Json => println!("{}", schema::to_string_pretty(&record)),
+            }
+        }
+
+        System::current().stop();
+    };
+    Arbiter::spawn(execution);
+
+    system.run().unwrap();
+}
@@ -0,0 +1,748 @@
+use rand::rngs::StdRng;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::evolution::PartnerChoice;
+use crate::game::{Action, ActionTable, Game, PayoffMatrix, Score};
+use crate::objectives::Ranking;
+use crate::prisoner::{Budget, FaultPolicy};
+use crate::registry::{Parameters, StrategyArgs, StrategyRegistry};
+use crate::reputation::ReputationRule;
+use crate::strategy::Tag;
+use crate::termination::TerminationConfig;
+
+/// A simulation described by a TOML file
+///
+/// ```toml
+/// game = "prisoners-dilemma"
+/// iterations = 200
+/// noise = 0.0
+/// misperception = 0.0
+/// seed = 42
+///
+/// [termination]
+/// kind = "fixed"
+///
+/// [faults]
+/// kind = "forfeit"
+///
+/// [payoffs]
+/// reward = 3
+/// temptation = 4
+/// punishment = 2
+/// sucker = 1
+///
+/// [[participants]]
+/// name = "blue"
+/// strategy = "tit-for-tat"
+///
+/// [[participants]]
+/// name = "red"
+/// strategy = "generous-tit-for-tat"
+/// parameters = { generosity = 0.1 }
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SimConfig {
+    /// Rounds per match, or the most any match may last if `termination`
+    /// can end it sooner
+    #[serde(default = "default_iterations")]
+    pub iterations: usize,
+
+    #[serde(default)]
+    pub termination: TerminationConfig,
+
+    /// What a prisoner does when its strategy panics
+    #[serde(default)]
+    pub faults: FaultPolicy,
+
+    /// What each strategy may spend deciding in a match; see [`Budget`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub budget: Option<Budget>,
+
+    /// Times to resend a message a prisoner hasn't answered before it forfeits
+    #[serde(default = "default_retries")]
+    pub retries: usize,
+
+    /// Milliseconds a prisoner has to decide, overridden by `--decision-timeout`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decision_timeout_ms: Option<u64>,
+
+    /// Milliseconds to wait between rounds, overridden by `--delay`, to
+    /// watch matches play out
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delay_ms: Option<u64>,
+
+    /// Played for a prisoner that misses the decision timeout
+    #[serde(default = "default_timeout_action")]
+    pub timeout_action: Action,
+
+    /// Probability that an executed action is flipped
+    #[serde(default)]
+    pub noise: f64,
+
+    /// Probability that the opponent's action is misreported to a prisoner
+    #[serde(default)]
+    pub misperception: f64,
+
+    /// Weight each round's payoffs by this to the power of the round,
+    /// counting from 0, so that later rounds count for less
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub discount: Option<f64>,
+
+    /// Seed for every random number generator in the run, overridden by `--seed`
+    #[serde(default)]
+    pub seed: Option<u64>,
+
+    /// Which 2x2 game the prisoners play, overridden by `--game`
+    #[serde(default)]
+    pub game: Game,
+
+    /// The game's default payoffs if omitted; see [`SimConfig::payoffs`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payoffs: Option<PayoffMatrix>,
+
+    /// What the red side of every match is paid, if not `payoffs`, for an
+    /// asymmetric game
+    ///
+    /// A round robin then plays each pairing a second time with the sides
+    /// swapped, so neither player of a pairing is better off for where it
+    /// was seated.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub red_payoffs: Option<PayoffMatrix>,
+
+    /// Times a tournament plays each pairing, for averaging out chance
+    #[serde(default = "default_repetitions")]
+    pub repetitions: usize,
+
+    /// Whether each participant also plays a twin of itself in a tournament,
+    /// as in Axelrod's; only the participant's own side is scored
+    #[serde(default)]
+    pub self_play: bool,
+
+    /// Keep a public reputation for every player, which strategies can see
+    /// in their opponents; see [`crate::reputation`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reputation: Option<ReputationRule>,
+
+    /// In round robins, play the repetitions one after another and hand
+    /// each strategy what it remembered of an opponent at the end of their
+    /// last match; see [`crate::memory`]
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub remember_opponents: bool,
+
+    /// Before every round, let each player send the other a non-binding
+    /// signal of what it means to do; see [`Strategy::signal`]
+    ///
+    /// [`Strategy::signal`]: crate::strategy::Strategy::signal
+    #[serde(default)]
+    pub cheap_talk: bool,
+
+    /// Have the players move in turn rather than at once, blue first in
+    /// even rounds and red in odd ones, the second seeing the first's move
+    #[serde(default)]
+    pub alternating: bool,
+
+    /// Log every random draw of every strategy's decisions into the match
+    /// results, so a recording can be checked against its seeds; see
+    /// [`crate::randomness`]
+    #[serde(default)]
+    pub audit_randomness: bool,
+
+    /// What the strategies are kept from knowing; see [`Information`]
+    #[serde(default)]
+    pub information: Information,
+
+    /// In tournaments, admit only participants whose moves are the same
+    /// whenever they are replayed; see [`crate::determinism`]
+    #[serde(default)]
+    pub deterministic_only: bool,
+
+    /// In evolutionary runs, let individuals refuse to play species that
+    /// treated theirs badly; see [`PartnerChoice`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub partner_choice: Option<PartnerChoice>,
+
+    /// In evolutionary and spatial runs, how related individuals of the
+    /// same species are: each also counts this share of what such a
+    /// partner scored towards its own fitness, as in Hamilton's rule
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub relatedness: Option<f64>,
+
+    /// In evolutionary and spatial runs, give every individual its own
+    /// temptation, drawn from this range; see [`TemptationRange`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub perceived_temptation: Option<TemptationRange>,
+
+    /// Rank tournament standings by a weighting of score, cooperation, and
+    /// fairness, rather than by score alone; see [`crate::objectives`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ranking: Option<Ranking>,
+
+    /// Every participant plays every other participant once per repetition
+    pub participants: Vec<ParticipantConfig>,
+}
+
+fn default_iterations() -> usize {
+    100
+}
+
+fn default_retries() -> usize {
+    2
+}
+
+fn default_timeout_action() -> Action {
+    Action::DEFECT
+}
+
+fn default_repetitions() -> usize {
+    1
+}
+
+/// Schemes of strategies loaded from outside the engine, whose memory only
+/// counts against a budget if they can tell what they hold
+const UNMEASURED: [&str; 3] = ["script:", "plugin:", "remote:"];
+
+/// Whether `strategy`, or a strategy it wraps, is loaded from outside
+/// the engine
+fn unmeasured(strategy: &str) -> bool {
+    UNMEASURED.iter().any(|scheme| strategy.contains(scheme))
+}
+
+/// The name a participant's twin plays under when `self_play` is set
+pub fn twin_name(name: &str) -> String {
+    format!("{}-twin", name)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ParticipantConfig {
+    pub name: String,
+
+    /// A name from the strategy registry
+    pub strategy: String,
+
+    #[serde(default)]
+    pub parameters: Parameters,
+
+    /// A marker the participant shows its opponents, for tag-based
+    /// strategies such as `tag-matcher`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag: Option<Tag>,
+
+    /// The population the participant belongs to in a co-evolutionary run,
+    /// which only plays across populations; see [`coevolve`]
+    ///
+    /// [`coevolve`]: crate::evolution::coevolve
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub population: Option<String>,
+
+    /// Another strategy the participant switches to partway through each
+    /// match
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub swap: Option<SwapConfig>,
+
+    /// The payoffs as the participant sees them, if not the game's: what
+    /// its strategy is told the game pays and, in evolutionary and spatial
+    /// runs, what its fitness is counted in
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payoffs: Option<PayoffMatrix>,
+}
+
+/// What the strategies are kept from knowing about the game they play
+///
+/// ```toml
+/// [information]
+/// blind = true
+/// delay = 2
+/// visibility = 0.8
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Information {
+    /// Hide the payoffs, leaving the strategies to infer them from the
+    /// actions alone: they are told every payoff is 0, in the game and in
+    /// each round's outcome
+    #[serde(default)]
+    pub blind: bool,
+
+    /// Rounds the referee holds back each round's outcome before telling
+    /// the strategies of it
+    #[serde(default)]
+    pub delay: usize,
+
+    /// The chance a strategy is shown its opponent's action each round;
+    /// otherwise it is told the action is unknown
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub visibility: Option<f64>,
+}
+
+/// The range each individual's temptation is drawn from, uniformly, in
+/// evolutionary and spatial runs
+///
+/// ```toml
+/// perceived_temptation = { min = 3.2, max = 4.8 }
+/// ```
+///
+/// Every other payoff is the game's. A node keeps its draw for the whole
+/// run, and an individual of an evolving population has its own for its
+/// generation.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TemptationRange {
+    pub min: Score,
+    pub max: Score,
+}
+
+/// A participant's change of strategy at a given round of every match
+///
+/// ```toml
+/// [[participants]]
+/// name = "convert"
+/// strategy = "always-defect"
+/// swap = { round = 50, strategy = "always-cooperate" }
+/// ```
+///
+/// With `reset = true` the new strategy starts from an empty history, as if
+/// the match began at the swap; otherwise it sees the rounds before.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SwapConfig {
+    /// The first round the new strategy plays, counting from 0
+    pub round: usize,
+
+    /// A name from the strategy registry
+    pub strategy: String,
+
+    #[serde(default)]
+    pub parameters: Parameters,
+
+    #[serde(default)]
+    pub reset: bool,
+}
+
+impl ParticipantConfig {
+    pub fn new(name: &str, strategy: &str) -> ParticipantConfig {
+        ParticipantConfig {
+            name: name.to_owned(),
+            strategy: strategy.to_owned(),
+            parameters: Parameters::new(),
+            tag: None,
+            population: None,
+            swap: None,
+            payoffs: None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(io::Error),
+    Parse(toml::de::Error),
+    Invalid(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Io(e) => write!(f, "unable to read config: {}", e),
+            ConfigError::Parse(e) => write!(f, "unable to parse config: {}", e),
+            ConfigError::Invalid(s) => write!(f, "invalid config: {}", s),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl Default for SimConfig {
+    /// Two random prisoners, as in the original simulation
+    fn default() -> SimConfig {
+        SimConfig {
+            iterations: default_iterations(),
+            termination: TerminationConfig::default(),
+            faults: FaultPolicy::default(),
+            budget: None,
+            retries: default_retries(),
+            decision_timeout_ms: None,
+            delay_ms: None,
+            timeout_action: default_timeout_action(),
+            noise: 0.0,
+            misperception: 0.0,
+            discount: None,
+            seed: None,
+            game: Game::default(),
+            payoffs: None,
+            red_payoffs: None,
+            repetitions: default_repetitions(),
+            self_play: false,
+            reputation: None,
+            remember_opponents: false,
+            cheap_talk: false,
+            alternating: false,
+            audit_randomness: false,
+            deterministic_only: false,
+            information: Information::default(),
+            partner_choice: None,
+            relatedness: None,
+            perceived_temptation: None,
+            ranking: None,
+            participants: vec![
+                ParticipantConfig::new("blue", "random"),
+                ParticipantConfig::new("red", "random"),
+            ],
+        }
+    }
+}
+
+impl SimConfig {
+    /// Axelrod's first tournament, as far as the strategy library allows
+    ///
+    /// Payoffs R 3, T 5, P 1, S 0, 200 round matches, each entry also
+    /// playing its twin, and the whole tournament played five times. Of the
+    /// fourteen entries plus Random, the nine that are simple enough to
+    /// reconstruct are included; Friedman's is Grim Trigger.
+    pub fn axelrod() -> SimConfig {
+        let roster = [
+            "tit-for-tat",
+            "shubik",
+            "grofman",
+            "davis",
+            "grim",
+            "feld",
+            "joss",
+            "tullock",
+            "random",
+        ];
+        SimConfig {
+            iterations: 200,
+            payoffs: Some(PayoffMatrix::new(3.0, 5.0, 1.0, 0.0)),
+            repetitions: 5,
+            self_play: true,
+            participants: roster
+                .iter()
+                .map(|strategy| ParticipantConfig::new(strategy, strategy))
+                .collect(),
+            ..SimConfig::default()
+        }
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<SimConfig, ConfigError> {
+        let text = fs::read_to_string(path).map_err(ConfigError::Io)?;
+        SimConfig::parse(&text)
+    }
+
+    pub fn parse(text: &str) -> Result<SimConfig, ConfigError> {
+        toml::from_str(text).map_err(ConfigError::Parse)
+    }
+
+    /// The payoffs given in the config, or else the game's defaults
+    pub fn payoffs(&self) -> PayoffMatrix {
+        self.payoffs.unwrap_or_else(|| self.game.default_payoffs())
+    }
+
+    /// What the red side of a match is paid: `red_payoffs`, or else what
+    /// blue is
+    pub fn red_payoffs(&self) -> PayoffMatrix {
+        self.red_payoffs.unwrap_or_else(|| self.payoffs())
+    }
+
+    /// The payoffs `participant` sees: its own, or else the game's
+    pub fn payoffs_for(&self, participant: &ParticipantConfig) -> PayoffMatrix {
+        participant.payoffs.unwrap_or_else(|| self.payoffs())
+    }
+
+    /// The payoffs `participant`'s strategy is told the game pays: those it
+    /// sees, or nothing at all in a blind game
+    pub fn payoffs_told(&self, participant: &ParticipantConfig) -> PayoffMatrix {
+        if self.information.blind {
+            PayoffMatrix::new(0.0, 0.0, 0.0, 0.0)
+        } else {
+            self.payoffs_for(participant)
+        }
+    }
+
+    /// The payoffs `participant`'s strategy is told the game pays when it
+    /// is seated red
+    pub fn red_payoffs_told(&self, participant: &ParticipantConfig) -> PayoffMatrix {
+        match self.red_payoffs {
+            Some(red) if !self.information.blind && participant.payoffs.is_none() => red,
+            _ => self.payoffs_told(participant),
+        }
+    }
+
+    /// A temptation for a new individual, if `perceived_temptation` is set;
+    /// nothing is drawn from `rng` otherwise
+    pub fn draw_temptation(&self, rng: &mut StdRng) -> Option<Score> {
+        self.perceived_temptation.map(|range| {
+            if range.min < range.max {
+                rng.gen_range(range.min, range.max)
+            } else {
+                range.min
+            }
+        })
+    }
+
+    /// The payoffs an individual of `participant`'s species sees, with
+    /// `temptation` if it drew one, or none if they are simply the game's
+    pub fn perceive(
+        &self,
+        participant: &ParticipantConfig,
+        temptation: Option<Score>,
+    ) -> Option<PayoffMatrix> {
+        match temptation {
+            Some(temptation) => Some(self.payoffs_for(participant).with_temptation(temptation)),
+            None => participant.payoffs,
+        }
+    }
+
+    /// How long each prisoner has to decide, if there's a limit
+    pub fn decision_timeout(&self) -> Option<Duration> {
+        self.decision_timeout_ms.map(Duration::from_millis)
+    }
+
+    /// The wait between rounds, if any
+    pub fn delay(&self) -> Option<Duration> {
+        self.delay_ms
+            .filter(|&ms| ms > 0)
+            .map(Duration::from_millis)
+    }
+
+    /// Check the config makes sense against the strategies in `registry`
+    pub fn validate(&self, registry: &StrategyRegistry) -> Result<(), ConfigError> {
+        let payoffs = self.payoffs();
+        self.game
+            .check(&payoffs)
+            .map_err(|e| ConfigError::Invalid(e.to_string()))?;
+        if let Some(red) = self.red_payoffs {
+            self.game
+                .check(&red)
+                .map_err(|e| ConfigError::Invalid(format!("red payoffs: {}", e)))?;
+        }
+        // a table's game has no classes of payoff to perceive or pay apart
+        let actions = payoffs.action_table().map(ActionTable::actions);
+        let apart = self.red_payoffs.is_some()
+            || self.perceived_temptation.is_some()
+            || self.participants.iter().any(|p| p.payoffs.is_some());
+        let tabled = self
+            .red_payoffs
+            .iter()
+            .chain(self.participants.iter().filter_map(|p| p.payoffs.as_ref()))
+            .any(|payoffs| payoffs.action_table().is_some());
+        if (actions.is_some() && apart) || tabled {
+            return Err(ConfigError::Invalid(
+                "only the game's own payoffs can be given by a table, and then no others"
+                    .to_owned(),
+            ));
+        }
+        if self.participants.len() < 2 {
+            return Err(ConfigError::Invalid(
+                "at least two participants are required".to_owned(),
+            ));
+        }
+        if self.iterations == 0 {
+            return Err(ConfigError::Invalid(
+                "iterations must be at least 1".to_owned(),
+            ));
+        }
+        if let TerminationConfig::Continuation { probability } = self.termination {
+            if !(0.0..1.0).contains(&probability) {
+                return Err(ConfigError::Invalid(format!(
+                    "continuation probability {} must be at least 0 and below 1",
+                    probability
+                )));
+            }
+        }
+        if let TerminationConfig::Converged { rounds: 0 } = self.termination {
+            return Err(ConfigError::Invalid(
+                "convergence needs at least 1 round".to_owned(),
+            ));
+        }
+        if self.repetitions == 0 {
+            return Err(ConfigError::Invalid(
+                "repetitions must be at least 1".to_owned(),
+            ));
+        }
+        if let Some(ReputationRule::ImageScoring { limit }) = self.reputation {
+            if limit < 1 {
+                return Err(ConfigError::Invalid(format!(
+                    "image score limit {} must be at least 1",
+                    limit
+                )));
+            }
+        }
+        if let Some(choice) = self.partner_choice {
+            if !(0.0..=1.0).contains(&choice.threshold) {
+                return Err(ConfigError::Invalid(format!(
+                    "partner choice threshold {} is not between 0 and 1",
+                    choice.threshold
+                )));
+            }
+        }
+        if let Some(ranking) = self.ranking {
+            ranking.check().map_err(ConfigError::Invalid)?;
+        }
+        if let Some(r) = self.relatedness {
+            if !(0.0..=1.0).contains(&r) {
+                return Err(ConfigError::Invalid(format!(
+                    "relatedness {} is not between 0 and 1",
+                    r
+                )));
+            }
+        }
+        if let Some(range) = self.perceived_temptation {
+            if range
+                .min
+                .partial_cmp(&range.max)
+                .is_none_or(|order| order.is_gt())
+            {
+                return Err(ConfigError::Invalid(format!(
+                    "perceived temptation range {} to {} is empty",
+                    range.min, range.max
+                )));
+            }
+            // the game's conditions are linear, so the ends answer for the range
+            for temptation in [range.min, range.max] {
+                self.game
+                    .check(&payoffs.with_temptation(temptation))
+                    .map_err(|e| {
+                        ConfigError::Invalid(format!("perceived temptation {}: {}", temptation, e))
+                    })?;
+            }
+        }
+        let perceived = self.perceived_temptation.is_some()
+            || self.participants.iter().any(|p| p.payoffs.is_some());
+        if perceived && self.discount.is_some() {
+            return Err(ConfigError::Invalid(
+                "payoffs perceived apart from the game's can't be discounted".to_owned(),
+            ));
+        }
+        if let Some(budget) = self.budget {
+            if budget.decisions.is_none() && budget.cpu_ms.is_none() && budget.memory_kb.is_none() {
+                return Err(ConfigError::Invalid(
+                    "a budget needs decisions, cpu_ms, or memory_kb".to_owned(),
+                ));
+            }
+            if budget.decisions == Some(0)
+                || budget.cpu_ms == Some(0)
+                || budget.memory_kb == Some(0)
+            {
+                return Err(ConfigError::Invalid(
+                    "a budget must allow at least 1 decision, 1 ms, and 1 KB".to_owned(),
+                ));
+            }
+        }
+        if self.decision_timeout_ms == Some(0) {
+            return Err(ConfigError::Invalid(
+                "decision timeout must be at least 1 ms".to_owned(),
+            ));
+        }
+        let visibility = self.information.visibility.unwrap_or(1.0);
+        for (name, p) in &[
+            ("noise", self.noise),
+            ("misperception", self.misperception),
+            ("visibility", visibility),
+        ] {
+            if !(0.0..=1.0).contains(p) {
+                return Err(ConfigError::Invalid(format!(
+                    "{} {} is not a probability",
+                    name, p
+                )));
+            }
+        }
+        if let Some(discount) = self.discount {
+            if !(discount > 0.0 && discount <= 1.0) {
+                return Err(ConfigError::Invalid(format!(
+                    "discount {} must be above 0 and at most 1",
+                    discount
+                )));
+            }
+        }
+        let mut names = HashSet::new();
+        for participant in &self.participants {
+            if !names.insert(participant.name.clone()) {
+                return Err(ConfigError::Invalid(format!(
+                    "duplicate participant name '{}'",
+                    participant.name
+                )));
+            }
+            if self.self_play && !names.insert(twin_name(&participant.name)) {
+                return Err(ConfigError::Invalid(format!(
+                    "participant name '{}' is taken by a twin",
+                    twin_name(&participant.name)
+                )));
+            }
+            if let Some(tag) = participant.tag {
+                if !(0.0..=1.0).contains(&tag) {
+                    return Err(ConfigError::Invalid(format!(
+                        "participant '{}': tag {} is not between 0 and 1",
+                        participant.name, tag
+                    )));
+                }
+            }
+            if let Some(own) = &participant.payoffs {
+                self.game.check(own).map_err(|e| {
+                    ConfigError::Invalid(format!("participant '{}': {}", participant.name, e))
+                })?;
+            }
+            let args = StrategyArgs {
+                parameters: &participant.parameters,
+                payoffs: &self.payoffs_told(participant),
+                seed: 0,
+            };
+            // the strategy can be built, can play the game's actions, and can
+            // be held to the budget's memory
+            let playable = |strategy: &str, args: &StrategyArgs| {
+                let built = registry.create(strategy, args).map_err(|e| e.to_string())?;
+                match actions {
+                    Some(actions) if !built.supports(actions) => {
+                        return Err(format!(
+                            "{} can't play a game of {} actions",
+                            strategy, actions
+                        ))
+                    }
+                    _ => {}
+                }
+                let memory_kb = self.budget.and_then(|budget| budget.memory_kb);
+                if memory_kb.is_some() && built.memory_used().is_none() && unmeasured(strategy) {
+                    return Err(format!(
+                        "{} can't tell how much memory it holds, so a budget's memory_kb \
+                         can't hold it",
+                        strategy
+                    ));
+                }
+                Ok(())
+            };
+            if let Err(e) = playable(&participant.strategy, &args) {
+                return Err(ConfigError::Invalid(format!(
+                    "participant '{}': {}",
+                    participant.name, e
+                )));
+            }
+            if let Some(swap) = &participant.swap {
+                if swap.round == 0 {
+                    return Err(ConfigError::Invalid(format!(
+                        "participant '{}': a swap must come at round 1 or later",
+                        participant.name
+                    )));
+                }
+                let args = StrategyArgs {
+                    parameters: &swap.parameters,
+                    ..args
+                };
+                if let Err(e) = playable(&swap.strategy, &args) {
+                    return Err(ConfigError::Invalid(format!(
+                        "participant '{}': swap: {}",
+                        participant.name, e
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+}
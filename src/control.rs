@@ -0,0 +1,333 @@
+//! Steering a running simulation from the terminal
+//!
+//! With `--control`, a [`Controller`] reads one command per line from
+//! stdin while the run goes on. It answers in the log, which goes to
+//! stderr, so that stdout keeps only the results:
+//!
+//! - `pause` holds every match before its next round, and `resume` lets
+//!   them go on.
+//! - `standings` prints each player's totals from the matches finished so
+//!   far, and the population of the latest generation in an evolutionary
+//!   run.
+//! - `noise 0.05` plays every round from then on with that execution noise,
+//!   whatever the config says; `noise off` goes back to the config's.
+//! - `skip` ends the matches in progress after their current round. An
+//!   evolutionary run scores them as they stand and goes on to its next
+//!   generation.
+//! - `stop` stops the run, as Ctrl-C does.
+//!
+//! A [`Control`] holds the settings. The controller keeps one and hands
+//! clones of it to the matches started from its thread, see [`steer`], and
+//! each referee reads the settings from its own clone.
+
+use actix::prelude::*;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::io::{self, BufRead};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use tracing::{info, warn};
+
+use crate::events::GameEvent;
+use crate::evolution::GenerationRecord;
+use crate::game::Score;
+use crate::interrupt;
+
+/// The noise override's bits, or `NO_NOISE` for none; f64 has no atomic
+const NO_NOISE: u64 = u64::MAX;
+
+/// How often a paused match looks to see whether it may go on
+pub const POLL: Duration = Duration::from_millis(100);
+
+struct Settings {
+    paused: AtomicBool,
+    skips: AtomicUsize,
+    noise: AtomicU64,
+}
+
+/// Settings that steer running matches, shared by a [`Controller`] and the
+/// referees it was handed to
+///
+/// Clones share the same settings. A referee that isn't given one plays
+/// with a `Control` of its own that nobody changes.
+#[derive(Clone)]
+pub struct Control(Arc<Settings>);
+
+impl Default for Control {
+    fn default() -> Control {
+        Control(Arc::new(Settings {
+            paused: AtomicBool::new(false),
+            skips: AtomicUsize::new(0),
+            noise: AtomicU64::new(NO_NOISE),
+        }))
+    }
+}
+
+impl Control {
+    /// Whether matches should hold before their next round
+    pub fn paused(&self) -> bool {
+        self.0.paused.load(Ordering::Relaxed)
+    }
+
+    pub fn set_paused(&self, paused: bool) {
+        self.0.paused.store(paused, Ordering::Relaxed);
+    }
+
+    /// The execution noise to play with instead of the config's, if one was
+    /// set
+    pub fn noise(&self) -> Option<f64> {
+        match self.0.noise.load(Ordering::Relaxed) {
+            NO_NOISE => None,
+            bits => Some(f64::from_bits(bits)),
+        }
+    }
+
+    pub fn set_noise(&self, noise: Option<f64>) {
+        let bits = noise.map_or(NO_NOISE, f64::to_bits);
+        self.0.noise.store(bits, Ordering::Relaxed);
+    }
+
+    /// How many times `skip` has been asked for; a match started when this
+    /// was lower should end
+    pub fn skips(&self) -> usize {
+        self.0.skips.load(Ordering::Relaxed)
+    }
+
+    /// End the matches in progress after their current round
+    pub fn skip(&self) {
+        self.0.skips.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+thread_local! {
+    static STEERING: RefCell<Option<Control>> = const { RefCell::new(None) };
+}
+
+/// Steer every match started from this thread with `control`, or stop
+/// steering them with `None`, as [`crate::events::publish_to`] does for
+/// events
+pub fn steer(control: Option<Control>) {
+    STEERING.with(|steering| *steering.borrow_mut() = control);
+}
+
+/// What steers the matches started from this thread, if anything
+pub(crate) fn steering() -> Option<Control> {
+    STEERING.with(|steering| steering.borrow().clone())
+}
+
+/// One player's totals over the matches finished so far
+#[derive(Debug, Clone, Copy, Default)]
+struct Tally {
+    matches: usize,
+    rounds: usize,
+    score: Score,
+}
+
+/// A line read from stdin
+struct Line(String);
+
+impl Message for Line {
+    type Result = ();
+}
+
+/// Carries out commands on its [`Control`], keeping the running totals
+/// `standings` prints from the events it is subscribed to
+#[derive(Default)]
+pub struct Controller {
+    control: Control,
+    tallies: BTreeMap<String, Tally>,
+    generation: Option<GenerationRecord>,
+}
+
+impl Controller {
+    /// A controller changing the settings of `control`
+    pub fn new(control: Control) -> Controller {
+        Controller {
+            control,
+            ..Controller::default()
+        }
+    }
+
+    /// Start a controller changing the settings of `control` by the
+    /// commands it reads from stdin, until it closes
+    pub fn on_stdin(control: Control) -> Addr<Controller> {
+        let controller = Controller::new(control).start();
+        let lines = controller.clone();
+        thread::spawn(move || {
+            for line in io::stdin().lock().lines() {
+                match line {
+                    Ok(line) => lines.do_send(Line(line)),
+                    Err(_) => break,
+                }
+            }
+        });
+        controller
+    }
+
+    fn standings(&self) {
+        if self.tallies.is_empty() && self.generation.is_none() {
+            info!("no matches have finished yet");
+        }
+        let mut tallies: Vec<(&String, &Tally)> = self.tallies.iter().collect();
+        tallies.sort_by(|a, b| b.1.score.total_cmp(&a.1.score).then_with(|| a.0.cmp(b.0)));
+        for (name, tally) in tallies {
+            info!(
+                player = %name,
+                points = tally.score,
+                matches = tally.matches,
+                per_round = tally.score / tally.rounds.max(1) as f64,
+                "standing"
+            );
+        }
+        if let Some(generation) = &self.generation {
+            let composition: Vec<String> = generation
+                .composition
+                .iter()
+                .map(|(name, count)| format!("{} {}", name, count))
+                .collect();
+            info!(
+                generation = generation.generation,
+                composition = %composition.join(", "),
+                "population"
+            );
+        }
+    }
+
+    fn command(&mut self, line: &str) {
+        let control = &self.control;
+        let mut words = line.split_whitespace();
+        match (words.next(), words.next(), words.next()) {
+            (None, ..) => {}
+            (Some("pause"), None, _) => {
+                control.set_paused(true);
+                info!("paused; 'resume' to go on");
+            }
+            (Some("resume"), None, _) => {
+                control.set_paused(false);
+                info!("resumed");
+            }
+            (Some("standings"), None, _) => self.standings(),
+            (Some("noise"), None, _) => match control.noise() {
+                Some(noise) => info!(noise, "noise overridden"),
+                None => info!("noise as configured"),
+            },
+            (Some("noise"), Some("off"), None) => {
+                control.set_noise(None);
+                info!("noise as configured");
+            }
+            (Some("noise"), Some(p), None) => match p.parse::<f64>() {
+                Ok(p) if (0.0..=1.0).contains(&p) => {
+                    control.set_noise(Some(p));
+                    info!(noise = p, "noise overridden");
+                }
+                _ => warn!(noise = %p, "not a probability"),
+            },
+            (Some("skip"), None, _) => {
+                control.skip();
+                info!("ending the matches in progress");
+            }
+            (Some("stop"), None, _) => {
+                // a paused match would never get to stop
+                control.set_paused(false);
+                interrupt::interrupt();
+                info!("stopping after the current round");
+            }
+            _ => warn!(
+                command = %line,
+                "unknown; commands are pause, resume, standings, noise [P|off], skip, stop"
+            ),
+        }
+    }
+}
+
+impl Actor for Controller {
+    type Context = Context<Self>;
+}
+
+impl Handler<Line> for Controller {
+    type Result = ();
+
+    fn handle(&mut self, msg: Line, _ctx: &mut Context<Self>) {
+        self.command(&msg.0);
+    }
+}
+
+impl Handler<GameEvent> for Controller {
+    type Result = ();
+
+    fn handle(&mut self, event: GameEvent, _ctx: &mut Context<Self>) {
+        match event {
+            GameEvent::MatchFinished(result) => {
+                for player in [&result.blue, &result.red] {
+                    let tally = self.tallies.entry(player.name.clone()).or_default();
+                    tally.matches += 1;
+                    tally.rounds += result.rounds;
+                    tally.score += player.score;
+                }
+            }
+            GameEvent::GenerationComplete(record) => {
+                // each generation's matches are new individuals
+                self.tallies.clear();
+                self.generation = Some(record);
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{Action, PayoffMatrix};
+    use crate::prisoner::Prisoner;
+    use crate::referee::{Play, Referee};
+    use crate::strategies::AlwaysCooperate;
+
+    #[test]
+    fn clones_share_their_settings() {
+        let control = Control::default();
+        let handed_out = control.clone();
+        control.set_paused(true);
+        control.set_noise(Some(0.25));
+        control.skip();
+        assert!(handed_out.paused());
+        assert_eq!(handed_out.noise(), Some(0.25));
+        assert_eq!(handed_out.skips(), 1);
+        control.set_noise(None);
+        assert_eq!(handed_out.noise(), None);
+        assert!(!Control::default().paused());
+    }
+
+    #[actix_rt::test]
+    async fn a_referee_plays_with_the_noise_its_control_sets() {
+        let control = Control::default();
+        control.set_noise(Some(1.0));
+        let prisoner = |name| Prisoner::new(name, Box::new(AlwaysCooperate {})).start();
+        let referee = |control: Control| {
+            Referee::new(
+                ("blue", prisoner("blue")),
+                ("red", prisoner("red")),
+                PayoffMatrix::default(),
+                10,
+            )
+            .with_control(control)
+            .start()
+        };
+        let steered = referee(control.clone()).send(Play).await.unwrap().unwrap();
+        assert_eq!(steered.blue.flips, 10);
+        assert!(steered
+            .transcript
+            .iter()
+            .all(|round| round.blue.executed == Action::DEFECT));
+
+        // another control's settings don't reach it
+        let unsteered = referee(Control::default())
+            .send(Play)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(unsteered.blue.flips, 0);
+    }
+}
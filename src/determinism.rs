@@ -0,0 +1,168 @@
+//! Checking that entrants are deterministic
+//!
+//! Some tournaments only admit strategies whose play can be reproduced.
+//! With `deterministic_only` in the config, [`nondeterministic`] plays each
+//! participant twice against every opponent of the scripted
+//! [`PANEL`](crate::profile::PANEL), with the same seeds both times, and
+//! turns away those whose moves weren't the same. Built-in strategies pass,
+//! since whatever they roll comes from their seed; what the check catches
+//! is a script, plugin, or remote strategy that draws on something else,
+//! such as the clock or a generator of its own.
+
+use actix::prelude::*;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::config::{twin_name, ParticipantConfig, SimConfig};
+use crate::game::Action;
+use crate::human;
+use crate::prisoner::Prisoner;
+use crate::profile::{self, Opponent, PANEL};
+use crate::referee::{Play, Referee};
+use crate::registry::StrategyRegistry;
+use crate::tournament;
+
+/// A participant turned away, and why
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rejection {
+    pub name: String,
+    pub reason: String,
+}
+
+/// The moves `participant` chose against `opponent`, in a match seeded by
+/// `seed`
+async fn moves(
+    config: &SimConfig,
+    registry: &StrategyRegistry,
+    participant: &ParticipantConfig,
+    opponent: &Opponent,
+    seed: u64,
+) -> Result<Vec<Action>, String> {
+    let mut seeds = StdRng::seed_from_u64(seed);
+    let blue = tournament::prisoner(config, registry, participant, seeds.gen()).start();
+    let red = Prisoner::new(opponent.name, profile::scripted(opponent)).start();
+    let red_name = if participant.name == opponent.name {
+        twin_name(opponent.name)
+    } else {
+        opponent.name.to_owned()
+    };
+    let result = Referee::new(
+        (&participant.name, blue),
+        (&red_name, red),
+        config.payoffs(),
+        config.iterations,
+    )
+    .with_seed(seeds.gen())
+    .start()
+    .send(Play)
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())?;
+    Ok(result
+        .transcript
+        .iter()
+        .map(|round| round.blue.chosen)
+        .collect())
+}
+
+/// Why `participant` isn't deterministic, if it isn't
+async fn check(
+    config: &SimConfig,
+    registry: &StrategyRegistry,
+    participant: &ParticipantConfig,
+    seed: u64,
+) -> Option<String> {
+    if participant.strategy == human::NAME {
+        return Some("a person at the terminal can't be replayed".to_owned());
+    }
+    for opponent in &PANEL {
+        let first = moves(config, registry, participant, opponent, seed).await;
+        let second = moves(config, registry, participant, opponent, seed).await;
+        match (first, second) {
+            (Ok(first), Ok(second)) => {
+                if let Some(round) = first.iter().zip(&second).position(|(a, b)| a != b) {
+                    return Some(format!(
+                        "it moved differently in round {} of two matches against the {} \
+                         seeded alike",
+                        round + 1,
+                        opponent.name
+                    ));
+                }
+            }
+            (Err(e), _) | (_, Err(e)) => {
+                return Some(format!(
+                    "its match against the {} failed: {}",
+                    opponent.name, e
+                ));
+            }
+        }
+    }
+    None
+}
+
+/// Every participant of `config` that didn't repeat its moves when
+/// replayed with seeds drawn from `seed`, in config order
+pub async fn nondeterministic(
+    config: &SimConfig,
+    registry: &StrategyRegistry,
+    seed: u64,
+) -> Vec<Rejection> {
+    let mut seeds = StdRng::seed_from_u64(seed);
+    let mut rejected = Vec::new();
+    for participant in &config.participants {
+        if let Some(reason) = check(config, registry, participant, seeds.gen()).await {
+            rejected.push(Rejection {
+                name: participant.name.clone(),
+                reason,
+            });
+        }
+    }
+    rejected
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry::StrategyArgs;
+    use crate::strategy::{History, Strategy};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static CALLS: AtomicUsize = AtomicUsize::new(0);
+
+    /// Defects on every seventh call it has had from anyone, seed or no seed
+    struct Moody;
+
+    impl Strategy for Moody {
+        fn name(&self) -> &str {
+            "moody"
+        }
+
+        fn choose(&mut self, _history: &History) -> Action {
+            if CALLS.fetch_add(1, Ordering::Relaxed) % 7 == 6 {
+                Action::DEFECT
+            } else {
+                Action::COOPERATE
+            }
+        }
+    }
+
+    #[actix_rt::test]
+    async fn entrants_that_move_differently_when_replayed_are_rejected() {
+        let mut registry = StrategyRegistry::new();
+        registry.register("moody", |_: &StrategyArgs| Ok(Box::new(Moody)));
+        let config = SimConfig {
+            iterations: 10,
+            participants: vec![
+                ParticipantConfig::new("gtft", "generous-tit-for-tat"),
+                ParticipantConfig::new("random", "random"),
+                ParticipantConfig::new("moody", "moody"),
+                ParticipantConfig::new("you", human::NAME),
+            ],
+            ..SimConfig::default()
+        };
+        let rejected = nondeterministic(&config, &registry, 5).await;
+        let names: Vec<&str> = rejected.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, vec!["moody", "you"]);
+        assert!(rejected[0].reason.contains("against the cooperator"));
+    }
+}
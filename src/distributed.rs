@@ -0,0 +1,625 @@
+//! Round robins spread over worker processes, over TCP
+//!
+//! [`round_robin`] plays the same tournament as
+//! [`tournament::round_robin`], but instead of playing the matches itself
+//! it listens for workers and a [`Coordinator`] hands the matches out
+//! among them: `actoripd worker --coordinator host:port` on as many
+//! machines as there are. Workers may join at any time, and the matches of
+//! one that drops out are handed to another. Each match is played from the
+//! seeds the local tournament would have given it, so the results don't
+//! depend on how the matches were shared out. A config that keeps
+//! reputations can't be distributed, since matches played apart couldn't
+//! share them.
+//!
+//! The protocol is newline-delimited JSON, each message tagged by `type`:
+//!
+//! 1. The worker sends `{"type":"hello","protocol":1,"jobs":4}`, `jobs`
+//!    being how many matches it plays at once.
+//! 2. The coordinator answers `{"type":"welcome","config":{...}}` with the
+//!    tournament's [`SimConfig`].
+//! 3. The coordinator sends up to `jobs` matches at a time as
+//!    `{"type":"assign","index":0,"blue":{...},"red":{...},"seeds":{...}}`,
+//!    with `"trace"` added when the coordinator exports its spans, and the
+//!    worker answers each, in any order, with
+//!    `{"type":"finished","index":0,"result":{"Ok":{...}}}` holding the
+//!    [`MatchResult`], or `{"Err":"..."}` if the match was aborted.
+//! 4. Once every match is done, the coordinator sends `{"type":"done"}`
+//!    and closes the connection.
+//!
+//! A worker has to know every strategy in the config, so it should run the
+//! same build as the coordinator. Nothing is authenticated, so the
+//! coordinator should only listen where its workers can reach it.
+
+use actix::prelude::*;
+use futures::channel::oneshot;
+use futures::future::{self, Either};
+use futures::lock::Mutex;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
+use std::io;
+use std::sync::Arc;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tracing::{info, info_span, warn, Instrument};
+
+use crate::config::{ConfigError, ParticipantConfig, SimConfig};
+use crate::events::{self, GameEvent};
+use crate::interrupt;
+use crate::referee::MatchResult;
+use crate::registry::StrategyRegistry;
+use crate::tournament::{self, MatchReport, MatchSeeds};
+
+/// The version sent in a worker's hello
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// One match for a worker to play
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Assignment {
+    /// The match's place in the tournament's schedule
+    pub index: usize,
+    pub blue: ParticipantConfig,
+    pub red: ParticipantConfig,
+    pub seeds: MatchSeeds,
+
+    /// The W3C `traceparent` of the coordinator's tournament span, when
+    /// its spans are exported, for the worker's match to join its trace
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trace: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+enum ToCoordinator {
+    Hello {
+        protocol: u32,
+        jobs: usize,
+    },
+    Finished {
+        index: usize,
+        result: Result<Box<MatchResult>, String>,
+    },
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+enum ToWorker {
+    Welcome { config: SimConfig },
+    Assign(Assignment),
+    Done,
+}
+
+#[derive(Debug)]
+pub enum DistributedError {
+    Io(io::Error),
+
+    /// The other side sent something that wasn't in the protocol
+    Protocol(String),
+
+    /// A worker can't play the coordinator's config
+    Config(ConfigError),
+}
+
+impl fmt::Display for DistributedError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DistributedError::Io(e) => write!(f, "connection failed: {}", e),
+            DistributedError::Protocol(e) => write!(f, "protocol error: {}", e),
+            DistributedError::Config(e) => write!(f, "unable to play the tournament: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for DistributedError {}
+
+impl From<io::Error> for DistributedError {
+    fn from(e: io::Error) -> DistributedError {
+        DistributedError::Io(e)
+    }
+}
+
+/// The next message from `reader`, or `None` if it was closed
+async fn receive<T, R>(reader: &mut R) -> Result<Option<T>, DistributedError>
+where
+    T: DeserializeOwned,
+    R: AsyncBufRead + Unpin,
+{
+    let mut line = String::new();
+    if reader.read_line(&mut line).await? == 0 {
+        return Ok(None);
+    }
+    serde_json::from_str(&line)
+        .map(Some)
+        .map_err(|e| DistributedError::Protocol(e.to_string()))
+}
+
+async fn send<T, W>(writer: &mut W, message: &T) -> Result<(), DistributedError>
+where
+    T: Serialize,
+    W: AsyncWrite + Unpin,
+{
+    let line = serde_json::to_string(message).expect("serializable message") + "\n";
+    writer.write_all(line.as_bytes()).await?;
+    Ok(())
+}
+
+/// Ask for a match to hand to a worker
+///
+/// With `wait`, for a worker with nothing in hand, the answer comes once
+/// there is a match to play, or is `None` once there never will be;
+/// otherwise it is `None` straight away if nothing is waiting to be played.
+struct Take {
+    wait: bool,
+}
+
+impl Message for Take {
+    type Result = Option<Assignment>;
+}
+
+/// A worker finished a match
+struct Complete {
+    assignment: Assignment,
+    result: Result<Box<MatchResult>, String>,
+}
+
+impl Message for Complete {
+    type Result = ();
+}
+
+/// A worker dropped out with these matches unfinished
+struct Requeue(Vec<Assignment>);
+
+impl Message for Requeue {
+    type Result = ();
+}
+
+/// Hands out a tournament's matches to the workers' connections and
+/// collects the results
+pub struct Coordinator {
+    pending: VecDeque<Assignment>,
+
+    /// Indexed by the matches' places in the schedule
+    reports: Vec<Option<MatchReport>>,
+
+    /// Matches neither finished nor abandoned
+    unfinished: usize,
+
+    /// Connections with nothing in hand, waiting for a match
+    idle: Vec<oneshot::Sender<Option<Assignment>>>,
+    events: Option<Recipient<GameEvent>>,
+    done_tx: Option<oneshot::Sender<Vec<MatchReport>>>,
+}
+
+impl Coordinator {
+    fn new(
+        assignments: Vec<Assignment>,
+        done_tx: oneshot::Sender<Vec<MatchReport>>,
+    ) -> Coordinator {
+        Coordinator {
+            reports: vec![None; assignments.len()],
+            unfinished: assignments.len(),
+            pending: assignments.into(),
+            idle: Vec::new(),
+            events: events::bus(),
+            done_tx: Some(done_tx),
+        }
+    }
+
+    /// Give up the matches not yet handed out if the run was interrupted,
+    /// and let everyone know once no match is left
+    fn settle(&mut self) {
+        if interrupt::interrupted() {
+            self.unfinished -= self.pending.len();
+            self.pending.clear();
+        }
+        if self.unfinished == 0 {
+            for idle in self.idle.drain(..) {
+                let _ = idle.send(None);
+            }
+            if let Some(done_tx) = self.done_tx.take() {
+                let reports = self.reports.drain(..).flatten().collect();
+                let _ = done_tx.send(reports);
+            }
+        }
+    }
+}
+
+impl Actor for Coordinator {
+    type Context = Context<Self>;
+
+    fn started(&mut self, _ctx: &mut Context<Self>) {
+        self.settle();
+    }
+}
+
+impl Handler<Take> for Coordinator {
+    type Result = ResponseFuture<Option<Assignment>>;
+
+    fn handle(&mut self, msg: Take, _ctx: &mut Context<Self>) -> Self::Result {
+        self.settle();
+        if let Some(assignment) = self.pending.pop_front() {
+            return Box::pin(future::ready(Some(assignment)));
+        }
+        if !msg.wait || self.unfinished == 0 {
+            return Box::pin(future::ready(None));
+        }
+        let (tx, rx) = oneshot::channel();
+        self.idle.push(tx);
+        Box::pin(async move { rx.await.unwrap_or(None) })
+    }
+}
+
+impl Handler<Complete> for Coordinator {
+    type Result = ResponseActFuture<Self, ()>;
+
+    fn handle(&mut self, msg: Complete, _ctx: &mut Context<Self>) -> Self::Result {
+        let Complete { assignment, result } = msg;
+        // the last match's summary should be out before the standings
+        let published = match (&result, &self.events) {
+            (Ok(result), Some(events)) => {
+                Either::Left(events.send(GameEvent::MatchFinished(result.clone())))
+            }
+            _ => Either::Right(future::ready(Ok(()))),
+        };
+        self.reports[assignment.index] = Some(MatchReport {
+            blue: assignment.blue.name,
+            red: assignment.red.name,
+            seeds: assignment.seeds,
+            result: result.map(|result| *result).map_err(|e| {
+                if e == MailboxError::Timeout.to_string() {
+                    MailboxError::Timeout
+                } else {
+                    MailboxError::Closed
+                }
+            }),
+        });
+        Box::pin(published.into_actor(self).map(|_, act, _ctx| {
+            act.unfinished -= 1;
+            act.settle();
+        }))
+    }
+}
+
+impl Handler<Requeue> for Coordinator {
+    type Result = ();
+
+    fn handle(&mut self, msg: Requeue, _ctx: &mut Context<Self>) {
+        for assignment in msg.0 {
+            match self.idle.pop() {
+                Some(idle) => {
+                    if let Err(Some(assignment)) = idle.send(Some(assignment)) {
+                        self.pending.push_back(assignment);
+                    }
+                }
+                None => self.pending.push_back(assignment),
+            }
+        }
+        self.settle();
+    }
+}
+
+/// Keep one worker supplied with matches until there are no more
+async fn supply(
+    connection: &mut BufReader<TcpStream>,
+    coordinator: &Addr<Coordinator>,
+    config: &SimConfig,
+    in_hand: &mut BTreeMap<usize, Assignment>,
+) -> Result<(), DistributedError> {
+    let jobs = match receive(connection).await? {
+        Some(ToCoordinator::Hello { protocol, jobs }) if protocol == PROTOCOL_VERSION => jobs,
+        Some(ToCoordinator::Hello { protocol, .. }) => {
+            return Err(DistributedError::Protocol(format!(
+                "worker speaks protocol {}, not {}",
+                protocol, PROTOCOL_VERSION
+            )))
+        }
+        _ => return Err(DistributedError::Protocol("expected hello".to_owned())),
+    };
+    let welcome = ToWorker::Welcome {
+        config: config.clone(),
+    };
+    send(connection.get_mut(), &welcome).await?;
+
+    loop {
+        while in_hand.len() < jobs.max(1) {
+            let take = Take {
+                wait: in_hand.is_empty(),
+            };
+            match coordinator.send(take).await {
+                Ok(Some(assignment)) => {
+                    send(connection.get_mut(), &ToWorker::Assign(assignment.clone())).await?;
+                    in_hand.insert(assignment.index, assignment);
+                }
+                _ => break,
+            }
+        }
+        if in_hand.is_empty() {
+            return send(connection.get_mut(), &ToWorker::Done).await;
+        }
+        match receive(connection).await? {
+            Some(ToCoordinator::Finished { index, result }) => match in_hand.remove(&index) {
+                Some(assignment) => coordinator.do_send(Complete { assignment, result }),
+                None => {
+                    return Err(DistributedError::Protocol(format!(
+                        "match {} wasn't assigned",
+                        index
+                    )))
+                }
+            },
+            Some(ToCoordinator::Hello { .. }) => {
+                return Err(DistributedError::Protocol("unexpected hello".to_owned()))
+            }
+            None => {
+                return Err(DistributedError::Io(io::ErrorKind::UnexpectedEof.into()));
+            }
+        }
+    }
+}
+
+/// Play a round robin of `config` on the workers that connect to
+/// `address`, with the seeds that [`tournament::round_robin`] would draw
+/// from `seed`
+///
+/// Returns once every match has been played, or, if the run is
+/// interrupted, once those handed out have. The reports come in schedule
+/// order, like a local round robin's, and are published to the event bus
+/// as they arrive.
+pub async fn round_robin(
+    config: &SimConfig,
+    seed: u64,
+    address: &str,
+) -> io::Result<Vec<MatchReport>> {
+    let span = tournament::tournament_span(config, seed);
+    let mut listener = TcpListener::bind(address).await?;
+    let local = listener.local_addr()?;
+    span.in_scope(|| info!(address = %local, "waiting for workers"));
+
+    #[cfg(feature = "otel")]
+    let trace = crate::telemetry::traceparent(&span);
+    #[cfg(not(feature = "otel"))]
+    let trace = None;
+    let mut seeds = StdRng::seed_from_u64(seed);
+    let (entrants, pairs) = tournament::schedule(config);
+    let assignments = pairs
+        .iter()
+        .enumerate()
+        .map(|(index, &(i, j))| Assignment {
+            index,
+            blue: entrants[i].clone(),
+            red: entrants[j].clone(),
+            seeds: MatchSeeds::draw(&mut seeds),
+            trace: trace.clone(),
+        })
+        .collect();
+    let (done_tx, done_rx) = oneshot::channel();
+    let coordinator = Coordinator::new(assignments, done_tx).start();
+
+    let config = config.clone();
+    let accept = async move {
+        loop {
+            let (stream, peer) = match listener.accept().await {
+                Ok(connection) => connection,
+                Err(e) => {
+                    warn!(error = %e, "unable to accept a worker");
+                    continue;
+                }
+            };
+            info!(%peer, "worker connected");
+            let (coordinator, config) = (coordinator.clone(), config.clone());
+            Arbiter::spawn(async move {
+                let mut connection = BufReader::new(stream);
+                let mut in_hand = BTreeMap::new();
+                match supply(&mut connection, &coordinator, &config, &mut in_hand).await {
+                    Ok(()) => info!(%peer, "worker finished"),
+                    Err(e) => {
+                        warn!(%peer, error = %e, unfinished = in_hand.len(), "worker dropped out");
+                        coordinator.do_send(Requeue(in_hand.into_values().collect()));
+                    }
+                }
+            });
+        }
+    };
+    match future::select(Box::pin(accept.instrument(span)), done_rx).await {
+        Either::Right((Ok(reports), _)) => Ok(reports),
+        Either::Right((Err(_), _)) => Err(io::Error::other("the coordinator stopped")),
+        Either::Left(_) => unreachable!("workers are accepted until the end"),
+    }
+}
+
+/// Play the matches that the coordinator at `address` hands out, up to
+/// `jobs` at a time, until it has no more
+///
+/// `registry` must know every strategy in the coordinator's config.
+/// Returns how many matches were played.
+pub async fn work(
+    address: &str,
+    registry: &StrategyRegistry,
+    jobs: usize,
+) -> Result<usize, DistributedError> {
+    let stream = TcpStream::connect(address).await?;
+    let (reader, mut writer) = stream.into_split();
+    let mut reader = BufReader::new(reader);
+    let hello = ToCoordinator::Hello {
+        protocol: PROTOCOL_VERSION,
+        jobs,
+    };
+    send(&mut writer, &hello).await?;
+    let config = match receive(&mut reader).await? {
+        Some(ToWorker::Welcome { config }) => config,
+        _ => return Err(DistributedError::Protocol("expected welcome".to_owned())),
+    };
+    config
+        .validate(registry)
+        .map_err(DistributedError::Config)?;
+    info!(
+        participants = config.participants.len(),
+        "joined the tournament"
+    );
+
+    // finished matches answer as they come, so they share the writer
+    let writer = Arc::new(Mutex::new(writer));
+    let mut played = 0;
+    loop {
+        match receive(&mut reader).await? {
+            Some(ToWorker::Assign(assignment)) => {
+                let span = info_span!(parent: None, "assignment", index = assignment.index);
+                #[cfg(feature = "otel")]
+                if let Some(trace) = &assignment.trace {
+                    crate::telemetry::follow(&span, trace);
+                }
+                let playing = span.in_scope(|| {
+                    tournament::start_match(
+                        &config,
+                        registry,
+                        &assignment.blue,
+                        &assignment.red,
+                        assignment.seeds,
+                        None,
+                        &tournament::Shared::default(),
+                    )
+                });
+                let writer = writer.clone();
+                Arbiter::spawn(
+                    async move {
+                        let report = playing.await;
+                        let finished = ToCoordinator::Finished {
+                            index: assignment.index,
+                            result: report.result.map(Box::new).map_err(|e| e.to_string()),
+                        };
+                        if let Err(e) = send(&mut *writer.lock().await, &finished).await {
+                            warn!(error = %e, "unable to report a match");
+                        }
+                    }
+                    .instrument(span),
+                );
+                played += 1;
+            }
+            Some(ToWorker::Done) | None => return Ok(played),
+            Some(ToWorker::Welcome { .. }) => {
+                return Err(DistributedError::Protocol("unexpected welcome".to_owned()))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Score;
+
+    fn config() -> SimConfig {
+        SimConfig {
+            iterations: 20,
+            noise: 0.05,
+            participants: vec![
+                ParticipantConfig::new("tft", "tit-for-tat"),
+                ParticipantConfig::new("random", "random"),
+                ParticipantConfig::new("pavlov", "pavlov"),
+            ],
+            ..SimConfig::default()
+        }
+    }
+
+    fn scores(reports: &[MatchReport]) -> Vec<(Score, Score)> {
+        reports
+            .iter()
+            .map(|r| {
+                let result = r.result.as_ref().expect("match played");
+                (result.blue.score, result.red.score)
+            })
+            .collect()
+    }
+
+    #[actix_rt::test]
+    async fn messages_are_tagged_lines() {
+        let mut wire = Vec::new();
+        let hello = ToCoordinator::Hello {
+            protocol: PROTOCOL_VERSION,
+            jobs: 4,
+        };
+        send(&mut wire, &hello).await.unwrap();
+        send(&mut wire, &ToWorker::Done).await.unwrap();
+        let text = String::from_utf8(wire.clone()).unwrap();
+        assert_eq!(
+            text,
+            "{\"type\":\"hello\",\"protocol\":1,\"jobs\":4}\n{\"type\":\"done\"}\n"
+        );
+
+        let mut reader = &wire[..];
+        match receive(&mut reader).await.unwrap() {
+            Some(ToCoordinator::Hello { protocol, jobs }) => {
+                assert_eq!((protocol, jobs), (PROTOCOL_VERSION, 4))
+            }
+            _ => panic!("expected hello"),
+        }
+        // a worker's message isn't a coordinator's
+        assert!(matches!(
+            receive::<ToCoordinator, _>(&mut reader).await,
+            Err(DistributedError::Protocol(_))
+        ));
+        assert!(receive::<ToWorker, _>(&mut reader).await.unwrap().is_none());
+
+        let mut wire = Vec::new();
+        let (entrants, _) = tournament::schedule(&config());
+        let assign = ToWorker::Assign(Assignment {
+            index: 2,
+            blue: entrants[1].clone(),
+            red: entrants[2].clone(),
+            seeds: MatchSeeds {
+                blue: 1,
+                red: 2,
+                referee: 3,
+            },
+            trace: None,
+        });
+        send(&mut wire, &assign).await.unwrap();
+        // a worker that doesn't trace is sent what it always was
+        assert!(!String::from_utf8_lossy(&wire).contains("trace"));
+        match receive(&mut &wire[..]).await.unwrap() {
+            Some(ToWorker::Assign(assignment)) => {
+                assert_eq!(assignment.index, 2);
+                assert_eq!(assignment.red.name, "pavlov");
+                assert_eq!(assignment.seeds.referee, 3);
+            }
+            _ => panic!("expected an assignment"),
+        }
+    }
+
+    #[actix_rt::test]
+    async fn workers_play_the_same_round_robin_as_one_process() {
+        let registry = StrategyRegistry::new();
+        let local = tournament::round_robin(&config(), &registry, 11, None).await;
+
+        // a free port, so that the worker knows where to find the coordinator
+        let address = std::net::TcpListener::bind("127.0.0.1:0")
+            .and_then(|listener| listener.local_addr())
+            .unwrap()
+            .to_string();
+        let config = config();
+        let coordinated = round_robin(&config, 11, &address);
+        let worker = async {
+            loop {
+                match work(&address, &registry, 2).await {
+                    Err(DistributedError::Io(e))
+                        if e.kind() == io::ErrorKind::ConnectionRefused =>
+                    {
+                        tokio::time::delay_for(std::time::Duration::from_millis(10)).await
+                    }
+                    played => return played,
+                }
+            }
+        };
+        let (reports, played) = futures::join!(coordinated, worker);
+        assert_eq!(played.unwrap(), 3);
+        let reports = reports.unwrap();
+        let names: Vec<_> = reports.iter().map(|r| (&r.blue[..], &r.red[..])).collect();
+        assert_eq!(
+            names,
+            vec![("tft", "random"), ("tft", "pavlov"), ("random", "pavlov")]
+        );
+        assert_eq!(scores(&reports), scores(&local));
+    }
+}
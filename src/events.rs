@@ -0,0 +1,178 @@
+//! Live events from running matches and evolutions
+//!
+//! Referees and the evolution loop publish [`GameEvent`]s to the
+//! [`EventBus`] set with [`publish_to`], which forwards each one to every
+//! subscriber. Output sinks such as the dashboard or a file writer attach
+//! to the bus instead of being wired into the game loop.
+
+use actix::prelude::*;
+use futures::future;
+use serde::Serialize;
+use std::cell::RefCell;
+use std::fs::File;
+use std::future::Future;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use tracing::warn;
+
+use crate::evolution::GenerationRecord;
+use crate::referee::{MatchResult, RoundRecord};
+
+thread_local! {
+    static BUS: RefCell<Option<Recipient<GameEvent>>> = const { RefCell::new(None) };
+}
+
+/// Something that happened in a running simulation
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "kebab-case")]
+pub enum GameEvent {
+    MatchStarted {
+        blue: String,
+        red: String,
+    },
+    RoundPlayed {
+        blue: String,
+        red: String,
+        record: RoundRecord,
+    },
+    MatchFinished(Box<MatchResult>),
+
+    /// An evolving population played a generation
+    GenerationComplete(GenerationRecord),
+}
+
+impl Message for GameEvent {
+    type Result = ();
+}
+
+/// Attach `subscriber` to the bus; it gets every event published from then on
+pub struct Subscribe(pub Recipient<GameEvent>);
+
+impl Message for Subscribe {
+    type Result = ();
+}
+
+/// Forwards every `GameEvent` it receives to all of its subscribers
+#[derive(Default)]
+pub struct EventBus {
+    subscribers: Vec<Recipient<GameEvent>>,
+}
+
+impl Actor for EventBus {
+    type Context = Context<Self>;
+}
+
+impl Handler<Subscribe> for EventBus {
+    type Result = ();
+
+    fn handle(&mut self, msg: Subscribe, _ctx: &mut Context<Self>) -> Self::Result {
+        self.subscribers.push(msg.0);
+    }
+}
+
+impl Handler<GameEvent> for EventBus {
+    type Result = ();
+
+    fn handle(&mut self, event: GameEvent, _ctx: &mut Context<Self>) -> Self::Result {
+        // subscribers that have stopped are dropped
+        self.subscribers.retain(|subscriber| {
+            !matches!(subscriber.do_send(event.clone()), Err(SendError::Closed(_)))
+        });
+    }
+}
+
+/// Answered once every event sent before it has been dealt with
+///
+/// Send it to the bus and then to a subscriber to wait for the subscriber
+/// to catch up with everything published so far.
+pub struct Drain;
+
+impl Message for Drain {
+    type Result = ();
+}
+
+impl Handler<Drain> for EventBus {
+    type Result = ();
+
+    fn handle(&mut self, _msg: Drain, _ctx: &mut Context<Self>) -> Self::Result {}
+}
+
+/// Writes every event to a file as it arrives, one JSON object per line,
+/// so that a script can tail a long run or read it from a named pipe
+pub struct EventLog {
+    out: BufWriter<File>,
+}
+
+impl EventLog {
+    /// A log writing to `path`, replacing any file there
+    pub fn create(path: &Path) -> io::Result<EventLog> {
+        Ok(EventLog {
+            out: BufWriter::new(File::create(path)?),
+        })
+    }
+
+    fn write(&mut self, event: &GameEvent) -> io::Result<()> {
+        serde_json::to_writer(&mut self.out, event)?;
+        self.out.write_all(b"\n")?;
+        // readers see each event as soon as it happens
+        self.out.flush()
+    }
+}
+
+impl Actor for EventLog {
+    type Context = Context<Self>;
+}
+
+impl Handler<GameEvent> for EventLog {
+    type Result = ();
+
+    fn handle(&mut self, event: GameEvent, ctx: &mut Context<Self>) -> Self::Result {
+        if let Err(e) = self.write(&event) {
+            // the bus drops a stopped subscriber
+            warn!(error = %e, "unable to write event; no more will be written");
+            ctx.stop();
+        }
+    }
+}
+
+impl Handler<Drain> for EventLog {
+    type Result = ();
+
+    fn handle(&mut self, _msg: Drain, _ctx: &mut Context<Self>) -> Self::Result {}
+}
+
+/// Publish the events of every match and generation started from this
+/// thread to `bus`, or stop publishing with `None`
+pub fn publish_to(bus: Option<Recipient<GameEvent>>) {
+    BUS.with(|sink| *sink.borrow_mut() = bus);
+}
+
+/// Run `future`, publishing the events of the matches it starts to `bus`
+/// whatever else this thread publishes to
+///
+/// Matches read the bus as they start, so a future that starts matches
+/// across several polls, such as a round robin whose repetitions share
+/// memories, keeps publishing to `bus` while others on the same thread
+/// publish elsewhere.
+pub async fn publishing_to<F: Future>(bus: Recipient<GameEvent>, future: F) -> F::Output {
+    let mut future = Box::pin(future);
+    future::poll_fn(move |cx| {
+        let outside = BUS.with(|sink| sink.replace(Some(bus.clone())));
+        let polled = future.as_mut().poll(cx);
+        BUS.with(|sink| *sink.borrow_mut() = outside);
+        polled
+    })
+    .await
+}
+
+/// Where events from this thread are published, if anywhere
+pub(crate) fn bus() -> Option<Recipient<GameEvent>> {
+    BUS.with(|sink| sink.borrow().clone())
+}
+
+/// Publish `event` from this thread, if anyone is listening
+pub(crate) fn publish(event: GameEvent) {
+    if let Some(bus) = bus() {
+        let _ = bus.do_send(event);
+    }
+}
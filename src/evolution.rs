@@ -0,0 +1,1848 @@
+//! Evolutionary population dynamics
+//!
+//! Each configured participant is a species. A population of individuals
+//! plays a round robin every generation, and the next generation is drawn
+//! from the current one in proportion to each individual's total score.
+//! With a mutation rate, offspring occasionally take up a different strategy,
+//! which then counts as a new species.
+//!
+//! A run can also stop early once it has settled, as [`Convergence`]
+//! decides.
+//!
+//! With [`PartnerChoice`] in the config, individuals may also refuse to play
+//! species that treated their own badly in an earlier generation, taking an
+//! outside payoff instead.
+//!
+//! With `relatedness` in the config, individuals of one species are kin,
+//! and each counts that share of a kin partner's score as its own, so that
+//! helping kin can pay where helping strangers doesn't, as Hamilton's rule
+//! has it.
+//!
+//! The Moran process instead changes one individual at a time, and is run
+//! repeatedly until one species takes over to estimate fixation probabilities.
+//!
+//! Co-evolution, with [`coevolve`], keeps two populations, such as hosts
+//! and parasites, whose individuals only ever play the other population's.
+//! Each population is selected on its own, so each adapts to the other as
+//! it stands, and an arms race between them can be followed generation by
+//! generation.
+//!
+//! Replicator dynamics follow the same selection as [`evolve`] in an
+//! infinitely large population: a deterministic sequence of species shares,
+//! computed from one match per pair of species rather than by playing every
+//! individual.
+
+use futures::future::join_all;
+use rand::rngs::StdRng;
+use rand::seq::index::sample;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use tracing::warn;
+
+use crate::config::{ConfigError, ParticipantConfig, SimConfig};
+use crate::events::{self, GameEvent};
+use crate::game::Score;
+use crate::hooks;
+use crate::interrupt;
+use crate::progress;
+use crate::referee::PlayerSummary;
+use crate::registry::{StrategyArgs, StrategyRegistry};
+use crate::topology::{Complete, Topology};
+use crate::tournament;
+
+/// Per-species headcount, keyed by participant name
+pub type Composition = BTreeMap<String, usize>;
+
+/// Per-species share of a population, keyed by participant name
+pub type Shares = BTreeMap<String, f64>;
+
+/// Partner choice: refuse to play those who didn't cooperate last time
+///
+/// After each generation, every species judges every species it met by how
+/// often their individuals cooperated with its own. From then on its
+/// individuals refuse to play any species that cooperated less often than
+/// `threshold`. Since a refused pairing isn't played again, a refusal
+/// stands for good: the refused species is ostracized.
+/// Either side refusing is enough, and both then earn `outside` points per
+/// round of the match instead.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PartnerChoice {
+    #[serde(default = "default_threshold")]
+    pub threshold: f64,
+
+    /// The outside option's payoff per round, the punishment payoff if not
+    /// given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub outside: Option<Score>,
+}
+
+fn default_threshold() -> f64 {
+    0.5
+}
+
+/// Which species refuse which, as (refusing, refused) species indices
+pub type Refusals = BTreeSet<(usize, usize)>;
+
+/// When an evolutionary run has settled: no species' share of the
+/// population has moved by more than `tolerance` from one generation to the
+/// next for `generations` generations running
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Convergence {
+    pub generations: usize,
+    pub tolerance: f64,
+}
+
+impl Convergence {
+    /// Whether the run has settled, given its compositions so far, oldest
+    /// first, each of `population` individuals
+    fn settled(&self, compositions: &[&Composition], population: usize) -> bool {
+        if self.generations == 0 || compositions.len() <= self.generations {
+            return false;
+        }
+        let share = |composition: &Composition, name: &str| {
+            composition.get(name).copied().unwrap_or(0) as f64 / population.max(1) as f64
+        };
+        compositions[compositions.len() - 1 - self.generations..]
+            .windows(2)
+            .all(|pair| {
+                pair[0].keys().chain(pair[1].keys()).all(|name| {
+                    (share(pair[1], name) - share(pair[0], name)).abs() <= self.tolerance
+                })
+            })
+    }
+}
+
+/// The population at the start of one generation and how well it did
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GenerationRecord {
+    pub generation: usize,
+    pub composition: Composition,
+
+    /// Mean total score per individual over the generation's matches
+    pub mean_fitness: f64,
+}
+
+/// Everything needed to reproduce and plot an evolutionary run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EvolutionRecord {
+    pub config: SimConfig,
+    pub seed: u64,
+    pub population: usize,
+    pub mutation_rate: f64,
+
+    /// The configured participants, then any species introduced by mutation
+    /// in the order they appeared
+    pub species: Vec<ParticipantConfig>,
+    pub generations: Vec<GenerationRecord>,
+
+    /// Headcounts after the last generation reproduced
+    pub survivors: Composition,
+
+    /// The generation after which the run stopped because it had settled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub converged_at: Option<usize>,
+}
+
+fn composition(species: &[ParticipantConfig], population: &[usize]) -> Composition {
+    let mut composition: Composition = species.iter().map(|p| (p.name.clone(), 0)).collect();
+    for &s in population {
+        *composition
+            .get_mut(&species[s].name)
+            .expect("known species") += 1;
+    }
+    composition
+}
+
+/// A name based on `base` that no species has yet
+fn unique_name(species: &[ParticipantConfig], base: &str) -> String {
+    let taken = |name: &str| species.iter().any(|p| p.name == name);
+    if !taken(base) {
+        return base.to_owned();
+    }
+    (1..)
+        .map(|n| format!("{}~{}", base, n))
+        .find(|name| !taken(name))
+        .expect("unbounded names")
+}
+
+/// The standard deviation of the step a mutation takes a parameter by,
+/// or 1 for a parameter that is a whole number
+pub const MUTATION_STEP: f64 = 0.05;
+
+/// A draw from the standard normal distribution, by the Box-Muller transform
+fn standard_normal(rng: &mut StdRng) -> f64 {
+    // 1 - u lies in (0, 1], so its logarithm is finite
+    let u = 1.0 - rng.gen::<f64>();
+    let v = rng.gen::<f64>();
+    (-2.0 * u.ln()).sqrt() * (2.0 * std::f64::consts::PI * v).cos()
+}
+
+/// The species an offspring of `parent` mutates into, adding it to `species`
+/// if it wasn't there already
+///
+/// A tagged parent has an even chance of passing on its strategy with a
+/// fresh tag, drawn uniformly from [0, 1]. Otherwise, a parameterized parent
+/// has an even chance of keeping its strategy with each parameter moved by
+/// a normally distributed step, see [`MUTATION_STEP`], and held to the values
+/// the registry says it can take. Failing that the offspring takes up a
+/// strategy drawn uniformly from the registry, with default parameters.
+/// Either way it keeps the parent's tag, population, and payoffs. A mutant the
+/// registry can't build stays the parent's species.
+fn mutate(
+    parent_species: usize,
+    species: &mut Vec<ParticipantConfig>,
+    config: &SimConfig,
+    registry: &StrategyRegistry,
+    rng: &mut StdRng,
+) -> usize {
+    let parent = &species[parent_species];
+    let mutant = if parent.tag.is_some() && rng.gen::<bool>() {
+        ParticipantConfig {
+            tag: Some(rng.gen()),
+            ..parent.clone()
+        }
+    } else if !parent.parameters.is_empty() && rng.gen::<bool>() {
+        let domains = registry.parameters(&parent.strategy);
+        let parameters = parent
+            .parameters
+            .iter()
+            .map(|(name, &value)| {
+                let domain = domains.iter().find(|domain| &domain.name == name);
+                let whole = domain.is_some_and(|domain| domain.whole);
+                let step = if whole { 1.0 } else { MUTATION_STEP };
+                let value = value + step * standard_normal(rng);
+                (
+                    name.clone(),
+                    domain.map_or(value, |domain| domain.clamp(value)),
+                )
+            })
+            .collect();
+        ParticipantConfig {
+            parameters,
+            ..parent.clone()
+        }
+    } else {
+        // nobody is waiting at the terminal to play a mutant
+        let names: Vec<&str> = registry
+            .names()
+            .filter(|&name| name != crate::human::NAME)
+            .collect();
+        let strategy = names[rng.gen_range(0, names.len())];
+        ParticipantConfig {
+            tag: parent.tag,
+            population: parent.population.clone(),
+            payoffs: parent.payoffs,
+            ..ParticipantConfig::new(strategy, strategy)
+        }
+    };
+    let args = StrategyArgs {
+        parameters: &mutant.parameters,
+        payoffs: &config.payoffs_told(&mutant),
+        seed: 0,
+    };
+    if registry.create(&mutant.strategy, &args).is_err() {
+        return parent_species;
+    }
+
+    if let Some(existing) = species.iter().position(|p| {
+        p.strategy == mutant.strategy
+            && p.parameters == mutant.parameters
+            && p.tag == mutant.tag
+            && p.population == mutant.population
+            && p.payoffs == mutant.payoffs
+    }) {
+        return existing;
+    }
+    let name = unique_name(species, &mutant.name);
+    species.push(ParticipantConfig { name, ..mutant });
+    species.len() - 1
+}
+
+/// Selection weights from fitness values, shifted up so that none is
+/// negative when payoffs can be
+pub(crate) fn selection_weights(fitness: &[Score]) -> Vec<f64> {
+    let floor = fitness.iter().copied().fold(0.0, f64::min);
+    fitness.iter().map(|f| f - floor).collect()
+}
+
+/// Draw the next generation, each slot filled by an individual chosen with
+/// probability proportional to its fitness
+///
+/// If every individual is equally fit, every individual is equally likely.
+fn reproduce(population: &[usize], fitness: &[Score], rng: &mut StdRng) -> Vec<usize> {
+    let weights = selection_weights(fitness);
+    let total: f64 = weights.iter().sum();
+    (0..population.len())
+        .map(|_| {
+            if total <= 0.0 {
+                population[rng.gen_range(0, population.len())]
+            } else {
+                population[pick(&weights, rng.gen::<f64>() * total)]
+            }
+        })
+        .collect()
+}
+
+/// An evolutionary run between generations, enough to carry it on from there
+///
+/// No match is in progress between generations and every strategy starts
+/// afresh each generation, so nothing else needs keeping. Each generation
+/// draws on its own seeds, picked at the end of the one before, so a run
+/// resumed from a checkpoint goes on exactly as it would have.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EvolutionCheckpoint {
+    pub config: SimConfig,
+    pub seed: u64,
+    pub population: usize,
+    pub mutation_rate: f64,
+    pub species: Vec<ParticipantConfig>,
+    pub generations: Vec<GenerationRecord>,
+
+    /// The species of each individual in the next generation
+    pub current: Vec<usize>,
+
+    /// Refusals standing under partner choice
+    #[serde(default, skip_serializing_if = "Refusals::is_empty")]
+    pub refusals: Refusals,
+
+    /// As in [`EvolutionRecord`]; a settled run isn't carried on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub converged_at: Option<usize>,
+
+    /// Seeds for the next generation's matches and for breeding from it
+    pub match_seed: u64,
+    pub breeding_seed: u64,
+}
+
+#[derive(Debug)]
+pub enum CheckpointError {
+    Io(io::Error),
+    Parse(serde_json::Error),
+}
+
+impl fmt::Display for CheckpointError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CheckpointError::Io(e) => write!(f, "unable to access checkpoint: {}", e),
+            CheckpointError::Parse(e) => write!(f, "unable to parse checkpoint: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for CheckpointError {}
+
+impl EvolutionCheckpoint {
+    /// A run that hasn't begun, with an even split across
+    /// `config.participants`
+    pub fn new(
+        config: &SimConfig,
+        seed: u64,
+        population: usize,
+        mutation_rate: f64,
+    ) -> EvolutionCheckpoint {
+        let mut seeds = StdRng::seed_from_u64(seed);
+        EvolutionCheckpoint {
+            config: config.clone(),
+            seed,
+            population,
+            mutation_rate,
+            species: config.participants.clone(),
+            generations: Vec::new(),
+            current: initial_population(config.participants.len(), population),
+            refusals: Refusals::new(),
+            converged_at: None,
+            match_seed: seeds.gen(),
+            breeding_seed: seeds.gen(),
+        }
+    }
+
+    /// Start from the species of each individual in `current`, as indexes
+    /// into `config.participants`, rather than an even split
+    pub fn with_population(mut self, current: Vec<usize>) -> EvolutionCheckpoint {
+        self.population = current.len();
+        self.current = current;
+        self
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<EvolutionCheckpoint, CheckpointError> {
+        let text = fs::read_to_string(path).map_err(CheckpointError::Io)?;
+        serde_json::from_str(&text).map_err(CheckpointError::Parse)
+    }
+
+    /// Write the checkpoint to `path`, replacing the last one only once the
+    /// new one is complete
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), CheckpointError> {
+        let path = path.as_ref();
+        let text = serde_json::to_string(self).expect("serializable checkpoint");
+        let mut partial = path.as_os_str().to_owned();
+        partial.push(".partial");
+        fs::write(&partial, text).map_err(CheckpointError::Io)?;
+        fs::rename(&partial, path).map_err(CheckpointError::Io)
+    }
+
+    fn into_record(self) -> EvolutionRecord {
+        EvolutionRecord {
+            survivors: composition(&self.species, &self.current),
+            config: self.config,
+            seed: self.seed,
+            population: self.population,
+            mutation_rate: self.mutation_rate,
+            species: self.species,
+            generations: self.generations,
+            converged_at: self.converged_at,
+        }
+    }
+}
+
+/// Where and how often to save an evolutionary run
+pub struct Checkpointing<'a> {
+    pub path: &'a Path,
+
+    /// Generations between checkpoints; one is also saved when the run ends
+    pub every: usize,
+}
+
+/// The next generation after `current`, drawn by fitness, with each
+/// offspring mutating with probability `mutation_rate`
+fn breed(
+    current: &[usize],
+    fitness: &[Score],
+    species: &mut Vec<ParticipantConfig>,
+    mutation_rate: f64,
+    config: &SimConfig,
+    registry: &StrategyRegistry,
+    rng: &mut StdRng,
+) -> Vec<usize> {
+    let mut next = reproduce(current, fitness, rng);
+    for offspring in next.iter_mut() {
+        if mutation_rate > 0.0 && rng.gen::<f64>() < mutation_rate {
+            *offspring = mutate(*offspring, species, config, registry, rng);
+        }
+    }
+    next
+}
+
+/// Evolve a population of `population` individuals over `generations`
+/// generations, starting from an even split across `config.participants`
+///
+/// Each offspring mutates with probability `mutation_rate`. The config should
+/// already have been validated against `registry`. The same seed replays the
+/// same run.
+pub async fn evolve(
+    config: &SimConfig,
+    registry: &StrategyRegistry,
+    seed: u64,
+    population: usize,
+    generations: usize,
+    mutation_rate: f64,
+) -> EvolutionRecord {
+    let start = EvolutionCheckpoint::new(config, seed, population, mutation_rate);
+    resume(registry, start, generations, None, None).await
+}
+
+/// Play a round robin among the individuals `current`, each a member of one
+/// of `species`, and total up each one's score
+///
+/// Each pairing is seated as [`tournament::seatings`] has it. See
+/// [`pairs_fitness`] for partner choice and interruptions.
+async fn round_robin_fitness(
+    config: &SimConfig,
+    registry: &StrategyRegistry,
+    species: &[ParticipantConfig],
+    current: &[usize],
+    refusals: &mut Refusals,
+    seeds: &mut StdRng,
+) -> Option<Vec<Score>> {
+    let pairs = tournament::seatings(config, Complete(current.len()).edges());
+    pairs_fitness(config, registry, species, current, pairs, refusals, seeds).await
+}
+
+/// Play the individuals `current`, each a member of one of `species`, in
+/// `pairs` of their indexes, and total up each one's score
+///
+/// Each individual's score is counted in the payoffs it sees, its
+/// temptation drawn from `seeds` if the config has a perceived temptation.
+/// With `relatedness` in the config, a partner of the same species also
+/// adds that share of its score to each side's. Under partner choice,
+/// pairings that `refusals` rules out aren't played and earn both sides
+/// the outside payoff; `refusals` is then updated from
+/// the matches that were. Aborted matches earn nothing for either side.
+/// Returns none if the run was interrupted, since cut-short matches say
+/// little about fitness.
+async fn pairs_fitness(
+    config: &SimConfig,
+    registry: &StrategyRegistry,
+    species: &[ParticipantConfig],
+    current: &[usize],
+    mut pairs: Vec<(usize, usize)>,
+    refusals: &mut Refusals,
+    seeds: &mut StdRng,
+) -> Option<Vec<Score>> {
+    let individuals: Vec<ParticipantConfig> = current
+        .iter()
+        .enumerate()
+        .map(|(i, &s)| {
+            let participant = &species[s];
+            ParticipantConfig {
+                name: format!("{}-{}", participant.name, i),
+                payoffs: config.perceive(participant, config.draw_temptation(seeds)),
+                ..participant.clone()
+            }
+        })
+        .collect();
+    let index: HashMap<&str, usize> = individuals
+        .iter()
+        .enumerate()
+        .map(|(i, individual)| (individual.name.as_str(), i))
+        .collect();
+
+    let earned = |player: &PlayerSummary| match &individuals[index[player.name.as_str()]].payoffs {
+        Some(payoffs) => player.score_under(payoffs),
+        None => player.score,
+    };
+
+    let mut fitness = vec![0.0; individuals.len()];
+    if let Some(choice) = config.partner_choice {
+        let refused = |&(a, b): &(usize, usize)| {
+            let (a, b) = (current[a], current[b]);
+            refusals.contains(&(a, b)) || refusals.contains(&(b, a))
+        };
+        let outside = choice
+            .outside
+            .unwrap_or_else(|| config.payoffs().punishment())
+            * config.iterations as f64;
+        for &(a, b) in pairs.iter().filter(|pair| refused(pair)) {
+            fitness[a] += outside;
+            fitness[b] += outside;
+        }
+        pairs.retain(|pair| !refused(pair));
+    }
+    let reports = tournament::play_pairs(config, registry, &individuals, &pairs, seeds, None).await;
+    if interrupt::interrupted() {
+        return None;
+    }
+
+    // how often each species cooperated with each other one, by (judge, judged)
+    let mut cooperation: BTreeMap<(usize, usize), (usize, usize)> = BTreeMap::new();
+    let relatedness = config.relatedness.unwrap_or(0.0);
+    for result in reports
+        .iter()
+        .filter_map(|report| report.result.as_ref().ok())
+    {
+        for (player, opponent) in [(&result.blue, &result.red), (&result.red, &result.blue)] {
+            let judge = current[index[opponent.name.as_str()]];
+            let judged = current[index[player.name.as_str()]];
+            fitness[index[player.name.as_str()]] += earned(player);
+            if judge == judged {
+                fitness[index[player.name.as_str()]] += relatedness * earned(opponent);
+            }
+            let tally = cooperation.entry((judge, judged)).or_default();
+            tally.0 += player.cooperations;
+            tally.1 += result.rounds;
+        }
+    }
+    if let Some(choice) = config.partner_choice {
+        for (pair, (cooperations, rounds)) in cooperation {
+            if rounds > 0 && (cooperations as f64) < choice.threshold * rounds as f64 {
+                refusals.insert(pair);
+            } else {
+                refusals.remove(&pair);
+            }
+        }
+    }
+    Some(fitness)
+}
+
+/// Carry `state` on until it has run `generations` generations in all, or
+/// has settled as `convergence` decides, saving it as `checkpointing` asks
+///
+/// A failed save is logged and the run goes on. An interrupted run is saved
+/// as of its last complete generation.
+pub async fn resume(
+    registry: &StrategyRegistry,
+    mut state: EvolutionCheckpoint,
+    generations: usize,
+    convergence: Option<Convergence>,
+    checkpointing: Option<Checkpointing<'_>>,
+) -> EvolutionRecord {
+    let save = |state: &EvolutionCheckpoint| {
+        if let Some(checkpointing) = &checkpointing {
+            if let Err(e) = state.save(checkpointing.path) {
+                warn!(path = %checkpointing.path.display(), error = %e, "unable to save checkpoint");
+            }
+        }
+    };
+    let config = state.config.clone();
+
+    while state.generations.len() < generations && state.converged_at.is_none() {
+        let generation = state.generations.len();
+        let mut seeds = StdRng::seed_from_u64(state.match_seed);
+        let mut rng = StdRng::seed_from_u64(state.breeding_seed);
+        let fitness = match round_robin_fitness(
+            &config,
+            registry,
+            &state.species,
+            &state.current,
+            &mut state.refusals,
+            &mut seeds,
+        )
+        .await
+        {
+            Some(fitness) => fitness,
+            // the matches were cut short, so this generation's fitness is meaningless
+            None => break,
+        };
+
+        let record = GenerationRecord {
+            generation,
+            composition: composition(&state.species, &state.current),
+            mean_fitness: fitness.iter().sum::<Score>() / fitness.len().max(1) as f64,
+        };
+        events::publish(GameEvent::GenerationComplete(record.clone()));
+        progress::finish_generation();
+        let stop = hooks::generation_ended(&record);
+        state.generations.push(record);
+        state.current = breed(
+            &state.current,
+            &fitness,
+            &mut state.species,
+            state.mutation_rate,
+            &config,
+            registry,
+            &mut rng,
+        );
+        state.match_seed = seeds.gen();
+        state.breeding_seed = rng.gen();
+
+        if let Some(convergence) = &convergence {
+            let next = composition(&state.species, &state.current);
+            let compositions: Vec<&Composition> = state
+                .generations
+                .iter()
+                .map(|g| &g.composition)
+                .chain(std::iter::once(&next))
+                .collect();
+            if convergence.settled(&compositions, state.population) {
+                state.converged_at = Some(generation);
+            }
+        }
+
+        if let Some(checkpointing) = &checkpointing {
+            if checkpointing.every > 0
+                && state.generations.len().is_multiple_of(checkpointing.every)
+            {
+                save(&state);
+            }
+        }
+        if stop {
+            break;
+        }
+    }
+
+    save(&state);
+    state.into_record()
+}
+
+/// How an island-model run splits its population and moves individuals
+/// between the islands
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct IslandSettings {
+    pub islands: usize,
+
+    /// Generations between migrations; 0 for none
+    pub migration_interval: usize,
+
+    /// Individuals each island sends to the next at every migration
+    pub migrants: usize,
+}
+
+/// Every island at the start of one generation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IslandGeneration {
+    pub generation: usize,
+
+    /// Each island's headcounts, in island order
+    pub islands: Vec<Composition>,
+
+    /// Mean total score per individual, over every island
+    pub mean_fitness: f64,
+}
+
+/// Everything needed to reproduce and plot an island-model run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IslandRecord {
+    pub config: SimConfig,
+    pub seed: u64,
+
+    /// Individuals per island
+    pub population: usize,
+    pub mutation_rate: f64,
+    pub settings: IslandSettings,
+
+    /// As in [`EvolutionRecord`], shared by every island
+    pub species: Vec<ParticipantConfig>,
+    pub generations: Vec<IslandGeneration>,
+
+    /// Each island's headcounts after the last generation reproduced
+    pub survivors: Vec<Composition>,
+}
+
+fn total(islands: &[Composition]) -> Composition {
+    let mut total = Composition::new();
+    for island in islands {
+        for (name, &count) in island {
+            *total.entry(name.clone()).or_default() += count;
+        }
+    }
+    total
+}
+
+impl IslandRecord {
+    /// The run as one population, headcounts summed over the islands
+    pub fn combined(&self) -> EvolutionRecord {
+        EvolutionRecord {
+            config: self.config.clone(),
+            seed: self.seed,
+            population: self.population * self.settings.islands,
+            mutation_rate: self.mutation_rate,
+            species: self.species.clone(),
+            generations: self
+                .generations
+                .iter()
+                .map(|generation| GenerationRecord {
+                    generation: generation.generation,
+                    composition: total(&generation.islands),
+                    mean_fitness: generation.mean_fitness,
+                })
+                .collect(),
+            survivors: total(&self.survivors),
+            converged_at: None,
+        }
+    }
+}
+
+/// Evolve `settings.islands` populations of `population` individuals side
+/// by side, every `migration_interval` generations moving `migrants`
+/// individuals, chosen at random, from each island to the next in a ring,
+/// where they replace as many chosen at random
+///
+/// Otherwise each island evolves as in [`evolve`], and all the islands'
+/// round robins are played at once, so with [`tournament::use_threads`]
+/// they spread across the worker arbiters. Species, including mutants, are
+/// shared by every island. The same seed replays the same run.
+pub async fn evolve_islands(
+    config: &SimConfig,
+    registry: &StrategyRegistry,
+    seed: u64,
+    population: usize,
+    generations: usize,
+    mutation_rate: f64,
+    settings: IslandSettings,
+) -> IslandRecord {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut species = config.participants.clone();
+    let mut islands: Vec<Vec<usize>> = (0..settings.islands)
+        .map(|_| initial_population(species.len(), population))
+        .collect();
+    let mut refusals = vec![Refusals::new(); settings.islands];
+    let mut history = Vec::with_capacity(generations);
+
+    for generation in 0..generations {
+        let mut seeds: Vec<StdRng> = islands
+            .iter()
+            .map(|_| StdRng::seed_from_u64(rng.gen()))
+            .collect();
+        // each island keeps its own refusals
+        let plays = islands
+            .iter()
+            .zip(seeds.iter_mut())
+            .zip(refusals.iter_mut())
+            .map(|((island, seeds), refusals)| {
+                round_robin_fitness(config, registry, &species, island, refusals, seeds)
+            });
+        let fitness: Option<Vec<Vec<Score>>> = join_all(plays).await.into_iter().collect();
+        let fitness = match fitness {
+            Some(fitness) => fitness,
+            // the matches were cut short, so this generation's fitness is meaningless
+            None => break,
+        };
+
+        let record = IslandGeneration {
+            generation,
+            islands: islands
+                .iter()
+                .map(|island| composition(&species, island))
+                .collect(),
+            mean_fitness: fitness.iter().flatten().sum::<Score>()
+                / (population * settings.islands).max(1) as f64,
+        };
+        let overall = GenerationRecord {
+            generation,
+            composition: total(&record.islands),
+            mean_fitness: record.mean_fitness,
+        };
+        events::publish(GameEvent::GenerationComplete(overall.clone()));
+        let stop = hooks::generation_ended(&overall);
+        history.push(record);
+
+        islands = islands
+            .iter()
+            .zip(&fitness)
+            .map(|(island, fitness)| {
+                breed(
+                    island,
+                    fitness,
+                    &mut species,
+                    mutation_rate,
+                    config,
+                    registry,
+                    &mut rng,
+                )
+            })
+            .collect();
+
+        if settings.islands > 1
+            && settings.migration_interval > 0
+            && (generation + 1).is_multiple_of(settings.migration_interval)
+        {
+            let migrants = settings.migrants.min(population);
+            let leaving: Vec<Vec<usize>> = islands
+                .iter()
+                .map(|island| {
+                    sample(&mut rng, island.len(), migrants)
+                        .into_iter()
+                        .map(|i| island[i])
+                        .collect()
+                })
+                .collect();
+            for (from, emigrants) in leaving.into_iter().enumerate() {
+                let to = &mut islands[(from + 1) % settings.islands];
+                for (slot, emigrant) in sample(&mut rng, to.len(), migrants)
+                    .into_iter()
+                    .zip(emigrants)
+                {
+                    to[slot] = emigrant;
+                }
+            }
+        }
+        if stop {
+            break;
+        }
+    }
+
+    IslandRecord {
+        config: config.clone(),
+        seed,
+        population,
+        mutation_rate,
+        settings,
+        survivors: islands
+            .iter()
+            .map(|island| composition(&species, island))
+            .collect(),
+        species,
+        generations: history,
+    }
+}
+
+/// An even split of `population` individuals across `species` species, any
+/// remainder going to the first species in config order
+fn initial_population(species: usize, population: usize) -> Vec<usize> {
+    (0..population).map(|i| i % species).collect()
+}
+
+/// Both populations at the start of one generation of a co-evolutionary run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CoevolutionGeneration {
+    pub generation: usize,
+
+    /// Each population's headcounts, in the order of
+    /// [`CoevolutionRecord::labels`]
+    pub populations: Vec<Composition>,
+
+    /// Mean total score per individual in each population
+    pub mean_fitness: Vec<f64>,
+}
+
+/// Everything needed to reproduce and plot a co-evolutionary run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CoevolutionRecord {
+    pub config: SimConfig,
+    pub seed: u64,
+
+    /// Individuals per population
+    pub population: usize,
+    pub mutation_rate: f64,
+
+    /// The populations' labels, in the order they first appear in the config
+    pub labels: Vec<String>,
+
+    /// As in [`EvolutionRecord`]; each species belongs to one population,
+    /// and a mutant to its parent's
+    pub species: Vec<ParticipantConfig>,
+    pub generations: Vec<CoevolutionGeneration>,
+
+    /// Each population's headcounts after the last generation reproduced
+    pub survivors: Vec<Composition>,
+}
+
+impl CoevolutionRecord {
+    /// The `i`th population's side of the run, as if it had evolved alone
+    pub fn population(&self, i: usize) -> EvolutionRecord {
+        let label = Some(self.labels[i].as_str());
+        EvolutionRecord {
+            config: self.config.clone(),
+            seed: self.seed,
+            population: self.population,
+            mutation_rate: self.mutation_rate,
+            species: self
+                .species
+                .iter()
+                .filter(|p| p.population.as_deref() == label)
+                .cloned()
+                .collect(),
+            generations: self
+                .generations
+                .iter()
+                .map(|generation| GenerationRecord {
+                    generation: generation.generation,
+                    composition: generation.populations[i].clone(),
+                    mean_fitness: generation.mean_fitness[i],
+                })
+                .collect(),
+            survivors: self.survivors[i].clone(),
+            converged_at: None,
+        }
+    }
+
+    /// The run as one population, headcounts summed over both
+    pub fn combined(&self) -> EvolutionRecord {
+        EvolutionRecord {
+            config: self.config.clone(),
+            seed: self.seed,
+            population: self.population * self.labels.len(),
+            mutation_rate: self.mutation_rate,
+            species: self.species.clone(),
+            generations: self
+                .generations
+                .iter()
+                .map(|generation| GenerationRecord {
+                    generation: generation.generation,
+                    composition: total(&generation.populations),
+                    mean_fitness: generation.mean_fitness.iter().sum::<f64>()
+                        / generation.mean_fitness.len().max(1) as f64,
+                })
+                .collect(),
+            survivors: total(&self.survivors),
+            converged_at: None,
+        }
+    }
+}
+
+/// The two populations' labels, in the order they first appear among
+/// `participants`, every one of which must be in one of them
+pub fn population_labels(participants: &[ParticipantConfig]) -> Result<Vec<String>, ConfigError> {
+    let mut labels: Vec<String> = Vec::new();
+    for participant in participants {
+        match &participant.population {
+            Some(label) if !labels.contains(label) => labels.push(label.clone()),
+            Some(_) => {}
+            None => {
+                return Err(ConfigError::Invalid(format!(
+                "participant '{}' is in no population; co-evolution needs a population for each",
+                participant.name
+            )))
+            }
+        }
+    }
+    if labels.len() != 2 {
+        return Err(ConfigError::Invalid(format!(
+            "co-evolution needs two populations, not {}",
+            labels.len()
+        )));
+    }
+    Ok(labels)
+}
+
+/// The headcounts among `individuals` of the species in population `label`
+fn population_composition(
+    species: &[ParticipantConfig],
+    label: &str,
+    individuals: &[usize],
+) -> Composition {
+    let mut composition = composition(species, individuals);
+    composition.retain(|name, _| {
+        species
+            .iter()
+            .any(|p| &p.name == name && p.population.as_deref() == Some(label))
+    });
+    composition
+}
+
+/// Co-evolve two populations of `population` individuals each over
+/// `generations` generations, each starting from an even split across its
+/// own participants
+///
+/// Every participant names its population, and there must be two. Every
+/// generation, each individual plays every individual of the other
+/// population and none of its own, then each population breeds from its own
+/// fitness alone, as in [`evolve`]. The same seed replays the same run.
+pub async fn coevolve(
+    config: &SimConfig,
+    registry: &StrategyRegistry,
+    seed: u64,
+    population: usize,
+    generations: usize,
+    mutation_rate: f64,
+) -> Result<CoevolutionRecord, ConfigError> {
+    let labels = population_labels(&config.participants)?;
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut species = config.participants.clone();
+    let mut populations: Vec<Vec<usize>> = labels
+        .iter()
+        .map(|label| {
+            let members: Vec<usize> = (0..species.len())
+                .filter(|&s| species[s].population.as_ref() == Some(label))
+                .collect();
+            initial_population(members.len(), population)
+                .into_iter()
+                .map(|i| members[i])
+                .collect()
+        })
+        .collect();
+    let mut refusals = Refusals::new();
+    let mut history = Vec::with_capacity(generations);
+
+    for generation in 0..generations {
+        let mut seeds = StdRng::seed_from_u64(rng.gen());
+        let (first, second) = (populations[0].len(), populations[1].len());
+        let everyone = populations.concat();
+        let pairs = (0..first)
+            .flat_map(|a| (first..first + second).map(move |b| (a, b)))
+            .collect();
+        let fitness = match pairs_fitness(
+            config,
+            registry,
+            &species,
+            &everyone,
+            pairs,
+            &mut refusals,
+            &mut seeds,
+        )
+        .await
+        {
+            Some(fitness) => fitness,
+            // the matches were cut short, so this generation's fitness is meaningless
+            None => break,
+        };
+        let fitness = [&fitness[..first], &fitness[first..]];
+
+        let record = CoevolutionGeneration {
+            generation,
+            populations: labels
+                .iter()
+                .zip(&populations)
+                .map(|(label, individuals)| population_composition(&species, label, individuals))
+                .collect(),
+            mean_fitness: fitness
+                .iter()
+                .map(|fitness| fitness.iter().sum::<Score>() / fitness.len().max(1) as f64)
+                .collect(),
+        };
+        let overall = GenerationRecord {
+            generation,
+            composition: total(&record.populations),
+            mean_fitness: fitness.concat().iter().sum::<Score>() / everyone.len().max(1) as f64,
+        };
+        events::publish(GameEvent::GenerationComplete(overall.clone()));
+        let stop = hooks::generation_ended(&overall);
+        history.push(record);
+
+        populations = populations
+            .iter()
+            .zip(fitness)
+            .map(|(individuals, fitness)| {
+                breed(
+                    individuals,
+                    fitness,
+                    &mut species,
+                    mutation_rate,
+                    config,
+                    registry,
+                    &mut rng,
+                )
+            })
+            .collect();
+        if stop {
+            break;
+        }
+    }
+
+    Ok(CoevolutionRecord {
+        config: config.clone(),
+        seed,
+        population,
+        mutation_rate,
+        survivors: labels
+            .iter()
+            .zip(&populations)
+            .map(|(label, individuals)| population_composition(&species, label, individuals))
+            .collect(),
+        labels,
+        species,
+        generations: history,
+    })
+}
+
+/// Fixation counts from repeated Moran processes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FixationRecord {
+    pub config: SimConfig,
+    pub seed: u64,
+    pub population: usize,
+    /// Repetitions run, fewer than asked for if the run was interrupted
+    pub repetitions: usize,
+
+    /// Average points per round for the row species against the column
+    /// species, in config order
+    pub payoffs: Vec<Vec<f64>>,
+
+    /// How many repetitions each species took over, keyed by participant name
+    pub fixations: Composition,
+
+    /// Repetitions still mixed after `max_steps`
+    pub unresolved: usize,
+
+    /// Mean birth-death steps taken by the repetitions that fixed
+    pub mean_steps: f64,
+}
+
+impl FixationRecord {
+    /// The fraction of repetitions in which `name` took over
+    pub fn fixation_probability(&self, name: &str) -> f64 {
+        match self.fixations.get(name) {
+            Some(&count) if self.repetitions > 0 => count as f64 / self.repetitions as f64,
+            _ => 0.0,
+        }
+    }
+}
+
+/// Play one match between every pair of species, including each species
+/// against itself, and tabulate the average points per round
+///
+/// Each species' payoffs are counted as it sees them. A species' payoff
+/// against itself includes the share of its partner's
+/// that `relatedness` in the config counts. If `red_payoffs` pays the sides
+/// differently, each pair of species plays seated both ways, and each
+/// entry is the average of the two. Aborted matches leave their entries at
+/// zero.
+pub async fn payoff_table(
+    config: &SimConfig,
+    registry: &StrategyRegistry,
+    seeds: &mut StdRng,
+) -> Vec<Vec<f64>> {
+    let n = config.participants.len();
+    let species: Vec<(usize, usize)> = (0..n).flat_map(|i| (i..n).map(move |j| (i, j))).collect();
+    let entrants: Vec<ParticipantConfig> = species
+        .iter()
+        .flat_map(|&(i, j)| {
+            let blue = &config.participants[i];
+            let red = &config.participants[j];
+            vec![
+                ParticipantConfig {
+                    name: format!("{}-blue", blue.name),
+                    ..blue.clone()
+                },
+                ParticipantConfig {
+                    name: format!("{}-red", red.name),
+                    ..red.clone()
+                },
+            ]
+        })
+        .collect();
+    let pairs = tournament::seatings(
+        config,
+        (0..species.len()).map(|k| (2 * k, 2 * k + 1)).collect(),
+    );
+    let reports = tournament::play_pairs(config, registry, &entrants, &pairs, seeds, None).await;
+
+    // the species each entrant is a member of
+    let member = |entrant: usize| {
+        let (i, j) = species[entrant / 2];
+        if entrant.is_multiple_of(2) {
+            i
+        } else {
+            j
+        }
+    };
+    let seatings = pairs.len() / species.len().max(1);
+    let relatedness = config.relatedness.unwrap_or(0.0);
+    let mut table = vec![vec![0.0; n]; n];
+    for (&(a, b), report) in pairs.iter().zip(&reports) {
+        if let Ok(result) = &report.result {
+            let (i, j) = (member(a), member(b));
+            let average = |player: &PlayerSummary, participant: &ParticipantConfig| {
+                match &participant.payoffs {
+                    Some(payoffs) => player.score_under(payoffs) / result.rounds.max(1) as f64,
+                    None => player.average_score(result.rounds),
+                }
+            };
+            let blue = average(&result.blue, &config.participants[i]);
+            let red = average(&result.red, &config.participants[j]);
+            let kin = if i == j { relatedness } else { 0.0 };
+            if i == j {
+                // a species against itself earns what either side did
+                table[i][i] += (blue + red) * (1.0 + kin) / 2.0 / seatings as f64;
+            } else {
+                table[i][j] += (blue + kin * red) / seatings as f64;
+                table[j][i] += (red + kin * blue) / seatings as f64;
+            }
+        }
+    }
+    table
+}
+
+/// One birth-death step: a parent is chosen in proportion to fitness and
+/// its offspring replaces an individual chosen uniformly
+///
+/// `counts` are headcounts per species. An individual's fitness is its
+/// average payoff against everyone else in the population.
+fn moran_step(counts: &mut [usize], payoffs: &[Vec<f64>], rng: &mut StdRng) {
+    let population: usize = counts.iter().sum();
+    let present: Vec<usize> = (0..counts.len()).filter(|&s| counts[s] > 0).collect();
+    let individual: Vec<Score> = present
+        .iter()
+        .map(|&s| {
+            let total: f64 = counts
+                .iter()
+                .enumerate()
+                .map(|(t, &count)| count as f64 * payoffs[s][t])
+                .sum::<f64>()
+                - payoffs[s][s];
+            total / (population - 1) as f64
+        })
+        .collect();
+    let mut fitness = vec![0.0; counts.len()];
+    for (&s, weight) in present.iter().zip(selection_weights(&individual)) {
+        fitness[s] = counts[s] as f64 * weight;
+    }
+
+    let headcounts: Vec<f64> = counts.iter().map(|&c| c as f64).collect();
+    let total: f64 = fitness.iter().sum();
+    let parent = if total > 0.0 {
+        pick(&fitness, rng.gen::<f64>() * total)
+    } else {
+        pick(&headcounts, rng.gen::<f64>() * population as f64)
+    };
+    let dead = pick(&headcounts, rng.gen::<f64>() * population as f64);
+
+    counts[parent] += 1;
+    counts[dead] -= 1;
+}
+
+/// The index whose cumulative weight first exceeds `target`, skipping
+/// zero weights
+pub(crate) fn pick(weights: &[f64], mut target: f64) -> usize {
+    let mut last = 0;
+    for (i, &w) in weights.iter().enumerate() {
+        if w <= 0.0 {
+            continue;
+        }
+        if target < w {
+            return i;
+        }
+        target -= w;
+        last = i;
+    }
+    // only reached through rounding
+    last
+}
+
+/// Take birth-death steps from `counts` until one species is left or
+/// `max_steps` have passed, returning the survivor, if any, and the steps
+/// taken
+fn to_fixation(
+    counts: &mut [usize],
+    payoffs: &[Vec<f64>],
+    rng: &mut StdRng,
+    max_steps: usize,
+) -> (Option<usize>, usize) {
+    let mut steps = 0;
+    while steps < max_steps && counts.iter().filter(|&&c| c > 0).count() > 1 {
+        moran_step(counts, payoffs, rng);
+        steps += 1;
+    }
+    let population: usize = counts.iter().sum();
+    (counts.iter().position(|&c| c == population), steps)
+}
+
+/// Run the Moran process `repetitions` times from an even split across
+/// `config.participants`, counting which species takes over each time
+///
+/// Fitness comes from a table of one match per pair of species, played once
+/// up front. A repetition that hasn't fixed after `max_steps` is counted as
+/// unresolved.
+pub async fn moran(
+    config: &SimConfig,
+    registry: &StrategyRegistry,
+    seed: u64,
+    population: usize,
+    repetitions: usize,
+    max_steps: usize,
+) -> FixationRecord {
+    let mut seeds = StdRng::seed_from_u64(seed);
+    let payoffs = payoff_table(config, registry, &mut seeds).await;
+    let mut rng = StdRng::seed_from_u64(seeds.gen());
+
+    let species = config.participants.len();
+    let mut fixations: Composition = config
+        .participants
+        .iter()
+        .map(|p| (p.name.clone(), 0))
+        .collect();
+    let mut unresolved = 0;
+    let mut steps_to_fixation = 0;
+    let mut completed = 0;
+    // an interrupted payoff table is incomplete, so no repetitions are run
+    while completed < repetitions && !interrupt::interrupted() {
+        completed += 1;
+        let mut counts = vec![0; species];
+        for s in initial_population(species, population) {
+            counts[s] += 1;
+        }
+        match to_fixation(&mut counts, &payoffs, &mut rng, max_steps) {
+            (Some(winner), steps) => {
+                *fixations
+                    .get_mut(&config.participants[winner].name)
+                    .expect("known species") += 1;
+                steps_to_fixation += steps;
+            }
+            (None, _) => unresolved += 1,
+        }
+    }
+
+    let fixed = completed - unresolved;
+    FixationRecord {
+        config: config.clone(),
+        seed,
+        population,
+        repetitions: completed,
+        payoffs,
+        fixations,
+        unresolved,
+        mean_steps: if fixed == 0 {
+            0.0
+        } else {
+            steps_to_fixation as f64 / fixed as f64
+        },
+    }
+}
+
+/// How often a few invaders take over a resident population
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InvasionRecord {
+    /// The resident, then the invader
+    pub config: SimConfig,
+    pub seed: u64,
+    pub population: usize,
+
+    /// Invaders at the start of each repetition
+    pub invaders: usize,
+
+    /// Repetitions run, fewer than asked for if the run was interrupted
+    pub repetitions: usize,
+
+    /// Average points per round, resident first, as in [`FixationRecord`]
+    pub payoffs: Vec<Vec<f64>>,
+
+    /// Repetitions in which the invaders took over
+    pub invasions: usize,
+
+    /// Repetitions in which the invaders died out
+    pub extinctions: usize,
+
+    /// Repetitions still mixed after `max_steps`
+    pub unresolved: usize,
+
+    /// Mean birth-death steps taken by the repetitions that fixed
+    pub mean_steps: f64,
+
+    /// The invaders' chance of taking over under neutral drift, their share
+    /// of the starting population
+    pub neutral_probability: f64,
+
+    /// Whether the resident meets Maynard Smith's conditions for an
+    /// evolutionarily stable strategy against the invader
+    pub stable: bool,
+}
+
+impl InvasionRecord {
+    /// The fraction of repetitions in which the invaders took over
+    pub fn invasion_probability(&self) -> f64 {
+        if self.repetitions == 0 {
+            0.0
+        } else {
+            self.invasions as f64 / self.repetitions as f64
+        }
+    }
+}
+
+/// Whether a resident with `payoffs[0]` resists an invader with
+/// `payoffs[1]`: it does strictly better against itself than the invader
+/// does against it, or as well and strictly better against the invader
+/// than the invader does against itself
+pub fn evolutionarily_stable(payoffs: &[Vec<f64>]) -> bool {
+    let (aa, ab, ba, bb) = (payoffs[0][0], payoffs[0][1], payoffs[1][0], payoffs[1][1]);
+    aa > ba || (aa == ba && ab > bb)
+}
+
+/// Run the Moran process `repetitions` times from `invaders` of the second
+/// participant of `config` among residents of the first, counting how
+/// often the invaders take over
+///
+/// As in [`moran`], fitness comes from one match per pair of species,
+/// played up front, and the same payoffs decide [`evolutionarily_stable`].
+/// An invasion probability above the neutral one means selection favours
+/// the invader.
+pub async fn invasion(
+    config: &SimConfig,
+    registry: &StrategyRegistry,
+    seed: u64,
+    population: usize,
+    invaders: usize,
+    repetitions: usize,
+    max_steps: usize,
+) -> InvasionRecord {
+    let mut seeds = StdRng::seed_from_u64(seed);
+    let payoffs = payoff_table(config, registry, &mut seeds).await;
+    let mut rng = StdRng::seed_from_u64(seeds.gen());
+
+    let (mut invasions, mut extinctions, mut unresolved) = (0, 0, 0);
+    let mut steps_to_fixation = 0;
+    let mut completed = 0;
+    while completed < repetitions && !interrupt::interrupted() {
+        completed += 1;
+        let mut counts = [population - invaders, invaders];
+        match to_fixation(&mut counts, &payoffs, &mut rng, max_steps) {
+            (Some(winner), steps) => {
+                if winner == 1 {
+                    invasions += 1;
+                } else {
+                    extinctions += 1;
+                }
+                steps_to_fixation += steps;
+            }
+            (None, _) => unresolved += 1,
+        }
+    }
+
+    let fixed = invasions + extinctions;
+    InvasionRecord {
+        config: config.clone(),
+        seed,
+        population,
+        invaders,
+        repetitions: completed,
+        stable: evolutionarily_stable(&payoffs),
+        payoffs,
+        invasions,
+        extinctions,
+        unresolved,
+        mean_steps: if fixed == 0 {
+            0.0
+        } else {
+            steps_to_fixation as f64 / fixed as f64
+        },
+        neutral_probability: invaders as f64 / population as f64,
+    }
+}
+
+/// The shares at the start of one generation of replicator dynamics
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplicatorGeneration {
+    pub generation: usize,
+    pub shares: Shares,
+
+    /// Mean points per round across the population
+    pub mean_fitness: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplicatorRecord {
+    pub config: SimConfig,
+    pub seed: u64,
+
+    /// Average points per round, as in [`FixationRecord`]
+    pub payoffs: Vec<Vec<f64>>,
+    pub generations: Vec<ReplicatorGeneration>,
+
+    /// Shares after the last generation
+    pub survivors: Shares,
+}
+
+/// One generation of the discrete replicator equation: each species' share
+/// grows in proportion to its fitness against the population, relative to
+/// the mean
+///
+/// Returns the mean fitness before selection.
+fn replicate(shares: &mut [f64], payoffs: &[Vec<f64>]) -> f64 {
+    let fitness: Vec<Score> = payoffs
+        .iter()
+        .map(|row| row.iter().zip(shares.iter()).map(|(a, x)| a * x).sum())
+        .collect();
+    let mean_fitness = fitness.iter().zip(shares.iter()).map(|(f, x)| f * x).sum();
+    let weights = selection_weights(&fitness);
+    let total: f64 = weights.iter().zip(shares.iter()).map(|(w, x)| w * x).sum();
+    // as in reproduce, if nobody is fitter than anybody, nothing changes
+    if total > 0.0 {
+        for (x, w) in shares.iter_mut().zip(&weights) {
+            *x *= w / total;
+        }
+    }
+    mean_fitness
+}
+
+/// Run `generations` generations of replicator dynamics from an even split
+/// across `config.participants`
+///
+/// The payoffs come from one match per pair of species, played up front;
+/// nothing after that is random.
+pub async fn replicator(
+    config: &SimConfig,
+    registry: &StrategyRegistry,
+    seed: u64,
+    generations: usize,
+) -> ReplicatorRecord {
+    let mut seeds = StdRng::seed_from_u64(seed);
+    let payoffs = payoff_table(config, registry, &mut seeds).await;
+
+    let n = config.participants.len();
+    let named = |shares: &[f64]| -> Shares {
+        config
+            .participants
+            .iter()
+            .map(|p| p.name.clone())
+            .zip(shares.iter().copied())
+            .collect()
+    };
+    let mut shares = vec![1.0 / n as f64; n];
+    let mut history = Vec::with_capacity(generations);
+    for generation in 0..generations {
+        let before = named(&shares);
+        let mean_fitness = replicate(&mut shares, &payoffs);
+        history.push(ReplicatorGeneration {
+            generation,
+            shares: before,
+            mean_fitness,
+        });
+    }
+    ReplicatorRecord {
+        config: config.clone(),
+        seed,
+        payoffs,
+        generations: history,
+        survivors: named(&shares),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::PayoffMatrix;
+    use crate::local::LocalMatch;
+    use crate::strategies::{AlwaysCooperate, AlwaysDefect, TitForTat};
+    use crate::strategy::Strategy;
+
+    #[test]
+    fn mutated_parameters_stay_in_their_domain() {
+        let registry = StrategyRegistry::new();
+        let config = SimConfig::default();
+        let mut parent = ParticipantConfig::new("gtft", "generous-tit-for-tat");
+        parent.parameters.insert("generosity".to_owned(), 0.99);
+        let mut species = vec![parent];
+        let mut rng = StdRng::seed_from_u64(7);
+        for _ in 0..200 {
+            mutate(0, &mut species, &config, &registry, &mut rng);
+        }
+        let generosities: Vec<f64> = species
+            .iter()
+            .filter_map(|p| p.parameters.get("generosity").copied())
+            .collect();
+        assert!(generosities.len() > 1);
+        assert!(generosities.iter().all(|g| (0.0..=1.0).contains(g)));
+        // a step past 1 is held at 1 rather than lost
+        assert!(generosities.contains(&1.0));
+    }
+
+    #[test]
+    fn mutation_steps_are_normally_distributed() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let draws: Vec<f64> = (0..10_000).map(|_| standard_normal(&mut rng)).collect();
+        let mean = draws.iter().sum::<f64>() / draws.len() as f64;
+        let variance = draws.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / draws.len() as f64;
+        assert!(mean.abs() < 0.05, "mean {}", mean);
+        assert!((variance - 1.0).abs() < 0.05, "variance {}", variance);
+        let within = draws.iter().filter(|x| x.abs() < 1.0).count() as f64;
+        assert!((within / draws.len() as f64 - 0.683).abs() < 0.02);
+    }
+
+    #[test]
+    fn whole_parameters_mutate_to_whole_numbers() {
+        let registry = StrategyRegistry::new();
+        let config = SimConfig::default();
+        let mut parent = ParticipantConfig::new("meta", "meta");
+        parent.parameters.insert("window".to_owned(), 0.0);
+        let mut species = vec![parent];
+        let mut rng = StdRng::seed_from_u64(7);
+        for _ in 0..100 {
+            mutate(0, &mut species, &config, &registry, &mut rng);
+        }
+        let windows: Vec<f64> = species
+            .iter()
+            .filter_map(|p| p.parameters.get("window").copied())
+            .collect();
+        assert!(windows.len() > 1);
+        assert!(windows.iter().all(|w| w.fract() == 0.0 && *w >= 0.0));
+    }
+
+    #[test]
+    fn a_population_without_mutants_has_already_fixed() {
+        let payoffs = vec![vec![3.0, 1.0], vec![4.0, 2.0]];
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut counts = vec![10, 0];
+        assert_eq!(
+            to_fixation(&mut counts, &payoffs, &mut rng, 1000),
+            (Some(0), 0)
+        );
+        assert_eq!(counts, [10, 0]);
+    }
+
+    #[test]
+    fn moran_steps_keep_the_population_size_until_one_species_fixes() {
+        let payoffs = vec![vec![3.0, 1.0], vec![4.0, 2.0]];
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut counts = vec![5, 5];
+        for _ in 0..20 {
+            moran_step(&mut counts, &payoffs, &mut rng);
+            assert_eq!(counts.iter().sum::<usize>(), 10);
+        }
+        let (survivor, _) = to_fixation(&mut counts, &payoffs, &mut rng, 100_000);
+        let survivor = survivor.expect("fixes");
+        assert_eq!(counts[survivor], 10);
+    }
+
+    /// Average points per round for each strategy against each, row first,
+    /// over `rounds`-round matches under the default payoffs
+    fn table(strategies: &[fn() -> Box<dyn Strategy>], rounds: usize) -> Vec<Vec<f64>> {
+        strategies
+            .iter()
+            .map(|row| {
+                strategies
+                    .iter()
+                    .map(|column| {
+                        let result = LocalMatch::new(
+                            ("row", row()),
+                            ("column", column()),
+                            PayoffMatrix::default(),
+                            rounds,
+                        )
+                        .play();
+                        result.blue.score / rounds as f64
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn allc() -> Box<dyn Strategy> {
+        Box::new(AlwaysCooperate {})
+    }
+
+    fn alld() -> Box<dyn Strategy> {
+        Box::new(AlwaysDefect {})
+    }
+
+    fn tft() -> Box<dyn Strategy> {
+        Box::new(TitForTat {})
+    }
+
+    #[test]
+    fn always_defect_resists_always_cooperate() {
+        assert!(evolutionarily_stable(&table(&[alld, allc], 200)));
+        assert!(!evolutionarily_stable(&table(&[allc, alld], 200)));
+    }
+
+    #[test]
+    fn tit_for_tat_resists_always_defect_only_in_long_matches() {
+        // in a single round, tit-for-tat is simply exploited
+        assert!(!evolutionarily_stable(&table(&[tft, alld], 1)));
+        // over many, the defector's one temptation doesn't make up for
+        // the punishments after it
+        assert!(evolutionarily_stable(&table(&[tft, alld], 200)));
+    }
+
+    #[test]
+    fn tit_for_tat_cannot_keep_out_always_cooperate() {
+        // they are indistinguishable in play, so drift can carry cooperators in
+        assert!(!evolutionarily_stable(&table(&[tft, allc], 200)));
+    }
+
+    #[test]
+    fn a_replicator_step_by_hand() {
+        // cooperators earn 3 * 0.5 + 1 * 0.5 = 2 and defectors 3, so the
+        // mean is 2.5 and the shares become 0.5 * 2 / 2.5 and 0.5 * 3 / 2.5
+        let payoffs = vec![vec![3.0, 1.0], vec![4.0, 2.0]];
+        let mut shares = vec![0.5, 0.5];
+        let mean_fitness = replicate(&mut shares, &payoffs);
+        assert!((mean_fitness - 2.5).abs() < 1e-12);
+        assert!((shares[0] - 0.4).abs() < 1e-12);
+        assert!((shares[1] - 0.6).abs() < 1e-12);
+    }
+
+    #[test]
+    fn replicator_shares_keep_summing_to_one() {
+        let payoffs = table(&[allc, alld, tft], 100);
+        let mut shares = vec![0.2, 0.3, 0.5];
+        for _ in 0..50 {
+            replicate(&mut shares, &payoffs);
+            assert!((shares.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+            assert!(shares.iter().all(|&x| x >= 0.0));
+        }
+        // cooperators are exploited away while tit-for-tat holds its own
+        assert!(shares[0] < 0.2);
+    }
+
+    #[actix_rt::test]
+    async fn generations_evolve_alike_on_one_thread_or_several() {
+        let registry = StrategyRegistry::new();
+        let config = SimConfig {
+            iterations: 20,
+            noise: 0.1,
+            participants: vec![
+                ParticipantConfig::new("tft", "tit-for-tat"),
+                ParticipantConfig::new("random", "random"),
+                ParticipantConfig::new("alld", "always-defect"),
+            ],
+            ..SimConfig::default()
+        };
+        let settings = IslandSettings {
+            islands: 2,
+            migration_interval: 2,
+            migrants: 1,
+        };
+        let mut runs = Vec::new();
+        for &threads in &[1, 4] {
+            tournament::use_threads(threads);
+            let evolved = evolve(&config, &registry, 5, 6, 4, 0.1).await;
+            let islands = evolve_islands(&config, &registry, 5, 6, 4, 0.1, settings).await;
+            runs.push((
+                serde_json::to_value(evolved).unwrap(),
+                serde_json::to_value(islands).unwrap(),
+            ));
+        }
+        tournament::use_threads(1);
+        assert_eq!(runs[0], runs[1]);
+    }
+
+    fn kin(relatedness: f64) -> SimConfig {
+        SimConfig {
+            iterations: 10,
+            relatedness: Some(relatedness),
+            participants: vec![
+                ParticipantConfig::new("allc", "always-cooperate"),
+                ParticipantConfig::new("alld", "always-defect"),
+            ],
+            ..SimConfig::default()
+        }
+    }
+
+    #[actix_rt::test]
+    async fn kin_count_a_share_of_each_others_scores() {
+        let config = kin(0.5);
+        let fitness = round_robin_fitness(
+            &config,
+            &StrategyRegistry::new(),
+            &config.participants,
+            &[0, 0, 1],
+            &mut Refusals::new(),
+            &mut StdRng::seed_from_u64(1),
+        )
+        .await
+        .expect("not interrupted");
+        // 30 from each other and half of the other's 30, then 10 from the
+        // defector, who is no kin of theirs
+        assert_eq!(fitness, vec![55.0, 55.0, 80.0]);
+    }
+
+    #[actix_rt::test]
+    async fn individuals_count_their_fitness_in_the_payoffs_they_see() {
+        use crate::config::TemptationRange;
+
+        let registry = StrategyRegistry::new();
+        let mut config = SimConfig {
+            perceived_temptation: Some(TemptationRange { min: 3.5, max: 4.5 }),
+            ..kin(0.0)
+        };
+        assert!(config.validate(&registry).is_ok());
+        let fitness = round_robin_fitness(
+            &config,
+            &registry,
+            &config.participants,
+            &[0, 1, 1, 1],
+            &mut Refusals::new(),
+            &mut StdRng::seed_from_u64(1),
+        )
+        .await
+        .expect("not interrupted");
+        // the cooperator is only ever a sucker, whatever it would be tempted by
+        assert_eq!(fitness[0], 30.0);
+        let defectors = &fitness[1..];
+        for &f in defectors {
+            // 10 temptations from the cooperator, 20 punishments from the others
+            assert!((35.0 + 40.0..=45.0 + 40.0).contains(&f), "{}", f);
+        }
+        assert!(defectors[0] != defectors[1] && defectors[1] != defectors[2]);
+
+        // a temptation of 5 is no dilemma with a reward of 3 and a sucker's 1
+        config.perceived_temptation = Some(TemptationRange { min: 3.5, max: 5.0 });
+        assert!(config.validate(&registry).is_err());
+        config.perceived_temptation = None;
+        config.participants[1].payoffs = Some(PayoffMatrix::default().with_temptation(4.5));
+        assert!(config.validate(&registry).is_ok());
+        config.discount = Some(0.9);
+        assert!(config.validate(&registry).is_err());
+    }
+
+    #[actix_rt::test]
+    async fn close_enough_kin_keep_defectors_out() {
+        let registry = StrategyRegistry::new();
+        let mut seeds = StdRng::seed_from_u64(1);
+        let strangers = payoff_table(&kin(0.0), &registry, &mut seeds).await;
+        assert!(!evolutionarily_stable(&strangers));
+        // cooperators among themselves get 3 + 3, more than the 4 a
+        // defector takes from them
+        let brothers = payoff_table(&kin(1.0), &registry, &mut seeds).await;
+        assert_eq!(brothers[0][0], 6.0);
+        assert_eq!(brothers[1][1], 4.0);
+        assert_eq!(brothers[1][0], 4.0);
+        assert!(evolutionarily_stable(&brothers));
+    }
+
+    #[actix_rt::test]
+    async fn sides_paid_apart_are_averaged_over_both_seatings() {
+        let registry = StrategyRegistry::new();
+        let mut config = kin(0.0);
+        config.relatedness = None;
+        // red is paid 4, 6, 1 and 0 where blue is paid 3, 4, 2 and 1
+        config.red_payoffs = Some(PayoffMatrix::new(4.0, 6.0, 1.0, 0.0));
+        let mut seeds = StdRng::seed_from_u64(1);
+        let table = payoff_table(&config, &registry, &mut seeds).await;
+        assert_eq!(table, vec![vec![3.5, 0.5], vec![5.0, 1.5]]);
+    }
+}
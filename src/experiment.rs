@@ -0,0 +1,351 @@
+//! Experiment bundles, to hand a run to someone else
+//!
+//! An [`Experiment`] is one JSON file holding a config, the seed to run it
+//! with, and every file its participants load strategies from: scripts,
+//! WebAssembly modules, native plugins, and lookup tables.
+//! `export-experiment` writes one, and `import-experiment` unpacks it into
+//! a directory with the config's strategies pointed at the files unpacked
+//! beside it, so that running the unpacked config plays the same run again.
+//!
+//! A native plugin only loads on the platform it was built for, and a
+//! remote strategy is only named, not bundled.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::config::SimConfig;
+use crate::schema::{self, SchemaError};
+
+/// The schemes whose location is the path of a file to load
+pub const FILE_SCHEMES: [&str; 4] = ["script", "wasm", "plugin", "lookup"];
+
+/// Where bundled files are unpacked, under the directory imported into
+pub const FILES_DIR: &str = "files";
+
+/// What the unpacked config is called, in the directory imported into
+pub const CONFIG_FILE: &str = "experiment.toml";
+
+/// One file a bundled strategy loads
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BundledFile {
+    /// Where the bundled config looks for it, under the directory the
+    /// bundle is unpacked into
+    pub path: String,
+
+    /// The path it was exported from
+    pub origin: String,
+
+    /// Its bytes, in base64
+    pub contents: String,
+}
+
+/// A config, its seed, and the files its strategies load
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Experiment {
+    /// The config with its seed set, naming the files by their bundled paths
+    pub config: SimConfig,
+    pub seed: u64,
+    pub files: Vec<BundledFile>,
+}
+
+#[derive(Debug)]
+pub enum ExperimentError {
+    Io(PathBuf, io::Error),
+    Parse(SchemaError),
+
+    /// The bundle names a file outside the directory it unpacks into, or
+    /// holds one that isn't base64
+    Malformed(String),
+}
+
+impl fmt::Display for ExperimentError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExperimentError::Io(path, e) => write!(f, "unable to access {}: {}", path.display(), e),
+            ExperimentError::Parse(e) => write!(f, "unable to parse experiment: {}", e),
+            ExperimentError::Malformed(e) => write!(f, "malformed experiment: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ExperimentError {}
+
+/// Every file `strategy` loads, with where it is named in `strategy`, in
+/// the order named
+///
+/// A file is named as the location of one of the [`FILE_SCHEMES`], either
+/// the whole name or an argument of a combinator, as in
+/// `generous(0.1, script:soft.rhai)`.
+fn references(strategy: &str) -> Vec<(usize, usize)> {
+    let mut found = Vec::new();
+    for (start, _) in strategy.char_indices() {
+        let at_boundary = strategy[..start]
+            .chars()
+            .next_back()
+            .is_none_or(|c| c == '(' || c == ',' || c.is_whitespace());
+        if !at_boundary {
+            continue;
+        }
+        let rest = &strategy[start..];
+        let location = FILE_SCHEMES.iter().find_map(|scheme| {
+            rest.strip_prefix(scheme)
+                .and_then(|rest| rest.strip_prefix(':'))
+        });
+        if let Some(location) = location {
+            let from = strategy.len() - location.len();
+            let end = location.find([')', ',']).unwrap_or(location.len());
+            let path = location[..end].trim_end();
+            if !path.is_empty() {
+                found.push((from, from + path.len()));
+            }
+        }
+    }
+    found
+}
+
+/// The paths of the files `strategy` loads, in the order named
+pub fn referenced_files(strategy: &str) -> Vec<&str> {
+    references(strategy)
+        .into_iter()
+        .map(|(start, end)| &strategy[start..end])
+        .collect()
+}
+
+/// `strategy` with every file it loads renamed by `rename`
+fn relocate(strategy: &str, rename: &mut dyn FnMut(&str) -> String) -> String {
+    let mut relocated = String::new();
+    let mut last = 0;
+    for (start, end) in references(strategy) {
+        relocated.push_str(&strategy[last..start]);
+        relocated.push_str(&rename(&strategy[start..end]));
+        last = end;
+    }
+    relocated.push_str(&strategy[last..]);
+    relocated
+}
+
+/// Every strategy name of `config`, its participants' and their swaps'
+fn strategies(config: &mut SimConfig) -> impl Iterator<Item = &mut String> {
+    config.participants.iter_mut().flat_map(|participant| {
+        let swap = participant.swap.as_mut().map(|swap| &mut swap.strategy);
+        std::iter::once(&mut participant.strategy).chain(swap)
+    })
+}
+
+impl Experiment {
+    /// Bundle `config` to run with `seed`, reading every file its
+    /// strategies load
+    pub fn export(config: &SimConfig, seed: u64) -> Result<Experiment, ExperimentError> {
+        let mut config = config.clone();
+        config.seed = Some(seed);
+        let mut bundled: BTreeMap<String, String> = BTreeMap::new();
+        let mut files = Vec::new();
+        let mut failure = None;
+        for strategy in strategies(&mut config) {
+            *strategy = relocate(strategy, &mut |origin: &str| {
+                if let Some(path) = bundled.get(origin) {
+                    return path.clone();
+                }
+                let name = Path::new(origin)
+                    .file_name()
+                    .map_or_else(|| "file".into(), |name| name.to_string_lossy());
+                // numbered, so that files of the same name don't collide
+                let path = format!("{}/{}-{}", FILES_DIR, files.len() + 1, name);
+                match fs::read(origin) {
+                    Ok(bytes) => files.push(BundledFile {
+                        path: path.clone(),
+                        origin: origin.to_owned(),
+                        contents: base64::encode(bytes),
+                    }),
+                    Err(e) => failure = failure.take().or(Some((origin.into(), e))),
+                }
+                bundled.insert(origin.to_owned(), path.clone());
+                path
+            });
+        }
+        if let Some((path, e)) = failure {
+            return Err(ExperimentError::Io(path, e));
+        }
+        Ok(Experiment {
+            config,
+            seed,
+            files,
+        })
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Experiment, ExperimentError> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path).map_err(|e| ExperimentError::Io(path.to_owned(), e))?;
+        schema::from_str(&text).map_err(ExperimentError::Parse)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), ExperimentError> {
+        let path = path.as_ref();
+        let text = schema::to_string_pretty(self);
+        fs::write(path, text + "\n").map_err(|e| ExperimentError::Io(path.to_owned(), e))
+    }
+
+    /// Write the bundled files and the config under `dir`, with the config's
+    /// strategies loading the files from there, and return the config's path
+    pub fn unpack(&self, dir: &Path) -> Result<PathBuf, ExperimentError> {
+        let io = |path: &Path| {
+            let path = path.to_owned();
+            move |e| ExperimentError::Io(path, e)
+        };
+        let files = dir.join(FILES_DIR);
+        fs::create_dir_all(&files).map_err(io(&files))?;
+        for file in &self.files {
+            // a bundle from someone else writes nowhere but its own directory
+            let name = file
+                .path
+                .strip_prefix(FILES_DIR)
+                .and_then(|name| name.strip_prefix('/'))
+                .filter(|name| {
+                    !name.is_empty() && !name.contains(['/', '\\']) && *name != ".." && *name != "."
+                })
+                .ok_or_else(|| {
+                    ExperimentError::Malformed(format!("{} is not under {}/", file.path, FILES_DIR))
+                })?;
+            let bytes = base64::decode(&file.contents)
+                .map_err(|e| ExperimentError::Malformed(format!("{}: {}", file.path, e)))?;
+            let path = files.join(name);
+            fs::write(&path, bytes).map_err(io(&path))?;
+        }
+
+        let mut config = self.config.clone();
+        config.seed = Some(self.seed);
+        for strategy in strategies(&mut config) {
+            *strategy = relocate(strategy, &mut |path: &str| {
+                dir.join(path).to_string_lossy().into_owned()
+            });
+        }
+        let text =
+            toml::to_string(&config).map_err(|e| ExperimentError::Malformed(e.to_string()))?;
+        let path = dir.join(CONFIG_FILE);
+        fs::write(&path, text).map_err(io(&path))?;
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ParticipantConfig;
+
+    #[test]
+    fn files_are_found_wherever_a_strategy_is_named() {
+        assert_eq!(referenced_files("script:a/b.rhai"), vec!["a/b.rhai"]);
+        assert_eq!(
+            referenced_files("generous(0.1, wasm:x.wasm)"),
+            vec!["x.wasm"]
+        );
+        assert_eq!(
+            referenced_files("mix(0.5, lookup:t.json, plugin:lib.so)"),
+            vec!["t.json", "lib.so"]
+        );
+        assert!(referenced_files("tit-for-tat").is_empty());
+        assert!(referenced_files("remote:localhost:7000").is_empty());
+        assert!(referenced_files("fsm:0C1D").is_empty());
+        assert_eq!(
+            relocate("generous(0.1, script:a.rhai)", &mut |path| format!(
+                "files/{}",
+                path
+            )),
+            "generous(0.1, script:files/a.rhai)"
+        );
+    }
+
+    #[test]
+    fn an_exported_experiment_unpacks_to_the_same_run() {
+        let dir = std::env::temp_dir().join(format!("actoripd-experiment-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let script = dir.join("soft.rhai");
+        fs::write(&script, "fn choose(history) { \"C\" }\n").unwrap();
+        let script = script.to_string_lossy().into_owned();
+
+        let mut swapping = ParticipantConfig::new("swapping", "grim");
+        swapping.swap = Some(crate::config::SwapConfig {
+            round: 5,
+            strategy: format!("script:{}", script),
+            parameters: Default::default(),
+            reset: false,
+        });
+        let config = SimConfig {
+            iterations: 10,
+            participants: vec![
+                ParticipantConfig::new("soft", &format!("generous(0.1, script:{})", script)),
+                swapping,
+                ParticipantConfig::new("tft", "tit-for-tat"),
+            ],
+            ..SimConfig::default()
+        };
+        let experiment = Experiment::export(&config, 42).unwrap();
+        // the script is bundled once, under its bundled name
+        assert_eq!(experiment.files.len(), 1);
+        assert_eq!(experiment.files[0].path, "files/1-soft.rhai");
+        assert_eq!(
+            experiment.config.participants[0].strategy,
+            "generous(0.1, script:files/1-soft.rhai)"
+        );
+        let bundle = dir.join("bundle.json");
+        experiment.save(&bundle).unwrap();
+
+        let unpacked = dir.join("unpacked");
+        let path = Experiment::load(&bundle)
+            .unwrap()
+            .unpack(&unpacked)
+            .unwrap();
+        let imported = SimConfig::load(&path).unwrap();
+        assert_eq!(imported.seed, Some(42));
+        let file = unpacked.join("files/1-soft.rhai");
+        assert_eq!(
+            imported.participants[0].strategy,
+            format!("generous(0.1, script:{})", file.display())
+        );
+        assert_eq!(
+            imported.participants[1].swap.as_ref().unwrap().strategy,
+            format!("script:{}", file.display())
+        );
+        assert_eq!(
+            fs::read_to_string(&file).unwrap(),
+            fs::read_to_string(&script).unwrap()
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn bundles_only_write_under_their_own_directory() {
+        let experiment = Experiment {
+            config: SimConfig::default(),
+            seed: 1,
+            files: vec![BundledFile {
+                path: "files/../../escape".to_owned(),
+                origin: "escape".to_owned(),
+                contents: String::new(),
+            }],
+        };
+        let dir = std::env::temp_dir().join(format!("actoripd-escape-{}", std::process::id()));
+        assert!(matches!(
+            experiment.unpack(&dir),
+            Err(ExperimentError::Malformed(_))
+        ));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn missing_files_are_refused() {
+        let config = SimConfig {
+            participants: vec![ParticipantConfig::new("gone", "script:no/such/file.rhai")],
+            ..SimConfig::default()
+        };
+        assert!(matches!(
+            Experiment::export(&config, 1),
+            Err(ExperimentError::Io(..))
+        ));
+    }
+}
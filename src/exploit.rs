@@ -0,0 +1,454 @@
+//! How much a memory-one opponent can exploit a strategy
+//!
+//! [`exploit`] tries every deterministic memory-one strategy against a
+//! target: each of the 16 ways to answer the last round's outcome, with
+//! either first move. Against a target that only looks at the last round,
+//! the best of these is the best response of any memory-one strategy, since
+//! a mixed strategy scores an average of the pure ones it mixes.
+//!
+//! A target built as a memory-one strategy (`memory-one`, `extort`,
+//! `zd-gtft`, or `equalizer`) is scored exactly: the chance of each outcome
+//! is followed round by round for the config's iterations, with its noise
+//! and discount. Any other target, or a config whose matches don't end at
+//! a fixed length, whose players can't be modelled by their last round
+//! alone, or whose sides are paid differently, is played against each
+//! response `repetitions` times instead.
+//!
+//! A strategy's exploitability is how many points per round its best
+//! response scores beyond the reward for mutual cooperation. It is about 0
+//! for a strategy that can't be pushed below mutual cooperation, such as
+//! Tit-for-Tat, and `T - R` for one that never defects.
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use serde::{Deserialize, Serialize};
+
+use crate::config::{ConfigError, ParticipantConfig, SimConfig};
+use crate::game::{PayoffMatrix, Score};
+use crate::registry::{Parameters, StrategyArgs, StrategyRegistry};
+use crate::termination::TerminationConfig;
+use crate::tournament;
+
+/// Strategies built as [`MemoryOneStrategy`], which report their
+/// probabilities as parameters
+///
+/// [`MemoryOneStrategy`]: crate::strategies::MemoryOneStrategy
+const MEMORY_ONE: [&str; 4] = ["memory-one", "extort", "zd-gtft", "equalizer"];
+
+/// How the responses' scores were found
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Method {
+    /// Expected scores, computed from the target's probabilities
+    Exact,
+
+    /// Mean scores over played matches
+    Simulated,
+}
+
+/// One deterministic memory-one response and how it did
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Response {
+    /// The moves after CC, CD, DC, and DD, own move first, then the first
+    /// move, e.g. `CDCD/C` for Tit-for-Tat
+    pub code: String,
+
+    /// The usual name of the response, if it has one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alias: Option<String>,
+
+    /// Points per round the response scored
+    pub score: f64,
+
+    /// Points per round the target scored against it
+    pub target_score: f64,
+}
+
+/// The memory-one responses to one target, best first
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExploitRecord {
+    pub target: String,
+    pub strategy: String,
+    pub method: Method,
+
+    /// The reward for mutual cooperation, per round
+    pub reward: Score,
+
+    /// How far the best response scores above `reward`, per round
+    pub exploitability: f64,
+
+    /// Every response, highest score first
+    pub responses: Vec<Response>,
+}
+
+impl ExploitRecord {
+    pub fn best(&self) -> &Response {
+        &self.responses[0]
+    }
+}
+
+/// A deterministic memory-one strategy, as `memory-one` plays it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Responder {
+    /// Whether to cooperate after CC, CD, DC, and DD, own move first
+    cooperation: [bool; 4],
+    initial: bool,
+}
+
+impl Responder {
+    fn all() -> Vec<Responder> {
+        (0..32)
+            .map(|bits: u8| Responder {
+                cooperation: [bits & 16 != 0, bits & 8 != 0, bits & 4 != 0, bits & 2 != 0],
+                initial: bits & 1 != 0,
+            })
+            .collect()
+    }
+
+    fn code(&self) -> String {
+        let letter = |c: bool| if c { 'C' } else { 'D' };
+        let moves: String = self.cooperation.iter().map(|&c| letter(c)).collect();
+        format!("{}/{}", moves, letter(self.initial))
+    }
+
+    fn alias(&self) -> Option<&'static str> {
+        match self.code().as_str() {
+            "CCCC/C" => Some("always-cooperate"),
+            "DDDD/D" => Some("always-defect"),
+            "CDCD/C" => Some("tit-for-tat"),
+            "CDCD/D" => Some("suspicious-tit-for-tat"),
+            "CDDC/C" => Some("pavlov"),
+            "CDDD/C" => Some("grim"),
+            _ => None,
+        }
+    }
+
+    fn participant(&self) -> ParticipantConfig {
+        let probability = |c: bool| if c { 1.0 } else { 0.0 };
+        let names = ["p_cc", "p_cd", "p_dc", "p_dd"];
+        let mut participant = ParticipantConfig::new(&self.code(), "memory-one");
+        participant.parameters = names
+            .iter()
+            .zip(&self.cooperation)
+            .map(|(name, &c)| (name.to_string(), probability(c)))
+            .chain(std::iter::once((
+                "initial".to_owned(),
+                probability(self.initial),
+            )))
+            .collect();
+        participant
+    }
+
+    fn response(&self, score: f64, target_score: f64) -> Response {
+        Response {
+            code: self.code(),
+            alias: self.alias().map(str::to_owned),
+            score,
+            target_score,
+        }
+    }
+}
+
+/// The target's cooperation probabilities after CC, CD, DC, and DD, own
+/// move first, and in the first round, if it is memory-one
+fn probabilities(parameters: &Parameters) -> Option<([f64; 4], f64)> {
+    let p = |name: &str| parameters.get(name).copied();
+    Some((
+        [p("p_cc")?, p("p_cd")?, p("p_dc")?, p("p_dd")?],
+        p("initial")?,
+    ))
+}
+
+/// Whether a match in `config` is decided only by each player's answer to
+/// the last round, at a fixed length, with both sides paid alike
+fn markovian(config: &SimConfig) -> bool {
+    matches!(config.termination, TerminationConfig::Fixed)
+        && config.misperception == 0.0
+        && config.reputation.is_none()
+        && !config.cheap_talk
+        && !config.alternating
+        // a target told of each round late answers an older one, and one
+        // shown only some of them can't answer the rest
+        && config.information.delay == 0
+        && config.information.visibility.is_none()
+        && config.red_payoffs.is_none()
+}
+
+/// The response's and target's expected points per round, over `rounds`
+/// rounds with execution `noise` and `discount`
+fn expected_scores(
+    payoffs: &PayoffMatrix,
+    responder: &Responder,
+    target: ([f64; 4], f64),
+    noise: f64,
+    discount: f64,
+    rounds: usize,
+) -> (f64, f64) {
+    // outcomes from the response's side, own move first: CC, CD, DC, DD
+    let own = [
+        payoffs.reward(),
+        payoffs.sucker(),
+        payoffs.temptation(),
+        payoffs.punishment(),
+    ];
+    let theirs = [own[0], own[2], own[1], own[3]];
+    let executed = |p: f64| p * (1.0 - noise) + (1.0 - p) * noise;
+    let certain = |c: bool| if c { 1.0 } else { 0.0 };
+    let (cooperation, initial) = target;
+
+    let first = (executed(certain(responder.initial)), executed(initial));
+    let joint = |(y, x): (f64, f64)| [y * x, y * (1.0 - x), (1.0 - y) * x, (1.0 - y) * (1.0 - x)];
+    let mut outcome = joint(first);
+    let (mut score, mut target_score, mut weight) = (0.0, 0.0, 1.0);
+    for round in 0..rounds {
+        if round > 0 {
+            let mut next = [0.0; 4];
+            for (state, &chance) in outcome.iter().enumerate() {
+                if chance == 0.0 {
+                    continue;
+                }
+                // the target sees the same round with its own move first
+                let mirrored = [0, 2, 1, 3][state];
+                let moves = (
+                    executed(certain(responder.cooperation[state])),
+                    executed(cooperation[mirrored]),
+                );
+                for (n, p) in next.iter_mut().zip(joint(moves)) {
+                    *n += chance * p;
+                }
+            }
+            outcome = next;
+        }
+        for (state, &chance) in outcome.iter().enumerate() {
+            score += weight * chance * own[state];
+            target_score += weight * chance * theirs[state];
+        }
+        weight *= discount;
+    }
+    let rounds = rounds.max(1) as f64;
+    (score / rounds, target_score / rounds)
+}
+
+/// Score every deterministic memory-one response against `target`
+///
+/// Matches follow `config`, whose participants stand in for nothing but
+/// the target's parameters.
+pub async fn exploit(
+    config: &SimConfig,
+    registry: &StrategyRegistry,
+    target: &ParticipantConfig,
+    seed: u64,
+) -> Result<ExploitRecord, ConfigError> {
+    let responders = Responder::all();
+    let mut config = config.clone();
+    config.self_play = false;
+    config.participants = std::iter::once(target.clone())
+        .chain(responders.iter().map(Responder::participant))
+        .collect();
+    config.validate(registry)?;
+
+    let payoffs = config.payoffs();
+    let described = registry
+        .describe(
+            &target.strategy,
+            &StrategyArgs {
+                parameters: &target.parameters,
+                payoffs: &payoffs,
+                seed: 0,
+            },
+        )
+        .map_err(|e| ConfigError::Invalid(e.to_string()))?;
+    // a target told payoffs of its own may answer with other probabilities
+    let exact = probabilities(&described.parameters).filter(|_| {
+        MEMORY_ONE.contains(&described.name.as_str())
+            && markovian(&config)
+            && target.payoffs.is_none()
+    });
+
+    let (method, mut responses): (Method, Vec<Response>) = match exact {
+        Some(probabilities) => {
+            let discount = config.discount.unwrap_or(1.0);
+            let responses = responders
+                .iter()
+                .map(|responder| {
+                    let (score, target_score) = expected_scores(
+                        &payoffs,
+                        responder,
+                        probabilities,
+                        config.noise,
+                        discount,
+                        config.iterations,
+                    );
+                    responder.response(score, target_score)
+                })
+                .collect();
+            (Method::Exact, responses)
+        }
+        None => {
+            // each response is blue against the target, repetitions times
+            let pairs: Vec<(usize, usize)> = (1..=responders.len())
+                .flat_map(|j| std::iter::repeat_n((j, 0), config.repetitions))
+                .collect();
+            let mut seeds = StdRng::seed_from_u64(seed);
+            let reports = tournament::play_pairs(
+                &config,
+                registry,
+                &config.participants,
+                &pairs,
+                &mut seeds,
+                None,
+            )
+            .await;
+            let mut totals = vec![(0.0, 0.0, 0); responders.len()];
+            for (&(j, _), report) in pairs.iter().zip(&reports) {
+                if let Ok(result) = &report.result {
+                    totals[j - 1].0 += result.blue.score;
+                    totals[j - 1].1 += result.red.score;
+                    totals[j - 1].2 += result.rounds;
+                }
+            }
+            let responses = responders
+                .iter()
+                .zip(totals)
+                .map(|(responder, (score, target_score, rounds))| {
+                    let rounds = rounds.max(1) as f64;
+                    responder.response(score / rounds, target_score / rounds)
+                })
+                .collect();
+            (Method::Simulated, responses)
+        }
+    };
+    responses.sort_by(|a, b| {
+        b.score
+            .total_cmp(&a.score)
+            .then_with(|| a.target_score.total_cmp(&b.target_score))
+    });
+    let reward = payoffs.reward();
+    Ok(ExploitRecord {
+        target: target.name.clone(),
+        strategy: target.strategy.clone(),
+        method,
+        reward,
+        exploitability: responses[0].score - reward,
+        responses,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> SimConfig {
+        SimConfig {
+            iterations: 20,
+            ..SimConfig::default()
+        }
+    }
+
+    /// Tit-for-Tat, built as a memory-one strategy
+    fn memory_one_tft() -> ParticipantConfig {
+        let mut target = ParticipantConfig::new("target", "memory-one");
+        target.parameters = [
+            ("p_cc", 1.0),
+            ("p_cd", 0.0),
+            ("p_dc", 1.0),
+            ("p_dd", 0.0),
+            ("initial", 1.0),
+        ]
+        .iter()
+        .map(|&(name, p)| (name.to_owned(), p))
+        .collect();
+        target
+    }
+
+    fn score(record: &ExploitRecord, code: &str) -> f64 {
+        record
+            .responses
+            .iter()
+            .find(|r| r.code == code)
+            .expect("every response")
+            .score
+    }
+
+    #[actix_rt::test]
+    async fn tit_for_tat_cannot_be_exploited_and_a_cooperator_can() {
+        let registry = StrategyRegistry::new();
+        let tft = ParticipantConfig::new("target", "tit-for-tat");
+        let record = exploit(&config(), &registry, &tft, 1).await.unwrap();
+        assert_eq!(record.method, Method::Simulated);
+        assert!(record.exploitability.abs() < 1e-9);
+        assert_eq!(record.responses.len(), 32);
+
+        let allc = ParticipantConfig::new("target", "always-cooperate");
+        let record = exploit(&config(), &registry, &allc, 1).await.unwrap();
+        let payoffs = PayoffMatrix::default();
+        assert!((record.exploitability - (payoffs.temptation() - payoffs.reward())).abs() < 1e-9);
+        assert_eq!(record.best().target_score, payoffs.sucker());
+    }
+
+    #[actix_rt::test]
+    async fn exact_scores_are_those_played() {
+        let registry = StrategyRegistry::new();
+        let exact = exploit(&config(), &registry, &memory_one_tft(), 1)
+            .await
+            .unwrap();
+        assert_eq!(exact.method, Method::Exact);
+        let tft = ParticipantConfig::new("target", "tit-for-tat");
+        let played = exploit(&config(), &registry, &tft, 1).await.unwrap();
+        for response in &played.responses {
+            assert!((score(&exact, &response.code) - response.score).abs() < 1e-9);
+        }
+        assert!((exact.exploitability - played.exploitability).abs() < 1e-9);
+    }
+
+    #[actix_rt::test]
+    async fn a_delayed_target_is_played_rather_than_computed() {
+        let registry = StrategyRegistry::new();
+        let target = memory_one_tft();
+        let mut delayed = config();
+        delayed.information.delay = 1;
+        let record = exploit(&delayed, &registry, &target, 1).await.unwrap();
+        assert_eq!(record.method, Method::Simulated);
+
+        // played, it scores as Tit-for-Tat itself does under the delay
+        let tft = ParticipantConfig::new("target", "tit-for-tat");
+        let played = exploit(&delayed, &registry, &tft, 1).await.unwrap();
+        for response in &played.responses {
+            assert_eq!(score(&record, &response.code), response.score);
+        }
+        // which isn't what the last round alone would give
+        let exact = exploit(&config(), &registry, &target, 1).await.unwrap();
+        assert_eq!(exact.method, Method::Exact);
+        assert!(exact
+            .responses
+            .iter()
+            .any(|r| (score(&record, &r.code) - r.score).abs() > 1e-9));
+    }
+
+    #[actix_rt::test]
+    async fn a_target_shown_only_some_rounds_is_played() {
+        let registry = StrategyRegistry::new();
+        let mut partial = config();
+        partial.information.visibility = Some(0.5);
+        let record = exploit(&partial, &registry, &memory_one_tft(), 1)
+            .await
+            .unwrap();
+        assert_eq!(record.method, Method::Simulated);
+    }
+
+    #[actix_rt::test]
+    async fn a_target_paid_apart_is_played() {
+        let registry = StrategyRegistry::new();
+        let mut red_paid = config();
+        red_paid.red_payoffs = Some(PayoffMatrix::new(4.0, 6.0, 1.0, 0.0));
+        let record = exploit(&red_paid, &registry, &memory_one_tft(), 1)
+            .await
+            .unwrap();
+        assert_eq!(record.method, Method::Simulated);
+
+        let mut target = memory_one_tft();
+        target.payoffs = Some(PayoffMatrix::new(4.0, 6.0, 1.0, 0.0));
+        let record = exploit(&config(), &registry, &target, 1).await.unwrap();
+        assert_eq!(record.method, Method::Simulated);
+    }
+}
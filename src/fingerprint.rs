@@ -0,0 +1,348 @@
+//! Ashlock fingerprints: a strategy's score against a family of probes
+//!
+//! The probe at `(x, y)` cooperates with probability `x`, defects with
+//! probability `y`, and otherwise plays Tit-for-Tat. Where `x + y > 1` it
+//! instead plays the dual, anti-Tit-for-Tat (do the opposite of the
+//! opponent's last action), cooperating with probability `1 - y` and
+//! defecting with probability `1 - x`, so that the whole unit square is
+//! covered. A strategy's average score against every probe on a grid gives
+//! a surface that tells strategies apart, and is similar for strategies that
+//! behave alike, whatever their implementation.
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::Path;
+use tracing::warn;
+
+use crate::config::{ParticipantConfig, SimConfig};
+use crate::game::{Action, PayoffMatrix};
+use crate::randomness::StrategyRng;
+use crate::registry::{Parameters, StrategyRegistry};
+use crate::strategy::{History, Strategy};
+use crate::tournament;
+
+/// The name the probe is registered under, with parameters `x` and `y`
+pub const PROBE: &str = "fingerprint-probe";
+
+/// The probe at one point of the fingerprint
+pub struct Probe {
+    x: f64,
+    y: f64,
+    rng: StrategyRng,
+}
+
+impl Probe {
+    pub fn new(x: f64, y: f64, seed: u64) -> Probe {
+        Probe {
+            x,
+            y,
+            rng: StrategyRng::seed_from_u64(seed),
+        }
+    }
+}
+
+impl Strategy for Probe {
+    fn name(&self) -> &str {
+        PROBE
+    }
+
+    fn choose(&mut self, history: &History) -> Action {
+        let (cooperate, defect, dual) = if self.x + self.y <= 1.0 {
+            (self.x, self.y, false)
+        } else {
+            (1.0 - self.y, 1.0 - self.x, true)
+        };
+        let draw = self.rng.gen::<f64>();
+        if draw < cooperate {
+            Action::COOPERATE
+        } else if draw < cooperate + defect {
+            Action::DEFECT
+        } else {
+            let copied = history.opponent_last_action().unwrap_or(Action::COOPERATE);
+            if dual {
+                copied.flipped()
+            } else {
+                copied
+            }
+        }
+    }
+
+    fn memory_depth(&self) -> Option<usize> {
+        Some(1)
+    }
+
+    fn parameters(&self) -> Parameters {
+        Parameters::from([("x".to_owned(), self.x), ("y".to_owned(), self.y)])
+    }
+}
+
+/// A strategy's average points per round against the probe at `(x, y)`
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct FingerprintPoint {
+    pub x: f64,
+    pub y: f64,
+    pub score: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Fingerprint {
+    pub strategy: String,
+    pub seed: u64,
+
+    /// The spacing of the grid, which runs from 0 to 1 on both axes
+    pub step: f64,
+    pub payoffs: PayoffMatrix,
+
+    /// Row by row, `y` then `x` ascending; points whose match was aborted
+    /// are left out
+    pub points: Vec<FingerprintPoint>,
+}
+
+/// Grid points per axis for a requested `step`, which is rounded so that
+/// the grid ends exactly at 1
+pub fn grid_size(step: f64) -> usize {
+    (1.0 / step).round().max(1.0) as usize + 1
+}
+
+/// Play `subject` against the probe at every point of a grid `step` apart
+///
+/// Each point is one match of the config's length, played concurrently as in
+/// a tournament; the config's participants are ignored. The whole run
+/// replays from `seed`.
+pub async fn fingerprint(
+    config: &SimConfig,
+    registry: &StrategyRegistry,
+    subject: &ParticipantConfig,
+    step: f64,
+    seed: u64,
+) -> Fingerprint {
+    let size = grid_size(step);
+    let coordinate = |i: usize| i as f64 / (size - 1) as f64;
+    let grid: Vec<(f64, f64)> = (0..size * size)
+        .map(|i| (coordinate(i % size), coordinate(i / size)))
+        .collect();
+
+    let mut entrants = vec![subject.clone()];
+    entrants.extend(grid.iter().map(|&(x, y)| {
+        let mut probe = ParticipantConfig::new(&format!("probe({:.3},{:.3})", x, y), PROBE);
+        probe.parameters.insert("x".to_owned(), x);
+        probe.parameters.insert("y".to_owned(), y);
+        probe
+    }));
+    let pairs: Vec<(usize, usize)> = (1..entrants.len()).map(|probe| (0, probe)).collect();
+    let mut seeds = StdRng::seed_from_u64(seed);
+    let reports =
+        tournament::play_pairs(config, registry, &entrants, &pairs, &mut seeds, None).await;
+
+    let points = grid
+        .iter()
+        .zip(reports)
+        .filter_map(|(&(x, y), report)| match report.result {
+            Ok(result) => Some(FingerprintPoint {
+                x,
+                y,
+                score: result.blue.average_score(result.rounds),
+            }),
+            Err(e) => {
+                warn!(x, y, error = %e, "match aborted");
+                None
+            }
+        })
+        .collect();
+    Fingerprint {
+        strategy: subject.strategy.clone(),
+        seed,
+        step: 1.0 / (size - 1) as f64,
+        payoffs: config.payoffs(),
+        points,
+    }
+}
+
+impl Fingerprint {
+    /// One `x,y,score` line per point, after a header
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("x,y,score\n");
+        for point in &self.points {
+            csv.push_str(&format!("{},{},{}\n", point.x, point.y, point.score));
+        }
+        csv
+    }
+
+    /// Draw the surface as a PNG heat map, `x` rightwards and `y` upwards
+    ///
+    /// Colours run from dark purple at the sucker's payoff to yellow at the
+    /// temptation; missing points are left black.
+    pub fn write_png<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        const CELL: usize = 16;
+        let size = grid_size(self.step);
+        let (low, high) = (self.payoffs.sucker(), self.payoffs.temptation());
+        let mut cells = vec![[0u8; 3]; size * size];
+        for point in &self.points {
+            let column = (point.x * (size - 1) as f64).round() as usize;
+            let row = size - 1 - (point.y * (size - 1) as f64).round() as usize;
+            cells[row * size + column] = colour((point.score - low) / (high - low));
+        }
+
+        let side = size * CELL;
+        let mut pixels = Vec::with_capacity(side * (1 + 3 * side));
+        for y in 0..side {
+            // each scanline starts with its filter type, none
+            pixels.push(0);
+            for x in 0..side {
+                pixels.extend_from_slice(&cells[(y / CELL) * size + x / CELL]);
+            }
+        }
+        fs::write(path, png(side as u32, side as u32, &pixels))
+    }
+}
+
+/// A point along a purple-teal-yellow scale, `t` from 0 to 1
+pub(crate) fn colour(t: f64) -> [u8; 3] {
+    const STOPS: [[f64; 3]; 3] = [
+        [68.0, 1.0, 84.0],
+        [33.0, 145.0, 140.0],
+        [253.0, 231.0, 37.0],
+    ];
+    let t = if t.is_finite() {
+        t.clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    let (from, to, t) = if t < 0.5 {
+        (STOPS[0], STOPS[1], t * 2.0)
+    } else {
+        (STOPS[1], STOPS[2], t * 2.0 - 1.0)
+    };
+    let mix = |i: usize| (from[i] + (to[i] - from[i]) * t).round() as u8;
+    [mix(0), mix(1), mix(2)]
+}
+
+/// An 8-bit RGB PNG of `scanlines`, each already prefixed with its filter
+/// byte, stored without compression
+pub(crate) fn png(width: u32, height: u32, scanlines: &[u8]) -> Vec<u8> {
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&width.to_be_bytes());
+    header.extend_from_slice(&height.to_be_bytes());
+    // bit depth 8, colour type RGB, default compression, filtering, no interlace
+    header.extend_from_slice(&[8, 2, 0, 0, 0]);
+
+    // a zlib stream of stored deflate blocks
+    let mut zlib = vec![0x78, 0x01];
+    let mut blocks = scanlines.chunks(u16::MAX as usize).peekable();
+    while let Some(block) = blocks.next() {
+        zlib.push(if blocks.peek().is_none() { 1 } else { 0 });
+        let len = block.len() as u16;
+        zlib.extend_from_slice(&len.to_le_bytes());
+        zlib.extend_from_slice(&(!len).to_le_bytes());
+        zlib.extend_from_slice(block);
+    }
+    zlib.extend_from_slice(&adler32(scanlines).to_be_bytes());
+
+    let mut file = b"\x89PNG\r\n\x1a\n".to_vec();
+    for (kind, data) in [(b"IHDR", &header[..]), (b"IDAT", &zlib[..]), (b"IEND", &[])] {
+        file.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        let start = file.len();
+        file.extend_from_slice(kind);
+        file.extend_from_slice(data);
+        let crc = crc32(&file[start..]);
+        file.extend_from_slice(&crc.to_be_bytes());
+    }
+    file
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                0xedb8_8320 ^ (crc >> 1)
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+fn adler32(bytes: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in bytes {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn of(strategy: &str, rounds: usize, step: f64) -> Fingerprint {
+        let config = SimConfig {
+            iterations: rounds,
+            ..SimConfig::default()
+        };
+        let subject = ParticipantConfig::new("subject", strategy);
+        fingerprint(&config, &StrategyRegistry::new(), &subject, step, 1).await
+    }
+
+    fn at(fingerprint: &Fingerprint, x: f64, y: f64) -> f64 {
+        fingerprint
+            .points
+            .iter()
+            .find(|p| p.x == x && p.y == y)
+            .expect("a point of the grid")
+            .score
+    }
+
+    #[actix_rt::test]
+    async fn always_defect_scores_its_punishment_plus_what_x_gives_it() {
+        let fingerprint = of("always-defect", 2000, 0.5).await;
+        assert_eq!(fingerprint.points.len(), 9);
+        // the probe cooperates a share x of the time, on either side of the
+        // diagonal, so the defector scores P + x (T - P)
+        let payoffs = PayoffMatrix::default();
+        for point in &fingerprint.points {
+            let expected =
+                payoffs.punishment() + point.x * (payoffs.temptation() - payoffs.punishment());
+            assert!(
+                (point.score - expected).abs() < 0.1,
+                "{} at ({}, {})",
+                point.score,
+                point.x,
+                point.y
+            );
+        }
+    }
+
+    #[actix_rt::test]
+    async fn tit_for_tat_at_the_corners() {
+        let fingerprint = of("tit-for-tat", 10, 1.0).await;
+        // Tit-for-Tat itself, a cooperator, and a defector
+        assert_eq!(at(&fingerprint, 0.0, 0.0), 3.0);
+        assert_eq!(at(&fingerprint, 1.0, 0.0), 3.0);
+        assert_eq!(at(&fingerprint, 0.0, 1.0), 1.9);
+        // past the diagonal the probe is anti-Tit-for-Tat, which opens with
+        // a defection and cycles through SPTR against Tit-for-Tat
+        assert_eq!(at(&fingerprint, 1.0, 1.0), 2.3);
+    }
+
+    #[test]
+    fn grids_end_at_one_and_images_are_well_formed() {
+        assert_eq!(grid_size(0.1), 11);
+        assert_eq!(grid_size(0.3), 4);
+        assert_eq!(grid_size(2.0), 2);
+        assert_eq!(crc32(b"IEND"), 0xae42_6082);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+        let image = png(1, 1, &[0, 255, 0, 0]);
+        assert!(image.starts_with(b"\x89PNG\r\n\x1a\n"));
+        assert!(image.ends_with(&[0xae, 0x42, 0x60, 0x82]));
+        assert_eq!(colour(0.0), [68, 1, 84]);
+        assert_eq!(colour(f64::NAN), colour(0.0));
+        assert_eq!(colour(2.0), [253, 231, 37]);
+    }
+}
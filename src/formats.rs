@@ -0,0 +1,471 @@
+//! Knockout and Swiss tournaments, besides the round robin
+//!
+//! Both formats play in rounds, each round's matches concurrently, and pair
+//! the next round from the results so far. Participants are seeded best
+//! first, by prior [`Ratings`] if there are any and otherwise in config
+//! order; a match whose scores are level goes to the better seed.
+//!
+//! - [`elimination`]: a single-elimination bracket. Seeds are placed so the
+//!   top two can only meet in the final, and when the field isn't a power
+//!   of two the top seeds get byes through the first round.
+//! - [`swiss`]: a fixed number of rounds, each pairing players on the same
+//!   points with someone they haven't met yet. A win is worth a point, a
+//!   draw half, and with an odd field the lowest-placed player still
+//!   without a bye sits out for a point. Once no pairing is left without a
+//!   rematch, as in a long tournament with a small field, players are
+//!   paired from the top regardless.
+//!
+//! Each pairing is played once, so `repetitions` and `self_play` only apply
+//! to round robins. If `red_payoffs` pays the sides differently, a pairing
+//! is played as two legs instead, the sides swapped in the second, and
+//! decided on the two legs' scores together.
+
+use actix::prelude::*;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::BTreeSet;
+
+use crate::config::SimConfig;
+use crate::game::Score;
+use crate::interrupt;
+use crate::rating::Ratings;
+use crate::referee::Report;
+use crate::registry::StrategyRegistry;
+use crate::tournament::{play_pairs, seatings, MatchReport};
+
+/// Where a participant finished in an elimination or Swiss tournament
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Placing {
+    pub name: String,
+    pub strategy: String,
+
+    /// 1 for the top seed
+    pub seed: usize,
+
+    /// In a Swiss tournament, a point per win or bye and half per draw; in
+    /// an elimination, rounds survived, byes included
+    pub points: f64,
+    pub wins: usize,
+    pub draws: usize,
+    pub losses: usize,
+    pub byes: usize,
+
+    /// Points from the matches themselves, the Swiss tie-break
+    pub score: Score,
+}
+
+/// The participants of `config` by index, best seed first: highest rating
+/// in `ratings` if given, then config order
+pub fn seeding(config: &SimConfig, ratings: Option<&Ratings>) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..config.participants.len()).collect();
+    if let Some(ratings) = ratings {
+        // a stable sort keeps config order among equal ratings
+        order.sort_by(|&a, &b| {
+            let rating = |i: usize| ratings.get(&config.participants[i].name).rating;
+            rating(b).total_cmp(&rating(a))
+        });
+    }
+    order
+}
+
+/// Rounds enough for a Swiss tournament of `participants` to have a single
+/// player with every win
+pub fn swiss_rounds(participants: usize) -> usize {
+    (participants.next_power_of_two().trailing_zeros() as usize).max(1)
+}
+
+/// Results so far, indexed like the config's participants
+struct Table<'a> {
+    seeding: &'a [usize],
+
+    /// Each participant's place in `seeding`
+    ranks: Vec<usize>,
+    placings: Vec<Placing>,
+}
+
+impl<'a> Table<'a> {
+    fn new(config: &'a SimConfig, seeding: &'a [usize]) -> Table<'a> {
+        let mut ranks = vec![0; config.participants.len()];
+        for (rank, &i) in seeding.iter().enumerate() {
+            ranks[i] = rank;
+        }
+        let placings = config
+            .participants
+            .iter()
+            .zip(&ranks)
+            .map(|(participant, rank)| Placing {
+                name: participant.name.clone(),
+                strategy: participant.strategy.clone(),
+                seed: rank + 1,
+                points: 0.0,
+                wins: 0,
+                draws: 0,
+                losses: 0,
+                byes: 0,
+                score: 0.0,
+            })
+            .collect();
+        Table {
+            seeding,
+            ranks,
+            placings,
+        }
+    }
+
+    /// Tally the pairing of `blue` and `red` from its `legs`, the first
+    /// seated that way round and any second the other, returning the winner
+    /// on score, or none if it was drawn or a leg was aborted
+    fn record(&mut self, blue: usize, red: usize, legs: &[MatchReport]) -> Option<usize> {
+        let (mut blue_score, mut red_score) = (0.0, 0.0);
+        for (k, leg) in legs.iter().enumerate() {
+            let result = leg.result.as_ref().ok()?;
+            let (first, second) = (result.blue.score, result.red.score);
+            let (blue_leg, red_leg) = if k == 0 {
+                (first, second)
+            } else {
+                (second, first)
+            };
+            blue_score += blue_leg;
+            red_score += red_leg;
+        }
+        self.placings[blue].score += blue_score;
+        self.placings[red].score += red_score;
+        match blue_score.total_cmp(&red_score) {
+            Ordering::Greater => {
+                self.placings[blue].wins += 1;
+                self.placings[red].losses += 1;
+                Some(blue)
+            }
+            Ordering::Less => {
+                self.placings[red].wins += 1;
+                self.placings[blue].losses += 1;
+                Some(red)
+            }
+            Ordering::Equal => {
+                self.placings[blue].draws += 1;
+                self.placings[red].draws += 1;
+                None
+            }
+        }
+    }
+
+    fn better_seed(&self, a: usize, b: usize) -> usize {
+        if self.ranks[a] < self.ranks[b] {
+            a
+        } else {
+            b
+        }
+    }
+
+    /// The seeds in order of points, then score if `by_score`, then seed
+    fn standings(&self, by_score: bool) -> Vec<usize> {
+        let mut order = self.seeding.to_vec();
+        order.sort_by(|&a, &b| {
+            let (a_placing, b_placing) = (&self.placings[a], &self.placings[b]);
+            b_placing
+                .points
+                .total_cmp(&a_placing.points)
+                .then_with(|| {
+                    if by_score {
+                        b_placing.score.total_cmp(&a_placing.score)
+                    } else {
+                        Ordering::Equal
+                    }
+                })
+                .then_with(|| self.ranks[a].cmp(&self.ranks[b]))
+        });
+        order
+    }
+
+    fn into_placings(self, by_score: bool) -> Vec<Placing> {
+        let order = self.standings(by_score);
+        let mut placings: Vec<Option<Placing>> = self.placings.into_iter().map(Some).collect();
+        order
+            .into_iter()
+            .filter_map(|i| placings[i].take())
+            .collect()
+    }
+}
+
+/// Seed numbers, from 0, in bracket order for a bracket of `size`, a power
+/// of two: each neighbouring pair meets in the first round
+fn bracket_order(size: usize) -> Vec<usize> {
+    let mut order = vec![0];
+    while order.len() < size {
+        let seeds = order.len() * 2;
+        order = order
+            .iter()
+            .flat_map(|&seed| [seed, seeds - 1 - seed])
+            .collect();
+    }
+    order
+}
+
+/// The first round's slots for `seeding`, in bracket order: each
+/// neighbouring pair meets, and a seed beside an empty slot has a bye
+fn bracket(seeding: &[usize]) -> Vec<Option<usize>> {
+    bracket_order(seeding.len().next_power_of_two())
+        .into_iter()
+        .map(|seed| seeding.get(seed).copied())
+        .collect()
+}
+
+/// Pairs in the same order, as the met set keeps them
+fn pairing(a: usize, b: usize) -> (usize, usize) {
+    (a.min(b), a.max(b))
+}
+
+/// Pair the players in `order` from the top, each with the highest-placed
+/// player below it they haven't met, going back on a pairing when it would
+/// leave the rest with a rematch
+///
+/// If every way of pairing them has a rematch, each is paired with the next
+/// player down they haven't met, and the last ones left play again.
+fn swiss_pairs(order: &[usize], met: &BTreeSet<(usize, usize)>) -> Vec<(usize, usize)> {
+    fn without_rematches(
+        order: &[usize],
+        met: &BTreeSet<(usize, usize)>,
+    ) -> Option<Vec<(usize, usize)>> {
+        let Some((&blue, rest)) = order.split_first() else {
+            return Some(Vec::new());
+        };
+        rest.iter().enumerate().find_map(|(k, &red)| {
+            if met.contains(&pairing(blue, red)) {
+                return None;
+            }
+            let mut others = rest.to_vec();
+            others.remove(k);
+            let mut pairs = without_rematches(&others, met)?;
+            pairs.insert(0, (blue, red));
+            Some(pairs)
+        })
+    }
+
+    without_rematches(order, met).unwrap_or_else(|| {
+        let mut order = order.to_vec();
+        let mut pairs = Vec::new();
+        while !order.is_empty() {
+            let blue = order.remove(0);
+            let next = order
+                .iter()
+                .position(|&red| !met.contains(&pairing(blue, red)))
+                .unwrap_or(0);
+            pairs.push((blue, order.remove(next)));
+        }
+        pairs
+    })
+}
+
+/// Play a single-elimination bracket of `config`'s participants, seeded by
+/// `seeding` from [`seeding`]
+///
+/// Returns every match played, round by round, and the placings: the
+/// champion first, then everyone else by how far they got. An interrupted
+/// tournament stops after its current round.
+pub async fn elimination(
+    config: &SimConfig,
+    registry: &StrategyRegistry,
+    seed: u64,
+    seeding: &[usize],
+    reporter: Option<Recipient<Report>>,
+) -> (Vec<MatchReport>, Vec<Placing>) {
+    let mut seeds = StdRng::seed_from_u64(seed);
+    let mut table = Table::new(config, seeding);
+    let mut reports = Vec::new();
+    let mut slots = bracket(seeding);
+
+    while slots.len() > 1 && !interrupt::interrupted() {
+        let pairs: Vec<(usize, usize)> = slots
+            .chunks(2)
+            .filter_map(|slot| match *slot {
+                [Some(blue), Some(red)] => Some((blue, red)),
+                _ => None,
+            })
+            .collect();
+        let round = play_pairs(
+            config,
+            registry,
+            &config.participants,
+            &seatings(config, pairs.clone()),
+            &mut seeds,
+            reporter.clone(),
+        )
+        .await;
+        let legs = round.len() / pairs.len().max(1);
+        let mut played = pairs.iter().zip(round.chunks(legs.max(1)));
+        slots = slots
+            .chunks(2)
+            .map(|slot| {
+                let winner = match *slot {
+                    [Some(_), Some(_)] => {
+                        let (&(blue, red), legs) = played.next().expect("paired slot");
+                        table
+                            .record(blue, red, legs)
+                            .unwrap_or_else(|| table.better_seed(blue, red))
+                    }
+                    [Some(alone), None] | [None, Some(alone)] => {
+                        table.placings[alone].byes += 1;
+                        alone
+                    }
+                    _ => return None,
+                };
+                table.placings[winner].points += 1.0;
+                Some(winner)
+            })
+            .collect();
+        reports.extend(round);
+    }
+    (reports, table.into_placings(false))
+}
+
+/// Play `rounds` rounds of a Swiss tournament of `config`'s participants,
+/// seeded by `seeding` from [`seeding`]
+///
+/// Returns every match played, round by round, and the placings: most
+/// points first, then most match score, then best seed. An interrupted
+/// tournament stops after its current round.
+pub async fn swiss(
+    config: &SimConfig,
+    registry: &StrategyRegistry,
+    seed: u64,
+    seeding: &[usize],
+    rounds: usize,
+    reporter: Option<Recipient<Report>>,
+) -> (Vec<MatchReport>, Vec<Placing>) {
+    let mut seeds = StdRng::seed_from_u64(seed);
+    let mut table = Table::new(config, seeding);
+    let mut reports = Vec::new();
+    let mut met = BTreeSet::new();
+
+    for _ in 0..rounds {
+        if interrupt::interrupted() {
+            break;
+        }
+        let mut order = table.standings(true);
+        if order.len() % 2 == 1 {
+            let sits_out = order
+                .iter()
+                .rposition(|&i| table.placings[i].byes == 0)
+                .unwrap_or(order.len() - 1);
+            let i = order.remove(sits_out);
+            table.placings[i].byes += 1;
+            table.placings[i].points += 1.0;
+        }
+        let pairs = swiss_pairs(&order, &met);
+        met.extend(pairs.iter().map(|&(blue, red)| pairing(blue, red)));
+        let round = play_pairs(
+            config,
+            registry,
+            &config.participants,
+            &seatings(config, pairs.clone()),
+            &mut seeds,
+            reporter.clone(),
+        )
+        .await;
+        let legs = round.len() / pairs.len().max(1);
+        for (&(blue, red), legs) in pairs.iter().zip(round.chunks(legs.max(1))) {
+            let played = legs.iter().all(|leg| leg.result.is_ok());
+            match table.record(blue, red, legs) {
+                Some(winner) => table.placings[winner].points += 1.0,
+                None if played => {
+                    table.placings[blue].points += 0.5;
+                    table.placings[red].points += 0.5;
+                }
+                None => {}
+            }
+        }
+        reports.extend(round);
+    }
+    (reports, table.into_placings(true))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ParticipantConfig;
+    use crate::game::PayoffMatrix;
+
+    #[test]
+    fn the_top_seeds_meet_only_in_the_final() {
+        assert_eq!(bracket_order(8), [0, 7, 3, 4, 1, 6, 2, 5]);
+        // every first-round pair adds up to the bracket size less one
+        for pair in bracket_order(16).chunks(2) {
+            assert_eq!(pair[0] + pair[1], 15);
+        }
+    }
+
+    #[test]
+    fn the_top_seeds_get_the_byes() {
+        let byes = |size: usize| -> Vec<usize> {
+            let seeding: Vec<usize> = (0..size).collect();
+            bracket(&seeding)
+                .chunks(2)
+                .filter_map(|slot| match *slot {
+                    [Some(alone), None] | [None, Some(alone)] => Some(alone),
+                    _ => None,
+                })
+                .collect()
+        };
+        assert_eq!(byes(5), [0, 1, 2]);
+        assert_eq!(byes(6), [0, 1]);
+        assert_eq!(byes(7), [0]);
+        assert!(byes(8).is_empty());
+        // a bye is never a slot with nobody in it
+        assert_eq!(bracket(&[0, 1, 2, 3, 4]).iter().flatten().count(), 5);
+    }
+
+    #[test]
+    fn swiss_pairing_avoids_rematches_when_it_can() {
+        let order = [0, 1, 2, 3];
+        // pairing the top two leaves 2 and 3, who have met
+        let met: BTreeSet<_> = [(2, 3)].iter().copied().collect();
+        assert_eq!(swiss_pairs(&order, &met), [(0, 2), (1, 3)]);
+
+        let fresh = BTreeSet::new();
+        assert_eq!(swiss_pairs(&order, &fresh), [(0, 1), (2, 3)]);
+    }
+
+    #[test]
+    fn swiss_pairing_allows_a_rematch_when_it_must() {
+        // everyone has met everyone but 0 and 1
+        let met: BTreeSet<_> = [(0, 2), (0, 3), (1, 2), (1, 3), (2, 3)]
+            .iter()
+            .copied()
+            .collect();
+        assert_eq!(swiss_pairs(&[0, 1, 2, 3], &met), [(0, 1), (2, 3)]);
+    }
+
+    #[actix_rt::test]
+    async fn sides_paid_apart_play_two_legs_seated_both_ways() {
+        let registry = StrategyRegistry::new();
+        let mut config = SimConfig {
+            iterations: 10,
+            participants: vec![
+                ParticipantConfig::new("allc", "always-cooperate"),
+                ParticipantConfig::new("alld", "always-defect"),
+            ],
+            ..SimConfig::default()
+        };
+        let (reports, placings) = elimination(&config, &registry, 1, &[0, 1], None).await;
+        assert_eq!(reports.len(), 1);
+        assert_eq!(placings[0].score, 40.0);
+
+        // red is paid 6 for a cooperator's defeat, where blue is paid 4
+        config.red_payoffs = Some(PayoffMatrix::new(4.0, 6.0, 1.0, 0.0));
+        let (reports, placings) = elimination(&config, &registry, 1, &[0, 1], None).await;
+        let seated: Vec<(&str, &str)> = reports
+            .iter()
+            .map(|r| (r.blue.as_str(), r.red.as_str()))
+            .collect();
+        assert_eq!(seated, vec![("allc", "alld"), ("alld", "allc")]);
+        assert_eq!(placings[0].name, "alld");
+        assert_eq!((placings[0].score, placings[1].score), (100.0, 10.0));
+
+        let (reports, placings) = swiss(&config, &registry, 1, &[0, 1], 2, None).await;
+        assert_eq!(reports.len(), 4);
+        assert_eq!((placings[0].wins, placings[1].losses), (2, 2));
+        assert_eq!(placings[0].score, 200.0);
+    }
+}
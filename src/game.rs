@@ -0,0 +1,885 @@
+use serde::de::{self, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+
+/// The most actions a game may have
+pub const MAX_ACTIONS: usize = 6;
+
+/// A move, one of the actions of the game being played, numbered from 0
+///
+/// The Prisoner's Dilemma and the other 2x2 games have two,
+/// [`Action::COOPERATE`] and [`Action::DEFECT`], and the optional Prisoner's
+/// Dilemma adds [`Action::ABSTAIN`]. A game given by an n×n table, see
+/// [`PayoffMatrix::with_table`], has n: the first two are still cooperating
+/// and defecting, by convention the most and least cooperative, and the
+/// rest are numbered on from 2.
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Action(u8);
+
+impl Action {
+    pub const COOPERATE: Action = Action(0);
+    pub const DEFECT: Action = Action(1);
+
+    /// Stay out of the round, the third action of the optional Prisoner's
+    /// Dilemma; see [`PayoffMatrix::with_loner`]
+    pub const ABSTAIN: Action = Action(2);
+
+    /// The action numbered `index`, which must be below [`MAX_ACTIONS`]
+    pub fn new(index: usize) -> Action {
+        assert!(
+            index < MAX_ACTIONS,
+            "action {} of at most {}",
+            index,
+            MAX_ACTIONS
+        );
+        Action(index as u8)
+    }
+
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+
+    /// The other of cooperating and defecting; any other action has none,
+    /// so noise leaves it alone
+    pub fn flipped(self) -> Action {
+        match self {
+            Action::COOPERATE => Action::DEFECT,
+            Action::DEFECT => Action::COOPERATE,
+            other => other,
+        }
+    }
+
+    /// The action's letter in transcripts: C, D, and A, then the digits of
+    /// the actions numbered from 3
+    pub fn letter(self) -> char {
+        match self {
+            Action::COOPERATE => 'C',
+            Action::DEFECT => 'D',
+            Action::ABSTAIN => 'A',
+            Action(index) => char::from(b'0' + index),
+        }
+    }
+
+    /// The action whose [`letter`](Action::letter) is `letter`
+    pub fn from_letter(letter: char) -> Option<Action> {
+        match letter {
+            'C' => Some(Action::COOPERATE),
+            'D' => Some(Action::DEFECT),
+            'A' => Some(Action::ABSTAIN),
+            digit => match digit.to_digit(10) {
+                Some(index) if (3..MAX_ACTIONS as u32).contains(&index) => {
+                    Some(Action(index as u8))
+                }
+                _ => None,
+            },
+        }
+    }
+
+    fn keyword(self) -> Option<&'static str> {
+        match self {
+            Action::COOPERATE => Some("COOPERATE"),
+            Action::DEFECT => Some("DEFECT"),
+            Action::ABSTAIN => Some("ABSTAIN"),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Action::COOPERATE => write!(f, "Cooperate"),
+            Action::DEFECT => write!(f, "Defect"),
+            Action::ABSTAIN => write!(f, "Abstain"),
+            Action(index) => write!(f, "Action {}", index),
+        }
+    }
+}
+
+impl fmt::Debug for Action {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.keyword() {
+            Some(keyword) => write!(f, "{}", keyword),
+            None => write!(f, "Action({})", self.0),
+        }
+    }
+}
+
+/// The first three actions by name, as they have always been written, and
+/// any others by number
+impl Serialize for Action {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.keyword() {
+            Some(keyword) => serializer.serialize_str(keyword),
+            None => serializer.serialize_u8(self.0),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Action {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Action, D::Error> {
+        struct ActionVisitor;
+
+        impl<'de> Visitor<'de> for ActionVisitor {
+            type Value = Action;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "COOPERATE, DEFECT, ABSTAIN, or an action's number")
+            }
+
+            fn visit_str<E: de::Error>(self, s: &str) -> Result<Action, E> {
+                match s {
+                    "COOPERATE" => Ok(Action::COOPERATE),
+                    "DEFECT" => Ok(Action::DEFECT),
+                    "ABSTAIN" => Ok(Action::ABSTAIN),
+                    _ => Err(E::unknown_variant(s, &["COOPERATE", "DEFECT", "ABSTAIN"])),
+                }
+            }
+
+            fn visit_u64<E: de::Error>(self, index: u64) -> Result<Action, E> {
+                if index < MAX_ACTIONS as u64 {
+                    Ok(Action(index as u8))
+                } else {
+                    Err(E::custom(format!(
+                        "action {} of at most {}",
+                        index, MAX_ACTIONS
+                    )))
+                }
+            }
+
+            fn visit_i64<E: de::Error>(self, index: i64) -> Result<Action, E> {
+                match u64::try_from(index) {
+                    Ok(index) => self.visit_u64(index),
+                    Err(_) => Err(E::custom(format!("no action numbered {}", index))),
+                }
+            }
+        }
+
+        deserializer.deserialize_any(ActionVisitor)
+    }
+}
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Hash, Eq, PartialEq, Ord, PartialOrd, Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum Payoff {
+    /// Start the interrogation
+    NULL,
+
+    /// If both players cooperate, they both receive the reward R for cooperating.
+    REWARD,
+
+    /// If both players defect, they both receive the punishment Payoff P.
+    PUNISHMENT,
+
+    /// If Blue defects while Red cooperates, then Blue receives the temptation Payoff T, while Red receives the "sucker's" Payoff, S.
+    /// Similarly, if Blue cooperates while Red defects, then Blue receives the sucker's Payoff S, while Red receives the temptation Payoff T.
+    TEMPTATION,
+    SUCKER,
+
+    /// If either player abstains, they both receive the loner's payoff L.
+    LONER,
+
+    /// What a game given by a table pays for a pair of actions; its value
+    /// depends on both, so it has none of its own
+    TABLE,
+}
+
+impl fmt::Display for Payoff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            Payoff::NULL => "Null",
+            Payoff::REWARD => "Reward",
+            Payoff::PUNISHMENT => "Punishment",
+            Payoff::TEMPTATION => "Temptation",
+            Payoff::SUCKER => "Sucker",
+            Payoff::LONER => "Loner",
+            Payoff::TABLE => "Table",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// Points awarded for a payoff; signed and fractional values are allowed
+pub type Score = f64;
+
+/// What a player gets for each of its actions against each of its
+/// opponent's, in a symmetric game of up to [`MAX_ACTIONS`] actions
+///
+/// Written as rows, one per action of the player, e.g. `[[0, -1, 1], [1,
+/// 0, -1], [-1, 1, 0]]` for rock, paper, scissors.
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "Vec<Vec<Score>>", into = "Vec<Vec<Score>>")]
+pub struct ActionTable {
+    size: u8,
+    cells: [[Score; MAX_ACTIONS]; MAX_ACTIONS],
+}
+
+impl ActionTable {
+    /// How many actions the game has
+    pub fn actions(&self) -> usize {
+        self.size as usize
+    }
+
+    /// What playing `own` against `opponent` pays
+    pub fn get(&self, own: Action, opponent: Action) -> Score {
+        self.cells[own.index()][opponent.index()]
+    }
+}
+
+impl TryFrom<Vec<Vec<Score>>> for ActionTable {
+    type Error = PayoffError;
+
+    fn try_from(rows: Vec<Vec<Score>>) -> Result<ActionTable, PayoffError> {
+        let size = rows.len();
+        if !(2..=MAX_ACTIONS).contains(&size) {
+            return Err(PayoffError::Invalid(format!(
+                "a table needs 2 to {} actions, got {}",
+                MAX_ACTIONS, size
+            )));
+        }
+        let mut cells = [[0.0; MAX_ACTIONS]; MAX_ACTIONS];
+        for (i, row) in rows.iter().enumerate() {
+            if row.len() != size {
+                return Err(PayoffError::Invalid(format!(
+                    "row {} of the table has {} payoffs, not {}",
+                    i + 1,
+                    row.len(),
+                    size
+                )));
+            }
+            cells[i][..size].copy_from_slice(row);
+        }
+        Ok(ActionTable {
+            size: size as u8,
+            cells,
+        })
+    }
+}
+
+impl From<ActionTable> for Vec<Vec<Score>> {
+    fn from(table: ActionTable) -> Vec<Vec<Score>> {
+        let size = table.actions();
+        table.cells[..size]
+            .iter()
+            .map(|row| row[..size].to_vec())
+            .collect()
+    }
+}
+
+impl fmt::Debug for ActionTable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list()
+            .entries(Vec::<Vec<Score>>::from(*self))
+            .finish()
+    }
+}
+
+/// What each payoff class is worth in a symmetric 2x2 game, or what each
+/// pair of actions is worth in a symmetric game given by a table
+///
+/// Whether the values make sense depends on the [`Game`] being played; see
+/// [`Game::check`].
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "PayoffSpec", into = "PayoffSpec")]
+pub struct PayoffMatrix {
+    reward: Score,
+    temptation: Score,
+    punishment: Score,
+    sucker: Score,
+
+    /// What both players get when either abstains; without it, abstaining
+    /// counts as defecting
+    loner: Option<Score>,
+
+    /// The payoffs of a game of more than cooperating and defecting, which
+    /// R, T, P, and S are read from
+    table: Option<ActionTable>,
+}
+
+/// A [`PayoffMatrix`] as it is written: R, T, P, S, and maybe L, or else a
+/// table
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PayoffSpec {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    reward: Option<Score>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    temptation: Option<Score>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    punishment: Option<Score>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sucker: Option<Score>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    loner: Option<Score>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    table: Option<ActionTable>,
+}
+
+impl TryFrom<PayoffSpec> for PayoffMatrix {
+    type Error = PayoffError;
+
+    fn try_from(spec: PayoffSpec) -> Result<PayoffMatrix, PayoffError> {
+        match spec {
+            PayoffSpec {
+                reward: None,
+                temptation: None,
+                punishment: None,
+                sucker: None,
+                loner: None,
+                table: Some(table),
+            } => Ok(PayoffMatrix::table(table)),
+            PayoffSpec {
+                reward: Some(reward),
+                temptation: Some(temptation),
+                punishment: Some(punishment),
+                sucker: Some(sucker),
+                loner,
+                table: None,
+            } => Ok(PayoffMatrix {
+                loner,
+                ..PayoffMatrix::new(reward, temptation, punishment, sucker)
+            }),
+            _ => Err(PayoffError::Invalid(
+                "expected reward, temptation, punishment, and sucker, with or without loner, \
+                 or else a table"
+                    .to_owned(),
+            )),
+        }
+    }
+}
+
+impl From<PayoffMatrix> for PayoffSpec {
+    fn from(payoffs: PayoffMatrix) -> PayoffSpec {
+        match payoffs.table {
+            Some(table) => PayoffSpec {
+                reward: None,
+                temptation: None,
+                punishment: None,
+                sucker: None,
+                loner: None,
+                table: Some(table),
+            },
+            None => PayoffSpec {
+                reward: Some(payoffs.reward),
+                temptation: Some(payoffs.temptation),
+                punishment: Some(payoffs.punishment),
+                sucker: Some(payoffs.sucker),
+                loner: payoffs.loner,
+                table: None,
+            },
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum PayoffError {
+    Parse(String),
+    Invalid(String),
+}
+
+impl fmt::Display for PayoffError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PayoffError::Parse(s) => write!(f, "unable to parse payoffs: {}", s),
+            PayoffError::Invalid(s) => write!(f, "invalid payoffs: {}", s),
+        }
+    }
+}
+
+impl std::error::Error for PayoffError {}
+
+impl Default for PayoffMatrix {
+    /// The payoff values used by the original simulation
+    fn default() -> PayoffMatrix {
+        PayoffMatrix::new(3.0, 4.0, 2.0, 1.0)
+    }
+}
+
+impl PayoffMatrix {
+    pub fn new(reward: Score, temptation: Score, punishment: Score, sucker: Score) -> PayoffMatrix {
+        PayoffMatrix {
+            reward,
+            temptation,
+            punishment,
+            sucker,
+            loner: None,
+            table: None,
+        }
+    }
+
+    /// The payoffs of the game `table` gives; R, T, P, and S are what it
+    /// pays for cooperating and defecting, its first two actions
+    pub fn table(table: ActionTable) -> PayoffMatrix {
+        let value = |own, opponent| table.get(own, opponent);
+        PayoffMatrix {
+            table: Some(table),
+            ..PayoffMatrix::new(
+                value(Action::COOPERATE, Action::COOPERATE),
+                value(Action::DEFECT, Action::COOPERATE),
+                value(Action::DEFECT, Action::DEFECT),
+                value(Action::COOPERATE, Action::DEFECT),
+            )
+        }
+    }
+
+    /// The payoffs of the game whose table has `rows`, one per action; see
+    /// [`ActionTable`]
+    pub fn with_table(rows: Vec<Vec<Score>>) -> Result<PayoffMatrix, PayoffError> {
+        ActionTable::try_from(rows).map(PayoffMatrix::table)
+    }
+
+    /// The same payoffs but for the temptation, `temptation`
+    pub fn with_temptation(mut self, temptation: Score) -> PayoffMatrix {
+        self.temptation = temptation;
+        self
+    }
+
+    /// Allow abstaining, for the optional Prisoner's Dilemma, with `loner`
+    /// as its payoff; it should lie between P and R
+    pub fn with_loner(mut self, loner: Score) -> PayoffMatrix {
+        self.loner = Some(loner);
+        self
+    }
+
+    pub fn reward(&self) -> Score {
+        self.reward
+    }
+
+    pub fn temptation(&self) -> Score {
+        self.temptation
+    }
+
+    pub fn punishment(&self) -> Score {
+        self.punishment
+    }
+
+    pub fn sucker(&self) -> Score {
+        self.sucker
+    }
+
+    pub fn loner(&self) -> Option<Score> {
+        self.loner
+    }
+
+    pub fn action_table(&self) -> Option<&ActionTable> {
+        self.table.as_ref()
+    }
+
+    /// How many actions the game has: those of its table, if it has one,
+    /// and otherwise cooperating and defecting, and abstaining if there is
+    /// a loner's payoff
+    pub fn actions(&self) -> usize {
+        match (&self.table, self.loner) {
+            (Some(table), _) => table.actions(),
+            (None, Some(_)) => 3,
+            (None, None) => 2,
+        }
+    }
+
+    /// What `payoff` is worth; nothing before the first round, nor for a
+    /// [`Payoff::TABLE`], whose worth depends on the actions
+    pub fn value(&self, payoff: Payoff) -> Score {
+        match payoff {
+            Payoff::NULL => 0.0,
+            Payoff::REWARD => self.reward,
+            Payoff::TEMPTATION => self.temptation,
+            Payoff::PUNISHMENT => self.punishment,
+            Payoff::SUCKER => self.sucker,
+            Payoff::LONER => self.loner.unwrap_or(self.punishment),
+            Payoff::TABLE => 0.0,
+        }
+    }
+
+    /// For payoff https://en.wikipedia.org/wiki/Prisoner's_dilemma
+    ///
+    /// If both players cooperate, they both receive the reward R for cooperating.
+    /// If both players defect, they both receive the punishment payoff P.
+    /// If Blue defects while Red cooperates, then Blue receives the temptation payoff T, while Red receives the "sucker's" payoff, S.
+    /// Similarly, if Blue cooperates while Red defects, then Blue receives the sucker's payoff S, while Red receives the temptation payoff T.
+    /// If either abstains and there is a loner's payoff, both receive it; otherwise abstaining counts as defecting.
+    /// In a game given by a table, each receives what the table pays for its action against the other's, under the
+    /// payoff class of the two if both cooperate or defect and [`Payoff::TABLE`] if not; any action beyond the
+    /// table's counts as defecting.
+    pub fn score(&self, red: Action, blue: Action) -> RoundOutcome {
+        if let Some(table) = &self.table {
+            let counted = |action: Action| {
+                if action.index() < table.actions() {
+                    action
+                } else {
+                    Action::DEFECT
+                }
+            };
+            let (red, blue) = (counted(red), counted(blue));
+            if red.index() < 2 && blue.index() < 2 {
+                return PayoffMatrix::new(
+                    self.reward,
+                    self.temptation,
+                    self.punishment,
+                    self.sucker,
+                )
+                .score(red, blue);
+            }
+            let table_outcome = |amount| PlayerOutcome {
+                payoff: Payoff::TABLE,
+                amount,
+            };
+            return RoundOutcome {
+                red: table_outcome(table.get(red, blue)),
+                blue: table_outcome(table.get(blue, red)),
+            };
+        }
+        let counted = |action| match action {
+            Action::ABSTAIN if self.loner.is_some() => Action::ABSTAIN,
+            Action::COOPERATE => Action::COOPERATE,
+            _ => Action::DEFECT,
+        };
+        let (red, blue) = match (counted(red), counted(blue)) {
+            (Action::ABSTAIN, _) | (_, Action::ABSTAIN) => (Payoff::LONER, Payoff::LONER),
+            (Action::COOPERATE, Action::COOPERATE) => (Payoff::REWARD, Payoff::REWARD),
+            (Action::DEFECT, Action::COOPERATE) => (Payoff::TEMPTATION, Payoff::SUCKER),
+            (Action::COOPERATE, Action::DEFECT) => (Payoff::SUCKER, Payoff::TEMPTATION),
+            _ => (Payoff::PUNISHMENT, Payoff::PUNISHMENT),
+        };
+        RoundOutcome {
+            red: self.outcome(red),
+            blue: self.outcome(blue),
+        }
+    }
+
+    /// `payoff` with what it is worth
+    pub fn outcome(&self, payoff: Payoff) -> PlayerOutcome {
+        PlayerOutcome {
+            payoff,
+            amount: self.value(payoff),
+        }
+    }
+}
+
+/// What one player got from a round: the payoff class and its value
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PlayerOutcome {
+    pub payoff: Payoff,
+    pub amount: Score,
+}
+
+impl PlayerOutcome {
+    /// Nothing, before the first round
+    pub const NONE: PlayerOutcome = PlayerOutcome {
+        payoff: Payoff::NULL,
+        amount: 0.0,
+    };
+
+    /// The same payoff with its value weighted by `weight`, as a discounted
+    /// round is
+    pub fn weighted(self, weight: f64) -> PlayerOutcome {
+        PlayerOutcome {
+            amount: self.amount * weight,
+            ..self
+        }
+    }
+}
+
+/// What both players got from a round, each side named so that red's and
+/// blue's can't be swapped
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RoundOutcome {
+    pub red: PlayerOutcome,
+    pub blue: PlayerOutcome,
+}
+
+/// A symmetric 2x2 game, played with the same two actions and payoff
+/// classes as the Prisoner's Dilemma
+///
+/// Each game is a different ordering of the four payoffs, which
+/// [`Game::check`] enforces. Cooperating means hunting stag in the Stag Hunt
+/// and swerving in Chicken.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Game {
+    /// T > R > P > S and 2R > T + S: defecting always pays, but mutual
+    /// cooperation beats taking turns
+    #[default]
+    PrisonersDilemma,
+
+    /// R > T >= P > S: cooperating (hunting stag) pays only if the other
+    /// does too
+    StagHunt,
+
+    /// T > R > S > P: it is best to do the opposite of the other player,
+    /// and mutual defection is a crash
+    Chicken,
+
+    /// T > R > S > P, as Chicken, but conventionally with a benefit b and
+    /// cost c shared when both cooperate: R = b - c/2, T = b, S = b - c, P = 0
+    Snowdrift,
+}
+
+impl Game {
+    pub const ALL: [Game; 4] = [
+        Game::PrisonersDilemma,
+        Game::StagHunt,
+        Game::Chicken,
+        Game::Snowdrift,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Game::PrisonersDilemma => "prisoners-dilemma",
+            Game::StagHunt => "stag-hunt",
+            Game::Chicken => "chicken",
+            Game::Snowdrift => "snowdrift",
+        }
+    }
+
+    /// The payoffs used when a config doesn't give any
+    pub fn default_payoffs(&self) -> PayoffMatrix {
+        match self {
+            Game::PrisonersDilemma => PayoffMatrix::default(),
+            Game::StagHunt => PayoffMatrix::new(4.0, 3.0, 2.0, 1.0),
+            Game::Chicken => PayoffMatrix::new(0.0, 1.0, -10.0, -1.0),
+            Game::Snowdrift => PayoffMatrix::new(3.0, 4.0, 0.0, 2.0),
+        }
+    }
+
+    /// Check that `payoffs` have the ordering that defines this game
+    ///
+    /// A table defines a game of its own, so payoffs given by one pass
+    /// whatever the game.
+    pub fn check(&self, payoffs: &PayoffMatrix) -> Result<(), PayoffError> {
+        if payoffs.table.is_some() {
+            return Ok(());
+        }
+        let (r, t, p, s) = (
+            payoffs.reward,
+            payoffs.temptation,
+            payoffs.punishment,
+            payoffs.sucker,
+        );
+        let (holds, rule) = match self {
+            Game::PrisonersDilemma => {
+                if !(t > r && r > p && p > s) {
+                    (false, "T > R > P > S")
+                } else {
+                    (2.0 * r > t + s, "2R > T + S")
+                }
+            }
+            Game::StagHunt => (r > t && t >= p && p > s, "R > T >= P > S"),
+            Game::Chicken | Game::Snowdrift => (t > r && r > s && s > p, "T > R > S > P"),
+        };
+        if let Some(l) = payoffs.loner {
+            if !(p < l && l < r) {
+                return Err(PayoffError::Invalid(format!(
+                    "the loner's payoff needs P < L < R, got R {} P {} L {}",
+                    r, p, l
+                )));
+            }
+        }
+        if holds {
+            Ok(())
+        } else {
+            Err(PayoffError::Invalid(format!(
+                "{} needs {}, got R {} T {} P {} S {}",
+                self, rule, r, t, p, s
+            )))
+        }
+    }
+}
+
+impl fmt::Display for Game {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for Game {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Game, String> {
+        Game::ALL
+            .iter()
+            .copied()
+            .find(|game| game.name() == s)
+            .ok_or_else(|| {
+                let names: Vec<&str> = Game::ALL.iter().map(Game::name).collect();
+                format!("unknown game '{}', expected one of {}", s, names.join(", "))
+            })
+    }
+}
+
+impl FromStr for PayoffMatrix {
+    type Err = PayoffError;
+
+    /// Four comma-separated values in the order R,T,P,S, e.g. `3,5,1,0` or
+    /// `1,1.5,-1,-2`, and optionally a fifth, the loner's payoff L; or the
+    /// rows of a table separated by semicolons, e.g. `0,-1,1;1,0,-1;-1,1,0`
+    fn from_str(s: &str) -> Result<PayoffMatrix, PayoffError> {
+        if s.contains(';') {
+            let rows = s
+                .split(';')
+                .map(|row| {
+                    row.split(',')
+                        .map(|v| v.trim().parse::<Score>())
+                        .collect::<Result<Vec<_>, _>>()
+                })
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| PayoffError::Parse(format!("'{}': {}", s, e)))?;
+            return PayoffMatrix::with_table(rows);
+        }
+        let values = s
+            .split(',')
+            .map(|v| v.trim().parse::<Score>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| PayoffError::Parse(format!("'{}': {}", s, e)))?;
+        match values[..] {
+            [reward, temptation, punishment, sucker] => {
+                Ok(PayoffMatrix::new(reward, temptation, punishment, sucker))
+            }
+            [reward, temptation, punishment, sucker, loner] => {
+                Ok(PayoffMatrix::new(reward, temptation, punishment, sucker).with_loner(loner))
+            }
+            _ => Err(PayoffError::Parse(format!(
+                "'{}': expected four values, R,T,P,S, or five with L",
+                s
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_defector_is_tempted_and_the_cooperator_is_the_sucker() {
+        let payoffs = PayoffMatrix::default();
+        let outcome = payoffs.score(Action::DEFECT, Action::COOPERATE);
+        assert_eq!(
+            (outcome.red.payoff, outcome.blue.payoff),
+            (Payoff::TEMPTATION, Payoff::SUCKER)
+        );
+        let outcome = payoffs.score(Action::COOPERATE, Action::DEFECT);
+        assert_eq!(
+            (outcome.red.payoff, outcome.blue.payoff),
+            (Payoff::SUCKER, Payoff::TEMPTATION)
+        );
+    }
+
+    #[test]
+    fn payoff_orderings_are_game_specific() {
+        let pd = Game::PrisonersDilemma;
+        assert!(pd.check(&PayoffMatrix::new(3.0, 5.0, 1.0, 0.0)).is_ok());
+        assert!(pd.check(&PayoffMatrix::new(1.0, 1.5, -1.0, -2.5)).is_ok());
+        assert!(pd.check(&PayoffMatrix::new(3.0, 2.0, 1.0, 0.0)).is_err());
+        assert!(pd.check(&PayoffMatrix::new(3.0, 7.0, 1.0, 0.0)).is_err());
+        assert!(Game::StagHunt
+            .check(&PayoffMatrix::new(3.0, 5.0, 1.0, 0.0))
+            .is_err());
+        for game in &Game::ALL {
+            assert!(game.check(&game.default_payoffs()).is_ok(), "{}", game);
+            assert_eq!(game.name().parse::<Game>(), Ok(*game));
+        }
+    }
+
+    #[test]
+    fn abstaining_earns_the_loner_payoff() {
+        let optional = PayoffMatrix::new(3.0, 5.0, 1.0, 0.0).with_loner(2.0);
+        let loner = optional.outcome(Payoff::LONER);
+        let both = RoundOutcome {
+            red: loner,
+            blue: loner,
+        };
+        assert_eq!(optional.score(Action::ABSTAIN, Action::DEFECT), both);
+        assert_eq!(optional.score(Action::COOPERATE, Action::ABSTAIN), both);
+        assert_eq!(optional.value(Payoff::LONER), 2.0);
+        assert!(Game::PrisonersDilemma.check(&optional).is_ok());
+        assert!(Game::PrisonersDilemma
+            .check(&optional.with_loner(4.0))
+            .is_err());
+        // without a loner's payoff, abstaining is defecting
+        let outcome = PayoffMatrix::default().score(Action::ABSTAIN, Action::COOPERATE);
+        assert_eq!(
+            (outcome.red.payoff, outcome.blue.payoff),
+            (Payoff::TEMPTATION, Payoff::SUCKER)
+        );
+        assert_eq!((outcome.red.amount, outcome.blue.amount), (4.0, 1.0));
+        assert_eq!("3,5,1,0,2".parse::<PayoffMatrix>().unwrap(), optional);
+    }
+
+    #[test]
+    fn payoffs_parse_in_rtps_order() {
+        assert_eq!(
+            "3,4,2,1".parse::<PayoffMatrix>().unwrap(),
+            PayoffMatrix::default()
+        );
+        assert!("3,4,2".parse::<PayoffMatrix>().is_err());
+        assert_eq!(
+            "1,1.5,-1,-2".parse::<PayoffMatrix>().unwrap().sucker(),
+            -2.0
+        );
+    }
+
+    #[test]
+    fn a_table_scores_games_of_more_actions() {
+        let rps: PayoffMatrix = "0,-1,1;1,0,-1;-1,1,0".parse().unwrap();
+        assert_eq!(rps.actions(), 3);
+        let (rock, paper, scissors) = (Action::new(0), Action::new(1), Action::new(2));
+        let outcome = rps.score(scissors, paper);
+        assert_eq!(outcome.red.payoff, Payoff::TABLE);
+        assert_eq!((outcome.red.amount, outcome.blue.amount), (1.0, -1.0));
+        // the first two actions are still cooperating and defecting
+        let outcome = rps.score(paper, rock);
+        assert_eq!(
+            (outcome.red.payoff, outcome.red.amount),
+            (Payoff::TEMPTATION, 1.0)
+        );
+        assert_eq!((rps.reward(), rps.sucker()), (0.0, -1.0));
+        // an action beyond the table's is taken for defecting
+        assert_eq!(rps.score(Action::new(4), rock), rps.score(paper, rock));
+        assert!(Game::StagHunt.check(&rps).is_ok());
+        assert!("0,1;1".parse::<PayoffMatrix>().is_err());
+        assert!(PayoffMatrix::with_table(vec![vec![1.0]]).is_err());
+    }
+
+    #[test]
+    fn payoffs_are_written_as_rtps_or_as_a_table() {
+        let rtps: PayoffMatrix =
+            toml::from_str("reward = 3\ntemptation = 5\npunishment = 1\nsucker = 0").unwrap();
+        assert_eq!(rtps, PayoffMatrix::new(3.0, 5.0, 1.0, 0.0));
+        assert_eq!(
+            serde_json::to_string(&rtps).unwrap(),
+            r#"{"reward":3.0,"temptation":5.0,"punishment":1.0,"sucker":0.0}"#
+        );
+        let graded: PayoffMatrix =
+            toml::from_str("table = [[3, 0, 1], [5, 1, 2], [4, 0.5, 1.5]]").unwrap();
+        assert_eq!(graded.actions(), 3);
+        assert_eq!(graded.temptation(), 5.0);
+        let json = serde_json::to_string(&graded).unwrap();
+        assert_eq!(
+            json,
+            r#"{"table":[[3.0,0.0,1.0],[5.0,1.0,2.0],[4.0,0.5,1.5]]}"#
+        );
+        assert_eq!(serde_json::from_str::<PayoffMatrix>(&json).unwrap(), graded);
+        assert!(toml::from_str::<PayoffMatrix>("reward = 3\ntable = [[1, 2], [3, 4]]").is_err());
+        assert!(toml::from_str::<PayoffMatrix>("reward = 3").is_err());
+    }
+
+    #[test]
+    fn actions_beyond_abstaining_are_written_by_number() {
+        let actions = vec![Action::COOPERATE, Action::ABSTAIN, Action::new(4)];
+        let json = serde_json::to_string(&actions).unwrap();
+        assert_eq!(json, r#"["COOPERATE","ABSTAIN",4]"#);
+        assert_eq!(serde_json::from_str::<Vec<Action>>(&json).unwrap(), actions);
+        assert!(serde_json::from_str::<Action>("9").is_err());
+        let letters: String = actions.iter().map(|a| a.letter()).collect();
+        assert_eq!(letters, "CA4");
+        let read: Vec<_> = letters.chars().map(Action::from_letter).collect();
+        assert_eq!(
+            read,
+            vec![Some(actions[0]), Some(actions[1]), Some(actions[2])]
+        );
+        assert_eq!(Action::new(3).flipped(), Action::new(3));
+    }
+}
@@ -0,0 +1,274 @@
+//! Evolving finite state machine strategies with a genetic algorithm
+//!
+//! Every individual is a [`Machine`] with the same number of states. Each
+//! generation the machines play a round robin among themselves and against
+//! every configured participant, and a machine's fitness is its average
+//! points per round over those matches. The fittest few pass to the next
+//! generation unchanged; the rest of it are children of two parents chosen
+//! in proportion to fitness, taking each state from one or the other, with
+//! every action and transition occasionally mutated. The best machines of
+//! the last generation are kept, named so that they can be played again.
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::config::{ParticipantConfig, SimConfig};
+use crate::evolution::{pick, selection_weights};
+use crate::game::Action;
+use crate::interrupt;
+use crate::registry::StrategyRegistry;
+use crate::strategies::{FiniteStateMachine, Machine, MachineState};
+use crate::tournament;
+
+/// How a genetic run breeds its machines
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GeneticSettings {
+    /// Machines per generation
+    pub population: usize,
+    pub generations: usize,
+
+    /// States per machine
+    pub states: usize,
+
+    /// Probability that each action and transition of a child is redrawn
+    pub mutation_rate: f64,
+
+    /// The fittest machines copied unchanged into the next generation
+    pub elite: usize,
+
+    /// How many of the best machines to report
+    pub keep: usize,
+}
+
+/// The fittest machine of one generation, and how the rest did
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MachineGeneration {
+    pub generation: usize,
+    pub best_fitness: f64,
+    pub mean_fitness: f64,
+
+    /// The fittest machine's strategy name
+    pub best: String,
+}
+
+/// A machine from the last generation, with the name it plays under
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EvolvedMachine {
+    pub strategy: String,
+    pub machine: Machine,
+
+    /// Average points per round over its matches in the last generation
+    pub fitness: f64,
+}
+
+/// Everything needed to reproduce a genetic run, and what it found
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GeneticRecord {
+    pub config: SimConfig,
+    pub seed: u64,
+    pub settings: GeneticSettings,
+    pub generations: Vec<MachineGeneration>,
+
+    /// Distinct machines of the last generation played, fittest first
+    pub best: Vec<EvolvedMachine>,
+}
+
+impl EvolvedMachine {
+    /// A participant playing this machine, for a config
+    pub fn participant(&self, name: &str) -> ParticipantConfig {
+        ParticipantConfig::new(name, &self.strategy)
+    }
+}
+
+fn strategy_name(machine: &Machine) -> String {
+    format!("{}:{}", FiniteStateMachine::SCHEME, machine)
+}
+
+fn random_action(rng: &mut StdRng) -> Action {
+    if rng.gen::<bool>() {
+        Action::COOPERATE
+    } else {
+        Action::DEFECT
+    }
+}
+
+fn random_machine(states: usize, rng: &mut StdRng) -> Machine {
+    Machine {
+        states: (0..states)
+            .map(|_| MachineState {
+                action: random_action(rng),
+                on_cooperate: rng.gen_range(0, states),
+                on_defect: rng.gen_range(0, states),
+            })
+            .collect(),
+    }
+}
+
+/// A child taking each state from either parent, then mutated
+fn breed(mother: &Machine, father: &Machine, mutation_rate: f64, rng: &mut StdRng) -> Machine {
+    let states = mother.states.len();
+    let mutated = |rng: &mut StdRng| mutation_rate > 0.0 && rng.gen::<f64>() < mutation_rate;
+    Machine {
+        states: mother
+            .states
+            .iter()
+            .zip(&father.states)
+            .map(|(m, f)| {
+                let mut state = if rng.gen::<bool>() { *m } else { *f };
+                if mutated(rng) {
+                    state.action = state.action.flipped();
+                }
+                if mutated(rng) {
+                    state.on_cooperate = rng.gen_range(0, states);
+                }
+                if mutated(rng) {
+                    state.on_defect = rng.gen_range(0, states);
+                }
+                state
+            })
+            .collect(),
+    }
+}
+
+/// Each machine's average points per round in a round robin among
+/// `machines` and against every one of `opponents`
+///
+/// Aborted matches are left out of the average.
+async fn fitness(
+    config: &SimConfig,
+    registry: &StrategyRegistry,
+    machines: &[Machine],
+    opponents: &[ParticipantConfig],
+    seeds: &mut StdRng,
+) -> Vec<f64> {
+    let n = machines.len();
+    let mut entrants: Vec<ParticipantConfig> = machines
+        .iter()
+        .enumerate()
+        .map(|(i, machine)| {
+            ParticipantConfig::new(&format!("machine-{}", i), &strategy_name(machine))
+        })
+        .collect();
+    entrants.extend(opponents.iter().cloned());
+    let mut pairs = Vec::new();
+    for i in 0..n {
+        for j in i + 1..entrants.len() {
+            pairs.push((i, j));
+        }
+    }
+    let pairs = tournament::seatings(config, pairs);
+    let reports = tournament::play_pairs(config, registry, &entrants, &pairs, seeds, None).await;
+
+    let mut totals = vec![(0.0, 0); n];
+    for (&(blue, red), report) in pairs.iter().zip(&reports) {
+        if let Ok(result) = &report.result {
+            // the opponents themselves aren't being evolved
+            for (i, player) in [(blue, &result.blue), (red, &result.red)] {
+                if i < n {
+                    totals[i].0 += player.average_score(result.rounds);
+                    totals[i].1 += 1;
+                }
+            }
+        }
+    }
+    totals
+        .into_iter()
+        .map(|(total, matches)| {
+            if matches == 0 {
+                0.0
+            } else {
+                total / matches as f64
+            }
+        })
+        .collect()
+}
+
+/// Evolve machines of `settings.states` states from random ones, using
+/// `config.participants` as fixed opponents
+///
+/// The config should already have been validated against `registry`. The
+/// same seed replays the same run.
+pub async fn evolve_machines(
+    config: &SimConfig,
+    registry: &StrategyRegistry,
+    seed: u64,
+    settings: GeneticSettings,
+) -> GeneticRecord {
+    let mut seeds = StdRng::seed_from_u64(seed);
+    let mut rng = StdRng::seed_from_u64(seeds.gen());
+    let mut machines: Vec<Machine> = (0..settings.population)
+        .map(|_| random_machine(settings.states, &mut rng))
+        .collect();
+    let mut generations = Vec::with_capacity(settings.generations);
+    let mut ranked: Vec<(Machine, f64)> = Vec::new();
+
+    for generation in 0..settings.generations {
+        let scores = fitness(
+            config,
+            registry,
+            &machines,
+            &config.participants,
+            &mut seeds,
+        )
+        .await;
+        // the matches were cut short, so this generation's fitness is meaningless
+        if interrupt::interrupted() {
+            break;
+        }
+
+        let mut order: Vec<usize> = (0..machines.len()).collect();
+        order.sort_by(|&a, &b| scores[b].total_cmp(&scores[a]));
+        ranked = order
+            .iter()
+            .map(|&i| (machines[i].clone(), scores[i]))
+            .collect();
+        generations.push(MachineGeneration {
+            generation,
+            best_fitness: ranked[0].1,
+            mean_fitness: scores.iter().sum::<f64>() / scores.len() as f64,
+            best: strategy_name(&ranked[0].0),
+        });
+
+        let weights = selection_weights(&scores);
+        let total: f64 = weights.iter().sum();
+        let parent = |rng: &mut StdRng| {
+            if total <= 0.0 {
+                &machines[rng.gen_range(0, machines.len())]
+            } else {
+                &machines[pick(&weights, rng.gen::<f64>() * total)]
+            }
+        };
+        let mut next: Vec<Machine> = ranked
+            .iter()
+            .take(settings.elite)
+            .map(|(machine, _)| machine.clone())
+            .collect();
+        while next.len() < settings.population {
+            let (mother, father) = (parent(&mut rng), parent(&mut rng));
+            next.push(breed(mother, father, settings.mutation_rate, &mut rng));
+        }
+        machines = next;
+    }
+
+    let mut best: Vec<EvolvedMachine> = Vec::with_capacity(settings.keep);
+    for (machine, fitness) in ranked {
+        if best.len() == settings.keep {
+            break;
+        }
+        if best.iter().all(|b| b.machine != machine) {
+            best.push(EvolvedMachine {
+                strategy: strategy_name(&machine),
+                machine,
+                fitness,
+            });
+        }
+    }
+    GeneticRecord {
+        config: config.clone(),
+        seed,
+        settings,
+        generations,
+        best,
+    }
+}
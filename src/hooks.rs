@@ -0,0 +1,192 @@
+//! Callbacks for programs that embed the engine
+//!
+//! A [`Hooks`] [installed](install) on a thread is called as the matches
+//! and evolutions started from that thread go on: after every round, as
+//! every match ends, and after every generation. Unlike subscribers to the
+//! [event bus](crate::events), hooks are called in step with the run, so
+//! they can also steer it: a round hook can end its match, as a custom
+//! [`Termination`](crate::termination::Termination) would, and a
+//! generation hook can end the evolution. An embedding program can then
+//! collect its own metrics, or stop on its own rules, without changing the
+//! engine.
+//!
+//! The matches of a tournament run concurrently, and with `--threads` on
+//! other threads, so the hooks are shared and what they collect should be
+//! kept behind a lock or in atomics.
+
+use std::cell::RefCell;
+use std::sync::Arc;
+
+use crate::evolution::GenerationRecord;
+use crate::referee::{MatchResult, RoundRecord};
+use crate::termination::MatchProgress;
+
+/// What a match's `stopped_by` says when a hook ended it
+pub const STOPPED_BY: &str = "hook";
+
+thread_local! {
+    static INSTALLED: RefCell<Option<Arc<dyn Hooks>>> = const { RefCell::new(None) };
+}
+
+/// Whether the run should go on, as a hook sees it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flow {
+    Continue,
+    Stop,
+}
+
+/// What to call as a run goes on; every hook does nothing by default
+pub trait Hooks: Send + Sync {
+    /// After every round of the match between `blue` and `red`, with the
+    /// round just scored and where the match stands
+    ///
+    /// [`Flow::Stop`] ends the match after this round, with
+    /// [`STOPPED_BY`] as what stopped it.
+    fn on_round(
+        &self,
+        _blue: &str,
+        _red: &str,
+        _round: &RoundRecord,
+        _progress: &MatchProgress,
+    ) -> Flow {
+        Flow::Continue
+    }
+
+    /// As a match ends, however it ended
+    fn on_match_end(&self, _result: &MatchResult) {}
+
+    /// After every generation of an evolving population
+    ///
+    /// [`Flow::Stop`] ends the evolution once this generation has bred the
+    /// next.
+    fn on_generation_end(&self, _generation: &GenerationRecord) -> Flow {
+        Flow::Continue
+    }
+}
+
+/// Call `hooks` for every match and evolution started from this thread, or
+/// stop calling any with `None`, as [`crate::events::publish_to`] does for
+/// events
+pub fn install(hooks: Option<Arc<dyn Hooks>>) {
+    INSTALLED.with(|installed| *installed.borrow_mut() = hooks);
+}
+
+/// The hooks installed on this thread, if any
+pub(crate) fn installed() -> Option<Arc<dyn Hooks>> {
+    INSTALLED.with(|installed| installed.borrow().clone())
+}
+
+/// Tell this thread's hooks that a generation is over; whether they want
+/// the evolution to stop
+pub(crate) fn generation_ended(generation: &GenerationRecord) -> bool {
+    installed().is_some_and(|hooks| hooks.on_generation_end(generation) == Flow::Stop)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{ParticipantConfig, SimConfig};
+    use crate::evolution;
+    use crate::game::Action;
+    use crate::registry::StrategyRegistry;
+    use crate::tournament;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+
+    /// Counts rounds, ends a match at its first defection, and stops an
+    /// evolution after its second generation
+    #[derive(Default)]
+    struct Watcher {
+        rounds: AtomicUsize,
+        ended: Mutex<Vec<(String, String, usize)>>,
+        generations: AtomicUsize,
+    }
+
+    impl Hooks for Watcher {
+        fn on_round(
+            &self,
+            _blue: &str,
+            _red: &str,
+            round: &RoundRecord,
+            progress: &MatchProgress,
+        ) -> Flow {
+            self.rounds.fetch_add(1, Ordering::Relaxed);
+            assert_eq!(progress.rounds, round.round + 1);
+            if progress.last.0 == Action::DEFECT || progress.last.1 == Action::DEFECT {
+                Flow::Stop
+            } else {
+                Flow::Continue
+            }
+        }
+
+        fn on_match_end(&self, result: &MatchResult) {
+            self.ended.lock().unwrap().push((
+                result.blue.name.clone(),
+                result.red.name.clone(),
+                result.rounds,
+            ));
+        }
+
+        fn on_generation_end(&self, _generation: &GenerationRecord) -> Flow {
+            if self.generations.fetch_add(1, Ordering::Relaxed) == 1 {
+                Flow::Stop
+            } else {
+                Flow::Continue
+            }
+        }
+    }
+
+    fn config() -> SimConfig {
+        SimConfig {
+            iterations: 10,
+            participants: vec![
+                ParticipantConfig::new("tft", "tit-for-tat"),
+                ParticipantConfig::new("allc", "always-cooperate"),
+                ParticipantConfig::new("alld", "always-defect"),
+            ],
+            ..SimConfig::default()
+        }
+    }
+
+    #[actix_rt::test]
+    async fn hooks_see_every_round_and_can_end_matches() {
+        let watcher = Arc::new(Watcher::default());
+        install(Some(watcher.clone()));
+        let reports = tournament::round_robin(&config(), &StrategyRegistry::new(), 1, None).await;
+        install(None);
+
+        let mut ended = watcher.ended.lock().unwrap().clone();
+        ended.sort();
+        let ended: Vec<(&str, &str, usize)> = ended
+            .iter()
+            .map(|(blue, red, rounds)| (blue.as_str(), red.as_str(), *rounds))
+            .collect();
+        // only the cooperators play on to the round limit
+        assert_eq!(
+            ended,
+            vec![("allc", "alld", 1), ("tft", "allc", 10), ("tft", "alld", 1)]
+        );
+        assert_eq!(watcher.rounds.load(Ordering::Relaxed), 12);
+        let stopped: Vec<Option<&str>> = reports
+            .iter()
+            .map(|r| r.result.as_ref().unwrap().stopped_by.as_deref())
+            .collect();
+        assert_eq!(stopped, vec![None, Some(STOPPED_BY), Some(STOPPED_BY)]);
+
+        // without hooks the matches play out
+        let reports = tournament::round_robin(&config(), &StrategyRegistry::new(), 1, None).await;
+        assert!(reports
+            .iter()
+            .all(|r| r.result.as_ref().unwrap().rounds == 10));
+    }
+
+    #[actix_rt::test]
+    async fn a_generation_hook_can_end_an_evolution() {
+        let watcher = Arc::new(Watcher::default());
+        install(Some(watcher.clone()));
+        let record = evolution::evolve(&config(), &StrategyRegistry::new(), 1, 6, 5, 0.0).await;
+        install(None);
+        assert_eq!(record.generations.len(), 2);
+        assert_eq!(watcher.generations.load(Ordering::Relaxed), 2);
+    }
+}
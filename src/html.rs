@@ -0,0 +1,243 @@
+//! A tournament as one self-contained HTML page, for sharing results
+//!
+//! The page has the seed and config needed to replay the tournament, the
+//! standings, the pairwise [`PayoffTable`] coloured as a heat map, and every
+//! match's transcript, collapsed until clicked. It needs no scripts or
+//! files besides itself.
+
+use std::fmt::Write;
+
+use crate::fingerprint::colour;
+use crate::payoff_table::PayoffTable;
+use crate::referee::{MatchResult, SideRecord};
+use crate::tournament::TournamentRecord;
+
+const STYLE: &str = "
+  body { font-family: sans-serif; margin: 2em; color: #222; }
+  table { border-collapse: collapse; }
+  td, th { padding: 0.2em 1em; text-align: right; }
+  th:first-child, td:first-child { text-align: left; }
+  .matrix td { color: #fff; }
+  .matrix td.light { color: #222; }
+  details { margin: 0.5em 0; }
+  .side { display: flex; align-items: center; gap: 1em; margin: 0.2em 0; }
+  .name { width: 10em; }
+  .rounds { display: flex; flex-wrap: wrap; gap: 1px; }
+  .rounds span { width: 6px; height: 14px; }
+  .C { background: #2a2; }
+  .D { background: #c22; }
+  .A { background: #999; }
+  .stats, .faults { color: #666; }
+  pre { background: #f4f4f4; padding: 1em; }
+";
+
+/// Escape text for an HTML element or attribute
+fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// The whole report for `record`
+pub fn tournament_report(record: &TournamentRecord) -> String {
+    let mut s = String::new();
+    let _ = write!(
+        s,
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>actoripd tournament, seed {seed}</title>\n<style>{style}</style>\n</head>\n\
+         <body>\n<h1>actoripd tournament</h1>\n<p>seed {seed}</p>\n",
+        seed = record.seed,
+        style = STYLE,
+    );
+    standings(&mut s, record);
+    matrix(&mut s, &PayoffTable::new(record));
+    let _ = writeln!(s, "<h2>Matches</h2>");
+    for result in &record.matches {
+        transcript(&mut s, result);
+    }
+    for aborted in &record.aborted {
+        let _ = writeln!(
+            s,
+            "<p class=\"faults\">{} vs {}: aborted, {}</p>",
+            escape(&aborted.blue),
+            escape(&aborted.red),
+            escape(&aborted.error)
+        );
+    }
+    let config = toml::to_string(&record.config)
+        .unwrap_or_else(|_| serde_json::to_string_pretty(&record.config).expect("config"));
+    let _ = write!(
+        s,
+        "<h2>Config</h2>\n<pre>{}</pre>\n</body>\n</html>\n",
+        escape(&config)
+    );
+    s
+}
+
+fn standings(s: &mut String, record: &TournamentRecord) {
+    let _ = writeln!(
+        s,
+        "<h2>Standings</h2>\n<table>\n<thead><tr><th>name</th><th>strategy</th>\
+         <th>author</th><th>stochastic</th><th>parameters</th>\
+         <th>score</th><th>per match</th><th>per round</th><th>cooperation</th></tr></thead>\n<tbody>"
+    );
+    for standing in &record.standings {
+        let (strategy, author, stochastic, parameters) = match &standing.about {
+            Some(info) => (
+                format!(
+                    "<span title=\"{}\">{}</span>",
+                    escape(&info.description),
+                    escape(&info.display_name)
+                ),
+                escape(info.author.as_deref().unwrap_or("")),
+                if info.deterministic { "no" } else { "yes" },
+                escape(
+                    &info
+                        .parameters
+                        .iter()
+                        .map(|(name, value)| format!("{}={}", name, value))
+                        .collect::<Vec<_>>()
+                        .join(" "),
+                ),
+            ),
+            None => (escape(&standing.strategy), String::new(), "", String::new()),
+        };
+        let _ = writeln!(
+            s,
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td>\
+             <td>{}</td><td>{:.1}</td><td>{:.3}</td><td>{:.1}%</td></tr>",
+            escape(&standing.name),
+            strategy,
+            author,
+            stochastic,
+            parameters,
+            standing.score,
+            standing.average_match_score,
+            standing.average_score,
+            100.0 * standing.cooperation_rate,
+        );
+    }
+    let _ = writeln!(s, "</tbody>\n</table>");
+}
+
+fn matrix(s: &mut String, table: &PayoffTable) {
+    let _ = write!(
+        s,
+        "<h2>Points per round, row against column</h2>\n<table class=\"matrix\">\n<thead><tr><th></th>"
+    );
+    for name in &table.names {
+        let _ = write!(s, "<th>{}</th>", escape(name));
+    }
+    let _ = writeln!(s, "</tr></thead>\n<tbody>");
+    let (low, high) = (table.payoffs.sucker(), table.payoffs.temptation());
+    for (name, row) in table.names.iter().zip(&table.scores) {
+        let _ = write!(s, "<tr><th>{}</th>", escape(name));
+        for score in row {
+            match score {
+                Some(score) => {
+                    let t = (score - low) / (high - low);
+                    let [r, g, b] = colour(t);
+                    // the yellow end of the scale needs dark text
+                    let class = if t > 0.7 { " class=\"light\"" } else { "" };
+                    let _ = write!(
+                        s,
+                        "<td{} style=\"background: #{:02x}{:02x}{:02x}\">{:.3}</td>",
+                        class, r, g, b, score
+                    );
+                }
+                None => {
+                    let _ = write!(s, "<td></td>");
+                }
+            }
+        }
+        let _ = writeln!(s, "</tr>");
+    }
+    let _ = writeln!(s, "</tbody>\n</table>");
+}
+
+fn side(s: &mut String, name: &str, score: f64, rounds: &[&SideRecord]) {
+    let _ = write!(
+        s,
+        "<div class=\"side\"><span class=\"name\">{}</span><span>{}</span><span class=\"rounds\">",
+        escape(name),
+        score
+    );
+    for (i, round) in rounds.iter().enumerate() {
+        let _ = write!(
+            s,
+            "<span class=\"{}\" title=\"round {}: {} {}\"></span>",
+            round.executed.letter(),
+            i + 1,
+            round.outcome.payoff,
+            round.outcome.amount
+        );
+    }
+    let _ = writeln!(s, "</span></div>");
+}
+
+fn transcript(s: &mut String, result: &MatchResult) {
+    let _ = writeln!(
+        s,
+        "<details>\n<summary>{} {} vs {} {}, {} rounds</summary>",
+        escape(&result.blue.name),
+        result.blue.score,
+        escape(&result.red.name),
+        result.red.score,
+        result.rounds
+    );
+    let blue: Vec<&SideRecord> = result.transcript.iter().map(|round| &round.blue).collect();
+    let red: Vec<&SideRecord> = result.transcript.iter().map(|round| &round.red).collect();
+    side(s, &result.blue.name, result.blue.score, &blue);
+    side(s, &result.red.name, result.red.score, &red);
+    let stats = &result.stats;
+    let _ = writeln!(
+        s,
+        "<p class=\"stats\">mutual cooperation: {} streaks, longest {}; payoff share {:.1}% / {:.1}%</p>",
+        stats.mutual_cooperation_streaks,
+        stats.longest_mutual_cooperation,
+        100.0 * stats.blue.payoff_share,
+        100.0 * stats.red.payoff_share
+    );
+    for fault in &result.faults {
+        let _ = writeln!(
+            s,
+            "<p class=\"faults\">forfeit: {}</p>",
+            escape(&fault.to_string())
+        );
+    }
+    let _ = writeln!(s, "</details>");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{ParticipantConfig, SimConfig};
+    use crate::registry::StrategyRegistry;
+
+    #[test]
+    fn standings_show_what_the_registry_says_of_each_strategy() {
+        let config = SimConfig {
+            participants: vec![
+                ParticipantConfig::new("tft", "tit-for-tat"),
+                ParticipantConfig::new("random", "random"),
+            ],
+            ..SimConfig::default()
+        };
+        let mut record = TournamentRecord::new(&config, 1, Vec::new());
+        record.describe_strategies(&StrategyRegistry::new());
+        let report = tournament_report(&record);
+        let tft = "<td>tft</td><td><span title=\"Cooperates first, then copies the opponent&#39;s \
+                   last move\">Tit for Tat</span></td><td>Anatol Rapoport</td><td>no</td><td></td>";
+        assert!(report.contains(tft), "{}", report);
+        assert!(report.contains("Random</span></td><td></td><td>yes</td><td>p_cooperate=0.5</td>"));
+    }
+}
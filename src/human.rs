@@ -0,0 +1,113 @@
+//! A strategy played by a person at the terminal
+//!
+//! Each round the prisoner prints the recent history and both scores to
+//! stderr, then waits for `c` or `d` on stdin. The wait happens on a thread
+//! of its own, so the rest of the tournament keeps running meanwhile. Name
+//! it as `strategy = "human"`, or play it from the command line:
+//!
+//! ```text
+//! cargo run -- --blue human --red tit-for-tat run
+//! ```
+
+use futures::channel::oneshot;
+use futures::future::BoxFuture;
+use std::io::{self, BufRead, Write};
+use std::thread;
+
+use crate::game::{Action, PayoffMatrix};
+use crate::strategy::{History, Strategy};
+
+/// The name the strategy is registered under
+pub const NAME: &str = "human";
+
+/// Rounds of history shown in each prompt
+const SHOWN_ROUNDS: usize = 20;
+
+pub struct HumanStrategy {
+    payoffs: PayoffMatrix,
+}
+
+impl HumanStrategy {
+    pub fn new(payoffs: PayoffMatrix) -> HumanStrategy {
+        HumanStrategy { payoffs }
+    }
+
+    fn prompt(&self, history: &History) -> String {
+        let (own, opponent) = history.rounds().fold((0.0, 0.0), |(own, opponent), r| {
+            let theirs = self.payoffs.score(r.own, r.opponent).blue;
+            (own + r.outcome.amount, opponent + theirs.amount)
+        });
+        let skipped = if history.round() > SHOWN_ROUNDS {
+            "..."
+        } else {
+            ""
+        };
+        let mine: String = history
+            .recent(SHOWN_ROUNDS)
+            .map(|r| r.own.letter())
+            .collect();
+        let theirs: String = history
+            .recent(SHOWN_ROUNDS)
+            .map(|r| r.opponent.letter())
+            .collect();
+        format!(
+            "\nround {}: you {}, opponent {}\n  you      {}{}\n  opponent {}{}\n(c)ooperate or (d)efect? ",
+            history.round() + 1,
+            own,
+            opponent,
+            skipped,
+            mine,
+            skipped,
+            theirs,
+        )
+    }
+}
+
+/// Prompt until the player answers, holding stdin so that two human
+/// prisoners take turns
+fn ask(prompt: &str) -> Result<Action, String> {
+    let stdin = io::stdin();
+    let mut input = stdin.lock();
+    loop {
+        eprint!("{}", prompt);
+        io::stderr().flush().map_err(|e| e.to_string())?;
+        let mut line = String::new();
+        if input.read_line(&mut line).map_err(|e| e.to_string())? == 0 {
+            return Err("stdin closed".to_owned());
+        }
+        match line.trim().to_lowercase().as_str() {
+            "c" | "cooperate" => return Ok(Action::COOPERATE),
+            "d" | "defect" => return Ok(Action::DEFECT),
+            "a" | "abstain" => return Ok(Action::ABSTAIN),
+            _ => eprintln!("please answer c, d, or a"),
+        }
+    }
+}
+
+impl Strategy for HumanStrategy {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    /// Never called: prisoners use [`Strategy::choose_async`] instead
+    fn choose(&mut self, _history: &History) -> Action {
+        panic!("human strategies only decide asynchronously")
+    }
+
+    fn choose_async(
+        &mut self,
+        history: &History,
+    ) -> Option<BoxFuture<'static, Result<Action, String>>> {
+        let prompt = self.prompt(history);
+        let (sender, receiver) = oneshot::channel();
+        thread::spawn(move || {
+            // the prisoner may have gone, e.g. after a timeout
+            let _ = sender.send(ask(&prompt));
+        });
+        Some(Box::pin(async move {
+            receiver
+                .await
+                .map_err(|_| "input thread stopped".to_owned())?
+        }))
+    }
+}
@@ -0,0 +1,37 @@
+//! Stopping a run early on Ctrl-C
+//!
+//! The first Ctrl-C asks every running match to stop after its current
+//! round. Each referee then reports what it has, so the run still ends with
+//! a partial result. A second Ctrl-C exits at once.
+
+use std::process;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tracing::warn;
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Whether the run has been asked to stop
+pub fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::Relaxed)
+}
+
+/// Ask every running match to stop after its current round
+pub fn interrupt() {
+    INTERRUPTED.store(true, Ordering::Relaxed);
+}
+
+/// Call [`interrupt`] on the first Ctrl-C, and exit on the second
+///
+/// Must be called from inside a running `System`.
+pub fn watch_ctrl_c() {
+    actix::spawn(async {
+        if actix_rt::signal::ctrl_c().await.is_err() {
+            return;
+        }
+        warn!("interrupted: stopping after the current round; press Ctrl-C again to quit");
+        interrupt();
+        if actix_rt::signal::ctrl_c().await.is_ok() {
+            process::exit(130);
+        }
+    });
+}
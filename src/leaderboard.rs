@@ -0,0 +1,314 @@
+//! Wins, losses, and points per strategy, accumulated over many runs
+//!
+//! Like [`crate::rating`], the leaderboard is kept by strategy name in a
+//! JSON file that each run adds to. Where ratings weigh each result by the
+//! opponent's strength, the leaderboard simply counts: matches won, drawn,
+//! and lost on score, and points earned. Matches against twins, or between
+//! two participants playing the same strategy, aren't counted, since a
+//! strategy can't beat itself.
+//!
+//! So that a long-running competition stays fresh, the leaderboard can also
+//! keep [`Recent`] results, which [`Leaderboard::decay`] fades with age:
+//! a result loses half its weight every half-life, and strategies are ranked
+//! by their recent points per round. A strategy with fewer matches than
+//! [`PROVISIONAL_MATCHES`] is provisional, and ranks below every strategy
+//! that has played enough for its early results not to decide its place.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::game::Score;
+use crate::tournament::TournamentRecord;
+
+/// The matches a strategy needs before its place is no longer provisional
+pub const PROVISIONAL_MATCHES: usize = 10;
+
+/// One strategy's results so far
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct Tally {
+    pub matches: usize,
+    pub wins: usize,
+    pub draws: usize,
+    pub losses: usize,
+    pub rounds: usize,
+    pub score: Score,
+
+    /// The same results faded with age, once the leaderboard has decayed;
+    /// until then they are the totals
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recent: Option<Recent>,
+}
+
+impl Tally {
+    /// The results faded with age, or the totals if they never were
+    pub fn recent(&self) -> Recent {
+        self.recent.unwrap_or(Recent {
+            matches: self.matches as f64,
+            rounds: self.rounds as f64,
+            score: self.score,
+            as_of: 0,
+        })
+    }
+}
+
+/// A strategy's results, each weighted by how recent it is
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct Recent {
+    pub matches: f64,
+    pub rounds: f64,
+    pub score: Score,
+
+    /// When the weights were last brought up to date, in seconds since the
+    /// Unix epoch
+    pub as_of: u64,
+}
+
+/// A strategy's place on the leaderboard
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LeaderboardEntry {
+    pub strategy: String,
+    #[serde(flatten)]
+    pub tally: Tally,
+
+    /// Points per round across every match
+    pub average_score: f64,
+    pub average_match_score: f64,
+
+    /// Points per round, weighting recent matches more if the leaderboard
+    /// has decayed
+    pub recent_average_score: f64,
+
+    /// Whether the strategy has played too few matches to be ranked with
+    /// the rest
+    pub provisional: bool,
+}
+
+#[derive(Debug)]
+pub enum LeaderboardError {
+    Io(io::Error),
+    Parse(serde_json::Error),
+}
+
+impl fmt::Display for LeaderboardError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LeaderboardError::Io(e) => write!(f, "unable to access leaderboard: {}", e),
+            LeaderboardError::Parse(e) => write!(f, "unable to parse leaderboard: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for LeaderboardError {}
+
+/// Tallies by strategy name, stored as a JSON object
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Leaderboard(BTreeMap<String, Tally>);
+
+impl Leaderboard {
+    /// Read the leaderboard at `path`, or start afresh if there is no file yet
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Leaderboard, LeaderboardError> {
+        match fs::read_to_string(path) {
+            Ok(text) => serde_json::from_str(&text).map_err(LeaderboardError::Parse),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Leaderboard::default()),
+            Err(e) => Err(LeaderboardError::Io(e)),
+        }
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), LeaderboardError> {
+        let text = serde_json::to_string_pretty(self).expect("serializable leaderboard");
+        fs::write(path, text + "\n").map_err(LeaderboardError::Io)
+    }
+
+    /// Count one match of `rounds` rounds between the strategies `blue` and
+    /// `red`, which scored `blue_score` and `red_score`
+    ///
+    /// A strategy playing itself isn't counted, as it isn't rated either.
+    pub fn record_match(
+        &mut self,
+        blue: &str,
+        red: &str,
+        rounds: usize,
+        blue_score: Score,
+        red_score: Score,
+    ) {
+        if blue == red {
+            return;
+        }
+        for (strategy, score, other) in
+            [(blue, blue_score, red_score), (red, red_score, blue_score)]
+        {
+            let tally = self.0.entry(strategy.to_owned()).or_default();
+            tally.matches += 1;
+            tally.rounds += rounds;
+            tally.score += score;
+            if let Some(recent) = &mut tally.recent {
+                recent.matches += 1.0;
+                recent.rounds += rounds as f64;
+                recent.score += score;
+            }
+            if score > other {
+                tally.wins += 1;
+            } else if score < other {
+                tally.losses += 1;
+            } else {
+                tally.draws += 1;
+            }
+        }
+    }
+
+    /// Count every match in `record` between two participants
+    pub fn record_tournament(&mut self, record: &TournamentRecord) {
+        let strategies: BTreeMap<&str, &str> = record
+            .config
+            .participants
+            .iter()
+            .map(|p| (p.name.as_str(), p.strategy.as_str()))
+            .collect();
+        for result in &record.matches {
+            // twins aren't participants
+            if let (Some(blue), Some(red)) = (
+                strategies.get(result.blue.name.as_str()),
+                strategies.get(result.red.name.as_str()),
+            ) {
+                self.record_match(
+                    blue,
+                    red,
+                    result.rounds,
+                    result.blue.score,
+                    result.red.score,
+                );
+            }
+        }
+    }
+
+    /// Fade every result by its age at `now`, in seconds since the Unix
+    /// epoch, halving its weight every `half_life` seconds
+    ///
+    /// A strategy's results start fading from the first time they are
+    /// decayed, since the leaderboard doesn't know when older ones were
+    /// played.
+    pub fn decay(&mut self, now: u64, half_life: u64) {
+        for tally in self.0.values_mut() {
+            let mut recent = tally.recent.unwrap_or(Recent {
+                as_of: now,
+                ..tally.recent()
+            });
+            let age = now.saturating_sub(recent.as_of) as f64;
+            let weight = if half_life == 0 {
+                0.0
+            } else {
+                0.5f64.powf(age / half_life as f64)
+            };
+            recent.matches *= weight;
+            recent.rounds *= weight;
+            recent.score *= weight;
+            recent.as_of = recent.as_of.max(now);
+            tally.recent = Some(recent);
+        }
+    }
+
+    /// Every strategy, most recent points per round first, with those that
+    /// have played fewer than [`PROVISIONAL_MATCHES`] after the rest
+    pub fn entries(&self) -> Vec<LeaderboardEntry> {
+        self.ranked(PROVISIONAL_MATCHES)
+    }
+
+    /// Every strategy, most recent points per round first, with those that
+    /// have played fewer than `provisional_below` matches after the rest
+    pub fn ranked(&self, provisional_below: usize) -> Vec<LeaderboardEntry> {
+        let per = |n: f64, d: f64| if d == 0.0 { 0.0 } else { n / d };
+        let mut entries: Vec<LeaderboardEntry> = self
+            .0
+            .iter()
+            .map(|(strategy, &tally)| {
+                let recent = tally.recent();
+                LeaderboardEntry {
+                    strategy: strategy.clone(),
+                    tally,
+                    average_score: per(tally.score, tally.rounds as f64),
+                    average_match_score: per(tally.score, tally.matches as f64),
+                    recent_average_score: per(recent.score, recent.rounds),
+                    provisional: tally.matches < provisional_below,
+                }
+            })
+            .collect();
+        entries.sort_by(|a, b| {
+            a.provisional
+                .cmp(&b.provisional)
+                .then_with(|| b.recent_average_score.total_cmp(&a.recent_average_score))
+                .then_with(|| a.strategy.cmp(&b.strategy))
+        });
+        entries
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_are_counted_for_both_sides() {
+        let mut leaderboard = Leaderboard::default();
+        leaderboard.record_match("alld", "tft", 10, 22.0, 19.0);
+        leaderboard.record_match("alld", "pavlov", 10, 25.0, 25.0);
+        let entries = leaderboard.entries();
+        let alld = entries.iter().find(|e| e.strategy == "alld").unwrap();
+        assert_eq!(
+            (alld.tally.matches, alld.tally.wins, alld.tally.draws),
+            (2, 1, 1)
+        );
+        assert_eq!(alld.average_score, 47.0 / 20.0);
+        assert_eq!(alld.average_match_score, 23.5);
+        let tft = entries.iter().find(|e| e.strategy == "tft").unwrap();
+        assert_eq!((tft.tally.matches, tft.tally.losses), (1, 1));
+        // most points per round first
+        assert_eq!(entries[0].strategy, "pavlov");
+        assert!(entries.iter().all(|e| e.provisional));
+    }
+
+    #[test]
+    fn old_results_fade_with_their_half_life() {
+        let day = 24 * 60 * 60;
+        let mut leaderboard = Leaderboard::default();
+        leaderboard.record_match("alld", "tft", 10, 50.0, 0.0);
+        leaderboard.decay(day, day);
+        // the first decay starts the clock without fading anything
+        assert_eq!(leaderboard.entries()[0].strategy, "alld");
+
+        leaderboard.decay(3 * day, day);
+        leaderboard.record_match("alld", "tft", 10, 0.0, 50.0);
+        let entries = leaderboard.entries();
+        assert_eq!(entries[0].strategy, "tft");
+        let tft = &entries[0];
+        // 50 points in 10 rounds just now, none in a quarter of 10 rounds
+        assert_eq!(tft.recent_average_score, 50.0 / 12.5);
+        assert_eq!(tft.average_score, 50.0 / 20.0);
+        assert_eq!(tft.tally.recent.unwrap().matches, 1.25);
+    }
+
+    #[test]
+    fn provisional_strategies_rank_after_the_rest() {
+        let mut leaderboard = Leaderboard::default();
+        for _ in 0..3 {
+            leaderboard.record_match("alld", "tft", 10, 14.0, 9.0);
+        }
+        leaderboard.record_match("pavlov", "tft", 10, 30.0, 30.0);
+        let entries = leaderboard.ranked(3);
+        let order: Vec<_> = entries.iter().map(|e| e.strategy.as_str()).collect();
+        assert_eq!(order, vec!["tft", "alld", "pavlov"]);
+        assert!(entries[2].provisional);
+        assert!(entries[2].recent_average_score > entries[0].recent_average_score);
+    }
+
+    #[test]
+    fn a_strategy_playing_itself_is_not_counted() {
+        let mut leaderboard = Leaderboard::default();
+        leaderboard.record_match("tft", "tft", 10, 30.0, 30.0);
+        assert!(leaderboard.entries().is_empty());
+    }
+}
@@ -0,0 +1,123 @@
+//! Actor Driven Iterated Prisoners Dilemma
+//!
+//! Each prisoner is an actix actor holding a [`strategy::Strategy`]; a
+//! [`referee::Referee`] actor interrogates both prisoners once per round and
+//! scores their actions against a table of [`game::PayoffMatrix`].
+
+#[cfg(feature = "arrow")]
+pub mod arrow;
+#[cfg(feature = "actors")]
+pub mod axelrod;
+#[cfg(feature = "actors")]
+pub mod bench;
+#[cfg(feature = "actors")]
+pub mod clock;
+#[cfg(feature = "actors")]
+pub mod commands;
+#[cfg(feature = "actors")]
+pub mod config;
+#[cfg(feature = "actors")]
+pub mod control;
+#[cfg(feature = "actors")]
+pub mod determinism;
+#[cfg(feature = "actors")]
+pub mod distributed;
+#[cfg(feature = "actors")]
+pub mod events;
+#[cfg(feature = "actors")]
+pub mod evolution;
+#[cfg(feature = "actors")]
+pub mod experiment;
+#[cfg(feature = "actors")]
+pub mod exploit;
+#[cfg(feature = "actors")]
+pub mod fingerprint;
+#[cfg(feature = "actors")]
+pub mod formats;
+pub mod game;
+#[cfg(feature = "actors")]
+pub mod genetic;
+#[cfg(feature = "actors")]
+pub mod hooks;
+#[cfg(feature = "actors")]
+pub mod html;
+pub mod human;
+#[cfg(feature = "actors")]
+pub mod interrupt;
+#[cfg(feature = "actors")]
+pub mod leaderboard;
+pub mod local;
+#[cfg(feature = "actors")]
+pub mod memory;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+#[cfg(feature = "actors")]
+pub mod objectives;
+#[cfg(feature = "actors")]
+pub mod payoff_table;
+#[cfg(feature = "plots")]
+pub mod plots;
+#[cfg(feature = "plugins")]
+pub mod plugin;
+#[cfg(feature = "actors")]
+pub mod population;
+#[cfg(feature = "actors")]
+pub mod prisoner;
+pub mod profile;
+#[cfg(feature = "actors")]
+pub mod progress;
+#[cfg(feature = "actors")]
+pub mod public_goods;
+pub mod randomness;
+#[cfg(feature = "actors")]
+pub mod rating;
+#[cfg(feature = "actors")]
+pub mod recording;
+#[cfg(feature = "actors")]
+pub mod referee;
+pub mod registry;
+#[cfg(feature = "actors")]
+pub mod remote;
+#[cfg(feature = "actors")]
+pub mod report;
+pub mod reputation;
+#[cfg(feature = "actors")]
+pub mod scheduler;
+pub mod schema;
+#[cfg(feature = "scripting")]
+pub mod scripted;
+#[cfg(feature = "actors")]
+pub mod search;
+#[cfg(feature = "websocket")]
+pub mod seats;
+#[cfg(feature = "server")]
+pub mod server;
+#[cfg(feature = "actors")]
+pub mod spatial;
+pub mod stats;
+#[cfg(feature = "sqlite")]
+pub mod store;
+pub mod strategies;
+pub mod strategy;
+#[cfg(feature = "actors")]
+pub mod sweep;
+#[cfg(feature = "actors")]
+pub mod teams;
+#[cfg(feature = "otel")]
+pub mod telemetry;
+pub mod termination;
+#[cfg(feature = "actors")]
+pub mod topology;
+#[cfg(feature = "actors")]
+pub mod tournament;
+pub mod transcript;
+#[cfg(feature = "tui")]
+pub mod tui;
+#[cfg(feature = "actors")]
+pub mod tune;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "webhooks")]
+pub mod webhooks;
+#[cfg(feature = "websocket")]
+pub mod websocket;
@@ -0,0 +1,386 @@
+//! Matches played in a plain loop, without actors
+//!
+//! A [`LocalMatch`] plays two strategies against each other on the calling
+//! thread, one round after another, which is all a browser playground can
+//! do: built with `--no-default-features`, the crate drops actix and tokio
+//! and compiles to `wasm32-unknown-unknown`, keeping the game, every
+//! strategy in the registry, match statistics, and this loop.
+//!
+//! The loop plays a match the way a referee does, drawing noise,
+//! misperception, and termination from one seeded generator in the same
+//! order, so a match between strategies seeded alike has the same
+//! transcript in either. What a referee adds on top, such as cheap talk,
+//! alternating moves, reputations, timeouts, and faults, is left out; a
+//! strategy that needs `choose_async` plays its synchronous `choose`.
+//!
+//! A local match can also take up a recorded one partway, see
+//! [`LocalMatch::resumed`].
+
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::game::{Action, PayoffMatrix, Score};
+use crate::randomness::{Positions, Seat, Stream};
+use crate::stats::MatchStats;
+use crate::strategy::{History, Round, Strategy};
+use crate::termination::{FixedLength, MatchProgress, Termination};
+use crate::transcript::{RoundRecord, SideRecord};
+
+/// How one side fared in a local match
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LocalSummary {
+    pub name: String,
+    pub score: Score,
+
+    /// Executed cooperations, after any execution noise
+    pub cooperations: usize,
+
+    /// Rounds in which noise flipped the chosen action before scoring
+    pub flips: usize,
+
+    /// Rounds in which this side was told the wrong opponent action
+    pub misperceptions: usize,
+}
+
+/// The outcome of a local match
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LocalResult {
+    pub rounds: usize,
+    pub blue: LocalSummary,
+    pub red: LocalSummary,
+
+    /// Every round played, in order
+    pub transcript: Vec<RoundRecord>,
+
+    /// Cooperation metrics computed from the transcript
+    pub stats: MatchStats,
+
+    /// What ended the match before its round limit, e.g. `converged`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stopped_by: Option<String>,
+}
+
+/// One side of a local match: its strategy and what it has seen
+struct Side {
+    strategy: Box<dyn Strategy>,
+    history: History,
+    summary: LocalSummary,
+}
+
+impl Side {
+    fn new(name: &str, strategy: Box<dyn Strategy>) -> Side {
+        Side {
+            history: History::with_depth(strategy.memory_depth()),
+            strategy,
+            summary: LocalSummary {
+                name: name.to_owned(),
+                score: 0.0,
+                cooperations: 0,
+                flips: 0,
+                misperceptions: 0,
+            },
+        }
+    }
+}
+
+/// Two strategies and the rules of their match
+pub struct LocalMatch {
+    blue: Side,
+    red: Side,
+    payoffs: PayoffMatrix,
+    terminations: Vec<Box<dyn Termination>>,
+    noise: f64,
+    misperception: f64,
+    discount: f64,
+    rng: Stream,
+
+    /// The rounds already played, if the match was taken up partway
+    played: Vec<RoundRecord>,
+    resumed: bool,
+}
+
+impl LocalMatch {
+    pub fn new(
+        blue: (&str, Box<dyn Strategy>),
+        red: (&str, Box<dyn Strategy>),
+        payoffs: PayoffMatrix,
+        iterations: usize,
+    ) -> LocalMatch {
+        LocalMatch {
+            blue: Side::new(blue.0, blue.1),
+            red: Side::new(red.0, red.1),
+            payoffs,
+            terminations: vec![Box::new(FixedLength(iterations))],
+            noise: 0.0,
+            misperception: 0.0,
+            discount: 1.0,
+            rng: Stream::seed_from_u64(0),
+            played: Vec::new(),
+            resumed: false,
+        }
+    }
+
+    /// Seed the match's random number generator, used as a referee's is
+    pub fn with_seed(mut self, seed: u64) -> LocalMatch {
+        self.rng = Stream::seed_from_u64(seed);
+        self
+    }
+
+    /// Flip each chosen action with probability `noise` before it is scored
+    pub fn with_noise(mut self, noise: f64) -> LocalMatch {
+        self.noise = noise;
+        self
+    }
+
+    /// Misreport the opponent's action to each side with probability
+    /// `misperception`
+    pub fn with_misperception(mut self, misperception: f64) -> LocalMatch {
+        self.misperception = misperception;
+        self
+    }
+
+    /// Weight round `t`'s payoffs by `discount` to the power `t`
+    pub fn with_discount(mut self, discount: f64) -> LocalMatch {
+        self.discount = discount;
+        self
+    }
+
+    /// Also end the match when `termination` says so
+    pub fn with_termination(mut self, termination: Box<dyn Termination>) -> LocalMatch {
+        self.terminations.push(termination);
+        self
+    }
+
+    /// Take the match up after `played`, the rounds of a recorded match
+    /// before `positions.round`, set last of all
+    ///
+    /// Both strategies, fresh from their seeds and built under `seats`,
+    /// decide every round played with the history their sides saw, and the
+    /// terminations see how the match stood after each one. Then the
+    /// strategies' generators, in the order built at each seat, and the
+    /// match's own are moved to where `positions` says they stood, so that
+    /// the rest of the match is played as it was recorded.
+    pub fn resumed(
+        mut self,
+        played: &[RoundRecord],
+        positions: &Positions,
+        seats: (&Seat, &Seat),
+    ) -> LocalMatch {
+        let mut progress = MatchProgress {
+            rounds: 0,
+            blue_score: 0.0,
+            red_score: 0.0,
+            last: (Action::COOPERATE, Action::COOPERATE),
+        };
+        for round in played {
+            self.blue.strategy.choose(&self.blue.history);
+            self.red.strategy.choose(&self.red.history);
+            for (side, own, other) in [
+                (&mut self.blue, &round.blue, &round.red),
+                (&mut self.red, &round.red, &round.blue),
+            ] {
+                side.history.push(Round {
+                    own: own.executed,
+                    opponent: own.perceived,
+                    hidden: false,
+                    outcome: own.outcome,
+                });
+                side.summary.score += own.outcome.amount;
+                side.summary.cooperations += (own.executed == Action::COOPERATE) as usize;
+                side.summary.flips += (own.chosen != own.executed) as usize;
+                side.summary.misperceptions += (own.perceived != other.executed) as usize;
+            }
+            progress = MatchProgress {
+                rounds: progress.rounds + 1,
+                blue_score: self.blue.summary.score,
+                red_score: self.red.summary.score,
+                last: (round.blue.executed, round.red.executed),
+            };
+            for termination in &mut self.terminations {
+                termination.finished(&progress, &mut self.rng);
+            }
+        }
+        seats.0.seek(&positions.blue);
+        seats.1.seek(&positions.red);
+        self.rng.seek(positions.referee);
+        self.played = played.to_vec();
+        self.resumed = true;
+        self
+    }
+
+    /// Randomly flip `action` with the given probability
+    fn perturb(&mut self, action: Action, probability: f64) -> (Action, bool) {
+        if probability > 0.0 && self.rng.gen::<f64>() < probability {
+            (action.flipped(), true)
+        } else {
+            (action, false)
+        }
+    }
+
+    /// Play the match to its end
+    pub fn play(mut self) -> LocalResult {
+        if !self.resumed {
+            self.blue.strategy.reset();
+            self.red.strategy.reset();
+        }
+        let mut transcript = std::mem::take(&mut self.played);
+        let stopped_by = loop {
+            let blue_chosen = self.blue.strategy.choose(&self.blue.history);
+            let red_chosen = self.red.strategy.choose(&self.red.history);
+
+            let (red, red_flipped) = self.perturb(red_chosen, self.noise);
+            let (blue, blue_flipped) = self.perturb(blue_chosen, self.noise);
+            let (red_seen, red_misperceived) = self.perturb(blue, self.misperception);
+            let (blue_seen, blue_misperceived) = self.perturb(red, self.misperception);
+
+            let round = transcript.len();
+            let outcome = self.payoffs.score(red, blue);
+            let weight = self.discount.powi(round as i32);
+            let (red_outcome, blue_outcome) =
+                (outcome.red.weighted(weight), outcome.blue.weighted(weight));
+            for (side, own, seen, outcome, flipped, misperceived) in [
+                (
+                    &mut self.blue,
+                    blue,
+                    blue_seen,
+                    blue_outcome,
+                    blue_flipped,
+                    blue_misperceived,
+                ),
+                (
+                    &mut self.red,
+                    red,
+                    red_seen,
+                    red_outcome,
+                    red_flipped,
+                    red_misperceived,
+                ),
+            ] {
+                side.history.push(Round {
+                    own,
+                    opponent: seen,
+                    hidden: false,
+                    outcome,
+                });
+                side.summary.score += outcome.amount;
+                side.summary.cooperations += (own == Action::COOPERATE) as usize;
+                side.summary.flips += flipped as usize;
+                side.summary.misperceptions += misperceived as usize;
+            }
+            let side = |chosen, executed, perceived, outcome| SideRecord {
+                chosen,
+                executed,
+                perceived,
+                hidden: false,
+                outcome,
+                timed_out: false,
+                over_budget: false,
+                signal: None,
+            };
+            transcript.push(RoundRecord {
+                round,
+                blue: side(blue_chosen, blue, blue_seen, blue_outcome),
+                red: side(red_chosen, red, red_seen, red_outcome),
+            });
+
+            let progress = MatchProgress {
+                rounds: transcript.len(),
+                blue_score: self.blue.summary.score,
+                red_score: self.red.summary.score,
+                last: (blue, red),
+            };
+            let rng = &mut self.rng;
+            let finished = self.terminations.iter_mut().find_map(|termination| {
+                if termination.finished(&progress, rng) {
+                    Some(termination.name())
+                } else {
+                    None
+                }
+            });
+            if let Some(reason) = finished {
+                break Some(reason).filter(|&reason| reason != FixedLength::NAME);
+            }
+        };
+        LocalResult {
+            rounds: transcript.len(),
+            stats: MatchStats::new(&transcript),
+            blue: self.blue.summary,
+            red: self.red.summary,
+            transcript,
+            stopped_by: stopped_by.map(str::to_owned),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "actors"))]
+mod tests {
+    use super::*;
+
+    #[actix_rt::test]
+    async fn a_local_match_plays_as_a_referee_does() {
+        use crate::config::{ParticipantConfig, SimConfig};
+        use crate::registry::{StrategyArgs, StrategyRegistry};
+        use crate::termination::TerminationConfig;
+        use crate::tournament::{self, MatchSeeds};
+
+        let registry = StrategyRegistry::new();
+        let config = SimConfig {
+            iterations: 300,
+            noise: 0.05,
+            misperception: 0.05,
+            termination: TerminationConfig::Continuation { probability: 0.995 },
+            participants: vec![
+                ParticipantConfig::new("gtft", "generous-tit-for-tat"),
+                ParticipantConfig::new("random", "random"),
+            ],
+            ..SimConfig::default()
+        };
+        let seeds = MatchSeeds {
+            blue: 11,
+            red: 22,
+            referee: 33,
+        };
+        let (blue, red) = (&config.participants[0], &config.participants[1]);
+        let refereed = tournament::start_match(
+            &config,
+            &registry,
+            blue,
+            red,
+            seeds,
+            None,
+            &tournament::Shared::default(),
+        )
+        .await
+        .result
+        .expect("match played");
+
+        let build = |participant: &ParticipantConfig, seed| {
+            let args = StrategyArgs {
+                parameters: &participant.parameters,
+                payoffs: &config.payoffs(),
+                seed,
+            };
+            registry.create(&participant.strategy, &args).unwrap()
+        };
+        let mut local = LocalMatch::new(
+            (&blue.name, build(blue, seeds.blue)),
+            (&red.name, build(red, seeds.red)),
+            config.payoffs(),
+            config.iterations,
+        )
+        .with_seed(seeds.referee)
+        .with_noise(config.noise)
+        .with_misperception(config.misperception);
+        if let Some(termination) = config.termination.build() {
+            local = local.with_termination(termination);
+        }
+        let local = local.play();
+
+        assert!(local.rounds > 10);
+        assert_eq!(local.rounds, refereed.rounds);
+        assert_eq!(local.stopped_by, refereed.stopped_by);
+        assert_eq!(local.transcript, refereed.transcript);
+        assert_eq!(local.blue.score, refereed.blue.score);
+        assert_eq!(local.red.flips, refereed.red.flips);
+    }
+}
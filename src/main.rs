@@ -1,218 +1,163 @@
-use actix::prelude::*;
-use log::debug;
-use rand::thread_rng;
-use rand::Rng;
-use std::collections::HashMap;
-use std::fmt;
-
-#[derive(Clone, Copy)]
-enum Action {
-    COOPERATE,
-    DEFECT,
+use clap::{CommandFactory, Parser, Subcommand};
+
+use actoripd::commands::analyze::{self, Analysis, DescribeArgs};
+use actoripd::commands::completions::{self, CompletionsArgs};
+use actoripd::commands::evolve::{self, EvolveArgs, GeneticArgs, MoranArgs, ReplicatorArgs};
+use actoripd::commands::records::{
+    self, ExportExperimentArgs, ImportExperimentArgs, LeaderboardCommand, ReplayArgs,
+};
+use actoripd::commands::run::{self, DuelArgs, RunArgs};
+#[cfg(feature = "server")]
+use actoripd::commands::services::ServeArgs;
+use actoripd::commands::services::{self, BenchArgs, WorkerArgs};
+use actoripd::commands::spatial::{
+    self, CompareArgs, LatticeArgs, NetworkArgs, PublicGoodsArgs, TeamsArgs,
+};
+use actoripd::commands::sweep::{self, SweepArgs, TuneArgs};
+use actoripd::commands::{self, GlobalArgs};
+
+#[derive(Parser)]
+#[command(name = "actoripd", about = "Actor Driven Iterated Prisoners Dilemma")]
+struct Cli {
+    #[command(flatten)]
+    global: GlobalArgs,
+
+    #[command(subcommand)]
+    command: Option<Command>,
 }
 
-impl fmt::Display for Action {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let s = match self {
-            Action::COOPERATE => "Cooperate",
-            Action::DEFECT => "Defect",
-        };
-        write!(f, "{}", s)
-    }
-}
+#[derive(Subcommand)]
+enum Command {
+    /// Run a simulation, two random prisoners by default
+    Run(Box<RunArgs>),
 
-#[derive(Hash, Eq, PartialEq, Clone, Copy, Debug)]
-enum Payoff {
-    /// Start the interrogation
-    NULL,
+    /// Play a single match between two strategies and print who won
+    Duel(DuelArgs),
 
-    /// If both players cooperate, they both receive the reward R for cooperating.
-    REWARD,
+    /// Evolve a population of the configured strategies over many generations
+    Evolve(EvolveArgs),
 
-    /// If both players defect, they both receive the punishment Payoff P.
-    PUNISHMENT,
+    /// Estimate fixation probabilities with repeated Moran processes
+    Moran(MoranArgs),
 
-    /// If Blue defects while Red cooperates, then Blue receives the temptation Payoff T, while Red receives the "sucker's" Payoff, S.
-    /// Similarly, if Blue cooperates while Red defects, then Blue receives the sucker's Payoff S, while Red receives the temptation Payoff T.
-    TEMPTATION,
-    SUCKER,
-}
+    /// Follow species' shares under deterministic replicator dynamics,
+    /// from one match per pair of species
+    Replicator(ReplicatorArgs),
 
-impl fmt::Display for Payoff {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let s = match self {
-            Payoff::NULL => "Null",
-            Payoff::REWARD => "Reward",
-            Payoff::PUNISHMENT => "Punishment",
-            Payoff::TEMPTATION => "Temptation",
-            Payoff::SUCKER => "Sucker",
-        };
-        write!(f, "{}", s)
-    }
-}
+    /// Evolve finite state machine strategies with a genetic algorithm
+    Genetic(GeneticArgs),
 
-type PayoffValues = HashMap<Payoff, usize>;
+    /// Play the configured strategies on a grid where each cell meets only its neighbours
+    Lattice(LatticeArgs),
 
-struct Interrogate {
-    sequence: usize,
-    prev_payoff: Payoff,
-    prev_amount: usize,
-}
+    /// Play the configured strategies on an interaction network, loaded or generated
+    Network(NetworkArgs),
 
-impl Message for Interrogate {
-    type Result = Action;
-}
+    /// Play the configured strategies well-mixed, on a lattice, and on a
+    /// network from the same start, and compare how each ends
+    Compare(CompareArgs),
 
-impl Handler<Interrogate> for Prisoner {
-    type Result = MessageResult<Interrogate>;
+    /// Play an n-player public goods game in every group of the participants
+    PublicGoods(PublicGoodsArgs),
 
-    fn handle(&mut self, msg: Interrogate, _ctx: &mut Context<Self>) -> Self::Result {
-        self.score += msg.prev_amount;
-        let action = self.strategy.choose();
+    /// Play teams of two participants against each other, one member of
+    /// each seated per round, for shared scores
+    Teams(TeamsArgs),
 
-        debug!(
-            "{}: Interrogate received: sequence = {}; prev payoff = {}, prev amount = {}, score = {} => action = {}",
-            self.name, msg.sequence, msg.prev_payoff, msg.prev_amount, self.score, action
-        );
+    /// List the registered strategies, what each does, and the parameters
+    /// it takes with their defaults
+    #[command(visible_alias = "list-strategies")]
+    Strategies,
 
-        MessageResult(action)
-    }
-}
+    /// Play a strategy against a panel of scripted opponents and print how
+    /// it answers them, cooperates, and retaliates
+    Describe(DescribeArgs),
 
-trait Strategy {
-    fn choose(&mut self) -> Action;
-}
+    /// Play the matches saved by `run --record` again and check they go the
+    /// same way
+    Replay(ReplayArgs),
 
-struct Prisoner {
-    strategy: Box<dyn Strategy>,
-    name: String,
-    score: usize,
-}
+    /// Bundle a config, its seed, and the scripts, modules, plugins, and
+    /// tables its strategies load into one file, to share the run
+    ExportExperiment(ExportExperimentArgs),
 
-impl Actor for Prisoner {
-    type Context = Context<Self>;
+    /// Unpack a bundle from `export-experiment` into a directory, ready to
+    /// run
+    ImportExperiment(ImportExperimentArgs),
 
-    fn started(&mut self, _ctx: &mut Context<Self>) {
-        debug!("Actor {}: starts", self.name);
-    }
- 
-    fn stopped(&mut self, _ctx: &mut Context<Self>) {
-        debug!("Actor {}: stops: final score: {}", self.name, self.score);
-    }
- }
+    /// Play a tournament at every point of a grid of settings and print a CSV
+    /// of the standings
+    Sweep(SweepArgs),
 
-fn main() {
-    const ITERATIONS: usize = 100;
+    /// Search a strategy's parameters for its best response to a pool of
+    /// opponents
+    Tune(TuneArgs),
 
-    std::env::set_var("RUST_LOG", "actoripd=debug,actix=info");
-    env_logger::init();
+    /// Show the leaderboard accumulated over past runs
+    #[command(subcommand)]
+    Leaderboard(LeaderboardCommand),
 
-    let system = System::new("prisoners-dilemma");
+    /// Characterize a strategy, or how stable a tournament's standings are
+    #[command(subcommand)]
+    Analyze(Analysis),
 
-    let execution = async {
-        let mut payoff_values: PayoffValues = HashMap::new();
-        payoff_values.insert(Payoff::REWARD, 3);
-        payoff_values.insert(Payoff::TEMPTATION, 4);
-        payoff_values.insert(Payoff::PUNISHMENT, 2);
-        payoff_values.insert(Payoff::SUCKER, 1);
+    /// Run matches and tournaments submitted over a JSON API
+    #[cfg(feature = "server")]
+    Serve(ServeArgs),
 
-        let blue_addr = Prisoner {
-            name: "blue".to_owned(),
-            strategy: Box::new(RandomStrategy {}),
-            score: 0,
-        }
-        .start();
-        let red_addr = Prisoner {
-            name: "red".to_owned(),
-            strategy: Box::new(RandomStrategy {}),
-            score: 0,
-        }
-        .start();
-
-        let mut sequence = 0;
-        let mut blue_payoff = Payoff::NULL;
-        let mut blue_amount = 0;
-        let mut red_payoff = Payoff::NULL;
-        let mut red_amount = 0;
-
-        loop {
-            let blue_result = blue_addr
-                .send(Interrogate {
-                    sequence,
-                    prev_payoff: blue_payoff,
-                    prev_amount: blue_amount,
-                })
-                .await;
-
-            let red_result = red_addr
-                .send(Interrogate {
-                    sequence,
-                    prev_payoff: red_payoff,
-                    prev_amount: red_amount,
-                })
-                .await;
-
-            let red_action = red_result.unwrap();
-            let blue_action = blue_result.unwrap();
-
-            let payoff = compute_payoff(red_action, blue_action);
-
-            red_payoff = payoff.0;
-            red_amount = *payoff_values.get(&red_payoff).unwrap_or(&0);
-
-            blue_payoff = payoff.1;
-            blue_amount = *payoff_values.get(&blue_payoff).unwrap_or(&0);
-
-            sequence += 1;
-            if sequence >= ITERATIONS {
-                debug!("completed {} iterations", sequence);
-                break;
-            }
-        }
-
-        System::current().stop();
+    /// Time a fixed workload of long matches, to catch slowdowns in the
+    /// messaging between actors
+    Bench(BenchArgs),
 
-    };
-    Arbiter::spawn(execution);
+    /// Play matches handed out by a `run --distribute` coordinator until it
+    /// has no more
+    Worker(WorkerArgs),
 
-    system.run().unwrap();
-}
-
-impl Strategy for Action {
-    fn choose(&mut self) -> Action {
-        *self
-    }
+    /// Print a script completing subcommands, options, and strategy names
+    /// in bash, zsh, fish, elvish, or PowerShell
+    Completions(CompletionsArgs),
 }
 
-struct RandomStrategy {}
-
-impl Strategy for RandomStrategy {
-    fn choose(&mut self) -> Action {
-        let action_number = thread_rng().gen::<u8>();
-        if action_number % 2 == 0 {
-            Action::COOPERATE
-        } else {
-            Action::DEFECT
+fn main() {
+    let cli = Cli::parse();
+    let global = &cli.global;
+    commands::init_logging(global);
+
+    match cli.command {
+        Some(Command::Run(ref args)) => run::run(global, args),
+        Some(Command::Duel(ref args)) => run::duel(global, args),
+        Some(Command::Evolve(ref args)) => evolve::evolve(global, args),
+        Some(Command::Moran(ref args)) => evolve::moran(global, args),
+        Some(Command::Replicator(ref args)) => evolve::replicator(global, args),
+        Some(Command::Genetic(ref args)) => evolve::genetic(global, args),
+        Some(Command::Lattice(ref args)) => spatial::lattice(global, args),
+        Some(Command::Network(ref args)) => spatial::network(global, args),
+        Some(Command::Compare(ref args)) => spatial::compare(global, args),
+        Some(Command::PublicGoods(ref args)) => spatial::public_goods(global, args),
+        Some(Command::Teams(ref args)) => spatial::teams(global, args),
+        Some(Command::Strategies) => records::strategies(global),
+        Some(Command::Describe(ref args)) => analyze::describe(global, args),
+        Some(Command::Replay(ref args)) => records::replay(global, args),
+        Some(Command::ExportExperiment(ref args)) => records::export_experiment(global, args),
+        Some(Command::ImportExperiment(ref args)) => records::import_experiment(global, args),
+        Some(Command::Sweep(ref args)) => sweep::sweep(global, args),
+        Some(Command::Tune(ref args)) => sweep::tune(global, args),
+        Some(Command::Leaderboard(LeaderboardCommand::Show(ref args))) => {
+            records::show_leaderboard(global, args)
         }
+        Some(Command::Analyze(Analysis::Fingerprint(ref args))) => {
+            analyze::fingerprint(global, args)
+        }
+        Some(Command::Analyze(Analysis::Invade(ref args))) => analyze::invade(global, args),
+        Some(Command::Analyze(Analysis::Robustness(ref args))) => analyze::robustness(global, args),
+        Some(Command::Analyze(Analysis::Exploit(ref args))) => analyze::exploit(global, args),
+        Some(Command::Analyze(Analysis::Seeds(ref args))) => analyze::seeds(global, args),
+        #[cfg(feature = "server")]
+        Some(Command::Serve(ref args)) => services::serve(global, args),
+        Some(Command::Bench(ref args)) => services::bench(global, args),
+        Some(Command::Worker(ref args)) => services::worker(global, args),
+        Some(Command::Completions(ref args)) => completions::completions(Cli::command(), args),
+        None => run::run(global, &RunArgs::default()),
     }
-}
-
-/// For payoff https://en.wikipedia.org/wiki/Prisoner's_dilemma
-///
-/// If both players cooperate, they both receive the reward R for cooperating.
-/// If both players defect, they both receive the punishment payoff P.
-/// If Blue defects while Red cooperates, then Blue receives the temptation payoff T, while Red receives the "sucker's" payoff, S.
-/// Similarly, if Blue cooperates while Red defects, then Blue receives the sucker's payoff S, while Red receives the temptation payoff T.
-///
-/// T > R > P > S
-/// We want 2R > T + S for the iterative game
-///
-
-fn compute_payoff(red: Action, blue: Action) -> (Payoff, Payoff) {
-    match (red, blue) {
-        (Action::COOPERATE, Action::COOPERATE) => (Payoff::REWARD, Payoff::REWARD),
-        (Action::DEFECT, Action::DEFECT) => (Payoff::PUNISHMENT, Payoff::PUNISHMENT),
-        (Action::DEFECT, Action::COOPERATE) => (Payoff::SUCKER, Payoff::TEMPTATION),
-        (Action::COOPERATE, Action::DEFECT) => (Payoff::TEMPTATION, Payoff::SUCKER),
-    }
+    commands::finish();
 }
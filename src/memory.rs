@@ -0,0 +1,77 @@
+//! What strategies remember of each opponent from one match to the next
+//!
+//! With `remember_opponents` in the config, the repetitions of a round robin
+//! are played one after another instead of all at once. A strategy that
+//! keeps something of an opponent through [`Strategy::remember`], such as
+//! `grim`'s grudge, is handed it back through [`Strategy::recall`] before
+//! its next match against the same opponent starts.
+//!
+//! Memories are kept by participant and opponent name, so a participant and
+//! its twin are known apart, and last one tournament: every round robin
+//! starts with none. Each match result records what both sides remembered
+//! as it ended, and the tournament's results list the last memory of every
+//! pairing.
+//!
+//! [`Strategy::remember`]: crate::strategy::Strategy::remember
+//! [`Strategy::recall`]: crate::strategy::Strategy::recall
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+use crate::referee::MatchResult;
+
+/// What every participant remembers of each of its opponents, shared by the
+/// matches of one tournament
+#[derive(Debug, Clone, Default)]
+pub struct Memories(Arc<Mutex<BTreeMap<(String, String), Value>>>);
+
+impl Memories {
+    pub fn new() -> Memories {
+        Memories::default()
+    }
+
+    /// What `owner` kept of `opponent` at the end of their last match
+    pub fn recall(&self, owner: &str, opponent: &str) -> Option<Value> {
+        let memories = self.0.lock().expect("memories lock");
+        memories
+            .get(&(owner.to_owned(), opponent.to_owned()))
+            .cloned()
+    }
+
+    /// Keep `memory` as what `owner` remembers of `opponent`, in place of
+    /// anything it remembered before
+    pub fn keep(&self, owner: &str, opponent: &str, memory: Value) {
+        let mut memories = self.0.lock().expect("memories lock");
+        memories.insert((owner.to_owned(), opponent.to_owned()), memory);
+    }
+}
+
+/// What one participant remembered of one opponent
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Memory {
+    pub owner: String,
+    pub opponent: String,
+    pub memory: Value,
+}
+
+/// The last thing each participant remembered of each opponent over
+/// `matches`, taken in order, by owner and then opponent
+pub fn last(matches: &[MatchResult]) -> Vec<Memory> {
+    let mut last = BTreeMap::new();
+    for result in matches {
+        for (own, other) in [(&result.blue, &result.red), (&result.red, &result.blue)] {
+            if let Some(memory) = &own.memory {
+                last.insert((own.name.clone(), other.name.clone()), memory.clone());
+            }
+        }
+    }
+    last.into_iter()
+        .map(|((owner, opponent), memory)| Memory {
+            owner,
+            opponent,
+            memory,
+        })
+        .collect()
+}
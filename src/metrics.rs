@@ -0,0 +1,162 @@
+//! Prometheus metrics for long-running experiments
+//!
+//! Matches and mailboxes record into process-wide metrics, which [`serve`]
+//! exposes at `/metrics` in the Prometheus text format:
+//!
+//! - `actoripd_matches_total`, matches finished
+//! - `actoripd_rounds_total{strategy}`, rounds played by each strategy
+//! - `actoripd_cooperations_total{strategy}`, and
+//!   `actoripd_cooperation_rate{strategy}`, the share of those rounds in
+//!   which it cooperated
+//! - `actoripd_match_duration_seconds`, a histogram of wall-clock match time
+//! - `actoripd_mailbox_latency_seconds`, a histogram of how long prisoners
+//!   take to answer each message
+//!
+//! Strategies are labelled by strategy name rather than participant name,
+//! so an evolving population doesn't create a series per individual.
+
+use actix_web::dev::Server;
+use actix_web::{web, App, HttpResponse, HttpServer};
+use prometheus::{
+    exponential_buckets, Encoder, GaugeVec, Histogram, HistogramOpts, IntCounter, IntCounterVec,
+    Opts, Registry, TextEncoder,
+};
+use std::io;
+use std::net::ToSocketAddrs;
+use std::sync::OnceLock;
+use std::time::Duration;
+use tracing::info;
+
+use crate::referee::MatchResult;
+
+static METRICS: OnceLock<Metrics> = OnceLock::new();
+
+struct Metrics {
+    registry: Registry,
+    matches: IntCounter,
+    rounds: IntCounterVec,
+    cooperations: IntCounterVec,
+    cooperation_rate: GaugeVec,
+    match_duration: Histogram,
+    mailbox_latency: Histogram,
+}
+
+impl Metrics {
+    fn new() -> Metrics {
+        let registry =
+            Registry::new_custom(Some("actoripd".to_owned()), None).expect("valid metric prefix");
+        let matches = IntCounter::new("matches_total", "Matches finished").expect("valid metric");
+        let rounds = IntCounterVec::new(
+            Opts::new("rounds_total", "Rounds played by each strategy"),
+            &["strategy"],
+        )
+        .expect("valid metric");
+        let cooperations = IntCounterVec::new(
+            Opts::new(
+                "cooperations_total",
+                "Rounds in which each strategy cooperated",
+            ),
+            &["strategy"],
+        )
+        .expect("valid metric");
+        let cooperation_rate = GaugeVec::new(
+            Opts::new(
+                "cooperation_rate",
+                "Share of its rounds in which each strategy cooperated",
+            ),
+            &["strategy"],
+        )
+        .expect("valid metric");
+        let match_duration = Histogram::with_opts(
+            HistogramOpts::new("match_duration_seconds", "Wall-clock time per match")
+                .buckets(exponential_buckets(0.001, 4.0, 10).expect("valid buckets")),
+        )
+        .expect("valid metric");
+        let mailbox_latency = Histogram::with_opts(
+            HistogramOpts::new(
+                "mailbox_latency_seconds",
+                "Time for a prisoner to answer a message",
+            )
+            .buckets(exponential_buckets(0.00001, 4.0, 12).expect("valid buckets")),
+        )
+        .expect("valid metric");
+
+        for collector in [
+            Box::new(matches.clone()) as Box<dyn prometheus::core::Collector>,
+            Box::new(rounds.clone()),
+            Box::new(cooperations.clone()),
+            Box::new(cooperation_rate.clone()),
+            Box::new(match_duration.clone()),
+            Box::new(mailbox_latency.clone()),
+        ] {
+            registry.register(collector).expect("unique metric");
+        }
+        Metrics {
+            registry,
+            matches,
+            rounds,
+            cooperations,
+            cooperation_rate,
+            match_duration,
+            mailbox_latency,
+        }
+    }
+}
+
+fn metrics() -> &'static Metrics {
+    METRICS.get_or_init(Metrics::new)
+}
+
+/// Record a finished match between the strategies `blue` and `red`
+pub fn record_match(blue: &str, red: &str, result: &MatchResult, duration: Duration) {
+    let metrics = metrics();
+    metrics.matches.inc();
+    metrics.match_duration.observe(duration.as_secs_f64());
+    for (strategy, player) in [(blue, &result.blue), (red, &result.red)] {
+        let rounds = metrics.rounds.with_label_values(&[strategy]);
+        let cooperations = metrics.cooperations.with_label_values(&[strategy]);
+        rounds.inc_by(result.rounds as u64);
+        cooperations.inc_by(player.cooperations as u64);
+        if rounds.get() > 0 {
+            metrics
+                .cooperation_rate
+                .with_label_values(&[strategy])
+                .set(cooperations.get() as f64 / rounds.get() as f64);
+        }
+    }
+}
+
+/// Record how long a prisoner took to answer one message
+pub fn observe_mailbox_latency(latency: Duration) {
+    metrics().mailbox_latency.observe(latency.as_secs_f64());
+}
+
+/// Everything recorded so far, in the Prometheus text format
+pub fn gather() -> String {
+    let mut buffer = Vec::new();
+    TextEncoder::new()
+        .encode(&metrics().registry.gather(), &mut buffer)
+        .expect("encodable metrics");
+    String::from_utf8(buffer).expect("utf-8 metrics")
+}
+
+async fn export() -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type(prometheus::TEXT_FORMAT)
+        .body(gather())
+}
+
+/// Serve `/metrics` at `address`, e.g. `0.0.0.0:9100`
+///
+/// Must be called from inside a running `System`; the server stops with it
+/// and leaves Ctrl-C to [`crate::interrupt`].
+pub fn serve<A: ToSocketAddrs>(address: A) -> io::Result<Server> {
+    let server = HttpServer::new(|| App::new().route("/metrics", web::get().to(export)))
+        .workers(1)
+        .disable_signals()
+        .bind(address)?;
+    for address in server.addrs() {
+        info!(%address, "serving metrics");
+    }
+    Ok(server.run())
+}
@@ -0,0 +1,292 @@
+//! Ranking on more than points
+//!
+//! A `[ranking]` table in the config ranks a tournament's standings by a
+//! weighted sum of three objectives instead of by total score:
+//!
+//! - `score`: points per round;
+//! - `cooperation`: the share of rounds it cooperated in;
+//! - `fairness`: how evenly its matches were shared, as one less the
+//!   mean gap between its points per round and its opponent's, over the
+//!   widest gap the payoffs allow.
+//!
+//! Each objective is scaled so that the field's worst is 0 and its best 1
+//! before it is weighted, so the weights say how much each counts whatever
+//! its units. Every standing is also marked if it is on the Pareto front:
+//! no one else does at least as well on every weighted objective and
+//! better on one.
+//!
+//! ```toml
+//! [ranking]
+//! score = 1.0
+//! cooperation = 0.5
+//! fairness = 0.5
+//! ```
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::game::PayoffMatrix;
+use crate::referee::MatchResult;
+use crate::tournament::Standing;
+
+/// How much each objective counts towards a standing's rank
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Ranking {
+    #[serde(default = "default_score")]
+    pub score: f64,
+    #[serde(default)]
+    pub cooperation: f64,
+    #[serde(default)]
+    pub fairness: f64,
+}
+
+fn default_score() -> f64 {
+    1.0
+}
+
+impl Default for Ranking {
+    fn default() -> Ranking {
+        Ranking {
+            score: default_score(),
+            cooperation: 0.0,
+            fairness: 0.0,
+        }
+    }
+}
+
+/// How a participant did on each objective, and what they add up to
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Objectives {
+    /// Points per round
+    pub score: f64,
+    pub cooperation: f64,
+
+    /// From 0, where every match went wholly to one side, to 1, where
+    /// every match was shared evenly
+    pub fairness: f64,
+
+    /// The weighted sum of the scaled objectives that the standings are
+    /// ranked by
+    pub combined: f64,
+
+    /// Whether no one else beats it on one weighted objective without
+    /// doing worse on another
+    pub pareto: bool,
+}
+
+impl Ranking {
+    /// Why the weights can't rank anyone, if they can't
+    pub fn check(&self) -> Result<(), String> {
+        let weights = [self.score, self.cooperation, self.fairness];
+        if weights.iter().any(|w| !w.is_finite()) {
+            return Err("ranking weights must be finite".to_owned());
+        }
+        if weights.iter().all(|&w| w == 0.0) {
+            return Err("at least one ranking weight must be other than 0".to_owned());
+        }
+        Ok(())
+    }
+
+    /// Score each of `standings` on the objectives, from the `matches` they
+    /// were totalled from under `payoffs`, and reorder them by the combined
+    /// objective, best first, then by score and name
+    pub fn rank(
+        &self,
+        payoffs: &PayoffMatrix,
+        matches: &[MatchResult],
+        mut standings: Vec<Standing>,
+    ) -> Vec<Standing> {
+        // the widest gap one round can open between the two sides
+        let widest = payoffs.temptation() - payoffs.sucker();
+        let mut gaps: BTreeMap<&str, (f64, usize)> = BTreeMap::new();
+        for result in matches {
+            let rounds = result.rounds.max(1) as f64;
+            let gap = (result.blue.score - result.red.score).abs() / rounds;
+            for player in &[&result.blue, &result.red] {
+                let entry = gaps.entry(player.name.as_str()).or_default();
+                entry.0 += gap;
+                entry.1 += 1;
+            }
+        }
+        let fairness = |name: &str| match gaps.get(name) {
+            Some(&(gap, matches)) if matches > 0 && widest > 0.0 => {
+                (1.0 - gap / matches as f64 / widest).clamp(0.0, 1.0)
+            }
+            _ => 1.0,
+        };
+        let raw: Vec<[f64; 3]> = standings
+            .iter()
+            .map(|s| [s.average_score, s.cooperation_rate, fairness(&s.name)])
+            .collect();
+
+        let weights = [self.score, self.cooperation, self.fairness];
+        let scaled: Vec<[f64; 3]> = {
+            let mut ranges = [(f64::INFINITY, f64::NEG_INFINITY); 3];
+            for values in &raw {
+                for (range, &value) in ranges.iter_mut().zip(values) {
+                    *range = (range.0.min(value), range.1.max(value));
+                }
+            }
+            raw.iter()
+                .map(|values| {
+                    let mut scaled = [0.0; 3];
+                    for (k, &(low, high)) in ranges.iter().enumerate() {
+                        if high > low {
+                            scaled[k] = (values[k] - low) / (high - low);
+                        }
+                    }
+                    scaled
+                })
+                .collect()
+        };
+        // a negative weight counts less of an objective as better
+        let oriented = |values: &[f64; 3]| -> [f64; 3] {
+            let mut oriented = [0.0; 3];
+            for k in 0..3 {
+                oriented[k] = values[k] * weights[k].signum();
+            }
+            oriented
+        };
+        let dominates = |a: &[f64; 3], b: &[f64; 3]| {
+            let (a, b) = (oriented(a), oriented(b));
+            let counted = (0..3).filter(|&k| weights[k] != 0.0);
+            counted.clone().all(|k| a[k] >= b[k]) && counted.into_iter().any(|k| a[k] > b[k])
+        };
+
+        for (i, standing) in standings.iter_mut().enumerate() {
+            let [score, cooperation, fairness] = raw[i];
+            standing.objectives = Some(Objectives {
+                score,
+                cooperation,
+                fairness,
+                combined: (0..3).map(|k| weights[k] * scaled[i][k]).sum(),
+                pareto: !raw.iter().any(|other| dominates(other, &raw[i])),
+            });
+        }
+        let combined = |s: &Standing| s.objectives.map_or(0.0, |o| o.combined);
+        standings.sort_by(|a, b| {
+            combined(b)
+                .total_cmp(&combined(a))
+                .then_with(|| b.score.total_cmp(&a.score))
+                .then_with(|| a.name.cmp(&b.name))
+        });
+        standings
+    }
+}
+
+/// The names on the Pareto front of `standings`, in standings order
+pub fn pareto_front(standings: &[Standing]) -> Vec<&str> {
+    standings
+        .iter()
+        .filter(|s| s.objectives.is_some_and(|o| o.pareto))
+        .map(|s| s.name.as_str())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{ParticipantConfig, SimConfig};
+    use crate::registry::StrategyRegistry;
+    use crate::tournament::{self, TournamentRecord};
+
+    async fn ranked(ranking: Ranking) -> TournamentRecord {
+        let config = SimConfig {
+            iterations: 10,
+            ranking: Some(ranking),
+            participants: vec![
+                ParticipantConfig::new("allc", "always-cooperate"),
+                ParticipantConfig::new("alld", "always-defect"),
+                ParticipantConfig::new("tft", "tit-for-tat"),
+            ],
+            ..SimConfig::default()
+        };
+        let registry = StrategyRegistry::new();
+        config.validate(&registry).unwrap();
+        let reports = tournament::round_robin(&config, &registry, 1, None).await;
+        TournamentRecord::new(&config, 1, reports)
+    }
+
+    fn names(record: &TournamentRecord) -> Vec<&str> {
+        record.standings.iter().map(|s| s.name.as_str()).collect()
+    }
+
+    #[actix_rt::test]
+    async fn score_alone_ranks_as_the_plain_standings_do() {
+        let record = ranked(Ranking::default()).await;
+        assert_eq!(names(&record), vec!["alld", "tft", "allc"]);
+        // only the best scorer is best at the one objective weighed
+        assert_eq!(pareto_front(&record.standings), vec!["alld"]);
+        let alld = record.standings[0].objectives.unwrap();
+        assert_eq!(
+            (alld.score, alld.cooperation, alld.combined),
+            (3.1, 0.0, 1.0)
+        );
+    }
+
+    #[actix_rt::test]
+    async fn cooperation_can_outweigh_score() {
+        let record = ranked(Ranking {
+            score: 1.0,
+            cooperation: 2.0,
+            fairness: 0.0,
+        })
+        .await;
+        assert_eq!(names(&record), vec!["allc", "tft", "alld"]);
+        // each gives up one objective for the other
+        assert_eq!(pareto_front(&record.standings), vec!["allc", "tft", "alld"]);
+        let tft = record.standings[1].objectives.unwrap();
+        assert_eq!(tft.cooperation, 0.55);
+        assert!((tft.combined - (0.45 / 1.1 + 2.0 * 0.55)).abs() < 1e-9);
+    }
+
+    #[actix_rt::test]
+    async fn fairness_is_how_evenly_matches_were_shared() {
+        let record = ranked(Ranking {
+            score: 1.0,
+            cooperation: 0.0,
+            fairness: 1.0,
+        })
+        .await;
+        let fairness = |name: &str| {
+            let standing = record.standings.iter().find(|s| s.name == name).unwrap();
+            standing.objectives.unwrap().fairness
+        };
+        // alld takes 3 a round more than allc, and 0.3 more than tft
+        assert!((fairness("allc") - 0.5).abs() < 1e-9);
+        assert!((fairness("alld") - 0.45).abs() < 1e-9);
+        assert!((fairness("tft") - 0.95).abs() < 1e-9);
+        // tft scores more than allc and shares more evenly
+        assert_eq!(names(&record), vec!["tft", "alld", "allc"]);
+        assert_eq!(pareto_front(&record.standings), vec!["tft", "alld"]);
+    }
+
+    #[test]
+    fn weights_must_rank_something() {
+        let config: SimConfig = SimConfig::parse(
+            "[ranking]\ncooperation = 0.5\n\n[[participants]]\nname = \"a\"\nstrategy = \"grim\"\n",
+        )
+        .unwrap();
+        assert_eq!(
+            config.ranking,
+            Some(Ranking {
+                score: 1.0,
+                cooperation: 0.5,
+                fairness: 0.0
+            })
+        );
+        let none = Ranking {
+            score: 0.0,
+            cooperation: 0.0,
+            fairness: 0.0,
+        };
+        assert!(none.check().is_err());
+        let endless = Ranking {
+            fairness: f64::INFINITY,
+            ..Ranking::default()
+        };
+        assert!(endless.check().is_err());
+        assert!(SimConfig::parse("[ranking]\nluck = 1\n").is_err());
+    }
+}
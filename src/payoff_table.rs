@@ -0,0 +1,137 @@
+//! How every participant did against every other
+//!
+//! The cell in row `i` and column `j` is participant `i`'s average points
+//! per round in its matches against participant `j`, over every
+//! repetition. With self-play the diagonal holds each participant's score
+//! against its twin. A tournament's table is the usual way to compare two
+//! ecosystems of strategies, since it shows who earns their score from whom.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::config::twin_name;
+use crate::fingerprint::{colour, png};
+use crate::game::PayoffMatrix;
+use crate::tournament::TournamentRecord;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PayoffTable {
+    /// The participants, in config order, naming both the rows and the
+    /// columns
+    pub names: Vec<String>,
+
+    /// Row by row; none where the pair didn't play, or every match between
+    /// them was aborted
+    pub scores: Vec<Vec<Option<f64>>>,
+    pub payoffs: PayoffMatrix,
+}
+
+impl PayoffTable {
+    pub fn new(record: &TournamentRecord) -> PayoffTable {
+        let names: Vec<String> = record
+            .config
+            .participants
+            .iter()
+            .map(|p| p.name.clone())
+            .collect();
+        let players: HashMap<&str, usize> = names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| (name.as_str(), i))
+            .collect();
+        // a twin is only ever an opponent, standing in for its original
+        let twins: Vec<String> = names.iter().map(|name| twin_name(name)).collect();
+        let opponents: HashMap<&str, usize> = players
+            .iter()
+            .map(|(&name, &i)| (name, i))
+            .chain(twins.iter().enumerate().map(|(i, twin)| (twin.as_str(), i)))
+            .collect();
+
+        let n = names.len();
+        let mut totals = vec![vec![(0.0, 0); n]; n];
+        for result in &record.matches {
+            for (player, opponent) in [(&result.blue, &result.red), (&result.red, &result.blue)] {
+                if let (Some(&i), Some(&j)) = (
+                    players.get(player.name.as_str()),
+                    opponents.get(opponent.name.as_str()),
+                ) {
+                    totals[i][j].0 += player.score;
+                    totals[i][j].1 += result.rounds;
+                }
+            }
+        }
+        let scores = totals
+            .into_iter()
+            .map(|row| {
+                row.into_iter()
+                    .map(|(score, rounds)| {
+                        if rounds == 0 {
+                            None
+                        } else {
+                            Some(score / rounds as f64)
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+        PayoffTable {
+            names,
+            scores,
+            payoffs: record.config.payoffs(),
+        }
+    }
+
+    /// A header naming the columns, then one line per row starting with its
+    /// name; missing cells are left empty
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("player");
+        for name in &self.names {
+            csv.push(',');
+            csv.push_str(name);
+        }
+        csv.push('\n');
+        for (name, row) in self.names.iter().zip(&self.scores) {
+            csv.push_str(name);
+            for score in row {
+                csv.push(',');
+                if let Some(score) = score {
+                    csv.push_str(&score.to_string());
+                }
+            }
+            csv.push('\n');
+        }
+        csv
+    }
+
+    /// Draw the table as a PNG heat map, rows downwards and columns
+    /// rightwards in config order
+    ///
+    /// Colours run as in a fingerprint, from dark purple at the sucker's
+    /// payoff to yellow at the temptation; missing cells are left black.
+    pub fn write_png<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        const CELL: usize = 32;
+        let n = self.names.len();
+        let (low, high) = (self.payoffs.sucker(), self.payoffs.temptation());
+        let side = (n * CELL).max(1);
+        let mut pixels = Vec::with_capacity(side * (1 + 3 * side));
+        for y in 0..side {
+            // each scanline starts with its filter type, none
+            pixels.push(0);
+            for x in 0..side {
+                let cell = self
+                    .scores
+                    .get(y / CELL)
+                    .and_then(|row| row.get(x / CELL))
+                    .copied()
+                    .flatten();
+                pixels.extend_from_slice(
+                    &cell.map_or([0, 0, 0], |score| colour((score - low) / (high - low))),
+                );
+            }
+        }
+        fs::write(path, png(side as u32, side as u32, &pixels))
+    }
+}
@@ -0,0 +1,313 @@
+//! Charts drawn straight from a run's records
+//!
+//! Each chart is a set of lines, one per participant or species, written as
+//! SVG if the path ends in `.svg` and as PNG otherwise:
+//!
+//! - [`cumulative_scores`]: each participant's points so far, round by
+//!   round, summed over its matches in a tournament
+//! - [`cooperation`]: the share of each participant's matches in which it
+//!   cooperated, round by round
+//! - [`population_shares`]: each species' share of an evolving population,
+//!   generation by generation
+//! - [`replicator_shares`]: the same for replicator dynamics
+//!
+//! [`lattice_frames`] instead draws a spatial run's grid, one PNG per frame,
+//! for animating.
+
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use std::fmt;
+use std::path::Path;
+
+use crate::evolution::{EvolutionRecord, ReplicatorRecord};
+use crate::game::Action;
+use crate::referee::SideRecord;
+use crate::spatial::{self, SpatialRecord};
+use crate::topology::Lattice;
+use crate::tournament::TournamentRecord;
+
+const SIZE: (u32, u32) = (1024, 640);
+
+#[derive(Debug)]
+pub struct PlotError(String);
+
+impl fmt::Display for PlotError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "unable to draw chart: {}", self.0)
+    }
+}
+
+impl std::error::Error for PlotError {}
+
+/// A line chart: named lines of `(x, y)` points
+struct Chart<'a> {
+    title: &'a str,
+    x_label: &'a str,
+    y_label: &'a str,
+    lines: Vec<(String, Vec<(f64, f64)>)>,
+}
+
+impl Chart<'_> {
+    fn write(&self, path: &Path) -> Result<(), PlotError> {
+        let svg = path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("svg"));
+        let drawn = if svg {
+            self.draw(SVGBackend::new(path, SIZE).into_drawing_area())
+                .map_err(|e| e.to_string())
+        } else {
+            self.draw(BitMapBackend::new(path, SIZE).into_drawing_area())
+                .map_err(|e| e.to_string())
+        };
+        drawn.map_err(PlotError)
+    }
+
+    fn draw<DB: DrawingBackend>(
+        &self,
+        root: DrawingArea<DB, Shift>,
+    ) -> Result<(), DrawingAreaErrorKind<DB::ErrorType>> {
+        let points = || self.lines.iter().flat_map(|(_, points)| points.iter());
+        let bounds = |coordinate: fn(&(f64, f64)) -> f64| {
+            points()
+                .map(coordinate)
+                .fold((0.0f64, 0.0f64), |(low, high), v| (low.min(v), high.max(v)))
+        };
+        let (x_low, x_high) = bounds(|p| p.0);
+        let (y_low, y_high) = bounds(|p| p.1);
+        // leave room above the highest line, and never an empty range
+        let y_high = if y_high > y_low {
+            y_high + (y_high - y_low) * 0.05
+        } else {
+            y_low + 1.0
+        };
+        let x_high = if x_high > x_low { x_high } else { x_low + 1.0 };
+
+        root.fill(&WHITE)?;
+        let mut chart = ChartBuilder::on(&root)
+            .caption(self.title, ("sans-serif", 24))
+            .margin(16)
+            .x_label_area_size(40)
+            .y_label_area_size(64)
+            .build_cartesian_2d(x_low..x_high, y_low..y_high)?;
+        chart
+            .configure_mesh()
+            .x_desc(self.x_label)
+            .x_label_formatter(&|x| format!("{}", x))
+            .y_desc(self.y_label)
+            .draw()?;
+        for (i, (name, points)) in self.lines.iter().enumerate() {
+            let colour = Palette99::pick(i).to_rgba();
+            chart
+                .draw_series(LineSeries::new(
+                    points.iter().copied(),
+                    colour.stroke_width(2),
+                ))?
+                .label(name)
+                .legend(move |(x, y)| {
+                    PathElement::new(vec![(x, y), (x + 20, y)], colour.stroke_width(2))
+                });
+        }
+        chart
+            .configure_series_labels()
+            .background_style(WHITE.mix(0.8))
+            .border_style(BLACK)
+            .draw()?;
+        root.present()
+    }
+}
+
+/// Each participant's side of every match it played, leaving out its twin's
+fn sides<'a>(
+    record: &'a TournamentRecord,
+    name: &'a str,
+) -> impl Iterator<Item = Vec<&'a SideRecord>> + 'a {
+    record.matches.iter().filter_map(move |result| {
+        if result.blue.name == name {
+            Some(result.transcript.iter().map(|round| &round.blue).collect())
+        } else if result.red.name == name {
+            Some(result.transcript.iter().map(|round| &round.red).collect())
+        } else {
+            None
+        }
+    })
+}
+
+/// Each participant's total points after every round, over all its matches
+///
+/// A match that has ended keeps contributing its final score.
+pub fn cumulative_scores(record: &TournamentRecord, path: &Path) -> Result<(), PlotError> {
+    let lines = record
+        .config
+        .participants
+        .iter()
+        .map(|participant| {
+            let mut gains: Vec<f64> = Vec::new();
+            for side in sides(record, &participant.name) {
+                if gains.len() < side.len() {
+                    gains.resize(side.len(), 0.0);
+                }
+                for (t, s) in side.iter().enumerate() {
+                    gains[t] += s.outcome.amount;
+                }
+            }
+            let mut total = 0.0;
+            let points = gains
+                .iter()
+                .enumerate()
+                .map(|(t, gain)| {
+                    total += gain;
+                    ((t + 1) as f64, total)
+                })
+                .collect();
+            (participant.name.clone(), points)
+        })
+        .collect();
+    Chart {
+        title: "Cumulative score",
+        x_label: "round",
+        y_label: "points over all matches",
+        lines,
+    }
+    .write(path)
+}
+
+/// The share of each participant's matches, still going at each round, in
+/// which it cooperated in that round
+pub fn cooperation(record: &TournamentRecord, path: &Path) -> Result<(), PlotError> {
+    let lines = record
+        .config
+        .participants
+        .iter()
+        .map(|participant| {
+            let mut counts: Vec<(usize, usize)> = Vec::new();
+            for side in sides(record, &participant.name) {
+                if counts.len() < side.len() {
+                    counts.resize(side.len(), (0, 0));
+                }
+                for (t, s) in side.iter().enumerate() {
+                    counts[t].1 += 1;
+                    if s.executed == Action::COOPERATE {
+                        counts[t].0 += 1;
+                    }
+                }
+            }
+            let points = counts
+                .iter()
+                .enumerate()
+                .map(|(t, &(cooperations, matches))| {
+                    ((t + 1) as f64, cooperations as f64 / matches as f64)
+                })
+                .collect();
+            (participant.name.clone(), points)
+        })
+        .collect();
+    Chart {
+        title: "Cooperation",
+        x_label: "round",
+        y_label: "share of matches cooperating",
+        lines,
+    }
+    .write(path)
+}
+
+/// Each species' share of the population at the start of every generation
+pub fn population_shares(record: &EvolutionRecord, path: &Path) -> Result<(), PlotError> {
+    let lines = record
+        .species
+        .iter()
+        .map(|species| {
+            let points = record
+                .generations
+                .iter()
+                .map(|generation| {
+                    let count = generation
+                        .composition
+                        .get(&species.name)
+                        .copied()
+                        .unwrap_or(0);
+                    let total: usize = generation.composition.values().sum();
+                    (
+                        generation.generation as f64,
+                        count as f64 / total.max(1) as f64,
+                    )
+                })
+                .collect();
+            (species.name.clone(), points)
+        })
+        .collect();
+    Chart {
+        title: "Population shares",
+        x_label: "generation",
+        y_label: "share of population",
+        lines,
+    }
+    .write(path)
+}
+
+/// Each species' share under replicator dynamics at the start of every
+/// generation
+pub fn replicator_shares(record: &ReplicatorRecord, path: &Path) -> Result<(), PlotError> {
+    let lines = record
+        .config
+        .participants
+        .iter()
+        .map(|species| {
+            let points = record
+                .generations
+                .iter()
+                .map(|generation| {
+                    let share = generation.shares.get(&species.name).copied().unwrap_or(0.0);
+                    (generation.generation as f64, share)
+                })
+                .collect();
+            (species.name.clone(), points)
+        })
+        .collect();
+    Chart {
+        title: "Replicator dynamics",
+        x_label: "generation",
+        y_label: "share of population",
+        lines,
+    }
+    .write(path)
+}
+
+/// Pixels a side for each lattice cell, so that a frame is about 640 wide
+fn cell_size(lattice: Lattice) -> u32 {
+    (640 / lattice.width.max(lattice.height) as u32).max(4)
+}
+
+/// Draw every `stride`th generation of a lattice run to `dir`, created if
+/// need be, as `frame-0000.png`, `frame-0001.png`, and so on, one coloured
+/// square per cell, returning how many frames were drawn
+///
+/// Each participant keeps its colour from frame to frame, the colour of its
+/// line in the other charts.
+pub fn lattice_frames(
+    record: &SpatialRecord,
+    lattice: Lattice,
+    dir: &Path,
+    stride: usize,
+) -> Result<usize, PlotError> {
+    std::fs::create_dir_all(dir).map_err(|e| PlotError(e.to_string()))?;
+    let cell = cell_size(lattice);
+    let size = (lattice.width as u32 * cell, lattice.height as u32 * cell);
+    let frames = record.frames(stride);
+    for (frame, &(_, state)) in frames.iter().enumerate() {
+        let path = spatial::frame_path(dir, frame, "png");
+        let root = BitMapBackend::new(&path, size).into_drawing_area();
+        let drawn = (|| {
+            for (node, &species) in state.iter().enumerate() {
+                let (x, y) = ((node % lattice.width) as i32, (node / lattice.width) as i32);
+                let (x, y, side) = (x * cell as i32, y * cell as i32, cell as i32);
+                root.draw(&Rectangle::new(
+                    [(x, y), (x + side, y + side)],
+                    Palette99::pick(species).filled(),
+                ))?;
+            }
+            root.present()
+        })();
+        drawn.map_err(|e| PlotError(e.to_string()))?;
+    }
+    Ok(frames.len())
+}
@@ -0,0 +1,143 @@
+//! Strategies compiled into native dynamic libraries, loaded at run time
+//!
+//! A plugin is a `cdylib` exporting these `extern "C"` functions:
+//!
+//! - `actoripd_abi_version() -> u32`, which must return [`ABI_VERSION`]
+//! - `actoripd_new(seed: u64) -> *mut c_void`, a fresh instance for one prisoner
+//! - `actoripd_choose(instance: *mut c_void, round: u32, own: i32, opponent: i32) -> i32`,
+//!   given the round about to be played, counting from 0, and what each side
+//!   did last round as [`COOPERATE`], [`DEFECT`], or [`NONE`] before the first
+//!   round. It returns the action to play.
+//! - `actoripd_free(instance: *mut c_void)`, called once when the prisoner is done
+//!
+//! An instance is only ever used by one thread at a time, but may move
+//! between threads. Unlike WebAssembly strategies, plugins are not
+//! sandboxed: only load libraries you trust. See `examples/plugin` for a
+//! plugin crate. Name a plugin in a config as
+//! `strategy = "plugin:path/to/libstrategy.so"`.
+
+use libloading::Library;
+use std::ffi::c_void;
+
+use crate::game::Action;
+use crate::strategy::{History, Strategy};
+
+/// The version of the interface described above
+pub const ABI_VERSION: u32 = 1;
+
+pub const COOPERATE: i32 = 0;
+pub const DEFECT: i32 = 1;
+
+/// Only seen, or worth answering, in games with a loner's payoff
+pub const ABSTAIN: i32 = 2;
+pub const NONE: i32 = -1;
+
+type AbiVersionFn = unsafe extern "C" fn() -> u32;
+type NewFn = unsafe extern "C" fn(u64) -> *mut c_void;
+type ChooseFn = unsafe extern "C" fn(*mut c_void, u32, i32, i32) -> i32;
+type FreeFn = unsafe extern "C" fn(*mut c_void);
+
+pub struct PluginStrategy {
+    name: String,
+    path: String,
+    instance: *mut c_void,
+    choose: ChooseFn,
+    free: FreeFn,
+
+    /// Keeps the functions above loaded; dropped after `instance` is freed
+    _library: Library,
+}
+
+// The ABI requires that an instance can move between threads as long as
+// only one uses it at a time, which `&mut self` guarantees.
+unsafe impl Send for PluginStrategy {}
+
+impl PluginStrategy {
+    /// Load the library at `path` and create one instance from it
+    pub fn load(path: &str, seed: u64) -> Result<PluginStrategy, String> {
+        let fail = |e: libloading::Error| format!("{}: {}", path, e);
+        // SAFETY: loading runs the library's initialisers; plugins are trusted
+        let library =
+            unsafe { Library::new(path) }.map_err(|e| format!("unable to load {}: {}", path, e))?;
+        // SAFETY: the symbols are declared with the types the ABI specifies
+        let (version, new, choose, free) = unsafe {
+            (
+                *library
+                    .get::<AbiVersionFn>(b"actoripd_abi_version")
+                    .map_err(fail)?,
+                *library.get::<NewFn>(b"actoripd_new").map_err(fail)?,
+                *library.get::<ChooseFn>(b"actoripd_choose").map_err(fail)?,
+                *library.get::<FreeFn>(b"actoripd_free").map_err(fail)?,
+            )
+        };
+
+        // SAFETY: as above
+        let found = unsafe { version() };
+        if found != ABI_VERSION {
+            return Err(format!(
+                "{} implements plugin ABI version {}, expected {}",
+                path, found, ABI_VERSION
+            ));
+        }
+        // SAFETY: as above
+        let instance = unsafe { new(seed) };
+        if instance.is_null() {
+            return Err(format!("{}: actoripd_new returned null", path));
+        }
+        Ok(PluginStrategy {
+            name: format!("plugin:{}", path),
+            path: path.to_owned(),
+            instance,
+            choose,
+            free,
+            _library: library,
+        })
+    }
+}
+
+fn encode(action: Option<Action>) -> i32 {
+    match action {
+        Some(action) => action.index() as i32,
+        None => NONE,
+    }
+}
+
+impl Strategy for PluginStrategy {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Panics if the plugin returns an unknown action; the prisoner turns
+    /// that into a forfeit
+    fn choose(&mut self, history: &History) -> Action {
+        let last = history.last();
+        // SAFETY: `instance` came from this library's `actoripd_new` and
+        // hasn't been freed
+        let action = unsafe {
+            (self.choose)(
+                self.instance,
+                history.round() as u32,
+                encode(last.map(|r| r.own)),
+                encode(last.map(|r| r.opponent)),
+            )
+        };
+        match action {
+            COOPERATE => Action::COOPERATE,
+            DEFECT => Action::DEFECT,
+            ABSTAIN => Action::ABSTAIN,
+            other => panic!("{}: choose returned unknown action {}", self.path, other),
+        }
+    }
+
+    /// The plugin keeps its own memory of the match
+    fn memory_depth(&self) -> Option<usize> {
+        Some(1)
+    }
+}
+
+impl Drop for PluginStrategy {
+    fn drop(&mut self) {
+        // SAFETY: `instance` is freed exactly once, while the library is loaded
+        unsafe { (self.free)(self.instance) }
+    }
+}
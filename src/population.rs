@@ -0,0 +1,266 @@
+//! Populations to start evolutionary and spatial runs from
+//!
+//! By default `evolve` splits its population evenly across the
+//! participants, and a spatial run draws each node's participant at random.
+//! A [`Population`] file sets the start instead, so that a scenario such as
+//! one defector in a sea of cooperators can be set up exactly. It is JSON,
+//! in one of three forms:
+//!
+//! - `{"counts": {"allc": 99, "alld": 1}}`, how many individuals of each
+//!   participant, placed at random on a spatial run;
+//! - `{"nodes": ["allc", "alld", ...]}`, the participant at each node in
+//!   turn, a lattice's row by row;
+//! - `{"fill": "allc", "at": {"55": "alld"}}`, one participant everywhere
+//!   but at the nodes given.
+//!
+//! The snapshots that spatial runs write are of the second form, and those
+//! that [`write_snapshots`] writes of an evolutionary run are of the first,
+//! so any generation of a run can be started from again.
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::config::ParticipantConfig;
+use crate::evolution::{Composition, EvolutionRecord};
+
+/// The participant of every individual at the start of a run, by name
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged, try_from = "PopulationFile")]
+pub enum Population {
+    /// How many individuals of each participant
+    Counts { counts: BTreeMap<String, usize> },
+
+    /// The participant at each node, in node order
+    Nodes { nodes: Vec<String> },
+
+    /// The participant at every node not in `at`
+    Fill {
+        fill: String,
+        #[serde(default)]
+        at: BTreeMap<usize, String>,
+    },
+}
+
+/// A population as written, whichever form it takes; anything else in the
+/// file, such as a snapshot's generation, is left aside
+#[derive(Deserialize)]
+struct PopulationFile {
+    counts: Option<BTreeMap<String, usize>>,
+    nodes: Option<Vec<String>>,
+    fill: Option<String>,
+    #[serde(default)]
+    at: BTreeMap<usize, String>,
+}
+
+impl TryFrom<PopulationFile> for Population {
+    type Error = String;
+
+    fn try_from(file: PopulationFile) -> Result<Population, String> {
+        match (file.counts, file.nodes, file.fill) {
+            (Some(counts), None, None) => Ok(Population::Counts { counts }),
+            (None, Some(nodes), None) => Ok(Population::Nodes { nodes }),
+            (None, None, Some(fill)) => Ok(Population::Fill { fill, at: file.at }),
+            _ => Err("expected one of counts, nodes, or fill".to_owned()),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum PopulationError {
+    Io(io::Error),
+    Parse(serde_json::Error),
+}
+
+impl fmt::Display for PopulationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PopulationError::Io(e) => write!(f, "unable to read population: {}", e),
+            PopulationError::Parse(e) => write!(f, "unable to parse population: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for PopulationError {}
+
+impl Population {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Population, PopulationError> {
+        let text = fs::read_to_string(path).map_err(PopulationError::Io)?;
+        serde_json::from_str(&text).map_err(PopulationError::Parse)
+    }
+
+    /// Each individual's participant, as an index into `participants`
+    ///
+    /// Counts and nodes say how many individuals there are, counts in the
+    /// order of `participants`; a fill has `size`.
+    pub fn individuals(
+        &self,
+        participants: &[ParticipantConfig],
+        size: usize,
+    ) -> Result<Vec<usize>, String> {
+        let index = |name: &str| {
+            participants
+                .iter()
+                .position(|p| p.name == name)
+                .ok_or_else(|| format!("the population has {}, which is no participant", name))
+        };
+        match self {
+            Population::Counts { counts } => {
+                for name in counts.keys() {
+                    index(name)?;
+                }
+                Ok((0..participants.len())
+                    .flat_map(|s| {
+                        let count = counts.get(&participants[s].name).copied();
+                        std::iter::repeat_n(s, count.unwrap_or(0))
+                    })
+                    .collect())
+            }
+            Population::Nodes { nodes } => nodes.iter().map(|name| index(name)).collect(),
+            Population::Fill { fill, at } => {
+                let mut individuals = vec![index(fill)?; size];
+                for (&node, name) in at {
+                    if node >= size {
+                        return Err(format!(
+                            "the population puts {} at node {}, of only {}",
+                            name, node, size
+                        ));
+                    }
+                    individuals[node] = index(name)?;
+                }
+                Ok(individuals)
+            }
+        }
+    }
+
+    /// Each of `nodes` nodes' participant, counts placed at random
+    pub fn place(
+        &self,
+        participants: &[ParticipantConfig],
+        nodes: usize,
+        rng: &mut StdRng,
+    ) -> Result<Vec<usize>, String> {
+        let mut individuals = self.individuals(participants, nodes)?;
+        if individuals.len() != nodes {
+            return Err(format!(
+                "the population has {} individuals for {} nodes",
+                individuals.len(),
+                nodes
+            ));
+        }
+        if let Population::Counts { .. } = self {
+            individuals.shuffle(rng);
+        }
+        Ok(individuals)
+    }
+}
+
+/// One generation of an evolutionary run, as written by [`write_snapshots`]
+#[derive(Debug, Clone, Serialize)]
+pub struct Snapshot<'a> {
+    pub generation: usize,
+    pub counts: &'a Composition,
+}
+
+/// The file for generation `generation` in `dir`
+pub fn snapshot_path(dir: &Path, generation: usize) -> PathBuf {
+    dir.join(format!("generation-{:04}.json", generation))
+}
+
+/// Write every `stride`th generation of `record` to `dir`, created if need
+/// be, as a population to start from, and the survivors as the generation
+/// after the last whatever the stride, returning how many were written
+pub fn write_snapshots(record: &EvolutionRecord, dir: &Path, stride: usize) -> io::Result<usize> {
+    fs::create_dir_all(dir)?;
+    let last = record.generations.len();
+    let generations = record
+        .generations
+        .iter()
+        .map(|g| (g.generation, &g.composition))
+        .chain(std::iter::once((last, &record.survivors)))
+        .filter(|&(generation, _)| generation % stride.max(1) == 0 || generation == last);
+    let mut written = 0;
+    for (generation, counts) in generations {
+        let snapshot = Snapshot { generation, counts };
+        let json = serde_json::to_string(&snapshot).map_err(io::Error::other)?;
+        fs::write(snapshot_path(dir, generation), json + "\n")?;
+        written += 1;
+    }
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::SimConfig;
+    use crate::evolution::{self, EvolutionCheckpoint};
+    use crate::registry::StrategyRegistry;
+    use rand::SeedableRng;
+
+    fn participants() -> Vec<ParticipantConfig> {
+        vec![
+            ParticipantConfig::new("allc", "always-cooperate"),
+            ParticipantConfig::new("alld", "always-defect"),
+        ]
+    }
+
+    #[test]
+    fn every_form_names_each_individual() {
+        let read = |json: &str| serde_json::from_str::<Population>(json).unwrap();
+        let counts = read(r#"{"counts": {"alld": 1, "allc": 2}}"#);
+        assert_eq!(counts.individuals(&participants(), 9), Ok(vec![0, 0, 1]));
+        // a spatial snapshot, with its generation and grid
+        let nodes = read(r#"{"generation": 3, "nodes": ["alld", "allc"], "lattice": null}"#);
+        assert_eq!(nodes.individuals(&participants(), 9), Ok(vec![1, 0]));
+        let fill = read(r#"{"fill": "allc", "at": {"2": "alld"}}"#);
+        assert_eq!(fill.individuals(&participants(), 4), Ok(vec![0, 0, 1, 0]));
+
+        assert!(fill.individuals(&participants(), 2).is_err());
+        assert!(serde_json::from_str::<Population>(r#"{"fill": "allc", "nodes": []}"#).is_err());
+        assert!(read(r#"{"counts": {"tft": 1}}"#)
+            .individuals(&participants(), 1)
+            .is_err());
+        let mut rng = StdRng::seed_from_u64(1);
+        assert!(counts.place(&participants(), 4, &mut rng).is_err());
+        let mut placed = counts.place(&participants(), 3, &mut rng).unwrap();
+        placed.sort();
+        assert_eq!(placed, vec![0, 0, 1]);
+    }
+
+    #[actix_rt::test]
+    async fn an_evolutionary_run_restarts_from_its_snapshots() {
+        let config = SimConfig {
+            iterations: 10,
+            participants: participants(),
+            ..SimConfig::default()
+        };
+        let registry = StrategyRegistry::new();
+        let one_defector = Population::Fill {
+            fill: "allc".to_owned(),
+            at: BTreeMap::from([(0, "alld".to_owned())]),
+        };
+        let start = EvolutionCheckpoint::new(&config, 1, 10, 0.0)
+            .with_population(one_defector.individuals(&participants(), 10).unwrap());
+        let record = evolution::resume(&registry, start, 4, None, None).await;
+        assert_eq!(record.generations[0].composition["alld"], 1);
+
+        let dir = std::env::temp_dir().join(format!("actoripd-population-{}", std::process::id()));
+        assert_eq!(write_snapshots(&record, &dir, 3).unwrap(), 3);
+        let last = Population::load(snapshot_path(&dir, 4)).unwrap();
+        let again = last.individuals(&participants(), 0).unwrap();
+        assert_eq!(again.len(), 10);
+        assert_eq!(
+            again.iter().filter(|&&s| s == 1).count(),
+            record.survivors["alld"]
+        );
+        assert!(Population::load(snapshot_path(&dir, 3)).is_ok());
+        assert!(Population::load(snapshot_path(&dir, 1)).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
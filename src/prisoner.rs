@@ -0,0 +1,1233 @@
+use actix::prelude::*;
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+use std::time::{Duration, Instant};
+use tracing::{debug, info_span, warn, Span};
+
+use crate::game::{Action, Payoff, PlayerOutcome, Score};
+use crate::randomness::{Drawn, Seat};
+use crate::strategy::{GroupHistory, GroupRound, History, PlayerInfo, Round, Strategy, Turn};
+
+/// Tells apart the matches a prisoner is playing at once
+pub type MatchId = usize;
+
+#[derive(Clone)]
+pub struct Interrogate {
+    pub match_id: MatchId,
+    pub sequence: usize,
+
+    /// What this prisoner got last round, [`PlayerOutcome::NONE`] before the
+    /// first
+    pub prev: PlayerOutcome,
+
+    /// What this prisoner did last round, after any execution noise
+    pub own_action: Option<Action>,
+
+    /// What the opponent did last round, `None` before the first round
+    ///
+    /// In a group game this is what most of the other players did.
+    pub opponent_action: Option<Action>,
+
+    /// Set when the prisoner is playing an n-player game
+    pub group: Option<GroupOutcome>,
+}
+
+/// The group's side of an n-player round
+#[derive(Debug, Clone, Copy)]
+pub struct GroupOutcome {
+    /// Players in the group, this prisoner included
+    pub size: usize,
+
+    /// How many of the others cooperated last round, `None` before the first round
+    pub others_cooperated: Option<usize>,
+}
+
+impl Message for Interrogate {
+    type Result = Result<Action, StrategyFault>;
+}
+
+/// Ask for the prisoner's next `rounds` decisions in one round-trip
+///
+/// Only strategies that ignore history (`memory_depth` of `Some(0)`) can
+/// decide ahead, so they answer with all `rounds` actions. Any other
+/// strategy, memory-one included, depends on the opponent's next reply and
+/// answers with a single action, leaving the referee to ask again next round.
+#[derive(Clone)]
+pub struct PlayBatch {
+    pub match_id: MatchId,
+    pub sequence: usize,
+    pub rounds: usize,
+
+    /// Rounds played since the prisoner last heard from the referee, oldest first
+    pub history: Vec<Round>,
+
+    /// Rounds played that the prisoner isn't told of yet, when feedback is
+    /// delayed
+    pub withheld: usize,
+
+    /// What each player can see of the other going into this batch: its tag
+    /// and, if reputations are kept, its reputation
+    pub own: PlayerInfo,
+    pub opponent: PlayerInfo,
+
+    /// What the opponent signalled it would do this round, with cheap talk
+    pub opponent_signal: Option<Action>,
+
+    /// The prisoner's turn this round, in an alternating game
+    pub turn: Option<Turn>,
+}
+
+impl Message for PlayBatch {
+    type Result = Result<Vec<Action>, StrategyFault>;
+}
+
+/// Ask for the prisoner's signal before a round of a game with cheap talk
+///
+/// Carries what a [`PlayBatch`] would have reported since the prisoner
+/// last heard from the referee, so the signal can depend on it.
+#[derive(Clone)]
+pub struct Signal {
+    pub match_id: MatchId,
+    pub history: Vec<Round>,
+    pub withheld: usize,
+    pub own: PlayerInfo,
+    pub opponent: PlayerInfo,
+}
+
+impl Message for Signal {
+    type Result = Result<Option<Action>, StrategyFault>;
+}
+
+/// Sent before a match's first round, so the prisoner starts it with a
+/// fresh strategy and a clean slate
+///
+/// Optional: a prisoner sets up a match it hasn't heard of on its first
+/// `Interrogate` or `PlayBatch`.
+pub struct StartMatch {
+    pub match_id: MatchId,
+}
+
+impl Message for StartMatch {
+    type Result = ();
+}
+
+/// Sent once a match is over; the prisoner forgets it and answers with its
+/// own tally, or `None` if it never heard of the match
+pub struct EndMatch {
+    pub match_id: MatchId,
+
+    /// Rounds played since the prisoner last heard from the referee, oldest first
+    pub history: Vec<Round>,
+}
+
+impl Message for EndMatch {
+    type Result = Option<MatchScore>;
+}
+
+/// Asks for what the strategy remembered of its opponent as the match
+/// `match_id` ended, which the prisoner then forgets
+///
+/// Answered with `None` unless the prisoner was built
+/// [`Prisoner::with_memories`] and its strategy kept something.
+pub struct TakeMemory {
+    pub match_id: MatchId,
+}
+
+impl Message for TakeMemory {
+    type Result = Option<Value>;
+}
+
+/// A strategy to play instead of the prisoner's own from some round on
+pub struct Swap {
+    /// The first round the new strategy plays, counting from 0
+    pub round: usize,
+    pub strategy: Box<dyn Strategy>,
+
+    /// Fresh instances to restart the new strategy with, as for
+    /// [`Prisoner::with_spares`]; the old strategy's are dropped
+    pub spares: Vec<Box<dyn Strategy>>,
+
+    /// Start the new strategy from an empty history, as if the match began
+    /// at the swap, rather than showing it the rounds before
+    pub reset: bool,
+}
+
+/// Sent to change a prisoner's strategy partway through a match, such as to
+/// turn a defector into a cooperator at round 50
+///
+/// May be sent before the match starts or while it is being played; a swap
+/// for a round already played takes effect at the next decision. A
+/// strategy that keeps the history sees only as much of it as the old
+/// strategy kept, unless the swap arrived before the match began.
+pub struct SwapStrategy {
+    pub match_id: MatchId,
+    pub swap: Swap,
+}
+
+impl Message for SwapStrategy {
+    type Result = ();
+}
+
+/// What a prisoner scored in one match, by its own count
+///
+/// Points awarded by the referee outside play, such as for an opponent's
+/// forfeit, aren't included.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct MatchScore {
+    pub score: Score,
+    pub rounds: usize,
+}
+
+impl MatchScore {
+    /// Points per round, so matches of different lengths are comparable
+    pub fn average_score(&self) -> f64 {
+        if self.rounds == 0 {
+            0.0
+        } else {
+            self.score / self.rounds as f64
+        }
+    }
+}
+
+/// Why a prisoner forfeited
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FaultKind {
+    /// The strategy panicked inside `choose()`
+    #[default]
+    Panic,
+
+    /// The prisoner's mailbox never answered, even after retries
+    Unresponsive,
+
+    /// The player behind an asynchronous strategy, such as a server or a
+    /// person at the terminal, failed to decide
+    Remote,
+
+    /// The prisoner was asked to play a match it had no strategy for
+    Unavailable,
+
+    /// The strategy used up its computation budget for the match
+    OverBudget,
+}
+
+/// A prisoner that can't go on; it forfeits the match
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StrategyFault {
+    pub name: String,
+
+    #[serde(default)]
+    pub kind: FaultKind,
+    pub reason: String,
+}
+
+impl StrategyFault {
+    /// The prisoner `name` didn't answer
+    pub fn unresponsive(name: &str, error: MailboxError) -> StrategyFault {
+        StrategyFault {
+            name: name.to_owned(),
+            kind: FaultKind::Unresponsive,
+            reason: error.to_string(),
+        }
+    }
+
+    /// The player behind the prisoner `name` failed to decide
+    pub fn remote(name: &str, reason: String) -> StrategyFault {
+        StrategyFault {
+            name: name.to_owned(),
+            kind: FaultKind::Remote,
+            reason,
+        }
+    }
+}
+
+impl fmt::Display for StrategyFault {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            FaultKind::Panic => write!(f, "{}: strategy panicked: {}", self.name, self.reason),
+            FaultKind::Unresponsive => write!(f, "{}: did not answer: {}", self.name, self.reason),
+            FaultKind::Remote => {
+                write!(f, "{}: player failed to decide: {}", self.name, self.reason)
+            }
+            FaultKind::Unavailable => write!(f, "{}: {}", self.name, self.reason),
+            FaultKind::OverBudget => write!(f, "{}: over budget: {}", self.name, self.reason),
+        }
+    }
+}
+
+/// Wait before the first resend of an unanswered message, doubling each time
+pub const RETRY_BACKOFF: Duration = Duration::from_millis(10);
+
+/// Send `msg` to `prisoner`, resending up to `retries` times if the mailbox fails
+///
+/// With a `timeout`, an answer that takes longer fails with
+/// `MailboxError::Timeout` straight away; a slow prisoner isn't asked again.
+pub async fn ask<M>(
+    prisoner: &Addr<Prisoner>,
+    msg: M,
+    retries: usize,
+    timeout: Option<Duration>,
+) -> Result<M::Result, MailboxError>
+where
+    M: Message + Clone + Send + 'static,
+    M::Result: Send,
+    Prisoner: Handler<M>,
+{
+    let send = |msg: M| {
+        let request = prisoner.send(msg);
+        let request = match timeout {
+            Some(timeout) => request.timeout(timeout),
+            None => request,
+        };
+        #[cfg(feature = "metrics")]
+        let request = {
+            let sent = std::time::Instant::now();
+            futures::FutureExt::inspect(request, move |_| {
+                crate::metrics::observe_mailbox_latency(sent.elapsed())
+            })
+        };
+        request
+    };
+    let mut backoff = RETRY_BACKOFF;
+    for _ in 0..retries {
+        match send(msg.clone()).await {
+            Err(MailboxError::Timeout) => return Err(MailboxError::Timeout),
+            Err(e) => {
+                warn!(error = %e, ?backoff, "prisoner did not answer; retrying");
+                actix::clock::delay_for(backoff).await;
+                backoff *= 2;
+            }
+            answer => return answer,
+        }
+    }
+    send(msg).await
+}
+
+/// What a prisoner does when its strategy panics
+///
+/// ```toml
+/// [faults]
+/// kind = "restart"
+/// restarts = 3
+/// ```
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case", deny_unknown_fields)]
+pub enum FaultPolicy {
+    /// Forfeit the match at the first panic
+    #[default]
+    Forfeit,
+
+    /// Swap in a fresh instance of the strategy, up to `restarts` times a
+    /// match, before forfeiting
+    Restart { restarts: usize },
+}
+
+impl FaultPolicy {
+    /// How many fresh instances each prisoner should hold in reserve
+    pub fn restarts(&self) -> usize {
+        match *self {
+            FaultPolicy::Forfeit => 0,
+            FaultPolicy::Restart { restarts } => restarts,
+        }
+    }
+}
+
+/// What a strategy may spend deciding in each match
+///
+/// ```toml
+/// [budget]
+/// decisions = 10000
+/// cpu_ms = 50
+/// on_exceed = "forfeit-round"
+/// ```
+///
+/// A strategy is over budget at the first decision asked of it once it has
+/// made its decisions, spent its time, or grown past its memory. Time is
+/// measured around each call
+/// to `choose()`, which runs on the
+/// prisoner's thread, so it stands in for CPU time as long as the machine
+/// isn't oversubscribed. A strategy that answers asynchronously, such as a
+/// remote one, is held to its decisions alone; the decision timeout bounds
+/// its time.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Budget {
+    /// Decisions the strategy may make in a match
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decisions: Option<usize>,
+
+    /// Milliseconds the strategy may spend deciding in a match
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpu_ms: Option<u64>,
+
+    /// Kilobytes the strategy may hold, if it can tell how much it does,
+    /// as a WebAssembly guest can; see [`Strategy::memory_used`]
+    ///
+    /// Script, plugin, and remote strategies can't, so a config with this
+    /// set doesn't admit them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory_kb: Option<usize>,
+
+    #[serde(default)]
+    pub on_exceed: BudgetAction,
+}
+
+/// What happens to a strategy that has used up its budget
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BudgetAction {
+    /// Forfeit the match
+    #[default]
+    ForfeitMatch,
+
+    /// Forfeit every round left in the match, the timeout action being
+    /// played in the strategy's place
+    ForfeitRound,
+
+    /// Forfeit the match, and be left out of the tournament's standings
+    Disqualify,
+}
+
+impl Budget {
+    fn cpu(&self) -> Option<Duration> {
+        self.cpu_ms.map(Duration::from_millis)
+    }
+}
+
+impl Handler<StartMatch> for Prisoner {
+    type Result = ();
+
+    fn handle(&mut self, msg: StartMatch, _ctx: &mut Context<Self>) -> Self::Result {
+        let span = self.span.clone();
+        let _entered = span.enter();
+        self.sessions.remove(&msg.match_id);
+        // a prisoner with nothing to play faults at its first decision instead
+        if self.session(msg.match_id).is_ok() {
+            debug!(match_id = msg.match_id, "starts a match");
+        }
+    }
+}
+
+impl Handler<SwapStrategy> for Prisoner {
+    type Result = ();
+
+    fn handle(&mut self, msg: SwapStrategy, _ctx: &mut Context<Self>) -> Self::Result {
+        let span = self.span.clone();
+        let _entered = span.enter();
+        debug!(
+            match_id = msg.match_id,
+            round = msg.swap.round,
+            strategy = %msg.swap.strategy.name(),
+            "will swap strategies"
+        );
+        match self.sessions.get_mut(&msg.match_id) {
+            Some(session) => session.expect(msg.swap),
+            None => {
+                self.swaps.insert(msg.match_id, msg.swap);
+            }
+        }
+    }
+}
+
+impl Handler<EndMatch> for Prisoner {
+    type Result = Option<MatchScore>;
+
+    fn handle(&mut self, msg: EndMatch, _ctx: &mut Context<Self>) -> Self::Result {
+        let span = self.span.clone();
+        let _entered = span.enter();
+        let mut session = self.sessions.remove(&msg.match_id)?;
+        for round in msg.history {
+            session.score += round.outcome.amount;
+            session.rounds += 1;
+        }
+        let score = session.score();
+        if let (Some(remembered), Some(memory)) =
+            (&mut self.remembered, session.strategy.remember())
+        {
+            remembered.insert(msg.match_id, memory);
+        }
+        // a prisoner with a single strategy plays its matches in turn
+        if self.source.is_none() && self.first.is_none() {
+            let Session {
+                strategy,
+                spares,
+                swapped_out,
+                randomness,
+                ..
+            } = session;
+            // the next match starts with the strategy the prisoner was given
+            let (strategy, spares) = match swapped_out {
+                Some(own) => (own.strategy, own.spares),
+                None => (strategy, spares),
+            };
+            let mut next = Session::new(strategy, spares);
+            next.randomness = randomness;
+            next.reset_due = true;
+            self.first = Some(next);
+        }
+        debug!(
+            match_id = msg.match_id,
+            score = score.score,
+            per_round = score.average_score(),
+            rounds = score.rounds,
+            "ends a match"
+        );
+        Some(score)
+    }
+}
+
+impl Handler<TakeMemory> for Prisoner {
+    type Result = Option<Value>;
+
+    fn handle(&mut self, msg: TakeMemory, _ctx: &mut Context<Self>) -> Self::Result {
+        self.remembered.as_mut()?.remove(&msg.match_id)
+    }
+}
+
+impl Handler<Interrogate> for Prisoner {
+    type Result = Response<Action, StrategyFault>;
+
+    fn handle(&mut self, msg: Interrogate, _ctx: &mut Context<Self>) -> Self::Result {
+        let span = self.span.clone();
+        let _entered = span.enter();
+        let name = self.name.clone();
+        let session = match self.session(msg.match_id) {
+            Ok(session) => session,
+            Err(fault) => return Response::reply(Err(fault)),
+        };
+        if msg.prev.payoff != Payoff::NULL {
+            session.score += msg.prev.amount;
+            session.rounds += 1;
+        }
+
+        if let (Some(own), Some(opponent)) = (msg.own_action, msg.opponent_action) {
+            session.history.push(Round {
+                own,
+                opponent,
+                hidden: false,
+                outcome: msg.prev,
+            });
+        }
+
+        if let Some(group) = msg.group {
+            session.group_history.set_size(group.size);
+            if let (Some(own), Some(others_cooperated)) = (msg.own_action, group.others_cooperated)
+            {
+                session.group_history.push(GroupRound {
+                    own,
+                    others_cooperated,
+                });
+            }
+        }
+
+        session.swap_if_due();
+        let in_group = msg.group.is_some();
+        if !in_group {
+            if let Some(decision) = session.decide_async(&name) {
+                return Response::fut(decision);
+            }
+        }
+        let action = match session.decide(&name, 1, in_group) {
+            Ok(actions) => actions[0],
+            Err(fault) => return Response::reply(Err(fault)),
+        };
+
+        debug!(
+            match_id = msg.match_id,
+            sequence = msg.sequence,
+            prev_payoff = %msg.prev.payoff,
+            prev_amount = msg.prev.amount,
+            score = session.score,
+            %action,
+            "interrogated"
+        );
+
+        Response::reply(Ok(action))
+    }
+}
+
+impl Handler<PlayBatch> for Prisoner {
+    type Result = Response<Vec<Action>, StrategyFault>;
+
+    fn handle(&mut self, msg: PlayBatch, _ctx: &mut Context<Self>) -> Self::Result {
+        let span = self.span.clone();
+        let _entered = span.enter();
+        let name = self.name.clone();
+        let session = match self.session(msg.match_id) {
+            Ok(session) => session,
+            Err(fault) => return Response::reply(Err(fault)),
+        };
+        session.catch_up(msg.history, msg.withheld, msg.own, msg.opponent);
+        session.history.set_opponent_signal(msg.opponent_signal);
+        session.history.set_turn(msg.turn);
+        session.swap_if_due();
+
+        let rounds = if session.strategy.memory_depth() == Some(0) {
+            msg.rounds
+        } else {
+            msg.rounds.min(1)
+        };
+        if let Some(decision) = session.decide_async(&name) {
+            return Response::fut(Box::pin(async move { decision.await.map(|a| vec![a]) }));
+        }
+        let actions = match session.decide(&name, rounds, false) {
+            Ok(actions) => actions,
+            Err(fault) => return Response::reply(Err(fault)),
+        };
+
+        debug!(
+            match_id = msg.match_id,
+            sequence = msg.sequence,
+            score = session.score,
+            actions = actions.len(),
+            "played a batch"
+        );
+
+        Response::reply(Ok(actions))
+    }
+}
+
+impl Handler<Signal> for Prisoner {
+    type Result = Result<Option<Action>, StrategyFault>;
+
+    fn handle(&mut self, msg: Signal, _ctx: &mut Context<Self>) -> Self::Result {
+        let span = self.span.clone();
+        let _entered = span.enter();
+        let name = self.name.clone();
+        let session = self.session(msg.match_id)?;
+        session.catch_up(msg.history, msg.withheld, msg.own, msg.opponent);
+        session.history.set_opponent_signal(None);
+        session.swap_if_due();
+        session.drawing_at(session.round());
+        let signal = session.guarded(&name, |strategy, history, _| strategy.signal(history));
+        session.hand_in_draws(&name);
+        signal
+    }
+}
+
+/// Builds the strategy for each match a reusable prisoner joins
+///
+/// Spares are built by calling it again with the same id, so it should
+/// build the same strategy each time it is given one.
+pub type StrategySource = Box<dyn FnMut(MatchId) -> Box<dyn Strategy> + Send>;
+
+/// A prisoner's side of one match: its strategy and what it has seen
+struct Session {
+    strategy: Box<dyn Strategy>,
+
+    /// Fresh instances to restart with if `strategy` panics, used last first
+    spares: Vec<Box<dyn Strategy>>,
+    history: History,
+    group_history: GroupHistory,
+    score: Score,
+    rounds: usize,
+
+    /// What the strategy may spend on `decisions` and `thinking`, if limited
+    budget: Option<Budget>,
+    decisions: usize,
+
+    /// Time spent in the strategy's `choose()`
+    thinking: Duration,
+
+    /// A strategy to play instead from a later round
+    swap: Option<Swap>,
+
+    /// The strategy and spares played before the first swap, to go back to
+    /// for the next match
+    swapped_out: Option<Swap>,
+
+    /// Rounds played before the history was last reset by a swap
+    forgotten: usize,
+
+    /// Rounds played that the referee hasn't told of yet
+    withheld: usize,
+
+    /// Where to log what the strategy draws, if its randomness is audited
+    randomness: Option<Audit>,
+
+    /// Whether to reset the strategy before its first decision, having
+    /// played it in an earlier match
+    reset_due: bool,
+}
+
+/// The seat a prisoner's strategies draw through, and the provider keeping
+/// the match's draws
+#[derive(Clone)]
+struct Audit {
+    seat: Seat,
+    provider: Recipient<Drawn>,
+}
+
+impl Audit {
+    /// Hand the provider what was drawn since last time, and where the
+    /// generators stood
+    fn hand_in(&self, name: &str) {
+        let draws = self.seat.take();
+        let positions = self.seat.take_positions();
+        if !draws.is_empty() || !positions.is_empty() {
+            let _ = self.provider.do_send(Drawn {
+                name: name.to_owned(),
+                draws,
+                positions,
+            });
+        }
+    }
+}
+
+impl Session {
+    fn new(strategy: Box<dyn Strategy>, spares: Vec<Box<dyn Strategy>>) -> Session {
+        Session {
+            history: History::with_depth(strategy.memory_depth()),
+            group_history: GroupHistory::with_depth(strategy.memory_depth()),
+            strategy,
+            spares,
+            score: 0.0,
+            rounds: 0,
+            budget: None,
+            decisions: 0,
+            thinking: Duration::ZERO,
+            swap: None,
+            swapped_out: None,
+            forgotten: 0,
+            withheld: 0,
+            randomness: None,
+            reset_due: false,
+        }
+    }
+
+    /// Note what the strategy draws from here on under `round`
+    fn drawing_at(&self, round: usize) {
+        if let Some(audit) = &self.randomness {
+            audit.seat.at(round);
+        }
+    }
+
+    /// Hand over what the strategy drew since last time, if audited
+    fn hand_in_draws(&self, name: &str) {
+        if let Some(audit) = &self.randomness {
+            audit.hand_in(name);
+        }
+    }
+
+    /// Swap strategies once the match reaches `swap.round`
+    fn expect(&mut self, swap: Swap) {
+        // before the first round, keep as much history as either needs
+        if !swap.reset && self.round() == 0 {
+            let depth = match (self.strategy.memory_depth(), swap.strategy.memory_depth()) {
+                (Some(a), Some(b)) => Some(a.max(b)),
+                _ => None,
+            };
+            self.history = History::with_depth(depth);
+            self.group_history = GroupHistory::with_depth(depth);
+        }
+        self.swap = Some(swap);
+    }
+
+    /// Play the swapped-in strategy from here on, if its round has come
+    fn swap_if_due(&mut self) {
+        match &self.swap {
+            Some(swap) if swap.round <= self.round() => {}
+            _ => return,
+        }
+        let swap = self.swap.take().expect("a swap is due");
+        if swap.reset {
+            let depth = swap.strategy.memory_depth();
+            let mut history = History::with_depth(depth);
+            history.set_players(*self.history.own_info(), *self.history.opponent_info());
+            history.set_opponent_signal(self.history.opponent_signal());
+            history.set_turn(self.history.turn());
+            let mut group_history = GroupHistory::with_depth(depth);
+            group_history.set_size(self.group_history.size());
+            self.forgotten += self.history.round();
+            self.history = history;
+            self.group_history = group_history;
+        }
+        debug!(
+            round = swap.round,
+            strategy = %swap.strategy.name(),
+            reset = swap.reset,
+            "swaps strategies"
+        );
+        let own = Swap {
+            strategy: std::mem::replace(&mut self.strategy, swap.strategy),
+            spares: std::mem::replace(&mut self.spares, swap.spares),
+            ..swap
+        };
+        // only the first swap's outgoing strategy is the prisoner's own
+        if self.swapped_out.is_none() {
+            self.swapped_out = Some(own);
+        }
+    }
+
+    /// Rounds left before a pending swap, which a batch mustn't run past
+    fn until_swap(&self) -> Option<usize> {
+        self.swap
+            .as_ref()
+            .map(|swap| swap.round.saturating_sub(self.round()).max(1))
+    }
+
+    /// The round about to be played, counting any a reset swap forgot and
+    /// any the referee is still withholding
+    fn round(&self) -> usize {
+        self.forgotten + self.history.round() + self.withheld
+    }
+
+    fn score(&self) -> MatchScore {
+        MatchScore {
+            score: self.score,
+            rounds: self.rounds,
+        }
+    }
+
+    /// Take in the rounds played since the referee last got in touch, and
+    /// what the players can see of each other now
+    fn catch_up(
+        &mut self,
+        rounds: Vec<Round>,
+        withheld: usize,
+        own: PlayerInfo,
+        opponent: PlayerInfo,
+    ) {
+        for round in rounds {
+            self.score += round.outcome.amount;
+            self.rounds += 1;
+            self.history.push(round);
+        }
+        self.withheld = withheld;
+        self.history.set_players(own, opponent);
+    }
+
+    /// Ask the strategy for its next `rounds` actions
+    fn decide(
+        &mut self,
+        name: &str,
+        rounds: usize,
+        in_group: bool,
+    ) -> Result<Vec<Action>, StrategyFault> {
+        self.swap_if_due();
+        let rounds = self.allowance(name, rounds)?;
+        let rounds = self.until_swap().map_or(rounds, |left| rounds.min(left));
+        let started = Instant::now();
+        let first = self.round();
+        let seat = self.randomness.as_ref().map(|audit| audit.seat.clone());
+        self.drawing_at(first);
+        let actions = self.guarded(name, |strategy, history, group_history| {
+            (0..rounds)
+                .map(|i| {
+                    if let Some(seat) = &seat {
+                        seat.at(first + i);
+                    }
+                    if in_group {
+                        strategy.choose_in_group(history, group_history)
+                    } else {
+                        strategy.choose(history)
+                    }
+                })
+                .collect::<Vec<_>>()
+        });
+        self.hand_in_draws(name);
+        let actions = actions?;
+        self.thinking += started.elapsed();
+        self.decisions += actions.len();
+        Ok(actions)
+    }
+
+    /// How many of the next `rounds` decisions the budget allows, faulting
+    /// if it allows none
+    fn allowance(&self, name: &str, rounds: usize) -> Result<usize, StrategyFault> {
+        let budget = match self.budget {
+            Some(budget) => budget,
+            None => return Ok(rounds),
+        };
+        let over = |reason: String| StrategyFault {
+            name: name.to_owned(),
+            kind: FaultKind::OverBudget,
+            reason,
+        };
+        if let Some(cpu) = budget.cpu() {
+            if self.thinking > cpu {
+                return Err(over(format!(
+                    "spent {} ms deciding, more than its {} ms",
+                    self.thinking.as_millis(),
+                    cpu.as_millis()
+                )));
+            }
+        }
+        if let (Some(limit), Some(used)) = (budget.memory_kb, self.strategy.memory_used()) {
+            if used > limit * 1024 {
+                return Err(over(format!(
+                    "holds {} KB, more than its {} KB",
+                    used / 1024,
+                    limit
+                )));
+            }
+        }
+        match budget.decisions {
+            Some(limit) if self.decisions >= limit => {
+                Err(over(format!("made all {} decisions it had", limit)))
+            }
+            Some(limit) => Ok(rounds.min(limit - self.decisions)),
+            None => Ok(rounds),
+        }
+    }
+
+    /// Call on the strategy, restarting it on a panic while spares last
+    ///
+    /// A spare is first shown the match so far, see [`replay`].
+    fn guarded<T>(
+        &mut self,
+        name: &str,
+        mut call: impl FnMut(&mut dyn Strategy, &History, &GroupHistory) -> T,
+    ) -> Result<T, StrategyFault> {
+        let mut restarted = false;
+        loop {
+            // catch the panic here so it doesn't take down the arbiter thread
+            let strategy = self.strategy.as_mut();
+            let history = &self.history;
+            let group_history = &self.group_history;
+            let cause = match panic::catch_unwind(AssertUnwindSafe(|| {
+                if restarted {
+                    replay(strategy, history);
+                }
+                call(strategy, history, group_history)
+            })) {
+                Ok(answer) => return Ok(answer),
+                Err(cause) => cause,
+            };
+
+            let reason = if let Some(s) = cause.downcast_ref::<&str>() {
+                s.to_string()
+            } else if let Some(s) = cause.downcast_ref::<String>() {
+                s.clone()
+            } else {
+                "unknown panic".to_owned()
+            };
+            match self.spares.pop() {
+                Some(spare) => {
+                    warn!(%reason, "strategy panicked; restarting it");
+                    self.strategy = spare;
+                    restarted = true;
+                }
+                None => {
+                    return Err(StrategyFault {
+                        name: name.to_owned(),
+                        kind: FaultKind::Panic,
+                        reason,
+                    })
+                }
+            }
+        }
+    }
+
+    /// The next decision of a strategy that answers asynchronously
+    fn decide_async(
+        &mut self,
+        name: &str,
+    ) -> Option<BoxFuture<'static, Result<Action, StrategyFault>>> {
+        self.swap_if_due();
+        // over budget, `decide` faults without asking the strategy
+        self.allowance(name, 1).ok()?;
+        self.drawing_at(self.round());
+        let decision = self.strategy.choose_async(&self.history);
+        self.hand_in_draws(name);
+        let decision = decision?;
+        self.decisions += 1;
+        let name = name.to_owned();
+        let audit = self.randomness.clone();
+        Some(Box::pin(async move {
+            let action = decision.await;
+            // what it drew while deciding goes in before the answer
+            if let Some(audit) = audit {
+                audit.hand_in(&name);
+            }
+            action.map_err(|reason| StrategyFault::remote(&name, reason))
+        }))
+    }
+}
+
+/// Walk a fresh `strategy` through the rounds of `history`, asking it to
+/// choose before each as if it were playing, so that a spare restarted
+/// mid-match has built up the same state the panicked instance had
+///
+/// Its answers are thrown away, since the rounds were already played. Only
+/// a history holding every round is replayed: a strategy that keeps only
+/// its last few rounds decides from those alone, which it is shown anyway.
+/// A spare's random draws during the replay aren't the panicked instance's,
+/// so a stochastic strategy carries on from a different point in its stream.
+fn replay(strategy: &mut dyn Strategy, history: &History) {
+    if history.rounds().len() < history.round() {
+        return;
+    }
+    let mut replayed = History::with_depth(strategy.memory_depth());
+    replayed.set_players(*history.own_info(), *history.opponent_info());
+    for &round in history.rounds() {
+        strategy.choose(&replayed);
+        replayed.push(round);
+    }
+}
+
+/// A player, keeping its strategy and score apart for each match it plays
+///
+/// Every message names its match, so one prisoner can play several matches
+/// at once without one opponent's moves leaking into another's game.
+pub struct Prisoner {
+    name: String,
+
+    /// The strategy given to [`Prisoner::new`], for the next match the
+    /// prisoner is asked to play
+    first: Option<Session>,
+
+    /// Builds a strategy for each further match, if the prisoner can play more
+    source: Option<StrategySource>,
+
+    /// Spares to build for each match from `source`
+    restarts: usize,
+
+    /// What each match's strategy may spend deciding, if limited
+    budget: Option<Budget>,
+
+    /// Swaps sent for matches the prisoner hasn't started yet
+    swaps: HashMap<MatchId, Swap>,
+    sessions: HashMap<MatchId, Session>,
+
+    /// What the strategy of the next match recalls of its opponent, if
+    /// anything
+    recalled: Option<Value>,
+
+    /// What the strategy remembered as each match ended, until taken with
+    /// [`TakeMemory`], if memories are kept
+    remembered: Option<HashMap<MatchId, Value>>,
+
+    /// Tags this prisoner's log lines with its name, and its match if known
+    span: Span,
+}
+
+impl Prisoner {
+    /// A prisoner that plays `strategy`, one match at a time
+    ///
+    /// Before each match after the first, the strategy is [reset].
+    ///
+    /// [reset]: Strategy::reset
+    pub fn new(name: &str, strategy: Box<dyn Strategy>) -> Prisoner {
+        Prisoner {
+            name: name.to_owned(),
+            first: Some(Session::new(strategy, Vec::new())),
+            source: None,
+            restarts: 0,
+            budget: None,
+            swaps: HashMap::new(),
+            sessions: HashMap::new(),
+            recalled: None,
+            remembered: None,
+            span: info_span!("prisoner", player = %name),
+        }
+    }
+
+    /// A prisoner that can play any number of matches, concurrently or in
+    /// turn, with a strategy from `source` for each and `restarts` spares
+    pub fn with_source(name: &str, source: StrategySource, restarts: usize) -> Prisoner {
+        Prisoner {
+            name: name.to_owned(),
+            first: None,
+            source: Some(source),
+            restarts,
+            budget: None,
+            swaps: HashMap::new(),
+            sessions: HashMap::new(),
+            recalled: None,
+            remembered: None,
+            span: info_span!("prisoner", player = %name),
+        }
+    }
+
+    /// Log within `span`, usually the match the prisoner is playing in
+    pub fn in_span(mut self, span: &Span) -> Prisoner {
+        self.span = info_span!(parent: span, "prisoner", player = %self.name);
+        self
+    }
+
+    /// Restart with these instances, one per panic, before forfeiting
+    ///
+    /// A restarted strategy is shown the prisoner's history before it plays,
+    /// so it should be built exactly as the original was; see [`replay`].
+    /// Applies to the strategy given to [`Prisoner::new`].
+    pub fn with_spares(mut self, spares: Vec<Box<dyn Strategy>>) -> Prisoner {
+        if let Some(first) = &mut self.first {
+            first.spares = spares;
+        }
+        self
+    }
+
+    /// Swap to another strategy partway through the first match, as a
+    /// [`SwapStrategy`] sent before it would
+    ///
+    /// The prisoner goes back to its own strategy for any later match.
+    pub fn with_swap(mut self, swap: Swap) -> Prisoner {
+        if let Some(first) = &mut self.first {
+            first.expect(swap);
+        }
+        self
+    }
+
+    /// Hand what the strategy draws through `seat` to `provider`, round by
+    /// round, including what it draws in `signal`, `reset`, and
+    /// `choose_async`; see [`crate::randomness`]
+    ///
+    /// The strategy, its spares, and any swap should have been built
+    /// [seated](crate::randomness::seated) at `seat`.
+    pub fn with_randomness(mut self, seat: Seat, provider: Recipient<Drawn>) -> Prisoner {
+        if let Some(first) = &mut self.first {
+            first.randomness = Some(Audit { seat, provider });
+        }
+        self
+    }
+
+    /// Hold the strategy to `budget` in every match, faulting with
+    /// [`FaultKind::OverBudget`] once it is spent
+    pub fn with_budget(mut self, budget: Budget) -> Prisoner {
+        self.budget = Some(budget);
+        self
+    }
+
+    /// Keep what the strategy remembers of its opponent as each match ends,
+    /// for [`TakeMemory`], and have it recall `recalled` before its next
+    /// match; see [`crate::memory`]
+    pub fn with_memories(mut self, recalled: Option<Value>) -> Prisoner {
+        self.recalled = recalled;
+        self.remembered = Some(HashMap::new());
+        self
+    }
+
+    /// The prisoner's side of `match_id`, set up on the match's first message
+    fn session(&mut self, match_id: MatchId) -> Result<&mut Session, StrategyFault> {
+        if !self.sessions.contains_key(&match_id) {
+            let mut session = match (self.first.take(), &mut self.source) {
+                (Some(first), _) => first,
+                (None, Some(source)) => {
+                    let spares = (0..self.restarts).map(|_| source(match_id)).collect();
+                    Session::new(source(match_id), spares)
+                }
+                (None, None) => {
+                    return Err(StrategyFault {
+                        name: self.name.clone(),
+                        kind: FaultKind::Unavailable,
+                        reason: format!("has no strategy for another match, {}", match_id),
+                    })
+                }
+            };
+            session.budget = self.budget;
+            if session.reset_due {
+                session.reset_due = false;
+                session.drawing_at(0);
+                session.strategy.reset();
+                session.hand_in_draws(&self.name);
+            }
+            if let Some(memory) = self.recalled.take() {
+                session.strategy.recall(&memory);
+            }
+            if let Some(swap) = self.swaps.remove(&match_id) {
+                session.expect(swap);
+            }
+            self.sessions.insert(match_id, session);
+        }
+        Ok(self
+            .sessions
+            .get_mut(&match_id)
+            .expect("session just set up"))
+    }
+}
+
+impl Actor for Prisoner {
+    type Context = Context<Self>;
+
+    fn started(&mut self, _ctx: &mut Context<Self>) {
+        let _entered = self.span.enter();
+        debug!("starts");
+    }
+
+    fn stopped(&mut self, _ctx: &mut Context<Self>) {
+        let _entered = self.span.enter();
+        // matches that never ended
+        for (match_id, session) in &self.sessions {
+            let score = session.score();
+            debug!(
+                match_id,
+                score = score.score,
+                per_round = score.average_score(),
+                rounds = score.rounds,
+                "stops"
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Defects for good once its opponent has, and panics in `panics_at`
+    struct Grudge {
+        grudge: bool,
+        panics_at: Option<usize>,
+    }
+
+    impl Strategy for Grudge {
+        fn name(&self) -> &str {
+            "grudge"
+        }
+
+        fn choose(&mut self, history: &History) -> Action {
+            if history.opponent_last_action() == Some(Action::DEFECT) {
+                self.grudge = true;
+            }
+            if Some(history.round()) == self.panics_at {
+                panic!("round {}", history.round());
+            }
+            if self.grudge {
+                Action::DEFECT
+            } else {
+                Action::COOPERATE
+            }
+        }
+    }
+
+    #[test]
+    fn a_restarted_strategy_remembers_the_match_so_far() {
+        let grudge = |panics_at| -> Box<dyn Strategy> {
+            Box::new(Grudge {
+                grudge: false,
+                panics_at,
+            })
+        };
+        let mut session = Session::new(grudge(Some(3)), vec![grudge(None)]);
+        let opponent = [Action::DEFECT, Action::COOPERATE, Action::COOPERATE];
+        let mut played = Vec::new();
+        for round in 0..5 {
+            let own = session.decide("blue", 1, false).expect("restarted")[0];
+            played.push(own);
+            let round = Round {
+                own,
+                opponent: opponent.get(round).copied().unwrap_or(Action::COOPERATE),
+                hidden: false,
+                outcome: PlayerOutcome::NONE,
+            };
+            session.catch_up(vec![round], 0, PlayerInfo::default(), PlayerInfo::default());
+        }
+        // the spare took over in round 3 still holding the grudge
+        assert_eq!(
+            played,
+            [
+                Action::COOPERATE,
+                Action::DEFECT,
+                Action::DEFECT,
+                Action::DEFECT,
+                Action::DEFECT
+            ]
+        );
+        assert!(session.spares.is_empty());
+    }
+}
@@ -0,0 +1,358 @@
+//! A strategy's behaviour against a fixed panel of scripted opponents
+//!
+//! [`profile`] plays a strategy against each opponent of [`PANEL`], whose
+//! moves are set in advance whatever the strategy does, and sums up how it
+//! answered them: its moves against each, how often it cooperates, whether
+//! it ever defects first, and how it retaliates for a lone defection. The
+//! matches are local and noiseless, so a deterministic strategy's profile
+//! is the same every time, and a stochastic one's follows from its seed.
+
+use serde::{Deserialize, Serialize};
+
+use crate::game::{Action, PayoffMatrix};
+use crate::local::{LocalMatch, LocalResult};
+use crate::registry::{Parameters, StrategyArgs, StrategyRegistry};
+use crate::strategy::{History, Strategy};
+use crate::transcript::RoundRecord;
+
+/// The round, counting from 0, in which the provoker defects
+pub const PROVOCATION: usize = 4;
+
+/// An opponent whose moves depend only on the round
+pub struct Opponent {
+    pub name: &'static str,
+    pub description: &'static str,
+    moves: fn(usize) -> Action,
+}
+
+/// The opponents every strategy is profiled against
+pub const PANEL: [Opponent; 6] = [
+    Opponent {
+        name: "cooperator",
+        description: "always cooperates",
+        moves: |_| Action::COOPERATE,
+    },
+    Opponent {
+        name: "defector",
+        description: "always defects",
+        moves: |_| Action::DEFECT,
+    },
+    Opponent {
+        name: "alternator",
+        description: "cooperates and defects in turn, cooperating first",
+        moves: |round| {
+            if round % 2 == 0 {
+                Action::COOPERATE
+            } else {
+                Action::DEFECT
+            }
+        },
+    },
+    Opponent {
+        name: "provoker",
+        description: "cooperates except once, in round 5",
+        moves: |round| {
+            if round == PROVOCATION {
+                Action::DEFECT
+            } else {
+                Action::COOPERATE
+            }
+        },
+    },
+    Opponent {
+        name: "repentant",
+        description: "defects for five rounds, then cooperates",
+        moves: |round| {
+            if round < 5 {
+                Action::DEFECT
+            } else {
+                Action::COOPERATE
+            }
+        },
+    },
+    Opponent {
+        name: "prober",
+        description: "defects every third round",
+        moves: |round| {
+            if round % 3 == 2 {
+                Action::DEFECT
+            } else {
+                Action::COOPERATE
+            }
+        },
+    },
+];
+
+/// An opponent of the panel, playing its moves
+struct Scripted {
+    name: &'static str,
+    moves: fn(usize) -> Action,
+    round: usize,
+}
+
+impl Strategy for Scripted {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn choose(&mut self, _history: &History) -> Action {
+        let action = (self.moves)(self.round);
+        self.round += 1;
+        action
+    }
+
+    fn memory_depth(&self) -> Option<usize> {
+        Some(0)
+    }
+
+    fn reset(&mut self) {
+        self.round = 0;
+    }
+}
+
+/// `opponent` as a strategy, playing its moves whatever it is up against
+pub fn scripted(opponent: &Opponent) -> Box<dyn Strategy> {
+    Box::new(Scripted {
+        name: opponent.name,
+        moves: opponent.moves,
+        round: 0,
+    })
+}
+
+/// How the strategy answered one opponent
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Response {
+    pub opponent: String,
+
+    /// The strategy's moves, a C or D per round
+    pub moves: String,
+
+    /// The opponent's moves, the same way
+    pub opponent_moves: String,
+
+    /// The share of rounds in which the strategy cooperated
+    pub cooperation: f64,
+
+    /// Points per round the strategy scored
+    pub score: f64,
+}
+
+/// How the strategy answers defections
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Retaliation {
+    /// How often it defected the round after its opponent defected, across
+    /// the panel
+    pub after_defection: f64,
+
+    /// How often it defected the round after its opponent cooperated
+    pub after_cooperation: f64,
+
+    /// How many rounds after the provoker's defection it first defected,
+    /// if it did
+    pub delay: Option<usize>,
+
+    /// How often it defected after the provoker's defection
+    pub defections: usize,
+
+    /// Whether it was cooperating with the provoker again by the end
+    pub forgives: bool,
+}
+
+/// A strategy's behaviour against the whole panel
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Profile {
+    pub strategy: String,
+
+    /// Rounds played against each opponent
+    pub rounds: usize,
+
+    /// The share of rounds in which the strategy cooperated, across the
+    /// panel
+    pub cooperation: f64,
+
+    /// Whether the strategy never defected before its opponent did
+    pub nice: bool,
+
+    pub retaliation: Retaliation,
+
+    /// One per opponent, in the order of [`PANEL`]
+    pub responses: Vec<Response>,
+}
+
+/// One side's moves in `result`, a letter per round
+fn moves(result: &LocalResult, side: fn(&RoundRecord) -> Action) -> String {
+    result
+        .transcript
+        .iter()
+        .map(|round| side(round).letter())
+        .collect()
+}
+
+/// Play `strategy`, with `parameters`, for `rounds` rounds against each
+/// opponent of the panel
+pub fn profile(
+    registry: &StrategyRegistry,
+    strategy: &str,
+    parameters: &Parameters,
+    payoffs: PayoffMatrix,
+    rounds: usize,
+    seed: u64,
+) -> Result<Profile, String> {
+    if rounds <= PROVOCATION + 1 {
+        return Err(format!(
+            "rounds must be more than {}, to answer the provoker",
+            PROVOCATION + 1
+        ));
+    }
+    let args = StrategyArgs {
+        parameters,
+        payoffs: &payoffs,
+        seed,
+    };
+    let mut responses = Vec::new();
+    let mut results = Vec::new();
+    for opponent in &PANEL {
+        let subject = registry
+            .create(strategy, &args)
+            .map_err(|e| format!("{}: {}", strategy, e))?;
+        let result = LocalMatch::new(
+            (strategy, subject),
+            (opponent.name, scripted(opponent)),
+            payoffs,
+            rounds,
+        )
+        .with_seed(seed)
+        .play();
+        responses.push(Response {
+            opponent: opponent.name.to_owned(),
+            moves: moves(&result, |round| round.blue.executed),
+            opponent_moves: moves(&result, |round| round.red.executed),
+            cooperation: result.blue.cooperations as f64 / rounds as f64,
+            score: result.blue.score / rounds as f64,
+        });
+        results.push(result);
+    }
+
+    let mut after = [(0, 0), (0, 0)];
+    let mut nice = true;
+    for result in &results {
+        let rounds = &result.transcript;
+        for (previous, round) in rounds.iter().zip(&rounds[1..]) {
+            let provoked = previous.red.executed == Action::DEFECT;
+            let tally = &mut after[provoked as usize];
+            tally.0 += (round.blue.executed == Action::DEFECT) as usize;
+            tally.1 += 1;
+        }
+        let first = |side: fn(&RoundRecord) -> Action| {
+            rounds
+                .iter()
+                .position(|round| side(round) == Action::DEFECT)
+        };
+        if let Some(own) = first(|round| round.blue.executed) {
+            nice &= first(|round| round.red.executed).is_some_and(|theirs| theirs < own);
+        }
+    }
+    let share = |(defections, rounds): (usize, usize)| {
+        if rounds == 0 {
+            0.0
+        } else {
+            defections as f64 / rounds as f64
+        }
+    };
+
+    let provoker = PANEL
+        .iter()
+        .position(|opponent| opponent.name == "provoker")
+        .expect("a provoker on the panel");
+    let answers: Vec<Action> = results[provoker].transcript[PROVOCATION + 1..]
+        .iter()
+        .map(|round| round.blue.executed)
+        .collect();
+    let retaliation = Retaliation {
+        after_defection: share(after[1]),
+        after_cooperation: share(after[0]),
+        delay: answers
+            .iter()
+            .position(|&action| action == Action::DEFECT)
+            .map(|i| i + 1),
+        defections: answers.iter().filter(|&&a| a == Action::DEFECT).count(),
+        forgives: answers.last() == Some(&Action::COOPERATE),
+    };
+
+    let played = results.len() * rounds;
+    let cooperations: usize = results.iter().map(|r| r.blue.cooperations).sum();
+    Ok(Profile {
+        strategy: strategy.to_owned(),
+        rounds,
+        cooperation: cooperations as f64 / played as f64,
+        nice,
+        retaliation,
+        responses,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile_of(strategy: &str) -> Profile {
+        let registry = StrategyRegistry::new();
+        profile(
+            &registry,
+            strategy,
+            &Parameters::new(),
+            PayoffMatrix::default(),
+            20,
+            1,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn tit_for_tat_answers_each_defection_once() {
+        let profile = profile_of("tit-for-tat");
+        assert!(profile.nice);
+        let retaliation = &profile.retaliation;
+        assert_eq!(retaliation.after_defection, 1.0);
+        assert_eq!(retaliation.after_cooperation, 0.0);
+        assert_eq!(
+            (
+                retaliation.delay,
+                retaliation.defections,
+                retaliation.forgives
+            ),
+            (Some(1), 1, true)
+        );
+        let alternator = &profile.responses[2];
+        assert_eq!(&alternator.moves[..6], "CCDCDC");
+        assert_eq!(
+            alternator.opponent_moves[..6],
+            profile.responses[2].moves[1..7]
+        );
+    }
+
+    #[test]
+    fn grim_never_forgives_and_always_defect_is_not_nice() {
+        let grim = profile_of("grim");
+        assert!(grim.nice);
+        assert_eq!(grim.retaliation.defections, 20 - PROVOCATION - 1);
+        assert!(!grim.retaliation.forgives);
+
+        let defector = profile_of("always-defect");
+        assert!(!defector.nice);
+        assert_eq!(defector.cooperation, 0.0);
+        assert_eq!(defector.retaliation.after_cooperation, 1.0);
+
+        let forgiving = profile_of("tit-for-two-tats");
+        assert_eq!(forgiving.retaliation.delay, None);
+        assert_eq!(forgiving.responses[0].cooperation, 1.0);
+    }
+
+    #[test]
+    fn too_few_rounds_to_answer_the_provoker_are_refused() {
+        let (registry, parameters) = (StrategyRegistry::new(), Parameters::new());
+        let payoffs = PayoffMatrix::default();
+        assert!(profile(&registry, "tit-for-tat", &parameters, payoffs, 5, 1).is_err());
+        assert!(profile(&registry, "no-such", &parameters, payoffs, 20, 1).is_err());
+    }
+}
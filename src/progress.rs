@@ -0,0 +1,270 @@
+//! A progress bar for long runs, drawn on stderr
+//!
+//! A [`Progress`] counts what a run has done: the matches finished and their
+//! rounds, and the generations an evolving population has played. Matches
+//! and generations started from a thread count towards the progress set
+//! there with [`watch`], as they publish to the event bus set with
+//! [`events::publish_to`](crate::events::publish_to).
+//!
+//! A [`ProgressBar`] redraws a line from those counts a few times a second:
+//! how far the run has got out of how far it will go, if that is known,
+//! the rounds played per second, and when it should be done at that rate.
+//! Anything else that writes to the terminal should call [`clear`] first,
+//! and the bar is drawn again on its next tick.
+
+use actix::prelude::*;
+use std::cell::RefCell;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+thread_local! {
+    /// The progress that matches started on this thread count towards
+    static WATCHING: RefCell<Option<Progress>> = const { RefCell::new(None) };
+}
+
+/// Whether a bar is on the terminal's last line
+static DRAWN: AtomicBool = AtomicBool::new(false);
+
+/// How often the bar is redrawn
+const REFRESH: Duration = Duration::from_millis(200);
+
+/// Characters across the bar itself
+const WIDTH: usize = 30;
+
+#[derive(Default)]
+struct Counts {
+    matches: AtomicUsize,
+    rounds: AtomicUsize,
+    generations: AtomicUsize,
+}
+
+/// What a run has finished so far; clones share the same counts
+#[derive(Clone, Default)]
+pub struct Progress(Arc<Counts>);
+
+impl Progress {
+    pub fn new() -> Progress {
+        Progress::default()
+    }
+
+    /// Count a match of `rounds` rounds
+    pub fn finish_match(&self, rounds: usize) {
+        self.0.matches.fetch_add(1, Ordering::Relaxed);
+        self.0.rounds.fetch_add(rounds, Ordering::Relaxed);
+    }
+
+    /// Count a generation
+    pub fn finish_generation(&self) {
+        self.0.generations.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn matches(&self) -> usize {
+        self.0.matches.load(Ordering::Relaxed)
+    }
+
+    pub fn rounds(&self) -> usize {
+        self.0.rounds.load(Ordering::Relaxed)
+    }
+
+    pub fn generations(&self) -> usize {
+        self.0.generations.load(Ordering::Relaxed)
+    }
+}
+
+/// Count the matches and generations started from this thread towards
+/// `progress`, or stop counting them with `None`
+pub fn watch(progress: Option<Progress>) {
+    WATCHING.with(|watching| *watching.borrow_mut() = progress);
+}
+
+/// What matches started from this thread count towards, if anything
+pub(crate) fn watching() -> Option<Progress> {
+    WATCHING.with(|watching| watching.borrow().clone())
+}
+
+/// Count a generation towards this thread's progress, if any
+pub(crate) fn finish_generation() {
+    if let Some(progress) = watching() {
+        progress.finish_generation();
+    }
+}
+
+/// Erase the bar, if one is drawn, so that a line can be written in its
+/// place
+pub fn clear() {
+    if DRAWN.swap(false, Ordering::Relaxed) {
+        eprint!("\r\x1b[2K");
+    }
+}
+
+/// What the bar measures the run in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Unit {
+    Matches,
+    Generations,
+}
+
+/// Minutes and seconds, or hours too if it comes to that
+fn clock(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    if seconds >= 3600 {
+        format!(
+            "{}:{:02}:{:02}",
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60
+        )
+    } else {
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    }
+}
+
+/// The bar's line, `elapsed` into a run that has done `done` out of `total`
+/// and played `rounds` rounds
+fn line(done: usize, total: Option<usize>, unit: Unit, rounds: usize, elapsed: Duration) -> String {
+    let unit = match unit {
+        Unit::Matches => "matches",
+        Unit::Generations => "generations",
+    };
+    let seconds = elapsed.as_secs_f64();
+    let rate = if seconds > 0.0 {
+        rounds as f64 / seconds
+    } else {
+        0.0
+    };
+    match total {
+        Some(total) if total > 0 => {
+            let share = (done as f64 / total as f64).min(1.0);
+            let filled = (share * WIDTH as f64).round() as usize;
+            let eta = if done == 0 {
+                "?".to_owned()
+            } else {
+                let left = seconds * (total.saturating_sub(done)) as f64 / done as f64;
+                clock(Duration::from_secs_f64(left))
+            };
+            format!(
+                "[{}{}] {}/{} {} {:>3.0}%  {:.0} rounds/s  ETA {}",
+                "#".repeat(filled),
+                "-".repeat(WIDTH - filled),
+                done,
+                total,
+                unit,
+                share * 100.0,
+                rate,
+                eta
+            )
+        }
+        _ => format!(
+            "{} {}  {:.0} rounds/s  {}",
+            done,
+            unit,
+            rate,
+            clock(elapsed)
+        ),
+    }
+}
+
+/// Redraws the progress of a run on stderr until it is sent [`Close`]
+pub struct ProgressBar {
+    progress: Progress,
+    total: Option<usize>,
+    unit: Unit,
+    started: Instant,
+}
+
+impl ProgressBar {
+    /// A bar for `progress`, which will be done at `total` of `unit`, if
+    /// that is known
+    pub fn new(progress: Progress, total: Option<usize>, unit: Unit) -> ProgressBar {
+        ProgressBar {
+            progress,
+            total,
+            unit,
+            started: Instant::now(),
+        }
+    }
+
+    fn draw(&self) {
+        let done = match self.unit {
+            Unit::Matches => self.progress.matches(),
+            Unit::Generations => self.progress.generations(),
+        };
+        let line = line(
+            done,
+            self.total,
+            self.unit,
+            self.progress.rounds(),
+            self.started.elapsed(),
+        );
+        let mut stderr = io::stderr().lock();
+        let _ = write!(stderr, "\r\x1b[2K{}", line);
+        let _ = stderr.flush();
+        DRAWN.store(true, Ordering::Relaxed);
+    }
+}
+
+impl Actor for ProgressBar {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Context<Self>) {
+        ctx.run_interval(REFRESH, |act, _ctx| act.draw());
+    }
+}
+
+/// Take the bar off the terminal and stop redrawing it
+pub struct Close;
+
+impl Message for Close {
+    type Result = ();
+}
+
+impl Handler<Close> for ProgressBar {
+    type Result = ();
+
+    fn handle(&mut self, _msg: Close, ctx: &mut Context<Self>) -> Self::Result {
+        clear();
+        ctx.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_line_shows_how_far_and_how_fast() {
+        let bar = line(10, Some(40), Unit::Matches, 2000, Duration::from_secs(20));
+        assert!(bar.starts_with("[########----------------------] 10/40 matches  25%"));
+        assert!(bar.contains("100 rounds/s"));
+        // 30 more matches at 2 seconds each
+        assert!(bar.ends_with("ETA 1:00"), "{}", bar);
+
+        let open = line(3, None, Unit::Generations, 0, Duration::from_secs(3661));
+        assert_eq!(open, "3 generations  0 rounds/s  1:01:01");
+    }
+
+    #[actix_rt::test]
+    async fn matches_started_while_watching_are_counted() {
+        use crate::config::{ParticipantConfig, SimConfig};
+        use crate::registry::StrategyRegistry;
+        use crate::tournament;
+
+        let config = SimConfig {
+            iterations: 7,
+            participants: vec![
+                ParticipantConfig::new("tft", "tit-for-tat"),
+                ParticipantConfig::new("alld", "always-defect"),
+                ParticipantConfig::new("allc", "always-cooperate"),
+            ],
+            ..SimConfig::default()
+        };
+        let progress = Progress::new();
+        watch(Some(progress.clone()));
+        tournament::round_robin(&config, &StrategyRegistry::new(), 1, None).await;
+        watch(None);
+        tournament::round_robin(&config, &StrategyRegistry::new(), 1, None).await;
+        assert_eq!((progress.matches(), progress.rounds()), (3, 21));
+    }
+}
@@ -0,0 +1,575 @@
+//! The n-player public goods game
+//!
+//! Each round every prisoner in a group either contributes `cost` to a common
+//! pot (cooperates) or free-rides (defects). The pot is multiplied by
+//! `multiplier` and shared equally among the whole group, contributors or
+//! not. With `1 < multiplier < size`, everyone is better off when all
+//! contribute, but each is better off free-riding.
+
+use actix::prelude::*;
+use futures::channel::oneshot;
+use futures::future::join_all;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::Duration;
+use tracing::{debug, warn};
+
+use crate::config::{ParticipantConfig, SimConfig};
+use crate::control::{self, Control};
+use crate::game::{Action, Payoff, PlayerOutcome, Score};
+use crate::interrupt;
+use crate::prisoner::{
+    self, BudgetAction, FaultKind, GroupOutcome, Interrogate, MatchId, Prisoner, StrategyFault,
+};
+use crate::referee::Forfeit;
+use crate::registry::StrategyRegistry;
+use crate::tournament;
+
+/// The rules of one public goods game
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct PublicGoods {
+    pub size: usize,
+    pub multiplier: f64,
+    pub cost: Score,
+}
+
+impl PublicGoods {
+    /// Checks that the game is a social dilemma: `1 < multiplier < size`
+    pub fn new(size: usize, multiplier: f64, cost: Score) -> Result<PublicGoods, String> {
+        if size < 2 {
+            return Err(format!("a group of {} is too small", size));
+        }
+        if !(multiplier > 1.0 && multiplier < size as f64) {
+            return Err(format!(
+                "multiplier {} must be above 1 and below the group size {}",
+                multiplier, size
+            ));
+        }
+        if cost <= 0.0 {
+            return Err(format!("cost {} must be positive", cost));
+        }
+        Ok(PublicGoods {
+            size,
+            multiplier,
+            cost,
+        })
+    }
+
+    /// What a player receives given its own action and how many contributed
+    /// in all; abstaining is free-riding
+    pub fn payoff(&self, own: Action, contributors: usize) -> Score {
+        let share = self.multiplier * self.cost * contributors as f64 / self.size as f64;
+        match own {
+            Action::COOPERATE => share - self.cost,
+            _ => share,
+        }
+    }
+}
+
+/// Start the group game; resolves with the result once the last round is scored
+pub struct PlayGroup;
+
+impl Message for PlayGroup {
+    type Result = Result<GroupResult, MailboxError>;
+}
+
+/// Sent by the group referee to itself for each round
+struct PlayGroupRound;
+
+impl Message for PlayGroupRound {
+    type Result = ();
+}
+
+/// How one prisoner fared in a group game
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GroupPlayerSummary {
+    pub name: String,
+    pub score: Score,
+    pub contributions: usize,
+
+    /// Rounds in which this prisoner missed the decision timeout
+    #[serde(default)]
+    pub timeouts: usize,
+
+    /// Rounds this prisoner forfeited for having used up its computation
+    /// budget
+    #[serde(default)]
+    pub over_budget: usize,
+}
+
+/// The outcome of one group game
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GroupResult {
+    pub rounds: usize,
+    pub players: Vec<GroupPlayerSummary>,
+
+    /// Prisoners that panicked, went silent, or went over their budget; the
+    /// game ends when one does
+    pub faults: Vec<StrategyFault>,
+
+    /// How many contributed in each round
+    pub contributors: Vec<usize>,
+}
+
+struct Member {
+    name: String,
+    addr: Addr<Prisoner>,
+    last: Option<Action>,
+    amount: Score,
+    score: Score,
+    contributions: usize,
+    timeouts: usize,
+    over_budget: usize,
+}
+
+/// Owns the game loop for one group
+pub struct GroupReferee {
+    match_id: MatchId,
+    game: PublicGoods,
+    members: Vec<Member>,
+    iterations: usize,
+    retries: usize,
+    decision_timeout: Option<Duration>,
+    timeout_action: Action,
+    on_exceed: BudgetAction,
+    delay: Option<Duration>,
+    sequence: usize,
+
+    /// What steers the game from outside, see [`GroupReferee::with_control`]
+    control: Control,
+
+    /// [`Control::skips`] when the game started
+    skips: usize,
+    faults: Vec<StrategyFault>,
+    contributors: Vec<usize>,
+    result_tx: Option<oneshot::Sender<GroupResult>>,
+}
+
+impl GroupReferee {
+    /// `members` must number `game.size`
+    pub fn new(
+        game: PublicGoods,
+        members: Vec<(&str, Addr<Prisoner>)>,
+        iterations: usize,
+    ) -> GroupReferee {
+        assert_eq!(members.len(), game.size, "one member per seat");
+        GroupReferee {
+            match_id: 0,
+            game,
+            members: members
+                .into_iter()
+                .map(|(name, addr)| Member {
+                    name: name.to_owned(),
+                    addr,
+                    last: None,
+                    amount: 0.0,
+                    score: 0.0,
+                    contributions: 0,
+                    timeouts: 0,
+                    over_budget: 0,
+                })
+                .collect(),
+            iterations,
+            retries: 0,
+            decision_timeout: None,
+            timeout_action: Action::DEFECT,
+            on_exceed: BudgetAction::ForfeitMatch,
+            delay: None,
+            sequence: 0,
+            control: Control::default(),
+            skips: 0,
+            faults: Vec::new(),
+            contributors: Vec::new(),
+            result_tx: None,
+        }
+    }
+
+    /// Tell the members which game they are playing, so prisoners in several
+    /// games at once keep them apart
+    pub fn with_match_id(mut self, match_id: MatchId) -> GroupReferee {
+        self.match_id = match_id;
+        self
+    }
+
+    /// Resend an unanswered message up to `retries` times, backing off each
+    /// time, before the silent prisoner forfeits
+    pub fn with_retries(mut self, retries: usize) -> GroupReferee {
+        self.retries = retries;
+        self
+    }
+
+    /// Play `action` for a member that takes longer than `timeout` to decide
+    pub fn with_decision_timeout(mut self, timeout: Duration, action: Action) -> GroupReferee {
+        self.decision_timeout = Some(timeout);
+        self.timeout_action = action;
+        self
+    }
+
+    /// Play the timeout action for a member over its computation budget, if
+    /// `on_exceed` forfeits rounds, as
+    /// [`crate::referee::Referee::with_budget`] does
+    pub fn with_budget(mut self, on_exceed: BudgetAction) -> GroupReferee {
+        self.on_exceed = on_exceed;
+        self
+    }
+
+    /// Wait `delay` after each round before playing the next, as
+    /// [`crate::referee::Referee::with_delay`] does
+    pub fn with_delay(mut self, delay: Duration) -> GroupReferee {
+        self.delay = Some(delay);
+        self
+    }
+
+    /// Pause and skip as `control` says, as for [`Referee::with_control`]
+    ///
+    /// [`Referee::with_control`]: crate::referee::Referee::with_control
+    pub fn with_control(mut self, control: Control) -> GroupReferee {
+        self.control = control;
+        self
+    }
+
+    fn interrogate(&self, member: &Member) -> Interrogate {
+        let others_cooperated = member.last.map(|own| {
+            let total = self.contributors.last().copied().unwrap_or(0);
+            total - (own == Action::COOPERATE) as usize
+        });
+        // the group as one opponent: it cooperated if most of the others did
+        let others = self.game.size - 1;
+        let opponent = others_cooperated.map(|n| {
+            if 2 * n >= others {
+                Action::COOPERATE
+            } else {
+                Action::DEFECT
+            }
+        });
+        let prev_payoff = match (member.last, opponent) {
+            (Some(own), Some(opponent)) => payoff_class(own, opponent),
+            _ => Payoff::NULL,
+        };
+        Interrogate {
+            match_id: self.match_id,
+            sequence: self.sequence,
+            prev: PlayerOutcome {
+                payoff: prev_payoff,
+                amount: member.amount,
+            },
+            own_action: member.last,
+            opponent_action: opponent,
+            group: Some(GroupOutcome {
+                size: self.game.size,
+                others_cooperated,
+            }),
+        }
+    }
+
+    fn result(&self) -> GroupResult {
+        GroupResult {
+            rounds: self.sequence,
+            players: self
+                .members
+                .iter()
+                .map(|member| GroupPlayerSummary {
+                    name: member.name.clone(),
+                    score: member.score,
+                    contributions: member.contributions,
+                    timeouts: member.timeouts,
+                    over_budget: member.over_budget,
+                })
+                .collect(),
+            faults: self.faults.clone(),
+            contributors: self.contributors.clone(),
+        }
+    }
+
+    fn report(&mut self) {
+        if let Some(tx) = self.result_tx.take() {
+            let _ = tx.send(self.result());
+        }
+    }
+
+    fn finish(&mut self, ctx: &mut Context<Self>) {
+        self.report();
+        ctx.stop();
+    }
+}
+
+/// The pairwise payoff class for `own` against the group seen as one opponent
+fn payoff_class(own: Action, opponent: Action) -> Payoff {
+    match (own, opponent) {
+        (Action::COOPERATE, Action::COOPERATE) => Payoff::REWARD,
+        (Action::COOPERATE, Action::DEFECT) => Payoff::SUCKER,
+        (Action::DEFECT, Action::COOPERATE) => Payoff::TEMPTATION,
+        _ => Payoff::PUNISHMENT,
+    }
+}
+
+impl Actor for GroupReferee {
+    type Context = Context<Self>;
+
+    fn stopped(&mut self, _ctx: &mut Context<Self>) {
+        // stopped before the last round: report what we have
+        self.report();
+    }
+}
+
+impl Handler<PlayGroup> for GroupReferee {
+    type Result = ResponseFuture<Result<GroupResult, MailboxError>>;
+
+    fn handle(&mut self, _msg: PlayGroup, ctx: &mut Context<Self>) -> Self::Result {
+        let (tx, rx) = oneshot::channel();
+        self.result_tx = Some(tx);
+        self.skips = self.control.skips();
+        ctx.notify(PlayGroupRound);
+        Box::pin(async move { rx.await.map_err(|_| MailboxError::Closed) })
+    }
+}
+
+impl Handler<PlayGroupRound> for GroupReferee {
+    type Result = ResponseActFuture<Self, ()>;
+
+    fn handle(&mut self, _msg: PlayGroupRound, ctx: &mut Context<Self>) -> Self::Result {
+        if self.control.paused() && !interrupt::interrupted() {
+            ctx.notify_later(PlayGroupRound, control::POLL);
+            return Box::pin(actix::fut::ready(()));
+        }
+        let (retries, timeout, on_exceed) = (self.retries, self.decision_timeout, self.on_exceed);
+        let interrogations: Vec<_> = self
+            .members
+            .iter()
+            .map(|member| {
+                let (name, addr) = (member.name.clone(), member.addr.clone());
+                let msg = self.interrogate(member);
+                async move {
+                    match prisoner::ask(&addr, msg, retries, timeout).await {
+                        Ok(Err(fault))
+                            if fault.kind == FaultKind::OverBudget
+                                && on_exceed == BudgetAction::ForfeitRound =>
+                        {
+                            Ok(Err(Forfeit::OverBudget))
+                        }
+                        Ok(answer) => answer.map(Ok),
+                        Err(MailboxError::Timeout) => {
+                            warn!(player = %name, "ran out of time to decide");
+                            Ok(Err(Forfeit::TimedOut))
+                        }
+                        Err(e) => Err(StrategyFault::unresponsive(&name, e)),
+                    }
+                }
+            })
+            .collect();
+
+        Box::pin(
+            join_all(interrogations)
+                .into_actor(self)
+                .map(|results, act, ctx| {
+                    let mut actions = Vec::with_capacity(results.len());
+                    for (i, result) in results.into_iter().enumerate() {
+                        match result {
+                            Ok(Ok(action)) => actions.push(action),
+                            Ok(Err(forfeit)) => {
+                                match forfeit {
+                                    Forfeit::TimedOut => act.members[i].timeouts += 1,
+                                    Forfeit::OverBudget => act.members[i].over_budget += 1,
+                                }
+                                actions.push(act.timeout_action);
+                            }
+                            Err(fault) => {
+                                warn!(%fault, sequence = act.sequence, "forfeits the game");
+                                act.faults.push(fault);
+                            }
+                        }
+                    }
+                    if !act.faults.is_empty() {
+                        act.finish(ctx);
+                        return;
+                    }
+
+                    let contributors = actions
+                        .iter()
+                        .filter(|&&action| action == Action::COOPERATE)
+                        .count();
+                    for (member, &action) in act.members.iter_mut().zip(&actions) {
+                        member.last = Some(action);
+                        member.amount = act.game.payoff(action, contributors);
+                        member.score += member.amount;
+                        member.contributions += (action == Action::COOPERATE) as usize;
+                    }
+                    act.contributors.push(contributors);
+
+                    act.sequence += 1;
+                    if act.sequence >= act.iterations {
+                        debug!(rounds = act.sequence, "completed");
+                        act.finish(ctx);
+                    } else if interrupt::interrupted() {
+                        debug!(rounds = act.sequence, "interrupted");
+                        act.finish(ctx);
+                    } else if act.control.skips() > act.skips {
+                        debug!(rounds = act.sequence, "skipped");
+                        act.finish(ctx);
+                    } else if let Some(delay) = act.delay {
+                        ctx.notify_later(PlayGroupRound, delay);
+                    } else {
+                        ctx.notify(PlayGroupRound);
+                    }
+                }),
+        )
+    }
+}
+
+/// One participant's totals across every group it played in
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GroupStanding {
+    pub name: String,
+    pub strategy: String,
+    pub groups: usize,
+    pub rounds: usize,
+    pub score: Score,
+    pub average_score: f64,
+    pub contribution_rate: f64,
+}
+
+/// Everything needed to reproduce and analyse a public goods tournament
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PublicGoodsRecord {
+    pub config: SimConfig,
+    pub seed: u64,
+    pub game: PublicGoods,
+    pub groups: Vec<GroupResult>,
+
+    /// Highest total score first
+    pub standings: Vec<GroupStanding>,
+}
+
+/// Every way of choosing `k` of `n` entrants, in a fixed order
+fn combinations(n: usize, k: usize) -> Vec<Vec<usize>> {
+    if k == 0 {
+        return vec![Vec::new()];
+    }
+    if k > n {
+        return Vec::new();
+    }
+    let mut with_last: Vec<Vec<usize>> = combinations(n - 1, k - 1);
+    for group in &mut with_last {
+        group.push(n - 1);
+    }
+    let mut all = combinations(n - 1, k);
+    all.extend(with_last);
+    all.sort();
+    all
+}
+
+fn start_group(
+    config: &SimConfig,
+    registry: &StrategyRegistry,
+    game: PublicGoods,
+    members: &[&ParticipantConfig],
+    seeds: &mut StdRng,
+) -> impl std::future::Future<Output = Result<GroupResult, MailboxError>> {
+    let worker = tournament::next_worker();
+    let prisoners: Vec<(&str, Addr<Prisoner>)> = members
+        .iter()
+        .map(|participant| {
+            let prisoner = tournament::prisoner(config, registry, participant, seeds.gen());
+            (
+                participant.name.as_str(),
+                tournament::start_on(worker.as_ref(), prisoner),
+            )
+        })
+        .collect();
+    let mut referee = GroupReferee::new(game, prisoners, config.iterations)
+        .with_match_id(tournament::next_match_id())
+        .with_retries(config.retries);
+    if let Some(timeout) = config.decision_timeout() {
+        referee = referee.with_decision_timeout(timeout, config.timeout_action);
+    }
+    if let Some(budget) = config.budget {
+        referee = referee.with_budget(budget.on_exceed);
+    }
+    if let Some(delay) = config.delay() {
+        referee = referee.with_delay(delay);
+    }
+    if let Some(control) = control::steering() {
+        referee = referee.with_control(control);
+    }
+    let referee = tournament::start_on(worker.as_ref(), referee);
+    async move { referee.send(PlayGroup).await.and_then(|result| result) }
+}
+
+/// Play every group of `game.size` participants once, all concurrently
+///
+/// The config should already have been validated against `registry`. The
+/// same seed replays the same tournament. Groups that never report are
+/// left out.
+pub async fn play_public_goods(
+    config: &SimConfig,
+    registry: &StrategyRegistry,
+    seed: u64,
+    game: PublicGoods,
+) -> PublicGoodsRecord {
+    let mut seeds = StdRng::seed_from_u64(seed);
+    let groups: Vec<_> = combinations(config.participants.len(), game.size)
+        .into_iter()
+        .map(|group| {
+            let members: Vec<&ParticipantConfig> =
+                group.iter().map(|&i| &config.participants[i]).collect();
+            start_group(config, registry, game, &members, &mut seeds)
+        })
+        .collect();
+    let groups: Vec<GroupResult> = join_all(groups)
+        .await
+        .into_iter()
+        .filter_map(|result| match result {
+            Ok(result) => Some(result),
+            Err(e) => {
+                warn!(error = %e, "group game aborted");
+                None
+            }
+        })
+        .collect();
+
+    let mut totals: BTreeMap<&str, (usize, usize, Score, usize)> = BTreeMap::new();
+    for result in &groups {
+        for player in &result.players {
+            let entry = totals.entry(player.name.as_str()).or_default();
+            entry.0 += 1;
+            entry.1 += result.rounds;
+            entry.2 += player.score;
+            entry.3 += player.contributions;
+        }
+    }
+    let mut standings: Vec<GroupStanding> = config
+        .participants
+        .iter()
+        .map(|participant| {
+            let (groups, rounds, score, contributions) = totals
+                .get(participant.name.as_str())
+                .copied()
+                .unwrap_or_default();
+            let per_round = |n: f64| if rounds == 0 { 0.0 } else { n / rounds as f64 };
+            GroupStanding {
+                name: participant.name.clone(),
+                strategy: participant.strategy.clone(),
+                groups,
+                rounds,
+                score,
+                average_score: per_round(score),
+                contribution_rate: per_round(contributions as f64),
+            }
+        })
+        .collect();
+    standings.sort_by(|a, b| {
+        b.score
+            .total_cmp(&a.score)
+            .then_with(|| a.name.cmp(&b.name))
+    });
+
+    PublicGoodsRecord {
+        config: config.clone(),
+        seed,
+        game,
+        groups,
+        standings,
+    }
+}
@@ -0,0 +1,617 @@
+//! Auditable randomness for stochastic strategies
+//!
+//! Every built-in or scripted strategy that rolls dice draws from a
+//! [`StrategyRng`], seeded from its prisoner's seed for the match. A
+//! `StrategyRng` plays exactly the stream a `StdRng` would. One built under
+//! [`seated`] also draws through that [`Seat`], the source every strategy
+//! instance of one side shares, and the seat notes down each word drawn
+//! for the round its prisoner says is being played, whichever of the
+//! strategy's methods drew it.
+//!
+//! With `audit_randomness` set in the config, each match gets a
+//! [`RandomnessProvider`]: both prisoners hand it what their seats noted,
+//! and it keeps them as the match's [`DrawLog`], which goes into the
+//! result. Since the draws follow from the seeds alone, replaying a
+//! recorded match makes the same draws again, and [`draw_divergence`]
+//! points out the first round where it didn't, which shows whether the
+//! recording is consistent with its seeds.
+//!
+//! Every generator involved is a [`Stream`], which knows how far along it
+//! is, so the log also keeps where each strategy's generators and the
+//! referee's stood as each round began. A match can then be taken up from
+//! any round: strategies rebuilt from their seeds and walked through the
+//! rounds before are put back where their streams were, and play on
+//! exactly as they did; see [`crate::recording::resume`].
+
+#[cfg(feature = "actors")]
+use actix::prelude::*;
+use rand::rngs::StdRng;
+use rand::{Error, RngCore, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::sync::{Arc, Mutex};
+
+thread_local! {
+    /// The seat that generators built on this thread draw through, if any
+    static SEATING: RefCell<Option<Seat>> = const { RefCell::new(None) };
+}
+
+/// A generator playing the same stream as a `StdRng` seeded alike, which
+/// counts how many 32-bit words of it have been drawn
+///
+/// A `u32` is one word, a `u64` two, and bytes one word for every four or
+/// fewer, as the underlying generator takes them, so drawing `position`
+/// words one by one from a fresh stream brings it to the same state.
+#[derive(Clone)]
+pub struct Stream {
+    rng: StdRng,
+    start: StdRng,
+    position: u64,
+}
+
+impl Stream {
+    fn new(rng: StdRng) -> Stream {
+        Stream {
+            start: rng.clone(),
+            rng,
+            position: 0,
+        }
+    }
+
+    /// Words drawn since the stream was seeded
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// Go to `position` words into the stream, back to its seed first if
+    /// it is already past there
+    pub fn seek(&mut self, position: u64) {
+        if position < self.position {
+            self.rng = self.start.clone();
+            self.position = 0;
+        }
+        while self.position < position {
+            self.next_u32();
+        }
+    }
+}
+
+impl RngCore for Stream {
+    fn next_u32(&mut self) -> u32 {
+        self.position += 1;
+        self.rng.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.position += 2;
+        self.rng.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.position += dest.len().div_ceil(4) as u64;
+        self.rng.fill_bytes(dest);
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl SeedableRng for Stream {
+    type Seed = <StdRng as SeedableRng>::Seed;
+
+    fn from_seed(seed: Self::Seed) -> Stream {
+        Stream::new(StdRng::from_seed(seed))
+    }
+
+    fn seed_from_u64(state: u64) -> Stream {
+        Stream::new(StdRng::seed_from_u64(state))
+    }
+}
+
+/// What one side's strategies drew, round by round, since it was last taken
+#[derive(Default)]
+struct Drawing {
+    round: usize,
+    draws: Vec<Draw>,
+
+    /// Every generator built at the seat, in the order built
+    streams: Vec<Arc<Mutex<Stream>>>,
+
+    /// Where those generators stood as each round began
+    positions: Vec<Position>,
+}
+
+/// The source of one side's random draws, logging every word drawn
+#[derive(Clone, Default)]
+pub struct Seat(Arc<Mutex<Drawing>>);
+
+impl Seat {
+    pub fn new() -> Seat {
+        Seat::default()
+    }
+
+    /// Note what is drawn from here on under `round`, counting from 0, and
+    /// where the seat's generators stand as it begins
+    pub fn at(&self, round: usize) {
+        let streams = {
+            let mut drawing = self.drawing();
+            drawing.round = round;
+            if drawing.positions.last().map(|p| p.round) == Some(round) {
+                return;
+            }
+            drawing.streams.clone()
+        };
+        let streams = streams.iter().map(|s| lock(s).position()).collect();
+        self.drawing().positions.push(Position { round, streams });
+    }
+
+    /// The draws noted since they were last taken
+    pub fn take(&self) -> Vec<Draw> {
+        std::mem::take(&mut self.drawing().draws)
+    }
+
+    /// Where the generators stood as each round began, since last taken
+    pub fn take_positions(&self) -> Vec<Position> {
+        std::mem::take(&mut self.drawing().positions)
+    }
+
+    /// Move the generators built at the seat to `streams`, in the order
+    /// they were built; any built after the last of `streams` stay put
+    pub fn seek(&self, streams: &[u64]) {
+        let built = self.drawing().streams.clone();
+        for (stream, &position) in built.iter().zip(streams) {
+            lock(stream).seek(position);
+        }
+    }
+
+    fn enrol(&self, stream: Arc<Mutex<Stream>>) {
+        self.drawing().streams.push(stream);
+    }
+
+    fn note(&self, value: u64) {
+        let mut drawing = self.drawing();
+        let round = drawing.round;
+        match drawing.draws.last_mut() {
+            Some(draw) if draw.round == round => draw.values.push(value),
+            _ => drawing.draws.push(Draw {
+                round,
+                values: vec![value],
+            }),
+        }
+    }
+
+    fn drawing(&self) -> std::sync::MutexGuard<'_, Drawing> {
+        // a strategy that panicked mid-draw left a whole word behind
+        self.0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+fn lock(stream: &Mutex<Stream>) -> std::sync::MutexGuard<'_, Stream> {
+    stream
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Run `build`, so that every [`StrategyRng`] it seeds draws through `seat`
+pub fn seated<T>(seat: &Seat, build: impl FnOnce() -> T) -> T {
+    let outer = SEATING.with(|seating| seating.replace(Some(seat.clone())));
+    let built = build();
+    SEATING.with(|seating| *seating.borrow_mut() = outer);
+    built
+}
+
+/// A strategy's random number generator, whose draws can be logged
+pub struct StrategyRng {
+    /// Shared with the seat, if any, which may move it along its stream
+    stream: Arc<Mutex<Stream>>,
+    seat: Option<Seat>,
+}
+
+impl StrategyRng {
+    fn new(rng: StdRng) -> StrategyRng {
+        let stream = Arc::new(Mutex::new(Stream::new(rng)));
+        let seat = SEATING.with(|seating| seating.borrow().clone());
+        if let Some(seat) = &seat {
+            seat.enrol(stream.clone());
+        }
+        StrategyRng { stream, seat }
+    }
+
+    fn note(&self, value: u64) {
+        if let Some(seat) = &self.seat {
+            seat.note(value);
+        }
+    }
+}
+
+impl RngCore for StrategyRng {
+    fn next_u32(&mut self) -> u32 {
+        let value = lock(&self.stream).next_u32();
+        self.note(value.into());
+        value
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let value = lock(&self.stream).next_u64();
+        self.note(value);
+        value
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        lock(&self.stream).fill_bytes(dest);
+        for chunk in dest.chunks(8) {
+            let mut word = [0; 8];
+            word[..chunk.len()].copy_from_slice(chunk);
+            self.note(u64::from_le_bytes(word));
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl SeedableRng for StrategyRng {
+    type Seed = <StdRng as SeedableRng>::Seed;
+
+    fn from_seed(seed: Self::Seed) -> StrategyRng {
+        StrategyRng::new(StdRng::from_seed(seed))
+    }
+
+    /// The same stream as `StdRng::seed_from_u64(state)`, so that seeds
+    /// mean what they always have
+    fn seed_from_u64(state: u64) -> StrategyRng {
+        StrategyRng::new(StdRng::seed_from_u64(state))
+    }
+}
+
+/// The words one side's strategy drew to decide one round
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Draw {
+    /// The round decided, counting from 0
+    pub round: usize,
+    pub values: Vec<u64>,
+}
+
+/// Where one side's generators stood as a round began
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Position {
+    /// The round about to be decided, counting from 0
+    pub round: usize,
+
+    /// Words drawn from each generator, in the order they were built
+    pub streams: Vec<u64>,
+}
+
+/// Where every stream of a match stood as a round began, enough to take
+/// the match up from there
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Positions {
+    /// The round about to be played, counting from 0
+    pub round: usize,
+
+    /// Words drawn from each of the side's generators, in the order they
+    /// were built
+    pub blue: Vec<u64>,
+    pub red: Vec<u64>,
+
+    /// Words drawn from the referee's, for noise and termination
+    pub referee: u64,
+}
+
+/// Every draw of one match, side by side; rounds without draws are left out
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DrawLog {
+    pub blue: Vec<Draw>,
+    pub red: Vec<Draw>,
+
+    /// Where the streams stood as each round began, in order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub positions: Vec<Positions>,
+}
+
+impl DrawLog {
+    /// Words drawn in all, by both sides
+    pub fn len(&self) -> usize {
+        self.blue
+            .iter()
+            .chain(&self.red)
+            .map(|draw| draw.values.len())
+            .sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Where the streams stood as round `round`, counting from 0, began
+    pub fn at(&self, round: usize) -> Option<&Positions> {
+        self.positions.iter().find(|p| p.round == round)
+    }
+}
+
+/// The first round, from 1, in which either side of `replayed` drew
+/// something other than it did in `recorded`
+pub fn draw_divergence(recorded: &DrawLog, replayed: &DrawLog) -> Option<usize> {
+    let side = |recorded: &[Draw], replayed: &[Draw]| {
+        recorded
+            .iter()
+            .zip(replayed)
+            .find(|(a, b)| a != b)
+            .map(|(a, b)| a.round.min(b.round))
+            .or_else(|| {
+                // one side went on drawing after the other stopped
+                let (longer, shorter) = if recorded.len() > replayed.len() {
+                    (recorded, replayed)
+                } else {
+                    (replayed, recorded)
+                };
+                longer.get(shorter.len()).map(|draw| draw.round)
+            })
+    };
+    match (
+        side(&recorded.blue, &replayed.blue),
+        side(&recorded.red, &replayed.red),
+    ) {
+        (Some(blue), Some(red)) => Some(blue.min(red)),
+        (blue, red) => blue.or(red),
+    }
+    .map(|round| round + 1)
+}
+
+/// Keeps the draws of one match's prisoners, and where their streams and
+/// the referee's stood
+#[cfg(feature = "actors")]
+pub struct RandomnessProvider {
+    blue: String,
+    red: String,
+    log: DrawLog,
+    blue_positions: Vec<Position>,
+    red_positions: Vec<Position>,
+
+    /// The referee's stream as each round began
+    referee: Vec<(usize, u64)>,
+}
+
+#[cfg(feature = "actors")]
+impl RandomnessProvider {
+    /// A provider for the match between the prisoners named `blue` and
+    /// `red`
+    pub fn new(blue: &str, red: &str) -> RandomnessProvider {
+        RandomnessProvider {
+            blue: blue.to_owned(),
+            red: red.to_owned(),
+            log: DrawLog::default(),
+            blue_positions: Vec::new(),
+            red_positions: Vec::new(),
+            referee: Vec::new(),
+        }
+    }
+}
+
+#[cfg(feature = "actors")]
+impl Actor for RandomnessProvider {
+    type Context = Context<Self>;
+}
+
+/// Sent by a prisoner with what its strategy drew for some rounds, and
+/// where its generators stood as they began
+#[cfg(feature = "actors")]
+pub struct Drawn {
+    pub name: String,
+    pub draws: Vec<Draw>,
+    pub positions: Vec<Position>,
+}
+
+#[cfg(feature = "actors")]
+impl Message for Drawn {
+    type Result = ();
+}
+
+#[cfg(feature = "actors")]
+impl Handler<Drawn> for RandomnessProvider {
+    type Result = ();
+
+    fn handle(&mut self, msg: Drawn, _ctx: &mut Context<Self>) {
+        let (side, positions) = if msg.name == self.blue {
+            (&mut self.log.blue, &mut self.blue_positions)
+        } else if msg.name == self.red {
+            (&mut self.log.red, &mut self.red_positions)
+        } else {
+            return;
+        };
+        positions.extend(msg.positions);
+        for draw in msg.draws {
+            // a round's draws may come in several parts, e.g. its signal's
+            match side.last_mut() {
+                Some(last) if last.round == draw.round => last.values.extend(draw.values),
+                _ => side.push(draw),
+            }
+        }
+    }
+}
+
+/// Sent by the referee with where its stream stood as a round began
+#[cfg(feature = "actors")]
+pub struct RefereeAt {
+    pub round: usize,
+    pub position: u64,
+}
+
+#[cfg(feature = "actors")]
+impl Message for RefereeAt {
+    type Result = ();
+}
+
+#[cfg(feature = "actors")]
+impl Handler<RefereeAt> for RandomnessProvider {
+    type Result = ();
+
+    fn handle(&mut self, msg: RefereeAt, _ctx: &mut Context<Self>) {
+        self.referee.push((msg.round, msg.position));
+    }
+}
+
+/// Where one side's generators stood as `round` began: as they were last
+/// seen at or before it, or where they were built if never seen
+#[cfg(feature = "actors")]
+fn side_at(positions: &[Position], round: usize) -> Vec<u64> {
+    positions
+        .iter()
+        .rev()
+        .find(|p| p.round <= round)
+        .map(|p| p.streams.clone())
+        .unwrap_or_default()
+}
+
+/// Hand over the match's draws, once its prisoners have answered for
+/// its last round
+#[cfg(feature = "actors")]
+pub struct TakeDraws;
+
+#[cfg(feature = "actors")]
+impl Message for TakeDraws {
+    type Result = DrawLog;
+}
+
+#[cfg(feature = "actors")]
+impl Handler<TakeDraws> for RandomnessProvider {
+    type Result = MessageResult<TakeDraws>;
+
+    fn handle(&mut self, _msg: TakeDraws, _ctx: &mut Context<Self>) -> Self::Result {
+        let mut log = std::mem::take(&mut self.log);
+        let referee = std::mem::take(&mut self.referee);
+        log.positions = referee
+            .into_iter()
+            .map(|(round, referee)| Positions {
+                round,
+                blue: side_at(&self.blue_positions, round),
+                red: side_at(&self.red_positions, round),
+                referee,
+            })
+            .collect();
+        MessageResult(log)
+    }
+}
+
+#[cfg(all(test, feature = "actors"))]
+mod tests {
+    use super::*;
+    use crate::game::{Action, PayoffMatrix};
+    use crate::prisoner::Prisoner;
+    use crate::referee::{Play, Referee};
+    use crate::strategy::{History, Strategy};
+    use futures::future::BoxFuture;
+    use rand::Rng;
+
+    /// Draws a word in every method a prisoner may call
+    struct Dice(Arc<Mutex<StrategyRng>>);
+
+    impl Dice {
+        fn roll(&self) -> bool {
+            self.0.lock().unwrap().gen()
+        }
+    }
+
+    impl Strategy for Dice {
+        fn name(&self) -> &str {
+            "dice"
+        }
+
+        fn choose(&mut self, _history: &History) -> Action {
+            Action::COOPERATE
+        }
+
+        fn choose_async(
+            &mut self,
+            _history: &History,
+        ) -> Option<BoxFuture<'static, Result<Action, String>>> {
+            self.roll();
+            let rng = self.0.clone();
+            Some(Box::pin(async move {
+                rng.lock().unwrap().gen::<bool>();
+                Ok(Action::COOPERATE)
+            }))
+        }
+
+        fn signal(&mut self, _history: &History) -> Option<Action> {
+            self.roll();
+            None
+        }
+
+        fn reset(&mut self) {
+            self.roll();
+        }
+    }
+
+    #[actix_rt::test]
+    async fn draws_outside_choose_are_logged_too() {
+        let provider = RandomnessProvider::new("blue", "red").start();
+        let seat = Seat::new();
+        let dice = seated(&seat, || {
+            Dice(Arc::new(Mutex::new(StrategyRng::seed_from_u64(5))))
+        });
+        let blue = Prisoner::new("blue", Box::new(dice))
+            .with_randomness(seat, provider.clone().recipient())
+            .start();
+        let red = Prisoner::new("red", Box::new(crate::strategies::AlwaysCooperate {})).start();
+        for _ in 0..2 {
+            Referee::new(
+                ("blue", blue.clone()),
+                ("red", red.clone()),
+                PayoffMatrix::default(),
+                3,
+            )
+            .with_cheap_talk()
+            .start()
+            .send(Play)
+            .await
+            .unwrap()
+            .unwrap();
+        }
+        let log = provider.send(TakeDraws).await.unwrap();
+        let counts: Vec<_> = log
+            .blue
+            .iter()
+            .map(|draw| (draw.round, draw.values.len()))
+            .collect();
+        // a signal, the call to choose_async, and its future each round;
+        // the reset before the second match goes into its first round
+        assert_eq!(counts, vec![(0, 3), (1, 3), (2, 3), (0, 4), (1, 3), (2, 3)]);
+        assert!(log.red.is_empty());
+
+        let mut stream = StdRng::seed_from_u64(5);
+        let words: Vec<_> = (0..log.len()).map(|_| stream.next_u32() as u64).collect();
+        let logged: Vec<_> = log
+            .blue
+            .iter()
+            .flat_map(|draw| draw.values.clone())
+            .collect();
+        assert_eq!(logged, words);
+    }
+
+    #[test]
+    fn a_stream_seeks_to_where_it_stood() {
+        let mut stream = Stream::seed_from_u64(9);
+        let mut rng = StdRng::seed_from_u64(9);
+        assert_eq!(stream.next_u64(), rng.next_u64());
+        let mut bytes = [0; 5];
+        stream.fill_bytes(&mut bytes);
+        assert_eq!(stream.position(), 4);
+        let at = stream.position();
+        let next: Vec<u32> = (0..3).map(|_| stream.next_u32()).collect();
+
+        // back to where it stood, and from a fresh stream far behind
+        stream.seek(at);
+        assert_eq!((0..3).map(|_| stream.next_u32()).collect::<Vec<_>>(), next);
+        let mut fresh = Stream::seed_from_u64(9);
+        fresh.seek(at);
+        assert_eq!(fresh.next_u32(), next[0]);
+        assert_eq!(fresh.position(), at + 1);
+    }
+}
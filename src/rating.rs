@@ -0,0 +1,234 @@
+//! Elo ratings for strategies, carried from one run to the next
+//!
+//! Each participant starts at [`INITIAL_RATING`]. After every match both
+//! participants' ratings move towards the result: a win for the higher
+//! score, a draw for equal scores. Beating a stronger opponent gains more
+//! than beating a weaker one, so ratings stay comparable across tournaments
+//! with different rosters. Ratings are kept by participant name, so two
+//! participants playing the same strategy with different parameters, say
+//! two generosities, are rated apart, and a participant keeps its rating
+//! from run to run as long as it keeps its name.
+
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::referee::MatchResult;
+use crate::tournament::TournamentRecord;
+
+/// Every participant's rating before its first match
+pub const INITIAL_RATING: f64 = 1500.0;
+
+/// The most a rating can move in one match
+pub const K_FACTOR: f64 = 32.0;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Rating {
+    pub rating: f64,
+
+    /// Matches rated so far, across every run
+    pub matches: usize,
+}
+
+impl Default for Rating {
+    fn default() -> Rating {
+        Rating {
+            rating: INITIAL_RATING,
+            matches: 0,
+        }
+    }
+}
+
+/// A participant's rating after a tournament, as included in its record
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RatingChange {
+    pub name: String,
+    pub strategy: String,
+    pub rating: f64,
+    pub matches: usize,
+
+    /// How far the tournament moved the rating
+    pub change: f64,
+}
+
+#[derive(Debug)]
+pub enum RatingsError {
+    Io(io::Error),
+    Parse(serde_json::Error),
+}
+
+impl fmt::Display for RatingsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RatingsError::Io(e) => write!(f, "unable to access ratings: {}", e),
+            RatingsError::Parse(e) => write!(f, "unable to parse ratings: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for RatingsError {}
+
+/// Ratings by participant name, stored as a JSON object
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Ratings(BTreeMap<String, Rating>);
+
+impl Ratings {
+    /// Read the ratings at `path`, or start afresh if there is no file yet
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Ratings, RatingsError> {
+        match fs::read_to_string(path) {
+            Ok(text) => serde_json::from_str(&text).map_err(RatingsError::Parse),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Ratings::default()),
+            Err(e) => Err(RatingsError::Io(e)),
+        }
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), RatingsError> {
+        let text = serde_json::to_string_pretty(self).expect("serializable ratings");
+        fs::write(path, text + "\n").map_err(RatingsError::Io)
+    }
+
+    pub fn get(&self, name: &str) -> Rating {
+        self.0.get(name).copied().unwrap_or_default()
+    }
+
+    /// Rate one match between the participants named `blue` and `red`
+    ///
+    /// A participant playing itself keeps its rating.
+    pub fn record_match(&mut self, blue: &str, red: &str, result: &MatchResult) {
+        if blue == red {
+            return;
+        }
+        let (blue_rating, red_rating) = (self.get(blue), self.get(red));
+        let expected = 1.0 / (1.0 + 10f64.powf((red_rating.rating - blue_rating.rating) / 400.0));
+        let actual = match result.blue.score.total_cmp(&result.red.score) {
+            Ordering::Greater => 1.0,
+            Ordering::Equal => 0.5,
+            Ordering::Less => 0.0,
+        };
+        let change = K_FACTOR * (actual - expected);
+        for (name, rating, change) in [(blue, blue_rating, change), (red, red_rating, -change)] {
+            self.0.insert(
+                name.to_owned(),
+                Rating {
+                    rating: rating.rating + change,
+                    matches: rating.matches + 1,
+                },
+            );
+        }
+    }
+
+    /// Rate every match in `record`, in order, and return the new ratings of
+    /// the participants, highest first
+    pub fn record_tournament(&mut self, record: &TournamentRecord) -> Vec<RatingChange> {
+        let strategies: BTreeMap<&str, &str> = record
+            .config
+            .participants
+            .iter()
+            .map(|p| (p.name.as_str(), p.strategy.as_str()))
+            .collect();
+        let before: BTreeMap<&str, Rating> = strategies
+            .keys()
+            .map(|&name| (name, self.get(name)))
+            .collect();
+        for result in &record.matches {
+            // twins aren't participants, and playing itself can't move a rating
+            let (blue, red) = (result.blue.name.as_str(), result.red.name.as_str());
+            if strategies.contains_key(blue) && strategies.contains_key(red) {
+                self.record_match(blue, red, result);
+            }
+        }
+
+        let mut changes: Vec<RatingChange> = before
+            .into_iter()
+            .map(|(name, before)| {
+                let after = self.get(name);
+                RatingChange {
+                    name: name.to_owned(),
+                    strategy: strategies[name].to_owned(),
+                    rating: after.rating,
+                    matches: after.matches,
+                    change: after.rating - before.rating,
+                }
+            })
+            .collect();
+        changes.sort_by(|a, b| {
+            b.rating
+                .total_cmp(&a.rating)
+                .then_with(|| a.name.cmp(&b.name))
+        });
+        changes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{ParticipantConfig, SimConfig};
+
+    #[test]
+    fn a_win_between_equals_moves_half_the_k_factor() {
+        let mut ratings = Ratings::default();
+        let result = MatchResult::played(("a", "D"), ("b", "C"));
+        ratings.record_match("a", "b", &result);
+        assert_eq!(ratings.get("a").rating, INITIAL_RATING + K_FACTOR / 2.0);
+        assert_eq!(ratings.get("b").rating, INITIAL_RATING - K_FACTOR / 2.0);
+        assert_eq!(ratings.get("a").matches, 1);
+
+        // the favourite now expects to win, so beating it again gains less
+        ratings.record_match("a", "b", &result);
+        let expected = 1.0 / (1.0 + 10f64.powf(-K_FACTOR / 400.0));
+        let gain = K_FACTOR * (1.0 - expected);
+        assert!((ratings.get("a").rating - (INITIAL_RATING + K_FACTOR / 2.0 + gain)).abs() < 1e-9);
+        assert!(gain < K_FACTOR / 2.0);
+    }
+
+    #[test]
+    fn a_draw_between_equals_moves_nothing() {
+        let mut ratings = Ratings::default();
+        ratings.record_match("a", "b", &MatchResult::played(("a", "C"), ("b", "C")));
+        assert_eq!(ratings.get("a").rating, INITIAL_RATING);
+        assert_eq!(ratings.get("b").matches, 1);
+    }
+
+    #[test]
+    fn playing_itself_is_not_rated() {
+        let mut ratings = Ratings::default();
+        ratings.record_match("a", "a", &MatchResult::played(("a", "D"), ("a", "C")));
+        assert_eq!(ratings.get("a").rating, INITIAL_RATING);
+        assert_eq!(ratings.get("a").matches, 0);
+    }
+
+    #[test]
+    fn participants_sharing_a_strategy_are_rated_apart() {
+        let record = TournamentRecord {
+            config: SimConfig {
+                participants: vec![
+                    ParticipantConfig::new("stingy", "generous-tit-for-tat"),
+                    ParticipantConfig::new("generous", "generous-tit-for-tat"),
+                ],
+                ..SimConfig::default()
+            },
+            seed: 0,
+            matches: vec![MatchResult::played(("stingy", "DC"), ("generous", "CC"))],
+            aborted: Vec::new(),
+            standings: Vec::new(),
+            disqualified: Vec::new(),
+            ratings: Vec::new(),
+            pairings: Vec::new(),
+            placings: Vec::new(),
+            memories: Vec::new(),
+        };
+        let mut ratings = Ratings::default();
+        let changes = ratings.record_tournament(&record);
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].name, "stingy");
+        assert_eq!(changes[0].strategy, "generous-tit-for-tat");
+        assert_eq!(changes[0].change, K_FACTOR / 2.0);
+        assert_eq!(changes[1].change, -K_FACTOR / 2.0);
+    }
+}
@@ -0,0 +1,433 @@
+//! Recordings of past matches, to play them again
+//!
+//! A [`Recording`] keeps, for every match of a run, both participants, the
+//! [`MatchSeeds`] that every random draw in the match came from, and the
+//! result with its transcript of every decision. Since strategies draw only
+//! on their seeds, replaying a match from its recording makes the same
+//! decisions again, and [`divergence`] points out the first round where it
+//! didn't. That settles disputes over a result, and lets a strategy's
+//! behaviour be stepped through again under a debugger or with logging.
+//!
+//! With `audit_randomness` set, the recording also keeps every random
+//! number each strategy drew, and a replay checks that the same seeds make
+//! the same draws; see [`crate::randomness`]. It keeps where every
+//! generator stood as each round began too, so [`resume`] can take a match
+//! up from any round instead of playing it from the start.
+//!
+//! A replay can't be exact where the match depended on more than its seeds:
+//! a decision timeout, a remote strategy, or reputations shared with the
+//! other matches of its batch, which a replayed match plays without.
+
+use actix::MailboxError;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::config::{ParticipantConfig, SimConfig};
+use crate::hooks;
+use crate::local::{LocalMatch, LocalResult};
+use crate::randomness::{self, Seat};
+use crate::referee::{MatchResult, RoundRecord, SideRecord};
+use crate::registry::{StrategyArgs, StrategyRegistry};
+use crate::schema::{self, SchemaError};
+use crate::tournament::{self, MatchReport, MatchSeeds};
+
+/// One match as it was played
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedMatch {
+    pub blue: ParticipantConfig,
+    pub red: ParticipantConfig,
+    pub seeds: MatchSeeds,
+    pub result: MatchResult,
+}
+
+/// Every finished match of a run, in the order they were played
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Recording {
+    pub config: SimConfig,
+    pub seed: u64,
+    pub matches: Vec<RecordedMatch>,
+}
+
+#[derive(Debug)]
+pub enum RecordingError {
+    Io(io::Error),
+    Parse(SchemaError),
+}
+
+impl fmt::Display for RecordingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RecordingError::Io(e) => write!(f, "unable to access recording: {}", e),
+            RecordingError::Parse(e) => write!(f, "unable to parse recording: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for RecordingError {}
+
+impl Recording {
+    /// Record `reports` from a run of `config` with `seed`; aborted matches
+    /// are left out, having no result to compare with
+    pub fn new(config: &SimConfig, seed: u64, reports: &[MatchReport]) -> Recording {
+        // the entrants of a round robin include any twins
+        let (entrants, _) = tournament::schedule(config);
+        let participant = |name: &str| {
+            entrants
+                .iter()
+                .find(|p| p.name == name)
+                .expect("a match between participants")
+                .clone()
+        };
+        let matches = reports
+            .iter()
+            .filter_map(|report| {
+                let result = report.result.as_ref().ok()?;
+                Some(RecordedMatch {
+                    blue: participant(&report.blue),
+                    red: participant(&report.red),
+                    seeds: report.seeds,
+                    result: result.clone(),
+                })
+            })
+            .collect();
+        Recording {
+            config: config.clone(),
+            seed,
+            matches,
+        }
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Recording, RecordingError> {
+        let text = fs::read_to_string(path).map_err(RecordingError::Io)?;
+        schema::from_str(&text).map_err(RecordingError::Parse)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), RecordingError> {
+        let text = schema::to_string(self);
+        fs::write(path, text + "\n").map_err(RecordingError::Io)
+    }
+}
+
+/// A match played again from its recording
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Replay {
+    pub result: MatchResult,
+
+    /// The first round, from 1, that went differently from the recording
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub diverged_at: Option<usize>,
+
+    /// The first round, from 1, in which a strategy drew other random
+    /// numbers than it did in the recording, if its randomness was audited
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub draws_diverged_at: Option<usize>,
+}
+
+/// The first round, from 1, in which `replayed` didn't go as `recorded` did:
+/// either side chose, executed, perceived, or signalled something else, or
+/// one match ended there and the other didn't
+pub fn divergence(recorded: &MatchResult, replayed: &MatchResult) -> Option<usize> {
+    transcript_divergence(&recorded.transcript, &replayed.transcript)
+}
+
+/// The same as [`divergence`], between two transcripts
+fn transcript_divergence(recorded: &[RoundRecord], replayed: &[RoundRecord]) -> Option<usize> {
+    let same = |a: &SideRecord, b: &SideRecord| {
+        a.chosen == b.chosen
+            && a.executed == b.executed
+            && a.perceived == b.perceived
+            && a.signal == b.signal
+    };
+    recorded
+        .iter()
+        .zip(replayed)
+        .position(|(a, b)| !(same(&a.blue, &b.blue) && same(&a.red, &b.red)))
+        .or_else(|| {
+            if recorded.len() == replayed.len() {
+                None
+            } else {
+                Some(recorded.len().min(replayed.len()))
+            }
+        })
+        .map(|round| round + 1)
+}
+
+/// Play `recorded`, from `recording`, again from its seeds
+///
+/// `registry` must know its strategies. Fails if the replayed match was
+/// aborted.
+pub async fn replay(
+    recording: &Recording,
+    recorded: &RecordedMatch,
+    registry: &StrategyRegistry,
+) -> Result<Replay, MailboxError> {
+    let report = tournament::start_match(
+        &recording.config,
+        registry,
+        &recorded.blue,
+        &recorded.red,
+        recorded.seeds,
+        None,
+        &tournament::Shared::default(),
+    )
+    .await;
+    let result = report.result?;
+    let draws_diverged_at = match (&recorded.result.draws, &result.draws) {
+        (Some(recorded), Some(replayed)) => randomness::draw_divergence(recorded, replayed),
+        _ => None,
+    };
+    Ok(Replay {
+        diverged_at: divergence(&recorded.result, &result),
+        draws_diverged_at,
+        result,
+    })
+}
+
+/// A match taken up partway from its recording
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Resumed {
+    /// The round, from 1, it was taken up from
+    pub from: usize,
+
+    /// The whole match, the rounds before `from` as recorded
+    pub result: LocalResult,
+
+    /// The first round, from 1, that went differently from the recording
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub diverged_at: Option<usize>,
+}
+
+/// Why `recorded` can't be taken up partway, if it can't: it was played
+/// with something a [`LocalMatch`] leaves out, or without the positions of
+/// its generators
+fn resumable(config: &SimConfig, recorded: &RecordedMatch) -> Result<(), String> {
+    let unsupported = [
+        (config.cheap_talk, "cheap talk"),
+        (config.alternating, "alternating moves"),
+        (config.information.blind, "blind play"),
+        (config.information.delay > 0, "delayed feedback"),
+        (
+            config.information.visibility.is_some(),
+            "partial visibility",
+        ),
+        (config.red_payoffs.is_some(), "red payoffs"),
+        (config.budget.is_some(), "a computation budget"),
+        (config.decision_timeout_ms.is_some(), "a decision timeout"),
+        (config.reputation.is_some(), "reputations"),
+        (config.remember_opponents, "memories"),
+        (
+            recorded.blue.swap.is_some() || recorded.red.swap.is_some(),
+            "a strategy swap",
+        ),
+        (!recorded.result.faults.is_empty(), "a forfeit"),
+        (
+            recorded.result.stopped_by.as_deref() == Some(hooks::STOPPED_BY),
+            "a hook ending it",
+        ),
+    ];
+    if let Some((_, what)) = unsupported.iter().find(|(played, _)| *played) {
+        return Err(format!("the match was played with {}", what));
+    }
+    match &recorded.result.draws {
+        Some(draws) if !draws.positions.is_empty() => Ok(()),
+        _ => Err(
+            "the recording has no positions of its random number generators; \
+                  record it with audit_randomness"
+                .to_owned(),
+        ),
+    }
+}
+
+/// Play `recorded`, from `recording`, on from round `from`, counting from
+/// 1, after taking it up where the recording says it stood
+///
+/// Both strategies are built from their seeds, walked through the rounds
+/// before as recorded, and put back where their generators were, as is the
+/// match's own; see [`LocalMatch::resumed`]. The rest is played locally,
+/// so the match must have been played with nothing a local match leaves
+/// out, and recorded with `audit_randomness`.
+pub fn resume(
+    recording: &Recording,
+    recorded: &RecordedMatch,
+    registry: &StrategyRegistry,
+    from: usize,
+) -> Result<Resumed, String> {
+    let config = &recording.config;
+    resumable(config, recorded)?;
+    let rounds = recorded.result.transcript.len();
+    if from == 0 || from > rounds {
+        return Err(format!("the match has {} rounds, numbered from 1", rounds));
+    }
+    let positions = recorded
+        .result
+        .draws
+        .as_ref()
+        .and_then(|draws| draws.at(from - 1))
+        .ok_or_else(|| format!("the recording has no positions for round {}", from))?;
+
+    let build = |participant: &ParticipantConfig, payoffs, seed, seat: &Seat| {
+        let args = StrategyArgs {
+            parameters: &participant.parameters,
+            payoffs: &payoffs,
+            seed,
+        };
+        randomness::seated(seat, || registry.create(&participant.strategy, &args))
+            .map_err(|e| format!("{}: {}", participant.name, e))
+    };
+    let (blue_seat, red_seat) = (Seat::new(), Seat::new());
+    let blue = build(
+        &recorded.blue,
+        config.payoffs_told(&recorded.blue),
+        recorded.seeds.blue,
+        &blue_seat,
+    )?;
+    let red = build(
+        &recorded.red,
+        config.red_payoffs_told(&recorded.red),
+        recorded.seeds.red,
+        &red_seat,
+    )?;
+
+    let mut game = LocalMatch::new(
+        (&recorded.blue.name, blue),
+        (&recorded.red.name, red),
+        config.payoffs(),
+        config.iterations,
+    )
+    .with_seed(recorded.seeds.referee)
+    .with_noise(config.noise)
+    .with_misperception(config.misperception)
+    .with_discount(config.discount.unwrap_or(1.0));
+    if let Some(termination) = config.termination.build() {
+        game = game.with_termination(termination);
+    }
+    let played = &recorded.result.transcript[..from - 1];
+    let result = game
+        .resumed(played, positions, (&blue_seat, &red_seat))
+        .play();
+    Ok(Resumed {
+        from,
+        diverged_at: transcript_divergence(&recorded.result.transcript, &result.transcript),
+        result,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::randomness::draw_divergence;
+
+    #[actix_rt::test]
+    async fn a_replay_finds_a_changed_draw() {
+        let registry = StrategyRegistry::new();
+        let config = SimConfig {
+            iterations: 20,
+            audit_randomness: true,
+            participants: vec![
+                ParticipantConfig::new("random", "random"),
+                ParticipantConfig::new("gtft", "generous-tit-for-tat"),
+            ],
+            ..SimConfig::default()
+        };
+        let seeds = MatchSeeds {
+            blue: 1,
+            red: 2,
+            referee: 3,
+        };
+        let (blue, red) = (&config.participants[0], &config.participants[1]);
+        let report = tournament::start_match(
+            &config,
+            &registry,
+            blue,
+            red,
+            seeds,
+            None,
+            &tournament::Shared::default(),
+        )
+        .await;
+        let mut recording = Recording::new(&config, 0, &[report]);
+
+        let replayed = replay(&recording, &recording.matches[0], &registry)
+            .await
+            .unwrap();
+        assert_eq!(replayed.diverged_at, None);
+        assert_eq!(replayed.draws_diverged_at, None);
+        let log = replayed.result.draws.clone().unwrap();
+        assert_eq!(log.blue.len(), 20);
+
+        // tamper with what the random player drew in its seventh round
+        let draws = recording.matches[0].result.draws.as_mut().unwrap();
+        draws.blue[6].values[0] ^= 1;
+        let replayed = replay(&recording, &recording.matches[0], &registry)
+            .await
+            .unwrap();
+        assert_eq!(replayed.draws_diverged_at, Some(7));
+        assert_eq!(
+            draw_divergence(&recording.matches[0].result.draws.clone().unwrap(), &log),
+            Some(7)
+        );
+    }
+
+    #[actix_rt::test]
+    async fn a_match_is_taken_up_from_any_round() {
+        let registry = StrategyRegistry::new();
+        let mut config = SimConfig {
+            iterations: 40,
+            noise: 0.1,
+            misperception: 0.05,
+            audit_randomness: true,
+            participants: vec![
+                ParticipantConfig::new("random", "random"),
+                ParticipantConfig::new("gtft", "generous-tit-for-tat"),
+            ],
+            ..SimConfig::default()
+        };
+        let seeds = MatchSeeds {
+            blue: 4,
+            red: 5,
+            referee: 6,
+        };
+        let (blue, red) = (&config.participants[0], &config.participants[1]);
+        let report = tournament::start_match(
+            &config,
+            &registry,
+            blue,
+            red,
+            seeds,
+            None,
+            &tournament::Shared::default(),
+        )
+        .await;
+        let recording = Recording::new(&config, 0, &[report]);
+        let recorded = &recording.matches[0];
+        assert_eq!(recorded.result.draws.as_ref().unwrap().positions.len(), 40);
+
+        for from in [1, 17, 40] {
+            let resumed = resume(&recording, recorded, &registry, from).unwrap();
+            assert_eq!(resumed.diverged_at, None);
+            assert_eq!(resumed.result.rounds, 40);
+            assert_eq!(resumed.result.blue.score, recorded.result.blue.score);
+            assert_eq!(resumed.result.red.flips, recorded.result.red.flips);
+        }
+        assert!(resume(&recording, recorded, &registry, 41).is_err());
+
+        // without the audit there is nothing to take it up from
+        config.audit_randomness = false;
+        let (blue, red) = (&config.participants[0], &config.participants[1]);
+        let report = tournament::start_match(
+            &config,
+            &registry,
+            blue,
+            red,
+            seeds,
+            None,
+            &tournament::Shared::default(),
+        )
+        .await;
+        let recording = Recording::new(&config, 0, &[report]);
+        assert!(resume(&recording, &recording.matches[0], &registry, 17).is_err());
+    }
+}
@@ -0,0 +1,1434 @@
+use actix::prelude::*;
+use futures::channel::oneshot;
+use futures::future::join;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, VecDeque};
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{debug, debug_span, info_span, warn, Instrument, Span};
+
+use crate::clock::{Clock, NextTick};
+use crate::control::{self, Control};
+use crate::events::GameEvent;
+use crate::game::{Action, Payoff, PayoffMatrix, PlayerOutcome, RoundOutcome, Score};
+use crate::hooks::{self, Flow, Hooks};
+use crate::interrupt;
+use crate::prisoner::{
+    self, BudgetAction, EndMatch, FaultKind, MatchId, PlayBatch, Prisoner, Signal, StartMatch,
+    StrategyFault,
+};
+use crate::randomness::{DrawLog, RefereeAt, Stream};
+use crate::reputation::{Reputation, Reputations};
+use crate::scheduler::{self, Scheduler, Slot};
+use crate::stats::{MatchStats, Timings};
+use crate::strategy::{PlayerInfo, Round, Tag, Turn};
+use crate::termination::{FixedLength, MatchProgress, Termination};
+pub use crate::transcript::{RoundRecord, SideRecord};
+
+/// Start the match; resolves with the result once the last round is scored
+pub struct Play;
+
+impl Message for Play {
+    type Result = Result<MatchResult, MailboxError>;
+}
+
+/// Sent by the referee to itself for each round
+struct PlayRound;
+
+impl Message for PlayRound {
+    type Result = ();
+}
+
+/// How one prisoner fared in a match
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlayerSummary {
+    pub name: String,
+    pub score: Score,
+
+    /// Executed cooperations, after any execution noise
+    pub cooperations: usize,
+
+    /// How many times each payoff class was received
+    pub payoff_counts: BTreeMap<Payoff, usize>,
+
+    /// Rounds in which noise flipped the chosen action before scoring
+    pub flips: usize,
+
+    /// Rounds in which this prisoner was told the wrong opponent action
+    pub misperceptions: usize,
+
+    /// Rounds in which this prisoner missed the decision timeout
+    #[serde(default)]
+    pub timeouts: usize,
+
+    /// Rounds this prisoner forfeited for having used up its computation
+    /// budget
+    #[serde(default)]
+    pub over_budget: usize,
+
+    /// How long the prisoner took to answer each message asking for a
+    /// decision or a signal, timed by the referee, so mailbox delays count
+    #[serde(default)]
+    pub latency: Timings,
+
+    /// Its public reputation as the match ended, if reputations were kept
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reputation: Option<Reputation>,
+
+    /// What its strategy remembered of the opponent as the match ended, if
+    /// the config keeps memories; see [`crate::memory`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory: Option<Value>,
+}
+
+impl PlayerSummary {
+    /// Points per round, so matches of different lengths are comparable
+    pub fn average_score(&self, rounds: usize) -> f64 {
+        ratio(self.score, rounds)
+    }
+
+    /// What the prisoner's payoffs would have totalled under `payoffs`,
+    /// undiscounted
+    pub fn score_under(&self, payoffs: &PayoffMatrix) -> Score {
+        self.payoff_counts
+            .iter()
+            .map(|(&payoff, &count)| payoffs.value(payoff) * count as f64)
+            .sum()
+    }
+
+    pub fn cooperation_rate(&self, rounds: usize) -> f64 {
+        ratio(self.cooperations as f64, rounds)
+    }
+
+    pub fn payoff_count(&self, payoff: Payoff) -> usize {
+        *self.payoff_counts.get(&payoff).unwrap_or(&0)
+    }
+}
+
+fn ratio(total: f64, rounds: usize) -> f64 {
+    if rounds == 0 {
+        0.0
+    } else {
+        total / rounds as f64
+    }
+}
+
+/// The outcome of a match, reported by the referee when it finishes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MatchResult {
+    pub rounds: usize,
+    pub blue: PlayerSummary,
+    pub red: PlayerSummary,
+
+    /// Prisoners that panicked, went silent, or went over their budget, and
+    /// forfeited the match
+    ///
+    /// If only one side went silent, the other is awarded one temptation
+    /// payoff on top of its score.
+    pub faults: Vec<StrategyFault>,
+
+    /// Every round played, in order
+    pub transcript: Vec<RoundRecord>,
+
+    /// Cooperation metrics computed from the transcript
+    #[serde(default)]
+    pub stats: MatchStats,
+
+    /// What ended the match before its round limit, e.g. `converged`;
+    /// `interrupted` if Ctrl-C did, or `skipped` if a [`Controller`] did
+    ///
+    /// [`Controller`]: crate::control::Controller
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stopped_by: Option<String>,
+
+    /// How long each round took, from the referee taking it up to scoring
+    /// it
+    #[serde(default)]
+    pub round_times: Timings,
+
+    /// What each side's strategy drew, if the config audits randomness
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub draws: Option<DrawLog>,
+}
+
+impl Message for MatchResult {
+    type Result = ();
+}
+
+#[cfg(test)]
+impl MatchResult {
+    /// A match between the prisoners named `blue` and `red` in which they
+    /// played the moves spelled out with `C`, `D`, and `A`, scored by the
+    /// default payoffs
+    pub(crate) fn played(blue: (&str, &str), red: (&str, &str)) -> MatchResult {
+        let action = |letter| match letter {
+            'C' => Action::COOPERATE,
+            'D' => Action::DEFECT,
+            _ => Action::ABSTAIN,
+        };
+        let payoffs = PayoffMatrix::default();
+        let side = |own: Action, opponent: Action, outcome| SideRecord {
+            chosen: own,
+            executed: own,
+            perceived: opponent,
+            hidden: false,
+            outcome,
+            timed_out: false,
+            over_budget: false,
+            signal: None,
+        };
+        let transcript: Vec<RoundRecord> = blue
+            .1
+            .chars()
+            .map(action)
+            .zip(red.1.chars().map(action))
+            .enumerate()
+            .map(|(round, (b, r))| {
+                let outcome = payoffs.score(r, b);
+                RoundRecord {
+                    round,
+                    blue: side(b, r, outcome.blue),
+                    red: side(r, b, outcome.red),
+                }
+            })
+            .collect();
+        let summary = |name: &str, own: fn(&RoundRecord) -> SideRecord| {
+            let mut payoff_counts = BTreeMap::new();
+            for record in &transcript {
+                *payoff_counts.entry(own(record).outcome.payoff).or_insert(0) += 1;
+            }
+            PlayerSummary {
+                name: name.to_owned(),
+                score: transcript.iter().map(|r| own(r).outcome.amount).sum(),
+                cooperations: transcript
+                    .iter()
+                    .filter(|r| own(r).executed == Action::COOPERATE)
+                    .count(),
+                payoff_counts,
+                flips: 0,
+                misperceptions: 0,
+                timeouts: 0,
+                over_budget: 0,
+                latency: Timings::default(),
+                reputation: None,
+                memory: None,
+            }
+        };
+        MatchResult {
+            rounds: transcript.len(),
+            blue: summary(blue.0, |r| r.blue),
+            red: summary(red.0, |r| r.red),
+            faults: Vec::new(),
+            stats: MatchStats::new(&transcript),
+            transcript,
+            stopped_by: None,
+            round_times: Timings::default(),
+            draws: None,
+        }
+    }
+}
+
+/// Sent by the referee to its reporter when a match ends
+pub struct Report(pub MatchResult);
+
+impl Message for Report {
+    type Result = ();
+}
+
+/// One side of the table: a prisoner, what it actually did, and what it
+/// was told its opponent did
+struct Seat {
+    name: String,
+    addr: Addr<Prisoner>,
+    tag: Option<Tag>,
+    score: Score,
+    flips: usize,
+
+    /// Executed actions, after any execution noise
+    actions: Vec<Action>,
+
+    /// The opponent's actions as reported to this prisoner
+    perceived: Vec<Action>,
+    misperceptions: usize,
+    timeouts: usize,
+    over_budget: usize,
+    payoff_counts: BTreeMap<Payoff, usize>,
+    latency: Timings,
+
+    /// Decisions the prisoner has made ahead of time
+    pending: VecDeque<Action>,
+
+    /// Rounds the prisoner hasn't been told about yet
+    unreported: Vec<Round>,
+
+    /// Rounds held back from the prisoner until `delay` more are played
+    withheld: VecDeque<Round>,
+    delay: usize,
+
+    /// Whether the prisoner is told what its rounds paid
+    blind: bool,
+
+    /// What the prisoner signalled for the round about to be played
+    signal: Option<Action>,
+}
+
+impl Seat {
+    fn new(name: &str, addr: Addr<Prisoner>) -> Seat {
+        Seat {
+            name: name.to_owned(),
+            addr,
+            tag: None,
+            score: 0.0,
+            flips: 0,
+            actions: Vec::new(),
+            perceived: Vec::new(),
+            misperceptions: 0,
+            timeouts: 0,
+            over_budget: 0,
+            payoff_counts: BTreeMap::new(),
+            latency: Timings::default(),
+            pending: VecDeque::new(),
+            unreported: Vec::new(),
+            withheld: VecDeque::new(),
+            delay: 0,
+            blind: false,
+            signal: None,
+        }
+    }
+
+    /// Send `batch` with the rounds the prisoner hasn't been told about,
+    /// unless some of its decisions are still pending
+    fn next_batch(&mut self, batch: PlayBatch) -> Option<PlayBatch> {
+        if !self.pending.is_empty() {
+            return None;
+        }
+        Some(PlayBatch {
+            history: std::mem::take(&mut self.unreported),
+            withheld: self.withheld.len(),
+            ..batch
+        })
+    }
+
+    /// Ask for the prisoner's signal, telling it what it hasn't heard yet
+    fn talk(&mut self, match_id: MatchId, own: PlayerInfo, opponent: PlayerInfo) -> Signal {
+        Signal {
+            match_id,
+            history: std::mem::take(&mut self.unreported),
+            withheld: self.withheld.len(),
+            own,
+            opponent,
+        }
+    }
+
+    /// This round's decision, or `fallback` if the prisoner forfeited it
+    ///
+    /// Also says why the prisoner forfeited, if it did.
+    fn take_decision(
+        &mut self,
+        answer: Result<Vec<Action>, Forfeit>,
+        fallback: Action,
+    ) -> (Action, Option<Forfeit>) {
+        match answer {
+            Ok(actions) => {
+                self.pending.extend(actions);
+                let action = self.pending.pop_front().expect("a decision for this round");
+                (action, None)
+            }
+            Err(forfeit) => {
+                match forfeit {
+                    Forfeit::TimedOut => self.timeouts += 1,
+                    Forfeit::OverBudget => self.over_budget += 1,
+                }
+                (fallback, Some(forfeit))
+            }
+        }
+    }
+
+    /// Score `outcome`, already weighted by any discount, and tell the
+    /// prisoner of the round, keeping the opponent's action from it if
+    /// `hidden`
+    fn record(&mut self, action: Action, outcome: PlayerOutcome, hidden: bool) {
+        self.actions.push(action);
+        self.score += outcome.amount;
+        *self.payoff_counts.entry(outcome.payoff).or_insert(0) += 1;
+        let told = if self.blind {
+            PlayerOutcome {
+                amount: 0.0,
+                ..outcome
+            }
+        } else {
+            outcome
+        };
+        let opponent = if hidden {
+            action
+        } else {
+            *self.perceived.last().expect("perceived before recording")
+        };
+        self.withheld.push_back(Round {
+            own: action,
+            opponent,
+            outcome: told,
+            hidden,
+        });
+        while self.withheld.len() > self.delay {
+            let round = self.withheld.pop_front().expect("a withheld round");
+            self.unreported.push(round);
+        }
+    }
+
+    fn summary(&self) -> PlayerSummary {
+        PlayerSummary {
+            name: self.name.clone(),
+            score: self.score,
+            cooperations: self
+                .actions
+                .iter()
+                .filter(|&&action| action == Action::COOPERATE)
+                .count(),
+            payoff_counts: self.payoff_counts.clone(),
+            flips: self.flips,
+            misperceptions: self.misperceptions,
+            timeouts: self.timeouts,
+            over_budget: self.over_budget,
+            latency: self.latency.clone(),
+            reputation: None,
+            memory: None,
+        }
+    }
+}
+
+/// Decisions asked of a history-free prisoner in one message
+pub const DEFAULT_BATCH: usize = 64;
+
+/// Why a prisoner's decision for a round was played for it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Forfeit {
+    TimedOut,
+    OverBudget,
+}
+
+/// Ask for more decisions if the prisoner has none queued
+///
+/// A [`Forfeit`] means the prisoner ran out of time, or out of budget when
+/// that forfeits rounds rather than the match. A prisoner that never
+/// answers faults as unresponsive.
+async fn decide(
+    name: String,
+    addr: Addr<Prisoner>,
+    batch: Option<PlayBatch>,
+    retries: usize,
+    timeout: Option<Duration>,
+    on_exceed: BudgetAction,
+) -> Result<Result<Vec<Action>, Forfeit>, StrategyFault> {
+    let batch = match batch {
+        Some(batch) => batch,
+        None => return Ok(Ok(Vec::new())),
+    };
+    match prisoner::ask(&addr, batch, retries, timeout).await {
+        Ok(Err(fault))
+            if fault.kind == FaultKind::OverBudget && on_exceed == BudgetAction::ForfeitRound =>
+        {
+            debug!(%fault, "forfeits the round");
+            Ok(Err(Forfeit::OverBudget))
+        }
+        Ok(answer) => answer.map(Ok),
+        Err(MailboxError::Timeout) => {
+            warn!(player = %name, "ran out of time to decide");
+            Ok(Err(Forfeit::TimedOut))
+        }
+        Err(e) => Err(StrategyFault::unresponsive(&name, e)),
+    }
+}
+
+/// Wait for `answer`, noting how long it took
+async fn timed<F: Future>(answer: F) -> (F::Output, Duration) {
+    let asked = Instant::now();
+    let answer = answer.await;
+    (answer, asked.elapsed())
+}
+
+async fn signal(
+    name: String,
+    addr: Addr<Prisoner>,
+    msg: Signal,
+    retries: usize,
+    timeout: Option<Duration>,
+) -> Result<Option<Action>, StrategyFault> {
+    match prisoner::ask(&addr, msg, retries, timeout).await {
+        Ok(answer) => answer,
+        Err(MailboxError::Timeout) => {
+            warn!(player = %name, "ran out of time to signal");
+            Ok(None)
+        }
+        Err(e) => Err(StrategyFault::unresponsive(&name, e)),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Side {
+    Blue,
+    Red,
+}
+
+/// Owns the game loop for one match between blue and red
+pub struct Referee {
+    match_id: MatchId,
+    blue: Seat,
+    red: Seat,
+    payoffs: PayoffMatrix,
+
+    /// What red is paid, if not `payoffs`
+    red_payoffs: Option<PayoffMatrix>,
+
+    terminations: Vec<Box<dyn Termination>>,
+
+    /// Called after every round and as the match ends, if installed
+    hooks: Option<Arc<dyn Hooks>>,
+    iterations: usize,
+    batch: usize,
+    retries: usize,
+    decision_timeout: Option<Duration>,
+    timeout_action: Action,
+    on_exceed: BudgetAction,
+
+    /// How long to wait between rounds, for watching a match play out
+    delay: Option<Duration>,
+    noise: f64,
+    misperception: f64,
+
+    /// The chance each prisoner is shown its opponent's action
+    visibility: f64,
+    discount: f64,
+    rng: Stream,
+
+    /// Where to say how far along `rng` is as each round begins, if the
+    /// match's randomness is audited
+    randomness: Option<Recipient<RefereeAt>>,
+    sequence: usize,
+    faults: Vec<StrategyFault>,
+    transcript: Vec<RoundRecord>,
+    reporter: Option<Recipient<Report>>,
+    events: Option<Recipient<GameEvent>>,
+    reputations: Option<Reputations>,
+    cheap_talk: bool,
+
+    /// Whether the prisoners have signalled for the round about to be played
+    talked: bool,
+    alternating: bool,
+
+    /// The first mover's decision for the round about to be played, in an
+    /// alternating game, and why it was forfeited if it was
+    opening: Option<(Action, Option<Forfeit>)>,
+
+    /// What ended the match, if not the round limit
+    stopped_by: Option<&'static str>,
+
+    /// What steers the match from outside, see [`Referee::with_control`]
+    control: Control,
+
+    /// [`Control::skips`] when the match started
+    skips: usize,
+
+    /// When the round about to be played was taken up
+    round_started: Option<Instant>,
+    round_times: Timings,
+
+    /// Who gives the match its turns at playing rounds, and whether the
+    /// match is live, see [`Referee::with_scheduler`]
+    scheduler: Option<(Addr<Scheduler>, bool)>,
+
+    /// The turn at the round being played, held until it has been scored
+    slot: Option<Slot>,
+
+    /// What the match plays its rounds to, see [`Referee::with_clock`], and
+    /// whether it has ticked for the round about to be played
+    clock: Option<Addr<Clock>>,
+    ticked: bool,
+
+    /// Tags the match's log lines
+    span: Span,
+    result_tx: Option<oneshot::Sender<MatchResult>>,
+}
+
+impl Referee {
+    pub fn new(
+        blue: (&str, Addr<Prisoner>),
+        red: (&str, Addr<Prisoner>),
+        payoffs: PayoffMatrix,
+        iterations: usize,
+    ) -> Referee {
+        Referee {
+            match_id: 0,
+            blue: Seat::new(blue.0, blue.1),
+            red: Seat::new(red.0, red.1),
+            payoffs,
+            red_payoffs: None,
+            terminations: vec![Box::new(FixedLength(iterations))],
+            hooks: None,
+            iterations,
+            batch: DEFAULT_BATCH,
+            retries: 0,
+            decision_timeout: None,
+            delay: None,
+            timeout_action: Action::DEFECT,
+            on_exceed: BudgetAction::ForfeitMatch,
+            noise: 0.0,
+            misperception: 0.0,
+            visibility: 1.0,
+            discount: 1.0,
+            rng: Stream::seed_from_u64(0),
+            randomness: None,
+            sequence: 0,
+            faults: Vec::new(),
+            transcript: Vec::new(),
+            reporter: None,
+            events: None,
+            reputations: None,
+            cheap_talk: false,
+            talked: false,
+            alternating: false,
+            opening: None,
+            stopped_by: None,
+            control: Control::default(),
+            skips: 0,
+            round_started: None,
+            round_times: Timings::default(),
+            scheduler: None,
+            slot: None,
+            clock: None,
+            ticked: false,
+            span: info_span!("match", blue = %blue.0, red = %red.0),
+            result_tx: None,
+        }
+    }
+
+    /// Tell the prisoners which match they are playing, so prisoners in
+    /// several matches at once keep them apart
+    pub fn with_match_id(mut self, match_id: MatchId) -> Referee {
+        self.match_id = match_id;
+        self
+    }
+
+    /// Seed the referee's random number generator, used for noise
+    pub fn with_seed(mut self, seed: u64) -> Referee {
+        self.rng = Stream::seed_from_u64(seed);
+        self
+    }
+
+    /// Tell `provider` where the referee's generator stands as each round
+    /// begins, so the match can be taken up from there
+    pub fn with_randomness(mut self, provider: Recipient<RefereeAt>) -> Referee {
+        self.randomness = Some(provider);
+        self
+    }
+
+    /// Flip each chosen action with probability `noise` before it is scored
+    ///
+    /// This is the "trembling hand": the strategy meant one thing, but the
+    /// other was executed.
+    pub fn with_noise(mut self, noise: f64) -> Referee {
+        self.noise = noise;
+        self
+    }
+
+    /// Weight round `t`'s payoffs by `discount` to the power `t`, counting
+    /// from 0, so later rounds are worth less
+    ///
+    /// Scores, and everything built on them, are then discounted: the
+    /// amounts prisoners are told, match statistics, and the scores that
+    /// score-based termination rules see.
+    pub fn with_discount(mut self, discount: f64) -> Referee {
+        self.discount = discount;
+        self
+    }
+
+    /// Misreport the opponent's action to each prisoner with probability
+    /// `misperception`
+    ///
+    /// Scoring always uses what actually happened; only the report sent in
+    /// the next `Interrogate` is affected, independently for each side.
+    pub fn with_misperception(mut self, misperception: f64) -> Referee {
+        self.misperception = misperception;
+        self
+    }
+
+    /// Pay red by `payoffs` rather than blue's, for an asymmetric game
+    pub fn with_red_payoffs(mut self, payoffs: PayoffMatrix) -> Referee {
+        self.red_payoffs = Some(payoffs);
+        self
+    }
+
+    /// Show each prisoner its opponent's action only with probability
+    /// `visibility`, independently for each side and round
+    ///
+    /// A hidden action is reported as unknown: the round's
+    /// [`observation`](crate::strategy::Round::observation) is `None`.
+    pub fn with_visibility(mut self, visibility: f64) -> Referee {
+        self.visibility = visibility;
+        self
+    }
+
+    /// Also end the match as soon as `termination` is met
+    ///
+    /// `iterations` still caps the length of the match.
+    pub fn with_termination(mut self, termination: Box<dyn Termination>) -> Referee {
+        self.terminations.push(termination);
+        self
+    }
+
+    /// Ask history-free prisoners for up to `batch` decisions per message
+    ///
+    /// Other prisoners are still asked once per round. A batch of 1 turns
+    /// batching off.
+    pub fn with_batch(mut self, batch: usize) -> Referee {
+        self.batch = batch.max(1);
+        self
+    }
+
+    /// Resend an unanswered message up to `retries` times, backing off each
+    /// time, before the silent prisoner forfeits
+    ///
+    /// A forfeit by silence awards the opponent one temptation payoff.
+    pub fn with_retries(mut self, retries: usize) -> Referee {
+        self.retries = retries;
+        self
+    }
+
+    /// Play `action` for a prisoner that takes longer than `timeout` to decide
+    ///
+    /// The late decision is thrown away and the round is flagged in the
+    /// transcript. Only strategies that yield while they think, such as
+    /// remote ones, can be timed out; a strategy that blocks its arbiter
+    /// blocks the referee too when they share a thread.
+    pub fn with_decision_timeout(mut self, timeout: Duration, action: Action) -> Referee {
+        self.decision_timeout = Some(timeout);
+        self.timeout_action = action;
+        self
+    }
+
+    /// Play the timeout action for a prisoner over its computation budget,
+    /// if `on_exceed` forfeits rounds, instead of ending the match
+    ///
+    /// The budget itself is kept by the prisoner; see
+    /// [`Prisoner::with_budget`].
+    pub fn with_budget(mut self, on_exceed: BudgetAction) -> Referee {
+        self.on_exceed = on_exceed;
+        self
+    }
+
+    /// Wait `delay` after each round before playing the next, so the match
+    /// can be followed as it goes
+    ///
+    /// The wait is scheduled on the referee's context, so the arbiter goes
+    /// on serving other matches, and events are published as each round is
+    /// played.
+    pub fn with_delay(mut self, delay: Duration) -> Referee {
+        self.delay = Some(delay);
+        self
+    }
+
+    /// Send a `Report` to `reporter` when the match ends
+    pub fn with_reporter(mut self, reporter: Recipient<Report>) -> Referee {
+        self.reporter = Some(reporter);
+        self
+    }
+
+    /// Send a `GameEvent` to `events` as the match starts, after every
+    /// round, and when it ends
+    pub fn with_events(mut self, events: Recipient<GameEvent>) -> Referee {
+        self.events = Some(events);
+        self
+    }
+
+    /// Call `hooks` after every round and as the match ends; see
+    /// [`crate::hooks`]
+    pub fn with_hooks(mut self, hooks: Arc<dyn Hooks>) -> Referee {
+        self.hooks = Some(hooks);
+        self
+    }
+
+    /// Pause, skip, and override noise as `control` says, as a
+    /// [`Controller`] sets it
+    ///
+    /// [`Controller`]: crate::control::Controller
+    pub fn with_control(mut self, control: Control) -> Referee {
+        self.control = control;
+        self
+    }
+
+    /// Show each prisoner its opponent's reputation before deciding, and
+    /// update both reputations in `reputations` after every round
+    pub fn with_reputations(mut self, reputations: Reputations) -> Referee {
+        self.reputations = Some(reputations);
+        self
+    }
+
+    /// Before every round, ask both prisoners for a signal and pass each on
+    /// to the other before either decides
+    ///
+    /// Every prisoner is then asked once per round, as if batching were off.
+    pub fn with_cheap_talk(mut self) -> Referee {
+        self.cheap_talk = true;
+        self
+    }
+
+    /// Tell the prisoners nothing of what their rounds paid, only the
+    /// actions played: every amount in their histories is 0
+    ///
+    /// Their scores are kept as usual; only what they see is masked.
+    pub fn with_blindness(mut self) -> Referee {
+        self.blue.blind = true;
+        self.red.blind = true;
+        self
+    }
+
+    /// Tell the prisoners of each round only once `rounds` more have been
+    /// played, so that what round `t` did is first known in deciding round
+    /// `t + rounds + 1`
+    ///
+    /// What is still held back when the match ends is told with its end.
+    pub fn with_feedback_delay(mut self, rounds: usize) -> Referee {
+        self.blue.delay = rounds;
+        self.red.delay = rounds;
+        self
+    }
+
+    /// Have the prisoners move in turn, blue first in even rounds and red
+    /// in odd ones, the second mover seeing the first's choice
+    ///
+    /// Every prisoner is then asked once per round, as if batching were off.
+    /// Noise and misperception apply once both have moved, as usual.
+    pub fn with_alternating(mut self) -> Referee {
+        self.alternating = true;
+        self
+    }
+
+    /// Give the prisoners tags to show each other
+    pub fn with_tags(mut self, blue: Option<Tag>, red: Option<Tag>) -> Referee {
+        self.blue.tag = blue;
+        self.red.tag = red;
+        self
+    }
+
+    /// Wait for a turn from `scheduler` before every round, unless the
+    /// match is `live`; see [`crate::scheduler`]
+    pub fn with_scheduler(mut self, scheduler: Addr<Scheduler>, live: bool) -> Referee {
+        self.scheduler = Some((scheduler, live));
+        self
+    }
+
+    /// Wait for the next tick of `clock` before every round; see
+    /// [`crate::clock`]
+    pub fn with_clock(mut self, clock: Addr<Clock>) -> Referee {
+        self.clock = Some(clock);
+        self
+    }
+
+    /// Log within `span` instead of a span of the referee's own, e.g. one
+    /// shared with the prisoners
+    pub fn with_span(mut self, span: Span) -> Referee {
+        self.span = span;
+        self
+    }
+
+    fn publish(&self, event: GameEvent) {
+        if let Some(events) = &self.events {
+            let _ = events.do_send(event);
+        }
+    }
+
+    /// Randomly flip `action` with the given probability
+    fn perturb(&mut self, action: Action, probability: f64) -> (Action, bool) {
+        if probability > 0.0 && self.rng.gen::<f64>() < probability {
+            (action.flipped(), true)
+        } else {
+            (action, false)
+        }
+    }
+
+    /// What red is paid: its own payoffs in an asymmetric game, or else the
+    /// same as blue
+    fn red_payoffs(&self) -> PayoffMatrix {
+        self.red_payoffs.unwrap_or(self.payoffs)
+    }
+
+    /// Score a round, each side by its own payoffs
+    fn score(&self, red: Action, blue: Action) -> RoundOutcome {
+        let outcome = self.payoffs.score(red, blue);
+        let red = match outcome.red.payoff {
+            Payoff::TABLE => self.red_payoffs().score(red, blue).red,
+            payoff => self.red_payoffs().outcome(payoff),
+        };
+        RoundOutcome { red, ..outcome }
+    }
+
+    /// Whether to hide the opponent's action from one side this round
+    fn hide(&mut self) -> bool {
+        self.visibility < 1.0 && self.rng.gen::<f64>() >= self.visibility
+    }
+
+    /// What each prisoner can see of the other going into this round
+    fn players(&self) -> (PlayerInfo, PlayerInfo) {
+        let info = |seat: &Seat| PlayerInfo {
+            tag: seat.tag,
+            reputation: self.reputations.as_ref().map(|r| r.get(&seat.name)),
+        };
+        (info(&self.blue), info(&self.red))
+    }
+
+    fn seat(&mut self, side: Side) -> &mut Seat {
+        match side {
+            Side::Blue => &mut self.blue,
+            Side::Red => &mut self.red,
+        }
+    }
+
+    /// Who moves first this round in an alternating game
+    fn first_mover(&self) -> Side {
+        if self.sequence.is_multiple_of(2) {
+            Side::Blue
+        } else {
+            Side::Red
+        }
+    }
+
+    /// Ask `side` for up to `rounds` more decisions, unless some are still
+    /// pending
+    fn next_batch(&mut self, side: Side, rounds: usize, turn: Option<Turn>) -> Option<PlayBatch> {
+        let (blue, red) = self.players();
+        let (own, opponent, opponent_signal) = match side {
+            Side::Blue => (blue, red, self.red.signal),
+            Side::Red => (red, blue, self.blue.signal),
+        };
+        let batch = PlayBatch {
+            match_id: self.match_id,
+            sequence: self.sequence,
+            rounds,
+            history: Vec::new(),
+            withheld: 0,
+            own,
+            opponent,
+            opponent_signal,
+            turn,
+        };
+        self.seat(side).next_batch(batch)
+    }
+
+    /// End the match because one or both prisoners failed to answer
+    fn forfeit(
+        &mut self,
+        blue_fault: Option<StrategyFault>,
+        red_fault: Option<StrategyFault>,
+        ctx: &mut Context<Self>,
+    ) {
+        let silent = |fault: &Option<StrategyFault>| matches!(fault, Some(f) if f.kind == FaultKind::Unresponsive);
+        // the one who showed up wins by default
+        match (silent(&red_fault), silent(&blue_fault)) {
+            (true, false) => self.blue.score += self.payoffs.temptation(),
+            (false, true) => self.red.score += self.red_payoffs().temptation(),
+            _ => (),
+        }
+        for fault in red_fault.into_iter().chain(blue_fault) {
+            warn!(%fault, "forfeits the match");
+            self.faults.push(fault);
+        }
+        self.finish(ctx);
+    }
+
+    /// Collect both prisoners' signals for the coming round, then play it
+    fn talk(&mut self) -> ResponseActFuture<Self, ()> {
+        let (blue, red) = self.players();
+        let blue_msg = self.blue.talk(self.match_id, blue, red);
+        let red_msg = self.red.talk(self.match_id, red, blue);
+        let talk = join(
+            timed(signal(
+                self.blue.name.clone(),
+                self.blue.addr.clone(),
+                blue_msg,
+                self.retries,
+                self.decision_timeout,
+            )),
+            timed(signal(
+                self.red.name.clone(),
+                self.red.addr.clone(),
+                red_msg,
+                self.retries,
+                self.decision_timeout,
+            )),
+        );
+        Box::pin(talk.instrument(self.span.clone()).into_actor(self).map(
+            |((blue, blue_time), (red, red_time)), act, ctx| {
+                act.blue.latency.record(blue_time);
+                act.red.latency.record(red_time);
+                match (blue, red) {
+                    (Ok(blue), Ok(red)) => {
+                        act.blue.signal = blue;
+                        act.red.signal = red;
+                        act.talked = true;
+                        ctx.notify(PlayRound);
+                    }
+                    (blue, red) => act.forfeit(blue.err(), red.err(), ctx),
+                }
+            },
+        ))
+    }
+
+    /// Take the first mover's decision for the coming round, then play it
+    fn open(&mut self) -> ResponseActFuture<Self, ()> {
+        let side = self.first_mover();
+        let batch = self.next_batch(side, 1, Some(Turn::First));
+        let (retries, timeout, on_exceed) = (self.retries, self.decision_timeout, self.on_exceed);
+        let asked = batch.is_some();
+        let seat = self.seat(side);
+        let ask = timed(decide(
+            seat.name.clone(),
+            seat.addr.clone(),
+            batch,
+            retries,
+            timeout,
+            on_exceed,
+        ));
+        Box::pin(ask.instrument(self.span.clone()).into_actor(self).map(
+            move |(answer, time), act, ctx| match answer {
+                Ok(answer) => {
+                    if asked {
+                        act.seat(side).latency.record(time);
+                    }
+                    let fallback = act.timeout_action;
+                    act.opening = Some(act.seat(side).take_decision(answer, fallback));
+                    ctx.notify(PlayRound);
+                }
+                Err(fault) => match side {
+                    Side::Blue => act.forfeit(Some(fault), None, ctx),
+                    Side::Red => act.forfeit(None, Some(fault), ctx),
+                },
+            },
+        ))
+    }
+
+    /// Where the match stands after the round just scored
+    fn progress(&self) -> MatchProgress {
+        MatchProgress {
+            rounds: self.sequence,
+            blue_score: self.blue.score,
+            red_score: self.red.score,
+            last: (
+                *self.blue.actions.last().expect("a round was played"),
+                *self.red.actions.last().expect("a round was played"),
+            ),
+        }
+    }
+
+    /// Whether a hook asks to end the match after the round just scored
+    fn hooked(&self) -> bool {
+        match (&self.hooks, self.transcript.last()) {
+            (Some(hooks), Some(round)) => {
+                let progress = self.progress();
+                hooks.on_round(&self.blue.name, &self.red.name, round, &progress) == Flow::Stop
+            }
+            _ => false,
+        }
+    }
+
+    /// Which termination condition, if any, ends the match after the round
+    /// just scored
+    fn finished(&mut self) -> Option<&'static str> {
+        let progress = self.progress();
+        let rng = &mut self.rng;
+        self.terminations.iter_mut().find_map(|termination| {
+            if termination.finished(&progress, rng) {
+                Some(termination.name())
+            } else {
+                None
+            }
+        })
+    }
+
+    fn result(&self) -> MatchResult {
+        let summary = |seat: &Seat| PlayerSummary {
+            reputation: self.reputations.as_ref().map(|r| r.get(&seat.name)),
+            ..seat.summary()
+        };
+        MatchResult {
+            rounds: self.sequence,
+            blue: summary(&self.blue),
+            red: summary(&self.red),
+            faults: self.faults.clone(),
+            transcript: self.transcript.clone(),
+            stats: MatchStats::new(&self.transcript),
+            stopped_by: self.stopped_by.map(str::to_owned),
+            round_times: self.round_times.clone(),
+            draws: None,
+        }
+    }
+
+    /// Hand the result to whoever is waiting for it, at most once
+    fn report(&mut self) {
+        if let Some(tx) = self.result_tx.take() {
+            let result = self.result();
+            if let Some(reporter) = &self.reporter {
+                let _ = reporter.do_send(Report(result.clone()));
+            }
+            if self.events.is_some() {
+                self.publish(GameEvent::MatchFinished(Box::new(result.clone())));
+            }
+            if let Some(hooks) = &self.hooks {
+                hooks.on_match_end(&result);
+            }
+            let _ = tx.send(result);
+        }
+    }
+
+    fn finish(&mut self, ctx: &mut Context<Self>) {
+        // the prisoners hear the match is over before anyone waiting on the
+        // result can ask them about it
+        for seat in [&mut self.blue, &mut self.red] {
+            let mut history = std::mem::take(&mut seat.unreported);
+            history.extend(seat.withheld.drain(..));
+            seat.addr.do_send(EndMatch {
+                match_id: self.match_id,
+                history,
+            });
+        }
+        self.report();
+        ctx.stop();
+    }
+}
+
+impl Actor for Referee {
+    type Context = Context<Self>;
+
+    fn stopped(&mut self, _ctx: &mut Context<Self>) {
+        // stopped before the last round: report what we have
+        self.report();
+    }
+}
+
+impl Handler<Play> for Referee {
+    type Result = ResponseFuture<Result<MatchResult, MailboxError>>;
+
+    fn handle(&mut self, _msg: Play, ctx: &mut Context<Self>) -> Self::Result {
+        let (tx, rx) = oneshot::channel();
+        self.result_tx = Some(tx);
+        self.skips = self.control.skips();
+        for seat in [&self.blue, &self.red] {
+            seat.addr.do_send(StartMatch {
+                match_id: self.match_id,
+            });
+        }
+        self.publish(GameEvent::MatchStarted {
+            blue: self.blue.name.clone(),
+            red: self.red.name.clone(),
+        });
+        ctx.notify(PlayRound);
+        Box::pin(async move { rx.await.map_err(|_| MailboxError::Closed) })
+    }
+}
+
+impl Handler<PlayRound> for Referee {
+    type Result = ResponseActFuture<Self, ()>;
+
+    fn handle(&mut self, _msg: PlayRound, ctx: &mut Context<Self>) -> Self::Result {
+        if self.control.paused() && !interrupt::interrupted() {
+            ctx.notify_later(PlayRound, control::POLL);
+            return Box::pin(actix::fut::ready(()));
+        }
+        if let (Some(clock), false) = (&self.clock, self.ticked) {
+            return Box::pin(clock.send(NextTick).into_actor(self).map(|tick, act, ctx| {
+                match tick {
+                    Ok(Ok(_)) => act.ticked = true,
+                    // the match plays on freely if the clock has stopped
+                    _ => act.clock = None,
+                }
+                ctx.notify(PlayRound);
+            }));
+        }
+        if let (Some((scheduler, live)), None) = (&self.scheduler, &self.slot) {
+            let turn = scheduler.send(scheduler::Turn { live: *live });
+            return Box::pin(turn.into_actor(self).map(|slot, act, ctx| {
+                match slot {
+                    Ok(Ok(slot)) => act.slot = Some(slot),
+                    // the match plays on unscheduled if the scheduler has gone
+                    _ => act.scheduler = None,
+                }
+                ctx.notify(PlayRound);
+            }));
+        }
+        self.round_started.get_or_insert_with(Instant::now);
+        if self.cheap_talk && !self.talked {
+            return self.talk();
+        }
+        if self.alternating && self.opening.is_none() {
+            return self.open();
+        }
+        let rounds = if self.cheap_talk || self.alternating {
+            1
+        } else {
+            self.batch
+                .min(self.iterations.saturating_sub(self.sequence))
+                .max(1)
+        };
+        // the first mover has already decided
+        let (blue_batch, red_batch) = match (self.opening, self.first_mover()) {
+            (Some((first, _)), Side::Blue) => (
+                None,
+                self.next_batch(Side::Red, rounds, Some(Turn::Second(first))),
+            ),
+            (Some((first, _)), Side::Red) => (
+                self.next_batch(Side::Blue, rounds, Some(Turn::Second(first))),
+                None,
+            ),
+            (None, _) => (
+                self.next_batch(Side::Blue, rounds, None),
+                self.next_batch(Side::Red, rounds, None),
+            ),
+        };
+
+        // both prisoners decide at once, unless one has already moved
+        let asked = (blue_batch.is_some(), red_batch.is_some());
+        let interrogation = join(
+            timed(decide(
+                self.blue.name.clone(),
+                self.blue.addr.clone(),
+                blue_batch,
+                self.retries,
+                self.decision_timeout,
+                self.on_exceed,
+            )),
+            timed(decide(
+                self.red.name.clone(),
+                self.red.addr.clone(),
+                red_batch,
+                self.retries,
+                self.decision_timeout,
+                self.on_exceed,
+            )),
+        );
+
+        Box::pin(
+            interrogation
+                .instrument(self.span.clone())
+                .into_actor(self)
+                .map(
+                    move |((blue_result, blue_time), (red_result, red_time)), act, ctx| {
+                        let _round =
+                            debug_span!(parent: &act.span, "round", round = act.sequence).entered();
+                        if asked.0 {
+                            act.blue.latency.record(blue_time);
+                        }
+                        if asked.1 {
+                            act.red.latency.record(red_time);
+                        }
+                        let (red, blue) = match (red_result, blue_result) {
+                            (Ok(red), Ok(blue)) => {
+                                let fallback = act.timeout_action;
+                                match (act.opening.take(), act.first_mover()) {
+                                    (Some(opening), Side::Blue) => {
+                                        (act.red.take_decision(red, fallback), opening)
+                                    }
+                                    (Some(opening), Side::Red) => {
+                                        (opening, act.blue.take_decision(blue, fallback))
+                                    }
+                                    (None, _) => (
+                                        act.red.take_decision(red, fallback),
+                                        act.blue.take_decision(blue, fallback),
+                                    ),
+                                }
+                            }
+                            (red_result, blue_result) => {
+                                act.forfeit(blue_result.err(), red_result.err(), ctx);
+                                return;
+                            }
+                        };
+
+                        let ((red, red_forfeit), (blue, blue_forfeit)) = (red, blue);
+                        let (red_chosen, blue_chosen) = (red, blue);
+                        if let Some(provider) = &act.randomness {
+                            let _ = provider.do_send(RefereeAt {
+                                round: act.sequence,
+                                position: act.rng.position(),
+                            });
+                        }
+                        let noise = act.control.noise().unwrap_or(act.noise);
+                        let (red, red_flipped) = act.perturb(red, noise);
+                        let (blue, blue_flipped) = act.perturb(blue, noise);
+                        act.red.flips += red_flipped as usize;
+                        act.blue.flips += blue_flipped as usize;
+
+                        let (red_seen, red_misperceived) = act.perturb(blue, act.misperception);
+                        let (blue_seen, blue_misperceived) = act.perturb(red, act.misperception);
+                        act.red.perceived.push(red_seen);
+                        act.red.misperceptions += red_misperceived as usize;
+                        act.blue.perceived.push(blue_seen);
+                        act.blue.misperceptions += blue_misperceived as usize;
+                        let (red_hidden, blue_hidden) = (act.hide(), act.hide());
+
+                        let outcome = act.score(red, blue);
+                        let weight = act.discount.powi(act.sequence as i32);
+                        let (red_outcome, blue_outcome) =
+                            (outcome.red.weighted(weight), outcome.blue.weighted(weight));
+                        act.red.record(red, red_outcome, red_hidden);
+                        act.blue.record(blue, blue_outcome, blue_hidden);
+                        if let Some(reputations) = &act.reputations {
+                            reputations.record_round((&act.blue.name, blue), (&act.red.name, red));
+                        }
+                        let record = RoundRecord {
+                            round: act.sequence,
+                            blue: SideRecord {
+                                chosen: blue_chosen,
+                                executed: blue,
+                                perceived: blue_seen,
+                                hidden: blue_hidden,
+                                outcome: blue_outcome,
+                                timed_out: blue_forfeit == Some(Forfeit::TimedOut),
+                                over_budget: blue_forfeit == Some(Forfeit::OverBudget),
+                                signal: act.blue.signal.take(),
+                            },
+                            red: SideRecord {
+                                chosen: red_chosen,
+                                executed: red,
+                                perceived: red_seen,
+                                hidden: red_hidden,
+                                outcome: red_outcome,
+                                timed_out: red_forfeit == Some(Forfeit::TimedOut),
+                                over_budget: red_forfeit == Some(Forfeit::OverBudget),
+                                signal: act.red.signal.take(),
+                            },
+                        };
+                        act.transcript.push(record);
+                        if let Some(started) = act.round_started.take() {
+                            act.round_times.record(started.elapsed());
+                        }
+                        if act.events.is_some() {
+                            act.publish(GameEvent::RoundPlayed {
+                                blue: act.blue.name.clone(),
+                                red: act.red.name.clone(),
+                                record,
+                            });
+                        }
+
+                        act.sequence += 1;
+                        act.slot = None;
+                        act.ticked = false;
+                        act.talked = false;
+                        // hooks hear of every round, the last included
+                        let hooked = act.hooked();
+                        if let Some(reason) = act.finished() {
+                            debug!(rounds = act.sequence, reason, "completed");
+                            if reason != FixedLength::NAME {
+                                act.stopped_by = Some(reason);
+                            }
+                            act.finish(ctx);
+                        } else if hooked {
+                            debug!(rounds = act.sequence, "stopped by a hook");
+                            act.stopped_by = Some(hooks::STOPPED_BY);
+                            act.finish(ctx);
+                        } else if interrupt::interrupted() {
+                            debug!(rounds = act.sequence, "interrupted");
+                            act.stopped_by = Some("interrupted");
+                            act.finish(ctx);
+                        } else if act.control.skips() > act.skips {
+                            debug!(rounds = act.sequence, "skipped");
+                            act.stopped_by = Some("skipped");
+                            act.finish(ctx);
+                        } else if let Some(delay) = act.delay {
+                            ctx.notify_later(PlayRound, delay);
+                        } else {
+                            ctx.notify(PlayRound);
+                        }
+                    },
+                ),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strategies::{AlwaysCooperate, AlwaysDefect, TitForTat};
+    use crate::strategy::{History, Strategy};
+
+    /// Defects once it has seen a round pay anything
+    struct Greedy;
+
+    impl Strategy for Greedy {
+        fn name(&self) -> &str {
+            "greedy"
+        }
+
+        fn choose(&mut self, history: &History) -> Action {
+            if history.rounds().any(|round| round.outcome.amount > 0.0) {
+                Action::DEFECT
+            } else {
+                Action::COOPERATE
+            }
+        }
+    }
+
+    async fn play(blind: bool) -> MatchResult {
+        let blue = Prisoner::new("greedy", Box::new(Greedy)).start();
+        let red = Prisoner::new("allc", Box::new(AlwaysCooperate {})).start();
+        let mut referee = Referee::new(("greedy", blue), ("allc", red), PayoffMatrix::default(), 4);
+        if blind {
+            referee = referee.with_blindness();
+        }
+        referee.start().send(Play).await.unwrap().unwrap()
+    }
+
+    #[actix_rt::test]
+    async fn blind_prisoners_see_the_actions_but_not_the_payoffs() {
+        let sighted = play(false).await;
+        assert_eq!(sighted.blue.payoff_count(Payoff::TEMPTATION), 3);
+
+        let blind = play(true).await;
+        assert_eq!(blind.blue.cooperations, 4);
+        // the scores are kept all the same
+        assert_eq!(blind.blue.score, 12.0);
+    }
+
+    #[actix_rt::test]
+    async fn hidden_actions_are_reported_as_unknown() {
+        let tft = Prisoner::new("tft", Box::new(TitForTat {})).start();
+        let alld = Prisoner::new("alld", Box::new(AlwaysDefect {})).start();
+        let result = Referee::new(("tft", tft), ("alld", alld), PayoffMatrix::default(), 40)
+            .with_seed(3)
+            .with_visibility(0.5)
+            .start()
+            .send(Play)
+            .await
+            .unwrap()
+            .unwrap();
+        let rounds = &result.transcript;
+        assert!(rounds.iter().any(|round| round.blue.hidden));
+        assert!(rounds.iter().any(|round| !round.blue.hidden));
+        // seeing nothing new, tit for tat repeats its own last move
+        for (previous, round) in rounds.iter().zip(&rounds[1..]) {
+            let expected = if previous.blue.hidden {
+                previous.blue.executed
+            } else {
+                Action::DEFECT
+            };
+            assert_eq!(round.blue.chosen, expected);
+        }
+    }
+
+    #[actix_rt::test]
+    async fn delayed_feedback_reaches_the_prisoners_rounds_late() {
+        let tft = Prisoner::new("tft", Box::new(TitForTat {})).start();
+        let alld = Prisoner::new("alld", Box::new(AlwaysDefect {})).start();
+        let result = Referee::new(("tft", tft), ("alld", alld), PayoffMatrix::default(), 6)
+            .with_feedback_delay(2)
+            .start()
+            .send(Play)
+            .await
+            .unwrap()
+            .unwrap();
+        let moves: Vec<Action> = result.transcript.iter().map(|r| r.blue.chosen).collect();
+        // the first defection is only heard of after round 3 is chosen
+        let (c, d) = (Action::COOPERATE, Action::DEFECT);
+        assert_eq!(moves, vec![c, c, c, d, d, d]);
+    }
+}
@@ -0,0 +1,1004 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::game::{ActionTable, PayoffMatrix};
+use crate::reputation::Reputation;
+use crate::strategies::*;
+use crate::strategy::{Strategy, StrategyInfo};
+
+/// Named numeric parameters for a strategy, e.g. `generosity = 0.1`
+pub type Parameters = BTreeMap<String, f64>;
+
+/// What a factory is given to build a strategy for one prisoner
+pub struct StrategyArgs<'a> {
+    pub parameters: &'a Parameters,
+
+    /// The payoff table the strategy will be scored against
+    pub payoffs: &'a PayoffMatrix,
+
+    /// Seed for the strategy's own random number generator
+    pub seed: u64,
+}
+
+impl<'a> StrategyArgs<'a> {
+    /// Look up a parameter, falling back to `default` when it isn't given
+    pub fn parameter(&self, name: &str, default: f64) -> f64 {
+        self.parameters.get(name).copied().unwrap_or(default)
+    }
+
+    /// Look up a parameter that must lie in [0, 1]
+    pub fn probability(&self, name: &str, default: f64) -> Result<f64, String> {
+        let p = self.parameter(name, default);
+        if (0.0..=1.0).contains(&p) {
+            Ok(p)
+        } else {
+            Err(format!("{} {} is not a probability", name, p))
+        }
+    }
+}
+
+/// The values one of a strategy's parameters can take
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ParameterInfo {
+    pub name: String,
+    pub min: f64,
+    pub max: f64,
+
+    /// Whether only whole numbers make sense, e.g. for a number of rounds
+    pub whole: bool,
+
+    /// The value taken when none is given, if it doesn't depend on the
+    /// payoffs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<f64>,
+
+    /// What the parameter does, in a line
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub description: String,
+}
+
+impl ParameterInfo {
+    pub fn new(name: &str, min: f64, max: f64) -> ParameterInfo {
+        ParameterInfo {
+            name: name.to_owned(),
+            min,
+            max,
+            whole: false,
+            default: None,
+            description: String::new(),
+        }
+    }
+
+    /// Taking `default` when no value is given
+    pub fn with_default(mut self, default: f64) -> ParameterInfo {
+        self.default = Some(default);
+        self
+    }
+
+    /// Described as `description`
+    pub fn with_description(mut self, description: &str) -> ParameterInfo {
+        self.description = description.to_owned();
+        self
+    }
+
+    /// A parameter that must lie in [0, 1]
+    pub fn probability(name: &str) -> ParameterInfo {
+        ParameterInfo::new(name, 0.0, 1.0)
+    }
+
+    /// A whole number of rounds, from `min` up
+    pub fn rounds(name: &str, min: f64) -> ParameterInfo {
+        ParameterInfo {
+            whole: true,
+            ..ParameterInfo::new(name, min, f64::INFINITY)
+        }
+    }
+
+    /// The nearest value the parameter can take to `value`
+    pub fn clamp(&self, value: f64) -> f64 {
+        let value = if self.whole { value.round() } else { value };
+        value.max(self.min).min(self.max)
+    }
+
+    /// The values it can take, e.g. `0 to 1` or `whole, at least 0`
+    pub fn range(&self) -> String {
+        let range = match (self.min.is_finite(), self.max.is_finite()) {
+            (true, true) => format!("{} to {}", self.min, self.max),
+            (true, false) => format!("at least {}", self.min),
+            (false, true) => format!("at most {}", self.max),
+            (false, false) => "any".to_owned(),
+        };
+        if self.whole {
+            format!("whole, {}", range)
+        } else {
+            range
+        }
+    }
+}
+
+/// What the registry tells of a strategy besides how to build it
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct StrategyMetadata {
+    /// A name for people, e.g. `Tit for Tat`
+    pub display_name: Option<String>,
+
+    /// Who devised it
+    pub author: Option<String>,
+
+    /// What it does, in a line
+    pub description: String,
+}
+
+/// Why a strategy couldn't be created
+#[derive(Debug)]
+pub enum StrategyError {
+    Unknown(String),
+    Invalid { strategy: String, reason: String },
+}
+
+impl fmt::Display for StrategyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StrategyError::Unknown(name) => write!(f, "unknown strategy '{}'", name),
+            StrategyError::Invalid { strategy, reason } => write!(f, "{}: {}", strategy, reason),
+        }
+    }
+}
+
+impl std::error::Error for StrategyError {}
+
+/// Builds a fresh strategy instance for each prisoner, or says why the
+/// parameters don't make sense
+pub type StrategyFactory =
+    Box<dyn Fn(&StrategyArgs) -> Result<Box<dyn Strategy>, String> + Send + Sync>;
+
+/// Builds a strategy from a location, e.g. the path in `script:path/to/file.rhai`
+pub type StrategyLoader =
+    Box<dyn Fn(&str, &StrategyArgs) -> Result<Box<dyn Strategy>, String> + Send + Sync>;
+
+/// Wraps a strategy to change how it plays, given the numbers written before
+/// it, e.g. the 0.1 in `generous(0.1, tit-for-tat)`
+pub type StrategyCombinator = Box<
+    dyn Fn(&[f64], Box<dyn Strategy>, &StrategyArgs) -> Result<Box<dyn Strategy>, String>
+        + Send
+        + Sync,
+>;
+
+/// Maps strategy names to factories, so configs and tournaments can
+/// instantiate strategies by name
+///
+/// Downstream crates can add their own strategies with [`StrategyRegistry::register`].
+/// A name of the form `scheme:location` that isn't registered is handed to
+/// the loader for `scheme`, see [`StrategyRegistry::register_loader`]. A
+/// name of the form `combinator(numbers, strategy)` wraps `strategy`, see
+/// [`StrategyRegistry::register_combinator`], and `strategy(numbers)` sets
+/// the parameters of `strategy`, see [`StrategyRegistry::register_shorthand`].
+pub struct StrategyRegistry {
+    factories: BTreeMap<String, StrategyFactory>,
+    loaders: BTreeMap<String, StrategyLoader>,
+    combinators: BTreeMap<String, StrategyCombinator>,
+
+    /// The parameters that `strategy(numbers)` sets, in order, by strategy
+    shorthands: BTreeMap<String, Vec<String>>,
+
+    /// The values each strategy's parameters can take, by strategy
+    parameters: BTreeMap<String, Vec<ParameterInfo>>,
+
+    /// Who devised each strategy and what it does, by strategy
+    metadata: BTreeMap<String, StrategyMetadata>,
+}
+
+impl StrategyRegistry {
+    /// A registry with no strategies in it
+    pub fn empty() -> StrategyRegistry {
+        StrategyRegistry {
+            factories: BTreeMap::new(),
+            loaders: BTreeMap::new(),
+            combinators: BTreeMap::new(),
+            shorthands: BTreeMap::new(),
+            parameters: BTreeMap::new(),
+            metadata: BTreeMap::new(),
+        }
+    }
+
+    /// A registry holding the standard strategy library
+    pub fn new() -> StrategyRegistry {
+        let mut registry = StrategyRegistry::empty();
+        registry.register("random", |args| {
+            let actions = args.payoffs.action_table().map_or(2, ActionTable::actions);
+            Ok(Box::new(
+                RandomStrategy::new(args.probability("p_cooperate", 0.5)?, args.seed)
+                    .with_actions(actions),
+            ))
+        });
+        registry.register_shorthand("random", &["p_cooperate"]);
+        registry.register_parameters(
+            "random",
+            vec![ParameterInfo::probability("p_cooperate")
+                .with_default(0.5)
+                .with_description("the chance of cooperating in each round")],
+        );
+        registry.register("always-cooperate", |_| Ok(Box::new(AlwaysCooperate {})));
+        registry.register("always-defect", |_| Ok(Box::new(AlwaysDefect {})));
+        registry.register("loner", |_| Ok(Box::new(Loner {})));
+        registry.register("mirror", |_| Ok(Box::new(Mirror {})));
+        registry.register("tit-for-tat", |_| Ok(Box::new(TitForTat {})));
+        registry.register("tit-for-two-tats", |_| Ok(Box::new(TitForTwoTats {})));
+        registry.register("grim", |_| Ok(Box::new(GrimTrigger::default())));
+        registry.register("pavlov", |_| Ok(Box::new(Pavlov {})));
+        registry.register("davis", |_| Ok(Box::new(Davis::default())));
+        registry.register("joss", |args| Ok(Box::new(Joss::new(args.seed))));
+        registry.register("shubik", |_| Ok(Box::new(Shubik::default())));
+        registry.register("grofman", |args| Ok(Box::new(Grofman::new(args.seed))));
+        registry.register("feld", |args| Ok(Box::new(Feld::new(args.seed))));
+        registry.register("tullock", |args| Ok(Box::new(Tullock::new(args.seed))));
+        registry.register("generous-tit-for-tat", |args| {
+            Ok(Box::new(GenerousTitForTat::new(
+                args.probability("generosity", 1.0 / 3.0)?,
+                args.seed,
+            )))
+        });
+        registry.register_parameters(
+            "generous-tit-for-tat",
+            vec![ParameterInfo::probability("generosity")
+                .with_default(1.0 / 3.0)
+                .with_description("the chance of cooperating where Tit-for-Tat would retaliate")],
+        );
+        // defaults to Tit-for-Tat
+        registry.register("memory-one", |args| {
+            Ok(Box::new(MemoryOneStrategy::new(
+                [
+                    args.probability("p_cc", 1.0)?,
+                    args.probability("p_cd", 0.0)?,
+                    args.probability("p_dc", 1.0)?,
+                    args.probability("p_dd", 0.0)?,
+                ],
+                args.probability("initial", 1.0)?,
+                args.seed,
+            )))
+        });
+        registry.register_parameters(
+            "memory-one",
+            vec![
+                ParameterInfo::probability("p_cc")
+                    .with_default(1.0)
+                    .with_description("the chance of cooperating after both cooperated"),
+                ParameterInfo::probability("p_cd")
+                    .with_default(0.0)
+                    .with_description("the chance of cooperating after being defected against"),
+                ParameterInfo::probability("p_dc")
+                    .with_default(1.0)
+                    .with_description("the chance of cooperating after defecting alone"),
+                ParameterInfo::probability("p_dd")
+                    .with_default(0.0)
+                    .with_description("the chance of cooperating after both defected"),
+                ParameterInfo::probability("initial")
+                    .with_default(1.0)
+                    .with_description("the chance of cooperating in the first round"),
+            ],
+        );
+        registry.register("q-learning", |args| {
+            let memory = args.parameter("memory", 1.0);
+            if memory.fract() != 0.0 || memory < 0.0 {
+                return Err(format!("memory {} is not a whole number of rounds", memory));
+            }
+            let discount = args.probability("discount", 0.9)?;
+            // start every action at the worth of cooperating forever
+            let optimistic = args.payoffs.reward() / (1.0 - discount).max(0.01);
+            Ok(Box::new(QLearning::new(
+                args.probability("learning_rate", 0.1)?,
+                discount,
+                args.probability("epsilon", 0.1)?,
+                memory as usize,
+                args.parameter("initial", optimistic),
+                args.seed,
+            )?))
+        });
+        registry.register_parameters(
+            "q-learning",
+            vec![
+                ParameterInfo::probability("learning_rate")
+                    .with_default(0.1)
+                    .with_description("how far each payoff moves an action's estimated worth"),
+                ParameterInfo::probability("discount")
+                    .with_default(0.9)
+                    .with_description("how much later rounds count against this one"),
+                ParameterInfo::probability("epsilon")
+                    .with_default(0.1)
+                    .with_description("the chance of trying a random action"),
+                ParameterInfo::rounds("memory", 0.0)
+                    .with_default(1.0)
+                    .with_description("the past rounds that make up a state"),
+                ParameterInfo::new("initial", f64::NEG_INFINITY, f64::INFINITY).with_description(
+                    "every action's worth at first; by default that of cooperating forever",
+                ),
+            ],
+        );
+        registry.register("opponent-model", |args| {
+            Ok(Box::new(OpponentModel::new(
+                *args.payoffs,
+                args.parameter("discount", 0.9),
+            )?))
+        });
+        // a discount of 1 would never settle
+        registry.register_parameters(
+            "opponent-model",
+            vec![ParameterInfo::new("discount", 0.0, 0.99)
+                .with_default(0.9)
+                .with_description("how much later rounds count against this one")],
+        );
+        // tries reciprocating, forgiving, and exploiting, and exploits a pushover
+        registry.register("meta", |args| {
+            let window = args.parameter("window", 10.0);
+            if window.fract() != 0.0 || window < 0.0 {
+                return Err(format!("window {} is not a whole number of rounds", window));
+            }
+            let children: Vec<Box<dyn Strategy>> = vec![
+                Box::new(TitForTat {}),
+                Box::new(Pavlov {}),
+                Box::new(GenerousTitForTat::new(1.0 / 3.0, args.seed)),
+                Box::new(AlwaysDefect {}),
+            ];
+            Ok(Box::new(MetaStrategy::new(
+                children,
+                Some(3),
+                window as usize,
+            )?))
+        });
+        registry.register_parameters(
+            "meta",
+            vec![ParameterInfo::rounds("window", 0.0)
+                .with_default(10.0)
+                .with_description("the rounds between choices of which child plays")],
+        );
+        registry.register("handshake", |_| {
+            Ok(Box::new(Handshake::new("handshake", Handshake::OPENING)?))
+        });
+        registry.register("conditional-cooperator", |args| {
+            Ok(Box::new(ConditionalCooperator::new(
+                args.probability("threshold", 0.5)?,
+            )))
+        });
+        registry.register_parameters(
+            "conditional-cooperator",
+            vec![ParameterInfo::probability("threshold")
+                .with_default(0.5)
+                .with_description("the share of the others who must have cooperated")],
+        );
+        registry.register("image-scoring", |args| {
+            Ok(Box::new(ImageScoring::new(
+                args.parameter("threshold", 0.0).round() as Reputation,
+            )))
+        });
+        registry.register_parameters(
+            "image-scoring",
+            vec![ParameterInfo {
+                whole: true,
+                ..ParameterInfo::new("threshold", f64::NEG_INFINITY, f64::INFINITY)
+                    .with_default(0.0)
+                    .with_description("the lowest image score it still helps")
+            }],
+        );
+        registry.register("standing", |_| Ok(Box::new(Standing {})));
+        registry.register("tag-matcher", |args| {
+            Ok(Box::new(TagMatcher::new(
+                args.probability("tolerance", 0.1)?,
+            )))
+        });
+        registry.register_parameters(
+            "tag-matcher",
+            vec![ParameterInfo::probability("tolerance")
+                .with_default(0.1)
+                .with_description("how far the opponent's tag may be from its own")],
+        );
+        registry.register("signal-tit-for-tat", |_| Ok(Box::new(SignalTitForTat {})));
+        // phi defaults to half the largest value the payoffs allow
+        registry.register("extort", |args| {
+            Ok(Box::new(MemoryOneStrategy::extortionate(
+                args.payoffs,
+                args.parameter("chi", 2.0),
+                args.parameters.get("phi").copied(),
+                args.seed,
+            )?))
+        });
+        registry.register("zd-gtft", |args| {
+            Ok(Box::new(MemoryOneStrategy::generous_zero_determinant(
+                args.payoffs,
+                args.parameter("chi", 2.0),
+                args.parameters.get("phi").copied(),
+                args.seed,
+            )?))
+        });
+        // phi and the equalizer's bounds depend on the payoffs, so only the
+        // registry's factories know them
+        for name in ["extort", "zd-gtft"] {
+            registry.register_parameters(
+                name,
+                vec![
+                    ParameterInfo::new("chi", 1.0, f64::INFINITY)
+                        .with_default(2.0)
+                        .with_description("the ratio it enforces between the two sides' margins"),
+                    ParameterInfo::new("phi", 0.0, f64::INFINITY).with_description(
+                        "how strongly it responds; by default half the most the payoffs allow",
+                    ),
+                ],
+            );
+        }
+        registry.register("equalizer", |args| {
+            let (reward, punishment) = (args.payoffs.reward(), args.payoffs.punishment());
+            Ok(Box::new(MemoryOneStrategy::equalizer(
+                args.payoffs,
+                args.parameter("score", (reward + punishment) / 2.0),
+                args.parameters.get("beta").copied(),
+                args.seed,
+            )?))
+        });
+        registry.register_parameters(
+            "equalizer",
+            vec![
+                ParameterInfo::new("score", f64::NEG_INFINITY, f64::INFINITY).with_description(
+                    "the opponent's score per round, between punishment and reward; \
+                     by default halfway",
+                ),
+                ParameterInfo::new("beta", 0.0, f64::INFINITY).with_description(
+                    "how strongly it responds; by default half the most the payoffs allow",
+                ),
+            ],
+        );
+        #[cfg(feature = "actors")]
+        registry.register(crate::fingerprint::PROBE, |args| {
+            Ok(Box::new(crate::fingerprint::Probe::new(
+                args.probability("x", 0.0)?,
+                args.probability("y", 0.0)?,
+                args.seed,
+            )))
+        });
+        #[cfg(feature = "actors")]
+        registry.register_parameters(
+            crate::fingerprint::PROBE,
+            vec![
+                ParameterInfo::probability("x")
+                    .with_default(0.0)
+                    .with_description("the chance of cooperating instead of playing Tit-for-Tat"),
+                ParameterInfo::probability("y")
+                    .with_default(0.0)
+                    .with_description("the chance of defecting instead of playing Tit-for-Tat"),
+            ],
+        );
+        registry.register(crate::human::NAME, |args| {
+            Ok(Box::new(crate::human::HumanStrategy::new(*args.payoffs)))
+        });
+        for (name, description) in [
+            ("always-cooperate", "Always cooperates"),
+            ("always-defect", "Always defects"),
+            (
+                "conditional-cooperator",
+                "In groups, cooperates while enough others did",
+            ),
+            (
+                "davis",
+                "Cooperates for ten rounds, then turns grim on any defection",
+            ),
+            (
+                "equalizer",
+                "Pins the opponent's score to a set value, whatever it does",
+            ),
+            (
+                "extort",
+                "Extortionate zero-determinant: takes a share of any gain",
+            ),
+            (
+                "feld",
+                "Tit-for-Tat that returns cooperation less and less often",
+            ),
+            (
+                "generous-tit-for-tat",
+                "Tit-for-Tat that sometimes forgives a defection",
+            ),
+            (
+                "grim",
+                "Cooperates until the first defection, then defects forever",
+            ),
+            (
+                "grofman",
+                "Cooperates when both did the same, otherwise now and then",
+            ),
+            (
+                "handshake",
+                "Opens with a fixed sequence and cooperates with copies",
+            ),
+            (crate::human::NAME, "Asks at the terminal for each move"),
+            (
+                "image-scoring",
+                "Helps opponents whose image score is high enough",
+            ),
+            (
+                "joss",
+                "Tit-for-Tat that sneaks in a defection one time in ten",
+            ),
+            ("loner", "Always abstains, taking the loner's payoff"),
+            (
+                "memory-one",
+                "Cooperates with a chance set by the last round's outcome",
+            ),
+            (
+                "meta",
+                "Switches between children to whichever has earned most",
+            ),
+            (
+                "mirror",
+                "Tit-for-Tat that answers this round's move when second",
+            ),
+            (
+                "opponent-model",
+                "Learns the opponent's responses and plays its best reply",
+            ),
+            ("pavlov", "Win-Stay, Lose-Shift"),
+            (
+                "q-learning",
+                "Learns the worth of each action by Q-learning",
+            ),
+            ("random", "Cooperates at random"),
+            (
+                "shubik",
+                "Answers each defection with a run one longer than the last",
+            ),
+            (
+                "signal-tit-for-tat",
+                "Announces its move and punishes announced defections",
+            ),
+            (
+                "standing",
+                "Cooperates with good standing and defects against bad",
+            ),
+            (
+                "tag-matcher",
+                "Cooperates with opponents whose tag is close to its own",
+            ),
+            (
+                "tit-for-tat",
+                "Cooperates first, then copies the opponent's last move",
+            ),
+            (
+                "tit-for-two-tats",
+                "Retaliates only after two defections in a row",
+            ),
+            (
+                "tullock",
+                "Cooperates a little less often than the opponent did",
+            ),
+            (
+                "zd-gtft",
+                "Generous zero-determinant: forgives more than it loses",
+            ),
+        ] {
+            registry.register_description(name, description);
+        }
+        #[cfg(feature = "actors")]
+        registry.register_description(
+            crate::fingerprint::PROBE,
+            "The probe of Ashlock's fingerprints, set by x and y",
+        );
+        let nowak_sigmund = Some("Martin Nowak and Karl Sigmund");
+        let press_dyson = Some("William Press and Freeman Dyson");
+        for (name, display_name, author) in [
+            ("always-cooperate", "Always Cooperate", None),
+            ("always-defect", "Always Defect", None),
+            ("conditional-cooperator", "Conditional Cooperator", None),
+            ("davis", "Davis", Some("Morton Davis")),
+            ("equalizer", "Equalizer", press_dyson),
+            ("extort", "Extortionate ZD", press_dyson),
+            ("feld", "Feld", Some("Scott Feld")),
+            (
+                "generous-tit-for-tat",
+                "Generous Tit for Tat",
+                nowak_sigmund,
+            ),
+            ("grim", "Grim Trigger", Some("James Friedman")),
+            ("grofman", "Grofman", Some("Bernard Grofman")),
+            ("handshake", "Handshake", None),
+            (crate::human::NAME, "Human", None),
+            ("image-scoring", "Image Scoring", nowak_sigmund),
+            ("joss", "Joss", Some("Johann Joss")),
+            ("loner", "Loner", None),
+            ("memory-one", "Memory-one", None),
+            ("meta", "Meta", None),
+            ("mirror", "Mirror", None),
+            ("opponent-model", "Opponent Model", None),
+            ("pavlov", "Win-Stay, Lose-Shift", nowak_sigmund),
+            ("q-learning", "Q-learning", None),
+            ("random", "Random", None),
+            ("shubik", "Shubik", Some("Martin Shubik")),
+            ("signal-tit-for-tat", "Signal Tit for Tat", None),
+            ("standing", "Standing", None),
+            (
+                "tag-matcher",
+                "Tag Matcher",
+                Some("Rick Riolo, Michael Cohen, and Robert Axelrod"),
+            ),
+            ("tit-for-tat", "Tit for Tat", Some("Anatol Rapoport")),
+            ("tit-for-two-tats", "Tit for Two Tats", None),
+            ("tullock", "Tullock", Some("Gordon Tullock")),
+            (
+                "zd-gtft",
+                "Generous ZD",
+                Some("Alexander Stewart and Joshua Plotkin"),
+            ),
+        ] {
+            registry.register_display_name(name, display_name, author);
+        }
+        #[cfg(feature = "actors")]
+        registry.register_display_name(
+            crate::fingerprint::PROBE,
+            "Fingerprint Probe",
+            Some("Daniel Ashlock"),
+        );
+        registry.register_combinator("generous", |numbers, inner, args| match *numbers {
+            [generosity] if (0.0..=1.0).contains(&generosity) => {
+                Ok(Box::new(Generous::new(inner, generosity, args.seed)))
+            }
+            [generosity] => Err(format!("generosity {} is not a probability", generosity)),
+            _ => Err("generous takes one generosity before the strategy".to_owned()),
+        });
+        registry.register_combinator("contrite", |numbers, inner, _| {
+            if numbers.is_empty() {
+                Ok(Box::new(Contrite::new(inner)))
+            } else {
+                Err("contrite takes only the strategy".to_owned())
+            }
+        });
+        for name in ["suspicious", "trusting"] {
+            registry.register_combinator(name, move |numbers, inner, _| {
+                let rounds = match *numbers {
+                    [] => 1,
+                    [rounds] if rounds >= 1.0 && rounds.fract() == 0.0 => rounds as usize,
+                    [rounds] => {
+                        return Err(format!(
+                            "{} takes a whole number of rounds from 1, not {}",
+                            name, rounds
+                        ))
+                    }
+                    _ => return Err(format!("{} takes at most a number of rounds", name)),
+                };
+                Ok(Box::new(if name == "suspicious" {
+                    Opening::suspicious(rounds, inner)
+                } else {
+                    Opening::trusting(rounds, inner)
+                }))
+            });
+        }
+        registry.register_loader("handshake", |opening, _| {
+            let name = format!("handshake:{}", opening);
+            Ok(Box::new(Handshake::new(&name, opening)?))
+        });
+        registry.register_loader(FiniteStateMachine::SCHEME, |machine, _| {
+            Ok(Box::new(FiniteStateMachine::new(Machine::parse(machine)?)))
+        });
+        registry.register_loader(LookupStrategy::SCHEME, |path, _| {
+            let name = format!("{}:{}", LookupStrategy::SCHEME, path);
+            Ok(Box::new(LookupStrategy::new(
+                &name,
+                LookupTable::load(path)?,
+            )))
+        });
+        #[cfg(feature = "actors")]
+        registry.register_loader("remote", |address, args| {
+            Ok(Box::new(crate::remote::RemoteStrategy::new(
+                address, args.seed,
+            )?))
+        });
+        #[cfg(feature = "websocket")]
+        registry.register_loader(crate::seats::SCHEME, |seat, _| {
+            Ok(Box::new(crate::seats::PlayerStrategy::new(seat)?))
+        });
+        #[cfg(feature = "scripting")]
+        registry.register_loader("script", |path, args| {
+            Ok(Box::new(crate::scripted::ScriptedStrategy::load(
+                path, args.seed,
+            )?))
+        });
+        #[cfg(feature = "wasm")]
+        registry.register_loader("wasm", |path, args| {
+            Ok(Box::new(crate::wasm::WasmStrategy::load(path, args.seed)?))
+        });
+        #[cfg(feature = "plugins")]
+        registry.register_loader("plugin", |path, args| {
+            Ok(Box::new(crate::plugin::PluginStrategy::load(
+                path, args.seed,
+            )?))
+        });
+        registry
+    }
+
+    /// Add a strategy, replacing any existing one with the same name
+    pub fn register<F>(&mut self, name: &str, factory: F)
+    where
+        F: Fn(&StrategyArgs) -> Result<Box<dyn Strategy>, String> + Send + Sync + 'static,
+    {
+        self.factories.insert(name.to_owned(), Box::new(factory));
+    }
+
+    /// Load strategies named `scheme:location`, replacing any existing
+    /// loader for `scheme`
+    pub fn register_loader<F>(&mut self, scheme: &str, loader: F)
+    where
+        F: Fn(&str, &StrategyArgs) -> Result<Box<dyn Strategy>, String> + Send + Sync + 'static,
+    {
+        self.loaders.insert(scheme.to_owned(), Box::new(loader));
+    }
+
+    /// Wrap strategies named `name(numbers, strategy)`, replacing any
+    /// existing combinator called `name`
+    pub fn register_combinator<F>(&mut self, name: &str, combinator: F)
+    where
+        F: Fn(&[f64], Box<dyn Strategy>, &StrategyArgs) -> Result<Box<dyn Strategy>, String>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.combinators
+            .insert(name.to_owned(), Box::new(combinator));
+    }
+
+    /// Accept `name(numbers)` for the strategy `name`, the numbers setting
+    /// `parameters` in order, e.g. `random(0.7)` for `p_cooperate = 0.7`
+    ///
+    /// The numbers take precedence over the same parameters in the config.
+    pub fn register_shorthand(&mut self, name: &str, parameters: &[&str]) {
+        self.shorthands.insert(
+            name.to_owned(),
+            parameters.iter().map(|&p| p.to_owned()).collect(),
+        );
+    }
+
+    /// Say which values the parameters of the strategy `name` can take,
+    /// replacing what was said before
+    pub fn register_parameters(&mut self, name: &str, parameters: Vec<ParameterInfo>) {
+        self.parameters.insert(name.to_owned(), parameters);
+    }
+
+    /// The values the parameters of the strategy `name` can take, as far as
+    /// they were registered
+    pub fn parameters(&self, name: &str) -> &[ParameterInfo] {
+        self.parameters.get(name).map_or(&[], Vec::as_slice)
+    }
+
+    /// Say what the strategy `name` does, in a line for listings
+    pub fn register_description(&mut self, name: &str, description: &str) {
+        self.metadata
+            .entry(name.to_owned())
+            .or_default()
+            .description = description.to_owned();
+    }
+
+    /// Give the strategy `name` a name for people, and say who devised it
+    /// if known
+    pub fn register_display_name(&mut self, name: &str, display_name: &str, author: Option<&str>) {
+        let metadata = self.metadata.entry(name.to_owned()).or_default();
+        metadata.display_name = Some(display_name.to_owned());
+        metadata.author = author.map(str::to_owned);
+    }
+
+    /// What the strategy `name` does, if that was registered
+    pub fn description(&self, name: &str) -> Option<&str> {
+        self.metadata(name)
+            .map(|metadata| metadata.description.as_str())
+    }
+
+    /// Everything registered about the strategy `name` besides its factory
+    /// and parameters
+    pub fn metadata(&self, name: &str) -> Option<&StrategyMetadata> {
+        self.metadata.get(name)
+    }
+
+    /// Remove the strategy `name`, if it is registered
+    pub fn unregister(&mut self, name: &str) {
+        self.factories.remove(name);
+        self.parameters.remove(name);
+        self.metadata.remove(name);
+    }
+
+    /// Remove every loader, so only registered names can be created
+    ///
+    /// Loaders read files or open connections named by the config, which a
+    /// service running other people's configs shouldn't allow.
+    pub fn clear_loaders(&mut self) {
+        self.loaders.clear();
+    }
+
+    /// Create a new instance of the named strategy
+    pub fn create(
+        &self,
+        name: &str,
+        args: &StrategyArgs,
+    ) -> Result<Box<dyn Strategy>, StrategyError> {
+        let strategy = if let Some(factory) = self.factories.get(name) {
+            factory(args)
+        } else if let Some((factory, names, arguments)) = self.shorthand(name) {
+            let mut parameters = args.parameters.clone();
+            let numbers = arguments
+                .split(',')
+                .map(|number| number.trim().parse::<f64>())
+                .collect::<Result<Vec<f64>, _>>();
+            match numbers {
+                Ok(numbers) if numbers.len() == names.len() => {
+                    parameters.extend(names.iter().cloned().zip(numbers));
+                    factory(&StrategyArgs {
+                        parameters: &parameters,
+                        ..*args
+                    })
+                }
+                _ => Err(format!("takes {} as numbers", names.join(", "))),
+            }
+        } else if let Some((combinator, arguments)) = self.combinator(name) {
+            let (numbers, inner) =
+                split_arguments(arguments).map_err(|reason| StrategyError::Invalid {
+                    strategy: name.to_owned(),
+                    reason,
+                })?;
+            combinator(&numbers, self.create(inner, args)?, args)
+        } else if let Some((loader, location)) = self.loader(name) {
+            loader(location, args)
+        } else {
+            return Err(StrategyError::Unknown(name.to_owned()));
+        };
+        strategy.map_err(|reason| StrategyError::Invalid {
+            strategy: name.to_owned(),
+            reason,
+        })
+    }
+
+    /// The loader for a `scheme:location` name, and the location
+    fn loader<'a>(&self, name: &'a str) -> Option<(&StrategyLoader, &'a str)> {
+        let (scheme, location) = name.split_once(':')?;
+        self.loaders.get(scheme).map(|loader| (loader, location))
+    }
+
+    /// The factory for a `strategy(numbers)` name, the parameters the
+    /// numbers set, and the numbers
+    fn shorthand<'a>(&self, name: &'a str) -> Option<(&StrategyFactory, &[String], &'a str)> {
+        let (strategy, rest) = name.split_once('(')?;
+        let arguments = rest.strip_suffix(')')?;
+        let names = self.shorthands.get(strategy.trim())?;
+        let factory = self.factories.get(strategy.trim())?;
+        Some((factory, names, arguments))
+    }
+
+    /// The combinator for a `combinator(arguments)` name, and the arguments
+    fn combinator<'a>(&self, name: &'a str) -> Option<(&StrategyCombinator, &'a str)> {
+        let (combinator, rest) = name.split_once('(')?;
+        let arguments = rest.strip_suffix(')')?;
+        self.combinators
+            .get(combinator.trim())
+            .map(|combinator| (combinator, arguments))
+    }
+
+    /// Whether `name` is registered or has a registered loader, or wraps a
+    /// strategy that is
+    pub fn contains(&self, name: &str) -> bool {
+        if self.factories.contains_key(name)
+            || self.shorthand(name).is_some()
+            || self.loader(name).is_some()
+        {
+            return true;
+        }
+        match self
+            .combinator(name)
+            .map(|(_, arguments)| split_arguments(arguments))
+        {
+            Some(Ok((_, inner))) => self.contains(inner),
+            _ => false,
+        }
+    }
+
+    /// Build the named strategy from `args` and report what it says about
+    /// itself
+    ///
+    /// A registered strategy's info also says what the registry knows of
+    /// it: its display name, author, description, and the parameters it
+    /// takes.
+    pub fn describe(&self, name: &str, args: &StrategyArgs) -> Result<StrategyInfo, StrategyError> {
+        self.create(name, args).map(|strategy| {
+            let info = StrategyInfo::of(strategy.as_ref());
+            let metadata = self.metadata(name).cloned().unwrap_or_default();
+            StrategyInfo {
+                display_name: metadata.display_name.unwrap_or(info.display_name),
+                author: metadata.author,
+                description: metadata.description,
+                accepts: self.parameters(name).to_vec(),
+                ..info
+            }
+        })
+    }
+
+    /// Registered strategy names, in alphabetical order
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.factories.keys().map(String::as_str)
+    }
+}
+
+/// Split a combinator's arguments into the leading numbers and the strategy
+/// they apply to, which is everything after them even if it has commas
+fn split_arguments(arguments: &str) -> Result<(Vec<f64>, &str), String> {
+    let mut numbers = Vec::new();
+    let mut rest = arguments;
+    while let Some((first, remainder)) = rest.split_once(',') {
+        match first.trim().parse() {
+            Ok(number) => numbers.push(number),
+            Err(_) => break,
+        }
+        rest = remainder;
+    }
+    match rest.trim() {
+        "" => Err("no strategy given".to_owned()),
+        inner => Ok((numbers, inner)),
+    }
+}
+
+impl Default for StrategyRegistry {
+    fn default() -> StrategyRegistry {
+        StrategyRegistry::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn describe(registry: &StrategyRegistry, name: &str, parameters: &Parameters) -> StrategyInfo {
+        let args = StrategyArgs {
+            parameters,
+            payoffs: &PayoffMatrix::default(),
+            seed: 1,
+        };
+        registry.describe(name, &args).unwrap()
+    }
+
+    #[test]
+    fn registered_defaults_are_what_the_factories_take() {
+        let registry = StrategyRegistry::new();
+        let mut checked = 0;
+        for name in registry.names() {
+            let unset = describe(&registry, name, &Parameters::new());
+            for parameter in &unset.accepts {
+                if let Some(default) = parameter.default {
+                    let set = Parameters::from([(parameter.name.clone(), default)]);
+                    let info = describe(&registry, name, &set);
+                    assert_eq!(info.parameters, unset.parameters, "{}", parameter.name);
+                    assert_eq!(parameter.clamp(default), default);
+                    checked += 1;
+                }
+            }
+        }
+        assert!(checked > 10);
+    }
+
+    #[test]
+    fn every_strategy_and_parameter_is_described() {
+        let registry = StrategyRegistry::new();
+        for name in registry.names() {
+            let info = describe(&registry, name, &Parameters::new());
+            assert!(!info.description.is_empty(), "{}", name);
+            assert!(
+                registry.metadata(name).unwrap().display_name.is_some(),
+                "{}",
+                name
+            );
+            for parameter in &info.accepts {
+                assert!(!parameter.description.is_empty(), "{}", parameter.name);
+            }
+        }
+    }
+
+    #[test]
+    fn ranges_read_plainly() {
+        assert_eq!(ParameterInfo::probability("p").range(), "0 to 1");
+        assert_eq!(
+            ParameterInfo::rounds("window", 0.0).range(),
+            "whole, at least 0"
+        );
+        let any = ParameterInfo::new("initial", f64::NEG_INFINITY, f64::INFINITY);
+        assert_eq!(any.range(), "any");
+    }
+}
@@ -0,0 +1,236 @@
+//! Strategies played by another process over TCP
+//!
+//! Name a remote strategy in a config as `strategy = "remote:host:port"`.
+//! Each prisoner opens its own connection when it is first asked to decide,
+//! so a server should accept any number of connections and keep separate
+//! state for each. The protocol is newline-delimited JSON from the host and
+//! one plain line from the server:
+//!
+//! 1. On connecting, the host sends `{"protocol":1,"seed":42}`; the seed is
+//!    the prisoner's, for servers that want to be reproducible.
+//! 2. Before each round, the host sends what happened last round, or nulls
+//!    before the first one:
+//!    `{"round":1,"own":"C","opponent":"D","payoff":1.0}`
+//! 3. The server answers `C` to cooperate or `D` to defect.
+//!
+//! The connection is closed when the match ends. If it fails, the prisoner
+//! forfeits the match. `examples/remote` has a server written in Python.
+
+use futures::future::BoxFuture;
+use futures::lock::Mutex;
+use serde::Serialize;
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tracing::{info_span, Instrument};
+
+use crate::game::Action;
+use crate::strategy::{History, Strategy};
+
+/// The version sent in the greeting
+pub const PROTOCOL_VERSION: u32 = 1;
+
+#[derive(Serialize)]
+struct Greeting {
+    protocol: u32,
+    seed: u64,
+}
+
+#[derive(Serialize)]
+struct Query {
+    round: usize,
+    own: Option<char>,
+    opponent: Option<char>,
+    payoff: Option<f64>,
+}
+
+pub struct RemoteStrategy {
+    name: String,
+    address: String,
+    seed: u64,
+
+    /// Shared with the decision in flight; `None` until the first decision
+    connection: Arc<Mutex<Option<BufReader<TcpStream>>>>,
+}
+
+impl RemoteStrategy {
+    /// A strategy played by the server at `address`, e.g. `localhost:7878`
+    ///
+    /// Doesn't connect until the first decision.
+    pub fn new(address: &str, seed: u64) -> Result<RemoteStrategy, String> {
+        if address
+            .rsplit_once(':')
+            .is_none_or(|(host, port)| host.is_empty() || port.parse::<u16>().is_err())
+        {
+            return Err(format!("'{}' is not a host:port address", address));
+        }
+        Ok(RemoteStrategy {
+            name: format!("remote:{}", address),
+            address: address.to_owned(),
+            seed,
+            connection: Arc::new(Mutex::new(None)),
+        })
+    }
+}
+
+async fn connect(address: &str, seed: u64) -> Result<BufReader<TcpStream>, String> {
+    let mut stream = TcpStream::connect(address)
+        .await
+        .map_err(|e| format!("unable to connect to {}: {}", address, e))?;
+    let greeting = serde_json::to_string(&Greeting {
+        protocol: PROTOCOL_VERSION,
+        seed,
+    })
+    .expect("serializable greeting");
+    stream
+        .write_all(format!("{}\n", greeting).as_bytes())
+        .await
+        .map_err(|e| format!("{}: {}", address, e))?;
+    Ok(BufReader::new(stream))
+}
+
+async fn exchange(connection: &mut BufReader<TcpStream>, query: &str) -> Result<Action, String> {
+    connection
+        .get_mut()
+        .write_all(query.as_bytes())
+        .await
+        .map_err(|e| e.to_string())?;
+    let mut answer = String::new();
+    if connection
+        .read_line(&mut answer)
+        .await
+        .map_err(|e| e.to_string())?
+        == 0
+    {
+        return Err("connection closed".to_owned());
+    }
+    match answer.trim() {
+        "C" => Ok(Action::COOPERATE),
+        "D" => Ok(Action::DEFECT),
+        "A" => Ok(Action::ABSTAIN),
+        other => Err(format!("unknown action '{}'", other)),
+    }
+}
+
+impl Strategy for RemoteStrategy {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Never called: prisoners use [`Strategy::choose_async`] instead
+    fn choose(&mut self, _history: &History) -> Action {
+        panic!(
+            "{}: remote strategies only decide asynchronously",
+            self.address
+        )
+    }
+
+    fn choose_async(
+        &mut self,
+        history: &History,
+    ) -> Option<BoxFuture<'static, Result<Action, String>>> {
+        let last = history.last();
+        let query = Query {
+            round: history.round(),
+            own: last.map(|round| round.own.letter()),
+            opponent: last.map(|round| round.opponent.letter()),
+            payoff: last.map(|round| round.outcome.amount),
+        };
+        let query = format!(
+            "{}\n",
+            serde_json::to_string(&query).expect("serializable query")
+        );
+        let (address, seed) = (self.address.clone(), self.seed);
+        let connection = self.connection.clone();
+        let span = info_span!("remote", address = %address, round = history.round());
+        Some(Box::pin(
+            async move {
+                let mut connection = connection.lock().await;
+                if connection.is_none() {
+                    *connection = Some(connect(&address, seed).await?);
+                }
+                let stream = connection.as_mut().expect("connected");
+                exchange(stream, &query)
+                    .await
+                    .map_err(|e| format!("{}: {}", address, e))
+            }
+            .instrument(span),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::PayoffMatrix;
+    use crate::strategy::Round;
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn addresses_need_a_host_and_a_port() {
+        assert!(RemoteStrategy::new("localhost:7878", 1).is_ok());
+        for bad in &["localhost", ":7878", "localhost:port", "localhost:70000"] {
+            assert!(RemoteStrategy::new(bad, 1).is_err(), "{}", bad);
+        }
+        assert_eq!(
+            RemoteStrategy::new("localhost:7878", 1).unwrap().name(),
+            "remote:localhost:7878"
+        );
+    }
+
+    #[actix_rt::test]
+    async fn the_host_greets_then_asks_each_round() {
+        let mut listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let server = async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut stream = BufReader::new(stream);
+            let mut lines = Vec::new();
+            for answer in &["D\n", "C\n", "X\n"] {
+                let mut line = String::new();
+                stream.read_line(&mut line).await.unwrap();
+                if lines.is_empty() {
+                    // the greeting comes with the first question
+                    lines.push(line);
+                    line = String::new();
+                    stream.read_line(&mut line).await.unwrap();
+                }
+                lines.push(line);
+                stream.get_mut().write_all(answer.as_bytes()).await.unwrap();
+            }
+            lines
+        };
+        let player = async {
+            let mut remote = RemoteStrategy::new(&address, 42).unwrap();
+            let mut history = History::new();
+            let mut answers = Vec::new();
+            for _ in 0..3 {
+                let answer = remote.choose_async(&history).unwrap().await;
+                history.push(Round {
+                    own: *answer.as_ref().unwrap_or(&Action::COOPERATE),
+                    opponent: Action::DEFECT,
+                    hidden: false,
+                    outcome: PayoffMatrix::default()
+                        .score(Action::COOPERATE, Action::DEFECT)
+                        .red,
+                });
+                answers.push(answer);
+            }
+            answers
+        };
+        let (lines, answers) = futures::join!(server, player);
+        assert_eq!(
+            lines,
+            vec![
+                "{\"protocol\":1,\"seed\":42}\n",
+                "{\"round\":0,\"own\":null,\"opponent\":null,\"payoff\":null}\n",
+                "{\"round\":1,\"own\":\"D\",\"opponent\":\"D\",\"payoff\":1.0}\n",
+                "{\"round\":2,\"own\":\"C\",\"opponent\":\"D\",\"payoff\":1.0}\n",
+            ]
+        );
+        assert_eq!(answers[0], Ok(Action::DEFECT));
+        assert_eq!(answers[1], Ok(Action::COOPERATE));
+        let refused = answers[2].as_ref().unwrap_err();
+        assert!(refused.contains("unknown action 'X'"), "{}", refused);
+    }
+}
@@ -0,0 +1,1616 @@
+use actix::prelude::*;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+
+use crate::bench::BenchRecord;
+use crate::events::{Drain, GameEvent};
+use crate::evolution::{
+    EvolutionRecord, FixationRecord, InvasionRecord, IslandRecord, ReplicatorRecord,
+};
+use crate::exploit::{ExploitRecord, Method, Response};
+use crate::formats::Placing;
+use crate::game::{Payoff, Score};
+use crate::genetic::GeneticRecord;
+use crate::leaderboard::LeaderboardEntry;
+use crate::objectives;
+use crate::profile::{Profile, PANEL};
+use crate::public_goods::GroupStanding;
+use crate::rating::RatingChange;
+use crate::recording::Resumed;
+use crate::referee::{MatchResult, PlayerSummary, Report, RoundRecord, SideRecord};
+use crate::search::SeedSearch;
+use crate::spatial::{Comparison, SpatialRecord};
+use crate::stats::{Summary, Timings};
+use crate::strategy::StrategyInfo;
+use crate::sweep::Robustness;
+use crate::teams::TeamStanding;
+use crate::topology::Lattice;
+use crate::tournament::{PairingSummary, Standing};
+use crate::tune::TuneRecord;
+
+/// A score to two decimal places, without trailing zeros
+fn format_score(score: Score) -> String {
+    let s = format!("{:.2}", score);
+    let s = s.trim_end_matches('0').trim_end_matches('.');
+    if s == "-0" {
+        "0".to_owned()
+    } else {
+        s.to_owned()
+    }
+}
+
+/// Render a match as a short human-readable summary
+pub fn match_summary(result: &MatchResult) -> String {
+    let mut s = String::new();
+    let _ = write!(
+        s,
+        "{} vs {}: {} rounds",
+        result.blue.name, result.red.name, result.rounds
+    );
+    match &result.stopped_by {
+        Some(reason) => {
+            let _ = writeln!(s, ", {}", reason);
+        }
+        None => s.push('\n'),
+    }
+    let width = result.blue.name.len().max(result.red.name.len());
+    for player in &[&result.blue, &result.red] {
+        let _ = writeln!(s, "  {}", player_line(player, result.rounds, width));
+    }
+    let stats = &result.stats;
+    let _ = writeln!(
+        s,
+        "  mutual cooperation: {} streaks, longest {}, mean {:.1}",
+        stats.mutual_cooperation_streaks,
+        stats.longest_mutual_cooperation,
+        stats.mean_mutual_cooperation
+    );
+    for (player, stats) in [(&result.blue, &stats.blue), (&result.red, &stats.red)] {
+        let _ = writeln!(
+            s,
+            "  {:width$}  payoff share {:5.1}%  retaliation {}  forgiveness {}",
+            player.name,
+            100.0 * stats.payoff_share,
+            stats
+                .retaliation_latency
+                .map_or("-".to_owned(), |latency| format!("{:.1} rounds", latency)),
+            stats
+                .forgiveness_rate
+                .map_or("-".to_owned(), |rate| format!("{:.1}%", 100.0 * rate)),
+            width = width,
+        );
+    }
+    for fault in &result.faults {
+        let _ = writeln!(s, "  forfeit: {}", fault);
+    }
+    s
+}
+
+/// Rounds per line of a transcript
+const TRANSCRIPT_WIDTH: usize = 50;
+
+/// Render a match's rounds as aligned rows of `C` and `D`, one per side,
+/// wrapped every fifty rounds
+///
+/// A lower-case letter is an action that noise flipped from the one
+/// chosen. Under each pair is the round's payoff to blue, `R`, `T`, `P`, or
+/// `S`, and each line ends with both scores so far.
+pub fn transcript(result: &MatchResult) -> String {
+    rounds_table(&result.blue.name, &result.red.name, &result.transcript)
+}
+
+/// The letter of what a side played, in lower case if noise flipped it
+fn letter(side: &SideRecord) -> char {
+    let letter = side.executed.letter();
+    if side.executed == side.chosen {
+        letter
+    } else {
+        letter.to_ascii_lowercase()
+    }
+}
+
+/// The same as [`transcript`], for any match's rounds
+fn rounds_table(blue_name: &str, red_name: &str, transcript: &[RoundRecord]) -> String {
+    let mut s = String::new();
+    let width = blue_name.len().max(red_name.len()).max("payoff".len());
+    let (mut blue_score, mut red_score) = (0.0, 0.0);
+    for (block, rounds) in transcript.chunks(TRANSCRIPT_WIDTH).enumerate() {
+        let first = block * TRANSCRIPT_WIDTH;
+        let mut ticks = String::new();
+        for i in (0..rounds.len()).step_by(10) {
+            let _ = write!(ticks, "{:<10}", first + i + 1);
+        }
+        let _ = writeln!(s, "  {:width$}  {}", "", ticks.trim_end(), width = width);
+
+        let blue: String = rounds.iter().map(|round| letter(&round.blue)).collect();
+        let red: String = rounds.iter().map(|round| letter(&round.red)).collect();
+        let payoffs: String = rounds
+            .iter()
+            .map(|round| match round.blue.outcome.payoff {
+                Payoff::REWARD => 'R',
+                Payoff::TEMPTATION => 'T',
+                Payoff::PUNISHMENT => 'P',
+                Payoff::SUCKER => 'S',
+                Payoff::LONER => 'L',
+                Payoff::TABLE => '#',
+                Payoff::NULL => ' ',
+            })
+            .collect();
+        blue_score += rounds
+            .iter()
+            .map(|round| round.blue.outcome.amount)
+            .sum::<Score>();
+        red_score += rounds
+            .iter()
+            .map(|round| round.red.outcome.amount)
+            .sum::<Score>();
+        for (name, actions, score) in [(blue_name, &blue, blue_score), (red_name, &red, red_score)]
+        {
+            let _ = writeln!(
+                s,
+                "  {:width$}  {:line$}  {}",
+                name,
+                actions,
+                format_score(score),
+                width = width,
+                line = TRANSCRIPT_WIDTH
+            );
+        }
+        let _ = writeln!(s, "  {:width$}  {}", "payoff", payoffs, width = width);
+    }
+    s
+}
+
+/// Render a match taken up partway from its recording, with its rounds
+/// if `transcript` is set
+pub fn resumed_summary(resumed: &Resumed, transcript: bool) -> String {
+    let result = &resumed.result;
+    let mut s = String::new();
+    let _ = write!(
+        s,
+        "{} vs {}: {} rounds, taken up from round {}",
+        result.blue.name, result.red.name, result.rounds, resumed.from
+    );
+    match &result.stopped_by {
+        Some(reason) => {
+            let _ = writeln!(s, ", {}", reason);
+        }
+        None => s.push('\n'),
+    }
+    let width = result.blue.name.len().max(result.red.name.len());
+    for player in [&result.blue, &result.red] {
+        let _ = write!(
+            s,
+            "  {:width$}  score {:>6} ({:.3}/round)  cooperation {:5.1}%",
+            player.name,
+            format_score(player.score),
+            player.score / result.rounds.max(1) as f64,
+            100.0 * player.cooperations as f64 / result.rounds.max(1) as f64,
+            width = width,
+        );
+        if player.flips > 0 || player.misperceptions > 0 {
+            let _ = write!(
+                s,
+                "  flips {} misperceptions {}",
+                player.flips, player.misperceptions
+            );
+        }
+        s.push('\n');
+    }
+    if transcript {
+        s.push_str(&rounds_table(
+            &result.blue.name,
+            &result.red.name,
+            &result.transcript,
+        ));
+    }
+    s
+}
+
+fn player_line(player: &PlayerSummary, rounds: usize, width: usize) -> String {
+    let mut line = format!(
+        "{:width$}  score {:>6} ({:.3}/round)  cooperation {:5.1}%  R {} T {} P {} S {}",
+        player.name,
+        format_score(player.score),
+        player.average_score(rounds),
+        100.0 * player.cooperation_rate(rounds),
+        player.payoff_count(Payoff::REWARD),
+        player.payoff_count(Payoff::TEMPTATION),
+        player.payoff_count(Payoff::PUNISHMENT),
+        player.payoff_count(Payoff::SUCKER),
+        width = width,
+    );
+    if player.payoff_count(Payoff::LONER) > 0 {
+        let _ = write!(line, " L {}", player.payoff_count(Payoff::LONER));
+    }
+    if player.flips > 0 || player.misperceptions > 0 {
+        let _ = write!(
+            line,
+            "  flips {} misperceptions {}",
+            player.flips, player.misperceptions
+        );
+    }
+    if player.timeouts > 0 {
+        let _ = write!(line, "  timeouts {}", player.timeouts);
+    }
+    if player.over_budget > 0 {
+        let _ = write!(line, "  over budget {}", player.over_budget);
+    }
+    if let Some(reputation) = player.reputation {
+        let _ = write!(line, "  reputation {}", reputation);
+    }
+    line
+}
+
+/// Render tournament standings as a table, best first
+pub fn standings_table(standings: &[Standing]) -> String {
+    let width = standings
+        .iter()
+        .map(|standing| standing.name.len())
+        .max()
+        .unwrap_or(0)
+        .max("name".len());
+    let mut s = String::new();
+    let _ = writeln!(
+        s,
+        "{:>4}  {:width$}  {:>8}  {:>9}  {:>9}  {:>11}  strategy",
+        "rank",
+        "name",
+        "score",
+        "per match",
+        "per round",
+        "cooperation",
+        width = width
+    );
+    for (i, standing) in standings.iter().enumerate() {
+        let _ = writeln!(
+            s,
+            "{:>4}  {:width$}  {:>8}  {:>9.1}  {:>9.3}  {:>10.1}%  {}",
+            i + 1,
+            standing.name,
+            format_score(standing.score),
+            standing.average_match_score,
+            standing.average_score,
+            100.0 * standing.cooperation_rate,
+            strategy_label(standing),
+            width = width
+        );
+    }
+    s
+}
+
+/// Render how each standing did on the objectives it was ranked by, with
+/// `*` marking the Pareto front, in standings order
+pub fn objectives_table(standings: &[Standing]) -> String {
+    let width = standings
+        .iter()
+        .map(|standing| standing.name.len())
+        .max()
+        .unwrap_or(0)
+        .max("name".len());
+    let mut s = String::new();
+    let _ = writeln!(
+        s,
+        "{:>4}  {:width$}  {:>9}  {:>11}  {:>8}  {:>8}  pareto",
+        "rank",
+        "name",
+        "per round",
+        "cooperation",
+        "fairness",
+        "combined",
+        width = width
+    );
+    for (i, standing) in standings.iter().enumerate() {
+        if let Some(objectives) = &standing.objectives {
+            let line = format!(
+                "{:>4}  {:width$}  {:>9.3}  {:>10.1}%  {:>8.3}  {:>8.3}  {}",
+                i + 1,
+                standing.name,
+                objectives.score,
+                100.0 * objectives.cooperation,
+                objectives.fairness,
+                objectives.combined,
+                if objectives.pareto { "*" } else { "" },
+                width = width
+            );
+            let _ = writeln!(s, "{}", line.trim_end());
+        }
+    }
+    let _ = writeln!(
+        s,
+        "Pareto front: {}",
+        objectives::pareto_front(standings).join(", ")
+    );
+    s
+}
+
+/// A standing's strategy by its display name and author, where the
+/// registry described it, or else as configured
+pub fn strategy_label(standing: &Standing) -> String {
+    match &standing.about {
+        Some(StrategyInfo {
+            display_name,
+            author: Some(author),
+            ..
+        }) => format!("{}, by {}", display_name, author),
+        Some(info) => info.display_name.clone(),
+        None => standing.strategy.clone(),
+    }
+}
+
+/// Render how long each player took to answer the referee over `matches`,
+/// slowest first by the 99th percentile, then how long rounds took
+pub fn timing_table(matches: &[MatchResult]) -> String {
+    let mut players: BTreeMap<&str, Timings> = BTreeMap::new();
+    let mut rounds = Timings::default();
+    for result in matches {
+        for player in [&result.blue, &result.red] {
+            players
+                .entry(player.name.as_str())
+                .or_default()
+                .merge(&player.latency);
+        }
+        rounds.merge(&result.round_times);
+    }
+    let mut players: Vec<(&str, Timings)> = players.into_iter().collect();
+    players.sort_by(|a, b| {
+        let p99 = |timings: &Timings| timings.quantile(0.99).unwrap_or(0.0);
+        p99(&b.1).total_cmp(&p99(&a.1)).then_with(|| a.0.cmp(b.0))
+    });
+    let width = players
+        .iter()
+        .map(|(name, _)| name.len())
+        .max()
+        .unwrap_or(0)
+        .max("name".len());
+    let mut s = String::new();
+    let _ = writeln!(
+        s,
+        "{:width$}  {:>8}  {:>9}  {:>9}  {:>9}",
+        "name",
+        "answers",
+        "p50 (us)",
+        "p95 (us)",
+        "p99 (us)",
+        width = width
+    );
+    for (name, timings) in &players {
+        let _ = match timings.percentiles() {
+            Some(p) => writeln!(
+                s,
+                "{:width$}  {:>8}  {:>9.1}  {:>9.1}  {:>9.1}",
+                name,
+                timings.samples(),
+                p.p50,
+                p.p95,
+                p.p99,
+                width = width
+            ),
+            None => writeln!(s, "{:width$}  {:>8}", name, 0, width = width),
+        };
+    }
+    if let Some(p) = rounds.percentiles() {
+        let _ = writeln!(
+            s,
+            "per round (us): p50 {:.1}  p95 {:.1}  p99 {:.1}",
+            p.p50, p.p95, p.p99
+        );
+    }
+    s
+}
+
+fn response_name(response: &Response) -> String {
+    match &response.alias {
+        Some(alias) => format!("{} {}", response.code, alias),
+        None => response.code.clone(),
+    }
+}
+
+/// Render a single match in a few lines: who won and by how much, then
+/// each side's score, cooperation, and payoffs
+pub fn duel_summary(result: &MatchResult) -> String {
+    let (blue, red) = (&result.blue, &result.red);
+    let outcome = if blue.score == red.score {
+        "a draw".to_owned()
+    } else {
+        let (winner, loser) = if blue.score > red.score {
+            (blue, red)
+        } else {
+            (red, blue)
+        };
+        format!(
+            "{} wins by {}",
+            winner.name,
+            format_score(winner.score - loser.score)
+        )
+    };
+    let mut s = format!(
+        "{} {}, {} {} after {} rounds: {}\n",
+        blue.name,
+        format_score(blue.score),
+        red.name,
+        format_score(red.score),
+        result.rounds,
+        outcome
+    );
+    let width = blue.name.len().max(red.name.len());
+    for player in [blue, red] {
+        let _ = writeln!(s, "  {}", player_line(player, result.rounds, width));
+    }
+    s
+}
+
+/// Render the `top` best memory-one responses to one strategy
+pub fn responses_table(record: &ExploitRecord, top: usize) -> String {
+    let responses = &record.responses[..top.min(record.responses.len())];
+    let names: Vec<String> = responses.iter().map(response_name).collect();
+    let width = names
+        .iter()
+        .map(String::len)
+        .max()
+        .unwrap_or(0)
+        .max("response".len());
+    let mut s = String::new();
+    let _ = writeln!(
+        s,
+        "{:width$}  {:>9}  {:>9}",
+        "response",
+        "per round",
+        record.target,
+        width = width
+    );
+    for (name, response) in names.iter().zip(responses) {
+        let _ = writeln!(
+            s,
+            "{:width$}  {:>9.3}  {:>w$.3}",
+            name,
+            response.score,
+            response.target_score,
+            width = width,
+            w = record.target.len().max(9)
+        );
+    }
+    s
+}
+
+/// Render each strategy's best memory-one response and exploitability,
+/// most exploitable first
+pub fn exploit_table(records: &[ExploitRecord]) -> String {
+    let mut records: Vec<&ExploitRecord> = records.iter().collect();
+    records.sort_by(|a, b| {
+        b.exploitability
+            .total_cmp(&a.exploitability)
+            .then_with(|| a.target.cmp(&b.target))
+    });
+    let names: Vec<String> = records.iter().map(|r| response_name(r.best())).collect();
+    let width = records
+        .iter()
+        .map(|r| r.target.len())
+        .max()
+        .unwrap_or(0)
+        .max("name".len());
+    let best = names
+        .iter()
+        .map(String::len)
+        .max()
+        .unwrap_or(0)
+        .max("best response".len());
+    let mut s = String::new();
+    let _ = writeln!(
+        s,
+        "{:width$}  {:best$}  {:>9}  {:>9}  {:>14}  method",
+        "name",
+        "best response",
+        "its score",
+        "theirs",
+        "exploitability",
+        width = width,
+        best = best
+    );
+    for (record, name) in records.iter().zip(&names) {
+        let method = match record.method {
+            Method::Exact => "exact",
+            Method::Simulated => "simulated",
+        };
+        let _ = writeln!(
+            s,
+            "{:width$}  {:best$}  {:>9.3}  {:>9.3}  {:>14.3}  {}",
+            record.target,
+            name,
+            record.best().score,
+            record.best().target_score,
+            record.exploitability,
+            method,
+            width = width,
+            best = best
+        );
+    }
+    s
+}
+
+/// Render a strategy's profile: its moves against each opponent of the
+/// panel, then how often it cooperates and how it retaliates
+pub fn profile_table(profile: &Profile) -> String {
+    let width = PANEL
+        .iter()
+        .map(|opponent| opponent.name.len())
+        .max()
+        .unwrap_or(0)
+        .max("opponent".len());
+    let mut s = String::new();
+    let _ = writeln!(
+        s,
+        "{:width$}  {:>11}  {:>9}  moves",
+        "opponent",
+        "cooperation",
+        "per round",
+        width = width
+    );
+    for (response, opponent) in profile.responses.iter().zip(&PANEL) {
+        let _ = writeln!(
+            s,
+            "{:width$}  {:>10.1}%  {:>9.3}  {}",
+            response.opponent,
+            response.cooperation * 100.0,
+            response.score,
+            response.moves,
+            width = width
+        );
+        let _ = writeln!(
+            s,
+            "{:width$}  {:>11}  {:>9}  {}  {}",
+            "",
+            "",
+            "",
+            response.opponent_moves,
+            opponent.description,
+            width = width
+        );
+    }
+    let retaliation = &profile.retaliation;
+    let _ = writeln!(s);
+    let _ = writeln!(
+        s,
+        "cooperation: {:.1}%, {}",
+        profile.cooperation * 100.0,
+        if profile.nice {
+            "never defecting first"
+        } else {
+            "defecting unprovoked"
+        }
+    );
+    let _ = writeln!(
+        s,
+        "defects after a defection {:.1}% of the time, after a cooperation {:.1}%",
+        retaliation.after_defection * 100.0,
+        retaliation.after_cooperation * 100.0
+    );
+    let _ = match retaliation.delay {
+        Some(delay) => writeln!(
+            s,
+            "answers a lone defection after {} round{}, defecting {} time{}, and {}",
+            delay,
+            if delay == 1 { "" } else { "s" },
+            retaliation.defections,
+            if retaliation.defections == 1 { "" } else { "s" },
+            if retaliation.forgives {
+                "forgives it"
+            } else {
+                "never forgives it"
+            }
+        ),
+        None => writeln!(s, "lets a lone defection pass"),
+    };
+    s
+}
+
+/// Render each pairing's mean points per match over its repetitions, with
+/// the standard deviation and a 95% confidence interval
+pub fn pairing_table(pairings: &[PairingSummary]) -> String {
+    let width = pairings
+        .iter()
+        .flat_map(|pairing| [pairing.blue.len(), pairing.red.len()])
+        .max()
+        .unwrap_or(0)
+        .max("blue".len());
+    let mut s = String::new();
+    let _ = writeln!(
+        s,
+        "{:width$}  {:width$}  {:>7}  {:>30}  {:>30}",
+        "blue",
+        "red",
+        "matches",
+        "blue per match (95% CI)",
+        "red per match (95% CI)",
+        width = width
+    );
+    let summary = |score: &Summary| {
+        format!(
+            "{:.1} ± {:.1} [{:.1}, {:.1}]",
+            score.mean, score.std_dev, score.ci_low, score.ci_high
+        )
+    };
+    for pairing in pairings {
+        let _ = writeln!(
+            s,
+            "{:width$}  {:width$}  {:>7}  {:>30}  {:>30}",
+            pairing.blue,
+            pairing.red,
+            pairing.blue_score.count,
+            summary(&pairing.blue_score),
+            summary(&pairing.red_score),
+            width = width
+        );
+    }
+    s
+}
+
+/// Render the placings of an elimination or Swiss tournament, winner first
+pub fn placings_table(placings: &[Placing]) -> String {
+    let width = placings
+        .iter()
+        .map(|placing| placing.name.len())
+        .max()
+        .unwrap_or(0)
+        .max("name".len());
+    let mut s = String::new();
+    let _ = writeln!(
+        s,
+        "{:>5}  {:width$}  {:>4}  {:>6}  {:>4}  {:>5}  {:>6}  {:>4}  {:>8}  strategy",
+        "place",
+        "name",
+        "seed",
+        "points",
+        "wins",
+        "draws",
+        "losses",
+        "byes",
+        "score",
+        width = width
+    );
+    for (i, placing) in placings.iter().enumerate() {
+        let _ = writeln!(
+            s,
+            "{:>5}  {:width$}  {:>4}  {:>6}  {:>4}  {:>5}  {:>6}  {:>4}  {:>8}  {}",
+            i + 1,
+            placing.name,
+            placing.seed,
+            placing.points,
+            placing.wins,
+            placing.draws,
+            placing.losses,
+            placing.byes,
+            format_score(placing.score),
+            placing.strategy,
+            width = width
+        );
+    }
+    s
+}
+
+/// Render each participant's rank at every value of a robustness sweep,
+/// steadiest on average first, with how well each ranking agrees with the
+/// first
+pub fn robustness_table(robustness: &Robustness) -> String {
+    let width = robustness
+        .tracks
+        .iter()
+        .map(|track| track.name.len())
+        .max()
+        .unwrap_or(0)
+        .max("correlation".len());
+    let columns: Vec<String> = robustness.values.iter().map(|v| v.to_string()).collect();
+    let column = columns.iter().map(|c| c.len()).max().unwrap_or(0).max(4);
+    let mut s = String::new();
+    let _ = write!(
+        s,
+        "{:width$}",
+        robustness.setting.to_string(),
+        width = width
+    );
+    for value in &columns {
+        let _ = write!(s, "  {:>column$}", value, column = column);
+    }
+    let _ = writeln!(s, "  {:>4}  {:>6}  strategy", "mean", "spread");
+    for track in &robustness.tracks {
+        let _ = write!(s, "{:width$}", track.name, width = width);
+        for rank in &track.ranks {
+            let _ = write!(s, "  {:>column$}", rank, column = column);
+        }
+        let _ = writeln!(
+            s,
+            "  {:>4.1}  {:>6}  {}",
+            track.mean_rank, track.spread, track.strategy
+        );
+    }
+    let _ = write!(s, "{:width$}", "correlation", width = width);
+    for correlation in &robustness.correlations {
+        let _ = write!(s, "  {:>column$.2}", correlation, column = column);
+    }
+    let _ = writeln!(s);
+    s
+}
+
+/// Render the seeds a search found, one per line, and how many it scanned
+pub fn seed_search(search: &SeedSearch) -> String {
+    let mut s = String::new();
+    let outcomes: Vec<String> = search.outcomes.iter().map(|o| o.to_string()).collect();
+    let _ = writeln!(s, "seeds where {}:", outcomes.join(" and "));
+    for seed in &search.seeds {
+        let _ = writeln!(s, "  {}", seed);
+    }
+    let last = search.from + search.scanned as u64;
+    let _ = writeln!(
+        s,
+        "{} of {} seeds from {} to {} scanned",
+        search.seeds.len(),
+        search.scanned,
+        search.from,
+        last.saturating_sub(1)
+    );
+    s
+}
+
+/// Render the leaderboard as a table, in the order given, marking the
+/// provisional places
+pub fn leaderboard_table(entries: &[LeaderboardEntry]) -> String {
+    let mut s = String::new();
+    let _ = writeln!(
+        s,
+        "{:>4}  {:>7}  {:>5}  {:>5}  {:>6}  {:>9}  {:>9}  {:>6}  strategy",
+        "rank", "matches", "wins", "draws", "losses", "per match", "per round", "recent"
+    );
+    for (i, entry) in entries.iter().enumerate() {
+        let _ = writeln!(
+            s,
+            "{:>4}  {:>7}  {:>5}  {:>5}  {:>6}  {:>9.1}  {:>9.3}  {:>6.3}  {}{}",
+            i + 1,
+            entry.tally.matches,
+            entry.tally.wins,
+            entry.tally.draws,
+            entry.tally.losses,
+            entry.average_match_score,
+            entry.average_score,
+            entry.recent_average_score,
+            entry.strategy,
+            if entry.provisional {
+                " (provisional)"
+            } else {
+                ""
+            }
+        );
+    }
+    s
+}
+
+/// Render participants' ratings as a table, highest first, with how far
+/// each moved
+pub fn ratings_table(ratings: &[RatingChange]) -> String {
+    let width = ratings
+        .iter()
+        .map(|rating| rating.name.len())
+        .max()
+        .unwrap_or(0)
+        .max("name".len());
+    let mut s = String::new();
+    let _ = writeln!(
+        s,
+        "{:>6}  {:>6}  {:>7}  {:<width$}  strategy",
+        "rating",
+        "change",
+        "matches",
+        "name",
+        width = width
+    );
+    for rating in ratings {
+        let _ = writeln!(
+            s,
+            "{:>6.0}  {:>+6.0}  {:>7}  {:<width$}  {}",
+            rating.rating,
+            // adding zero turns a rounded -0 into 0
+            rating.change.round() + 0.0,
+            rating.matches,
+            rating.name,
+            rating.strategy,
+            width = width
+        );
+    }
+    s
+}
+
+/// Render what each strategy says about itself as a table, with the
+/// parameters it takes beneath it
+pub fn strategy_table(strategies: &[StrategyInfo]) -> String {
+    let width = strategies
+        .iter()
+        .map(|info| info.name.len())
+        .max()
+        .unwrap_or(0)
+        .max("name".len());
+    let mut s = String::new();
+    let _ = writeln!(
+        s,
+        "{:width$}  {:>6}  {:>13}  description",
+        "name",
+        "memory",
+        "deterministic",
+        width = width
+    );
+    for info in strategies {
+        let memory = info
+            .memory_depth
+            .map_or_else(|| "all".to_owned(), |depth| depth.to_string());
+        let mut description = if info.display_name == info.name {
+            info.description.clone()
+        } else {
+            format!("{}: {}", info.display_name, info.description)
+        };
+        if let Some(author) = &info.author {
+            description += &format!(" ({})", author);
+        }
+        let line = format!(
+            "{:width$}  {:>6}  {:>13}  {}",
+            info.name,
+            memory,
+            if info.deterministic { "yes" } else { "no" },
+            description,
+            width = width
+        );
+        let _ = writeln!(s, "{}", line.trim_end());
+        for parameter in &info.accepts {
+            let default = parameter
+                .default
+                .map(|value| format!(" = {}", format_score(value)))
+                .unwrap_or_default();
+            let _ = writeln!(
+                s,
+                "    {}{} ({}): {}",
+                parameter.name,
+                default,
+                parameter.range(),
+                parameter.description
+            );
+        }
+    }
+    s
+}
+
+/// Render an evolutionary run as one row of headcounts per generation
+pub fn composition_table(record: &EvolutionRecord) -> String {
+    let names: Vec<&String> = record.species.iter().map(|p| &p.name).collect();
+    let widths: Vec<usize> = names.iter().map(|name| name.len().max(5)).collect();
+    let mut s = String::new();
+    let _ = write!(s, "{:>10}", "generation");
+    for (name, width) in names.iter().zip(&widths) {
+        let _ = write!(s, "  {:>width$}", name, width = width);
+    }
+    let _ = writeln!(s, "  {:>12}", "mean fitness");
+
+    let rows = record
+        .generations
+        .iter()
+        .map(|g| {
+            (
+                g.generation.to_string(),
+                &g.composition,
+                Some(g.mean_fitness),
+            )
+        })
+        .chain(std::iter::once((
+            "final".to_owned(),
+            &record.survivors,
+            None,
+        )));
+    for (generation, composition, fitness) in rows {
+        let _ = write!(s, "{:>10}", generation);
+        for (name, width) in names.iter().zip(&widths) {
+            let count = composition.get(*name).copied().unwrap_or(0);
+            let _ = write!(s, "  {:>width$}", count, width = width);
+        }
+        match fitness {
+            Some(fitness) => {
+                let _ = writeln!(s, "  {:>12.1}", fitness);
+            }
+            None => s.push('\n'),
+        }
+    }
+    if let Some(generation) = record.converged_at {
+        let _ = writeln!(s, "converged at generation {}", generation);
+    }
+    s
+}
+
+/// Render each island's final headcounts, one row per island
+pub fn island_table(record: &IslandRecord) -> String {
+    let names: Vec<&String> = record.species.iter().map(|p| &p.name).collect();
+    let widths: Vec<usize> = names.iter().map(|name| name.len().max(5)).collect();
+    let mut s = String::new();
+    let _ = write!(s, "{:>10}", "island");
+    for (name, width) in names.iter().zip(&widths) {
+        let _ = write!(s, "  {:>width$}", name, width = width);
+    }
+    s.push('\n');
+    for (i, island) in record.survivors.iter().enumerate() {
+        let _ = write!(s, "{:>10}", i + 1);
+        for (name, width) in names.iter().zip(&widths) {
+            let count = island.get(*name).copied().unwrap_or(0);
+            let _ = write!(s, "  {:>width$}", count, width = width);
+        }
+        s.push('\n');
+    }
+    s
+}
+
+/// Render replicator dynamics as each species' share per generation
+pub fn shares_table(record: &ReplicatorRecord) -> String {
+    let names: Vec<&String> = record.config.participants.iter().map(|p| &p.name).collect();
+    let widths: Vec<usize> = names.iter().map(|name| name.len().max(6)).collect();
+    let mut s = String::new();
+    let _ = write!(s, "{:>10}", "generation");
+    for (name, width) in names.iter().zip(&widths) {
+        let _ = write!(s, "  {:>width$}", name, width = width);
+    }
+    let _ = writeln!(s, "  {:>12}", "mean fitness");
+
+    let rows = record
+        .generations
+        .iter()
+        .map(|g| (g.generation.to_string(), &g.shares, Some(g.mean_fitness)))
+        .chain(std::iter::once((
+            "final".to_owned(),
+            &record.survivors,
+            None,
+        )));
+    for (generation, shares, fitness) in rows {
+        let _ = write!(s, "{:>10}", generation);
+        for (name, width) in names.iter().zip(&widths) {
+            let share = shares.get(*name).copied().unwrap_or(0.0);
+            let _ = write!(s, "  {:>width$.4}", share, width = width);
+        }
+        match fitness {
+            Some(fitness) => {
+                let _ = writeln!(s, "  {:>12.3}", fitness);
+            }
+            None => s.push('\n'),
+        }
+    }
+    s
+}
+
+/// Render a genetic run as its fittest machine per generation, then the
+/// best machines it found
+pub fn machine_table(record: &GeneticRecord) -> String {
+    let mut s = String::new();
+    let _ = writeln!(
+        s,
+        "{:>10}  {:>7}  {:>7}  best machine",
+        "generation", "best", "mean"
+    );
+    for g in &record.generations {
+        let _ = writeln!(
+            s,
+            "{:>10}  {:>7.3}  {:>7.3}  {}",
+            g.generation, g.best_fitness, g.mean_fitness, g.best
+        );
+    }
+    let _ = writeln!(s, "\n{:>4}  {:>7}  strategy", "rank", "fitness");
+    for (rank, machine) in record.best.iter().enumerate() {
+        let _ = writeln!(
+            s,
+            "{:>4}  {:>7.3}  {}",
+            rank + 1,
+            machine.fitness,
+            machine.strategy
+        );
+    }
+    s
+}
+
+/// Render a benchmark's throughput and round latencies
+pub fn bench_table(record: &BenchRecord) -> String {
+    let mut s = String::new();
+    let _ = writeln!(
+        s,
+        "{} vs {}, {} rounds over {} match{}",
+        record.blue,
+        record.red,
+        record.rounds,
+        record.matches,
+        if record.matches == 1 { "" } else { "es" }
+    );
+    let _ = writeln!(
+        s,
+        "{:.3} s, {:.0} rounds/s",
+        record.seconds, record.rounds_per_second
+    );
+    let latency = &record.latency;
+    let _ = writeln!(
+        s,
+        "per round (us): p50 {:.1}  p90 {:.1}  p99 {:.1}  p99.9 {:.1}  max {:.1}",
+        latency.p50, latency.p90, latency.p99, latency.p999, latency.max
+    );
+    s
+}
+
+/// Render the best response found by a tuning, then the score at every
+/// point of its grid, in grid order, overall and against each opponent
+pub fn tune_table(record: &TuneRecord) -> String {
+    let mut s = String::new();
+    if let Some(best) = record.best.map(|i| &record.points[i]) {
+        let parameters: Vec<String> = record
+            .axes
+            .iter()
+            .zip(&best.values)
+            .map(|(axis, value)| format!("{}={}", axis.parameter, value))
+            .collect();
+        let _ = writeln!(
+            s,
+            "best response: {} {}, {:.3} points per round (95% CI {:.3} to {:.3} over {} \
+             matches)\n",
+            record.strategy,
+            parameters.join(" "),
+            best.score,
+            best.matches.ci_low,
+            best.matches.ci_high,
+            best.matches.count
+        );
+        if let Some(generations) = record.generations {
+            let _ = writeln!(
+                s,
+                "searched {} points over {} generations\n",
+                record.points.len(),
+                generations
+            );
+        }
+    }
+    let columns: Vec<&str> = record
+        .axes
+        .iter()
+        .map(|axis| axis.parameter.as_str())
+        .chain(std::iter::once("score"))
+        .chain(record.opponents.iter().map(String::as_str))
+        .collect();
+    let widths: Vec<usize> = columns.iter().map(|c| c.len().max(5)).collect();
+    let header: Vec<String> = columns
+        .iter()
+        .zip(&widths)
+        .map(|(column, &width)| format!("{:>width$}", column, width = width))
+        .collect();
+    let _ = writeln!(s, "{}", header.join("  "));
+    for point in &record.points {
+        let cells: Vec<String> = point
+            .values
+            .iter()
+            .map(|value| value.to_string())
+            .chain(
+                std::iter::once(point.score)
+                    .chain(point.against.iter().copied())
+                    .map(|score| format!("{:.3}", score)),
+            )
+            .zip(&widths)
+            .map(|(cell, &width)| format!("{:>width$}", cell, width = width))
+            .collect();
+        let _ = writeln!(s, "{}", cells.join("  "));
+    }
+    s
+}
+
+/// Render Moran fixation counts, one line per species
+pub fn fixation_table(record: &FixationRecord) -> String {
+    let width = record
+        .fixations
+        .keys()
+        .map(|name| name.len())
+        .max()
+        .unwrap_or(0)
+        .max("name".len());
+    let mut s = String::new();
+    let _ = writeln!(
+        s,
+        "{:width$}  {:>9}  {:>11}",
+        "name",
+        "fixations",
+        "probability",
+        width = width
+    );
+    for (name, count) in &record.fixations {
+        let _ = writeln!(
+            s,
+            "{:width$}  {:>9}  {:>11.3}",
+            name,
+            count,
+            record.fixation_probability(name),
+            width = width
+        );
+    }
+    let _ = writeln!(
+        s,
+        "{} repetitions, {} unresolved, {:.1} steps to fixation on average",
+        record.repetitions, record.unresolved, record.mean_steps
+    );
+    s
+}
+
+/// Render how often the invaders took over, against the neutral chance,
+/// with the payoffs that decide evolutionary stability
+pub fn invasion_table(record: &InvasionRecord) -> String {
+    let names: Vec<&str> = record
+        .config
+        .participants
+        .iter()
+        .map(|p| p.name.as_str())
+        .collect();
+    let width = names.iter().map(|name| name.len()).max().unwrap_or(0);
+    let mut s = String::new();
+    let _ = write!(s, "{:width$}", "", width = width);
+    for name in &names {
+        let _ = write!(s, "  {:>width$}", name, width = width.max(6));
+    }
+    let _ = writeln!(s);
+    for (name, row) in names.iter().zip(&record.payoffs) {
+        let _ = write!(s, "{:width$}", name, width = width);
+        for payoff in row {
+            let _ = write!(s, "  {:>width$.3}", payoff, width = width.max(6));
+        }
+        let _ = writeln!(s);
+    }
+    let _ = writeln!(
+        s,
+        "\n{} {} evolutionarily stable against {}",
+        names[0],
+        if record.stable { "is" } else { "is not" },
+        names[1]
+    );
+    let _ = writeln!(
+        s,
+        "population {}, invaders {}: took over {} of {} repetitions ({:.3}, neutral {:.3}), died out {}, {} unresolved",
+        record.population,
+        record.invaders,
+        record.invasions,
+        record.repetitions,
+        record.invasion_probability(),
+        record.neutral_probability,
+        record.extinctions,
+        record.unresolved
+    );
+    let _ = writeln!(s, "{:.1} steps to fixation on average", record.mean_steps);
+    s
+}
+
+/// The symbol for species `index` in a lattice grid
+fn species_symbol(index: usize) -> char {
+    const SYMBOLS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+    SYMBOLS.get(index).map_or('?', |&c| c as char)
+}
+
+fn species_legend(s: &mut String, record: &SpatialRecord) {
+    for (i, participant) in record.config.participants.iter().enumerate() {
+        let _ = writeln!(s, "{} = {}", species_symbol(i), participant.name);
+    }
+}
+
+fn generation_heading(s: &mut String, record: &SpatialRecord, generation: usize) {
+    if generation + 1 == record.states.len() {
+        let _ = writeln!(s, "\nfinal");
+    } else {
+        let _ = writeln!(s, "\ngeneration {}", generation);
+    }
+}
+
+/// Render every generation of a spatial run on `lattice` as a grid of
+/// letters, one per species, after a legend
+pub fn lattice_grids(record: &SpatialRecord, lattice: Lattice) -> String {
+    let mut s = String::new();
+    species_legend(&mut s, record);
+    for (generation, state) in record.states.iter().enumerate() {
+        generation_heading(&mut s, record, generation);
+        for row in state.chunks(lattice.width) {
+            let line: String = row.iter().map(|&species| species_symbol(species)).collect();
+            let _ = writeln!(s, "{}", line);
+        }
+    }
+    s
+}
+
+/// Render every generation of a spatial run on an arbitrary network as one
+/// letter per node, after a legend
+pub fn network_states(record: &SpatialRecord) -> String {
+    let mut s = String::new();
+    species_legend(&mut s, record);
+    for (generation, state) in record.states.iter().enumerate() {
+        generation_heading(&mut s, record, generation);
+        let line: String = state
+            .iter()
+            .map(|&species| species_symbol(species))
+            .collect();
+        let _ = writeln!(s, "{}", line);
+    }
+    s
+}
+
+/// Render a comparison of topologies as each participant's share of the
+/// nodes at the start and at the end on each, a column per topology
+pub fn comparison_table(comparison: &Comparison) -> String {
+    let mut s = String::new();
+    let first = match comparison.runs.first() {
+        Some(run) => &run.record,
+        None => return s,
+    };
+    let names: Vec<&str> = first
+        .config
+        .participants
+        .iter()
+        .map(|participant| participant.name.as_str())
+        .collect();
+    let width = names
+        .iter()
+        .map(|name| name.len())
+        .max()
+        .unwrap_or(0)
+        .max("mean degree".len());
+    let columns: Vec<usize> = comparison
+        .runs
+        .iter()
+        .map(|run| run.topology.len().max(8))
+        .collect();
+    let row = |s: &mut String, label: &str, start: &str, cells: Vec<String>| {
+        let _ = write!(s, "{:width$}  {:>8}", label, start, width = width);
+        for (cell, column) in cells.iter().zip(&columns) {
+            let _ = write!(s, "  {:>column$}", cell, column = column);
+        }
+        let _ = writeln!(s);
+    };
+
+    let _ = writeln!(
+        s,
+        "{} nodes, {} generations",
+        comparison.nodes, comparison.generations
+    );
+    let topologies = comparison.runs.iter().map(|run| run.topology.clone());
+    row(&mut s, "", "start", topologies.collect());
+    let degrees = comparison
+        .runs
+        .iter()
+        .map(|run| format!("{:.1}", run.mean_degree));
+    row(&mut s, "mean degree", "", degrees.collect());
+    let start = first.shares(0);
+    for (i, name) in names.iter().enumerate() {
+        let finals = comparison.runs.iter().map(|run| {
+            let last = run.record.states.len() - 1;
+            format!("{:.1}%", 100.0 * run.record.shares(last)[i])
+        });
+        let start = format!("{:.1}%", 100.0 * start[i]);
+        row(&mut s, name, &start, finals.collect());
+    }
+    let fixed = comparison.runs.iter().map(|run| {
+        run.record
+            .fixed_at()
+            .map_or("-".to_owned(), |generation| generation.to_string())
+    });
+    row(&mut s, "fixed at", "", fixed.collect());
+    s
+}
+
+/// Render public goods standings as a table, best first
+pub fn group_standings_table(standings: &[GroupStanding]) -> String {
+    let width = standings
+        .iter()
+        .map(|standing| standing.name.len())
+        .max()
+        .unwrap_or(0)
+        .max("name".len());
+    let mut s = String::new();
+    let _ = writeln!(
+        s,
+        "{:>4}  {:width$}  {:>6}  {:>8}  {:>9}  {:>12}  strategy",
+        "rank",
+        "name",
+        "groups",
+        "score",
+        "per round",
+        "contribution",
+        width = width
+    );
+    for (i, standing) in standings.iter().enumerate() {
+        let _ = writeln!(
+            s,
+            "{:>4}  {:width$}  {:>6}  {:>8}  {:>9.3}  {:>11.1}%  {}",
+            i + 1,
+            standing.name,
+            standing.groups,
+            format_score(standing.score),
+            standing.average_score,
+            100.0 * standing.contribution_rate,
+            standing.strategy,
+            width = width
+        );
+    }
+    s
+}
+
+/// Team standings, with what each member added to its team's score
+pub fn team_standings_table(standings: &[TeamStanding]) -> String {
+    let width = standings
+        .iter()
+        .map(|standing| standing.name.len())
+        .max()
+        .unwrap_or(0)
+        .max("team".len());
+    let mut s = String::new();
+    let _ = writeln!(
+        s,
+        "{:>4}  {:width$}  {:>7}  {:>8}  {:>9}  {:>11}  members",
+        "rank",
+        "team",
+        "matches",
+        "score",
+        "per round",
+        "cooperation",
+        width = width
+    );
+    for (i, standing) in standings.iter().enumerate() {
+        let members: Vec<String> = standing
+            .members
+            .iter()
+            .map(|member| {
+                format!(
+                    "{} ({}) {}",
+                    member.name,
+                    member.strategy,
+                    format_score(member.score)
+                )
+            })
+            .collect();
+        let _ = writeln!(
+            s,
+            "{:>4}  {:width$}  {:>7}  {:>8}  {:>9.3}  {:>10.1}%  {}",
+            i + 1,
+            standing.name,
+            standing.matches,
+            format_score(standing.score),
+            standing.average_score,
+            100.0 * standing.cooperation_rate,
+            members.join(", "),
+            width = width
+        );
+    }
+    s
+}
+
+/// Prints a summary of each match to stdout as its `Report` arrives, or as
+/// it finishes when subscribed to an [`EventBus`](crate::events::EventBus)
+#[derive(Default)]
+pub struct Reporter {
+    transcripts: bool,
+}
+
+impl Reporter {
+    /// Also print each match's [`transcript`] after its summary
+    pub fn with_transcripts(mut self) -> Reporter {
+        self.transcripts = true;
+        self
+    }
+
+    fn print(&self, result: &MatchResult) {
+        // a progress bar is drawn again under the summary
+        crate::progress::clear();
+        print!("{}", match_summary(result));
+        if self.transcripts {
+            print!("{}", transcript(result));
+        }
+    }
+}
+
+impl Actor for Reporter {
+    type Context = Context<Self>;
+}
+
+impl Handler<Report> for Reporter {
+    type Result = ();
+
+    fn handle(&mut self, msg: Report, _ctx: &mut Context<Self>) {
+        self.print(&msg.0);
+    }
+}
+
+impl Handler<GameEvent> for Reporter {
+    type Result = ();
+
+    fn handle(&mut self, event: GameEvent, _ctx: &mut Context<Self>) {
+        if let GameEvent::MatchFinished(result) = event {
+            self.print(&result);
+        }
+    }
+}
+
+/// One round of a match as `--watch` shows it: what each side played, in
+/// lower case if noise flipped it, what each was paid, and each side's
+/// running total
+pub fn watched_round(
+    blue_name: &str,
+    red_name: &str,
+    record: &RoundRecord,
+    totals: (Score, Score),
+) -> String {
+    let paid = |side: &SideRecord| {
+        let amount = side.outcome.amount;
+        if amount < 0.0 {
+            format_score(amount)
+        } else {
+            format!("+{}", format_score(amount))
+        }
+    };
+    format!(
+        "round {:>4}  {} {} {:>3} = {:<8} {} {} {:>3} = {}\n",
+        record.round + 1,
+        blue_name,
+        letter(&record.blue),
+        paid(&record.blue),
+        format_score(totals.0),
+        red_name,
+        letter(&record.red),
+        paid(&record.red),
+        format_score(totals.1),
+    )
+}
+
+/// Prints every round to stdout as it is played, from the events on an
+/// [`EventBus`](crate::events::EventBus), for `--watch`
+#[derive(Default)]
+pub struct Watcher {
+    /// Each match in play's running totals, by its blue and red names
+    totals: HashMap<(String, String), (Score, Score)>,
+}
+
+impl Actor for Watcher {
+    type Context = Context<Self>;
+}
+
+impl Handler<GameEvent> for Watcher {
+    type Result = ();
+
+    fn handle(&mut self, event: GameEvent, _ctx: &mut Context<Self>) {
+        match event {
+            GameEvent::MatchStarted { blue, red } => {
+                crate::progress::clear();
+                println!("{} vs {}", blue, red);
+                self.totals.insert((blue, red), (0.0, 0.0));
+            }
+            GameEvent::RoundPlayed { blue, red, record } => {
+                let totals = self.totals.entry((blue.clone(), red.clone())).or_default();
+                totals.0 += record.blue.outcome.amount;
+                totals.1 += record.red.outcome.amount;
+                crate::progress::clear();
+                print!("{}", watched_round(&blue, &red, &record, *totals));
+            }
+            GameEvent::MatchFinished(result) => {
+                self.totals
+                    .remove(&(result.blue.name.clone(), result.red.name.clone()));
+            }
+            GameEvent::GenerationComplete(_) => {}
+        }
+    }
+}
+
+impl Handler<Drain> for Watcher {
+    type Result = ();
+
+    fn handle(&mut self, _msg: Drain, _ctx: &mut Context<Self>) -> Self::Result {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{ParticipantConfig, SimConfig};
+    use crate::registry::StrategyRegistry;
+    use crate::tournament::{self, MatchSeeds, TournamentRecord};
+
+    #[actix_rt::test]
+    async fn a_duel_says_who_won() {
+        let config = SimConfig {
+            iterations: 10,
+            participants: vec![
+                ParticipantConfig::new("alld", "always-defect"),
+                ParticipantConfig::new("allc", "always-cooperate"),
+            ],
+            ..SimConfig::default()
+        };
+        let seeds = MatchSeeds {
+            blue: 1,
+            red: 2,
+            referee: 3,
+        };
+        let (blue, red) = (&config.participants[0], &config.participants[1]);
+        let registry = StrategyRegistry::new();
+        let result = tournament::start_match(
+            &config,
+            &registry,
+            blue,
+            red,
+            seeds,
+            None,
+            &tournament::Shared::default(),
+        )
+        .await
+        .result
+        .unwrap();
+        let summary = duel_summary(&result);
+        let lines: Vec<_> = summary.lines().collect();
+        assert_eq!(
+            lines[0],
+            "alld 40, allc 10 after 10 rounds: alld wins by 30"
+        );
+        assert!(lines[2].starts_with("  allc  score     10 (1.000/round)"));
+    }
+
+    #[actix_rt::test]
+    async fn watched_rounds_keep_running_totals() {
+        let config = SimConfig {
+            iterations: 2,
+            noise: 1.0,
+            participants: vec![
+                ParticipantConfig::new("alld", "always-defect"),
+                ParticipantConfig::new("allc", "always-cooperate"),
+            ],
+            ..SimConfig::default()
+        };
+        let seeds = MatchSeeds {
+            blue: 1,
+            red: 2,
+            referee: 3,
+        };
+        let (blue, red) = (&config.participants[0], &config.participants[1]);
+        let registry = StrategyRegistry::new();
+        let result = tournament::start_match(
+            &config,
+            &registry,
+            blue,
+            red,
+            seeds,
+            None,
+            &tournament::Shared::default(),
+        )
+        .await
+        .result
+        .unwrap();
+        // every action is flipped, so alld cooperates and allc defects
+        let lines: Vec<_> = result
+            .transcript
+            .iter()
+            .scan((0.0, 0.0), |totals, record| {
+                totals.0 += record.blue.outcome.amount;
+                totals.1 += record.red.outcome.amount;
+                Some(watched_round("alld", "allc", record, *totals))
+            })
+            .collect();
+        assert_eq!(
+            lines,
+            vec![
+                "round    1  alld c  +1 = 1        allc d  +4 = 4\n",
+                "round    2  alld c  +1 = 2        allc d  +4 = 8\n",
+            ]
+        );
+    }
+
+    #[test]
+    fn standings_name_strategies_as_the_registry_does() {
+        let config = SimConfig {
+            participants: vec![
+                ParticipantConfig::new("tft", "tit-for-tat"),
+                ParticipantConfig::new("meta", "meta"),
+            ],
+            ..SimConfig::default()
+        };
+        let mut record = TournamentRecord::new(&config, 1, Vec::new());
+        assert_eq!(strategy_label(&record.standings[0]), "meta");
+        record.describe_strategies(&StrategyRegistry::new());
+        let labels: Vec<_> = record.standings.iter().map(strategy_label).collect();
+        assert_eq!(labels, ["Meta", "Tit for Tat, by Anatol Rapoport"]);
+        let table = standings_table(&record.standings);
+        assert!(table
+            .lines()
+            .nth(2)
+            .unwrap()
+            .ends_with("Tit for Tat, by Anatol Rapoport"));
+    }
+}
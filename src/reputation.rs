@@ -0,0 +1,112 @@
+//! Public reputations, for indirect reciprocity
+//!
+//! With a [`ReputationRule`] in the config, every player carries a public
+//! reputation that the referees update from the actions it executes, and
+//! that is shown to its opponents through [`History::opponent_reputation`].
+//! Strategies such as `image-scoring` and `standing` can then help those who
+//! help others, even an opponent they have never met.
+//!
+//! Reputations are shared by every match in one batch of simultaneous
+//! matches: a round robin, a generation, or a round of an elimination or
+//! Swiss tournament. They are kept by player name, so a participant and its
+//! twin are known apart. Since matches interleave, a player's reputation in
+//! one match depends on how far its others have got; with `--threads` that
+//! varies from run to run.
+//!
+//! [`History::opponent_reputation`]: crate::strategy::History::opponent_reputation
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use crate::game::Action;
+
+pub type Reputation = i32;
+
+/// How a player's reputation follows from what it does
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "rule", rename_all = "kebab-case", deny_unknown_fields)]
+pub enum ReputationRule {
+    /// Nowak and Sigmund's image score: one up for each cooperation and one
+    /// down for each defection, from 0 and no further than `limit` either way
+    ImageScoring {
+        #[serde(default = "default_limit")]
+        limit: Reputation,
+    },
+
+    /// Sugden's standing: 1 for good and 0 for bad, starting good. A player
+    /// keeps or regains good standing by cooperating, or by defecting
+    /// against someone in bad standing, and loses it by defecting against
+    /// someone in good standing.
+    Standing,
+}
+
+fn default_limit() -> Reputation {
+    5
+}
+
+impl ReputationRule {
+    /// Every player's reputation before its first round
+    pub fn initial(&self) -> Reputation {
+        match self {
+            ReputationRule::ImageScoring { .. } => 0,
+            ReputationRule::Standing => 1,
+        }
+    }
+
+    /// A player's reputation after it executed `action` against an opponent
+    /// whose reputation was `opponent`
+    pub fn update(&self, own: Reputation, action: Action, opponent: Reputation) -> Reputation {
+        match (self, action) {
+            (ReputationRule::ImageScoring { limit }, Action::COOPERATE) => (own + 1).min(*limit),
+            (ReputationRule::ImageScoring { limit }, Action::DEFECT) => (own - 1).max(-limit),
+            (ReputationRule::Standing, Action::COOPERATE) => 1,
+            (ReputationRule::Standing, Action::DEFECT) => (opponent < 1) as Reputation,
+            // staying out helps nobody and hurts nobody, and the other
+            // actions of a larger game are neither helping nor hurting
+            _ => own,
+        }
+    }
+}
+
+/// Every player's reputation, shared by the referees of one batch of
+/// matches
+#[derive(Debug, Clone)]
+pub struct Reputations {
+    rule: ReputationRule,
+    board: Arc<Mutex<HashMap<String, Reputation>>>,
+}
+
+impl Reputations {
+    pub fn new(rule: ReputationRule) -> Reputations {
+        Reputations {
+            rule,
+            board: Arc::default(),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Reputation {
+        let board = self.board.lock().expect("reputation board");
+        board
+            .get(name)
+            .copied()
+            .unwrap_or_else(|| self.rule.initial())
+    }
+
+    /// Update both players' reputations after a round, judging each by the
+    /// other's reputation going into it
+    pub fn record_round(&self, blue: (&str, Action), red: (&str, Action)) {
+        let mut board = self.board.lock().expect("reputation board");
+        let initial = self.rule.initial();
+        let blue_before = board.get(blue.0).copied().unwrap_or(initial);
+        let red_before = board.get(red.0).copied().unwrap_or(initial);
+        board.insert(
+            blue.0.to_owned(),
+            self.rule.update(blue_before, blue.1, red_before),
+        );
+        board.insert(
+            red.0.to_owned(),
+            self.rule.update(red_before, red.1, blue_before),
+        );
+    }
+}
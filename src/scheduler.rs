@@ -0,0 +1,232 @@
+//! Taking turns at rounds across concurrent matches
+//!
+//! Left to themselves, the matches of a tournament play their rounds as
+//! fast as they can, and a worker thread spends most of its time on the
+//! matches whose prisoners answer at once. A [`Scheduler`]
+//! [installed](install) on the thread the matches start from makes them
+//! take turns instead: before every round a referee asks it for a
+//! [`Slot`], and the slots go round the waiting matches in the order they
+//! asked, at most `slots` rounds at a time. A match that has played a
+//! round goes to the back of the queue, so every batch match advances one
+//! round per turn of the queue.
+//!
+//! Live matches, those with a [human](crate::human),
+//! [remote](crate::remote), or seated player, never wait for a slot:
+//! whoever is playing them is answered as soon as they move, while the
+//! batch matches churn through the slots in the background.
+//!
+//! Turns only change when rounds are played, not what happens in them, so
+//! the same seeds give the same results with or without a scheduler.
+
+use actix::prelude::*;
+use futures::channel::oneshot;
+use std::cell::RefCell;
+use std::collections::VecDeque;
+
+use crate::config::ParticipantConfig;
+use crate::human;
+
+thread_local! {
+    static INSTALLED: RefCell<Option<Addr<Scheduler>>> = const { RefCell::new(None) };
+}
+
+/// Schedule the rounds of the matches started from this thread with
+/// `scheduler`, or let them run freely with `None`
+pub fn install(scheduler: Option<Addr<Scheduler>>) {
+    INSTALLED.with(|installed| *installed.borrow_mut() = scheduler);
+}
+
+/// What schedules the matches started from this thread, if anything
+pub(crate) fn installed() -> Option<Addr<Scheduler>> {
+    INSTALLED.with(|installed| installed.borrow().clone())
+}
+
+/// Whether `participant` is played by someone who is waiting on the match
+pub fn live(participant: &ParticipantConfig) -> bool {
+    let strategy = participant.strategy.as_str();
+    strategy == human::NAME || strategy.starts_with("remote:") || strategy.starts_with("player:")
+}
+
+/// Hands out turns at playing a round, round-robin across the matches
+/// waiting for one
+pub struct Scheduler {
+    slots: usize,
+
+    /// Batch rounds being played
+    busy: usize,
+    waiting: VecDeque<oneshot::Sender<Slot>>,
+
+    /// Rounds that waited for a slot, and rounds played without waiting
+    queued: usize,
+    live: usize,
+}
+
+impl Scheduler {
+    /// A scheduler letting `slots` rounds of batch matches be played at once
+    pub fn new(slots: usize) -> Scheduler {
+        Scheduler {
+            slots: slots.max(1),
+            busy: 0,
+            waiting: VecDeque::new(),
+            queued: 0,
+            live: 0,
+        }
+    }
+
+    /// Hand the free slots to the matches that have waited longest
+    fn grant(&mut self, ctx: &mut Context<Self>) {
+        while self.busy < self.slots {
+            let waiting = match self.waiting.pop_front() {
+                Some(waiting) => waiting,
+                None => break,
+            };
+            let slot = Slot {
+                scheduler: Some(ctx.address()),
+            };
+            // a match that stopped waiting has given its slot up already
+            if waiting.send(slot).is_ok() {
+                self.busy += 1;
+            }
+        }
+    }
+}
+
+impl Actor for Scheduler {
+    type Context = Context<Self>;
+}
+
+/// A turn at playing one round, given back when it is dropped
+pub struct Slot {
+    /// Where to give it back, or `None` for a live match's, which holds
+    /// nothing up
+    scheduler: Option<Addr<Scheduler>>,
+}
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        if let Some(scheduler) = self.scheduler.take() {
+            scheduler.do_send(Release);
+        }
+    }
+}
+
+/// Ask for a turn at playing the next round of a match
+pub struct Turn {
+    /// Whether someone is waiting on the match; see [`live`]
+    pub live: bool,
+}
+
+impl Message for Turn {
+    type Result = Result<Slot, oneshot::Canceled>;
+}
+
+struct Release;
+
+impl Message for Release {
+    type Result = ();
+}
+
+/// How many rounds were queued for a slot, and how many were live and
+/// played without one
+pub struct GetTurns;
+
+impl Message for GetTurns {
+    type Result = (usize, usize);
+}
+
+impl Handler<Turn> for Scheduler {
+    type Result = ResponseFuture<Result<Slot, oneshot::Canceled>>;
+
+    fn handle(&mut self, msg: Turn, ctx: &mut Context<Self>) -> Self::Result {
+        if msg.live {
+            self.live += 1;
+            return Box::pin(async { Ok(Slot { scheduler: None }) });
+        }
+        self.queued += 1;
+        let (tx, rx) = oneshot::channel();
+        self.waiting.push_back(tx);
+        self.grant(ctx);
+        Box::pin(rx)
+    }
+}
+
+impl Handler<Release> for Scheduler {
+    type Result = ();
+
+    fn handle(&mut self, _msg: Release, ctx: &mut Context<Self>) -> Self::Result {
+        self.busy = self.busy.saturating_sub(1);
+        self.grant(ctx);
+    }
+}
+
+impl Handler<GetTurns> for Scheduler {
+    type Result = MessageResult<GetTurns>;
+
+    fn handle(&mut self, _msg: GetTurns, _ctx: &mut Context<Self>) -> Self::Result {
+        MessageResult((self.queued, self.live))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::SimConfig;
+    use crate::game::Score;
+    use crate::registry::StrategyRegistry;
+    use crate::tournament;
+    use futures::future::{self, Either};
+
+    #[actix_rt::test]
+    async fn slots_go_round_the_waiting_matches_in_turn() {
+        let scheduler = Scheduler::new(1).start();
+        let first = scheduler.send(Turn { live: false }).await.unwrap().unwrap();
+        let second = scheduler.send(Turn { live: false });
+        let third = scheduler.send(Turn { live: false });
+        // live rounds don't wait for the batch rounds ahead of them
+        let live = scheduler.send(Turn { live: true }).await.unwrap();
+        assert!(live.is_ok());
+
+        drop(first);
+        let third = match future::select(Box::pin(third), Box::pin(second)).await {
+            Either::Right((second, third)) => {
+                drop(second.unwrap().unwrap());
+                third
+            }
+            Either::Left(_) => panic!("the third asked after the second"),
+        };
+        drop(third.await.unwrap().unwrap());
+        assert_eq!(scheduler.send(GetTurns).await.unwrap(), (3, 1));
+    }
+
+    #[actix_rt::test]
+    async fn scheduled_tournaments_play_as_free_ones_do() {
+        let config = SimConfig {
+            iterations: 30,
+            noise: 0.1,
+            participants: ["tit-for-tat", "random", "pavlov", "grim"]
+                .iter()
+                .map(|&name| ParticipantConfig::new(name, name))
+                .collect(),
+            ..SimConfig::default()
+        };
+        let registry = StrategyRegistry::new();
+        let scores = |reports: Vec<tournament::MatchReport>| -> Vec<(Score, Score)> {
+            reports
+                .into_iter()
+                .map(|report| {
+                    let result = report.result.unwrap();
+                    (result.blue.score, result.red.score)
+                })
+                .collect()
+        };
+        let free = scores(tournament::round_robin(&config, &registry, 5, None).await);
+
+        let scheduler = Scheduler::new(2).start();
+        install(Some(scheduler.clone()));
+        let scheduled = scores(tournament::round_robin(&config, &registry, 5, None).await);
+        install(None);
+        assert_eq!(scheduled, free);
+        // one turn per round of the six matches, none of them live
+        assert_eq!(scheduler.send(GetTurns).await.unwrap(), (6 * 30, 0));
+    }
+}
@@ -0,0 +1,278 @@
+//! The versioned layout of the results written for other programs
+//!
+//! Every JSON document written with `--format json`, saved by `run
+//! --record`, or served as a job's result carries a `schema_version`, and
+//! the CSV of `sweep` starts each row with one. The version is
+//! [`SCHEMA_VERSION`]. It goes up whenever a field is renamed, removed, or
+//! changes meaning. Adding a field doesn't change it, since readers should
+//! ignore fields they don't know.
+//!
+//! [`upgrade`] and [`upgrade_csv`] bring results of an older version up to
+//! the current one, one version at a time, so that [`from_str`] can read
+//! anything actoripd has written. Results from a newer actoripd are
+//! refused rather than misread.
+//!
+//! The versions so far:
+//!
+//! 1. Results as written before they were versioned, with no
+//!    `schema_version`. A list of results, such as `analyze seeds`' or
+//!    `replay`'s, was written as a bare JSON array.
+//! 2. Each document is an object with `schema_version` among its fields.
+//!    A list of results is wrapped as `{"schema_version": 2, "results":
+//!    [...]}`, and the rows of the `sweep` CSV start with a
+//!    `schema_version` column.
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::fmt;
+
+/// The version of the results written now
+pub const SCHEMA_VERSION: u32 = 2;
+
+/// The field holding a document's version
+pub const VERSION_FIELD: &str = "schema_version";
+
+/// The field holding results that aren't an object themselves
+pub const RESULTS_FIELD: &str = "results";
+
+#[derive(Debug)]
+pub enum SchemaError {
+    Parse(serde_json::Error),
+
+    /// The document was written by a newer actoripd
+    Newer(u64),
+
+    /// The document's version isn't a number, or the CSV has no header
+    Malformed(String),
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SchemaError::Parse(e) => write!(f, "{}", e),
+            SchemaError::Newer(version) => write!(
+                f,
+                "results of schema version {} are newer than this actoripd, which reads up to \
+                 version {}",
+                version, SCHEMA_VERSION
+            ),
+            SchemaError::Malformed(e) => write!(f, "malformed results: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for SchemaError {}
+
+/// `results` as a document of the current version
+pub fn stamp<T: Serialize>(results: &T) -> Value {
+    let mut document = match serde_json::to_value(results).expect("serializable results") {
+        Value::Object(fields) => fields,
+        other => {
+            let mut fields = Map::new();
+            fields.insert(RESULTS_FIELD.to_owned(), other);
+            fields
+        }
+    };
+    document.insert(VERSION_FIELD.to_owned(), SCHEMA_VERSION.into());
+    Value::Object(document)
+}
+
+/// `results` written out as a document of the current version
+pub fn to_string_pretty<T: Serialize>(results: &T) -> String {
+    serde_json::to_string_pretty(&stamp(results)).expect("serializable results")
+}
+
+/// The same as [`to_string_pretty`], on one line
+pub fn to_string<T: Serialize>(results: &T) -> String {
+    serde_json::to_string(&stamp(results)).expect("serializable results")
+}
+
+/// The version `document` was written with
+pub fn version(document: &Value) -> Result<u64, SchemaError> {
+    match document.get(VERSION_FIELD) {
+        None => Ok(1),
+        Some(version) => match version.as_u64() {
+            Some(0) => Err(SchemaError::Malformed(format!(
+                "{} is 0, and versions start at 1",
+                VERSION_FIELD
+            ))),
+            Some(version) => Ok(version),
+            None => Err(SchemaError::Malformed(format!(
+                "{} is {}, not a number",
+                VERSION_FIELD, version
+            ))),
+        },
+    }
+}
+
+/// Version 1 wrote a list of results as a bare array
+fn from_1(document: Value) -> Value {
+    match document {
+        Value::Object(fields) => Value::Object(fields),
+        other => {
+            let mut fields = Map::new();
+            fields.insert(RESULTS_FIELD.to_owned(), other);
+            Value::Object(fields)
+        }
+    }
+}
+
+/// The conversions from each version to the next, the first from version 1
+const UPGRADES: [fn(Value) -> Value; SCHEMA_VERSION as usize - 1] = [from_1];
+
+/// `document`, of whatever version, brought up to the current one
+pub fn upgrade(document: Value) -> Result<Value, SchemaError> {
+    let written = version(&document)?;
+    if written > SCHEMA_VERSION as u64 {
+        return Err(SchemaError::Newer(written));
+    }
+    let mut document = UPGRADES[written as usize - 1..]
+        .iter()
+        .fold(document, |document, step| step(document));
+    if let Value::Object(fields) = &mut document {
+        fields.insert(VERSION_FIELD.to_owned(), SCHEMA_VERSION.into());
+    }
+    Ok(document)
+}
+
+/// Read results written by any version
+pub fn from_str<T: DeserializeOwned>(text: &str) -> Result<T, SchemaError> {
+    let document = serde_json::from_str(text).map_err(SchemaError::Parse)?;
+    let mut fields = match upgrade(document)? {
+        Value::Object(fields) => fields,
+        other => {
+            return Err(SchemaError::Malformed(format!(
+                "expected an object, not {}",
+                other
+            )))
+        }
+    };
+    fields.remove(VERSION_FIELD);
+    // a list of results, or anything else that isn't an object, was wrapped
+    let results = match fields.remove(RESULTS_FIELD) {
+        Some(results) if fields.is_empty() => results,
+        Some(results) => {
+            fields.insert(RESULTS_FIELD.to_owned(), results);
+            Value::Object(fields)
+        }
+        None => Value::Object(fields),
+    };
+    serde_json::from_value(results).map_err(SchemaError::Parse)
+}
+
+/// A CSV written by any version brought up to the current one
+pub fn upgrade_csv(csv: &str) -> Result<String, SchemaError> {
+    let header = csv
+        .lines()
+        .next()
+        .ok_or_else(|| SchemaError::Malformed("the CSV has no header".to_owned()))?;
+    if header.split(',').next() == Some(VERSION_FIELD) {
+        let newest = csv
+            .lines()
+            .skip(1)
+            .filter_map(|row| row.split(',').next()?.parse::<u64>().ok())
+            .max()
+            .unwrap_or(SCHEMA_VERSION as u64);
+        if newest > SCHEMA_VERSION as u64 {
+            return Err(SchemaError::Newer(newest));
+        }
+        return Ok(csv.to_owned());
+    }
+    // version 1 had no schema_version column, and is otherwise the same
+    let mut upgraded = format!("{},{}\n", VERSION_FIELD, header);
+    for row in csv.lines().skip(1) {
+        upgraded.push_str(&format!("{},{}\n", SCHEMA_VERSION, row));
+    }
+    Ok(upgraded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Standing {
+        name: String,
+        score: f64,
+    }
+
+    fn standing(name: &str, score: f64) -> Standing {
+        Standing {
+            name: name.to_owned(),
+            score,
+        }
+    }
+
+    #[test]
+    fn results_are_written_with_their_version_and_read_back() {
+        let one = standing("tft", 2.5);
+        let document = stamp(&one);
+        assert_eq!(document[VERSION_FIELD], SCHEMA_VERSION);
+        assert_eq!(document["name"], "tft");
+        assert_eq!(from_str::<Standing>(&to_string(&one)).unwrap(), one);
+
+        let list = vec![standing("tft", 2.5), standing("alld", 1.0)];
+        let document = stamp(&list);
+        assert_eq!(document[RESULTS_FIELD][1]["name"], "alld");
+        assert_eq!(
+            from_str::<Vec<Standing>>(&to_string_pretty(&list)).unwrap(),
+            list
+        );
+    }
+
+    #[test]
+    fn results_of_version_1_are_upgraded() {
+        let unversioned = r#"{"name": "tft", "score": 2.5}"#;
+        assert_eq!(
+            from_str::<Standing>(unversioned).unwrap(),
+            standing("tft", 2.5)
+        );
+        let bare = r#"[{"name": "grim", "score": 3}]"#;
+        let upgraded = upgrade(serde_json::from_str(bare).unwrap()).unwrap();
+        assert_eq!(upgraded[VERSION_FIELD], SCHEMA_VERSION);
+        assert_eq!(upgraded[RESULTS_FIELD][0]["name"], "grim");
+        assert_eq!(
+            from_str::<Vec<Standing>>(bare).unwrap(),
+            vec![standing("grim", 3.0)]
+        );
+    }
+
+    #[test]
+    fn results_of_a_newer_version_are_refused() {
+        let newer = format!(
+            r#"{{"schema_version": {}, "name": "tft"}}"#,
+            SCHEMA_VERSION + 1
+        );
+        assert!(matches!(
+            from_str::<Standing>(&newer),
+            Err(SchemaError::Newer(_))
+        ));
+        for garbled in [
+            r#"{"schema_version": "two", "name": "tft", "score": 1}"#,
+            r#"{"schema_version": 0, "name": "tft", "score": 1}"#,
+        ] {
+            assert!(matches!(
+                from_str::<Standing>(garbled),
+                Err(SchemaError::Malformed(_))
+            ));
+        }
+        let newer_csv = format!("schema_version,rank\n{},1\n", SCHEMA_VERSION + 1);
+        assert!(upgrade_csv(&newer_csv).is_err());
+    }
+
+    #[test]
+    fn csv_of_version_1_gains_the_version_column() {
+        let old = "noise,rank,name\n0,1,tft\n0,2,alld\n";
+        let upgraded = upgrade_csv(old).unwrap();
+        assert_eq!(
+            upgraded,
+            format!(
+                "schema_version,noise,rank,name\n{0},0,1,tft\n{0},0,2,alld\n",
+                SCHEMA_VERSION
+            )
+        );
+        assert_eq!(upgrade_csv(&upgraded).unwrap(), upgraded);
+    }
+}
@@ -0,0 +1,97 @@
+//! Strategies written as Rhai scripts, loaded at run time
+//!
+//! A script defines `choose(history)` and returns `"C"` to cooperate or
+//! `"D"` to defect. `history` is an array of the rounds played so far,
+//! oldest first, each a map of `own` and `opponent` actions (`"C"` or `"D"`)
+//! and the `payoff` this prisoner received. Scripts may call `random()` for
+//! a number in [0, 1) drawn from the prisoner's seed.
+//!
+//! ```rhai
+//! fn choose(history) {
+//!     if history.is_empty() { "C" } else { history[-1].opponent }
+//! }
+//! ```
+//!
+//! Name a script in a config as `strategy = "script:path/to/file.rhai"`.
+
+use rand::{Rng, SeedableRng};
+use rhai::{Array, Dynamic, Engine, Map, Scope, AST};
+use std::sync::{Arc, Mutex};
+
+use crate::game::Action;
+use crate::randomness::StrategyRng;
+use crate::strategy::{History, Strategy};
+
+/// Operations a single `choose()` call may run before it is abandoned
+const MAX_OPERATIONS: u64 = 1_000_000;
+
+pub struct ScriptedStrategy {
+    name: String,
+    path: String,
+    engine: Engine,
+    ast: AST,
+}
+
+impl ScriptedStrategy {
+    /// Compile the script at `path`, checking that it defines `choose(history)`
+    pub fn load(path: &str, seed: u64) -> Result<ScriptedStrategy, String> {
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+        let rng = Arc::new(Mutex::new(StrategyRng::seed_from_u64(seed)));
+        engine.register_fn("random", move || -> f64 {
+            rng.lock().expect("unpoisoned rng").gen::<f64>()
+        });
+
+        let ast = engine
+            .compile_file(path.into())
+            .map_err(|e| format!("unable to load {}: {}", path, e))?;
+        if !ast
+            .iter_functions()
+            .any(|f| f.name == "choose" && f.params.len() == 1)
+        {
+            return Err(format!("{} does not define choose(history)", path));
+        }
+        Ok(ScriptedStrategy {
+            name: format!("script:{}", path),
+            path: path.to_owned(),
+            engine,
+            ast,
+        })
+    }
+}
+
+fn letter(action: Action) -> Dynamic {
+    action.letter().to_string().into()
+}
+
+impl Strategy for ScriptedStrategy {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Panics if the script fails or returns anything but "C" or "D"; the
+    /// prisoner turns that into a forfeit
+    fn choose(&mut self, history: &History) -> Action {
+        let rounds: Array = history
+            .rounds()
+            .map(|round| {
+                let mut map = Map::new();
+                map.insert("own".into(), letter(round.own));
+                map.insert("opponent".into(), letter(round.opponent));
+                map.insert("payoff".into(), Dynamic::from_float(round.outcome.amount));
+                Dynamic::from_map(map)
+            })
+            .collect();
+
+        let choice: Dynamic = self
+            .engine
+            .call_fn(&mut Scope::new(), &self.ast, "choose", (rounds,))
+            .unwrap_or_else(|e| panic!("{}: {}", self.path, e));
+        match choice.into_string().as_deref() {
+            Ok("C") => Action::COOPERATE,
+            Ok("D") => Action::DEFECT,
+            Ok("A") => Action::ABSTAIN,
+            _ => panic!("{}: choose() must return \"C\", \"D\", or \"A\"", self.path),
+        }
+    }
+}
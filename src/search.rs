@@ -0,0 +1,297 @@
+//! Scanning seeds for tournaments that turn out a given way
+//!
+//! [`search`] plays the round robin of one config under one seed
+//! after another and keeps the seeds whose tournament showed every
+//! [`Outcome`] asked for, such as `random beats tft` or `cooperation below
+//! 0.2 after 80`. Since a seed replays its tournament exactly, each seed
+//! found is a ready-made example of the outcome, for teaching or for a
+//! regression test.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::ops::Range;
+use std::str::FromStr;
+
+use crate::config::SimConfig;
+use crate::game::{Action, Score};
+use crate::interrupt;
+use crate::referee::MatchResult;
+use crate::registry::StrategyRegistry;
+use crate::tournament;
+
+/// Something a tournament may turn out to show
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum Outcome {
+    /// The first participant scored more than the second in their matches
+    /// against each other
+    Beats { winner: String, loser: String },
+
+    /// The participant topped the standings outright
+    Wins { participant: String },
+
+    /// The share of moves that were cooperations, across every match,
+    /// in the rounds after `after`
+    Cooperation {
+        above: bool,
+        threshold: f64,
+        after: usize,
+    },
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Outcome::Beats { winner, loser } => write!(f, "{} beats {}", winner, loser),
+            Outcome::Wins { participant } => write!(f, "{} wins", participant),
+            Outcome::Cooperation {
+                above,
+                threshold,
+                after,
+            } => {
+                let side = if *above { "above" } else { "below" };
+                write!(f, "cooperation {} {}", side, threshold)?;
+                if *after > 0 {
+                    write!(f, " after {}", after)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl FromStr for Outcome {
+    type Err = String;
+
+    /// `a beats b`, `a wins`, or `cooperation above x` or `below x`,
+    /// optionally followed by `after n` to count only the rounds after the
+    /// `n`th, e.g. `cooperation below 0.2 after round 80`
+    fn from_str(s: &str) -> Result<Outcome, String> {
+        let words: Vec<&str> = s.split_whitespace().collect();
+        let number = |word: &str| word.parse::<f64>().map_err(|e| format!("'{}': {}", s, e));
+        match words[..] {
+            [winner, "beats", loser] => Ok(Outcome::Beats {
+                winner: winner.to_owned(),
+                loser: loser.to_owned(),
+            }),
+            [participant, "wins"] => Ok(Outcome::Wins {
+                participant: participant.to_owned(),
+            }),
+            ["cooperation", side, threshold, ref rest @ ..]
+                if side == "above" || side == "below" =>
+            {
+                let after = match rest {
+                    [] => 0,
+                    ["after", n] | ["after", "round", n] => {
+                        n.parse().map_err(|e| format!("'{}': {}", s, e))?
+                    }
+                    _ => return Err(format!("'{}': expected after n to follow", s)),
+                };
+                let threshold = number(threshold)?;
+                if !(0.0..=1.0).contains(&threshold) {
+                    return Err(format!("'{}': {} is not a share", s, threshold));
+                }
+                Ok(Outcome::Cooperation {
+                    above: side == "above",
+                    threshold,
+                    after,
+                })
+            }
+            _ => Err(format!(
+                "'{}': expected a beats b, a wins, or cooperation above or below x",
+                s
+            )),
+        }
+    }
+}
+
+impl Outcome {
+    /// Whether every participant named is in `config`
+    pub fn check(&self, config: &SimConfig) -> Result<(), String> {
+        let names: Vec<&String> = match self {
+            Outcome::Beats { winner, loser } => vec![winner, loser],
+            Outcome::Wins { participant } => vec![participant],
+            Outcome::Cooperation { .. } => Vec::new(),
+        };
+        match names
+            .into_iter()
+            .find(|name| !config.participants.iter().any(|p| &&p.name == name))
+        {
+            Some(name) => Err(format!("{}: no participant named {}", self, name)),
+            None => Ok(()),
+        }
+    }
+
+    /// Whether the tournament of `config` that played `matches` showed it
+    pub fn holds(&self, config: &SimConfig, matches: &[MatchResult]) -> bool {
+        match self {
+            Outcome::Beats { winner, loser } => {
+                let (mut won, mut lost): (Score, Score) = (0.0, 0.0);
+                for result in matches {
+                    let (w, l) = match (result.blue.name.as_str(), result.red.name.as_str()) {
+                        (blue, red) if blue == winner && red == loser => {
+                            (&result.blue, &result.red)
+                        }
+                        (blue, red) if blue == loser && red == winner => {
+                            (&result.red, &result.blue)
+                        }
+                        _ => continue,
+                    };
+                    won += w.score;
+                    lost += l.score;
+                }
+                won > lost
+            }
+            Outcome::Wins { participant } => {
+                let standings = tournament::standings(config, matches);
+                match &standings[..] {
+                    [first, second, ..] => &first.name == participant && first.score > second.score,
+                    [first] => &first.name == participant,
+                    [] => false,
+                }
+            }
+            Outcome::Cooperation {
+                above,
+                threshold,
+                after,
+            } => {
+                let (mut cooperations, mut moves) = (0, 0);
+                for round in matches
+                    .iter()
+                    .flat_map(|result| &result.transcript)
+                    .filter(|round| round.round >= *after)
+                {
+                    for side in [&round.blue, &round.red] {
+                        cooperations += (side.executed == Action::COOPERATE) as usize;
+                        moves += 1;
+                    }
+                }
+                if moves == 0 {
+                    return false;
+                }
+                let share = cooperations as f64 / moves as f64;
+                if *above {
+                    share > *threshold
+                } else {
+                    share < *threshold
+                }
+            }
+        }
+    }
+}
+
+/// The seeds found to show every outcome asked for
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SeedSearch {
+    pub outcomes: Vec<Outcome>,
+
+    /// The first seed scanned
+    pub from: u64,
+
+    /// How many seeds were scanned, from `from` on
+    pub scanned: usize,
+
+    /// The seeds found, in order
+    pub seeds: Vec<u64>,
+}
+
+/// Play the round robin of `config` under each of `seeds` in turn, until
+/// `limit` of them have shown every one of `outcomes`
+///
+/// The config should already have been validated against `registry`, and
+/// the outcomes checked against it.
+pub async fn search(
+    config: &SimConfig,
+    registry: &StrategyRegistry,
+    outcomes: &[Outcome],
+    seeds: Range<u64>,
+    limit: usize,
+) -> SeedSearch {
+    let mut found = SeedSearch {
+        outcomes: outcomes.to_vec(),
+        from: seeds.start,
+        scanned: 0,
+        seeds: Vec::new(),
+    };
+    for seed in seeds {
+        if found.seeds.len() >= limit {
+            break;
+        }
+        let reports = tournament::round_robin(config, registry, seed, None).await;
+        // an interrupted tournament would be judged on part of its matches
+        if interrupt::interrupted() {
+            break;
+        }
+        let matches: Vec<MatchResult> = reports
+            .into_iter()
+            .filter_map(|report| report.result.ok())
+            .collect();
+        found.scanned += 1;
+        if outcomes
+            .iter()
+            .all(|outcome| outcome.holds(config, &matches))
+        {
+            found.seeds.push(seed);
+        }
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ParticipantConfig;
+
+    fn config() -> SimConfig {
+        SimConfig {
+            iterations: 10,
+            participants: vec![
+                ParticipantConfig::new("tft", "tit-for-tat"),
+                ParticipantConfig::new("alld", "always-defect"),
+                ParticipantConfig::new("random", "random"),
+            ],
+            ..SimConfig::default()
+        }
+    }
+
+    #[test]
+    fn outcomes_read_as_they_print() {
+        for text in [
+            "random beats tft",
+            "tft wins",
+            "cooperation below 0.2 after 80",
+        ] {
+            assert_eq!(text.parse::<Outcome>().unwrap().to_string(), text);
+        }
+        let after_round: Outcome = "cooperation above 0.5 after round 3".parse().unwrap();
+        assert_eq!(after_round.to_string(), "cooperation above 0.5 after 3");
+        assert!("cooperation below 2".parse::<Outcome>().is_err());
+        assert!("tft loses".parse::<Outcome>().is_err());
+        let stranger: Outcome = "nobody wins".parse().unwrap();
+        assert!(stranger.check(&config()).is_err());
+    }
+
+    #[actix_rt::test]
+    async fn the_seeds_found_replay_the_outcome() {
+        let (config, registry) = (config(), StrategyRegistry::new());
+        // always defect outscores tit for tat whatever the seed
+        let sure = vec!["alld beats tft".parse().unwrap()];
+        let found = search(&config, &registry, &sure, 0..10, 3).await;
+        assert_eq!((found.seeds, found.scanned), (vec![0, 1, 2], 3));
+        let never = vec!["tft beats alld".parse().unwrap()];
+        let found = search(&config, &registry, &never, 0..10, 3).await;
+        assert_eq!((found.seeds.len(), found.scanned), (0, 10));
+
+        let lucky: Vec<Outcome> = vec!["random beats tft".parse().unwrap()];
+        let found = search(&config, &registry, &lucky, 0..40, 2).await;
+        assert!(!found.seeds.is_empty());
+        for seed in found.seeds {
+            let matches: Vec<MatchResult> = tournament::round_robin(&config, &registry, seed, None)
+                .await
+                .into_iter()
+                .filter_map(|report| report.result.ok())
+                .collect();
+            assert!(lucky[0].holds(&config, &matches));
+        }
+    }
+}
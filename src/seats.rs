@@ -0,0 +1,301 @@
+//! Strategies played by people at a browser, for whole-class tournaments
+//!
+//! Name a participant's seat in a config as `strategy = "player:alice"`.
+//! `run --serve-ws` opens a seat for each of them, with a token of its own,
+//! and logs the link to give that player, `/player?token=...`. The page
+//! there connects to the WebSocket at `/play` with the token and asks its
+//! player for each move, in JSON text messages:
+//!
+//! 1. On connecting, the server sends `{"event":"welcome","seat":"alice"}`,
+//!    or `{"event":"refused",...}` for a token no seat has.
+//! 2. Before each round of each of the seat's matches, it sends what
+//!    happened in that match last round, as [`crate::remote`] does, with an
+//!    id to answer and the number of the match:
+//!    `{"event":"question","id":7,"game":2,"round":1,"own":"C","opponent":"D","payoff":1.0}`
+//! 3. The player answers `{"id":7,"action":"C"}`.
+//!
+//! Connecting again with a seat's token takes the seat over from the
+//! earlier connection, which is then asked nothing more, and is asked again
+//! whatever is still unanswered, so a player who drops off the network can
+//! come back. No one else sees the seat's name: spectators are shown the
+//! players by number.
+//!
+//! A seat no one answers for holds its matches up, unless the config has a
+//! `decision_timeout_ms`.
+
+use actix::prelude::*;
+use futures::channel::oneshot;
+use futures::future::BoxFuture;
+use rand::Rng;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, MutexGuard, OnceLock};
+
+use crate::game::Action;
+use crate::strategy::{History, Strategy};
+
+/// The scheme seats are named under, as in `player:alice`
+pub const SCHEME: &str = "player";
+
+/// A move a seat's player is asked for
+#[derive(Debug, Clone, Serialize)]
+pub struct Question {
+    /// What the answer goes back under
+    pub id: u64,
+
+    /// Which of the seat's matches it is for
+    pub game: u64,
+    pub round: usize,
+    pub own: Option<char>,
+    pub opponent: Option<char>,
+    pub payoff: Option<f64>,
+}
+
+impl Message for Question {
+    type Result = ();
+}
+
+struct Seat {
+    token: String,
+
+    /// Who is sitting there: which connection it is, and where it is asked
+    sitting: Option<(u64, Recipient<Question>)>,
+    unanswered: BTreeMap<u64, (Question, oneshot::Sender<Action>)>,
+}
+
+impl Seat {
+    /// Forget the questions whose matches stopped waiting for an answer
+    fn tidy(&mut self) {
+        self.unanswered
+            .retain(|_, (_, answer)| !answer.is_canceled());
+    }
+}
+
+static SEATS: OnceLock<Mutex<BTreeMap<String, Seat>>> = OnceLock::new();
+
+/// Numbers questions, connections, and games, so none share one
+static NEXT: AtomicU64 = AtomicU64::new(1);
+
+fn seats() -> MutexGuard<'static, BTreeMap<String, Seat>> {
+    SEATS
+        .get_or_init(Default::default)
+        .lock()
+        .expect("unpoisoned seats")
+}
+
+fn next() -> u64 {
+    NEXT.fetch_add(1, Ordering::Relaxed)
+}
+
+/// The seat `strategy` is played from, if it is a player's
+pub fn seat_of(strategy: &str) -> Option<&str> {
+    strategy.strip_prefix(SCHEME)?.strip_prefix(':')
+}
+
+/// Open a seat for each of `names` with a new token, answered with the
+/// seats and their tokens
+///
+/// A seat that was open already is opened afresh: its old token no longer
+/// works, and the matches waiting on it forfeit.
+pub fn open<'a>(names: impl IntoIterator<Item = &'a str>) -> Vec<(String, String)> {
+    let mut seats = seats();
+    let mut rng = rand::thread_rng();
+    names
+        .into_iter()
+        .map(|name| {
+            let token = format!("{:016x}{:016x}", rng.gen::<u64>(), rng.gen::<u64>());
+            let seat = Seat {
+                token: token.clone(),
+                sitting: None,
+                unanswered: BTreeMap::new(),
+            };
+            seats.insert(name.to_owned(), seat);
+            (name.to_owned(), token)
+        })
+        .collect()
+}
+
+/// The seat `token` was given for, if any
+pub fn find(token: &str) -> Option<String> {
+    seats()
+        .iter()
+        .find(|(_, seat)| seat.token == token)
+        .map(|(name, _)| name.clone())
+}
+
+/// Sit `player` at the seat `token` was given for, in place of whoever was
+/// there, and ask it whatever is unanswered
+///
+/// Answered with the seat, and which connection this is for [`leave`].
+pub fn sit(token: &str, player: Recipient<Question>) -> Option<(String, u64)> {
+    let mut seats = seats();
+    let (name, seat) = seats.iter_mut().find(|(_, seat)| seat.token == token)?;
+    seat.tidy();
+    for (question, _) in seat.unanswered.values() {
+        let _ = player.do_send(question.clone());
+    }
+    let sitting = next();
+    seat.sitting = Some((sitting, player));
+    Some((name.clone(), sitting))
+}
+
+/// Leave the seat `name`, if connection `sitting` still has it
+pub fn leave(name: &str, sitting: u64) {
+    if let Some(seat) = seats().get_mut(name) {
+        if matches!(seat.sitting, Some((current, _)) if current == sitting) {
+            seat.sitting = None;
+        }
+    }
+}
+
+/// Answer question `id` put to the seat `name` with `action`
+///
+/// Answered with whether the question was waiting for an answer.
+pub fn answer(name: &str, id: u64, action: Action) -> bool {
+    let answer = seats()
+        .get_mut(name)
+        .and_then(|seat| seat.unanswered.remove(&id));
+    match answer {
+        Some((_, answer)) => answer.send(action).is_ok(),
+        None => false,
+    }
+}
+
+/// Put `question` to whoever sits at the seat `name`, now or when they
+/// sit down
+fn ask(name: &str, question: Question) -> Result<oneshot::Receiver<Action>, String> {
+    let mut seats = seats();
+    let seat = seats.get_mut(name).ok_or_else(|| {
+        format!(
+            "no seat has been opened for '{}'; serve it with run --serve-ws",
+            name
+        )
+    })?;
+    seat.tidy();
+    if let Some((_, player)) = &seat.sitting {
+        let _ = player.do_send(question.clone());
+    }
+    let (tx, rx) = oneshot::channel();
+    seat.unanswered.insert(question.id, (question, tx));
+    Ok(rx)
+}
+
+pub struct PlayerStrategy {
+    name: String,
+    seat: String,
+
+    /// The number its questions go under, one per match
+    game: u64,
+}
+
+impl PlayerStrategy {
+    /// A strategy played by whoever sits at the seat `seat`
+    pub fn new(seat: &str) -> Result<PlayerStrategy, String> {
+        if seat.is_empty() {
+            return Err(format!("a player needs a seat, e.g. {}:alice", SCHEME));
+        }
+        Ok(PlayerStrategy {
+            name: format!("{}:{}", SCHEME, seat),
+            seat: seat.to_owned(),
+            game: next(),
+        })
+    }
+}
+
+impl Strategy for PlayerStrategy {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Never called: prisoners use [`Strategy::choose_async`] instead
+    fn choose(&mut self, _history: &History) -> Action {
+        panic!("{}: players only decide asynchronously", self.name)
+    }
+
+    fn choose_async(
+        &mut self,
+        history: &History,
+    ) -> Option<BoxFuture<'static, Result<Action, String>>> {
+        let last = history.last();
+        let question = Question {
+            id: next(),
+            game: self.game,
+            round: history.round(),
+            own: last.map(|round| round.own.letter()),
+            opponent: last.map(|round| round.opponent.letter()),
+            payoff: last.map(|round| round.outcome.amount),
+        };
+        let asked = ask(&self.seat, question);
+        let seat = self.seat.clone();
+        Some(Box::pin(async move {
+            asked?
+                .await
+                .map_err(|_| format!("the seat '{}' was opened afresh", seat))
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::channel::mpsc;
+    use futures::StreamExt;
+
+    /// Passes on the questions put to it
+    struct Player(mpsc::UnboundedSender<Question>);
+
+    impl Actor for Player {
+        type Context = Context<Self>;
+    }
+
+    impl Handler<Question> for Player {
+        type Result = ();
+
+        fn handle(&mut self, question: Question, _ctx: &mut Context<Self>) {
+            let _ = self.0.unbounded_send(question);
+        }
+    }
+
+    fn player() -> (Recipient<Question>, mpsc::UnboundedReceiver<Question>) {
+        let (tx, rx) = mpsc::unbounded();
+        (Player(tx).start().recipient(), rx)
+    }
+
+    #[actix_rt::test]
+    async fn questions_wait_for_the_player_to_sit_down() {
+        let (_, token) = open(vec!["ada"]).remove(0);
+        assert_eq!(find(&token).as_deref(), Some("ada"));
+        let mut strategy = PlayerStrategy::new("ada").unwrap();
+        let asked = strategy.choose_async(&History::new()).unwrap();
+
+        let (first, mut questions) = player();
+        let (seat, sitting) = sit(&token, first).unwrap();
+        assert_eq!(seat, "ada");
+        let question = questions.next().await.unwrap();
+        assert_eq!(question.own, None);
+
+        // whoever sits down next is asked again what is still unanswered
+        let (second, mut questions) = player();
+        sit(&token, second).unwrap();
+        leave("ada", sitting);
+        assert_eq!(questions.next().await.unwrap().id, question.id);
+
+        assert!(answer("ada", question.id, Action::DEFECT));
+        assert_eq!(asked.await, Ok(Action::DEFECT));
+        assert!(!answer("ada", question.id, Action::DEFECT));
+    }
+
+    #[actix_rt::test]
+    async fn only_open_seats_take_players() {
+        let (player, _) = player();
+        assert!(sit("not a token", player).is_none());
+        assert_eq!(seat_of("player:grace"), Some("grace"));
+        assert_eq!(seat_of("players"), None);
+        assert!(PlayerStrategy::new("").is_err());
+
+        let mut strategy = PlayerStrategy::new("nobody").unwrap();
+        let asked = strategy.choose_async(&History::new()).unwrap();
+        assert!(asked.await.unwrap_err().contains("no seat"));
+    }
+}
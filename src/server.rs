@@ -0,0 +1,688 @@
+//! A small simulation service over HTTP
+//!
+//! [`serve`] accepts jobs as JSON and runs each one under its own actor,
+//! whose matches each get their own referee, as they would from the command
+//! line. Every endpoint answers with JSON:
+//!
+//! - `POST /tournaments` with a config, as in the TOML files but written as
+//!   JSON, starts a round robin and answers `202 Accepted` with the job's
+//!   [`JobStatus`].
+//! - `POST /matches` does the same for a config with exactly two
+//!   participants.
+//! - `GET /jobs` lists every job, and `GET /jobs/{id}` shows one, including
+//!   how many of its matches have finished.
+//! - `GET /jobs/{id}/result` returns the [`TournamentRecord`] once the job
+//!   has finished, and `409 Conflict` until then.
+//! - `GET /jobs/{id}/watch` streams the job as it plays, one JSON object
+//!   per line: every [`GameEvent`] of its matches from then on, tagged by
+//!   `event` as in `run --events`; a `standings` event with the standings
+//!   so far after each match; and a `finished` event with the final
+//!   standings, after which the stream ends. A finished job's stream holds
+//!   only the `finished` event.
+//! - `GET /healthz` answers `200 OK` while the server is up, and
+//!   `GET /readyz` answers with its [`Readiness`]: `200 OK` while it takes
+//!   jobs, and `503 Service Unavailable` once it is stopping.
+//!
+//! A config without a seed is given a random one, reported in the status.
+//! There is no authentication, and jobs are kept until the server stops.
+//! The service speaks only HTTP and JSON; there is no gRPC interface, so
+//! watching a match or a tournament is a streamed response rather than a
+//! server-streaming RPC.
+
+use actix::prelude::*;
+use actix_web::dev::Server;
+use actix_web::web::Bytes;
+use actix_web::{web, App, HttpResponse, HttpServer};
+use futures::channel::mpsc;
+use futures::StreamExt;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io;
+use std::net::ToSocketAddrs;
+use std::sync::{Arc, Mutex};
+use tracing::info;
+
+use crate::config::SimConfig;
+use crate::events::{self, GameEvent};
+use crate::interrupt;
+use crate::referee::{MatchResult, Report};
+use crate::registry::StrategyRegistry;
+use crate::schema;
+use crate::tournament::{self, TournamentRecord};
+
+/// What a job was submitted as
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum JobKind {
+    Match,
+    Tournament,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum JobState {
+    Running,
+    Finished,
+}
+
+/// A job's progress, as returned by `GET /jobs/{id}`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobStatus {
+    pub id: u64,
+    pub kind: JobKind,
+    pub state: JobState,
+    pub seed: u64,
+
+    /// Matches in the job, and how many of them have finished
+    pub matches: usize,
+    pub finished: usize,
+}
+
+struct GetStatus;
+
+impl Message for GetStatus {
+    type Result = JobStatus;
+}
+
+struct GetResult;
+
+/// A job's matches being played, and those waiting to start
+struct GetLoad;
+
+impl Message for GetLoad {
+    type Result = (usize, usize);
+}
+
+impl Message for GetResult {
+    type Result = Option<TournamentRecord>;
+}
+
+/// Lines of a job's stream, as `GET /jobs/{id}/watch` sends them
+type Lines = mpsc::UnboundedReceiver<Bytes>;
+
+/// Start streaming a job's events
+struct Watch;
+
+impl Message for Watch {
+    type Result = Lines;
+}
+
+/// Runs one submitted config and keeps its result
+struct Job {
+    config: SimConfig,
+    registry: Arc<StrategyRegistry>,
+    status: JobStatus,
+    record: Option<TournamentRecord>,
+
+    /// The matches finished so far, for the standings sent to watchers
+    played: Vec<MatchResult>,
+    watchers: Vec<mpsc::UnboundedSender<Bytes>>,
+}
+
+impl Job {
+    /// Send `line` to everyone watching, forgetting those who have left
+    fn broadcast<T: Serialize>(&mut self, line: &T) {
+        if self.watchers.is_empty() {
+            return;
+        }
+        let mut line = serde_json::to_vec(line).expect("serializable event");
+        line.push(b'\n');
+        let line = Bytes::from(line);
+        self.watchers
+            .retain(|watcher| watcher.unbounded_send(line.clone()).is_ok());
+    }
+
+    fn finished_event(record: &TournamentRecord) -> serde_json::Value {
+        serde_json::json!({ "event": "finished", "standings": record.standings })
+    }
+}
+
+impl Actor for Job {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Context<Self>) {
+        let config = self.config.clone();
+        let registry = self.registry.clone();
+        let seed = self.status.seed;
+        let reporter = ctx.address().recipient();
+        let bus = ctx.address().recipient();
+        let play = events::publishing_to(bus, async move {
+            tournament::round_robin(&config, &registry, seed, Some(reporter)).await
+        });
+        ctx.spawn(play.into_actor(self).map(move |reports, act, _ctx| {
+            let mut record = TournamentRecord::new(&act.config, seed, reports);
+            record.describe_strategies(&act.registry);
+            act.broadcast(&Job::finished_event(&record));
+            // the streams end as their senders are dropped
+            act.watchers.clear();
+            act.record = Some(record);
+            act.status.state = JobState::Finished;
+            info!(job = act.status.id, "finished");
+        }));
+    }
+}
+
+impl Handler<Report> for Job {
+    type Result = ();
+
+    fn handle(&mut self, _msg: Report, _ctx: &mut Context<Self>) -> Self::Result {
+        self.status.finished += 1;
+    }
+}
+
+impl Handler<GameEvent> for Job {
+    type Result = ();
+
+    fn handle(&mut self, event: GameEvent, _ctx: &mut Context<Self>) -> Self::Result {
+        self.broadcast(&event);
+        if let GameEvent::MatchFinished(result) = event {
+            self.played.push(*result);
+            if !self.watchers.is_empty() {
+                let standings = tournament::standings(&self.config, &self.played);
+                self.broadcast(&serde_json::json!({
+                    "event": "standings",
+                    "finished": self.played.len(),
+                    "matches": self.status.matches,
+                    "standings": standings,
+                }));
+            }
+        }
+    }
+}
+
+impl Handler<Watch> for Job {
+    type Result = MessageResult<Watch>;
+
+    fn handle(&mut self, _msg: Watch, _ctx: &mut Context<Self>) -> Self::Result {
+        let (watcher, lines) = mpsc::unbounded();
+        self.watchers.push(watcher);
+        if let Some(record) = &self.record {
+            let finished = Job::finished_event(record);
+            self.broadcast(&finished);
+            self.watchers.clear();
+        }
+        MessageResult(lines)
+    }
+}
+
+impl Handler<GetStatus> for Job {
+    type Result = MessageResult<GetStatus>;
+
+    fn handle(&mut self, _msg: GetStatus, _ctx: &mut Context<Self>) -> Self::Result {
+        MessageResult(self.status.clone())
+    }
+}
+
+impl Handler<GetLoad> for Job {
+    type Result = MessageResult<GetLoad>;
+
+    /// A round robin that keeps memories plays one repetition after
+    /// another, so only the current repetition's matches are in play
+    fn handle(&mut self, _msg: GetLoad, _ctx: &mut Context<Self>) -> Self::Result {
+        let JobStatus {
+            state,
+            matches,
+            finished,
+            ..
+        } = self.status;
+        if state == JobState::Finished {
+            return MessageResult((0, 0));
+        }
+        let started = if self.config.remember_opponents && self.config.repetitions > 0 {
+            let repetition = matches / self.config.repetitions;
+            matches.min((finished / repetition.max(1) + 1) * repetition)
+        } else {
+            matches
+        };
+        MessageResult((started - finished, matches - started))
+    }
+}
+
+impl Handler<GetResult> for Job {
+    type Result = MessageResult<GetResult>;
+
+    fn handle(&mut self, _msg: GetResult, _ctx: &mut Context<Self>) -> Self::Result {
+        MessageResult(self.record.clone())
+    }
+}
+
+/// Whether the server takes jobs, and how busy it is, as returned by
+/// `GET /readyz`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Readiness {
+    pub ready: bool,
+
+    /// Jobs not yet finished
+    pub running: usize,
+
+    /// Matches being played, and matches waiting for earlier ones to end
+    pub active_matches: usize,
+    pub queued_matches: usize,
+}
+
+/// Every job submitted so far
+#[derive(Default)]
+struct Jobs {
+    next: u64,
+    jobs: BTreeMap<u64, Addr<Job>>,
+}
+
+struct State {
+    registry: Arc<StrategyRegistry>,
+
+    /// Where jobs run, so they share the workers set up by
+    /// [`tournament::use_threads`]
+    arbiter: Arbiter,
+    jobs: Mutex<Jobs>,
+}
+
+#[derive(Serialize)]
+struct ErrorBody {
+    error: String,
+}
+
+fn error(mut response: actix_web::dev::HttpResponseBuilder, error: String) -> HttpResponse {
+    response.json(ErrorBody { error })
+}
+
+fn submit(state: &State, kind: JobKind, config: SimConfig) -> HttpResponse {
+    if interrupt::interrupted() {
+        return error(
+            HttpResponse::ServiceUnavailable(),
+            "the server is stopping".to_owned(),
+        );
+    }
+    if let Err(e) = config.validate(&state.registry) {
+        return error(HttpResponse::BadRequest(), e.to_string());
+    }
+    if kind == JobKind::Match && config.participants.len() != 2 {
+        return error(
+            HttpResponse::BadRequest(),
+            "a match needs exactly two participants".to_owned(),
+        );
+    }
+
+    let mut jobs = state.jobs.lock().expect("unpoisoned jobs");
+    jobs.next += 1;
+    let status = JobStatus {
+        id: jobs.next,
+        kind,
+        state: JobState::Running,
+        seed: config.seed.unwrap_or_else(|| rand::thread_rng().gen()),
+        matches: tournament::schedule(&config).1.len(),
+        finished: 0,
+    };
+    info!(
+        job = status.id,
+        participants = config.participants.len(),
+        "submitted"
+    );
+    let job = Job {
+        config,
+        registry: state.registry.clone(),
+        status: status.clone(),
+        record: None,
+        played: Vec::new(),
+        watchers: Vec::new(),
+    };
+    let addr = Job::start_in_arbiter(&state.arbiter, move |_| job);
+    jobs.jobs.insert(status.id, addr);
+    HttpResponse::Accepted().json(status)
+}
+
+async fn create_tournament(state: web::Data<State>, config: web::Json<SimConfig>) -> HttpResponse {
+    submit(&state, JobKind::Tournament, config.into_inner())
+}
+
+async fn create_match(state: web::Data<State>, config: web::Json<SimConfig>) -> HttpResponse {
+    submit(&state, JobKind::Match, config.into_inner())
+}
+
+fn job(state: &State, id: u64) -> Option<Addr<Job>> {
+    state
+        .jobs
+        .lock()
+        .expect("unpoisoned jobs")
+        .jobs
+        .get(&id)
+        .cloned()
+}
+
+fn not_found(id: u64) -> HttpResponse {
+    error(HttpResponse::NotFound(), format!("no job {}", id))
+}
+
+async fn list_jobs(state: web::Data<State>) -> HttpResponse {
+    let jobs: Vec<Addr<Job>> = state
+        .jobs
+        .lock()
+        .expect("unpoisoned jobs")
+        .jobs
+        .values()
+        .cloned()
+        .collect();
+    let mut statuses = Vec::with_capacity(jobs.len());
+    for job in jobs {
+        if let Ok(status) = job.send(GetStatus).await {
+            statuses.push(status);
+        }
+    }
+    HttpResponse::Ok().json(statuses)
+}
+
+async fn job_status(state: web::Data<State>, id: web::Path<u64>) -> HttpResponse {
+    let id = id.into_inner();
+    match job(&state, id) {
+        Some(job) => match job.send(GetStatus).await {
+            Ok(status) => HttpResponse::Ok().json(status),
+            Err(e) => error(HttpResponse::InternalServerError(), e.to_string()),
+        },
+        None => not_found(id),
+    }
+}
+
+async fn job_result(state: web::Data<State>, id: web::Path<u64>) -> HttpResponse {
+    let id = id.into_inner();
+    match job(&state, id) {
+        Some(job) => match job.send(GetResult).await {
+            Ok(Some(record)) => HttpResponse::Ok().json(schema::stamp(&record)),
+            Ok(None) => error(
+                HttpResponse::Conflict(),
+                format!("job {} is still running", id),
+            ),
+            Err(e) => error(HttpResponse::InternalServerError(), e.to_string()),
+        },
+        None => not_found(id),
+    }
+}
+
+async fn watch_job(state: web::Data<State>, id: web::Path<u64>) -> HttpResponse {
+    let id = id.into_inner();
+    match job(&state, id) {
+        Some(job) => match job.send(Watch).await {
+            Ok(lines) => HttpResponse::Ok()
+                .content_type("application/x-ndjson")
+                .streaming(lines.map(Ok::<_, actix_web::Error>)),
+            Err(e) => error(HttpResponse::InternalServerError(), e.to_string()),
+        },
+        None => not_found(id),
+    }
+}
+
+async fn health() -> HttpResponse {
+    HttpResponse::Ok().json(serde_json::json!({ "status": "ok" }))
+}
+
+async fn readiness(state: web::Data<State>) -> HttpResponse {
+    let jobs: Vec<Addr<Job>> = state
+        .jobs
+        .lock()
+        .expect("unpoisoned jobs")
+        .jobs
+        .values()
+        .cloned()
+        .collect();
+    let mut readiness = Readiness {
+        ready: !interrupt::interrupted(),
+        running: 0,
+        active_matches: 0,
+        queued_matches: 0,
+    };
+    for job in jobs {
+        if let Ok((active, queued)) = job.send(GetLoad).await {
+            if active + queued > 0 {
+                readiness.running += 1;
+            }
+            readiness.active_matches += active;
+            readiness.queued_matches += queued;
+        }
+    }
+    if readiness.ready {
+        HttpResponse::Ok().json(readiness)
+    } else {
+        HttpResponse::ServiceUnavailable().json(readiness)
+    }
+}
+
+/// Serve the API at `address`, e.g. `127.0.0.1:8080`, creating strategies
+/// from `registry`
+///
+/// Must be called from inside a running `System`; jobs run on the current
+/// arbiter. The registry should only hold strategies that are safe to run
+/// for anyone who can reach the server: see [`StrategyRegistry::clear_loaders`].
+/// The server, and the system with it, stops on Ctrl-C.
+pub fn serve<A: ToSocketAddrs>(address: A, registry: StrategyRegistry) -> io::Result<Server> {
+    let state = state(registry);
+    let server = HttpServer::new(move || App::new().app_data(state.clone()).configure(routes))
+        .workers(1)
+        .system_exit()
+        .bind(address)?;
+    for address in server.addrs() {
+        info!(%address, "serving the API");
+    }
+    Ok(server.run())
+}
+
+fn state(registry: StrategyRegistry) -> web::Data<State> {
+    web::Data::new(State {
+        registry: Arc::new(registry),
+        arbiter: Arbiter::current(),
+        jobs: Mutex::new(Jobs::default()),
+    })
+}
+
+fn routes(config: &mut web::ServiceConfig) {
+    config
+        .route("/tournaments", web::post().to(create_tournament))
+        .route("/matches", web::post().to(create_match))
+        .route("/jobs", web::get().to(list_jobs))
+        .route("/jobs/{id}", web::get().to(job_status))
+        .route("/jobs/{id}/result", web::get().to(job_result))
+        .route("/jobs/{id}/watch", web::get().to(watch_job))
+        .route("/healthz", web::get().to(health))
+        .route("/readyz", web::get().to(readiness));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ParticipantConfig;
+    use actix_web::http::StatusCode;
+    use actix_web::test;
+    use serde_json::Value;
+    use std::time::Duration;
+
+    fn config(participants: &[(&str, &str)]) -> SimConfig {
+        SimConfig {
+            iterations: 10,
+            seed: Some(4),
+            participants: participants
+                .iter()
+                .map(|(name, strategy)| ParticipantConfig::new(name, strategy))
+                .collect(),
+            ..SimConfig::default()
+        }
+    }
+
+    #[actix_rt::test]
+    async fn jobs_are_submitted_then_their_results_fetched() {
+        let mut app = test::init_service(
+            App::new()
+                .app_data(state(StrategyRegistry::new()))
+                .configure(routes),
+        )
+        .await;
+        let trio = config(&[
+            ("tft", "tit-for-tat"),
+            ("allc", "always-cooperate"),
+            ("alld", "always-defect"),
+        ]);
+
+        let submitted = test::TestRequest::post()
+            .uri("/tournaments")
+            .set_json(&trio)
+            .to_request();
+        let response = test::call_service(&mut app, submitted).await;
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+        let status: JobStatus = test::read_body_json(response).await;
+        assert_eq!(
+            (status.id, status.kind, status.seed),
+            (1, JobKind::Tournament, 4)
+        );
+        assert_eq!(status.matches, 3);
+
+        let status = loop {
+            let request = test::TestRequest::get().uri("/jobs/1").to_request();
+            let status: JobStatus = test::read_response_json(&mut app, request).await;
+            if status.state == JobState::Finished {
+                break status;
+            }
+            tokio::time::delay_for(Duration::from_millis(10)).await;
+        };
+        assert_eq!(status.finished, 3);
+
+        let request = test::TestRequest::get().uri("/jobs/1/result").to_request();
+        let record: Value = test::read_response_json(&mut app, request).await;
+        assert_eq!(record[schema::VERSION_FIELD], schema::SCHEMA_VERSION);
+        assert_eq!(record["seed"], 4);
+        assert_eq!(record["matches"].as_array().unwrap().len(), 3);
+        assert_eq!(record["standings"][0]["name"], "alld");
+
+        let request = test::TestRequest::get().uri("/jobs").to_request();
+        let jobs: Vec<JobStatus> = test::read_response_json(&mut app, request).await;
+        assert_eq!(jobs.len(), 1);
+    }
+
+    #[actix_rt::test]
+    async fn watchers_are_streamed_rounds_and_standings() {
+        let mut app = test::init_service(
+            App::new()
+                .app_data(state(StrategyRegistry::new()))
+                .configure(routes),
+        )
+        .await;
+        // slow enough that the watcher joins before the first round
+        let mut pair = config(&[("tft", "tit-for-tat"), ("alld", "always-defect")]);
+        pair.delay_ms = Some(20);
+        let request = test::TestRequest::post()
+            .uri("/matches")
+            .set_json(&pair)
+            .to_request();
+        let response = test::call_service(&mut app, request).await;
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+
+        let request = test::TestRequest::get().uri("/jobs/1/watch").to_request();
+        let response = test::call_service(&mut app, request).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = test::read_body(response).await;
+        let lines: Vec<Value> = std::str::from_utf8(&body)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        let events: Vec<&str> = lines
+            .iter()
+            .map(|line| line["event"].as_str().unwrap())
+            .collect();
+        assert_eq!(events.iter().filter(|&&e| e == "round-played").count(), 10);
+        assert_eq!(
+            events[events.len() - 3..],
+            ["match-finished", "standings", "finished"]
+        );
+        let standings = &lines[lines.len() - 2];
+        assert_eq!(
+            (
+                standings["finished"].as_u64(),
+                standings["matches"].as_u64()
+            ),
+            (Some(1), Some(1))
+        );
+        assert_eq!(lines[lines.len() - 1]["standings"][0]["name"], "alld");
+
+        // once the job has finished, there is only its end to watch
+        let request = test::TestRequest::get().uri("/jobs/1/watch").to_request();
+        let body = test::read_body(test::call_service(&mut app, request).await).await;
+        let finished: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(finished["event"], "finished");
+    }
+
+    #[actix_rt::test]
+    async fn readiness_counts_the_matches_in_play_and_waiting() {
+        let mut app = test::init_service(
+            App::new()
+                .app_data(state(StrategyRegistry::new()))
+                .configure(routes),
+        )
+        .await;
+        let request = test::TestRequest::get().uri("/healthz").to_request();
+        let response = test::call_service(&mut app, request).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let request = test::TestRequest::get().uri("/readyz").to_request();
+        let idle: Readiness = test::read_response_json(&mut app, request).await;
+        assert!(idle.ready);
+        assert_eq!(
+            (idle.running, idle.active_matches, idle.queued_matches),
+            (0, 0, 0)
+        );
+
+        // long enough that no match ends before the server is asked
+        let mut remembering = config(&[
+            ("tft", "tit-for-tat"),
+            ("allc", "always-cooperate"),
+            ("alld", "always-defect"),
+        ]);
+        remembering.iterations = 1_000_000;
+        remembering.repetitions = 2;
+        remembering.remember_opponents = true;
+        let request = test::TestRequest::post()
+            .uri("/tournaments")
+            .set_json(&remembering)
+            .to_request();
+        let response = test::call_service(&mut app, request).await;
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+
+        let request = test::TestRequest::get().uri("/readyz").to_request();
+        let busy: Readiness = test::read_response_json(&mut app, request).await;
+        assert!(busy.ready);
+        assert_eq!(
+            (busy.running, busy.active_matches, busy.queued_matches),
+            (1, 3, 3)
+        );
+    }
+
+    #[actix_rt::test]
+    async fn bad_jobs_are_refused() {
+        let mut app = test::init_service(
+            App::new()
+                .app_data(state(StrategyRegistry::new()))
+                .configure(routes),
+        )
+        .await;
+        let crowd = config(&[
+            ("tft", "tit-for-tat"),
+            ("allc", "always-cooperate"),
+            ("alld", "always-defect"),
+        ]);
+        let request = test::TestRequest::post()
+            .uri("/matches")
+            .set_json(&crowd)
+            .to_request();
+        let response = test::call_service(&mut app, request).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let unknown = config(&[("tft", "tit-for-tat"), ("who", "nobody-knows")]);
+        let request = test::TestRequest::post()
+            .uri("/matches")
+            .set_json(&unknown)
+            .to_request();
+        let response = test::call_service(&mut app, request).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        for uri in &["/jobs/7", "/jobs/7/result", "/jobs/7/watch"] {
+            let request = test::TestRequest::get().uri(uri).to_request();
+            let response = test::call_service(&mut app, request).await;
+            assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        }
+    }
+}
@@ -0,0 +1,422 @@
+//! Spatial games
+//!
+//! Every node of a [`Topology`] holds one prisoner, which plays a match
+//! against each of its neighbours every generation. Each node then adopts
+//! the strategy of the highest scorer in its neighbourhood, itself included,
+//! so cooperators survive where they cluster together. With `relatedness`
+//! in the config, a node also counts that share of what each neighbour of
+//! its own strategy scores.
+//!
+//! Under [`Imitation::Fermi`], imitation is stochastic instead: each node
+//! looks at one neighbour drawn at random and copies it with a probability
+//! that rises with how much better the neighbour did, as the Fermi function
+//! of [`fermi`] has it, so that even a worse strategy is sometimes copied.
+//!
+//! [`compare`] plays one pool on several topologies, from the same start,
+//! for setting a well-mixed population beside structured ones.
+//!
+//! With `perceived_temptation` in the config, each node draws its own
+//! temptation at the start and counts its score in those payoffs, whatever
+//! strategy it comes to hold.
+//!
+//! A run can be written out as numbered snapshots, one file per frame, for
+//! stitching into an animation of cooperation spreading or dying out; see
+//! [`write_snapshots`].
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::config::{ParticipantConfig, SimConfig};
+use crate::game::Score;
+use crate::interrupt;
+use crate::referee::PlayerSummary;
+use crate::registry::StrategyRegistry;
+use crate::topology::{Lattice, Topology};
+use crate::tournament;
+
+/// The strategy at every node at the start of each generation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpatialRecord {
+    pub config: SimConfig,
+    pub seed: u64,
+
+    /// The interaction graph, lower node first
+    pub edges: Vec<(usize, usize)>,
+
+    /// How nodes took up their neighbours' strategies
+    #[serde(default)]
+    pub imitation: Imitation,
+
+    /// Indexes into `config.participants`, one per node, for each generation
+    /// and then after the last generation
+    pub states: Vec<Vec<usize>>,
+}
+
+/// How a node takes up its next strategy from its neighbourhood
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(tag = "rule", rename_all = "kebab-case")]
+pub enum Imitation {
+    /// Copy the highest scorer in the neighbourhood, the node included
+    #[default]
+    Best,
+
+    /// Copy a neighbour drawn at random with the probability [`fermi`]
+    /// gives, at this selection temperature
+    Fermi { temperature: f64 },
+}
+
+/// The probability that a node which scored `own` copies a neighbour which
+/// scored `neighbour`, at selection `temperature`
+///
+/// An even score is a coin toss. The lower the temperature, the more
+/// surely a better neighbour is copied and a worse one isn't; the higher,
+/// the closer every choice is to a coin toss.
+pub fn fermi(own: Score, neighbour: Score, temperature: f64) -> f64 {
+    1.0 / (1.0 + ((own - neighbour) / temperature).exp())
+}
+
+impl SpatialRecord {
+    /// Every `stride`th state, from the first, with its generation, and the
+    /// last state whatever the stride, as the frames of an animation
+    pub fn frames(&self, stride: usize) -> Vec<(usize, &[usize])> {
+        let last = self.states.len().saturating_sub(1);
+        self.states
+            .iter()
+            .enumerate()
+            .filter(|&(generation, _)| generation % stride.max(1) == 0 || generation == last)
+            .map(|(generation, state)| (generation, state.as_slice()))
+            .collect()
+    }
+
+    /// Each participant's share of the nodes in `generation`, in the
+    /// order of `config.participants`
+    pub fn shares(&self, generation: usize) -> Vec<f64> {
+        let state = &self.states[generation];
+        let mut shares = vec![0.0; self.config.participants.len()];
+        for &s in state {
+            shares[s] += 1.0 / state.len() as f64;
+        }
+        shares
+    }
+
+    /// The first generation in which every node held the same strategy,
+    /// if any did
+    pub fn fixed_at(&self) -> Option<usize> {
+        self.states
+            .iter()
+            .position(|state| state.iter().all(|&s| s == state[0]))
+    }
+}
+
+/// One topology's run in a [`Comparison`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TopologyRun {
+    pub topology: String,
+
+    /// Neighbours per node, on average
+    pub mean_degree: f64,
+
+    pub record: SpatialRecord,
+}
+
+/// The same pool played on several topologies
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Comparison {
+    pub seed: u64,
+    pub nodes: usize,
+    pub generations: usize,
+
+    /// One per topology, in the order played
+    pub runs: Vec<TopologyRun>,
+}
+
+/// The file for frame `frame` in `dir`, numbered from 0 without gaps so
+/// that tools such as ffmpeg can read them as a sequence
+pub fn frame_path(dir: &Path, frame: usize, extension: &str) -> PathBuf {
+    dir.join(format!("frame-{:04}.{}", frame, extension))
+}
+
+/// One frame of a spatial run, as written by [`write_snapshots`]
+#[derive(Debug, Clone, Serialize)]
+pub struct Snapshot<'a> {
+    pub generation: usize,
+
+    /// The grid, for a lattice run, whose nodes are numbered row by row
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lattice: Option<Lattice>,
+
+    /// The participant at each node
+    pub nodes: Vec<&'a str>,
+}
+
+/// Write every `stride`th generation of `record` to `dir`, created if need
+/// be, as `frame-0000.json`, `frame-0001.json`, and so on, returning how
+/// many frames were written
+pub fn write_snapshots(
+    record: &SpatialRecord,
+    lattice: Option<Lattice>,
+    dir: &Path,
+    stride: usize,
+) -> io::Result<usize> {
+    fs::create_dir_all(dir)?;
+    let frames = record.frames(stride);
+    for (frame, &(generation, state)) in frames.iter().enumerate() {
+        let snapshot = Snapshot {
+            generation,
+            lattice,
+            nodes: state
+                .iter()
+                .map(|&s| record.config.participants[s].name.as_str())
+                .collect(),
+        };
+        let json = serde_json::to_string(&snapshot).map_err(io::Error::other)?;
+        fs::write(frame_path(dir, frame, "json"), json + "\n")?;
+    }
+    Ok(frames.len())
+}
+
+/// Run `generations` generations on `topology`, each node starting with
+/// its participant in `start`, or one chosen uniformly at random, and
+/// imitating its neighbours by `imitation`
+///
+/// Under the Fermi rule, a node's score is the points per round it made in
+/// each of its matches, summed, so that the temperature is on the scale of
+/// the payoffs. The config should already have been validated against
+/// `registry`. The same seed and topology replay the same run.
+pub async fn run_spatial(
+    config: &SimConfig,
+    registry: &StrategyRegistry,
+    seed: u64,
+    topology: &dyn Topology,
+    generations: usize,
+    imitation: Imitation,
+    start: Option<&[usize]>,
+) -> SpatialRecord {
+    let mut seeds = StdRng::seed_from_u64(seed);
+    let mut rng = StdRng::seed_from_u64(seeds.gen());
+    let species = config.participants.len();
+    let mut state: Vec<usize> = match start {
+        Some(start) => start.to_vec(),
+        None => (0..topology.nodes())
+            .map(|_| rng.gen_range(0, species))
+            .collect(),
+    };
+    // each node sees the same temptation whatever strategy it takes up
+    let temptations: Vec<Option<Score>> = (0..state.len())
+        .map(|_| config.draw_temptation(&mut rng))
+        .collect();
+    let edges = topology.edges();
+    // neighbours are seated both ways if the sides are paid differently
+    let pairs = tournament::seatings(config, edges.clone());
+    let relatedness = config.relatedness.unwrap_or(0.0);
+    let mut states = Vec::with_capacity(generations + 1);
+
+    for _ in 0..generations {
+        let nodes: Vec<ParticipantConfig> = state
+            .iter()
+            .enumerate()
+            .map(|(node, &s)| ParticipantConfig {
+                name: format!("{}@{}", config.participants[s].name, node),
+                payoffs: config.perceive(&config.participants[s], temptations[node]),
+                ..config.participants[s].clone()
+            })
+            .collect();
+        let reports =
+            tournament::play_pairs(config, registry, &nodes, &pairs, &mut seeds, None).await;
+        // the matches were cut short, so don't imitate on their scores
+        if interrupt::interrupted() {
+            break;
+        }
+
+        // aborted matches earn nothing for either side
+        let mut fitness = vec![0.0; nodes.len()];
+        for (&(a, b), report) in pairs.iter().zip(&reports) {
+            if let Ok(result) = &report.result {
+                let kin = if state[a] == state[b] {
+                    relatedness
+                } else {
+                    0.0
+                };
+                let earned = |player: &PlayerSummary, node: usize| {
+                    let score = match &nodes[node].payoffs {
+                        Some(payoffs) => player.score_under(payoffs),
+                        None => player.score,
+                    };
+                    match imitation {
+                        Imitation::Best => score,
+                        Imitation::Fermi { .. } => score / result.rounds.max(1) as f64,
+                    }
+                };
+                let (blue, red) = (earned(&result.blue, a), earned(&result.red, b));
+                fitness[a] += blue + kin * red;
+                fitness[b] += red + kin * blue;
+            }
+        }
+
+        let next = (0..state.len())
+            .map(|node| {
+                let neighbours = topology.neighbours(node);
+                match imitation {
+                    // ties keep the node's own strategy
+                    Imitation::Best => {
+                        let best = neighbours.into_iter().fold(node, |best, n| {
+                            if fitness[n] > fitness[best] {
+                                n
+                            } else {
+                                best
+                            }
+                        });
+                        state[best]
+                    }
+                    Imitation::Fermi { temperature } => {
+                        if neighbours.is_empty() {
+                            return state[node];
+                        }
+                        let n = neighbours[rng.gen_range(0, neighbours.len())];
+                        if rng.gen::<f64>() < fermi(fitness[node], fitness[n], temperature) {
+                            state[n]
+                        } else {
+                            state[node]
+                        }
+                    }
+                }
+            })
+            .collect();
+        states.push(std::mem::replace(&mut state, next));
+    }
+    states.push(state);
+
+    SpatialRecord {
+        config: config.clone(),
+        seed,
+        edges,
+        imitation,
+        states,
+    }
+}
+
+/// Run `generations` generations on each of the named `topologies`, which
+/// must have as many nodes as each other
+///
+/// Every run has the same seed, so each node starts with the same
+/// participant on every topology, and only who meets whom differs.
+pub async fn compare(
+    config: &SimConfig,
+    registry: &StrategyRegistry,
+    seed: u64,
+    topologies: &[(&str, &dyn Topology)],
+    generations: usize,
+    imitation: Imitation,
+) -> Comparison {
+    let nodes = topologies
+        .first()
+        .map_or(0, |(_, topology)| topology.nodes());
+    let mut runs = Vec::new();
+    for &(name, topology) in topologies {
+        assert_eq!(topology.nodes(), nodes, "matched topologies");
+        let record = run_spatial(
+            config,
+            registry,
+            seed,
+            topology,
+            generations,
+            imitation,
+            None,
+        )
+        .await;
+        if interrupt::interrupted() {
+            break;
+        }
+        runs.push(TopologyRun {
+            topology: name.to_owned(),
+            mean_degree: 2.0 * record.edges.len() as f64 / nodes.max(1) as f64,
+            record,
+        });
+    }
+    Comparison {
+        seed,
+        nodes,
+        generations,
+        runs,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_fermi_rule_copies_better_neighbours_more_surely_when_cold() {
+        assert_eq!(fermi(2.0, 2.0, 0.5), 0.5);
+        let (cold, warm) = (fermi(2.0, 3.0, 0.1), fermi(2.0, 3.0, 10.0));
+        assert!(cold > 0.99 && warm > 0.5 && warm < 0.6);
+        assert!(fermi(3.0, 2.0, 0.1) < 0.01);
+        // far beyond what an exponent can hold
+        assert_eq!(fermi(1000.0, 0.0, 0.001), 0.0);
+        assert_eq!(fermi(0.0, 1000.0, 0.001), 1.0);
+    }
+
+    #[actix_rt::test]
+    async fn defectors_spread_among_cooperators_under_the_fermi_rule() {
+        let config = SimConfig {
+            iterations: 10,
+            participants: vec![
+                ParticipantConfig::new("allc", "always-cooperate"),
+                ParticipantConfig::new("alld", "always-defect"),
+            ],
+            ..SimConfig::default()
+        };
+        let registry = StrategyRegistry::new();
+        let lattice = Lattice::new(5, 5).unwrap();
+        let rule = Imitation::Fermi { temperature: 0.1 };
+        let record = run_spatial(&config, &registry, 4, &lattice, 15, rule, None).await;
+        let defectors = |state: &[usize]| state.iter().filter(|&&s| s == 1).count();
+        let (first, last) = (&record.states[0], record.states.last().unwrap());
+        assert!(defectors(first) > 0);
+        assert!(defectors(last) > defectors(first));
+        assert_eq!(record.imitation, rule);
+
+        let again = run_spatial(&config, &registry, 4, &lattice, 15, rule, None).await;
+        assert_eq!(again.states, record.states);
+    }
+
+    #[actix_rt::test]
+    async fn compared_topologies_start_alike() {
+        use crate::topology::{Complete, Graph};
+
+        let config = SimConfig {
+            iterations: 5,
+            participants: vec![
+                ParticipantConfig::new("tft", "tit-for-tat"),
+                ParticipantConfig::new("alld", "always-defect"),
+            ],
+            ..SimConfig::default()
+        };
+        let lattice = Lattice::new(3, 3).unwrap();
+        let ring = Graph::small_world(9, 2, 0.0, &mut StdRng::seed_from_u64(1)).unwrap();
+        let topologies: [(&str, &dyn Topology); 3] = [
+            ("well-mixed", &Complete(9)),
+            ("lattice", &lattice),
+            ("ring", &ring),
+        ];
+        let registry = StrategyRegistry::new();
+        let comparison = compare(&config, &registry, 2, &topologies, 3, Imitation::Best).await;
+        assert_eq!(comparison.nodes, 9);
+        let degrees: Vec<f64> = comparison.runs.iter().map(|run| run.mean_degree).collect();
+        assert_eq!(degrees, vec![8.0, 8.0, 2.0]);
+        let starts: Vec<_> = comparison
+            .runs
+            .iter()
+            .map(|run| &run.record.states[0])
+            .collect();
+        assert!(starts.iter().all(|&start| start == starts[0]));
+        for run in &comparison.runs {
+            let shares = run.record.shares(3);
+            assert!((shares.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+        }
+    }
+}
@@ -0,0 +1,339 @@
+//! Cooperation metrics for a match, computed from its transcript
+//!
+//! Cooperation and mutual cooperation count executed actions, what was
+//! actually scored. Retaliation and forgiveness look at how each strategy
+//! responded: what it chose, after what it was told its opponent did, so
+//! that noise doesn't make a forgiving strategy look vengeful. Payoff share
+//! counts only the points scored in play, leaving out any forfeit award.
+//!
+//! [`Summary`] describes a pairing's scores over repeated matches, since a
+//! single match against a random or noisy opponent says little.
+//!
+//! [`Timings`] count how long rounds and answers took, coarsely enough that
+//! the timings of every match in a tournament can be merged.
+
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::game::{Action, Score};
+use crate::transcript::{RoundRecord, SideRecord};
+
+/// How one side of a match behaved
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct PlayerStats {
+    /// Share of rounds in which it cooperated
+    pub cooperation_rate: f64,
+
+    /// Mean rounds from an opponent's defection, after cooperating, to this
+    /// side's next defection; none if it never retaliated
+    pub retaliation_latency: Option<f64>,
+
+    /// Share of rounds following an opponent's defection in which this side
+    /// cooperated; none if the opponent never defected before the last round
+    pub forgiveness_rate: Option<f64>,
+
+    /// This side's share of the points both sides scored
+    pub payoff_share: f64,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct MatchStats {
+    /// Runs of consecutive rounds in which both sides cooperated
+    pub mutual_cooperation_streaks: usize,
+    pub longest_mutual_cooperation: usize,
+    pub mean_mutual_cooperation: f64,
+
+    pub blue: PlayerStats,
+    pub red: PlayerStats,
+}
+
+impl MatchStats {
+    pub fn new(transcript: &[RoundRecord]) -> MatchStats {
+        let mut streaks = Vec::new();
+        let mut current = 0;
+        for round in transcript {
+            if round.blue.executed == Action::COOPERATE && round.red.executed == Action::COOPERATE {
+                current += 1;
+            } else if current > 0 {
+                streaks.push(current);
+                current = 0;
+            }
+        }
+        if current > 0 {
+            streaks.push(current);
+        }
+
+        let blue: Vec<&SideRecord> = transcript.iter().map(|round| &round.blue).collect();
+        let red: Vec<&SideRecord> = transcript.iter().map(|round| &round.red).collect();
+        let total = |side: &[&SideRecord]| side.iter().map(|s| s.outcome.amount).sum::<Score>();
+        let (blue_points, red_points) = (total(&blue), total(&red));
+        MatchStats {
+            mutual_cooperation_streaks: streaks.len(),
+            longest_mutual_cooperation: streaks.iter().copied().max().unwrap_or(0),
+            mean_mutual_cooperation: mean(streaks.iter().map(|&s| s as f64)).unwrap_or(0.0),
+            blue: player_stats(&blue, blue_points, red_points),
+            red: player_stats(&red, red_points, blue_points),
+        }
+    }
+}
+
+fn mean<I: Iterator<Item = f64>>(values: I) -> Option<f64> {
+    let (sum, count) = values.fold((0.0, 0), |(sum, count), v| (sum + v, count + 1));
+    if count == 0 {
+        None
+    } else {
+        Some(sum / count as f64)
+    }
+}
+
+fn player_stats(side: &[&SideRecord], points: Score, opponent_points: Score) -> PlayerStats {
+    let cooperations = side
+        .iter()
+        .filter(|s| s.executed == Action::COOPERATE)
+        .count();
+
+    // a provocation is a defection by the opponent that follows cooperation,
+    // or opens the match
+    let mut latencies = Vec::new();
+    for (t, s) in side.iter().enumerate() {
+        let provoked =
+            s.perceived == Action::DEFECT && (t == 0 || side[t - 1].perceived == Action::COOPERATE);
+        if provoked {
+            if let Some(answer) = side[t + 1..]
+                .iter()
+                .position(|later| later.chosen == Action::DEFECT)
+            {
+                latencies.push((answer + 1) as f64);
+            }
+        }
+    }
+
+    let responses = side.windows(2).filter_map(|pair| {
+        if pair[0].perceived == Action::DEFECT {
+            Some(if pair[1].chosen == Action::COOPERATE {
+                1.0
+            } else {
+                0.0
+            })
+        } else {
+            None
+        }
+    });
+
+    let both = points + opponent_points;
+    PlayerStats {
+        cooperation_rate: if side.is_empty() {
+            0.0
+        } else {
+            cooperations as f64 / side.len() as f64
+        },
+        retaliation_latency: mean(latencies.into_iter()),
+        forgiveness_rate: mean(responses),
+        payoff_share: if both == 0.0 { 0.5 } else { points / both },
+    }
+}
+
+/// The spread of a sample, such as one pairing's scores over its repetitions
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct Summary {
+    pub count: usize,
+    pub mean: f64,
+
+    /// The sample standard deviation; zero for fewer than two values
+    pub std_dev: f64,
+
+    /// A 95% confidence interval for the mean, from Student's t
+    pub ci_low: f64,
+    pub ci_high: f64,
+}
+
+impl Summary {
+    pub fn of(values: &[f64]) -> Summary {
+        let count = values.len();
+        let mean = mean(values.iter().copied()).unwrap_or(0.0);
+        if count < 2 {
+            return Summary {
+                count,
+                mean,
+                std_dev: 0.0,
+                ci_low: mean,
+                ci_high: mean,
+            };
+        }
+        let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (count - 1) as f64;
+        let std_dev = variance.sqrt();
+        let margin = t_critical(count - 1) * std_dev / (count as f64).sqrt();
+        Summary {
+            count,
+            mean,
+            std_dev,
+            ci_low: mean - margin,
+            ci_high: mean + margin,
+        }
+    }
+}
+
+/// The two-sided 95% critical value of Student's t with `df` degrees of
+/// freedom, from the usual table
+///
+/// Past 30 the table only lists 40, 60, and 120, then the normal value for
+/// infinitely many; in between, the value is interpolated linearly in
+/// `1 / df`, which the critical values follow closely.
+fn t_critical(df: usize) -> f64 {
+    const TABLE: [f64; 30] = [
+        12.706, 4.303, 3.182, 2.776, 2.571, 2.447, 2.365, 2.306, 2.262, 2.228, 2.201, 2.179, 2.160,
+        2.145, 2.131, 2.120, 2.110, 2.101, 2.093, 2.086, 2.080, 2.074, 2.069, 2.064, 2.060, 2.056,
+        2.052, 2.048, 2.045, 2.042,
+    ];
+    const TAIL: [(f64, f64); 4] = [(30.0, 2.042), (40.0, 2.021), (60.0, 2.000), (120.0, 1.980)];
+    const NORMAL: f64 = 1.960;
+    match df {
+        0 => f64::INFINITY,
+        1..=30 => TABLE[df - 1],
+        _ => {
+            let df = df as f64;
+            let (low, high) = TAIL
+                .windows(2)
+                .map(|pair| (pair[0], pair[1]))
+                .find(|&(_, (high, _))| df <= high)
+                .unwrap_or((TAIL[TAIL.len() - 1], (f64::INFINITY, NORMAL)));
+            let at = |(df, _): (f64, f64)| 1.0 / df;
+            let fraction = (at(low) - 1.0 / df) / (at(low) - at(high));
+            low.1 + fraction * (high.1 - low.1)
+        }
+    }
+}
+
+/// Durations counted in buckets a quarter of a doubling wide, from 1 µs up
+///
+/// Bucket 0 holds everything up to 1 µs, and each bucket `i` above it the
+/// durations up to 2^(i/4) µs, so percentiles read from the buckets are
+/// within a fifth of the true ones however many samples are merged.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Timings {
+    counts: Vec<u64>,
+}
+
+/// The 50th, 95th, and 99th percentiles of some [`Timings`], in microseconds
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Percentiles {
+    pub p50: f64,
+    pub p95: f64,
+    pub p99: f64,
+}
+
+impl Timings {
+    const PER_DOUBLING: f64 = 4.0;
+
+    pub fn record(&mut self, duration: Duration) {
+        let micros = duration.as_secs_f64() * 1e6;
+        let bucket = if micros < 1.0 {
+            0
+        } else {
+            (micros.log2() * Timings::PER_DOUBLING).ceil() as usize
+        };
+        if self.counts.len() <= bucket {
+            self.counts.resize(bucket + 1, 0);
+        }
+        self.counts[bucket] += 1;
+    }
+
+    /// Add `other`'s samples to these
+    pub fn merge(&mut self, other: &Timings) {
+        if self.counts.len() < other.counts.len() {
+            self.counts.resize(other.counts.len(), 0);
+        }
+        for (count, other) in self.counts.iter_mut().zip(&other.counts) {
+            *count += other;
+        }
+    }
+
+    pub fn samples(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    /// The bound of the bucket holding the `q` quantile, in microseconds,
+    /// or `None` without samples
+    pub fn quantile(&self, q: f64) -> Option<f64> {
+        let samples = self.samples();
+        if samples == 0 {
+            return None;
+        }
+        let rank = ((q * samples as f64).ceil() as u64).clamp(1, samples);
+        let mut seen = 0;
+        let bucket = self
+            .counts
+            .iter()
+            .position(|&count| {
+                seen += count;
+                seen >= rank
+            })
+            .expect("rank within the samples");
+        Some(2f64.powf(bucket as f64 / Timings::PER_DOUBLING))
+    }
+
+    pub fn percentiles(&self) -> Option<Percentiles> {
+        Some(Percentiles {
+            p50: self.quantile(0.5)?,
+            p95: self.quantile(0.95)?,
+            p99: self.quantile(0.99)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn a_single_value_has_no_spread() {
+        let summary = Summary::of(&[3.5]);
+        assert_eq!(summary.count, 1);
+        assert_eq!(summary.mean, 3.5);
+        assert_eq!(summary.std_dev, 0.0);
+        assert_eq!((summary.ci_low, summary.ci_high), (3.5, 3.5));
+    }
+
+    #[test]
+    fn two_values_use_one_degree_of_freedom() {
+        let summary = Summary::of(&[1.0, 3.0]);
+        assert_eq!(summary.mean, 2.0);
+        assert!(close(summary.std_dev, 2f64.sqrt()));
+        // 12.706 * sqrt(2) / sqrt(2)
+        assert!(close(summary.ci_low, 2.0 - 12.706));
+        assert!(close(summary.ci_high, 2.0 + 12.706));
+    }
+
+    #[test]
+    fn the_interval_follows_students_t() {
+        let values = [2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0];
+        let summary = Summary::of(&values);
+        assert_eq!(summary.mean, 5.0);
+        // squared deviations sum to 32, over 7 degrees of freedom
+        assert!(close(summary.std_dev, (32.0f64 / 7.0).sqrt()));
+        let margin = 2.365 * (32.0f64 / 7.0).sqrt() / 8f64.sqrt();
+        assert!(close(summary.ci_high - summary.mean, margin));
+        assert!(close(summary.mean - summary.ci_low, margin));
+    }
+
+    #[test]
+    fn critical_values_past_the_table_are_interpolated() {
+        assert_eq!(t_critical(30), 2.042);
+        assert!(close(t_critical(40), 2.021));
+        assert!(close(t_critical(60), 2.000));
+        assert!(close(t_critical(120), 1.980));
+        // halfway between 1/40 and 1/60 is 1/48
+        assert!(close(t_critical(48), (2.021 + 2.000) / 2.0));
+        let mut previous = t_critical(30);
+        for df in 31..1000 {
+            let t = t_critical(df);
+            assert!(t < previous && t > 1.960, "t({}) = {}", df, t);
+            previous = t;
+        }
+    }
+}
@@ -0,0 +1,446 @@
+//! SQLite persistence for tournament results
+//!
+//! Every run appends to the same database, so repeated experiments build up
+//! a queryable dataset:
+//!
+//! ```sql
+//! SELECT name, AVG(average_score) FROM standings GROUP BY name;
+//! ```
+//!
+//! Runs that keep ratings also record each participant's rating afterwards, so
+//! its history can be charted from the `ratings` table.
+//!
+//! A round robin saved as it goes keeps each match in the `played` table,
+//! as JSON, as soon as it ends. If the run stops before the end, the
+//! tournament is left without standings, and [`ResultStore::unfinished`]
+//! finds it there to be resumed; once it is finished, its matches are
+//! written out like any other tournament's.
+
+use rusqlite::types::Type;
+use rusqlite::{params, Connection, OptionalExtension, Result};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::config::SimConfig;
+use crate::leaderboard::Leaderboard;
+use crate::referee::{MatchResult, SideRecord};
+use crate::tournament::{PartialTournament, TournamentRecord};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS tournaments (
+    id INTEGER PRIMARY KEY,
+    recorded_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    seed TEXT NOT NULL,
+    config TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS matches (
+    id INTEGER PRIMARY KEY,
+    tournament_id INTEGER NOT NULL REFERENCES tournaments(id),
+    blue TEXT NOT NULL,
+    red TEXT NOT NULL,
+    rounds INTEGER NOT NULL,
+    blue_score REAL NOT NULL,
+    red_score REAL NOT NULL,
+    faults TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS rounds (
+    match_id INTEGER NOT NULL REFERENCES matches(id),
+    round INTEGER NOT NULL,
+    blue_chosen TEXT NOT NULL,
+    blue_executed TEXT NOT NULL,
+    blue_perceived TEXT NOT NULL,
+    blue_payoff TEXT NOT NULL,
+    blue_amount REAL NOT NULL,
+    red_chosen TEXT NOT NULL,
+    red_executed TEXT NOT NULL,
+    red_perceived TEXT NOT NULL,
+    red_payoff TEXT NOT NULL,
+    red_amount REAL NOT NULL,
+    PRIMARY KEY (match_id, round)
+);
+CREATE TABLE IF NOT EXISTS match_stats (
+    match_id INTEGER PRIMARY KEY REFERENCES matches(id),
+    mutual_cooperation_streaks INTEGER NOT NULL,
+    longest_mutual_cooperation INTEGER NOT NULL,
+    mean_mutual_cooperation REAL NOT NULL,
+    blue_cooperation_rate REAL NOT NULL,
+    blue_retaliation_latency REAL,
+    blue_forgiveness_rate REAL,
+    blue_payoff_share REAL NOT NULL,
+    red_cooperation_rate REAL NOT NULL,
+    red_retaliation_latency REAL,
+    red_forgiveness_rate REAL,
+    red_payoff_share REAL NOT NULL
+);
+CREATE TABLE IF NOT EXISTS played (
+    tournament_id INTEGER NOT NULL REFERENCES tournaments(id),
+    pairing INTEGER NOT NULL,
+    result TEXT NOT NULL,
+    PRIMARY KEY (tournament_id, pairing)
+);
+CREATE TABLE IF NOT EXISTS standings (
+    tournament_id INTEGER NOT NULL REFERENCES tournaments(id),
+    rank INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    strategy TEXT NOT NULL,
+    matches INTEGER NOT NULL,
+    rounds INTEGER NOT NULL,
+    score REAL NOT NULL,
+    average_score REAL NOT NULL,
+    cooperation_rate REAL NOT NULL,
+    PRIMARY KEY (tournament_id, rank)
+);
+CREATE TABLE IF NOT EXISTS ratings (
+    tournament_id INTEGER NOT NULL REFERENCES tournaments(id),
+    name TEXT NOT NULL,
+    strategy TEXT NOT NULL,
+    rating REAL NOT NULL,
+    matches INTEGER NOT NULL,
+    change REAL NOT NULL,
+    PRIMARY KEY (tournament_id, name)
+);
+";
+
+pub struct ResultStore {
+    conn: Connection,
+}
+
+impl ResultStore {
+    /// Open or create the database at `path`
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<ResultStore> {
+        let conn = Connection::open(path)?;
+        conn.execute_batch(SCHEMA)?;
+        Ok(ResultStore { conn })
+    }
+
+    /// Write a whole tournament in one transaction, returning its id
+    pub fn record_tournament(&mut self, record: &TournamentRecord) -> Result<i64> {
+        let tx = self.conn.transaction()?;
+        let tournament_id = insert_tournament(&tx, &record.config, record.seed)?;
+        insert_results(&tx, tournament_id, record)?;
+        tx.commit()?;
+        Ok(tournament_id)
+    }
+
+    /// Note down a round robin about to be played, returning its id, so that
+    /// its matches can be saved with [`record_played`](Self::record_played)
+    /// as they end
+    pub fn begin_tournament(&mut self, config: &SimConfig, seed: u64) -> Result<i64> {
+        insert_tournament(&self.conn, config, seed)
+    }
+
+    /// Save the match played at place `pairing` in the schedule of the
+    /// begun tournament `tournament_id`
+    pub fn record_played(
+        &self,
+        tournament_id: i64,
+        pairing: usize,
+        result: &MatchResult,
+    ) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO played (tournament_id, pairing, result)
+             VALUES (?1, ?2, ?3)",
+            params![
+                tournament_id,
+                pairing as i64,
+                serde_json::to_string(result).expect("serializable result")
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Write the results of the begun tournament `tournament_id`, in place
+    /// of the matches saved as they ended
+    pub fn finish_tournament(
+        &mut self,
+        tournament_id: i64,
+        record: &TournamentRecord,
+    ) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        insert_results(&tx, tournament_id, record)?;
+        tx.execute(
+            "DELETE FROM played WHERE tournament_id = ?1",
+            params![tournament_id],
+        )?;
+        tx.commit()
+    }
+
+    /// The last tournament begun and never finished, with its id, if any
+    pub fn unfinished(&self) -> Result<Option<(i64, PartialTournament)>> {
+        let begun = self
+            .conn
+            .query_row(
+                "SELECT id, seed, config FROM tournaments t
+                 WHERE NOT EXISTS (SELECT 1 FROM standings s WHERE s.tournament_id = t.id)
+                 ORDER BY id DESC LIMIT 1",
+                [],
+                |row| {
+                    Ok((
+                        row.get::<_, i64>(0)?,
+                        row.get::<_, String>(1)?,
+                        row.get::<_, String>(2)?,
+                    ))
+                },
+            )
+            .optional()?;
+        let (tournament_id, seed, config) = match begun {
+            Some(begun) => begun,
+            None => return Ok(None),
+        };
+        let seed = seed.parse().map_err(|e| conversion(1, e))?;
+        let config = serde_json::from_str(&config).map_err(|e| conversion(2, e))?;
+        let mut query = self
+            .conn
+            .prepare("SELECT pairing, result FROM played WHERE tournament_id = ?1")?;
+        let mut played = BTreeMap::new();
+        let mut rows = query.query(params![tournament_id])?;
+        while let Some(row) = rows.next()? {
+            let pairing: i64 = row.get(0)?;
+            let result: String = row.get(1)?;
+            let result = serde_json::from_str(&result).map_err(|e| conversion(1, e))?;
+            played.insert(pairing as usize, result);
+        }
+        Ok(Some((
+            tournament_id,
+            PartialTournament {
+                config,
+                seed,
+                played,
+            },
+        )))
+    }
+
+    /// Every recorded match between two participants, counted by strategy
+    ///
+    /// Twins have no standings, so their matches drop out of the join.
+    pub fn leaderboard(&self) -> Result<Leaderboard> {
+        let mut query = self.conn.prepare(
+            "SELECT b.strategy, r.strategy, m.rounds, m.blue_score, m.red_score
+             FROM matches m
+             JOIN standings b ON b.tournament_id = m.tournament_id AND b.name = m.blue
+             JOIN standings r ON r.tournament_id = m.tournament_id AND r.name = m.red
+             ORDER BY m.id",
+        )?;
+        let mut leaderboard = Leaderboard::default();
+        let mut rows = query.query([])?;
+        while let Some(row) = rows.next()? {
+            let (blue, red): (String, String) = (row.get(0)?, row.get(1)?);
+            let rounds: i64 = row.get(2)?;
+            leaderboard.record_match(&blue, &red, rounds as usize, row.get(3)?, row.get(4)?);
+        }
+        Ok(leaderboard)
+    }
+}
+
+fn insert_tournament(conn: &Connection, config: &SimConfig, seed: u64) -> Result<i64> {
+    conn.execute(
+        "INSERT INTO tournaments (seed, config) VALUES (?1, ?2)",
+        params![
+            seed.to_string(),
+            serde_json::to_string(config).expect("serializable config")
+        ],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+/// A column that didn't hold what was written there
+fn conversion<E: std::error::Error + Send + Sync + 'static>(
+    column: usize,
+    e: E,
+) -> rusqlite::Error {
+    rusqlite::Error::FromSqlConversionFailure(column, Type::Text, Box::new(e))
+}
+
+/// A tournament's matches, standings, and ratings
+fn insert_results(conn: &Connection, tournament_id: i64, record: &TournamentRecord) -> Result<()> {
+    for result in &record.matches {
+        insert_match(conn, tournament_id, result)?;
+    }
+
+    for (i, standing) in record.standings.iter().enumerate() {
+        conn.execute(
+            "INSERT INTO standings
+             (tournament_id, rank, name, strategy, matches, rounds, score,
+              average_score, cooperation_rate)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                tournament_id,
+                i as i64 + 1,
+                standing.name,
+                standing.strategy,
+                standing.matches as i64,
+                standing.rounds as i64,
+                standing.score,
+                standing.average_score,
+                standing.cooperation_rate,
+            ],
+        )?;
+    }
+
+    for rating in &record.ratings {
+        conn.execute(
+            "INSERT INTO ratings (tournament_id, name, strategy, rating, matches, change)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                tournament_id,
+                rating.name,
+                rating.strategy,
+                rating.rating,
+                rating.matches as i64,
+                rating.change,
+            ],
+        )?;
+    }
+    Ok(())
+}
+
+fn insert_match(conn: &Connection, tournament_id: i64, result: &MatchResult) -> Result<()> {
+    conn.execute(
+        "INSERT INTO matches
+         (tournament_id, blue, red, rounds, blue_score, red_score, faults)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            tournament_id,
+            result.blue.name,
+            result.red.name,
+            result.rounds as i64,
+            result.blue.score,
+            result.red.score,
+            serde_json::to_string(&result.faults).expect("serializable faults"),
+        ],
+    )?;
+    let match_id = conn.last_insert_rowid();
+
+    let stats = &result.stats;
+    conn.execute(
+        "INSERT INTO match_stats
+         (match_id, mutual_cooperation_streaks, longest_mutual_cooperation,
+          mean_mutual_cooperation,
+          blue_cooperation_rate, blue_retaliation_latency, blue_forgiveness_rate,
+          blue_payoff_share,
+          red_cooperation_rate, red_retaliation_latency, red_forgiveness_rate,
+          red_payoff_share)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+        params![
+            match_id,
+            stats.mutual_cooperation_streaks as i64,
+            stats.longest_mutual_cooperation as i64,
+            stats.mean_mutual_cooperation,
+            stats.blue.cooperation_rate,
+            stats.blue.retaliation_latency,
+            stats.blue.forgiveness_rate,
+            stats.blue.payoff_share,
+            stats.red.cooperation_rate,
+            stats.red.retaliation_latency,
+            stats.red.forgiveness_rate,
+            stats.red.payoff_share,
+        ],
+    )?;
+
+    let mut insert = conn.prepare(
+        "INSERT INTO rounds
+         (match_id, round,
+          blue_chosen, blue_executed, blue_perceived, blue_payoff, blue_amount,
+          red_chosen, red_executed, red_perceived, red_payoff, red_amount)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+    )?;
+    for round in &result.transcript {
+        let (b, r): (&SideRecord, &SideRecord) = (&round.blue, &round.red);
+        insert.execute(params![
+            match_id,
+            round.round as i64,
+            b.chosen.to_string(),
+            b.executed.to_string(),
+            b.perceived.to_string(),
+            b.outcome.payoff.to_string(),
+            b.outcome.amount,
+            r.chosen.to_string(),
+            r.executed.to_string(),
+            r.perceived.to_string(),
+            r.outcome.payoff.to_string(),
+            r.outcome.amount,
+        ])?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ParticipantConfig;
+    use crate::registry::StrategyRegistry;
+    use crate::tournament::{self, MatchReport};
+
+    fn scores(reports: &[MatchReport]) -> Vec<(f64, f64)> {
+        reports
+            .iter()
+            .map(|r| {
+                let result = r.result.as_ref().expect("match played");
+                (result.blue.score, result.red.score)
+            })
+            .collect()
+    }
+
+    #[actix_rt::test]
+    async fn a_round_robin_saved_as_it_goes_resumes_and_finishes() {
+        let config = SimConfig {
+            iterations: 20,
+            noise: 0.1,
+            participants: vec![
+                ParticipantConfig::new("tft", "tit-for-tat"),
+                ParticipantConfig::new("random", "random"),
+                ParticipantConfig::new("grim", "grim"),
+            ],
+            ..SimConfig::default()
+        };
+        let registry = StrategyRegistry::new();
+        let whole = tournament::round_robin(&config, &registry, 5, None).await;
+
+        let mut store = ResultStore::open(":memory:").unwrap();
+        assert!(store.unfinished().unwrap().is_none());
+        let id = store.begin_tournament(&config, 5).unwrap();
+        // the run stops after its first and last matches
+        for &pairing in &[0, 2] {
+            let result = whole[pairing].result.as_ref().unwrap();
+            store.record_played(id, pairing, result).unwrap();
+        }
+
+        let (found, partial) = store
+            .unfinished()
+            .unwrap()
+            .expect("an unfinished tournament");
+        assert_eq!(found, id);
+        assert_eq!(partial.seed, 5);
+        assert_eq!(partial.config.participants.len(), 3);
+        assert_eq!(
+            partial.played.keys().copied().collect::<Vec<_>>(),
+            vec![0, 2]
+        );
+
+        let resumed = tournament::resume(&partial, &registry, &|pairing, report| {
+            store
+                .record_played(id, pairing, report.result.as_ref().unwrap())
+                .unwrap()
+        })
+        .await;
+        assert_eq!(scores(&resumed), scores(&whole));
+
+        let record = TournamentRecord::new(&config, 5, resumed);
+        store.finish_tournament(id, &record).unwrap();
+        assert!(store.unfinished().unwrap().is_none());
+        let played: i64 = store
+            .conn
+            .query_row("SELECT COUNT(*) FROM played", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(played, 0);
+        let matches: i64 = store
+            .conn
+            .query_row(
+                "SELECT COUNT(*) FROM matches WHERE tournament_id = ?1",
+                params![id],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(matches, 3);
+    }
+}
@@ -0,0 +1,2554 @@
+//! The classic strategies from Axelrod's tournaments
+
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::game::{Action, Payoff, PayoffMatrix};
+use crate::randomness::StrategyRng;
+use crate::registry::Parameters;
+use crate::reputation::Reputation;
+use crate::strategy::{state_field, GroupHistory, History, Round, Strategy, Turn};
+
+/// Cooperate with probability `p_cooperate`, even odds by default, and
+/// otherwise defect
+pub struct RandomStrategy {
+    p_cooperate: f64,
+    actions: usize,
+    rng: StrategyRng,
+}
+
+impl RandomStrategy {
+    pub fn new(p_cooperate: f64, seed: u64) -> RandomStrategy {
+        RandomStrategy {
+            p_cooperate,
+            actions: 2,
+            rng: StrategyRng::seed_from_u64(seed),
+        }
+    }
+
+    /// Play a game of `actions` actions, choosing evenly among those other
+    /// than cooperating when it doesn't cooperate
+    pub fn with_actions(mut self, actions: usize) -> RandomStrategy {
+        self.actions = actions;
+        self
+    }
+}
+
+impl Strategy for RandomStrategy {
+    fn name(&self) -> &str {
+        "random"
+    }
+
+    fn choose(&mut self, _history: &History) -> Action {
+        if self.rng.gen_bool(self.p_cooperate) {
+            Action::COOPERATE
+        } else if self.actions > 2 {
+            Action::new(self.rng.gen_range(1, self.actions))
+        } else {
+            Action::DEFECT
+        }
+    }
+
+    fn memory_depth(&self) -> Option<usize> {
+        Some(0)
+    }
+
+    fn is_deterministic(&self) -> bool {
+        self.p_cooperate == 1.0 || (self.p_cooperate == 0.0 && self.actions == 2)
+    }
+
+    /// The registry builds it for the actions of the game it is to play
+    fn supports(&self, _actions: usize) -> bool {
+        true
+    }
+
+    fn parameters(&self) -> Parameters {
+        Parameters::from([("p_cooperate".to_owned(), self.p_cooperate)])
+    }
+}
+
+/// Always cooperate
+pub struct AlwaysCooperate {}
+
+impl Strategy for AlwaysCooperate {
+    fn name(&self) -> &str {
+        "always-cooperate"
+    }
+
+    fn choose(&mut self, _history: &History) -> Action {
+        Action::COOPERATE
+    }
+
+    fn memory_depth(&self) -> Option<usize> {
+        Some(0)
+    }
+
+    fn is_deterministic(&self) -> bool {
+        true
+    }
+
+    fn supports(&self, _actions: usize) -> bool {
+        true
+    }
+}
+
+/// Always defect
+pub struct AlwaysDefect {}
+
+impl Strategy for AlwaysDefect {
+    fn name(&self) -> &str {
+        "always-defect"
+    }
+
+    fn choose(&mut self, _history: &History) -> Action {
+        Action::DEFECT
+    }
+
+    fn memory_depth(&self) -> Option<usize> {
+        Some(0)
+    }
+
+    fn is_deterministic(&self) -> bool {
+        true
+    }
+
+    fn supports(&self, _actions: usize) -> bool {
+        true
+    }
+}
+
+/// Always abstain, settling for the loner's payoff, in games that have one
+pub struct Loner {}
+
+impl Strategy for Loner {
+    fn name(&self) -> &str {
+        "loner"
+    }
+
+    fn choose(&mut self, _history: &History) -> Action {
+        Action::ABSTAIN
+    }
+
+    fn memory_depth(&self) -> Option<usize> {
+        Some(0)
+    }
+
+    fn is_deterministic(&self) -> bool {
+        true
+    }
+}
+
+/// Cooperate first, then copy the opponent's last action
+pub struct TitForTat {}
+
+impl Strategy for TitForTat {
+    fn name(&self) -> &str {
+        "tit-for-tat"
+    }
+
+    fn choose(&mut self, history: &History) -> Action {
+        history.opponent_last_action().unwrap_or(Action::COOPERATE)
+    }
+
+    fn memory_depth(&self) -> Option<usize> {
+        Some(1)
+    }
+
+    fn is_deterministic(&self) -> bool {
+        true
+    }
+
+    /// Whatever the opponent played, it can play back
+    fn supports(&self, _actions: usize) -> bool {
+        true
+    }
+}
+
+/// Tit-for-Tat for alternating games: moving second, answer the opponent's
+/// move this round in kind; moving first, copy its last move
+///
+/// When both move at once it plays Tit-for-Tat.
+pub struct Mirror {}
+
+impl Strategy for Mirror {
+    fn name(&self) -> &str {
+        "mirror"
+    }
+
+    fn choose(&mut self, history: &History) -> Action {
+        match history.turn() {
+            Some(Turn::Second(opponent)) => opponent,
+            _ => history.opponent_last_action().unwrap_or(Action::COOPERATE),
+        }
+    }
+
+    fn memory_depth(&self) -> Option<usize> {
+        Some(1)
+    }
+
+    fn is_deterministic(&self) -> bool {
+        true
+    }
+}
+
+/// Like Tit-for-Tat, but only retaliate after two defections in a row
+pub struct TitForTwoTats {}
+
+impl Strategy for TitForTwoTats {
+    fn name(&self) -> &str {
+        "tit-for-two-tats"
+    }
+
+    fn choose(&mut self, history: &History) -> Action {
+        let mut recent = history.recent(2);
+        if recent.len() == 2 && recent.all(|round| round.opponent == Action::DEFECT) {
+            Action::DEFECT
+        } else {
+            Action::COOPERATE
+        }
+    }
+
+    fn memory_depth(&self) -> Option<usize> {
+        Some(2)
+    }
+
+    fn is_deterministic(&self) -> bool {
+        true
+    }
+}
+
+/// Cooperate until the opponent defects once, then defect forever
+#[derive(Default)]
+pub struct GrimTrigger {
+    triggered: bool,
+}
+
+impl Strategy for GrimTrigger {
+    fn name(&self) -> &str {
+        "grim"
+    }
+
+    fn choose(&mut self, history: &History) -> Action {
+        if history.opponent_last_action() == Some(Action::DEFECT) {
+            self.triggered = true;
+        }
+        if self.triggered {
+            Action::DEFECT
+        } else {
+            Action::COOPERATE
+        }
+    }
+
+    /// The grudge is kept in `triggered`, so only the last round is needed
+    fn memory_depth(&self) -> Option<usize> {
+        Some(1)
+    }
+
+    fn is_deterministic(&self) -> bool {
+        true
+    }
+
+    fn reset(&mut self) {
+        self.triggered = false;
+    }
+
+    fn save_state(&self) -> Option<Value> {
+        Some(json!({ "triggered": self.triggered }))
+    }
+
+    fn load_state(&mut self, state: &Value) -> Result<(), String> {
+        self.triggered = state_field(state, "triggered")?;
+        Ok(())
+    }
+
+    /// The grudge, held into the next match against the same opponent
+    fn remember(&self) -> Option<Value> {
+        Some(json!({ "triggered": self.triggered }))
+    }
+
+    fn recall(&mut self, memory: &Value) {
+        self.triggered = state_field(memory, "triggered").unwrap_or(false);
+    }
+}
+
+/// Win-Stay, Lose-Shift
+///
+/// Repeat the last action after a Reward or Temptation, switch after
+/// a Punishment or Sucker payoff.
+pub struct Pavlov {}
+
+impl Strategy for Pavlov {
+    fn name(&self) -> &str {
+        "pavlov"
+    }
+
+    fn choose(&mut self, history: &History) -> Action {
+        match history.last_payoff() {
+            Payoff::NULL | Payoff::REWARD | Payoff::PUNISHMENT | Payoff::LONER | Payoff::TABLE => {
+                Action::COOPERATE
+            }
+            Payoff::TEMPTATION | Payoff::SUCKER => Action::DEFECT,
+        }
+    }
+
+    fn memory_depth(&self) -> Option<usize> {
+        Some(1)
+    }
+
+    fn is_deterministic(&self) -> bool {
+        true
+    }
+}
+
+/// Tit-for-Tat that forgives a defection with probability `generosity`
+pub struct GenerousTitForTat {
+    generosity: f64,
+    rng: StrategyRng,
+}
+
+impl GenerousTitForTat {
+    pub fn new(generosity: f64, seed: u64) -> GenerousTitForTat {
+        GenerousTitForTat {
+            generosity,
+            rng: StrategyRng::seed_from_u64(seed),
+        }
+    }
+}
+
+impl Strategy for GenerousTitForTat {
+    fn name(&self) -> &str {
+        "generous-tit-for-tat"
+    }
+
+    fn choose(&mut self, history: &History) -> Action {
+        match history.opponent_last_action() {
+            Some(Action::DEFECT) if self.rng.gen::<f64>() >= self.generosity => Action::DEFECT,
+            _ => Action::COOPERATE,
+        }
+    }
+
+    fn memory_depth(&self) -> Option<usize> {
+        Some(1)
+    }
+
+    fn is_deterministic(&self) -> bool {
+        self.generosity == 0.0 || self.generosity == 1.0
+    }
+
+    fn parameters(&self) -> Parameters {
+        Parameters::from([("generosity".to_owned(), self.generosity)])
+    }
+}
+
+/// Davis, from Axelrod's first tournament: cooperate for the first ten
+/// rounds, then play Grim Trigger against any defection seen so far
+#[derive(Default)]
+pub struct Davis {
+    triggered: bool,
+}
+
+impl Strategy for Davis {
+    fn name(&self) -> &str {
+        "davis"
+    }
+
+    fn choose(&mut self, history: &History) -> Action {
+        if history.opponent_last_action() == Some(Action::DEFECT) {
+            self.triggered = true;
+        }
+        if self.triggered && history.round() >= 10 {
+            Action::DEFECT
+        } else {
+            Action::COOPERATE
+        }
+    }
+
+    fn memory_depth(&self) -> Option<usize> {
+        Some(1)
+    }
+
+    fn is_deterministic(&self) -> bool {
+        true
+    }
+
+    fn reset(&mut self) {
+        self.triggered = false;
+    }
+
+    fn save_state(&self) -> Option<Value> {
+        Some(json!({ "triggered": self.triggered }))
+    }
+
+    fn load_state(&mut self, state: &Value) -> Result<(), String> {
+        self.triggered = state_field(state, "triggered")?;
+        Ok(())
+    }
+}
+
+/// Joss, from Axelrod's first tournament: Tit-for-Tat that sneaks in a
+/// defection one time in ten after the opponent cooperates
+pub struct Joss {
+    rng: StrategyRng,
+}
+
+impl Joss {
+    pub fn new(seed: u64) -> Joss {
+        Joss {
+            rng: StrategyRng::seed_from_u64(seed),
+        }
+    }
+}
+
+impl Strategy for Joss {
+    fn name(&self) -> &str {
+        "joss"
+    }
+
+    fn choose(&mut self, history: &History) -> Action {
+        match history.opponent_last_action() {
+            Some(Action::DEFECT) => Action::DEFECT,
+            _ if self.rng.gen::<f64>() < 0.1 => Action::DEFECT,
+            _ => Action::COOPERATE,
+        }
+    }
+
+    fn memory_depth(&self) -> Option<usize> {
+        Some(1)
+    }
+}
+
+/// Shubik, from Axelrod's first tournament: answer each defection that
+/// catches it cooperating with a run of defections, one longer each time
+#[derive(Default)]
+pub struct Shubik {
+    /// The length of the last run of retaliation
+    run: usize,
+    remaining: usize,
+}
+
+impl Strategy for Shubik {
+    fn name(&self) -> &str {
+        "shubik"
+    }
+
+    fn choose(&mut self, history: &History) -> Action {
+        if self.remaining > 0 {
+            self.remaining -= 1;
+            return Action::DEFECT;
+        }
+        match history.last() {
+            Some(round) if round.own == Action::COOPERATE && round.opponent == Action::DEFECT => {
+                self.run += 1;
+                self.remaining = self.run - 1;
+                Action::DEFECT
+            }
+            _ => Action::COOPERATE,
+        }
+    }
+
+    fn memory_depth(&self) -> Option<usize> {
+        Some(1)
+    }
+
+    fn is_deterministic(&self) -> bool {
+        true
+    }
+
+    fn reset(&mut self) {
+        *self = Shubik::default();
+    }
+
+    fn save_state(&self) -> Option<Value> {
+        Some(json!({ "run": self.run, "remaining": self.remaining }))
+    }
+
+    fn load_state(&mut self, state: &Value) -> Result<(), String> {
+        self.run = state_field(state, "run")?;
+        self.remaining = state_field(state, "remaining")?;
+        Ok(())
+    }
+}
+
+/// Grofman, from Axelrod's first tournament: cooperate when both players
+/// did the same last round, and otherwise with probability 2/7
+pub struct Grofman {
+    rng: StrategyRng,
+}
+
+impl Grofman {
+    pub fn new(seed: u64) -> Grofman {
+        Grofman {
+            rng: StrategyRng::seed_from_u64(seed),
+        }
+    }
+}
+
+impl Strategy for Grofman {
+    fn name(&self) -> &str {
+        "grofman"
+    }
+
+    fn choose(&mut self, history: &History) -> Action {
+        match history.last() {
+            Some(round) if round.own != round.opponent && self.rng.gen::<f64>() >= 2.0 / 7.0 => {
+                Action::DEFECT
+            }
+            _ => Action::COOPERATE,
+        }
+    }
+
+    fn memory_depth(&self) -> Option<usize> {
+        Some(1)
+    }
+}
+
+/// Feld, from Axelrod's first tournament: Tit-for-Tat whose chance of
+/// returning cooperation falls steadily from 1 to 1/2 by round 200
+pub struct Feld {
+    rng: StrategyRng,
+}
+
+impl Feld {
+    pub fn new(seed: u64) -> Feld {
+        Feld {
+            rng: StrategyRng::seed_from_u64(seed),
+        }
+    }
+}
+
+impl Strategy for Feld {
+    fn name(&self) -> &str {
+        "feld"
+    }
+
+    fn choose(&mut self, history: &History) -> Action {
+        let probability = 1.0 - 0.5 * (history.round() as f64 / 200.0).min(1.0);
+        match history.opponent_last_action() {
+            Some(Action::DEFECT) => Action::DEFECT,
+            Some(Action::COOPERATE) if self.rng.gen::<f64>() >= probability => Action::DEFECT,
+            _ => Action::COOPERATE,
+        }
+    }
+
+    fn memory_depth(&self) -> Option<usize> {
+        Some(1)
+    }
+}
+
+/// Tullock, from Axelrod's first tournament: cooperate for the first
+/// eleven rounds, then cooperate 10% less often than the opponent did over
+/// the last ten
+pub struct Tullock {
+    rng: StrategyRng,
+}
+
+impl Tullock {
+    pub fn new(seed: u64) -> Tullock {
+        Tullock {
+            rng: StrategyRng::seed_from_u64(seed),
+        }
+    }
+}
+
+impl Strategy for Tullock {
+    fn name(&self) -> &str {
+        "tullock"
+    }
+
+    fn choose(&mut self, history: &History) -> Action {
+        if history.round() < 11 {
+            return Action::COOPERATE;
+        }
+        let cooperated = history
+            .opponent_actions()
+            .filter(|&action| action == Action::COOPERATE)
+            .count();
+        let probability = cooperated as f64 / 10.0 - 0.1;
+        if self.rng.gen::<f64>() < probability {
+            Action::COOPERATE
+        } else {
+            Action::DEFECT
+        }
+    }
+
+    fn memory_depth(&self) -> Option<usize> {
+        Some(10)
+    }
+}
+
+/// Learn what to play with Q-learning, as the match goes on
+///
+/// The state is the last `memory` rounds, both sides' actions. After each
+/// round the value of the action just played in the state it was played in
+/// moves towards the payoff it earned plus `discount` times the value of the
+/// best action in the new state, by `learning_rate`. The learner then plays
+/// the best action in the new state, or a random one with probability
+/// `epsilon`; ties go to cooperating. Every value starts at `initial`, and
+/// starting high encourages trying each action before settling. The table
+/// lives in the strategy, and a new instance is built for each match, but
+/// an instance reused after [`Strategy::reset`] keeps what it learned.
+pub struct QLearning {
+    learning_rate: f64,
+    discount: f64,
+    epsilon: f64,
+    memory: usize,
+    initial: f64,
+
+    /// Values of cooperating and defecting in each state seen
+    table: HashMap<State, [f64; 2]>,
+
+    /// The state the last action was chosen in
+    previous: Option<State>,
+    rng: StrategyRng,
+}
+
+/// Up to the last 16 rounds, two bits each, and how many there were
+type State = (usize, u32);
+
+impl QLearning {
+    /// The most rounds a state can remember
+    pub const MAX_MEMORY: usize = 16;
+
+    pub fn new(
+        learning_rate: f64,
+        discount: f64,
+        epsilon: f64,
+        memory: usize,
+        initial: f64,
+        seed: u64,
+    ) -> Result<QLearning, String> {
+        if !(1..=QLearning::MAX_MEMORY).contains(&memory) {
+            return Err(format!(
+                "memory {} must be from 1 to {} rounds",
+                memory,
+                QLearning::MAX_MEMORY
+            ));
+        }
+        Ok(QLearning {
+            learning_rate,
+            discount,
+            epsilon,
+            memory,
+            initial,
+            table: HashMap::new(),
+            previous: None,
+            rng: StrategyRng::seed_from_u64(seed),
+        })
+    }
+
+    fn state(&self, history: &History) -> State {
+        let recent = history.recent(self.memory);
+        let len = recent.len();
+        let bits = recent.fold(0, |bits, round| {
+            let bit = |action: Action| (action == Action::DEFECT) as u32;
+            (bits << 2) | (bit(round.own) << 1) | bit(round.opponent)
+        });
+        (len, bits)
+    }
+}
+
+/// Q-learning only ever cooperates or defects; an abstention, or any other
+/// action, forced on it counts as a defection
+fn index(action: Action) -> usize {
+    match action {
+        Action::COOPERATE => 0,
+        _ => 1,
+    }
+}
+
+impl Strategy for QLearning {
+    fn name(&self) -> &str {
+        "q-learning"
+    }
+
+    fn choose(&mut self, history: &History) -> Action {
+        let state = self.state(history);
+        let initial = self.initial;
+        let values = *self.table.entry(state).or_insert([initial; 2]);
+        let best = values[0].max(values[1]);
+        if let (Some(previous), Some(round)) = (self.previous, history.last()) {
+            let value = &mut self.table.entry(previous).or_insert([initial; 2])[index(round.own)];
+            *value += self.learning_rate * (round.outcome.amount + self.discount * best - *value);
+        }
+        self.previous = Some(state);
+
+        if self.rng.gen::<f64>() < self.epsilon {
+            return if self.rng.gen::<bool>() {
+                Action::COOPERATE
+            } else {
+                Action::DEFECT
+            };
+        }
+        let values = self.table[&state];
+        if values[1] > values[0] {
+            Action::DEFECT
+        } else {
+            Action::COOPERATE
+        }
+    }
+
+    fn memory_depth(&self) -> Option<usize> {
+        Some(self.memory)
+    }
+
+    fn parameters(&self) -> Parameters {
+        Parameters::from([
+            ("learning_rate".to_owned(), self.learning_rate),
+            ("discount".to_owned(), self.discount),
+            ("epsilon".to_owned(), self.epsilon),
+            ("memory".to_owned(), self.memory as f64),
+            ("initial".to_owned(), self.initial),
+        ])
+    }
+
+    /// Keeps the table, so that a learner reused across matches goes on
+    /// learning
+    fn reset(&mut self) {
+        self.previous = None;
+    }
+
+    fn save_state(&self) -> Option<Value> {
+        // JSON keys are strings, so the table goes as a list
+        let mut table: Vec<(State, [f64; 2])> = self.table.iter().map(|(&s, &v)| (s, v)).collect();
+        table.sort_by_key(|&(state, _)| state);
+        Some(json!({ "table": table, "previous": self.previous }))
+    }
+
+    fn load_state(&mut self, state: &Value) -> Result<(), String> {
+        let table: Vec<(State, [f64; 2])> = state_field(state, "table")?;
+        self.table = table.into_iter().collect();
+        self.previous = state_field(state, "previous")?;
+        Ok(())
+    }
+}
+
+/// Fit a memory-one model of the opponent as the match goes on, and play
+/// the best response to it
+///
+/// The model is the opponent's probability of cooperating after each outcome
+/// of the previous round, own action first as for [`MemoryOneStrategy`],
+/// estimated from how often it has done so plus one imagined cooperation, so
+/// it expects cooperation until it learns otherwise. Each round it plays
+/// whichever action earns the most against the model, counting future rounds
+/// down by `discount`. Against a memory-one opponent it learns to exploit
+/// whatever can be exploited, which makes it a benchmark for how
+/// exploitable a strategy is. It cooperates in the first round.
+pub struct OpponentModel {
+    payoffs: PayoffMatrix,
+    discount: f64,
+
+    /// Cooperations and rounds seen after each outcome
+    counts: [(u32, u32); 4],
+
+    /// Rounds of the match already added to the counts
+    seen: usize,
+}
+
+/// The index of an outcome, own action first
+fn outcome(own: Action, opponent: Action) -> usize {
+    2 * index(own) + index(opponent)
+}
+
+impl OpponentModel {
+    pub fn new(payoffs: PayoffMatrix, discount: f64) -> Result<OpponentModel, String> {
+        if !(0.0..1.0).contains(&discount) {
+            return Err(format!(
+                "discount {} must be at least 0 and below 1",
+                discount
+            ));
+        }
+        Ok(OpponentModel {
+            payoffs,
+            discount,
+            counts: [(0, 0); 4],
+            seen: 0,
+        })
+    }
+
+    /// The estimated probability that the opponent cooperates after each
+    /// outcome
+    pub fn model(&self) -> [f64; 4] {
+        let mut model = [0.0; 4];
+        for (p, &(cooperations, rounds)) in model.iter_mut().zip(&self.counts) {
+            *p = (cooperations as f64 + 1.0) / (rounds as f64 + 1.0);
+        }
+        model
+    }
+
+    /// What each action is worth after each outcome, playing best from then on
+    fn action_values(&self) -> [[f64; 2]; 4] {
+        let model = self.model();
+        let worth = |own: Action, opponent: Action| self.payoffs.score(own, opponent).red.amount;
+        let mut values = [0.0; 4];
+        let mut actions = [[0.0; 2]; 4];
+        for _ in 0..10_000 {
+            for (state, p) in model.iter().enumerate() {
+                for own in [Action::COOPERATE, Action::DEFECT] {
+                    actions[state][index(own)] =
+                        [(Action::COOPERATE, *p), (Action::DEFECT, 1.0 - p)]
+                            .iter()
+                            .map(|&(opponent, chance)| {
+                                chance
+                                    * (worth(own, opponent)
+                                        + self.discount * values[outcome(own, opponent)])
+                            })
+                            .sum();
+                }
+            }
+            let mut change: f64 = 0.0;
+            for (value, action) in values.iter_mut().zip(&actions) {
+                let best = action[0].max(action[1]);
+                change = change.max((best - *value).abs());
+                *value = best;
+            }
+            if change < 1e-9 {
+                break;
+            }
+        }
+        actions
+    }
+}
+
+impl Strategy for OpponentModel {
+    fn name(&self) -> &str {
+        "opponent-model"
+    }
+
+    fn choose(&mut self, history: &History) -> Action {
+        if history.round() > self.seen {
+            let mut recent = history.recent(2);
+            if let (Some(before), Some(after)) = (recent.next(), recent.next()) {
+                let counts = &mut self.counts[outcome(before.own, before.opponent)];
+                counts.0 += (after.opponent == Action::COOPERATE) as u32;
+                counts.1 += 1;
+            }
+            self.seen = history.round();
+        }
+
+        match history.last() {
+            None => Action::COOPERATE,
+            Some(round) => {
+                let values = self.action_values()[outcome(round.own, round.opponent)];
+                if values[1] > values[0] {
+                    Action::DEFECT
+                } else {
+                    Action::COOPERATE
+                }
+            }
+        }
+    }
+
+    fn memory_depth(&self) -> Option<usize> {
+        Some(2)
+    }
+
+    fn is_deterministic(&self) -> bool {
+        true
+    }
+
+    fn parameters(&self) -> Parameters {
+        Parameters::from([("discount".to_owned(), self.discount)])
+    }
+
+    /// The model is of one opponent, so starts afresh
+    fn reset(&mut self) {
+        self.counts = [(0, 0); 4];
+        self.seen = 0;
+    }
+
+    fn save_state(&self) -> Option<Value> {
+        Some(json!({ "counts": self.counts, "seen": self.seen }))
+    }
+
+    fn load_state(&mut self, state: &Value) -> Result<(), String> {
+        self.counts = state_field(state, "counts")?;
+        self.seen = state_field(state, "seen")?;
+        Ok(())
+    }
+}
+
+/// Play whichever of several strategies is doing best against this opponent
+///
+/// Every child sees every round, so each is ready to take over, but only the
+/// active child's choice is played. Every `window` rounds the strategy
+/// reconsiders: it first tries each child in turn, then keeps to the one
+/// that has earned the most per round while active. If the opponent
+/// cooperated throughout the last window although it was defected against,
+/// it is taken for a pushover and the `exploiter` child takes over.
+pub struct MetaStrategy {
+    children: Vec<Box<dyn Strategy>>,
+    exploiter: Option<usize>,
+    window: usize,
+    active: usize,
+
+    /// Points and rounds earned by each child while active
+    earnings: Vec<(f64, usize)>,
+
+    /// Rounds since the active child took over
+    tenure: usize,
+
+    /// Rounds of the match already credited to a child
+    seen: usize,
+}
+
+impl MetaStrategy {
+    /// Switch between `children`, starting with the first; `exploiter` is
+    /// the index of the child to play against a pushover, if any
+    pub fn new(
+        children: Vec<Box<dyn Strategy>>,
+        exploiter: Option<usize>,
+        window: usize,
+    ) -> Result<MetaStrategy, String> {
+        if children.is_empty() {
+            return Err("needs at least one strategy to switch between".to_owned());
+        }
+        if window == 0 {
+            return Err("window must be at least one round".to_owned());
+        }
+        if let Some(exploiter) = exploiter.filter(|&i| i >= children.len()) {
+            return Err(format!("no strategy {} to exploit with", exploiter));
+        }
+        Ok(MetaStrategy {
+            earnings: vec![(0.0, 0); children.len()],
+            children,
+            exploiter,
+            window,
+            active: 0,
+            tenure: 0,
+            seen: 0,
+        })
+    }
+
+    /// The name of the child currently being played
+    pub fn active(&self) -> &str {
+        self.children[self.active].name()
+    }
+
+    fn pushover(&self, history: &History) -> bool {
+        let recent = || history.recent(self.window);
+        recent().len() == self.window
+            && recent().all(|round| round.opponent == Action::COOPERATE)
+            && recent().any(|round| round.own == Action::DEFECT)
+    }
+
+    fn reconsider(&mut self, history: &History) -> usize {
+        if let Some(exploiter) = self.exploiter.filter(|_| self.pushover(history)) {
+            return exploiter;
+        }
+        if let Some(untried) = self.earnings.iter().position(|&(_, rounds)| rounds == 0) {
+            return untried;
+        }
+        let average = |&(points, rounds): &(f64, usize)| points / rounds as f64;
+        (0..self.children.len())
+            .max_by(|&a, &b| {
+                average(&self.earnings[a])
+                    .total_cmp(&average(&self.earnings[b]))
+                    // the earlier child wins a tie
+                    .then(b.cmp(&a))
+            })
+            .expect("at least one child")
+    }
+}
+
+impl Strategy for MetaStrategy {
+    fn name(&self) -> &str {
+        "meta"
+    }
+
+    fn choose(&mut self, history: &History) -> Action {
+        if history.round() > self.seen {
+            if let Some(round) = history.last() {
+                let earnings = &mut self.earnings[self.active];
+                earnings.0 += round.outcome.amount;
+                earnings.1 += 1;
+                self.tenure += 1;
+            }
+            self.seen = history.round();
+        }
+        if self.tenure >= self.window {
+            self.active = self.reconsider(history);
+            self.tenure = 0;
+        }
+
+        let choices: Vec<Action> = self
+            .children
+            .iter_mut()
+            .map(|child| child.choose(history))
+            .collect();
+        choices[self.active]
+    }
+
+    fn memory_depth(&self) -> Option<usize> {
+        self.children
+            .iter()
+            .map(|child| child.memory_depth())
+            .try_fold(self.window, |deepest, depth| depth.map(|d| d.max(deepest)))
+    }
+
+    fn is_deterministic(&self) -> bool {
+        self.children.iter().all(|child| child.is_deterministic())
+    }
+
+    fn supports(&self, actions: usize) -> bool {
+        self.children.iter().all(|child| child.supports(actions))
+    }
+
+    /// What the children that can tell hold between them
+    fn memory_used(&self) -> Option<usize> {
+        self.children
+            .iter()
+            .filter_map(|child| child.memory_used())
+            .reduce(|a, b| a + b)
+    }
+
+    fn parameters(&self) -> Parameters {
+        Parameters::from([("window".to_owned(), self.window as f64)])
+    }
+
+    fn reset(&mut self) {
+        for child in &mut self.children {
+            child.reset();
+        }
+        self.active = 0;
+        self.earnings = vec![(0.0, 0); self.children.len()];
+        self.tenure = 0;
+        self.seen = 0;
+    }
+
+    /// What each child remembers, in order, `null` for those that keep
+    /// nothing
+    fn remember(&self) -> Option<Value> {
+        let memories: Vec<Option<Value>> =
+            self.children.iter().map(|child| child.remember()).collect();
+        if memories.iter().all(Option::is_none) {
+            return None;
+        }
+        Some(Value::Array(
+            memories
+                .into_iter()
+                .map(|memory| memory.unwrap_or(Value::Null))
+                .collect(),
+        ))
+    }
+
+    fn recall(&mut self, memory: &Value) {
+        if let Some(memories) = memory.as_array() {
+            for (child, memory) in self.children.iter_mut().zip(memories) {
+                if !memory.is_null() {
+                    child.recall(memory);
+                }
+            }
+        }
+    }
+}
+
+/// Play `inner`, but forgive a defection by cooperating with probability
+/// `generosity` where it would have retaliated
+pub struct Generous {
+    name: String,
+    inner: Box<dyn Strategy>,
+    generosity: f64,
+    rng: StrategyRng,
+}
+
+impl Generous {
+    pub fn new(inner: Box<dyn Strategy>, generosity: f64, seed: u64) -> Generous {
+        Generous {
+            name: format!("generous({}, {})", generosity, inner.name()),
+            inner,
+            generosity,
+            // apart from the inner strategy's own, if it draws from the same seed
+            rng: StrategyRng::seed_from_u64(seed ^ 0x9e37_79b9_7f4a_7c15),
+        }
+    }
+}
+
+impl Strategy for Generous {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn choose(&mut self, history: &History) -> Action {
+        let action = self.inner.choose(history);
+        let provoked = history.opponent_last_action() == Some(Action::DEFECT);
+        if action == Action::DEFECT && provoked && self.rng.gen::<f64>() < self.generosity {
+            Action::COOPERATE
+        } else {
+            action
+        }
+    }
+
+    fn memory_depth(&self) -> Option<usize> {
+        self.inner.memory_depth().map(|depth| depth.max(1))
+    }
+
+    fn memory_used(&self) -> Option<usize> {
+        self.inner.memory_used()
+    }
+
+    fn parameters(&self) -> Parameters {
+        let mut parameters = self.inner.parameters();
+        parameters.insert("generosity".to_owned(), self.generosity);
+        parameters
+    }
+
+    fn reset(&mut self) {
+        self.inner.reset();
+    }
+
+    fn save_state(&self) -> Option<Value> {
+        self.inner.save_state()
+    }
+
+    fn load_state(&mut self, state: &Value) -> Result<(), String> {
+        self.inner.load_state(state)
+    }
+
+    fn remember(&self) -> Option<Value> {
+        self.inner.remember()
+    }
+
+    fn recall(&mut self, memory: &Value) {
+        self.inner.recall(memory);
+    }
+}
+
+/// Play `inner`, but after defecting only because noise flipped a
+/// cooperation, take the opponent's retaliation without retaliating in turn
+pub struct Contrite {
+    name: String,
+    inner: Box<dyn Strategy>,
+
+    /// What was chosen last round, before any noise
+    chosen: Option<Action>,
+
+    /// Whether the opponent's next defection was earned by an accident
+    sorry: bool,
+}
+
+impl Contrite {
+    pub fn new(inner: Box<dyn Strategy>) -> Contrite {
+        Contrite {
+            name: format!("contrite({})", inner.name()),
+            inner,
+            chosen: None,
+            sorry: false,
+        }
+    }
+}
+
+impl Strategy for Contrite {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn choose(&mut self, history: &History) -> Action {
+        let mut action = self.inner.choose(history);
+        if let Some(round) = history.last() {
+            if self.sorry {
+                // the retaliation for the accident, if it came, was deserved
+                if round.opponent == Action::DEFECT {
+                    action = Action::COOPERATE;
+                }
+                self.sorry = false;
+            }
+            if self.chosen == Some(Action::COOPERATE) && round.own == Action::DEFECT {
+                self.sorry = true;
+            }
+        }
+        self.chosen = Some(action);
+        action
+    }
+
+    fn memory_depth(&self) -> Option<usize> {
+        self.inner.memory_depth().map(|depth| depth.max(1))
+    }
+
+    fn is_deterministic(&self) -> bool {
+        self.inner.is_deterministic()
+    }
+
+    fn memory_used(&self) -> Option<usize> {
+        self.inner.memory_used()
+    }
+
+    fn parameters(&self) -> Parameters {
+        self.inner.parameters()
+    }
+
+    fn reset(&mut self) {
+        self.inner.reset();
+        self.chosen = None;
+        self.sorry = false;
+    }
+
+    fn save_state(&self) -> Option<Value> {
+        self.inner.save_state()
+    }
+
+    fn load_state(&mut self, state: &Value) -> Result<(), String> {
+        self.inner.load_state(state)
+    }
+
+    fn remember(&self) -> Option<Value> {
+        self.inner.remember()
+    }
+
+    fn recall(&mut self, memory: &Value) {
+        self.inner.recall(memory);
+    }
+}
+
+/// Play `moves` in the first rounds of a match, then `inner`
+///
+/// Suspicious Tit-for-Tat is Tit-for-Tat opening with a defection, written
+/// `suspicious(tit-for-tat)`. The inner strategy is still asked for its
+/// move in every round of the opening, so its own bookkeeping stays in
+/// step, but its answers are thrown away, and it sees the opening in its
+/// history as its own moves.
+pub struct Opening {
+    name: String,
+    moves: Vec<Action>,
+    inner: Box<dyn Strategy>,
+}
+
+impl Opening {
+    pub fn new(name: &str, moves: Vec<Action>, inner: Box<dyn Strategy>) -> Opening {
+        Opening {
+            name: name.to_owned(),
+            moves,
+            inner,
+        }
+    }
+
+    /// `inner`, opening with `rounds` defections
+    pub fn suspicious(rounds: usize, inner: Box<dyn Strategy>) -> Opening {
+        let name = match rounds {
+            1 => format!("suspicious({})", inner.name()),
+            n => format!("suspicious({}, {})", n, inner.name()),
+        };
+        Opening::new(&name, vec![Action::DEFECT; rounds], inner)
+    }
+
+    /// `inner`, opening with `rounds` cooperations
+    pub fn trusting(rounds: usize, inner: Box<dyn Strategy>) -> Opening {
+        let name = match rounds {
+            1 => format!("trusting({})", inner.name()),
+            n => format!("trusting({}, {})", n, inner.name()),
+        };
+        Opening::new(&name, vec![Action::COOPERATE; rounds], inner)
+    }
+}
+
+impl Strategy for Opening {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn choose(&mut self, history: &History) -> Action {
+        let action = self.inner.choose(history);
+        self.moves.get(history.round()).copied().unwrap_or(action)
+    }
+
+    fn memory_depth(&self) -> Option<usize> {
+        // the round count comes with the history
+        self.inner.memory_depth().map(|depth| depth.max(1))
+    }
+
+    fn is_deterministic(&self) -> bool {
+        self.inner.is_deterministic()
+    }
+
+    fn supports(&self, actions: usize) -> bool {
+        self.moves.iter().all(|action| action.index() < actions) && self.inner.supports(actions)
+    }
+
+    fn memory_used(&self) -> Option<usize> {
+        self.inner.memory_used()
+    }
+
+    fn parameters(&self) -> Parameters {
+        self.inner.parameters()
+    }
+
+    fn reset(&mut self) {
+        self.inner.reset();
+    }
+
+    fn save_state(&self) -> Option<Value> {
+        self.inner.save_state()
+    }
+
+    fn load_state(&mut self, state: &Value) -> Result<(), String> {
+        self.inner.load_state(state)
+    }
+
+    fn remember(&self) -> Option<Value> {
+        self.inner.remember()
+    }
+
+    fn recall(&mut self, memory: &Value) {
+        self.inner.recall(memory);
+    }
+}
+
+/// Play a fixed opening, then cooperate only with an opponent that played
+/// the same one, taking it for a copy of itself, and defect against anyone
+/// else
+///
+/// Any strategy that happens to open the same way is taken for a copy too.
+/// Written `handshake:CDDC` for a given opening; plain `handshake` opens
+/// [`Handshake::OPENING`].
+pub struct Handshake {
+    name: String,
+    opening: Vec<Action>,
+}
+
+impl Handshake {
+    pub const OPENING: &'static str = "CD";
+
+    pub fn new(name: &str, opening: &str) -> Result<Handshake, String> {
+        let opening = opening
+            .chars()
+            .map(|c| match c {
+                'C' => Ok(Action::COOPERATE),
+                'D' => Ok(Action::DEFECT),
+                _ => Err(format!("opening '{}' isn't made of C and D", opening)),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if opening.is_empty() {
+            return Err("an opening needs at least one round".to_owned());
+        }
+        Ok(Handshake {
+            name: name.to_owned(),
+            opening,
+        })
+    }
+}
+
+impl Strategy for Handshake {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn choose(&mut self, history: &History) -> Action {
+        if let Some(&action) = self.opening.get(history.round()) {
+            return action;
+        }
+        let recognized = history
+            .opponent_actions()
+            .zip(&self.opening)
+            .all(|(theirs, &ours)| theirs == ours);
+        if recognized {
+            Action::COOPERATE
+        } else {
+            Action::DEFECT
+        }
+    }
+
+    fn is_deterministic(&self) -> bool {
+        true
+    }
+}
+
+/// One state of a [`Machine`]: what to play in it, and where to go next
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MachineState {
+    pub action: Action,
+
+    /// The next state after the opponent cooperates
+    pub on_cooperate: usize,
+
+    /// The next state after the opponent defects
+    pub on_defect: usize,
+}
+
+/// A finite state machine that plays the action of its current state and
+/// moves on according to the opponent's action, starting in state 0
+///
+/// Written as `C0,1/D0,1`: each state's action, then its next states after
+/// the opponent cooperates and defects, with states separated by `/`. That
+/// machine is Tit-for-Tat.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Machine {
+    pub states: Vec<MachineState>,
+}
+
+impl Machine {
+    /// Read a machine written as in its `Display` form
+    pub fn parse(text: &str) -> Result<Machine, String> {
+        let states = text
+            .split('/')
+            .map(|state| {
+                let action = match state.chars().next() {
+                    Some('C') => Action::COOPERATE,
+                    Some('D') => Action::DEFECT,
+                    Some('A') => Action::ABSTAIN,
+                    _ => return Err(format!("state '{}' doesn't start with C, D, or A", state)),
+                };
+                let (on_cooperate, on_defect) = state[1..]
+                    .split_once(',')
+                    .and_then(|(c, d)| Some((c.parse().ok()?, d.parse().ok()?)))
+                    .ok_or_else(|| format!("state '{}' lacks two next states", state))?;
+                Ok(MachineState {
+                    action,
+                    on_cooperate,
+                    on_defect,
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let machine = Machine { states };
+        machine.validate()?;
+        Ok(machine)
+    }
+
+    /// Check that every transition leads to a state of the machine
+    pub fn validate(&self) -> Result<(), String> {
+        let count = self.states.len();
+        match self
+            .states
+            .iter()
+            .flat_map(|s| [s.on_cooperate, s.on_defect])
+            .find(|&next| next >= count)
+        {
+            Some(next) => Err(format!("no state {} in a {}-state machine", next, count)),
+            None => Ok(()),
+        }
+    }
+
+    /// The state after `state` when the opponent plays `opponent`, with
+    /// abstaining, or any other action, taken as defecting
+    pub fn next(&self, state: usize, opponent: Action) -> usize {
+        let state = &self.states[state];
+        match opponent {
+            Action::COOPERATE => state.on_cooperate,
+            _ => state.on_defect,
+        }
+    }
+}
+
+impl fmt::Display for Machine {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, state) in self.states.iter().enumerate() {
+            let action = state.action.letter();
+            let separator = if i == 0 { "" } else { "/" };
+            write!(
+                f,
+                "{}{}{},{}",
+                separator, action, state.on_cooperate, state.on_defect
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Play a [`Machine`], named `fsm:` followed by the machine
+///
+/// The current state is worked out from the rounds the strategy hasn't seen
+/// yet, so a fresh instance given a prisoner's history picks up where the
+/// last one left off.
+pub struct FiniteStateMachine {
+    name: String,
+    machine: Machine,
+    state: usize,
+
+    /// Rounds already followed through the machine
+    seen: usize,
+}
+
+impl FiniteStateMachine {
+    /// The scheme machines are loaded under, as in `fsm:C0,1/D0,1`
+    pub const SCHEME: &'static str = "fsm";
+
+    pub fn new(machine: Machine) -> FiniteStateMachine {
+        FiniteStateMachine {
+            name: format!("{}:{}", FiniteStateMachine::SCHEME, machine),
+            machine,
+            state: 0,
+            seen: 0,
+        }
+    }
+}
+
+impl Strategy for FiniteStateMachine {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn choose(&mut self, history: &History) -> Action {
+        for round in history.rounds().skip(self.seen) {
+            self.state = self.machine.next(self.state, round.opponent);
+        }
+        self.seen = history.round();
+        self.machine.states[self.state].action
+    }
+
+    fn is_deterministic(&self) -> bool {
+        true
+    }
+
+    fn parameters(&self) -> Parameters {
+        Parameters::from([("states".to_owned(), self.machine.states.len() as f64)])
+    }
+
+    fn reset(&mut self) {
+        self.state = 0;
+        self.seen = 0;
+    }
+
+    fn save_state(&self) -> Option<Value> {
+        Some(json!({ "state": self.state, "seen": self.seen }))
+    }
+
+    fn load_state(&mut self, state: &Value) -> Result<(), String> {
+        let current: usize = state_field(state, "state")?;
+        if current >= self.machine.states.len() {
+            return Err(format!("no state {} in the machine", current));
+        }
+        self.state = current;
+        self.seen = state_field(state, "seen")?;
+        Ok(())
+    }
+}
+
+/// A memory-N strategy as a table: the action for every outcome of the last
+/// N rounds, and the N moves to open with before there are that many
+///
+/// Outcomes are numbered from each side's point of view, own action first:
+/// CC 0, CD 1, DC 2, DD 3, with abstaining taken as defecting. The last N
+/// rounds, oldest first, are read as a number in base 4 that indexes the
+/// table, which therefore has 4^N entries. Written as `C/CDCD`: the opening
+/// moves, then the table, each as C, D, or A. That table is Tit-for-Tat,
+/// and `C/CDDC` is Pavlov.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LookupTable {
+    pub initial: Vec<Action>,
+    pub table: Vec<Action>,
+}
+
+impl LookupTable {
+    /// Read a table written as in its `Display` form
+    pub fn parse(text: &str) -> Result<LookupTable, String> {
+        let actions = |text: &str| {
+            text.trim()
+                .chars()
+                .map(|c| match c {
+                    'C' => Ok(Action::COOPERATE),
+                    'D' => Ok(Action::DEFECT),
+                    'A' => Ok(Action::ABSTAIN),
+                    c => Err(format!("'{}' is not C, D, or A", c)),
+                })
+                .collect::<Result<Vec<_>, _>>()
+        };
+        let (initial, table) = text
+            .split_once('/')
+            .ok_or_else(|| "a lookup table is its opening moves, '/', then the table".to_owned())?;
+        let lookup = LookupTable {
+            initial: actions(initial)?,
+            table: actions(table)?,
+        };
+        lookup.validate()?;
+        Ok(lookup)
+    }
+
+    /// Read a table from a file holding its `Display` form, ignoring lines
+    /// that start with `#`
+    pub fn load(path: &str) -> Result<LookupTable, String> {
+        let text =
+            std::fs::read_to_string(path).map_err(|e| format!("unable to load {}: {}", path, e))?;
+        let body: String = text
+            .lines()
+            .filter(|line| !line.trim_start().starts_with('#'))
+            .flat_map(|line| line.chars().filter(|c| !c.is_whitespace()))
+            .collect();
+        LookupTable::parse(&body)
+    }
+
+    /// Check that the table has an entry for every outcome of as many rounds
+    /// as there are opening moves
+    pub fn validate(&self) -> Result<(), String> {
+        let entries = 4usize.checked_pow(self.memory() as u32);
+        if entries == Some(self.table.len()) {
+            Ok(())
+        } else {
+            Err(format!(
+                "{} opening moves call for a table of 4^{} entries, not {}",
+                self.memory(),
+                self.memory(),
+                self.table.len()
+            ))
+        }
+    }
+
+    /// The rounds the table looks back over
+    pub fn memory(&self) -> usize {
+        self.initial.len()
+    }
+
+    /// The action after `rounds`, the last `memory` rounds oldest first
+    pub fn action<'a>(&self, rounds: impl Iterator<Item = &'a Round>) -> Action {
+        let defected = |action: Action| (action != Action::COOPERATE) as usize;
+        let index = rounds.fold(0, |index, round| {
+            index * 4 + 2 * defected(round.own) + defected(round.opponent)
+        });
+        self.table[index]
+    }
+}
+
+impl fmt::Display for LookupTable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let letter = |action: &Action| action.letter();
+        let initial: String = self.initial.iter().map(letter).collect();
+        let table: String = self.table.iter().map(letter).collect();
+        write!(f, "{}/{}", initial, table)
+    }
+}
+
+/// Play a [`LookupTable`], named `lookup:` followed by the path of a file
+/// holding it
+pub struct LookupStrategy {
+    name: String,
+    lookup: LookupTable,
+}
+
+impl LookupStrategy {
+    /// The scheme tables are loaded under, as in `lookup:tables/tft.txt`
+    pub const SCHEME: &'static str = "lookup";
+
+    pub fn new(name: &str, lookup: LookupTable) -> LookupStrategy {
+        LookupStrategy {
+            name: name.to_owned(),
+            lookup,
+        }
+    }
+}
+
+impl Strategy for LookupStrategy {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn choose(&mut self, history: &History) -> Action {
+        let memory = self.lookup.memory();
+        match self.lookup.initial.get(history.round()) {
+            Some(&opening) => opening,
+            None => self.lookup.action(history.recent(memory)),
+        }
+    }
+
+    fn memory_depth(&self) -> Option<usize> {
+        Some(self.lookup.memory())
+    }
+
+    fn is_deterministic(&self) -> bool {
+        true
+    }
+
+    fn parameters(&self) -> Parameters {
+        Parameters::from([("memory".to_owned(), self.lookup.memory() as f64)])
+    }
+}
+
+/// Contribute while enough of the others did: a group-aware Tit-for-Tat
+///
+/// In an n-player game, cooperate first and then whenever at least
+/// `threshold` of the other players cooperated last round. Against a single
+/// opponent it plays Tit-for-Tat.
+pub struct ConditionalCooperator {
+    threshold: f64,
+}
+
+impl ConditionalCooperator {
+    pub fn new(threshold: f64) -> ConditionalCooperator {
+        ConditionalCooperator { threshold }
+    }
+}
+
+impl Strategy for ConditionalCooperator {
+    fn name(&self) -> &str {
+        "conditional-cooperator"
+    }
+
+    fn choose(&mut self, history: &History) -> Action {
+        history.opponent_last_action().unwrap_or(Action::COOPERATE)
+    }
+
+    fn choose_in_group(&mut self, _history: &History, group: &GroupHistory) -> Action {
+        match group.last_cooperation_rate() {
+            Some(rate) if rate < self.threshold => Action::DEFECT,
+            _ => Action::COOPERATE,
+        }
+    }
+
+    fn memory_depth(&self) -> Option<usize> {
+        Some(1)
+    }
+
+    fn is_deterministic(&self) -> bool {
+        true
+    }
+
+    fn parameters(&self) -> Parameters {
+        Parameters::from([("threshold".to_owned(), self.threshold)])
+    }
+}
+
+/// Help those who help others: cooperate with an opponent whose image score
+/// is at least `threshold`
+///
+/// Needs a `[reputation]` rule in the config; without one it plays
+/// Tit-for-Tat.
+pub struct ImageScoring {
+    threshold: Reputation,
+}
+
+impl ImageScoring {
+    pub fn new(threshold: Reputation) -> ImageScoring {
+        ImageScoring { threshold }
+    }
+}
+
+impl Strategy for ImageScoring {
+    fn name(&self) -> &str {
+        "image-scoring"
+    }
+
+    fn choose(&mut self, history: &History) -> Action {
+        match history.opponent_reputation() {
+            Some(reputation) if reputation >= self.threshold => Action::COOPERATE,
+            Some(_) => Action::DEFECT,
+            None => history.opponent_last_action().unwrap_or(Action::COOPERATE),
+        }
+    }
+
+    /// Reputations change between rounds, so this can't be batched
+    fn memory_depth(&self) -> Option<usize> {
+        Some(1)
+    }
+
+    fn is_deterministic(&self) -> bool {
+        true
+    }
+
+    fn parameters(&self) -> Parameters {
+        Parameters::from([("threshold".to_owned(), self.threshold as f64)])
+    }
+}
+
+/// Cooperate with an opponent in good standing and defect against one in
+/// bad standing, which under the `standing` rule keeps its own good
+///
+/// Needs a `[reputation]` rule in the config; without one it plays
+/// Tit-for-Tat.
+pub struct Standing {}
+
+impl Strategy for Standing {
+    fn name(&self) -> &str {
+        "standing"
+    }
+
+    fn choose(&mut self, history: &History) -> Action {
+        match history.opponent_reputation() {
+            Some(reputation) if reputation >= 1 => Action::COOPERATE,
+            Some(_) => Action::DEFECT,
+            None => history.opponent_last_action().unwrap_or(Action::COOPERATE),
+        }
+    }
+
+    /// Reputations change between rounds, so this can't be batched
+    fn memory_depth(&self) -> Option<usize> {
+        Some(1)
+    }
+
+    fn is_deterministic(&self) -> bool {
+        true
+    }
+}
+
+/// A green beard: cooperate with an opponent whose tag is within
+/// `tolerance` of its own, and defect against anyone else
+///
+/// After Riolo, Cohen, and Axelrod. Without tags on both sides it plays
+/// Tit-for-Tat.
+pub struct TagMatcher {
+    tolerance: f64,
+}
+
+impl TagMatcher {
+    pub fn new(tolerance: f64) -> TagMatcher {
+        TagMatcher { tolerance }
+    }
+}
+
+impl Strategy for TagMatcher {
+    fn name(&self) -> &str {
+        "tag-matcher"
+    }
+
+    fn choose(&mut self, history: &History) -> Action {
+        match history.own_info().tag_distance(history.opponent_info()) {
+            Some(distance) if distance <= self.tolerance => Action::COOPERATE,
+            Some(_) => Action::DEFECT,
+            None => history.opponent_last_action().unwrap_or(Action::COOPERATE),
+        }
+    }
+
+    fn memory_depth(&self) -> Option<usize> {
+        Some(1)
+    }
+
+    fn is_deterministic(&self) -> bool {
+        true
+    }
+
+    fn parameters(&self) -> Parameters {
+        Parameters::from([("tolerance".to_owned(), self.tolerance)])
+    }
+}
+
+/// Announce Tit-for-Tat's move and keep to it, unless the opponent
+/// announces a defection, which it meets with one
+///
+/// Without cheap talk it simply plays Tit-for-Tat.
+pub struct SignalTitForTat {}
+
+impl Strategy for SignalTitForTat {
+    fn name(&self) -> &str {
+        "signal-tit-for-tat"
+    }
+
+    fn signal(&mut self, history: &History) -> Option<Action> {
+        Some(history.opponent_last_action().unwrap_or(Action::COOPERATE))
+    }
+
+    fn choose(&mut self, history: &History) -> Action {
+        match history.opponent_signal() {
+            Some(Action::DEFECT) => Action::DEFECT,
+            _ => history.opponent_last_action().unwrap_or(Action::COOPERATE),
+        }
+    }
+
+    fn memory_depth(&self) -> Option<usize> {
+        Some(1)
+    }
+
+    fn is_deterministic(&self) -> bool {
+        true
+    }
+}
+
+/// Cooperate with a probability that depends only on the last round
+///
+/// `cooperation` holds the probabilities after CC, CD, DC, and DD, own
+/// action first; `initial` is the probability of cooperating in the first
+/// round. Many classic strategies are points in this space: Tit-for-Tat is
+/// (1, 0, 1, 0), Pavlov is (1, 0, 0, 1), Always Defect is (0, 0, 0, 0), and
+/// Generous Tit-for-Tat is (1, g, 1, g).
+pub struct MemoryOneStrategy {
+    name: &'static str,
+    cooperation: [f64; 4],
+    initial: f64,
+    rng: StrategyRng,
+}
+
+impl MemoryOneStrategy {
+    pub fn new(cooperation: [f64; 4], initial: f64, seed: u64) -> MemoryOneStrategy {
+        MemoryOneStrategy {
+            name: "memory-one",
+            cooperation,
+            initial,
+            rng: StrategyRng::seed_from_u64(seed),
+        }
+    }
+
+    /// The same strategy registered under another name
+    fn named(self, name: &'static str) -> MemoryOneStrategy {
+        MemoryOneStrategy { name, ..self }
+    }
+
+    /// An extortionate zero-determinant strategy (Press and Dyson)
+    ///
+    /// It enforces `s_X - P = chi (s_Y - P)` between its own long-run score
+    /// and its opponent's, so an opponent can only raise its own score by
+    /// raising the extortioner's `chi` times as much. `chi = 2` is Extort-2.
+    pub fn extortionate(
+        payoffs: &PayoffMatrix,
+        chi: f64,
+        phi: Option<f64>,
+        seed: u64,
+    ) -> Result<MemoryOneStrategy, String> {
+        zero_determinant(payoffs, payoffs.punishment(), chi, phi, seed)
+            .map(|strategy| strategy.named("extort"))
+    }
+
+    /// A generous zero-determinant strategy (Stewart and Plotkin)
+    ///
+    /// It enforces `s_X - R = chi (s_Y - R)`: when the opponent falls short
+    /// of mutual cooperation, it falls short by more. `chi = 2` is ZDGTFT-2.
+    pub fn generous_zero_determinant(
+        payoffs: &PayoffMatrix,
+        chi: f64,
+        phi: Option<f64>,
+        seed: u64,
+    ) -> Result<MemoryOneStrategy, String> {
+        zero_determinant(payoffs, payoffs.reward(), chi, phi, seed)
+            .map(|strategy| strategy.named("zd-gtft"))
+    }
+
+    /// An equalizer: pins the opponent's long-run score to `score`,
+    /// whatever the opponent does
+    ///
+    /// `score` must lie between the punishment and reward payoffs. `beta`
+    /// scales how strongly it responds and defaults to half its largest
+    /// valid value.
+    pub fn equalizer(
+        payoffs: &PayoffMatrix,
+        score: f64,
+        beta: Option<f64>,
+        seed: u64,
+    ) -> Result<MemoryOneStrategy, String> {
+        let opponent = opponent_payoffs(payoffs);
+        let direction = [
+            score - opponent[0],
+            score - opponent[1],
+            score - opponent[2],
+            score - opponent[3],
+        ];
+        scaled_memory_one(direction, beta, seed)
+            .map(|strategy| strategy.named("equalizer"))
+            .map_err(|reason| format!("cannot equalize at score {}: {}", score, reason))
+    }
+
+    fn cooperate_with(&mut self, probability: f64) -> Action {
+        if self.rng.gen::<f64>() < probability {
+            Action::COOPERATE
+        } else {
+            Action::DEFECT
+        }
+    }
+}
+
+/// Own payoffs after CC, CD, DC, DD
+fn own_payoffs(payoffs: &PayoffMatrix) -> [f64; 4] {
+    [
+        payoffs.reward(),
+        payoffs.sucker(),
+        payoffs.temptation(),
+        payoffs.punishment(),
+    ]
+}
+
+/// The opponent's payoffs after CC, CD, DC, DD, own action first
+fn opponent_payoffs(payoffs: &PayoffMatrix) -> [f64; 4] {
+    let own = own_payoffs(payoffs);
+    [own[0], own[2], own[1], own[3]]
+}
+
+/// The zero-determinant strategy enforcing `s_X - baseline = chi (s_Y - baseline)`
+fn zero_determinant(
+    payoffs: &PayoffMatrix,
+    baseline: f64,
+    chi: f64,
+    phi: Option<f64>,
+    seed: u64,
+) -> Result<MemoryOneStrategy, String> {
+    if chi < 1.0 {
+        return Err(format!("chi {} must be at least 1", chi));
+    }
+    let own = own_payoffs(payoffs);
+    let opponent = opponent_payoffs(payoffs);
+    let mut direction = [0.0; 4];
+    for i in 0..4 {
+        direction[i] = (own[i] - baseline) - chi * (opponent[i] - baseline);
+    }
+    scaled_memory_one(direction, phi, seed)
+        .map_err(|reason| format!("no zero-determinant strategy with chi {}: {}", chi, reason))
+}
+
+/// Press and Dyson's construction: the cooperation probabilities are
+/// `(1, 1, 0, 0) + scale * direction`, a valid strategy only while each
+/// stays within [0, 1]
+///
+/// A missing `scale` picks half the largest valid one.
+fn scaled_memory_one(
+    direction: [f64; 4],
+    scale: Option<f64>,
+    seed: u64,
+) -> Result<MemoryOneStrategy, String> {
+    const BASE: [f64; 4] = [1.0, 1.0, 0.0, 0.0];
+    let largest = BASE
+        .iter()
+        .zip(&direction)
+        .map(|(&base, &d)| {
+            if d < 0.0 {
+                base / -d
+            } else if d > 0.0 {
+                (1.0 - base) / d
+            } else {
+                f64::INFINITY
+            }
+        })
+        .fold(f64::INFINITY, f64::min);
+    if largest <= 0.0 || !largest.is_finite() {
+        return Err("the payoffs leave no valid scale".to_owned());
+    }
+    let scale = scale.unwrap_or(largest / 2.0);
+    if scale <= 0.0 || scale > largest {
+        return Err(format!(
+            "scale {} must be above 0 and at most {}",
+            scale, largest
+        ));
+    }
+
+    let mut cooperation = BASE;
+    for (p, d) in cooperation.iter_mut().zip(&direction) {
+        *p = (*p + scale * d).clamp(0.0, 1.0);
+    }
+    Ok(MemoryOneStrategy::new(cooperation, 1.0, seed))
+}
+
+impl Strategy for MemoryOneStrategy {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn choose(&mut self, history: &History) -> Action {
+        let probability = match history.last() {
+            None => self.initial,
+            Some(round) => {
+                // abstaining counts as not cooperating
+                let defected = |action| (action != Action::COOPERATE) as usize;
+                let outcome = 2 * defected(round.own) + defected(round.opponent);
+                self.cooperation[outcome]
+            }
+        };
+        self.cooperate_with(probability)
+    }
+
+    fn memory_depth(&self) -> Option<usize> {
+        Some(1)
+    }
+
+    fn is_deterministic(&self) -> bool {
+        let certain = |p: &f64| *p == 0.0 || *p == 1.0;
+        certain(&self.initial) && self.cooperation.iter().all(certain)
+    }
+
+    /// The cooperation probabilities, as `memory-one` takes them, whichever
+    /// name the strategy was built under
+    fn parameters(&self) -> Parameters {
+        let names = ["p_cc", "p_cd", "p_dc", "p_dd"];
+        names
+            .iter()
+            .zip(&self.cooperation)
+            .map(|(name, &p)| (name.to_string(), p))
+            .chain(std::iter::once(("initial".to_owned(), self.initial)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::PlayerOutcome;
+    use crate::strategy::{GroupRound, PlayerInfo, Round};
+    use rand::rngs::StdRng;
+    use rand::RngCore;
+    const C: Action = Action::COOPERATE;
+    const D: Action = Action::DEFECT;
+
+    /// A full history from (own, opponent) action pairs
+    fn history(rounds: &[(Action, Action)]) -> History {
+        let mut history = History::new();
+        for &(own, opponent) in rounds {
+            history.push(Round {
+                own,
+                opponent,
+                hidden: false,
+                outcome: PlayerOutcome {
+                    amount: 0.0,
+                    ..PayoffMatrix::default().score(own, opponent).red
+                },
+            });
+        }
+        history
+    }
+
+    fn first() -> History {
+        History::new()
+    }
+
+    fn after(mine: Action, theirs: Action) -> History {
+        history(&[(mine, theirs)])
+    }
+
+    #[test]
+    fn always_strategies() {
+        assert_eq!(AlwaysCooperate {}.choose(&after(C, D)), C);
+        assert_eq!(AlwaysDefect {}.choose(&after(D, C)), D);
+    }
+
+    #[test]
+    fn tit_for_tat_copies_opponent() {
+        let mut s = TitForTat {};
+        assert_eq!(s.choose(&first()), C);
+        assert_eq!(s.choose(&after(C, D)), D);
+        assert_eq!(s.choose(&after(D, C)), C);
+    }
+
+    #[test]
+    fn mirror_answers_the_first_move() {
+        let mut s = Mirror {};
+        let mut h = after(C, C);
+        h.set_turn(Some(Turn::Second(D)));
+        assert_eq!(s.choose(&h), D);
+        h.set_turn(Some(Turn::First));
+        assert_eq!(s.choose(&h), C);
+        assert_eq!(s.choose(&after(C, D)), D);
+    }
+
+    #[test]
+    fn tit_for_two_tats_needs_two_defections() {
+        let mut s = TitForTwoTats {};
+        assert_eq!(s.choose(&first()), C);
+        assert_eq!(s.choose(&history(&[(C, D)])), C);
+        assert_eq!(s.choose(&history(&[(C, D), (C, D)])), D);
+        assert_eq!(s.choose(&history(&[(C, D), (C, D), (D, C)])), C);
+        assert_eq!(s.choose(&history(&[(C, D), (D, C), (C, D)])), C);
+    }
+
+    #[test]
+    fn grim_trigger_never_forgives() {
+        let mut s = GrimTrigger::default();
+        assert_eq!(s.choose(&first()), C);
+        assert_eq!(s.choose(&after(C, C)), C);
+        assert_eq!(s.choose(&after(C, D)), D);
+        assert_eq!(s.choose(&after(D, C)), D);
+    }
+
+    #[test]
+    fn image_scoring_judges_by_reputation() {
+        let mut s = ImageScoring::new(0);
+        let mut h = after(C, D);
+        assert_eq!(s.choose(&h), D);
+        let reputation = |reputation| PlayerInfo {
+            reputation: Some(reputation),
+            ..PlayerInfo::default()
+        };
+        h.set_players(reputation(0), reputation(0));
+        assert_eq!(s.choose(&h), C);
+        h.set_players(reputation(0), reputation(-1));
+        assert_eq!(s.choose(&h), D);
+    }
+
+    #[test]
+    fn signal_tit_for_tat_heeds_warnings() {
+        let mut s = SignalTitForTat {};
+        let mut h = after(C, C);
+        assert_eq!(s.signal(&h), Some(C));
+        assert_eq!(s.choose(&h), C);
+        h.set_opponent_signal(Some(D));
+        assert_eq!(s.choose(&h), D);
+        assert_eq!(s.signal(&after(C, D)), Some(D));
+    }
+
+    #[test]
+    fn pavlov_win_stay_lose_shift() {
+        let mut s = Pavlov {};
+        assert_eq!(s.choose(&first()), C);
+        assert_eq!(s.choose(&after(C, C)), C);
+        assert_eq!(s.choose(&after(D, C)), D);
+        assert_eq!(s.choose(&after(C, D)), D);
+        assert_eq!(s.choose(&after(D, D)), C);
+    }
+
+    #[test]
+    fn generous_tit_for_tat_extremes() {
+        let mut strict = GenerousTitForTat::new(0.0, 1);
+        assert_eq!(strict.choose(&first()), C);
+        assert_eq!(strict.choose(&after(C, D)), D);
+        assert_eq!(strict.choose(&after(D, C)), C);
+
+        let mut saint = GenerousTitForTat::new(1.0, 1);
+        assert_eq!(saint.choose(&after(C, D)), C);
+    }
+
+    #[test]
+    fn davis_and_shubik_from_the_first_tournament() {
+        let mut davis = Davis::default();
+        assert_eq!(davis.choose(&after(C, D)), C);
+        assert_eq!(davis.choose(&history(&[(C, C); 10])), D);
+
+        let mut shubik = Shubik::default();
+        assert_eq!(shubik.choose(&after(C, D)), D);
+        assert_eq!(shubik.choose(&after(D, C)), C);
+        assert_eq!(shubik.choose(&after(C, D)), D);
+        assert_eq!(shubik.choose(&after(D, C)), D);
+        assert_eq!(shubik.choose(&after(D, C)), C);
+    }
+
+    #[test]
+    fn q_learning_avoids_a_losing_action() {
+        let mut s = QLearning::new(1.0, 0.0, 0.0, 1, 0.0, 1).unwrap();
+        let suckered = Round {
+            own: C,
+            opponent: D,
+            hidden: false,
+            outcome: PlayerOutcome {
+                payoff: Payoff::SUCKER,
+                amount: -1.0,
+            },
+        };
+        let mut h = History::with_depth(Some(1));
+        assert_eq!(s.choose(&h), C);
+        h.push(suckered);
+        assert_eq!(s.choose(&h), C);
+        h.push(suckered);
+        assert_eq!(s.choose(&h), D);
+    }
+
+    #[test]
+    fn opponent_model_learns_the_opponent() {
+        let mut s = OpponentModel::new(PayoffMatrix::default(), 0.9).unwrap();
+        assert_eq!(s.choose(&first()), C);
+        let suckered = history(&[(C, D), (C, D), (C, D)]);
+        assert_eq!(s.choose(&suckered), D);
+        assert_eq!(s.model(), [1.0, 0.5, 1.0, 1.0]);
+        assert!(OpponentModel::new(PayoffMatrix::default(), 1.0).is_err());
+    }
+
+    #[test]
+    fn meta_strategy_tries_each_child_then_exploits_a_pushover() {
+        let children: Vec<Box<dyn Strategy>> =
+            vec![Box::new(AlwaysCooperate {}), Box::new(AlwaysDefect {})];
+        let mut s = MetaStrategy::new(children, Some(1), 2).unwrap();
+        let mut h = History::new();
+        let play = |s: &mut MetaStrategy, h: &mut History| {
+            let own = s.choose(h);
+            h.push(Round {
+                own,
+                opponent: C,
+                hidden: false,
+                outcome: PayoffMatrix::default().score(own, C).red,
+            });
+            own
+        };
+        assert_eq!(play(&mut s, &mut h), C);
+        assert_eq!(play(&mut s, &mut h), C);
+        assert_eq!(play(&mut s, &mut h), D);
+        assert_eq!(s.active(), "always-defect");
+        for _ in 0..4 {
+            assert_eq!(play(&mut s, &mut h), D);
+        }
+        assert!(MetaStrategy::new(Vec::new(), None, 2).is_err());
+    }
+
+    #[test]
+    fn random_cooperates_as_often_as_asked() {
+        let mut sure = RandomStrategy::new(1.0, 1);
+        assert!((0..20).all(|_| sure.choose(&first()) == C));
+        assert!(sure.is_deterministic());
+
+        let mut biased = RandomStrategy::new(0.7, 1);
+        let cooperations = (0..1000).filter(|_| biased.choose(&first()) == C).count();
+        assert!((650..750).contains(&cooperations));
+
+        let registry = crate::registry::StrategyRegistry::new();
+        let args = crate::registry::StrategyArgs {
+            parameters: &Parameters::new(),
+            payoffs: &PayoffMatrix::default(),
+            seed: 1,
+        };
+        let shorthand = registry.create("random(0.7)", &args).unwrap();
+        assert_eq!(shorthand.parameters()["p_cooperate"], 0.7);
+        assert!(registry.create("random(1.5)", &args).is_err());
+        assert!(registry.create("random(0.5, 0.5)", &args).is_err());
+    }
+
+    #[test]
+    fn state_resets_and_survives_a_round_trip() {
+        let mut grim = GrimTrigger::default();
+        assert_eq!(grim.choose(&after(C, D)), D);
+        let saved = grim.save_state().unwrap();
+        grim.reset();
+        assert_eq!(grim.choose(&after(C, C)), C);
+        grim.load_state(&saved).unwrap();
+        assert_eq!(grim.choose(&after(C, C)), D);
+
+        let mut learner = QLearning::new(0.5, 0.9, 0.0, 1, 0.0, 1).unwrap();
+        let mut h = History::new();
+        for _ in 0..5 {
+            let own = learner.choose(&h);
+            h.push(Round {
+                own,
+                opponent: D,
+                hidden: false,
+                outcome: PayoffMatrix::default().score(own, D).red,
+            });
+        }
+        let saved = learner.save_state().unwrap();
+        let mut restored = QLearning::new(0.5, 0.9, 0.0, 1, 0.0, 2).unwrap();
+        restored.load_state(&saved).unwrap();
+        assert_eq!(restored.save_state().unwrap(), saved);
+        assert_eq!(restored.choose(&h), learner.choose(&h));
+
+        assert!(TitForTat {}.load_state(&saved).is_err());
+    }
+
+    #[test]
+    fn contrite_takes_the_retaliation_it_earned() {
+        let mut s = Contrite::new(Box::new(TitForTat {}));
+        assert_eq!(s.name(), "contrite(tit-for-tat)");
+        assert_eq!(s.choose(&first()), C);
+        // noise turned the cooperation into a defection
+        assert_eq!(s.choose(&history(&[(D, C)])), C);
+        assert_eq!(s.choose(&history(&[(D, C), (C, D)])), C);
+        assert_eq!(s.choose(&history(&[(D, C), (C, D), (C, D)])), D);
+
+        let mut g = Generous::new(Box::new(AlwaysDefect {}), 1.0, 1);
+        assert_eq!(g.choose(&first()), D);
+        assert_eq!(g.choose(&after(D, D)), C);
+    }
+
+    /// Holds a fixed amount of memory, and tells
+    struct Holder(usize);
+
+    impl Strategy for Holder {
+        fn name(&self) -> &str {
+            "holder"
+        }
+
+        fn choose(&mut self, _history: &History) -> Action {
+            C
+        }
+
+        fn memory_used(&self) -> Option<usize> {
+            Some(self.0)
+        }
+    }
+
+    #[test]
+    fn wrappers_report_the_memory_they_wrap() {
+        let generous = Generous::new(Box::new(Holder(2048)), 0.1, 1);
+        assert_eq!(generous.memory_used(), Some(2048));
+        let contrite = Contrite::new(Box::new(Holder(1024)));
+        assert_eq!(contrite.memory_used(), Some(1024));
+        let meta = MetaStrategy::new(
+            vec![
+                Box::new(Holder(1024)),
+                Box::new(TitForTat {}),
+                Box::new(Holder(512)),
+            ],
+            None,
+            10,
+        )
+        .unwrap();
+        assert_eq!(meta.memory_used(), Some(1536));
+        assert_eq!(Contrite::new(Box::new(TitForTat {})).memory_used(), None);
+    }
+
+    #[test]
+    fn openings_override_the_first_moves() {
+        let mut s = Opening::suspicious(1, Box::new(TitForTat {}));
+        assert_eq!(s.name(), "suspicious(tit-for-tat)");
+        assert_eq!(s.choose(&first()), D);
+        assert_eq!(s.choose(&after(D, C)), C);
+        assert_eq!(s.choose(&history(&[(D, C), (C, D)])), D);
+
+        let mut t = Opening::trusting(2, Box::new(AlwaysDefect {}));
+        assert_eq!(t.name(), "trusting(2, always-defect)");
+        assert_eq!(t.choose(&first()), C);
+        assert_eq!(t.choose(&after(C, D)), C);
+        assert_eq!(t.choose(&history(&[(C, D), (C, D)])), D);
+        assert_eq!(t.memory_depth(), Some(1));
+    }
+
+    #[test]
+    fn handshake_cooperates_only_with_its_own_kind() {
+        let mut s = Handshake::new("handshake", "CD").unwrap();
+        assert_eq!(s.choose(&first()), C);
+        assert_eq!(s.choose(&after(C, C)), D);
+        assert_eq!(s.choose(&history(&[(C, C), (D, D)])), C);
+        assert_eq!(s.choose(&history(&[(C, C), (D, D), (C, D)])), C);
+        assert_eq!(s.choose(&history(&[(C, C), (D, C)])), D);
+        assert!(Handshake::new("handshake", "CX").is_err());
+    }
+
+    #[test]
+    fn machines_round_trip_and_play_their_states() {
+        let tft = Machine::parse("C0,1/D0,1").unwrap();
+        assert_eq!(tft.to_string(), "C0,1/D0,1");
+        assert!(Machine::parse("C0,2/D0,1").is_err());
+        assert!(Machine::parse("X0,0").is_err());
+
+        let mut s = FiniteStateMachine::new(tft);
+        assert_eq!(s.name(), "fsm:C0,1/D0,1");
+        let mut h = history(&[(C, D)]);
+        assert_eq!(s.choose(&first()), C);
+        assert_eq!(s.choose(&h), D);
+        h.push(Round {
+            own: D,
+            opponent: C,
+            hidden: false,
+            outcome: PlayerOutcome {
+                payoff: Payoff::TEMPTATION,
+                amount: 0.0,
+            },
+        });
+        assert_eq!(s.choose(&h), C);
+
+        // a fresh instance catches up with the history it is given
+        let mut fresh = FiniteStateMachine::new(Machine::parse("C0,1/D0,1").unwrap());
+        assert_eq!(fresh.choose(&history(&[(C, C), (C, D)])), D);
+    }
+
+    #[test]
+    fn lookup_tables_round_trip_and_read_the_last_rounds() {
+        let tft = LookupTable::parse("C/CDCD").unwrap();
+        assert_eq!(tft.to_string(), "C/CDCD");
+        assert!(LookupTable::parse("C/CDC").is_err());
+        assert!(LookupTable::parse("CDCD").is_err());
+
+        let mut s = LookupStrategy::new("lookup:tft", tft);
+        assert_eq!(s.choose(&first()), C);
+        assert_eq!(s.choose(&after(C, D)), D);
+        assert_eq!(s.choose(&after(D, C)), C);
+
+        // defect only after the opponent defected twice running
+        let mut table = vec![C; 16];
+        table[0b0101] = D;
+        let mut tf2t = LookupStrategy::new(
+            "lookup:tf2t",
+            LookupTable {
+                initial: vec![C, C],
+                table,
+            },
+        );
+        assert_eq!(tf2t.choose(&after(C, D)), C);
+        assert_eq!(tf2t.choose(&history(&[(C, D), (C, C)])), C);
+        assert_eq!(tf2t.choose(&history(&[(C, C), (C, D), (C, D)])), D);
+    }
+
+    #[test]
+    fn memory_one_reproduces_deterministic_strategies() {
+        let mut tft = MemoryOneStrategy::new([1.0, 0.0, 1.0, 0.0], 1.0, 1);
+        assert_eq!(tft.choose(&first()), C);
+        assert_eq!(tft.choose(&after(C, D)), D);
+        assert_eq!(tft.choose(&after(D, C)), C);
+
+        let mut pavlov = MemoryOneStrategy::new([1.0, 0.0, 0.0, 1.0], 1.0, 1);
+        assert_eq!(pavlov.choose(&after(C, C)), C);
+        assert_eq!(pavlov.choose(&after(C, D)), D);
+        assert_eq!(pavlov.choose(&after(D, C)), D);
+        assert_eq!(pavlov.choose(&after(D, D)), C);
+    }
+
+    /// The payoffs Press and Dyson used: R 3, S 0, T 5, P 1
+    fn classic_payoffs() -> PayoffMatrix {
+        PayoffMatrix::new(3.0, 5.0, 1.0, 0.0)
+    }
+
+    fn assert_probabilities(strategy: &MemoryOneStrategy, expected: [f64; 4]) {
+        for (p, e) in strategy.cooperation.iter().zip(&expected) {
+            assert!(
+                (p - e).abs() < 1e-9,
+                "{:?} != {:?}",
+                strategy.cooperation,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn zero_determinant_strategies_match_the_literature() {
+        let payoffs = classic_payoffs();
+        let extort2 = MemoryOneStrategy::extortionate(&payoffs, 2.0, Some(1.0 / 18.0), 1).unwrap();
+        assert_probabilities(&extort2, [8.0 / 9.0, 0.5, 1.0 / 3.0, 0.0]);
+
+        let zdgtft2 =
+            MemoryOneStrategy::generous_zero_determinant(&payoffs, 2.0, Some(0.125), 1).unwrap();
+        assert_probabilities(&zdgtft2, [1.0, 0.125, 1.0, 0.25]);
+    }
+
+    #[test]
+    fn zero_determinant_constraints_are_checked() {
+        let payoffs = classic_payoffs();
+        assert!(MemoryOneStrategy::extortionate(&payoffs, 0.5, None, 1).is_err());
+        assert!(MemoryOneStrategy::extortionate(&payoffs, 2.0, Some(1.0), 1).is_err());
+        assert!(MemoryOneStrategy::equalizer(&payoffs, 2.0, None, 1).is_ok());
+        assert!(MemoryOneStrategy::equalizer(&payoffs, 4.0, None, 1).is_err());
+    }
+
+    #[test]
+    fn conditional_cooperator_follows_the_group() {
+        let mut s = ConditionalCooperator::new(0.5);
+        let mut group = GroupHistory::with_depth(Some(1));
+        group.set_size(5);
+        assert_eq!(s.choose_in_group(&first(), &group), C);
+        group.push(GroupRound {
+            own: C,
+            others_cooperated: 2,
+        });
+        assert_eq!(s.choose_in_group(&first(), &group), C);
+        group.push(GroupRound {
+            own: C,
+            others_cooperated: 1,
+        });
+        assert_eq!(s.choose_in_group(&first(), &group), D);
+    }
+
+    #[test]
+    fn bounded_history_keeps_recent_rounds() {
+        let mut h = History::with_depth(Some(2));
+        for &(own, opponent) in &[(C, C), (C, D), (D, D)] {
+            h.push(Round {
+                own,
+                opponent,
+                hidden: false,
+                outcome: PlayerOutcome {
+                    amount: 0.0,
+                    ..PayoffMatrix::default().score(own, opponent).red
+                },
+            });
+        }
+        assert_eq!(h.round(), 3);
+        assert_eq!(h.rounds().len(), 2);
+        assert_eq!(h.opponent_actions().collect::<Vec<_>>(), vec![D, D]);
+        assert_eq!(h.last_payoff(), Payoff::PUNISHMENT);
+    }
+
+    #[test]
+    fn random_draws_are_noted_by_their_seat() {
+        use crate::randomness::{self, Draw, Seat};
+
+        let seat = Seat::new();
+        let mut s = randomness::seated(&seat, || RandomStrategy::new(0.5, 7));
+        let mut stream = StdRng::seed_from_u64(7);
+        s.choose(&first());
+        seat.at(1);
+        s.choose(&first());
+        s.choose(&first());
+        let (a, b, c) = (stream.next_u64(), stream.next_u64(), stream.next_u64());
+        assert_eq!(
+            seat.take(),
+            vec![
+                Draw {
+                    round: 0,
+                    values: vec![a],
+                },
+                Draw {
+                    round: 1,
+                    values: vec![b, c],
+                },
+            ]
+        );
+        assert!(seat.take().is_empty());
+        // built unseated, draws are made but not kept
+        let mut unseated = RandomStrategy::new(0.5, 7);
+        unseated.choose(&first());
+        assert!(seat.take().is_empty());
+    }
+}
@@ -0,0 +1,471 @@
+use futures::future::BoxFuture;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::VecDeque;
+
+use crate::game::{Action, Payoff, PlayerOutcome, MAX_ACTIONS};
+use crate::registry::{ParameterInfo, Parameters};
+use crate::reputation::Reputation;
+
+/// One played round, from one prisoner's point of view
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Round {
+    /// The action this prisoner executed
+    pub own: Action,
+
+    /// The opponent's action, as reported to this prisoner
+    ///
+    /// If it was hidden, this is the prisoner's own action, as a stand-in
+    /// for strategies that don't ask for [`observation`](Round::observation).
+    pub opponent: Action,
+
+    /// What this prisoner got from the round
+    #[serde(flatten)]
+    pub outcome: PlayerOutcome,
+
+    /// The opponent's action wasn't shown to this prisoner
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub hidden: bool,
+}
+
+impl Round {
+    /// What the prisoner saw its opponent do: one of the actions, or
+    /// `None` if it was hidden
+    pub fn observation(&self) -> Option<Action> {
+        if self.hidden {
+            None
+        } else {
+            Some(self.opponent)
+        }
+    }
+}
+
+/// An observable marker, in [0, 1], that a player carries regardless of
+/// how it plays
+pub type Tag = f64;
+
+/// What the players can see of each other besides their actions
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct PlayerInfo {
+    /// The player's tag, if it has one
+    pub tag: Option<Tag>,
+
+    /// Its public reputation going into this round, if the game keeps
+    /// reputations; see [`crate::reputation`]
+    pub reputation: Option<Reputation>,
+}
+
+impl PlayerInfo {
+    /// How far apart two players' tags are, if both have one
+    pub fn tag_distance(&self, other: &PlayerInfo) -> Option<f64> {
+        Some((self.tag? - other.tag?).abs())
+    }
+}
+
+/// A player's place in a round of an alternating game
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Turn {
+    /// Moving first, without knowing what the opponent will do
+    First,
+
+    /// Moving second, having seen the opponent's move this round
+    Second(Action),
+}
+
+/// What a prisoner knows about the match so far
+///
+/// When a strategy has a bounded [`Strategy::memory_depth`], only that many
+/// recent rounds are kept, so memory-one strategies cost O(1) per round no
+/// matter how long the match runs.
+#[derive(Clone, Debug, Default)]
+pub struct History {
+    rounds: VecDeque<Round>,
+    depth: Option<usize>,
+    played: usize,
+    own: PlayerInfo,
+    opponent: PlayerInfo,
+    opponent_signal: Option<Action>,
+    turn: Option<Turn>,
+}
+
+impl History {
+    /// A history that keeps every round
+    pub fn new() -> History {
+        History::default()
+    }
+
+    /// A history that keeps only the last `depth` rounds, or every round if `None`
+    pub fn with_depth(depth: Option<usize>) -> History {
+        History {
+            depth,
+            ..History::default()
+        }
+    }
+
+    pub fn push(&mut self, round: Round) {
+        if self.depth == Some(0) {
+            self.played += 1;
+            return;
+        }
+        if let Some(depth) = self.depth {
+            if self.rounds.len() >= depth {
+                self.rounds.pop_front();
+            }
+        }
+        self.rounds.push_back(round);
+        self.played += 1;
+    }
+
+    /// What this prisoner's opponent can see of it
+    pub fn own_info(&self) -> &PlayerInfo {
+        &self.own
+    }
+
+    /// What this prisoner can see of its opponent
+    pub fn opponent_info(&self) -> &PlayerInfo {
+        &self.opponent
+    }
+
+    /// The opponent's public reputation going into this round, if the game
+    /// keeps reputations
+    pub fn opponent_reputation(&self) -> Option<Reputation> {
+        self.opponent.reputation
+    }
+
+    pub fn set_players(&mut self, own: PlayerInfo, opponent: PlayerInfo) {
+        self.own = own;
+        self.opponent = opponent;
+    }
+
+    /// What the opponent signalled it would do this round, if the game has
+    /// cheap talk and it said anything; see [`Strategy::signal`]
+    pub fn opponent_signal(&self) -> Option<Action> {
+        self.opponent_signal
+    }
+
+    pub fn set_opponent_signal(&mut self, signal: Option<Action>) {
+        self.opponent_signal = signal;
+    }
+
+    /// Whether this prisoner moves first or second this round, in an
+    /// alternating game; `None` when both move at once
+    pub fn turn(&self) -> Option<Turn> {
+        self.turn
+    }
+
+    pub fn set_turn(&mut self, turn: Option<Turn>) {
+        self.turn = turn;
+    }
+
+    /// The round about to be played, starting from 0
+    pub fn round(&self) -> usize {
+        self.played
+    }
+
+    /// The most recent round, the memory-one view of the match
+    pub fn last(&self) -> Option<&Round> {
+        self.rounds.back()
+    }
+
+    /// The payoff received last round, `Payoff::NULL` before the first round
+    pub fn last_payoff(&self) -> Payoff {
+        self.last()
+            .map_or(Payoff::NULL, |round| round.outcome.payoff)
+    }
+
+    /// What the opponent did last round, `None` before the first round
+    pub fn opponent_last_action(&self) -> Option<Action> {
+        self.last().map(|round| round.opponent)
+    }
+
+    /// The retained rounds, oldest first
+    pub fn rounds(&self) -> impl DoubleEndedIterator<Item = &Round> + ExactSizeIterator {
+        self.rounds.iter()
+    }
+
+    /// The last `n` retained rounds, oldest first
+    pub fn recent(&self, n: usize) -> impl DoubleEndedIterator<Item = &Round> + ExactSizeIterator {
+        self.rounds.iter().skip(self.rounds.len().saturating_sub(n))
+    }
+
+    pub fn own_actions(&self) -> impl DoubleEndedIterator<Item = Action> + '_ {
+        self.rounds.iter().map(|round| round.own)
+    }
+
+    pub fn opponent_actions(&self) -> impl DoubleEndedIterator<Item = Action> + '_ {
+        self.rounds.iter().map(|round| round.opponent)
+    }
+
+    /// What the prisoner saw of each opponent action, `None` where it was
+    /// hidden
+    pub fn opponent_observations(&self) -> impl DoubleEndedIterator<Item = Option<Action>> + '_ {
+        self.rounds.iter().map(Round::observation)
+    }
+}
+
+/// One round of an n-player game, from one prisoner's point of view
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GroupRound {
+    /// The action this prisoner executed
+    pub own: Action,
+
+    /// How many of the other players cooperated
+    pub others_cooperated: usize,
+}
+
+/// What a prisoner knows about an n-player game so far
+///
+/// Retains rounds the same way as [`History`].
+#[derive(Clone, Debug, Default)]
+pub struct GroupHistory {
+    size: usize,
+    rounds: VecDeque<GroupRound>,
+    depth: Option<usize>,
+}
+
+impl GroupHistory {
+    pub fn with_depth(depth: Option<usize>) -> GroupHistory {
+        GroupHistory {
+            depth,
+            ..GroupHistory::default()
+        }
+    }
+
+    /// Players in the group, this prisoner included; 0 outside a group game
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn set_size(&mut self, size: usize) {
+        self.size = size;
+    }
+
+    pub fn push(&mut self, round: GroupRound) {
+        if self.depth == Some(0) {
+            return;
+        }
+        if let Some(depth) = self.depth {
+            if self.rounds.len() >= depth {
+                self.rounds.pop_front();
+            }
+        }
+        self.rounds.push_back(round);
+    }
+
+    pub fn last(&self) -> Option<&GroupRound> {
+        self.rounds.back()
+    }
+
+    /// The fraction of the other players who cooperated last round, `None`
+    /// before the first round
+    pub fn last_cooperation_rate(&self) -> Option<f64> {
+        let others = self.size.saturating_sub(1).max(1);
+        self.last()
+            .map(|round| round.others_cooperated as f64 / others as f64)
+    }
+
+    /// The retained rounds, oldest first
+    pub fn rounds(&self) -> impl DoubleEndedIterator<Item = &GroupRound> + ExactSizeIterator {
+        self.rounds.iter()
+    }
+}
+
+/// What a strategy says about itself, see [`Strategy`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StrategyInfo {
+    pub name: String,
+
+    /// A name for people, the registered one if any and `name` otherwise
+    #[serde(default)]
+    pub display_name: String,
+
+    /// Who devised the strategy, if the registry says
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+
+    /// Past rounds it looks at, `None` for the whole match
+    pub memory_depth: Option<usize>,
+    pub deterministic: bool,
+    pub parameters: Parameters,
+
+    /// What the registry says the strategy does
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub description: String,
+
+    /// The parameters the registry accepts for the strategy, with their
+    /// defaults
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub accepts: Vec<ParameterInfo>,
+
+    /// Whether it plays games of more than two actions, given by a table
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub any_actions: bool,
+}
+
+impl StrategyInfo {
+    pub fn of(strategy: &dyn Strategy) -> StrategyInfo {
+        StrategyInfo {
+            name: strategy.name().to_owned(),
+            display_name: strategy.name().to_owned(),
+            author: None,
+            memory_depth: strategy.memory_depth(),
+            deterministic: strategy.is_deterministic(),
+            parameters: strategy.parameters(),
+            description: String::new(),
+            accepts: Vec::new(),
+            any_actions: (3..=MAX_ACTIONS).all(|actions| strategy.supports(actions)),
+        }
+    }
+}
+
+/// Strategies are `Send` so a prisoner can be started on any arbiter
+pub trait Strategy: Send {
+    /// The name the strategy is registered under, e.g. `tit-for-tat`, or
+    /// the `scheme:location` it was loaded from
+    fn name(&self) -> &str;
+
+    fn choose(&mut self, history: &History) -> Action;
+
+    /// Choose in an n-player game such as public goods
+    ///
+    /// `history` sees the group as a single opponent who cooperated when
+    /// most of the others did, and by default the strategy plays that
+    /// opponent with [`Strategy::choose`]. Group-aware strategies look at
+    /// `group` instead.
+    fn choose_in_group(&mut self, history: &History, _group: &GroupHistory) -> Action {
+        self.choose(history)
+    }
+
+    /// Decide without blocking, for strategies whose answers come from
+    /// elsewhere, such as another process
+    ///
+    /// A prisoner calls this first, and only falls back to
+    /// [`Strategy::choose`] if it returns `None`, the default. The future
+    /// can't borrow `history`, so it must take what it needs up front.
+    fn choose_async(
+        &mut self,
+        _history: &History,
+    ) -> Option<BoxFuture<'static, Result<Action, String>>> {
+        None
+    }
+
+    /// What to tell the opponent before this round, when the game has cheap
+    /// talk
+    ///
+    /// The signal binds nobody: the strategy may go on to do something else,
+    /// and the opponent may ignore it. The default says nothing. Called
+    /// before [`Strategy::choose`] for the same round, with the same history
+    /// except for the opponent's signal.
+    fn signal(&mut self, _history: &History) -> Option<Action> {
+        None
+    }
+
+    /// How many past rounds the strategy looks at, `None` for the whole match
+    ///
+    /// The prisoner only retains this many rounds of history.
+    fn memory_depth(&self) -> Option<usize> {
+        None
+    }
+
+    /// Whether the same history always gets the same choice
+    ///
+    /// `false` by default, as the strategy may draw random numbers or ask
+    /// someone else.
+    fn is_deterministic(&self) -> bool {
+        false
+    }
+
+    /// Whether the strategy can play a game of `actions` actions given by a
+    /// table; see [`PayoffMatrix::with_table`](crate::game::PayoffMatrix::with_table)
+    ///
+    /// By default only one of cooperating and defecting, as most strategies
+    /// know nothing of other actions.
+    fn supports(&self, actions: usize) -> bool {
+        actions == 2
+    }
+
+    /// Bytes of memory the strategy holds, for a strategy that can tell,
+    /// such as a WebAssembly guest; `None`, the default, for one that can't
+    ///
+    /// A budget's `memory_kb` only holds strategies that can tell.
+    fn memory_used(&self) -> Option<usize> {
+        None
+    }
+
+    /// The parameters that decide how the strategy plays, none by default
+    fn parameters(&self) -> Parameters {
+        Parameters::new()
+    }
+
+    /// Forget the match so far, so the same instance can play another
+    ///
+    /// The default does nothing, which suits strategies that keep no state
+    /// of their own. What a strategy has learned, as opposed to what it
+    /// remembers of one opponent, is kept.
+    fn reset(&mut self) {}
+
+    /// The strategy's own state, to checkpoint it or export what it has
+    /// learned; `None`, the default, for a strategy with nothing to save
+    ///
+    /// Random number generators aren't part of the state, so a restored
+    /// strategy draws afresh from its own seed.
+    fn save_state(&self) -> Option<Value> {
+        None
+    }
+
+    /// Take up a state from [`Strategy::save_state`] of an instance built
+    /// the same way
+    fn load_state(&mut self, _state: &Value) -> Result<(), String> {
+        Err(format!("{} has no state to load", self.name()))
+    }
+
+    /// What to carry over to the next match against the same opponent, when
+    /// the config keeps memories; see [`crate::memory`]
+    ///
+    /// Asked as each match ends. `None`, the default, keeps nothing.
+    fn remember(&self) -> Option<Value> {
+        None
+    }
+
+    /// Take up what [`Strategy::remember`] kept at the end of the last
+    /// match against this opponent, after being reset for the next
+    ///
+    /// Only called if something was kept. The default ignores it.
+    fn recall(&mut self, _memory: &Value) {}
+}
+
+/// Read the field `name` of a state from [`Strategy::save_state`]
+pub fn state_field<T: DeserializeOwned>(state: &Value, name: &str) -> Result<T, String> {
+    let field = state
+        .get(name)
+        .ok_or_else(|| format!("the state has no {}", name))?;
+    serde_json::from_value(field.clone()).map_err(|e| format!("{}: {}", name, e))
+}
+
+impl Strategy for Action {
+    fn name(&self) -> &str {
+        match *self {
+            Action::COOPERATE => "always-cooperate",
+            Action::DEFECT => "always-defect",
+            Action::ABSTAIN => "loner",
+            _ => "constant",
+        }
+    }
+
+    fn choose(&mut self, _history: &History) -> Action {
+        *self
+    }
+
+    fn supports(&self, actions: usize) -> bool {
+        self.index() < actions
+    }
+
+    fn memory_depth(&self) -> Option<usize> {
+        Some(0)
+    }
+
+    fn is_deterministic(&self) -> bool {
+        true
+    }
+}
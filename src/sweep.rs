@@ -0,0 +1,569 @@
+//! Tournaments over a grid of settings, for phase diagrams
+//!
+//! Each [`Axis`] varies one setting of the config over a list of values,
+//! and a tournament is played at every combination of them. Every point
+//! replays from the same seed, so differences between neighbouring points
+//! come from the settings rather than from luck of the draw. The outcome is
+//! a tidy table with one row per participant per point.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+use crate::config::{ConfigError, SimConfig};
+use crate::interrupt;
+use crate::registry::StrategyRegistry;
+use crate::schema;
+use crate::termination::TerminationConfig;
+use crate::tournament::{self, Standing};
+
+/// A setting a sweep can vary
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum Setting {
+    Noise,
+    Misperception,
+    Iterations,
+
+    /// Matches continue after each round with this probability
+    Continuation,
+
+    /// Each round's payoffs are weighted by this to the power of the round
+    Discount,
+
+    /// One strategy parameter of one participant
+    Parameter {
+        participant: String,
+        parameter: String,
+    },
+}
+
+impl fmt::Display for Setting {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Setting::Noise => write!(f, "noise"),
+            Setting::Misperception => write!(f, "misperception"),
+            Setting::Iterations => write!(f, "iterations"),
+            Setting::Continuation => write!(f, "continuation"),
+            Setting::Discount => write!(f, "discount"),
+            Setting::Parameter {
+                participant,
+                parameter,
+            } => write!(f, "{}.{}", participant, parameter),
+        }
+    }
+}
+
+impl Setting {
+    fn apply(&self, config: &mut SimConfig, value: f64) {
+        match self {
+            Setting::Noise => config.noise = value,
+            Setting::Misperception => config.misperception = value,
+            Setting::Iterations => config.iterations = value.round() as usize,
+            Setting::Continuation => {
+                config.termination = TerminationConfig::Continuation { probability: value }
+            }
+            Setting::Discount => config.discount = Some(value),
+            Setting::Parameter {
+                participant,
+                parameter,
+            } => {
+                for p in config
+                    .participants
+                    .iter_mut()
+                    .filter(|p| &p.name == participant)
+                {
+                    p.parameters.insert(parameter.clone(), value);
+                }
+            }
+        }
+    }
+}
+
+/// One setting and the values it takes
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Axis {
+    pub setting: Setting,
+    pub values: Vec<f64>,
+}
+
+impl FromStr for Axis {
+    type Err = String;
+
+    /// `setting=values`, where the setting is `noise`, `misperception`,
+    /// `iterations`, `continuation`, `discount`, or `participant.parameter`, and the
+    /// values are either a comma-separated list or `start:stop:step`, with
+    /// both ends included, e.g. `noise=0:0.1:0.02` or `gtft.generosity=0.1,0.3`
+    fn from_str(s: &str) -> Result<Axis, String> {
+        let (name, values) = s
+            .split_once('=')
+            .ok_or_else(|| format!("'{}': expected setting=values", s))?;
+        let setting = match name.trim() {
+            "noise" => Setting::Noise,
+            "misperception" => Setting::Misperception,
+            "iterations" => Setting::Iterations,
+            "continuation" => Setting::Continuation,
+            "discount" => Setting::Discount,
+            other => match other.split_once('.') {
+                Some((participant, parameter))
+                    if !participant.is_empty() && !parameter.is_empty() =>
+                {
+                    Setting::Parameter {
+                        participant: participant.to_owned(),
+                        parameter: parameter.to_owned(),
+                    }
+                }
+                _ => {
+                    return Err(format!(
+                        "unknown setting '{}', expected noise, misperception, iterations, \
+                         continuation, discount, or participant.parameter",
+                        other
+                    ))
+                }
+            },
+        };
+        let number = |v: &str| {
+            v.trim()
+                .parse::<f64>()
+                .map_err(|e| format!("'{}': {}", s, e))
+        };
+        let values = match values.split(':').collect::<Vec<_>>()[..] {
+            [start, stop, step] => steps(number(start)?, number(stop)?, number(step)?)
+                .ok_or_else(|| format!("'{}': expected start:stop:step with step above 0", s))?,
+            [list] => list.split(',').map(number).collect::<Result<_, _>>()?,
+            _ => return Err(format!("'{}': expected a list or start:stop:step", s)),
+        };
+        Ok(Axis { setting, values })
+    }
+}
+
+/// From `start` to `stop`, both included, by `step`, or none if the step
+/// isn't above 0 or the range runs backwards
+pub(crate) fn steps(start: f64, stop: f64, step: f64) -> Option<Vec<f64>> {
+    if step.is_nan() || step <= 0.0 || stop < start {
+        return None;
+    }
+    // allow for rounding, so that the stop value itself is included
+    let steps = ((stop - start) / step + 1e-9).floor() as usize;
+    // and so that 0.1 steps print as 0.3 rather than 0.30000000000000004
+    Some(
+        (0..=steps)
+            .map(|i| ((start + i as f64 * step) * 1e9).round() / 1e9)
+            .collect(),
+    )
+}
+
+/// A config and the grid of settings to play it at
+pub struct Sweep {
+    config: SimConfig,
+    axes: Vec<Axis>,
+}
+
+/// The standings at one point of the grid
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SweepPoint {
+    /// The value of each axis, in order
+    pub values: Vec<f64>,
+    pub standings: Vec<Standing>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SweepRecord {
+    pub config: SimConfig,
+    pub seed: u64,
+    pub axes: Vec<Axis>,
+
+    /// Every combination of values, the last axis varying fastest; fewer
+    /// than the whole grid if the sweep was interrupted
+    pub points: Vec<SweepPoint>,
+}
+
+impl Sweep {
+    /// A sweep of `config` over `axes`, each of whose points must be a valid
+    /// config
+    pub fn new(
+        config: &SimConfig,
+        axes: Vec<Axis>,
+        registry: &StrategyRegistry,
+    ) -> Result<Sweep, ConfigError> {
+        for axis in &axes {
+            if let Setting::Parameter { participant, .. } = &axis.setting {
+                if !config.participants.iter().any(|p| &p.name == participant) {
+                    return Err(ConfigError::Invalid(format!(
+                        "sweep of {}: no participant named '{}'",
+                        axis.setting, participant
+                    )));
+                }
+            }
+        }
+        let sweep = Sweep {
+            config: config.clone(),
+            axes,
+        };
+        for point in sweep.points() {
+            sweep
+                .config_at(&point)
+                .validate(registry)
+                .map_err(|e| match e {
+                    ConfigError::Invalid(e) => {
+                        ConfigError::Invalid(format!("at {}: {}", sweep.describe(&point), e))
+                    }
+                    e => e,
+                })?;
+        }
+        Ok(sweep)
+    }
+
+    /// Every combination of the axes' values, the last axis varying fastest
+    pub fn points(&self) -> Vec<Vec<f64>> {
+        self.axes.iter().fold(vec![Vec::new()], |points, axis| {
+            points
+                .iter()
+                .flat_map(|point| {
+                    axis.values.iter().map(move |&value| {
+                        let mut point = point.clone();
+                        point.push(value);
+                        point
+                    })
+                })
+                .collect()
+        })
+    }
+
+    fn config_at(&self, point: &[f64]) -> SimConfig {
+        let mut config = self.config.clone();
+        for (axis, &value) in self.axes.iter().zip(point) {
+            axis.setting.apply(&mut config, value);
+        }
+        config
+    }
+
+    fn describe(&self, point: &[f64]) -> String {
+        self.axes
+            .iter()
+            .zip(point)
+            .map(|(axis, value)| format!("{}={}", axis.setting, value))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// The matches played at all the points together
+    pub fn matches(&self) -> usize {
+        self.points()
+            .iter()
+            .map(|point| tournament::schedule(&self.config_at(point)).1.len())
+            .sum()
+    }
+
+    /// Play a round robin at every point, one point after another
+    pub async fn run(&self, registry: &StrategyRegistry, seed: u64) -> SweepRecord {
+        let mut points = Vec::new();
+        for values in self.points() {
+            let config = self.config_at(&values);
+            let reports = tournament::round_robin(&config, registry, seed, None).await;
+            // a cut-short tournament would be a misleading point on the plot
+            if interrupt::interrupted() {
+                break;
+            }
+            let matches: Vec<_> = reports
+                .into_iter()
+                .filter_map(|report| report.result.ok())
+                .collect();
+            points.push(SweepPoint {
+                standings: tournament::standings(&config, &matches),
+                values,
+            });
+        }
+        SweepRecord {
+            config: self.config.clone(),
+            seed,
+            axes: self.axes.clone(),
+            points,
+        }
+    }
+}
+
+impl SweepRecord {
+    /// One line per participant per point, after a header: the
+    /// [schema version](crate::schema), the axes' values, then the
+    /// participant's standing
+    pub fn to_csv(&self) -> String {
+        let mut csv = format!("{},", schema::VERSION_FIELD);
+        for axis in &self.axes {
+            csv.push_str(&format!("{},", axis.setting));
+        }
+        csv.push_str(
+            "rank,name,strategy,matches,rounds,score,average_score,average_match_score,\
+             cooperation_rate\n",
+        );
+        for point in &self.points {
+            let mut values = format!("{},", schema::SCHEMA_VERSION);
+            for value in &point.values {
+                values.push_str(&format!("{},", value));
+            }
+            for (i, standing) in point.standings.iter().enumerate() {
+                csv.push_str(&format!(
+                    "{}{},{},{},{},{},{},{},{},{}\n",
+                    values,
+                    i + 1,
+                    field(&standing.name),
+                    field(&standing.strategy),
+                    standing.matches,
+                    standing.rounds,
+                    standing.score,
+                    standing.average_score,
+                    standing.average_match_score,
+                    standing.cooperation_rate
+                ));
+            }
+        }
+        csv
+    }
+}
+
+/// One participant's rank at each point of a one-axis sweep
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RankTrack {
+    pub name: String,
+    pub strategy: String,
+
+    /// From 1 for the top of the standings, one per value of the axis
+    pub ranks: Vec<usize>,
+    pub mean_rank: f64,
+
+    /// How far apart its best and worst ranks are
+    pub spread: usize,
+}
+
+/// How the standings reorder as one setting varies
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Robustness {
+    pub config: SimConfig,
+    pub seed: u64,
+    pub setting: Setting,
+    pub values: Vec<f64>,
+
+    /// Best mean rank first
+    pub tracks: Vec<RankTrack>,
+
+    /// Spearman's correlation between the ranking at each value and the
+    /// ranking at the first: 1 where nothing moved, -1 where it reversed
+    pub correlations: Vec<f64>,
+}
+
+impl Robustness {
+    /// The rankings along the first axis of `record`, which should be its
+    /// only one
+    pub fn new(record: &SweepRecord) -> Robustness {
+        let setting = record
+            .axes
+            .first()
+            .map_or(Setting::Noise, |axis| axis.setting.clone());
+        let values = record
+            .points
+            .iter()
+            .map(|point| point.values.first().copied().unwrap_or_default())
+            .collect();
+        let rank_at = |point: &SweepPoint, name: &str| {
+            point
+                .standings
+                .iter()
+                .position(|standing| standing.name == name)
+                .map_or(point.standings.len(), |i| i + 1)
+        };
+        let mut tracks: Vec<RankTrack> = record
+            .config
+            .participants
+            .iter()
+            .map(|participant| {
+                let ranks: Vec<usize> = record
+                    .points
+                    .iter()
+                    .map(|point| rank_at(point, &participant.name))
+                    .collect();
+                let best = ranks.iter().min().copied().unwrap_or_default();
+                let worst = ranks.iter().max().copied().unwrap_or_default();
+                RankTrack {
+                    name: participant.name.clone(),
+                    strategy: participant.strategy.clone(),
+                    mean_rank: ranks.iter().sum::<usize>() as f64 / ranks.len().max(1) as f64,
+                    spread: worst - best,
+                    ranks,
+                }
+            })
+            .collect();
+        tracks.sort_by(|a, b| {
+            a.mean_rank
+                .total_cmp(&b.mean_rank)
+                .then_with(|| a.name.cmp(&b.name))
+        });
+
+        // ranks are a permutation at every point, so there are no ties
+        let n = tracks.len() as f64;
+        let correlations = (0..record.points.len())
+            .map(|i| {
+                if n < 2.0 {
+                    return 1.0;
+                }
+                let squares: f64 = tracks
+                    .iter()
+                    .map(|track| (track.ranks[i] as f64 - track.ranks[0] as f64).powi(2))
+                    .sum();
+                1.0 - 6.0 * squares / (n * (n * n - 1.0))
+            })
+            .collect();
+        Robustness {
+            config: record.config.clone(),
+            seed: record.seed,
+            setting,
+            values,
+            tracks,
+            correlations,
+        }
+    }
+}
+
+/// A CSV field, quoted if it needs to be, as combinator names do
+fn field(s: &str) -> String {
+    if s.contains([',', '"', '\n']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ParticipantConfig;
+
+    fn config() -> SimConfig {
+        SimConfig {
+            iterations: 10,
+            participants: vec![
+                ParticipantConfig::new("tft", "tit-for-tat"),
+                ParticipantConfig::new("gtft", "generous-tit-for-tat"),
+            ],
+            ..SimConfig::default()
+        }
+    }
+
+    #[test]
+    fn axes_parse_lists_and_ranges() {
+        let noise: Axis = "noise=0:0.1:0.05".parse().unwrap();
+        assert_eq!(noise.setting, Setting::Noise);
+        assert_eq!(noise.values, vec![0.0, 0.05, 0.1]);
+        let generosity: Axis = "gtft.generosity=0.1,0.3".parse().unwrap();
+        assert_eq!(generosity.setting.to_string(), "gtft.generosity");
+        assert_eq!(generosity.values, vec![0.1, 0.3]);
+
+        assert_eq!(steps(0.1, 0.3, 0.1), Some(vec![0.1, 0.2, 0.3]));
+        assert_eq!(steps(1.0, 0.0, 0.1), None);
+        for bad in &["noise", "speed=1", "noise=0:1:0", "noise=a", "noise=0:1"] {
+            assert!(bad.parse::<Axis>().is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn grids_vary_the_last_axis_fastest() {
+        let registry = StrategyRegistry::new();
+        let axes = vec![
+            "iterations=10,20".parse().unwrap(),
+            "noise=0,0.1,0.2".parse().unwrap(),
+        ];
+        let sweep = Sweep::new(&config(), axes, &registry).unwrap();
+        let points = sweep.points();
+        assert_eq!(points.len(), 6);
+        assert_eq!(points[0], vec![10.0, 0.0]);
+        assert_eq!(points[1], vec![10.0, 0.1]);
+        assert_eq!(points[3], vec![20.0, 0.0]);
+        let at = sweep.config_at(&points[5]);
+        assert_eq!((at.iterations, at.noise), (20, 0.2));
+        assert_eq!(sweep.describe(&points[5]), "iterations=20 noise=0.2");
+        // one match between the two participants at each point
+        assert_eq!(sweep.matches(), 6);
+
+        let nobody = vec!["allc.generosity=0.1".parse().unwrap()];
+        assert!(Sweep::new(&config(), nobody, &registry).is_err());
+        let noisy = vec!["noise=0,2".parse().unwrap()];
+        match Sweep::new(&config(), noisy, &registry) {
+            Err(ConfigError::Invalid(e)) => assert!(e.starts_with("at noise=2: "), "{}", e),
+            _ => panic!("noise of 2 should be refused"),
+        }
+    }
+
+    #[test]
+    fn csv_rows_are_one_per_participant_per_point() {
+        let standing = |name: &str, strategy: &str, score: f64| Standing {
+            name: name.to_owned(),
+            strategy: strategy.to_owned(),
+            matches: 1,
+            rounds: 10,
+            score,
+            average_score: score / 10.0,
+            average_match_score: score,
+            cooperation_rate: 1.0,
+            about: None,
+            objectives: None,
+        };
+        let record = SweepRecord {
+            config: config(),
+            seed: 1,
+            axes: vec!["noise=0,0.1".parse().unwrap()],
+            points: vec![
+                SweepPoint {
+                    values: vec![0.0],
+                    standings: vec![standing("tft", "tit-for-tat", 30.0)],
+                },
+                SweepPoint {
+                    values: vec![0.1],
+                    standings: vec![
+                        standing("gtft", "generous(0.1,tit-for-tat)", 28.0),
+                        standing("tft", "tit-for-tat", 27.0),
+                    ],
+                },
+            ],
+        };
+        let csv = record.to_csv();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(
+            lines[0],
+            "schema_version,noise,rank,name,strategy,matches,rounds,score,average_score,\
+             average_match_score,cooperation_rate"
+        );
+        assert_eq!(lines.len(), 4);
+        assert_eq!(
+            lines[1],
+            format!(
+                "{},0,1,tft,tit-for-tat,1,10,30,3,30,1",
+                schema::SCHEMA_VERSION
+            )
+        );
+        assert_eq!(
+            lines[2],
+            format!(
+                "{},0.1,1,gtft,\"generous(0.1,tit-for-tat)\",1,10,28,2.8,28,1",
+                schema::SCHEMA_VERSION
+            )
+        );
+        assert!(lines[3].contains(",0.1,2,tft,"));
+    }
+
+    #[actix_rt::test]
+    async fn every_point_of_a_sweep_is_played() {
+        let registry = StrategyRegistry::new();
+        let axes = vec!["iterations=5,10".parse().unwrap()];
+        let record = Sweep::new(&config(), axes, &registry)
+            .unwrap()
+            .run(&registry, 3)
+            .await;
+        assert_eq!(record.points.len(), 2);
+        for (point, rounds) in record.points.iter().zip(&[5, 10]) {
+            assert_eq!(point.standings.len(), 2);
+            assert!(point.standings.iter().all(|s| s.rounds == *rounds));
+        }
+        let robustness = Robustness::new(&record);
+        assert_eq!(robustness.values, vec![5.0, 10.0]);
+        assert_eq!(robustness.correlations[0], 1.0);
+    }
+}
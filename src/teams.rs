@@ -0,0 +1,764 @@
+//! Team play: two prisoners against two
+//!
+//! Participants team up in pairs, in the order the config lists them, and
+//! every team meets every other once. Each round of a match one member of
+//! each team is seated against one of the other's, as the [`Rotation`] says,
+//! and what the two are paid goes to their teams' shared scores. A member
+//! sees only the rounds it is seated for, so whatever its history holds was
+//! played against whichever opponents the rotation gave it: how well a team
+//! does is down to how its members' strategies fit together.
+
+use actix::prelude::*;
+use futures::channel::oneshot;
+use futures::future::{join, join_all};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::time::Duration;
+use tracing::{debug, warn};
+
+use crate::config::{ParticipantConfig, SimConfig};
+use crate::control::{self, Control};
+use crate::game::{Action, PayoffMatrix, PlayerOutcome, RoundOutcome, Score};
+use crate::interrupt;
+use crate::prisoner::{
+    self, BudgetAction, FaultKind, Interrogate, MatchId, Prisoner, StrategyFault,
+};
+use crate::referee::Forfeit;
+use crate::registry::StrategyRegistry;
+use crate::tournament;
+
+/// Which member of each team is seated in each round
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Rotation {
+    /// The first members meet, then the second, and so on by turns: each
+    /// member only ever meets the same opponent
+    #[default]
+    Paired,
+
+    /// Every member meets each of the opposing members in turn
+    Rotating,
+
+    /// Either member of each team, drawn afresh each round
+    Random,
+}
+
+impl Rotation {
+    /// The members of the blue and red teams seated in round `round`
+    pub fn seats(self, round: usize, rng: &mut StdRng) -> (usize, usize) {
+        match self {
+            Rotation::Paired => (round % 2, round % 2),
+            Rotation::Rotating => [(0, 0), (1, 1), (0, 1), (1, 0)][round % 4],
+            Rotation::Random => (rng.gen_range(0, 2), rng.gen_range(0, 2)),
+        }
+    }
+}
+
+/// Start the team match; resolves with the result once the last round is
+/// scored
+pub struct PlayTeams;
+
+impl Message for PlayTeams {
+    type Result = Result<TeamResult, MailboxError>;
+}
+
+/// Sent by the team referee to itself for each round
+struct PlayTeamRound;
+
+impl Message for PlayTeamRound {
+    type Result = ();
+}
+
+/// How one member fared in a team match
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemberSummary {
+    pub name: String,
+    pub score: Score,
+
+    /// Rounds this member was seated for
+    pub rounds: usize,
+    pub cooperations: usize,
+
+    /// Rounds in which this member missed the decision timeout
+    #[serde(default)]
+    pub timeouts: usize,
+
+    /// Rounds this member forfeited for having used up its computation
+    /// budget
+    #[serde(default)]
+    pub over_budget: usize,
+}
+
+/// How one team fared in a match
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TeamSummary {
+    pub name: String,
+
+    /// What its members scored between them
+    pub score: Score,
+    pub members: Vec<MemberSummary>,
+}
+
+/// The outcome of one team match
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TeamResult {
+    pub rounds: usize,
+    pub blue: TeamSummary,
+    pub red: TeamSummary,
+
+    /// Prisoners that panicked, went silent, or went over their budget; the
+    /// match ends when one does
+    pub faults: Vec<StrategyFault>,
+
+    /// Which member of the blue and of the red team was seated in each round
+    pub seats: Vec<(usize, usize)>,
+}
+
+struct Member {
+    name: String,
+    addr: Addr<Prisoner>,
+
+    /// The last round this member was seated for: what it did, what its
+    /// opponent did, and what it got
+    own: Option<Action>,
+    opponent: Option<Action>,
+    prev: PlayerOutcome,
+    score: Score,
+    rounds: usize,
+    cooperations: usize,
+    timeouts: usize,
+    over_budget: usize,
+}
+
+impl Member {
+    fn new(name: &str, addr: Addr<Prisoner>) -> Member {
+        Member {
+            name: name.to_owned(),
+            addr,
+            own: None,
+            opponent: None,
+            prev: PlayerOutcome::NONE,
+            score: 0.0,
+            rounds: 0,
+            cooperations: 0,
+            timeouts: 0,
+            over_budget: 0,
+        }
+    }
+
+    fn summary(&self) -> MemberSummary {
+        MemberSummary {
+            name: self.name.clone(),
+            score: self.score,
+            rounds: self.rounds,
+            cooperations: self.cooperations,
+            timeouts: self.timeouts,
+            over_budget: self.over_budget,
+        }
+    }
+}
+
+/// Owns the game loop for one match between two teams of two
+pub struct TeamReferee {
+    match_id: MatchId,
+    payoffs: PayoffMatrix,
+    red_payoffs: Option<PayoffMatrix>,
+    rotation: Rotation,
+    rng: StdRng,
+
+    /// The blue team's members, then the red team's
+    members: Vec<Member>,
+    iterations: usize,
+    retries: usize,
+    decision_timeout: Option<Duration>,
+    timeout_action: Action,
+    on_exceed: BudgetAction,
+    delay: Option<Duration>,
+    sequence: usize,
+
+    /// What steers the match from outside, see [`TeamReferee::with_control`]
+    control: Control,
+
+    /// [`Control::skips`] when the match started
+    skips: usize,
+    faults: Vec<StrategyFault>,
+    seats: Vec<(usize, usize)>,
+    result_tx: Option<oneshot::Sender<TeamResult>>,
+}
+
+impl TeamReferee {
+    pub fn new(
+        blue: [(&str, Addr<Prisoner>); 2],
+        red: [(&str, Addr<Prisoner>); 2],
+        payoffs: PayoffMatrix,
+        iterations: usize,
+    ) -> TeamReferee {
+        TeamReferee {
+            match_id: 0,
+            payoffs,
+            red_payoffs: None,
+            rotation: Rotation::default(),
+            rng: StdRng::seed_from_u64(0),
+            members: IntoIterator::into_iter(blue)
+                .chain(IntoIterator::into_iter(red))
+                .map(|(name, addr)| Member::new(name, addr))
+                .collect(),
+            iterations,
+            retries: 0,
+            decision_timeout: None,
+            timeout_action: Action::DEFECT,
+            on_exceed: BudgetAction::ForfeitMatch,
+            delay: None,
+            sequence: 0,
+            control: Control::default(),
+            skips: 0,
+            faults: Vec::new(),
+            seats: Vec::new(),
+            result_tx: None,
+        }
+    }
+
+    /// Tell the members which match they are playing, so prisoners in several
+    /// matches at once keep them apart
+    pub fn with_match_id(mut self, match_id: MatchId) -> TeamReferee {
+        self.match_id = match_id;
+        self
+    }
+
+    /// Seat the members as `rotation` says, rather than by
+    /// [`Rotation::Paired`]
+    pub fn with_rotation(mut self, rotation: Rotation) -> TeamReferee {
+        self.rotation = rotation;
+        self
+    }
+
+    /// Seed the draws of a [`Rotation::Random`] rotation
+    pub fn with_seed(mut self, seed: u64) -> TeamReferee {
+        self.rng = StdRng::seed_from_u64(seed);
+        self
+    }
+
+    /// Pay the red team by `payoffs`, as
+    /// [`crate::referee::Referee::with_red_payoffs`] does
+    pub fn with_red_payoffs(mut self, payoffs: PayoffMatrix) -> TeamReferee {
+        self.red_payoffs = Some(payoffs);
+        self
+    }
+
+    /// Resend an unanswered message up to `retries` times, backing off each
+    /// time, before the silent prisoner forfeits
+    pub fn with_retries(mut self, retries: usize) -> TeamReferee {
+        self.retries = retries;
+        self
+    }
+
+    /// Play `action` for a member that takes longer than `timeout` to decide
+    pub fn with_decision_timeout(mut self, timeout: Duration, action: Action) -> TeamReferee {
+        self.decision_timeout = Some(timeout);
+        self.timeout_action = action;
+        self
+    }
+
+    /// Play the timeout action for a member over its computation budget, if
+    /// `on_exceed` forfeits rounds, as
+    /// [`crate::referee::Referee::with_budget`] does
+    pub fn with_budget(mut self, on_exceed: BudgetAction) -> TeamReferee {
+        self.on_exceed = on_exceed;
+        self
+    }
+
+    /// Wait `delay` after each round before playing the next, as
+    /// [`crate::referee::Referee::with_delay`] does
+    pub fn with_delay(mut self, delay: Duration) -> TeamReferee {
+        self.delay = Some(delay);
+        self
+    }
+
+    /// Pause and skip as `control` says, as for [`Referee::with_control`]
+    ///
+    /// [`Referee::with_control`]: crate::referee::Referee::with_control
+    pub fn with_control(mut self, control: Control) -> TeamReferee {
+        self.control = control;
+        self
+    }
+
+    /// Score a round, each side by its own payoffs
+    fn score(&self, red: Action, blue: Action) -> RoundOutcome {
+        let outcome = self.payoffs.score(red, blue);
+        match self.red_payoffs {
+            Some(red_payoffs) => RoundOutcome {
+                red: red_payoffs.score(red, blue).red,
+                ..outcome
+            },
+            None => outcome,
+        }
+    }
+
+    /// Ask the member in seat `seat` for its action, given the last round it
+    /// was seated for
+    fn ask(
+        &self,
+        seat: usize,
+    ) -> impl Future<Output = Result<Result<Action, Forfeit>, StrategyFault>> {
+        let member = &self.members[seat];
+        let (name, addr) = (member.name.clone(), member.addr.clone());
+        let msg = Interrogate {
+            match_id: self.match_id,
+            sequence: self.sequence,
+            prev: member.prev,
+            own_action: member.own,
+            opponent_action: member.opponent,
+            group: None,
+        };
+        let (retries, timeout, on_exceed) = (self.retries, self.decision_timeout, self.on_exceed);
+        async move {
+            match prisoner::ask(&addr, msg, retries, timeout).await {
+                Ok(Err(fault))
+                    if fault.kind == FaultKind::OverBudget
+                        && on_exceed == BudgetAction::ForfeitRound =>
+                {
+                    Ok(Err(Forfeit::OverBudget))
+                }
+                Ok(answer) => answer.map(Ok),
+                Err(MailboxError::Timeout) => {
+                    warn!(player = %name, "ran out of time to decide");
+                    Ok(Err(Forfeit::TimedOut))
+                }
+                Err(e) => Err(StrategyFault::unresponsive(&name, e)),
+            }
+        }
+    }
+
+    /// The action the member in seat `seat` answered with, counting any
+    /// forfeit against it
+    fn answer(
+        &mut self,
+        seat: usize,
+        answer: Result<Result<Action, Forfeit>, StrategyFault>,
+    ) -> Action {
+        match answer {
+            Ok(Ok(action)) => action,
+            Ok(Err(forfeit)) => {
+                match forfeit {
+                    Forfeit::TimedOut => self.members[seat].timeouts += 1,
+                    Forfeit::OverBudget => self.members[seat].over_budget += 1,
+                }
+                self.timeout_action
+            }
+            Err(fault) => {
+                warn!(%fault, sequence = self.sequence, "forfeits the match");
+                self.faults.push(fault);
+                self.timeout_action
+            }
+        }
+    }
+
+    fn team(&self, members: &[Member]) -> TeamSummary {
+        TeamSummary {
+            name: team_name(members.iter().map(|member| member.name.as_str())),
+            score: members.iter().map(|member| member.score).sum(),
+            members: members.iter().map(Member::summary).collect(),
+        }
+    }
+
+    fn result(&self) -> TeamResult {
+        let (blue, red) = self.members.split_at(2);
+        TeamResult {
+            rounds: self.sequence,
+            blue: self.team(blue),
+            red: self.team(red),
+            faults: self.faults.clone(),
+            seats: self.seats.clone(),
+        }
+    }
+
+    fn report(&mut self) {
+        if let Some(tx) = self.result_tx.take() {
+            let _ = tx.send(self.result());
+        }
+    }
+
+    fn finish(&mut self, ctx: &mut Context<Self>) {
+        self.report();
+        ctx.stop();
+    }
+}
+
+/// What a team of `members` is called
+fn team_name<'a>(members: impl Iterator<Item = &'a str>) -> String {
+    members.collect::<Vec<_>>().join("+")
+}
+
+impl Actor for TeamReferee {
+    type Context = Context<Self>;
+
+    fn stopped(&mut self, _ctx: &mut Context<Self>) {
+        // stopped before the last round: report what we have
+        self.report();
+    }
+}
+
+impl Handler<PlayTeams> for TeamReferee {
+    type Result = ResponseFuture<Result<TeamResult, MailboxError>>;
+
+    fn handle(&mut self, _msg: PlayTeams, ctx: &mut Context<Self>) -> Self::Result {
+        let (tx, rx) = oneshot::channel();
+        self.result_tx = Some(tx);
+        self.skips = self.control.skips();
+        ctx.notify(PlayTeamRound);
+        Box::pin(async move { rx.await.map_err(|_| MailboxError::Closed) })
+    }
+}
+
+impl Handler<PlayTeamRound> for TeamReferee {
+    type Result = ResponseActFuture<Self, ()>;
+
+    fn handle(&mut self, _msg: PlayTeamRound, ctx: &mut Context<Self>) -> Self::Result {
+        if self.control.paused() && !interrupt::interrupted() {
+            ctx.notify_later(PlayTeamRound, control::POLL);
+            return Box::pin(actix::fut::ready(()));
+        }
+        let (blue_seat, red_seat) = self.rotation.seats(self.sequence, &mut self.rng);
+        let (blue, red) = (blue_seat, 2 + red_seat);
+
+        Box::pin(join(self.ask(blue), self.ask(red)).into_actor(self).map(
+            move |(blue_answer, red_answer), act, ctx| {
+                let blue_action = act.answer(blue, blue_answer);
+                let red_action = act.answer(red, red_answer);
+                if !act.faults.is_empty() {
+                    act.finish(ctx);
+                    return;
+                }
+
+                let outcome = act.score(red_action, blue_action);
+                for &(seat, own, opponent, got) in &[
+                    (blue, blue_action, red_action, outcome.blue),
+                    (red, red_action, blue_action, outcome.red),
+                ] {
+                    let member = &mut act.members[seat];
+                    member.own = Some(own);
+                    member.opponent = Some(opponent);
+                    member.prev = got;
+                    member.score += got.amount;
+                    member.rounds += 1;
+                    member.cooperations += (own == Action::COOPERATE) as usize;
+                }
+                act.seats.push((blue_seat, red_seat));
+
+                act.sequence += 1;
+                if act.sequence >= act.iterations {
+                    debug!(rounds = act.sequence, "completed");
+                    act.finish(ctx);
+                } else if interrupt::interrupted() {
+                    debug!(rounds = act.sequence, "interrupted");
+                    act.finish(ctx);
+                } else if act.control.skips() > act.skips {
+                    debug!(rounds = act.sequence, "skipped");
+                    act.finish(ctx);
+                } else if let Some(delay) = act.delay {
+                    ctx.notify_later(PlayTeamRound, delay);
+                } else {
+                    ctx.notify(PlayTeamRound);
+                }
+            },
+        ))
+    }
+}
+
+/// How one member fared across every match its team played
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemberStanding {
+    pub name: String,
+    pub strategy: String,
+    pub rounds: usize,
+    pub score: Score,
+}
+
+/// One team's totals across every match it played
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TeamStanding {
+    pub name: String,
+    pub members: Vec<MemberStanding>,
+    pub matches: usize,
+    pub rounds: usize,
+    pub score: Score,
+    pub average_score: f64,
+    pub cooperation_rate: f64,
+}
+
+/// Everything needed to reproduce and analyse a team tournament
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TeamsRecord {
+    pub config: SimConfig,
+    pub seed: u64,
+    pub rotation: Rotation,
+    pub matches: Vec<TeamResult>,
+
+    /// Highest total score first
+    pub standings: Vec<TeamStanding>,
+}
+
+/// The teams `participants` form: each two in turn, in the order given
+pub fn teams(participants: &[ParticipantConfig]) -> Result<Vec<[&ParticipantConfig; 2]>, String> {
+    if participants.len() < 4 || !participants.len().is_multiple_of(2) {
+        return Err(format!(
+            "{} participants can't make two or more teams of two",
+            participants.len()
+        ));
+    }
+    Ok(participants
+        .chunks(2)
+        .map(|pair| [&pair[0], &pair[1]])
+        .collect())
+}
+
+fn start_match(
+    config: &SimConfig,
+    registry: &StrategyRegistry,
+    rotation: Rotation,
+    blue: [&ParticipantConfig; 2],
+    red: [&ParticipantConfig; 2],
+    seeds: &mut StdRng,
+) -> impl Future<Output = Result<TeamResult, MailboxError>> {
+    let worker = tournament::next_worker();
+    let mut start = |participant: &ParticipantConfig| {
+        let prisoner = tournament::prisoner(config, registry, participant, seeds.gen());
+        tournament::start_on(worker.as_ref(), prisoner)
+    };
+    let blue = [
+        (blue[0].name.as_str(), start(blue[0])),
+        (blue[1].name.as_str(), start(blue[1])),
+    ];
+    let red = [
+        (red[0].name.as_str(), start(red[0])),
+        (red[1].name.as_str(), start(red[1])),
+    ];
+    let mut referee = TeamReferee::new(blue, red, config.payoffs(), config.iterations)
+        .with_match_id(tournament::next_match_id())
+        .with_rotation(rotation)
+        .with_seed(seeds.gen())
+        .with_retries(config.retries);
+    if let Some(red_payoffs) = config.red_payoffs {
+        referee = referee.with_red_payoffs(red_payoffs);
+    }
+    if let Some(timeout) = config.decision_timeout() {
+        referee = referee.with_decision_timeout(timeout, config.timeout_action);
+    }
+    if let Some(budget) = config.budget {
+        referee = referee.with_budget(budget.on_exceed);
+    }
+    if let Some(delay) = config.delay() {
+        referee = referee.with_delay(delay);
+    }
+    if let Some(control) = control::steering() {
+        referee = referee.with_control(control);
+    }
+    let referee = tournament::start_on(worker.as_ref(), referee);
+    async move { referee.send(PlayTeams).await.and_then(|result| result) }
+}
+
+/// Play every team of [`teams`] against every other once, all concurrently,
+/// or both ways round under `red_payoffs`
+///
+/// The config should already have been validated against `registry`, and
+/// have the participants for at least two teams. The same seed replays the
+/// same tournament. Matches that never report are left out.
+pub async fn play_teams(
+    config: &SimConfig,
+    registry: &StrategyRegistry,
+    seed: u64,
+    rotation: Rotation,
+) -> TeamsRecord {
+    let teams = teams(&config.participants).unwrap_or_default();
+    let mut seeds = StdRng::seed_from_u64(seed);
+    let pairs = (0..teams.len())
+        .flat_map(|i| (i + 1..teams.len()).map(move |j| (i, j)))
+        .collect();
+    let matches: Vec<_> = tournament::seatings(config, pairs)
+        .into_iter()
+        .map(|(blue, red)| {
+            start_match(
+                config,
+                registry,
+                rotation,
+                teams[blue],
+                teams[red],
+                &mut seeds,
+            )
+        })
+        .collect();
+    let matches: Vec<TeamResult> = join_all(matches)
+        .await
+        .into_iter()
+        .filter_map(|result| match result {
+            Ok(result) => Some(result),
+            Err(e) => {
+                warn!(error = %e, "team match aborted");
+                None
+            }
+        })
+        .collect();
+
+    // each member's rounds, score and cooperations
+    type MemberTotals = (usize, Score, usize);
+    let mut totals: BTreeMap<&str, (usize, [MemberTotals; 2])> = BTreeMap::new();
+    for result in &matches {
+        for team in &[&result.blue, &result.red] {
+            let entry = totals.entry(team.name.as_str()).or_default();
+            entry.0 += 1;
+            for (total, member) in entry.1.iter_mut().zip(&team.members) {
+                total.0 += member.rounds;
+                total.1 += member.score;
+                total.2 += member.cooperations;
+            }
+        }
+    }
+    let mut standings: Vec<TeamStanding> = teams
+        .iter()
+        .map(|members| {
+            let name = team_name(members.iter().map(|member| member.name.as_str()));
+            let (matches, totals) = totals.get(name.as_str()).copied().unwrap_or_default();
+            let rounds: usize = totals.iter().map(|total| total.0).sum();
+            let score: Score = totals.iter().map(|total| total.1).sum();
+            let cooperations: usize = totals.iter().map(|total| total.2).sum();
+            let per_round = |n: f64| if rounds == 0 { 0.0 } else { n / rounds as f64 };
+            TeamStanding {
+                members: members
+                    .iter()
+                    .zip(&totals)
+                    .map(|(member, total)| MemberStanding {
+                        name: member.name.clone(),
+                        strategy: member.strategy.clone(),
+                        rounds: total.0,
+                        score: total.1,
+                    })
+                    .collect(),
+                name,
+                matches,
+                rounds,
+                score,
+                average_score: per_round(score),
+                cooperation_rate: per_round(cooperations as f64),
+            }
+        })
+        .collect();
+    standings.sort_by(|a, b| {
+        b.score
+            .total_cmp(&a.score)
+            .then_with(|| a.name.cmp(&b.name))
+    });
+
+    TeamsRecord {
+        config: config.clone(),
+        seed,
+        rotation,
+        matches,
+        standings,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn participants(strategies: &[&str]) -> SimConfig {
+        SimConfig {
+            iterations: 8,
+            participants: strategies
+                .iter()
+                .enumerate()
+                .map(|(i, strategy)| ParticipantConfig::new(&format!("p{}", i), strategy))
+                .collect(),
+            ..SimConfig::default()
+        }
+    }
+
+    #[test]
+    fn rotations_seat_the_members_in_turn() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let seats = |rotation: Rotation, rng: &mut StdRng| -> Vec<(usize, usize)> {
+            (0..5).map(|round| rotation.seats(round, rng)).collect()
+        };
+        assert_eq!(
+            seats(Rotation::Paired, &mut rng),
+            vec![(0, 0), (1, 1), (0, 0), (1, 1), (0, 0)]
+        );
+        assert_eq!(
+            seats(Rotation::Rotating, &mut rng),
+            vec![(0, 0), (1, 1), (0, 1), (1, 0), (0, 0)]
+        );
+        let random = seats(Rotation::Random, &mut rng);
+        assert!(random.iter().all(|&(blue, red)| blue < 2 && red < 2));
+    }
+
+    #[test]
+    fn participants_team_up_in_pairs() {
+        let config = participants(&["grim", "grim", "grim", "grim", "grim", "grim"]);
+        let teams = teams(&config.participants).unwrap();
+        let names: Vec<_> = teams
+            .iter()
+            .map(|team| team_name(team.iter().map(|member| member.name.as_str())))
+            .collect();
+        assert_eq!(names, vec!["p0+p1", "p2+p3", "p4+p5"]);
+        assert!(super::teams(&config.participants[..3]).is_err());
+        assert!(super::teams(&config.participants[..2]).is_err());
+    }
+
+    #[actix_rt::test]
+    async fn members_remember_only_the_rounds_they_were_seated_for() {
+        let config = participants(&[
+            "tit-for-tat",
+            "tit-for-tat",
+            "always-defect",
+            "always-cooperate",
+        ]);
+        let registry = StrategyRegistry::new();
+        config.validate(&registry).unwrap();
+        let record = play_teams(&config, &registry, 1, Rotation::Rotating).await;
+        let result = &record.matches[0];
+        assert_eq!(result.rounds, 8);
+        assert_eq!(result.seats[..4], [(0, 0), (1, 1), (0, 1), (1, 0)]);
+
+        // p0 meets alld then allc in turn, and copies whichever it met last
+        // time it was seated: C, D, C, D; p1 meets allc first: C, C, D, C
+        let cooperations: Vec<usize> = result
+            .blue
+            .members
+            .iter()
+            .map(|member| member.cooperations)
+            .collect();
+        assert_eq!(cooperations, vec![2, 3]);
+        let scores = |team: &TeamSummary| -> Vec<Score> {
+            team.members.iter().map(|member| member.score).collect()
+        };
+        assert_eq!(scores(&result.blue), vec![10.0, 9.0]);
+        assert_eq!(scores(&result.red), vec![16.0, 6.0]);
+        assert_eq!((result.blue.score, result.red.score), (19.0, 22.0));
+
+        assert_eq!(record.standings[0].name, "p2+p3");
+        assert_eq!(record.standings[0].score, 22.0);
+        assert_eq!(record.standings[1].members[0].rounds, 4);
+    }
+
+    #[actix_rt::test]
+    async fn paired_members_always_meet_the_same_opponent() {
+        let config = participants(&[
+            "always-cooperate",
+            "always-defect",
+            "always-cooperate",
+            "always-cooperate",
+        ]);
+        let registry = StrategyRegistry::new();
+        config.validate(&registry).unwrap();
+        let record = play_teams(&config, &registry, 1, Rotation::Paired).await;
+        let result = &record.matches[0];
+        // p0 and p2 cooperate four times for 3; p1 exploits p3 four times
+        assert_eq!(result.blue.members[0].score, 12.0);
+        assert_eq!(result.blue.members[1].score, 16.0);
+        assert_eq!(result.red.members[1].score, 4.0);
+        assert_eq!((result.blue.score, result.red.score), (28.0, 16.0));
+    }
+}
@@ -0,0 +1,106 @@
+//! Tracing spans exported over OTLP, for following a run in Jaeger or Tempo
+//!
+//! `--otlp http://localhost:4318` sends every span logged to a collector
+//! over OTLP/HTTP as well as to stderr: a `tournament` span around each
+//! round robin, a `match` span inside it for each match, and a `remote`
+//! span for each question put to a remote strategy. `OTEL_SERVICE_NAME`
+//! names the service, `actoripd` by default.
+//!
+//! A distributed round robin hands workers the [`traceparent`] of its
+//! tournament with each match, and the worker [`follow`]s it, so the
+//! matches played across workers land in the coordinator's trace.
+
+use opentelemetry::propagation::TextMapPropagator;
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_otlp::{ExporterBuildError, WithExportConfig};
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::{SdkTracer, SdkTracerProvider};
+use opentelemetry_sdk::Resource;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use tracing::{Span, Subscriber};
+use tracing_opentelemetry::{OpenTelemetryLayer, OpenTelemetrySpanExt};
+use tracing_subscriber::registry::LookupSpan;
+
+/// Where OTLP/HTTP collectors take traces, under their address
+const TRACES_PATH: &str = "/v1/traces";
+
+/// The service spans are exported as when `OTEL_SERVICE_NAME` isn't set
+const SERVICE_NAME: &str = "actoripd";
+
+/// The key a span's context is carried under, as W3C trace context has it
+const TRACEPARENT: &str = "traceparent";
+
+static PROVIDER: OnceLock<SdkTracerProvider> = OnceLock::new();
+
+/// A layer exporting spans to the collector at `endpoint`, in batches
+pub fn layer<S>(endpoint: &str) -> Result<OpenTelemetryLayer<S, SdkTracer>, ExporterBuildError>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    let endpoint = endpoint.trim_end_matches('/');
+    let endpoint = if endpoint.ends_with(TRACES_PATH) {
+        endpoint.to_owned()
+    } else {
+        format!("{}{}", endpoint, TRACES_PATH)
+    };
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_http()
+        .with_endpoint(endpoint)
+        .build()?;
+    let service = std::env::var("OTEL_SERVICE_NAME").unwrap_or_else(|_| SERVICE_NAME.to_owned());
+    let provider = SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(Resource::builder().with_service_name(service).build())
+        .build();
+    let tracer = provider.tracer(SERVICE_NAME);
+    let _ = PROVIDER.set(provider);
+    Ok(tracing_opentelemetry::layer().with_tracer(tracer))
+}
+
+/// Send the spans not yet exported, before the process exits
+pub fn shutdown() {
+    if let Some(provider) = PROVIDER.get() {
+        let _ = provider.shutdown();
+    }
+}
+
+/// `span`'s context as a W3C `traceparent`, if it is being exported
+pub fn traceparent(span: &Span) -> Option<String> {
+    let mut carrier = HashMap::new();
+    TraceContextPropagator::new().inject_context(&span.context(), &mut carrier);
+    carrier.remove(TRACEPARENT)
+}
+
+/// Make `span` a child of the span that `traceparent` came from
+pub fn follow(span: &Span, traceparent: &str) {
+    let carrier = HashMap::from([(TRACEPARENT.to_owned(), traceparent.to_owned())]);
+    span.set_parent(TraceContextPropagator::new().extract(&carrier));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opentelemetry::trace::TraceContextExt;
+    use tracing::info_span;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    #[test]
+    fn followed_spans_join_the_trace() {
+        let tracer = SdkTracerProvider::builder().build().tracer("test");
+        let subscriber =
+            tracing_subscriber::registry().with(tracing_opentelemetry::layer().with_tracer(tracer));
+        tracing::subscriber::with_default(subscriber, || {
+            let tournament = info_span!("tournament");
+            let carried = traceparent(&tournament).expect("an exported span");
+            assert!(carried.starts_with("00-"));
+
+            let assignment = info_span!(parent: None, "assignment");
+            follow(&assignment, &carried);
+            let trace = |span: &Span| span.context().span().span_context().trace_id();
+            assert_eq!(trace(&assignment), trace(&tournament));
+        });
+        // nothing is carried from a span that isn't exported
+        assert_eq!(traceparent(&info_span!("tournament")), None);
+    }
+}
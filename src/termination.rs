@@ -0,0 +1,280 @@
+//! When a match ends
+//!
+//! A referee checks each of its [`Termination`] conditions after every
+//! round and stops at the first one that is met. The fixed round limit is
+//! always among them, so every match is bounded.
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::game::{Action, Score};
+use crate::randomness::Stream;
+
+/// Where a match stands after a round has been scored
+#[derive(Debug, Clone, Copy)]
+pub struct MatchProgress {
+    pub rounds: usize,
+    pub blue_score: Score,
+    pub red_score: Score,
+
+    /// What blue and red executed in that round
+    pub last: (Action, Action),
+}
+
+/// A condition that ends a match
+pub trait Termination: Send {
+    /// Whether the match should stop now; `rng` is the referee's own
+    fn finished(&mut self, progress: &MatchProgress, rng: &mut Stream) -> bool;
+
+    /// What to say stopped the match, e.g. `continuation`
+    fn name(&self) -> &'static str;
+}
+
+/// Stop after a fixed number of rounds
+pub struct FixedLength(pub usize);
+
+impl FixedLength {
+    pub const NAME: &'static str = "iterations";
+}
+
+impl Termination for FixedLength {
+    fn finished(&mut self, progress: &MatchProgress, _rng: &mut Stream) -> bool {
+        progress.rounds >= self.0
+    }
+
+    fn name(&self) -> &'static str {
+        FixedLength::NAME
+    }
+}
+
+/// The "shadow of the future": after each round, play another with
+/// probability `probability`
+///
+/// Matches last `1 / (1 - probability)` rounds on average, so strategies
+/// can't count on knowing which round is the last.
+pub struct Continuation {
+    pub probability: f64,
+}
+
+impl Termination for Continuation {
+    fn finished(&mut self, _progress: &MatchProgress, rng: &mut Stream) -> bool {
+        rng.gen::<f64>() >= self.probability
+    }
+
+    fn name(&self) -> &'static str {
+        "continuation"
+    }
+}
+
+/// Stop as soon as either prisoner has scored at least `score`
+pub struct ScoreThreshold(pub Score);
+
+impl Termination for ScoreThreshold {
+    fn finished(&mut self, progress: &MatchProgress, _rng: &mut Stream) -> bool {
+        progress.blue_score >= self.0 || progress.red_score >= self.0
+    }
+
+    fn name(&self) -> &'static str {
+        "score-threshold"
+    }
+}
+
+/// Stop once the play has settled: the last `rounds` rounds all went the
+/// same way, both players repeating their actions
+pub struct Converged {
+    rounds: usize,
+    last: Option<(Action, Action)>,
+    streak: usize,
+}
+
+impl Converged {
+    pub fn new(rounds: usize) -> Converged {
+        Converged {
+            rounds,
+            last: None,
+            streak: 0,
+        }
+    }
+}
+
+impl Termination for Converged {
+    fn finished(&mut self, progress: &MatchProgress, _rng: &mut Stream) -> bool {
+        if self.last == Some(progress.last) {
+            self.streak += 1;
+        } else {
+            self.last = Some(progress.last);
+            self.streak = 1;
+        }
+        self.streak >= self.rounds
+    }
+
+    fn name(&self) -> &'static str {
+        "converged"
+    }
+}
+
+/// How matches end, in addition to the `iterations` limit
+///
+/// ```toml
+/// [termination]
+/// kind = "continuation"
+/// probability = 0.99
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case", deny_unknown_fields)]
+pub enum TerminationConfig {
+    /// Play exactly `iterations` rounds
+    #[default]
+    Fixed,
+
+    /// Continue after each round with probability `probability`
+    Continuation { probability: f64 },
+
+    /// Stop once either prisoner reaches `score`
+    ScoreThreshold { score: Score },
+
+    /// Stop once the last `rounds` rounds were all alike
+    Converged { rounds: usize },
+}
+
+impl TerminationConfig {
+    /// The condition to add to a referee, if any
+    pub fn build(&self) -> Option<Box<dyn Termination>> {
+        match *self {
+            TerminationConfig::Fixed => None,
+            TerminationConfig::Continuation { probability } => {
+                Some(Box::new(Continuation { probability }))
+            }
+            TerminationConfig::ScoreThreshold { score } => Some(Box::new(ScoreThreshold(score))),
+            TerminationConfig::Converged { rounds } => Some(Box::new(Converged::new(rounds))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    use crate::game::PayoffMatrix;
+    use crate::local::LocalMatch;
+    use crate::strategies::{AlwaysCooperate, AlwaysDefect};
+
+    fn progress(rounds: usize, blue_score: Score, red_score: Score) -> MatchProgress {
+        MatchProgress {
+            rounds,
+            blue_score,
+            red_score,
+            last: (Action::COOPERATE, Action::COOPERATE),
+        }
+    }
+
+    #[test]
+    fn continuation_lasts_one_over_one_minus_p_rounds_on_average() {
+        let mut rng = Stream::seed_from_u64(42);
+        let mut continuation = Continuation { probability: 0.9 };
+        let matches = 5_000;
+        let mut rounds = 0;
+        for _ in 0..matches {
+            let mut played = 1;
+            while !continuation.finished(&progress(played, 0.0, 0.0), &mut rng) {
+                played += 1;
+            }
+            rounds += played;
+        }
+        let mean = rounds as f64 / matches as f64;
+        assert!((mean - 10.0).abs() < 0.5, "mean length {}", mean);
+    }
+
+    #[test]
+    fn continuation_at_the_extremes() {
+        let mut rng = Stream::seed_from_u64(42);
+        let mut never = Continuation { probability: 0.0 };
+        let mut always = Continuation { probability: 1.0 };
+        for _ in 0..100 {
+            assert!(never.finished(&progress(1, 0.0, 0.0), &mut rng));
+            assert!(!always.finished(&progress(1, 0.0, 0.0), &mut rng));
+        }
+    }
+
+    #[test]
+    fn score_threshold_stops_when_either_side_reaches_it() {
+        let mut rng = Stream::seed_from_u64(42);
+        let mut threshold = ScoreThreshold(10.0);
+        assert!(!threshold.finished(&progress(3, 9.0, 9.5), &mut rng));
+        assert!(threshold.finished(&progress(4, 10.0, 2.0), &mut rng));
+        assert!(threshold.finished(&progress(4, 2.0, 11.0), &mut rng));
+    }
+
+    #[test]
+    fn a_configured_condition_joins_the_round_limit() {
+        let payoffs = PayoffMatrix::default();
+        let play = |termination: TerminationConfig, iterations| {
+            let mut game = LocalMatch::new(
+                ("alld", Box::new(AlwaysDefect {})),
+                ("allc", Box::new(AlwaysCooperate {})),
+                payoffs,
+                iterations,
+            )
+            .with_seed(7);
+            if let Some(termination) = termination.build() {
+                game = game.with_termination(termination);
+            }
+            game.play()
+        };
+
+        assert!(TerminationConfig::Fixed.build().is_none());
+        let fixed = play(TerminationConfig::Fixed, 20);
+        assert_eq!((fixed.rounds, fixed.stopped_by), (20, None));
+
+        // always defect earns the temptation of 4 a round
+        let threshold = play(TerminationConfig::ScoreThreshold { score: 10.0 }, 20);
+        assert_eq!(threshold.rounds, 3);
+        assert_eq!(threshold.stopped_by.as_deref(), Some("score-threshold"));
+
+        // the round limit comes first when the threshold is out of reach
+        let limited = play(TerminationConfig::ScoreThreshold { score: 1000.0 }, 20);
+        assert_eq!((limited.rounds, limited.stopped_by), (20, None));
+
+        let seeded = || play(TerminationConfig::Continuation { probability: 0.9 }, 1000);
+        let (first, second) = (seeded(), seeded());
+        assert_eq!(first.rounds, second.rounds);
+        assert!(first.rounds < 1000);
+        assert_eq!(first.stopped_by.as_deref(), Some("continuation"));
+    }
+
+    #[test]
+    fn converged_waits_for_a_run_of_identical_rounds() {
+        let mut rng = Stream::seed_from_u64(42);
+        let mut converged = Converged::new(3);
+        let (c, d) = (Action::COOPERATE, Action::DEFECT);
+        let rounds = [(c, c), (c, d), (c, d), (d, d), (d, d), (d, d)];
+        let finished: Vec<bool> = rounds
+            .iter()
+            .enumerate()
+            .map(|(i, &last)| {
+                let progress = MatchProgress {
+                    last,
+                    ..progress(i + 1, 0.0, 0.0)
+                };
+                converged.finished(&progress, &mut rng)
+            })
+            .collect();
+        assert_eq!(finished, [false, false, false, false, false, true]);
+    }
+
+    #[test]
+    fn a_configured_convergence_stops_a_settled_match() {
+        let termination = TerminationConfig::Converged { rounds: 5 }.build().unwrap();
+        let result = LocalMatch::new(
+            ("alld", Box::new(AlwaysDefect {})),
+            ("allc", Box::new(AlwaysCooperate {})),
+            PayoffMatrix::default(),
+            100,
+        )
+        .with_termination(termination)
+        .play();
+        assert_eq!(result.rounds, 5);
+        assert_eq!(result.stopped_by.as_deref(), Some("converged"));
+    }
+}
@@ -0,0 +1,365 @@
+//! Who plays whom
+//!
+//! A [`Topology`] is an undirected interaction graph over numbered entrants.
+//! The tournament scheduler plays one match per edge: a round robin is the
+//! complete graph, and spatial games use a [`Lattice`] or a [`Graph`] loaded
+//! from a file or generated as a small-world or scale-free network.
+
+use rand::rngs::StdRng;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// An undirected interaction graph without self-loops
+pub trait Topology {
+    fn nodes(&self) -> usize;
+
+    /// Everyone `node` plays, in ascending order
+    fn neighbours(&self, node: usize) -> Vec<usize>;
+
+    /// Every pair of neighbours once, lower index first, in a fixed order
+    fn edges(&self) -> Vec<(usize, usize)> {
+        (0..self.nodes())
+            .flat_map(|node| {
+                self.neighbours(node)
+                    .into_iter()
+                    .filter(move |&n| node < n)
+                    .map(move |n| (node, n))
+            })
+            .collect()
+    }
+}
+
+/// Everyone plays everyone: a round robin
+#[derive(Debug, Clone, Copy)]
+pub struct Complete(pub usize);
+
+impl Topology for Complete {
+    fn nodes(&self) -> usize {
+        self.0
+    }
+
+    fn neighbours(&self, node: usize) -> Vec<usize> {
+        (0..self.0).filter(|&n| n != node).collect()
+    }
+
+    fn edges(&self) -> Vec<(usize, usize)> {
+        (0..self.0)
+            .flat_map(|i| (i + 1..self.0).map(move |j| (i, j)))
+            .collect()
+    }
+}
+
+/// A `width` x `height` grid that wraps around at the edges, each cell
+/// neighbouring the eight around it
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Lattice {
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Lattice {
+    /// Both sides must be at least 3 so that the eight neighbours are distinct
+    pub fn new(width: usize, height: usize) -> Option<Lattice> {
+        if width < 3 || height < 3 {
+            None
+        } else {
+            Some(Lattice { width, height })
+        }
+    }
+}
+
+impl Topology for Lattice {
+    fn nodes(&self) -> usize {
+        self.width * self.height
+    }
+
+    /// The Moore neighbourhood of the cell at row-major index `node`
+    fn neighbours(&self, node: usize) -> Vec<usize> {
+        let (x, y) = (node % self.width, node / self.width);
+        let mut neighbours = Vec::with_capacity(8);
+        for dy in &[self.height - 1, 0, 1] {
+            for dx in &[self.width - 1, 0, 1] {
+                if (*dx, *dy) != (0, 0) {
+                    let nx = (x + dx) % self.width;
+                    let ny = (y + dy) % self.height;
+                    neighbours.push(ny * self.width + nx);
+                }
+            }
+        }
+        neighbours.sort_unstable();
+        neighbours
+    }
+}
+
+#[derive(Debug)]
+pub enum TopologyError {
+    Io(io::Error),
+    Parse { line: usize, reason: String },
+    Invalid(String),
+}
+
+impl fmt::Display for TopologyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TopologyError::Io(e) => write!(f, "unable to read edge list: {}", e),
+            TopologyError::Parse { line, reason } => {
+                write!(f, "unable to parse edge list: line {}: {}", line, reason)
+            }
+            TopologyError::Invalid(s) => write!(f, "invalid network: {}", s),
+        }
+    }
+}
+
+impl std::error::Error for TopologyError {}
+
+/// An arbitrary interaction graph held as adjacency sets
+#[derive(Debug, Clone)]
+pub struct Graph {
+    adjacency: Vec<BTreeSet<usize>>,
+}
+
+impl Graph {
+    /// A graph on `nodes` nodes; self-loops and repeated edges are ignored
+    pub fn from_edges(nodes: usize, edges: &[(usize, usize)]) -> Result<Graph, TopologyError> {
+        let mut graph = Graph {
+            adjacency: vec![BTreeSet::new(); nodes],
+        };
+        for &(a, b) in edges {
+            if a >= nodes || b >= nodes {
+                return Err(TopologyError::Invalid(format!(
+                    "edge {} {} refers to a node beyond {}",
+                    a,
+                    b,
+                    nodes.saturating_sub(1)
+                )));
+            }
+            graph.connect(a, b);
+        }
+        Ok(graph)
+    }
+
+    /// Read an edge list: one `a b` pair of zero-based node numbers per line
+    ///
+    /// Blank lines and anything after a `#` are ignored. The graph has as
+    /// many nodes as the highest number mentioned, plus one.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Graph, TopologyError> {
+        let text = fs::read_to_string(path).map_err(TopologyError::Io)?;
+        Graph::parse(&text)
+    }
+
+    pub fn parse(text: &str) -> Result<Graph, TopologyError> {
+        let mut edges = Vec::new();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let parse_error = |reason: String| TopologyError::Parse {
+                line: i + 1,
+                reason,
+            };
+            let nodes: Vec<usize> = line
+                .split_whitespace()
+                .map(|n| {
+                    n.parse()
+                        .map_err(|_| parse_error(format!("'{}' is not a node number", n)))
+                })
+                .collect::<Result<_, _>>()?;
+            match nodes[..] {
+                [a, b] => edges.push((a, b)),
+                _ => return Err(parse_error("expected two node numbers".to_owned())),
+            }
+        }
+        let nodes = edges.iter().map(|&(a, b)| a.max(b) + 1).max().unwrap_or(0);
+        Graph::from_edges(nodes, &edges)
+    }
+
+    /// A Watts-Strogatz small-world network
+    ///
+    /// Start from a ring where each node neighbours the `degree / 2` nearest
+    /// on either side, then rewire each edge's far end to a random node with
+    /// probability `rewire`.
+    pub fn small_world(
+        nodes: usize,
+        degree: usize,
+        rewire: f64,
+        rng: &mut StdRng,
+    ) -> Result<Graph, TopologyError> {
+        if degree < 2 || !degree.is_multiple_of(2) || degree >= nodes {
+            return Err(TopologyError::Invalid(format!(
+                "a small-world degree must be even, at least 2, and below the {} nodes",
+                nodes
+            )));
+        }
+        if !(0.0..=1.0).contains(&rewire) {
+            return Err(TopologyError::Invalid(format!(
+                "rewiring probability {} is not a probability",
+                rewire
+            )));
+        }
+
+        let mut graph = Graph::from_edges(nodes, &[])?;
+        for node in 0..nodes {
+            for offset in 1..=degree / 2 {
+                graph.connect(node, (node + offset) % nodes);
+            }
+        }
+        for node in 0..nodes {
+            for offset in 1..=degree / 2 {
+                let far = (node + offset) % nodes;
+                // a node already connected to everyone has nowhere to go
+                if rewire > 0.0
+                    && rng.gen::<f64>() < rewire
+                    && graph.adjacency[node].len() < nodes - 1
+                {
+                    let target = loop {
+                        let target = rng.gen_range(0, nodes);
+                        if target != node && !graph.adjacency[node].contains(&target) {
+                            break target;
+                        }
+                    };
+                    graph.disconnect(node, far);
+                    graph.connect(node, target);
+                }
+            }
+        }
+        Ok(graph)
+    }
+
+    /// A Barabasi-Albert scale-free network
+    ///
+    /// Start from a complete graph on `degree + 1` nodes, then attach each
+    /// new node to `degree` distinct existing nodes chosen in proportion to
+    /// their degree.
+    pub fn scale_free(
+        nodes: usize,
+        degree: usize,
+        rng: &mut StdRng,
+    ) -> Result<Graph, TopologyError> {
+        if degree == 0 || degree >= nodes {
+            return Err(TopologyError::Invalid(format!(
+                "a scale-free degree must be at least 1 and below the {} nodes",
+                nodes
+            )));
+        }
+
+        let seed_edges = Complete(degree + 1).edges();
+        let mut graph = Graph::from_edges(nodes, &seed_edges)?;
+        // every edge end once, so a uniform pick is proportional to degree
+        let mut ends: Vec<usize> = seed_edges.iter().flat_map(|&(a, b)| vec![a, b]).collect();
+        for node in degree + 1..nodes {
+            let mut targets = BTreeSet::new();
+            while targets.len() < degree {
+                targets.insert(ends[rng.gen_range(0, ends.len())]);
+            }
+            for target in targets {
+                graph.connect(node, target);
+                ends.push(node);
+                ends.push(target);
+            }
+        }
+        Ok(graph)
+    }
+
+    fn connect(&mut self, a: usize, b: usize) {
+        if a != b {
+            self.adjacency[a].insert(b);
+            self.adjacency[b].insert(a);
+        }
+    }
+
+    fn disconnect(&mut self, a: usize, b: usize) {
+        self.adjacency[a].remove(&b);
+        self.adjacency[b].remove(&a);
+    }
+}
+
+impl Topology for Graph {
+    fn nodes(&self) -> usize {
+        self.adjacency.len()
+    }
+
+    fn neighbours(&self, node: usize) -> Vec<usize> {
+        self.adjacency[node].iter().copied().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    fn degrees<T: Topology>(topology: &T) -> Vec<usize> {
+        (0..topology.nodes())
+            .map(|node| topology.neighbours(node).len())
+            .collect()
+    }
+
+    #[test]
+    fn complete_edges_are_every_pair_once() {
+        let complete = Complete(4);
+        assert_eq!(complete.neighbours(2), vec![0, 1, 3]);
+        assert_eq!(
+            complete.edges(),
+            vec![(0, 1), (0, 2), (0, 3), (1, 2), (1, 3), (2, 3)]
+        );
+        assert!(Complete(1).edges().is_empty());
+    }
+
+    #[test]
+    fn lattices_wrap_around() {
+        assert!(Lattice::new(2, 5).is_none());
+        let lattice = Lattice::new(4, 3).unwrap();
+        assert_eq!(lattice.nodes(), 12);
+        // the corner at (0, 0) reaches across both edges
+        assert_eq!(lattice.neighbours(0), vec![1, 3, 4, 5, 7, 8, 9, 11]);
+        assert_eq!(lattice.neighbours(5), vec![0, 1, 2, 4, 6, 8, 9, 10]);
+        assert!(degrees(&lattice).iter().all(|&d| d == 8));
+        assert_eq!(lattice.edges().len(), 12 * 8 / 2);
+        for (a, b) in lattice.edges() {
+            assert!(a < b);
+            assert!(lattice.neighbours(b).contains(&a));
+        }
+    }
+
+    #[test]
+    fn edge_lists_parse_into_graphs() {
+        let graph = Graph::parse("# a path\n0 1\n\n1 2 # and back\n2 1\n3 3\n").unwrap();
+        assert_eq!(graph.nodes(), 4);
+        assert_eq!(graph.edges(), vec![(0, 1), (1, 2)]);
+        assert_eq!(graph.neighbours(1), vec![0, 2]);
+        assert!(graph.neighbours(3).is_empty());
+
+        match Graph::parse("0 1\n1 x\n") {
+            Err(TopologyError::Parse { line: 2, .. }) => {}
+            other => panic!("expected a parse error on line 2, got {:?}", other),
+        }
+        assert!(Graph::parse("0 1 2\n").is_err());
+        assert!(Graph::from_edges(2, &[(0, 2)]).is_err());
+    }
+
+    #[test]
+    fn generated_networks_keep_their_degree() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let ring = Graph::small_world(10, 4, 0.0, &mut rng).unwrap();
+        assert!(degrees(&ring).iter().all(|&d| d == 4));
+        assert_eq!(ring.neighbours(0), vec![1, 2, 8, 9]);
+
+        // rewiring moves edges but never adds or drops one
+        let rewired = Graph::small_world(20, 4, 0.5, &mut rng).unwrap();
+        assert_eq!(rewired.edges().len(), 20 * 4 / 2);
+
+        let scale_free = Graph::scale_free(30, 2, &mut rng).unwrap();
+        assert_eq!(scale_free.edges().len(), 3 + (30 - 3) * 2);
+        assert!(degrees(&scale_free).iter().all(|&d| d >= 2));
+
+        assert!(Graph::small_world(10, 3, 0.1, &mut rng).is_err());
+        assert!(Graph::small_world(10, 4, 1.5, &mut rng).is_err());
+        assert!(Graph::scale_free(3, 3, &mut rng).is_err());
+    }
+}
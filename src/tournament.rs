@@ -0,0 +1,1095 @@
+use actix::prelude::*;
+use futures::future::{self, join_all, Either};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet};
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tracing::{info_span, Instrument, Span};
+
+use crate::clock;
+use crate::config::{twin_name, ParticipantConfig, SimConfig};
+use crate::control;
+use crate::events;
+use crate::formats::Placing;
+use crate::game::{PayoffMatrix, Score};
+use crate::hooks;
+use crate::memory::{self, Memories, Memory};
+use crate::objectives::Objectives;
+use crate::prisoner::{BudgetAction, FaultKind, MatchId, Prisoner, Swap, TakeMemory};
+use crate::progress;
+use crate::randomness::{self, RandomnessProvider, Seat, TakeDraws};
+use crate::rating::RatingChange;
+use crate::referee::{MatchResult, Play, Referee, Report};
+use crate::registry::{StrategyArgs, StrategyRegistry};
+use crate::reputation::Reputations;
+use crate::scheduler;
+use crate::stats::Summary;
+use crate::strategy::StrategyInfo;
+use crate::topology::{Complete, Topology};
+
+/// Numbers each match started in this process, to tell their log lines
+/// and prisoners' sessions apart
+static NEXT_MATCH: AtomicUsize = AtomicUsize::new(0);
+
+/// An id no other match in this process has
+pub fn next_match_id() -> MatchId {
+    NEXT_MATCH.fetch_add(1, Ordering::Relaxed)
+}
+
+thread_local! {
+    static WORKERS: RefCell<Workers> = RefCell::new(Workers::default());
+}
+
+/// Arbiters that matches are spread across, one thread each
+#[derive(Default)]
+struct Workers {
+    arbiters: Vec<Arbiter>,
+    next: usize,
+}
+
+/// Spread the matches started from this thread across `threads` new arbiters
+///
+/// With 0 or 1 threads, the default, every match runs on the current arbiter.
+/// Call this from inside a running `System`; the arbiters stop with it.
+/// Each match still draws its seeds in a fixed order, so results don't depend
+/// on the thread count, only the order in which matches finish does.
+pub fn use_threads(threads: usize) {
+    WORKERS.with(|workers| {
+        let mut workers = workers.borrow_mut();
+        workers.arbiters = if threads > 1 {
+            (0..threads).map(|_| Arbiter::new()).collect()
+        } else {
+            Vec::new()
+        };
+        workers.next = 0;
+    });
+}
+
+/// The arbiter for the next match, round-robin across the workers, or
+/// `None` to use the current one
+pub(crate) fn next_worker() -> Option<Arbiter> {
+    WORKERS.with(|workers| {
+        let mut workers = workers.borrow_mut();
+        if workers.arbiters.is_empty() {
+            return None;
+        }
+        let index = workers.next % workers.arbiters.len();
+        workers.next = index + 1;
+        Some(workers.arbiters[index].clone())
+    })
+}
+
+/// Start `actor` on `worker`, or on the current arbiter
+pub(crate) fn start_on<A>(worker: Option<&Arbiter>, actor: A) -> Addr<A>
+where
+    A: Actor<Context = Context<A>> + Send,
+{
+    match worker {
+        Some(arbiter) => A::start_in_arbiter(arbiter, |_| actor),
+        None => actor.start(),
+    }
+}
+
+/// One finished pairing in a tournament
+#[derive(Debug, Clone)]
+pub struct MatchReport {
+    pub blue: String,
+    pub red: String,
+
+    /// What the match was played from, enough to play it again
+    pub seeds: MatchSeeds,
+    pub result: Result<MatchResult, MailboxError>,
+}
+
+/// A round robin cut short: what it was played from, and the matches it
+/// finished, by their place in its [`schedule`]
+#[derive(Debug, Clone)]
+pub struct PartialTournament {
+    pub config: SimConfig,
+    pub seed: u64,
+    pub played: BTreeMap<usize, MatchResult>,
+}
+
+/// The seeds every random draw in one match comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MatchSeeds {
+    pub blue: u64,
+    pub red: u64,
+    pub referee: u64,
+}
+
+impl MatchSeeds {
+    /// The next match's seeds from `seeds`
+    pub fn draw(seeds: &mut StdRng) -> MatchSeeds {
+        MatchSeeds {
+            blue: seeds.gen(),
+            red: seeds.gen(),
+            referee: seeds.gen(),
+        }
+    }
+}
+
+/// A pairing that never produced a result
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AbortedMatch {
+    pub blue: String,
+    pub red: String,
+    pub error: String,
+}
+
+/// One participant's totals across a tournament
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Standing {
+    pub name: String,
+    pub strategy: String,
+    pub matches: usize,
+    pub rounds: usize,
+    pub score: Score,
+
+    /// Points per round across all matches
+    pub average_score: f64,
+
+    /// Points per match, the measure Axelrod reported
+    pub average_match_score: f64,
+    pub cooperation_rate: f64,
+
+    /// What the registry says of the strategy, once asked; see
+    /// [`TournamentRecord::describe_strategies`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub about: Option<StrategyInfo>,
+
+    /// How it did on each objective, if the config ranks on more than
+    /// score; see [`crate::objectives`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub objectives: Option<Objectives>,
+}
+
+/// Everything needed to reproduce and analyse a tournament
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TournamentRecord {
+    pub config: SimConfig,
+    pub seed: u64,
+    pub matches: Vec<MatchResult>,
+    pub aborted: Vec<AbortedMatch>,
+
+    /// Highest total score first, leaving out the disqualified
+    pub standings: Vec<Standing>,
+
+    /// Participants that went over budget in a match when that disqualifies
+    /// them; see [`BudgetAction::Disqualify`]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub disqualified: Vec<String>,
+
+    /// Each strategy's rating after this tournament, if ratings were kept;
+    /// see [`crate::rating`]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ratings: Vec<RatingChange>,
+
+    /// Each pairing's scores over its repetitions, if it had more than one
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pairings: Vec<PairingSummary>,
+
+    /// The final order of an elimination or Swiss tournament; see
+    /// [`crate::formats`]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub placings: Vec<Placing>,
+
+    /// What each participant last remembered of each opponent, if the
+    /// config keeps memories; see [`crate::memory`]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub memories: Vec<Memory>,
+}
+
+/// Points per match for both sides of a pairing, over its repetitions
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PairingSummary {
+    pub blue: String,
+    pub red: String,
+    pub blue_score: Summary,
+    pub red_score: Summary,
+}
+
+impl TournamentRecord {
+    pub fn new(config: &SimConfig, seed: u64, reports: Vec<MatchReport>) -> TournamentRecord {
+        let mut matches = Vec::new();
+        let mut aborted = Vec::new();
+        for report in reports {
+            match report.result {
+                Ok(result) => matches.push(result),
+                Err(e) => aborted.push(AbortedMatch {
+                    blue: report.blue,
+                    red: report.red,
+                    error: e.to_string(),
+                }),
+            }
+        }
+        let disqualified = disqualified(config, &matches);
+        let mut standings: Vec<Standing> = standings(config, &matches)
+            .into_iter()
+            .filter(|standing| !disqualified.contains(&standing.name))
+            .collect();
+        if let Some(ranking) = config.ranking {
+            standings = ranking.rank(&config.payoffs(), &matches, standings);
+        }
+        let pairings = if config.repetitions > 1 {
+            pairings(&matches)
+        } else {
+            Vec::new()
+        };
+        TournamentRecord {
+            config: config.clone(),
+            seed,
+            memories: memory::last(&matches),
+            matches,
+            aborted,
+            standings,
+            disqualified,
+            ratings: Vec::new(),
+            pairings,
+            placings: Vec::new(),
+        }
+    }
+
+    /// Have the registry describe each standing's strategy, as configured
+    /// for its participant
+    pub fn describe_strategies(&mut self, registry: &StrategyRegistry) {
+        let payoffs = self.config.payoffs();
+        for standing in &mut self.standings {
+            let participant = self
+                .config
+                .participants
+                .iter()
+                .find(|participant| participant.name == standing.name);
+            if let Some(participant) = participant {
+                let args = StrategyArgs {
+                    parameters: &participant.parameters,
+                    payoffs: &payoffs,
+                    seed: 0,
+                };
+                standing.about = registry.describe(&participant.strategy, &args).ok();
+            }
+        }
+    }
+}
+
+/// Summarize each pairing's match scores, in the order the pairings were
+/// first played
+///
+/// Aborted repetitions are left out, so a summary may count fewer matches
+/// than were scheduled.
+pub fn pairings(matches: &[MatchResult]) -> Vec<PairingSummary> {
+    let mut order: Vec<(&str, &str)> = Vec::new();
+    let mut scores: Vec<(Vec<f64>, Vec<f64>)> = Vec::new();
+    let mut index = BTreeMap::new();
+    for result in matches {
+        let key = (result.blue.name.as_str(), result.red.name.as_str());
+        let i = *index.entry(key).or_insert_with(|| {
+            order.push(key);
+            scores.push(Default::default());
+            scores.len() - 1
+        });
+        scores[i].0.push(result.blue.score);
+        scores[i].1.push(result.red.score);
+    }
+    order
+        .into_iter()
+        .zip(&scores)
+        .map(|((blue, red), (blue_scores, red_scores))| PairingSummary {
+            blue: blue.to_owned(),
+            red: red.to_owned(),
+            blue_score: Summary::of(blue_scores),
+            red_score: Summary::of(red_scores),
+        })
+        .collect()
+}
+
+/// Total up each participant's results, highest score first
+pub fn standings(config: &SimConfig, matches: &[MatchResult]) -> Vec<Standing> {
+    let mut totals: BTreeMap<&str, (usize, usize, Score, usize)> = BTreeMap::new();
+    for result in matches {
+        for player in &[&result.blue, &result.red] {
+            let entry = totals.entry(player.name.as_str()).or_default();
+            entry.0 += 1;
+            entry.1 += result.rounds;
+            entry.2 += player.score;
+            entry.3 += player.cooperations;
+        }
+    }
+
+    let mut standings: Vec<Standing> = config
+        .participants
+        .iter()
+        .map(|participant| {
+            let (matches, rounds, score, cooperations) = totals
+                .get(participant.name.as_str())
+                .copied()
+                .unwrap_or_default();
+            let per = |n: f64, d: usize| if d == 0 { 0.0 } else { n / d as f64 };
+            Standing {
+                name: participant.name.clone(),
+                strategy: participant.strategy.clone(),
+                matches,
+                rounds,
+                score,
+                average_score: per(score, rounds),
+                average_match_score: per(score, matches),
+                cooperation_rate: per(cooperations as f64, rounds),
+                about: None,
+                objectives: None,
+            }
+        })
+        .collect();
+    standings.sort_by(|a, b| {
+        b.score
+            .total_cmp(&a.score)
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    standings
+}
+
+/// Everyone who went over budget in any of `matches`, by name, if the
+/// config's budget disqualifies them for it
+pub fn disqualified(config: &SimConfig, matches: &[MatchResult]) -> Vec<String> {
+    if !config
+        .budget
+        .is_some_and(|budget| budget.on_exceed == BudgetAction::Disqualify)
+    {
+        return Vec::new();
+    }
+    let names: BTreeSet<String> = matches
+        .iter()
+        .flat_map(|result| &result.faults)
+        .filter(|fault| fault.kind == FaultKind::OverBudget)
+        .map(|fault| fault.name.clone())
+        .collect();
+    names.into_iter().collect()
+}
+
+/// A prisoner playing `participant`'s strategy, with the spares its
+/// config's fault policy calls for and held to its budget
+///
+/// Its strategies are told the payoffs the participant sees, unless the
+/// game is blind. Every instance is built from the same `seed`, so a restarted strategy
+/// starts out just as the original did.
+pub(crate) fn prisoner(
+    config: &SimConfig,
+    registry: &StrategyRegistry,
+    participant: &ParticipantConfig,
+    seed: u64,
+) -> Prisoner {
+    prisoner_told(
+        config,
+        registry,
+        participant,
+        config.payoffs_told(participant),
+        seed,
+    )
+}
+
+/// A prisoner as [`prisoner`] builds it, whose strategies are told the game
+/// pays `payoffs`
+fn prisoner_told(
+    config: &SimConfig,
+    registry: &StrategyRegistry,
+    participant: &ParticipantConfig,
+    payoffs: PayoffMatrix,
+    seed: u64,
+) -> Prisoner {
+    let args = StrategyArgs {
+        parameters: &participant.parameters,
+        payoffs: &payoffs,
+        seed,
+    };
+    let build = || {
+        registry
+            .create(&participant.strategy, &args)
+            .expect("validated strategy")
+    };
+    let spares = (0..config.faults.restarts()).map(|_| build()).collect();
+    let mut prisoner = Prisoner::new(&participant.name, build()).with_spares(spares);
+    if let Some(swap) = &participant.swap {
+        let args = StrategyArgs {
+            parameters: &swap.parameters,
+            ..args
+        };
+        let build = || {
+            registry
+                .create(&swap.strategy, &args)
+                .expect("validated strategy")
+        };
+        prisoner = prisoner.with_swap(Swap {
+            round: swap.round,
+            strategy: build(),
+            spares: (0..config.faults.restarts()).map(|_| build()).collect(),
+            reset: swap.reset,
+        });
+    }
+    match config.budget {
+        Some(budget) => prisoner.with_budget(budget),
+        None => prisoner,
+    }
+}
+
+/// What a match shares with the others played alongside or before it
+#[derive(Debug, Clone, Default)]
+pub struct Shared {
+    /// Every player's reputation, if the config keeps them
+    pub reputations: Option<Reputations>,
+
+    /// What the players remember of each other, if the config keeps
+    /// memories
+    pub memories: Option<Memories>,
+}
+
+impl Shared {
+    /// A fresh set of whatever `config` has its matches share
+    pub fn new(config: &SimConfig) -> Shared {
+        Shared {
+            reputations: config.reputation.map(Reputations::new),
+            memories: config.remember_opponents.then(Memories::new),
+        }
+    }
+}
+
+/// Start one match between two participants under its own referee
+///
+/// The referee and both prisoners share an arbiter, picked from the workers
+/// set up by [`use_threads`]. The returned future resolves when the match ends. The same
+/// `seeds` give the same match, unless its players time out or the match
+/// reads and updates reputations `shared` with other matches. Memories
+/// `shared` are read as the match starts and updated once it has ended.
+pub fn start_match(
+    config: &SimConfig,
+    registry: &StrategyRegistry,
+    blue: &ParticipantConfig,
+    red: &ParticipantConfig,
+    seeds: MatchSeeds,
+    reporter: Option<&Recipient<Report>>,
+    shared: &Shared,
+) -> impl Future<Output = MatchReport> {
+    let payoffs = config.payoffs();
+    let match_id = next_match_id();
+    let span = info_span!(
+        "match",
+        id = match_id,
+        blue = %blue.name,
+        red = %red.name
+    );
+    let worker = next_worker();
+    let (blue_told, red_told) = (config.payoffs_told(blue), config.red_payoffs_told(red));
+    let (blue_prisoner, red_prisoner, randomness) = if config.audit_randomness {
+        let provider = start_on(
+            worker.as_ref(),
+            RandomnessProvider::new(&blue.name, &red.name),
+        );
+        // every instance of a side's strategy draws through that side's seat
+        let audited = |participant: &ParticipantConfig, payoffs, seed| {
+            let seat = Seat::new();
+            randomness::seated(&seat, || {
+                prisoner_told(config, registry, participant, payoffs, seed)
+            })
+            .with_randomness(seat, provider.clone().recipient())
+        };
+        (
+            audited(blue, blue_told, seeds.blue),
+            audited(red, red_told, seeds.red),
+            Some(provider),
+        )
+    } else {
+        (
+            prisoner_told(config, registry, blue, blue_told, seeds.blue),
+            prisoner_told(config, registry, red, red_told, seeds.red),
+            None,
+        )
+    };
+    let (mut blue_prisoner, mut red_prisoner) =
+        (blue_prisoner.in_span(&span), red_prisoner.in_span(&span));
+    if let Some(memories) = &shared.memories {
+        blue_prisoner = blue_prisoner.with_memories(memories.recall(&blue.name, &red.name));
+        red_prisoner = red_prisoner.with_memories(memories.recall(&red.name, &blue.name));
+    }
+    let blue_addr = start_on(worker.as_ref(), blue_prisoner);
+    let red_addr = start_on(worker.as_ref(), red_prisoner);
+    let mut referee = Referee::new(
+        (&blue.name, blue_addr.clone()),
+        (&red.name, red_addr.clone()),
+        payoffs,
+        config.iterations,
+    )
+    .with_match_id(match_id)
+    .with_seed(seeds.referee)
+    .with_noise(config.noise)
+    .with_misperception(config.misperception)
+    .with_discount(config.discount.unwrap_or(1.0))
+    .with_retries(config.retries)
+    .with_tags(blue.tag, red.tag)
+    .with_span(span);
+    if let Some(termination) = config.termination.build() {
+        referee = referee.with_termination(termination);
+    }
+    if let Some(timeout) = config.decision_timeout() {
+        referee = referee.with_decision_timeout(timeout, config.timeout_action);
+    }
+    if let Some(budget) = config.budget {
+        referee = referee.with_budget(budget.on_exceed);
+    }
+    if let Some(delay) = config.delay() {
+        referee = referee.with_delay(delay);
+    }
+    if let Some(reporter) = reporter {
+        referee = referee.with_reporter(reporter.clone());
+    }
+    if let Some(events) = events::bus() {
+        referee = referee.with_events(events);
+    }
+    if let Some(hooks) = hooks::installed() {
+        referee = referee.with_hooks(hooks);
+    }
+    if let Some(control) = control::steering() {
+        referee = referee.with_control(control);
+    }
+    if let Some(clock) = clock::installed() {
+        referee = referee.with_clock(clock);
+    }
+    if let Some(scheduler) = scheduler::installed() {
+        referee = referee.with_scheduler(scheduler, scheduler::live(blue) || scheduler::live(red));
+    }
+    if let Some(red_payoffs) = config.red_payoffs {
+        referee = referee.with_red_payoffs(red_payoffs);
+    }
+    if config.information.blind {
+        referee = referee.with_blindness();
+    }
+    if config.information.delay > 0 {
+        referee = referee.with_feedback_delay(config.information.delay);
+    }
+    if let Some(visibility) = config.information.visibility {
+        referee = referee.with_visibility(visibility);
+    }
+    if config.cheap_talk {
+        referee = referee.with_cheap_talk();
+    }
+    if config.alternating {
+        referee = referee.with_alternating();
+    }
+    if let Some(reputations) = &shared.reputations {
+        referee = referee.with_reputations(reputations.clone());
+    }
+    if let Some(provider) = &randomness {
+        referee = referee.with_randomness(provider.clone().recipient());
+    }
+    let referee = start_on(worker.as_ref(), referee);
+
+    #[cfg(feature = "metrics")]
+    let (strategies, started) = (
+        (blue.strategy.clone(), red.strategy.clone()),
+        std::time::Instant::now(),
+    );
+    let progress = progress::watching();
+    let memories = shared.memories.clone();
+    let blue = blue.name.clone();
+    let red = red.name.clone();
+    async move {
+        let mut result = referee.send(Play).await.and_then(|result| result);
+        if let (Ok(result), Some(memories)) = (&mut result, memories) {
+            // the prisoners were told the match ended before the result came
+            for (side, addr, opponent) in [
+                (&mut result.blue, blue_addr, &red),
+                (&mut result.red, red_addr, &blue),
+            ] {
+                side.memory = addr.send(TakeMemory { match_id }).await.ok().flatten();
+                if let Some(memory) = &side.memory {
+                    memories.keep(&side.name, opponent, memory.clone());
+                }
+            }
+        }
+        if let (Ok(result), Some(progress)) = (&result, progress) {
+            progress.finish_match(result.rounds);
+        }
+        if let (Ok(result), Some(randomness)) = (&mut result, randomness) {
+            // the prisoners sent their draws before answering for the last round
+            result.draws = randomness.send(TakeDraws).await.ok();
+        }
+        #[cfg(feature = "metrics")]
+        if let Ok(result) = &result {
+            crate::metrics::record_match(&strategies.0, &strategies.1, result, started.elapsed());
+        }
+        MatchReport {
+            blue,
+            red,
+            seeds,
+            result,
+        }
+    }
+}
+
+/// Play the given pairings of `participants` concurrently
+///
+/// Reports come back in the same order as `pairs`. Each match's seeds are
+/// drawn from `seeds` in that order. If the config keeps reputations, these
+/// matches share one set of them, starting afresh.
+pub async fn play_pairs(
+    config: &SimConfig,
+    registry: &StrategyRegistry,
+    participants: &[ParticipantConfig],
+    pairs: &[(usize, usize)],
+    seeds: &mut StdRng,
+    reporter: Option<Recipient<Report>>,
+) -> Vec<MatchReport> {
+    let shared = Shared {
+        memories: None,
+        ..Shared::new(config)
+    };
+    play_shared(
+        config,
+        registry,
+        participants,
+        pairs,
+        seeds,
+        reporter,
+        &shared,
+    )
+    .await
+}
+
+/// [`play_pairs`], with the matches sharing `shared`
+async fn play_shared(
+    config: &SimConfig,
+    registry: &StrategyRegistry,
+    participants: &[ParticipantConfig],
+    pairs: &[(usize, usize)],
+    seeds: &mut StdRng,
+    reporter: Option<Recipient<Report>>,
+    shared: &Shared,
+) -> Vec<MatchReport> {
+    let matches: Vec<_> = pairs
+        .iter()
+        .map(|&(i, j)| {
+            start_match(
+                config,
+                registry,
+                &participants[i],
+                &participants[j],
+                MatchSeeds::draw(seeds),
+                reporter.as_ref(),
+                shared,
+            )
+        })
+        .collect();
+    join_all(matches).await
+}
+
+/// Play one match along every edge of `topology`, whose nodes are indexes
+/// into `participants`
+///
+/// Reports come back in the order of `topology.edges()`.
+pub async fn play_topology(
+    config: &SimConfig,
+    registry: &StrategyRegistry,
+    participants: &[ParticipantConfig],
+    topology: &dyn Topology,
+    seeds: &mut StdRng,
+    reporter: Option<Recipient<Report>>,
+) -> Vec<MatchReport> {
+    let pairs = topology.edges();
+    play_pairs(config, registry, participants, &pairs, seeds, reporter).await
+}
+
+/// `pairs` as `config` has them played: each once, or, if `red_payoffs`
+/// makes the game asymmetric, twice in a row with the sides swapped the
+/// second time, so that neither side of a pairing is always paid as blue
+pub fn seatings(config: &SimConfig, pairs: Vec<(usize, usize)>) -> Vec<(usize, usize)> {
+    if config.red_payoffs.is_some() {
+        pairs
+            .into_iter()
+            .flat_map(|(blue, red)| [(blue, red), (red, blue)])
+            .collect()
+    } else {
+        pairs
+    }
+}
+
+/// Everyone who plays in a round robin of `config`, and the pairings of
+/// them to play, in order
+///
+/// That is the participants, followed by their twins if `self_play` is set,
+/// and every pairing once per repetition, seated as [`seatings`] has it.
+pub fn schedule(config: &SimConfig) -> (Vec<ParticipantConfig>, Vec<(usize, usize)>) {
+    let n = config.participants.len();
+    let mut entrants = config.participants.clone();
+    let mut pairs = Complete(n).edges();
+    if config.self_play {
+        for (i, participant) in config.participants.iter().enumerate() {
+            entrants.push(ParticipantConfig {
+                name: twin_name(&participant.name),
+                ..participant.clone()
+            });
+            pairs.push((i, n + i));
+        }
+    }
+    let pairs = seatings(config, pairs);
+    let pairs = pairs
+        .iter()
+        .copied()
+        .cycle()
+        .take(pairs.len() * config.repetitions)
+        .collect();
+    (entrants, pairs)
+}
+
+/// Play every participant against every other participant, once per
+/// repetition
+///
+/// All matches run concurrently, each under its own referee, unless the
+/// config keeps memories: then the matches of each repetition only start
+/// once the last repetition's have ended, and recall them. Every prisoner
+/// gets its own seed drawn from `seed`, so the same seed replays the same
+/// tournament. The config should already have been validated against `registry`.
+///
+/// Each referee sends its `Report` to `reporter`, if given, as its match ends.
+pub async fn round_robin(
+    config: &SimConfig,
+    registry: &StrategyRegistry,
+    seed: u64,
+    reporter: Option<Recipient<Report>>,
+) -> Vec<MatchReport> {
+    let span = tournament_span(config, seed);
+    async move {
+        let mut seeds = StdRng::seed_from_u64(seed);
+        let (entrants, pairs) = schedule(config);
+        if !config.remember_opponents || pairs.is_empty() {
+            return play_pairs(config, registry, &entrants, &pairs, &mut seeds, reporter).await;
+        }
+        let shared = Shared::new(config);
+        let mut reports = Vec::new();
+        for repetition in pairs.chunks(pairs.len() / config.repetitions) {
+            let played = play_shared(
+                config,
+                registry,
+                &entrants,
+                repetition,
+                &mut seeds,
+                reporter.clone(),
+                &shared,
+            )
+            .await;
+            reports.extend(played);
+        }
+        reports
+    }
+    .instrument(span)
+    .await
+}
+
+/// The span a round robin of `config` from `seed`, and its matches, are
+/// played in
+pub fn tournament_span(config: &SimConfig, seed: u64) -> Span {
+    info_span!("tournament", participants = config.participants.len(), seed)
+}
+
+/// Play what is left of `partial`'s round robin, handing each new report
+/// to `finished`, with its place in the schedule, as its match ends
+///
+/// Every match is played from the seeds it would have had in the whole
+/// tournament, so the pairings played before and after a break add up to
+/// the same round robin as one played without it. Reports come back in
+/// schedule order, the earlier matches' included. Matches sharing
+/// reputations or memories would play differently resumed, so the config
+/// shouldn't keep them.
+pub async fn resume(
+    partial: &PartialTournament,
+    registry: &StrategyRegistry,
+    finished: &dyn Fn(usize, &MatchReport),
+) -> Vec<MatchReport> {
+    let config = &partial.config;
+    let mut seeds = StdRng::seed_from_u64(partial.seed);
+    let (entrants, pairs) = schedule(config);
+    let matches: Vec<_> = pairs
+        .iter()
+        .enumerate()
+        .map(|(index, &(i, j))| {
+            let (blue, red) = (&entrants[i], &entrants[j]);
+            let seeds = MatchSeeds::draw(&mut seeds);
+            match partial.played.get(&index) {
+                Some(result) => Either::Left(future::ready(MatchReport {
+                    blue: blue.name.clone(),
+                    red: red.name.clone(),
+                    seeds,
+                    result: Ok(result.clone()),
+                })),
+                None => {
+                    let playing =
+                        start_match(config, registry, blue, red, seeds, None, &Shared::default());
+                    Either::Right(async move {
+                        let report = playing.await;
+                        finished(index, &report);
+                        report
+                    })
+                }
+            }
+        })
+        .collect();
+    join_all(matches).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Action;
+    use crate::prisoner::{Budget, StrategyFault};
+    use crate::strategy::{History, Strategy};
+    use serde_json::{json, Value};
+
+    #[actix_rt::test]
+    async fn asymmetric_pairings_are_played_from_both_sides() {
+        let config = SimConfig {
+            iterations: 4,
+            red_payoffs: Some(PayoffMatrix::new(3.0, 5.0, 1.0, 0.0)),
+            participants: vec![
+                ParticipantConfig::new("tft", "tit-for-tat"),
+                ParticipantConfig::new("alld", "always-defect"),
+            ],
+            ..SimConfig::default()
+        };
+        assert_eq!(schedule(&config).1, vec![(0, 1), (1, 0)]);
+
+        let reports = round_robin(&config, &StrategyRegistry::new(), 1, None).await;
+        let matches: Vec<MatchResult> = reports.into_iter().filter_map(|r| r.result.ok()).collect();
+        let red: Vec<Score> = matches.iter().map(|m| m.red.score).collect();
+        // a sucker and three punishments as red pay 3, a temptation and
+        // three punishments pay 8
+        assert_eq!(red, vec![8.0, 3.0]);
+        let standings = standings(&config, &matches);
+        let totals: Vec<(&str, Score)> = standings
+            .iter()
+            .map(|s| (s.name.as_str(), s.score))
+            .collect();
+        assert_eq!(totals, vec![("alld", 18.0), ("tft", 10.0)]);
+    }
+
+    #[actix_rt::test]
+    async fn games_of_more_actions_are_played_by_strategies_that_support_them() {
+        let registry = StrategyRegistry::new();
+        let mut config = SimConfig {
+            iterations: 30,
+            payoffs: Some("0,-1,1;1,0,-1;-1,1,0".parse().unwrap()),
+            participants: vec![
+                ParticipantConfig::new("tft", "tit-for-tat"),
+                ParticipantConfig::new("random", "random"),
+                ParticipantConfig::new("rock", "always-cooperate"),
+            ],
+            ..SimConfig::default()
+        };
+        config.validate(&registry).unwrap();
+
+        let reports = round_robin(&config, &registry, 3, None).await;
+        let matches: Vec<MatchResult> = reports.into_iter().filter_map(|r| r.result.ok()).collect();
+        assert_eq!(matches.len(), 3);
+        for result in &matches {
+            // rock, paper, scissors is zero-sum
+            assert_eq!(result.blue.score + result.red.score, 0.0);
+        }
+        let played: BTreeSet<usize> = matches
+            .iter()
+            .flat_map(|result| &result.transcript)
+            .flat_map(|round| [&round.blue, &round.red])
+            .map(|side| side.executed.index())
+            .collect();
+        assert_eq!(played, (0..3).collect());
+
+        config
+            .participants
+            .push(ParticipantConfig::new("pavlov", "pavlov"));
+        let refused = config.validate(&registry).unwrap_err().to_string();
+        assert!(
+            refused.contains("pavlov can't play a game of 3 actions"),
+            "{}",
+            refused
+        );
+    }
+
+    #[actix_rt::test]
+    async fn a_resumed_round_robin_plays_as_an_unbroken_one() {
+        let config = SimConfig {
+            iterations: 20,
+            noise: 0.1,
+            repetitions: 2,
+            participants: vec![
+                ParticipantConfig::new("gtft", "generous-tit-for-tat"),
+                ParticipantConfig::new("random", "random"),
+                ParticipantConfig::new("pavlov", "pavlov"),
+            ],
+            ..SimConfig::default()
+        };
+        let registry = StrategyRegistry::new();
+        let scores = |reports: &[MatchReport]| -> Vec<(Score, Score)> {
+            reports
+                .iter()
+                .map(|r| {
+                    let result = r.result.as_ref().expect("match played");
+                    (result.blue.score, result.red.score)
+                })
+                .collect()
+        };
+        let whole = round_robin(&config, &registry, 9, None).await;
+
+        // as if the first, third, and fourth matches were played before a break
+        let played = [0, 2, 3]
+            .iter()
+            .map(|&i| (i, whole[i].result.as_ref().unwrap().clone()))
+            .collect();
+        let partial = PartialTournament {
+            config: config.clone(),
+            seed: 9,
+            played,
+        };
+        let replayed = RefCell::new(Vec::new());
+        let resumed = resume(&partial, &registry, &|index, _| {
+            replayed.borrow_mut().push(index)
+        })
+        .await;
+        replayed.borrow_mut().sort();
+        assert_eq!(*replayed.borrow(), vec![1, 4, 5]);
+        assert_eq!(scores(&resumed), scores(&whole));
+    }
+
+    #[actix_rt::test]
+    async fn grudges_are_remembered_into_the_next_match_against_the_same_opponent() {
+        let mut config = SimConfig {
+            iterations: 3,
+            repetitions: 2,
+            participants: vec![
+                ParticipantConfig::new("grim", "grim"),
+                ParticipantConfig::new("stft", "suspicious(tit-for-tat)"),
+                ParticipantConfig::new("allc", "always-cooperate"),
+            ],
+            ..SimConfig::default()
+        };
+        let registry = StrategyRegistry::new();
+        let grim_moves = |reports: &[MatchReport]| -> Vec<String> {
+            reports
+                .iter()
+                .filter_map(|r| r.result.as_ref().ok())
+                .filter(|result| result.blue.name == "grim")
+                .map(|result| {
+                    result
+                        .transcript
+                        .iter()
+                        .map(|round| round.blue.executed.letter())
+                        .collect()
+                })
+                .collect()
+        };
+        let forgetful = round_robin(&config, &registry, 1, None).await;
+        assert_eq!(grim_moves(&forgetful), vec!["CDD", "CCC", "CDD", "CCC"]);
+
+        config.remember_opponents = true;
+        for _ in 0..2 {
+            // each tournament starts with no memories
+            let reports = round_robin(&config, &registry, 1, None).await;
+            assert_eq!(grim_moves(&reports), vec!["CDD", "CCC", "DDD", "CCC"]);
+            let record = TournamentRecord::new(&config, 1, reports);
+            let memories: Vec<(&str, &str, &Value)> = record
+                .memories
+                .iter()
+                .map(|m| (m.owner.as_str(), m.opponent.as_str(), &m.memory))
+                .collect();
+            assert_eq!(
+                memories,
+                vec![
+                    ("grim", "allc", &json!({ "triggered": false })),
+                    ("grim", "stft", &json!({ "triggered": true })),
+                ]
+            );
+        }
+
+        // a wrapped grim holds its grudge all the same
+        config.participants[0].strategy = "contrite(grim)".to_owned();
+        let reports = round_robin(&config, &registry, 1, None).await;
+        assert_eq!(grim_moves(&reports), vec!["CDD", "CCC", "DDD", "CCC"]);
+    }
+
+    /// Holds another kilobyte after every decision
+    #[derive(Default)]
+    struct Hoarder {
+        held: usize,
+    }
+
+    impl Strategy for Hoarder {
+        fn name(&self) -> &str {
+            "hoarder"
+        }
+
+        fn choose(&mut self, _history: &History) -> Action {
+            self.held += 1024;
+            Action::COOPERATE
+        }
+
+        fn memory_used(&self) -> Option<usize> {
+            Some(self.held)
+        }
+    }
+
+    #[actix_rt::test]
+    async fn strategies_over_their_memory_budget_can_be_disqualified() {
+        let mut registry = StrategyRegistry::new();
+        registry.register("hoarder", |_: &StrategyArgs| {
+            Ok(Box::new(Hoarder::default()))
+        });
+        let mut config = SimConfig {
+            iterations: 10,
+            participants: vec![
+                ParticipantConfig::new("tft", "tit-for-tat"),
+                ParticipantConfig::new("allc", "always-cooperate"),
+                ParticipantConfig::new("hoarder", "hoarder"),
+            ],
+            budget: Some(Budget {
+                memory_kb: Some(4),
+                on_exceed: BudgetAction::Disqualify,
+                ..Budget::default()
+            }),
+            ..SimConfig::default()
+        };
+        assert!(config.validate(&registry).is_ok());
+        let record =
+            TournamentRecord::new(&config, 1, round_robin(&config, &registry, 1, None).await);
+        assert_eq!(record.disqualified, vec!["hoarder"]);
+
+        // a script can't tell what it holds, wrapped or not
+        #[cfg(feature = "scripting")]
+        for strategy in [
+            "script:examples/scripts/tit-for-tat.rhai",
+            "generous(0.1, script:examples/scripts/tit-for-tat.rhai)",
+        ] {
+            let mut scripted = config.clone();
+            scripted
+                .participants
+                .push(ParticipantConfig::new("script", strategy));
+            assert!(scripted.validate(&registry).is_err());
+        }
+        let mut names: Vec<&str> = record.standings.iter().map(|s| s.name.as_str()).collect();
+        names.sort();
+        assert_eq!(names, vec!["allc", "tft"]);
+        let faults: Vec<&StrategyFault> = record.matches.iter().flat_map(|m| &m.faults).collect();
+        assert_eq!(faults.len(), 2);
+        assert!(faults
+            .iter()
+            .all(|f| f.kind == FaultKind::OverBudget && f.reason.contains("holds 5 KB")));
+
+        // forfeiting rounds instead plays on, and keeps the hoarder in
+        config.budget = Some(Budget {
+            memory_kb: Some(4),
+            on_exceed: BudgetAction::ForfeitRound,
+            ..Budget::default()
+        });
+        let record =
+            TournamentRecord::new(&config, 1, round_robin(&config, &registry, 1, None).await);
+        assert!(record.disqualified.is_empty());
+        assert_eq!(record.standings.len(), 3);
+        let hoarder = &record.matches[1].red;
+        assert_eq!((hoarder.name.as_str(), hoarder.over_budget), ("hoarder", 5));
+    }
+}
@@ -0,0 +1,55 @@
+//! What happened in each round of a match
+//!
+//! A transcript is kept the same way whether a match is refereed between
+//! actors or played by a [`LocalMatch`], so that [`MatchStats`] and the
+//! reports read either.
+//!
+//! [`LocalMatch`]: crate::local::LocalMatch
+//! [`MatchStats`]: crate::stats::MatchStats
+
+use serde::{Deserialize, Serialize};
+
+use crate::game::{Action, PlayerOutcome};
+
+/// One side of one round in a match transcript
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SideRecord {
+    /// What the strategy chose
+    pub chosen: Action,
+
+    /// What was scored, after any execution noise
+    pub executed: Action,
+
+    /// The opponent's action as it will be reported to this prisoner
+    pub perceived: Action,
+
+    /// The opponent's action was hidden from this prisoner, so it was told
+    /// nothing of it; `perceived` is what it would have been told
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub hidden: bool,
+
+    /// What the prisoner got, its value weighted by any discount
+    #[serde(flatten)]
+    pub outcome: PlayerOutcome,
+
+    /// The prisoner missed the decision timeout, so `chosen` was substituted
+    #[serde(default)]
+    pub timed_out: bool,
+
+    /// The prisoner was over its computation budget, so `chosen` was
+    /// substituted
+    #[serde(default)]
+    pub over_budget: bool,
+
+    /// What the prisoner signalled it would do, with cheap talk
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signal: Option<Action>,
+}
+
+/// One round in a match transcript
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RoundRecord {
+    pub round: usize,
+    pub blue: SideRecord,
+    pub red: SideRecord,
+}
@@ -0,0 +1,308 @@
+//! A live terminal dashboard for a running tournament
+//!
+//! The [`Dashboard`] actor takes over the terminal while it runs. It
+//! subscribes to the [`EventBus`](crate::events::EventBus) and follows each
+//! match through the [`GameEvent`]s its referee publishes,
+//! showing the latest actions and rolling cooperation rates of every
+//! match, and a leaderboard of the matches finished so far. Pressing `q`
+//! or Ctrl-C stops the run after the current round, as Ctrl-C does without
+//! the dashboard.
+
+use actix::prelude::*;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Cell, Paragraph, Row, Table};
+use ratatui::{DefaultTerminal, Frame};
+use std::collections::{BTreeMap, VecDeque};
+use std::time::Duration;
+
+use crate::events::GameEvent;
+use crate::game::{Action, Score};
+use crate::interrupt;
+
+/// How often the screen is redrawn and the keyboard checked
+const REFRESH: Duration = Duration::from_millis(100);
+
+/// Rounds shown per match, and over which cooperation is averaged
+const WINDOW: usize = 40;
+
+/// Close the dashboard and give the terminal back
+pub struct Close;
+
+impl Message for Close {
+    type Result = ();
+}
+
+/// The recent actions of one side of a live match
+#[derive(Default)]
+struct Side {
+    score: Score,
+    recent: VecDeque<Action>,
+}
+
+impl Side {
+    fn play(&mut self, action: Action, amount: Score) {
+        self.score += amount;
+        if self.recent.len() == WINDOW {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(action);
+    }
+
+    fn cooperation_rate(&self) -> f64 {
+        if self.recent.is_empty() {
+            return 0.0;
+        }
+        let cooperations = self
+            .recent
+            .iter()
+            .filter(|&&action| action == Action::COOPERATE)
+            .count();
+        cooperations as f64 / self.recent.len() as f64
+    }
+
+    fn actions(&self) -> Line<'static> {
+        self.recent
+            .iter()
+            .map(|&action| {
+                let colour = match action {
+                    Action::COOPERATE => Color::Green,
+                    Action::DEFECT => Color::Red,
+                    _ => Color::Gray,
+                };
+                Span::styled(action.letter().to_string(), Style::default().fg(colour))
+            })
+            .collect()
+    }
+}
+
+#[derive(Default)]
+struct LiveMatch {
+    rounds: usize,
+    blue: Side,
+    red: Side,
+    finished: bool,
+}
+
+/// One participant's totals over the finished matches
+#[derive(Default)]
+struct Total {
+    matches: usize,
+    rounds: usize,
+    score: Score,
+    cooperations: usize,
+}
+
+/// Shows matches as they are played, in place of the per-match reports
+pub struct Dashboard {
+    terminal: Option<DefaultTerminal>,
+    matches: BTreeMap<(String, String), LiveMatch>,
+    totals: BTreeMap<String, Total>,
+    dirty: bool,
+}
+
+impl Dashboard {
+    pub fn new() -> Dashboard {
+        Dashboard {
+            terminal: None,
+            matches: BTreeMap::new(),
+            totals: BTreeMap::new(),
+            dirty: true,
+        }
+    }
+
+    fn handle_keys(&mut self) {
+        while let Ok(true) = event::poll(Duration::ZERO) {
+            if let Ok(Event::Key(key)) = event::read() {
+                let ctrl_c =
+                    key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL);
+                if key.kind == KeyEventKind::Press && (key.code == KeyCode::Char('q') || ctrl_c) {
+                    interrupt::interrupt();
+                    self.dirty = true;
+                }
+            }
+        }
+    }
+
+    fn redraw(&mut self) {
+        if !self.dirty {
+            return;
+        }
+        self.dirty = false;
+        if let Some(mut terminal) = self.terminal.take() {
+            let _ = terminal.draw(|frame| self.render(frame));
+            self.terminal = Some(terminal);
+        }
+    }
+
+    fn render(&self, frame: &mut Frame) {
+        let [header, body] =
+            Layout::vertical([Constraint::Length(1), Constraint::Min(0)]).areas(frame.area());
+        let [matches, leaderboard] =
+            Layout::horizontal([Constraint::Percentage(65), Constraint::Percentage(35)])
+                .areas(body);
+
+        let finished = self.matches.values().filter(|m| m.finished).count();
+        let status = if interrupt::interrupted() {
+            "stopping after the current round".to_owned()
+        } else {
+            "q to stop".to_owned()
+        };
+        frame.render_widget(
+            Paragraph::new(format!(
+                "actoripd  {} of {} matches finished  {}",
+                finished,
+                self.matches.len(),
+                status
+            ))
+            .style(Style::default().add_modifier(Modifier::BOLD)),
+            header,
+        );
+
+        // live matches first, then the finished ones
+        let mut live: Vec<_> = self.matches.iter().collect();
+        live.sort_by_key(|(_, m)| m.finished);
+        let rows = live.into_iter().flat_map(|((blue, red), m)| {
+            let style = if m.finished {
+                Style::default().fg(Color::DarkGray)
+            } else {
+                Style::default()
+            };
+            let side = |name: &str, side: &Side, rounds: String| {
+                Row::new(vec![
+                    Cell::from(name.to_owned()),
+                    Cell::from(rounds),
+                    Cell::from(format!("{}", side.score)),
+                    Cell::from(format!("{:5.1}%", 100.0 * side.cooperation_rate())),
+                    Cell::from(side.actions()),
+                ])
+                .style(style)
+            };
+            vec![
+                side(blue, &m.blue, m.rounds.to_string()),
+                side(red, &m.red, String::new()).bottom_margin(1),
+            ]
+        });
+        let widths = [
+            Constraint::Length(16),
+            Constraint::Length(8),
+            Constraint::Length(8),
+            Constraint::Length(7),
+            Constraint::Min(WINDOW as u16),
+        ];
+        frame.render_widget(
+            Table::new(rows, widths)
+                .header(Row::new(vec!["player", "round", "score", "coop", "recent"]))
+                .block(Block::bordered().title(" matches ")),
+            matches,
+        );
+
+        let mut totals: Vec<_> = self.totals.iter().collect();
+        totals.sort_by(|a, b| b.1.score.total_cmp(&a.1.score).then_with(|| a.0.cmp(b.0)));
+        let rows = totals.into_iter().enumerate().map(|(rank, (name, total))| {
+            let rate = if total.rounds == 0 {
+                0.0
+            } else {
+                total.cooperations as f64 / total.rounds as f64
+            };
+            Row::new(vec![
+                (rank + 1).to_string(),
+                name.clone(),
+                total.matches.to_string(),
+                format!("{}", total.score),
+                format!("{:5.1}%", 100.0 * rate),
+            ])
+        });
+        let widths = [
+            Constraint::Length(4),
+            Constraint::Min(10),
+            Constraint::Length(7),
+            Constraint::Length(8),
+            Constraint::Length(7),
+        ];
+        frame.render_widget(
+            Table::new(rows, widths)
+                .header(Row::new(vec!["rank", "name", "played", "score", "coop"]))
+                .block(Block::bordered().title(" leaderboard ")),
+            leaderboard,
+        );
+    }
+}
+
+impl Default for Dashboard {
+    fn default() -> Dashboard {
+        Dashboard::new()
+    }
+}
+
+impl Actor for Dashboard {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Context<Self>) {
+        self.terminal = Some(ratatui::init());
+        ctx.run_interval(REFRESH, |act, _ctx| {
+            act.handle_keys();
+            act.redraw();
+        });
+    }
+
+    fn stopped(&mut self, _ctx: &mut Context<Self>) {
+        if self.terminal.take().is_some() {
+            ratatui::restore();
+        }
+    }
+}
+
+impl Handler<GameEvent> for Dashboard {
+    type Result = ();
+
+    fn handle(&mut self, event: GameEvent, _ctx: &mut Context<Self>) -> Self::Result {
+        self.dirty = true;
+        match event {
+            GameEvent::MatchStarted { blue, red } => {
+                self.matches.insert((blue, red), LiveMatch::default());
+            }
+            GameEvent::RoundPlayed { blue, red, record } => {
+                let live = self.matches.entry((blue, red)).or_default();
+                live.rounds = record.round + 1;
+                live.blue
+                    .play(record.blue.executed, record.blue.outcome.amount);
+                live.red
+                    .play(record.red.executed, record.red.outcome.amount);
+            }
+            GameEvent::MatchFinished(result) => {
+                let key = (result.blue.name.clone(), result.red.name.clone());
+                self.matches.entry(key).or_default().finished = true;
+                for player in &[&result.blue, &result.red] {
+                    let total = self.totals.entry(player.name.clone()).or_default();
+                    total.matches += 1;
+                    total.rounds += result.rounds;
+                    total.score += player.score;
+                    total.cooperations += player.cooperations;
+                }
+            }
+            GameEvent::GenerationComplete(_) => (),
+        }
+    }
+}
+
+impl Handler<Close> for Dashboard {
+    type Result = ();
+
+    fn handle(&mut self, _msg: Close, ctx: &mut Context<Self>) -> Self::Result {
+        // show the final state before handing the terminal back
+        self.dirty = true;
+        self.redraw();
+        ctx.stop();
+    }
+}
+
+impl Drop for Dashboard {
+    fn drop(&mut self) {
+        if self.terminal.take().is_some() {
+            ratatui::restore();
+        }
+    }
+}
@@ -0,0 +1,499 @@
+//! Searching a strategy's parameters for the best response to a pool
+//!
+//! A [`Tuning`] plays one strategy, at every point of a grid over its
+//! parameters, against each member of a fixed opponent pool, and scores
+//! each point by the points per round it averaged over the pool. The best
+//! point is the strategy's best response to that pool, as far as the grid
+//! can tell; the rest of the grid is the payoff landscape around it, which
+//! shows how sharp the optimum is and which parameters matter.
+//!
+//! Every point is played from the same seed, like a [`Sweep`], so that
+//! neighbouring points differ by their parameters rather than by luck.
+//! Each point's score comes with a confidence interval over its matches,
+//! which tells whether the best point is ahead of the next by more than the
+//! luck of the draw.
+//!
+//! A grid that is too fine to play in full can be searched by
+//! [`Tuning::evolve`] instead: a small population of points, anywhere in
+//! the box the grid spans, of which the better half survives each
+//! generation and the rest are replaced by mutants of the survivors.
+//!
+//! [`Sweep`]: crate::sweep::Sweep
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+use crate::config::{ConfigError, ParticipantConfig, SimConfig};
+use crate::interrupt;
+use crate::registry::{Parameters, StrategyArgs, StrategyRegistry};
+use crate::stats::Summary;
+use crate::sweep;
+use crate::tournament;
+
+/// The name the tuned strategy plays under
+pub const CANDIDATE: &str = "candidate";
+
+/// A named pool of opponents: `classic`, a handful of well-known simple
+/// strategies, or `axelrod`, the entries of Axelrod's first tournament
+pub fn opponent_pool(name: &str) -> Option<Vec<ParticipantConfig>> {
+    let classic = [
+        "tit-for-tat",
+        "tit-for-two-tats",
+        "generous-tit-for-tat",
+        "grim",
+        "pavlov",
+        "always-cooperate",
+        "always-defect",
+        "random",
+    ];
+    match name {
+        "classic" => Some(
+            classic
+                .iter()
+                .map(|strategy| ParticipantConfig::new(strategy, strategy))
+                .collect(),
+        ),
+        "axelrod" => Some(SimConfig::axelrod().participants),
+        _ => None,
+    }
+}
+
+/// One parameter and the values it takes
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GridAxis {
+    pub parameter: String,
+    pub values: Vec<f64>,
+}
+
+impl FromStr for GridAxis {
+    type Err = String;
+
+    /// `parameter=start..stop:step`, with both ends included, or
+    /// `parameter=value` to hold it fixed, e.g. `p_cc=0..1:0.1`
+    fn from_str(s: &str) -> Result<GridAxis, String> {
+        let (parameter, values) = s
+            .split_once('=')
+            .ok_or_else(|| format!("'{}': expected parameter=start..stop:step", s))?;
+        let number = |v: &str| {
+            v.trim()
+                .parse::<f64>()
+                .map_err(|e| format!("'{}': {}", s, e))
+        };
+        let values = match values.split_once("..") {
+            Some((start, rest)) => {
+                let (stop, step) = rest
+                    .split_once(':')
+                    .ok_or_else(|| format!("'{}': expected start..stop:step", s))?;
+                sweep::steps(number(start)?, number(stop)?, number(step)?).ok_or_else(|| {
+                    format!("'{}': expected start..stop:step with step above 0", s)
+                })?
+            }
+            None => vec![number(values)?],
+        };
+        Ok(GridAxis {
+            parameter: parameter.trim().to_owned(),
+            values,
+        })
+    }
+}
+
+/// A strategy, the grid over its parameters, and the pool to tune it
+/// against
+pub struct Tuning {
+    config: SimConfig,
+    strategy: String,
+    axes: Vec<GridAxis>,
+}
+
+/// How one point of the grid did
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TunePoint {
+    /// The value of each axis, in order
+    pub values: Vec<f64>,
+
+    /// Points per round over every match against the pool
+    pub score: f64,
+
+    /// Points per round against each opponent, in pool order
+    pub against: Vec<f64>,
+
+    /// The spread of the points per round of each match against the pool
+    #[serde(default)]
+    pub matches: Summary,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TuneRecord {
+    pub strategy: String,
+    pub seed: u64,
+    pub axes: Vec<GridAxis>,
+
+    /// The opponents' names, in pool order
+    pub opponents: Vec<String>,
+
+    /// Every combination of values, the last axis varying fastest, or
+    /// every point an evolutionary search played, in the order played;
+    /// fewer if the search was interrupted
+    pub points: Vec<TunePoint>,
+
+    /// The generations of an evolutionary search, if it was one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub generations: Option<usize>,
+
+    /// Where in `points` the best response is, the first of any tied
+    pub best: Option<usize>,
+}
+
+impl Tuning {
+    /// Tune `strategy` over `axes` against `pool`, with the payoffs, match
+    /// length, noise, and repetitions of `config`
+    ///
+    /// Every axis must be a parameter the strategy reports, and every point
+    /// a valid config.
+    pub fn new(
+        config: &SimConfig,
+        strategy: &str,
+        axes: Vec<GridAxis>,
+        pool: Vec<ParticipantConfig>,
+        registry: &StrategyRegistry,
+    ) -> Result<Tuning, ConfigError> {
+        if pool.is_empty() {
+            return Err(ConfigError::Invalid(
+                "the opponent pool is empty".to_owned(),
+            ));
+        }
+        let payoffs = config.payoffs();
+        let described = registry
+            .describe(
+                strategy,
+                &StrategyArgs {
+                    parameters: &Parameters::new(),
+                    payoffs: &payoffs,
+                    seed: 0,
+                },
+            )
+            .map_err(|e| ConfigError::Invalid(e.to_string()))?;
+        for axis in &axes {
+            if !described.parameters.contains_key(&axis.parameter) {
+                let known: Vec<&str> = described.parameters.keys().map(String::as_str).collect();
+                return Err(ConfigError::Invalid(if known.is_empty() {
+                    format!("{} has no parameters to tune", strategy)
+                } else {
+                    format!(
+                        "{} has no parameter '{}'; it has {}",
+                        strategy,
+                        axis.parameter,
+                        known.join(", ")
+                    )
+                }));
+            }
+        }
+        let mut config = config.clone();
+        config.self_play = false;
+        config.participants = std::iter::once(ParticipantConfig::new(CANDIDATE, strategy))
+            .chain(pool)
+            .collect();
+        let tuning = Tuning {
+            config,
+            strategy: strategy.to_owned(),
+            axes,
+        };
+        for point in tuning.points() {
+            tuning
+                .config_at(&point)
+                .validate(registry)
+                .map_err(|e| match e {
+                    ConfigError::Invalid(e) => {
+                        ConfigError::Invalid(format!("at {}: {}", tuning.describe(&point), e))
+                    }
+                    e => e,
+                })?;
+        }
+        Ok(tuning)
+    }
+
+    /// Every combination of the axes' values, the last axis varying fastest
+    pub fn points(&self) -> Vec<Vec<f64>> {
+        self.axes.iter().fold(vec![Vec::new()], |points, axis| {
+            points
+                .iter()
+                .flat_map(|point| {
+                    axis.values.iter().map(move |&value| {
+                        let mut point = point.clone();
+                        point.push(value);
+                        point
+                    })
+                })
+                .collect()
+        })
+    }
+
+    fn config_at(&self, point: &[f64]) -> SimConfig {
+        let mut config = self.config.clone();
+        for (axis, &value) in self.axes.iter().zip(point) {
+            config.participants[0]
+                .parameters
+                .insert(axis.parameter.clone(), value);
+        }
+        config
+    }
+
+    fn describe(&self, point: &[f64]) -> String {
+        self.axes
+            .iter()
+            .zip(point)
+            .map(|(axis, value)| format!("{}={}", axis.parameter, value))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// The pairings that play one point, the candidate against each
+    /// opponent in turn, seated as [`tournament::seatings`] has it
+    fn pairs(&self) -> Vec<(usize, usize)> {
+        let opponents = self.config.participants.len() - 1;
+        let pairs = (1..=opponents)
+            .cycle()
+            .take(opponents * self.config.repetitions)
+            .map(|j| (0, j))
+            .collect();
+        tournament::seatings(&self.config, pairs)
+    }
+
+    /// Play the candidate with `values` against the pool; `None` if the
+    /// matches were interrupted
+    async fn play(
+        &self,
+        registry: &StrategyRegistry,
+        seed: u64,
+        values: Vec<f64>,
+    ) -> Option<TunePoint> {
+        let opponents = self.config.participants.len() - 1;
+        let pairs = self.pairs();
+        let config = self.config_at(&values);
+        let mut seeds = StdRng::seed_from_u64(seed);
+        let reports = tournament::play_pairs(
+            &config,
+            registry,
+            &config.participants,
+            &pairs,
+            &mut seeds,
+            None,
+        )
+        .await;
+        if interrupt::interrupted() {
+            return None;
+        }
+        let per_round = |(score, rounds): (f64, usize)| score / rounds.max(1) as f64;
+        let mut totals = vec![(0.0, 0); opponents];
+        let mut each = Vec::new();
+        for (&(blue, red), report) in pairs.iter().zip(&reports) {
+            if let Ok(result) = &report.result {
+                let (j, score) = if blue == 0 {
+                    (red, result.blue.score)
+                } else {
+                    (blue, result.red.score)
+                };
+                totals[j - 1].0 += score;
+                totals[j - 1].1 += result.rounds;
+                each.push(per_round((score, result.rounds)));
+            }
+        }
+        let overall = totals
+            .iter()
+            .fold((0.0, 0), |(s, r), &(score, rounds)| (s + score, r + rounds));
+        Some(TunePoint {
+            values,
+            score: per_round(overall),
+            against: totals.into_iter().map(per_round).collect(),
+            matches: Summary::of(&each),
+        })
+    }
+
+    fn record(&self, seed: u64, points: Vec<TunePoint>, generations: Option<usize>) -> TuneRecord {
+        let best = (0..points.len()).reduce(|best, i| {
+            if points[i].score > points[best].score {
+                i
+            } else {
+                best
+            }
+        });
+        TuneRecord {
+            strategy: self.strategy.clone(),
+            seed,
+            axes: self.axes.clone(),
+            opponents: self.config.participants[1..]
+                .iter()
+                .map(|p| p.name.clone())
+                .collect(),
+            points,
+            generations,
+            best,
+        }
+    }
+
+    /// Play the candidate against the pool at every point, one point after
+    /// another
+    pub async fn run(&self, registry: &StrategyRegistry, seed: u64) -> TuneRecord {
+        let mut points = Vec::new();
+        for values in self.points() {
+            match self.play(registry, seed, values).await {
+                Some(point) => points.push(point),
+                None => break,
+            }
+        }
+        self.record(seed, points, None)
+    }
+
+    /// Search for the best response with a population of `population`
+    /// points over `generations` generations, instead of the whole grid
+    ///
+    /// The points may lie anywhere between each axis' lowest and highest
+    /// value. The first generation is drawn evenly from that box. After
+    /// each, the better half of the points survive, and each of the rest is
+    /// replaced by a survivor moved a tenth of the box or less along every
+    /// axis. Points are played from `seed`, as on the grid, and the search
+    /// draws them from it too, so the same seed replays the same search.
+    pub async fn evolve(
+        &self,
+        registry: &StrategyRegistry,
+        seed: u64,
+        generations: usize,
+        population: usize,
+    ) -> TuneRecord {
+        // each axis' range, and whether its values are all whole numbers
+        let bounds: Vec<(f64, f64, bool)> = self
+            .axes
+            .iter()
+            .map(|axis| {
+                let values = axis.values.iter().copied();
+                (
+                    values.clone().fold(f64::INFINITY, f64::min),
+                    values.clone().fold(f64::NEG_INFINITY, f64::max),
+                    values.clone().all(|value| value.fract() == 0.0),
+                )
+            })
+            .collect();
+        let draw = |near: Option<&[f64]>, rng: &mut StdRng| -> Vec<f64> {
+            bounds
+                .iter()
+                .enumerate()
+                .map(|(i, &(low, high, whole))| {
+                    let value = match near {
+                        _ if low == high => low,
+                        None => rng.gen_range(low, high),
+                        Some(parent) => {
+                            let reach = (high - low) / 10.0;
+                            (parent[i] + rng.gen_range(-reach, reach)).clamp(low, high)
+                        }
+                    };
+                    // short enough to read in the table and pass as a parameter
+                    if whole {
+                        value.round()
+                    } else {
+                        (value * 1000.0).round() / 1000.0
+                    }
+                })
+                .collect()
+        };
+        let mut rng = StdRng::seed_from_u64(seed);
+        let population = population.max(2);
+        let mut played: Vec<TunePoint> = Vec::new();
+        // where in `played` each member of the population is
+        let mut members: Vec<usize> = Vec::new();
+        let mut unplayed: Vec<Vec<f64>> = (0..population).map(|_| draw(None, &mut rng)).collect();
+        for _ in 0..generations {
+            for values in unplayed.drain(..) {
+                // a point the strategy refuses is left out of the generation
+                if self.config_at(&values).validate(registry).is_err() {
+                    continue;
+                }
+                match self.play(registry, seed, values).await {
+                    Some(point) => {
+                        members.push(played.len());
+                        played.push(point);
+                    }
+                    None => return self.record(seed, played, Some(generations)),
+                }
+            }
+            if members.is_empty() {
+                break;
+            }
+            members.sort_by(|&a, &b| played[b].score.total_cmp(&played[a].score));
+            members.truncate(population.div_ceil(2));
+            unplayed = (0..population - members.len())
+                .map(|i| {
+                    let parent = &played[members[i % members.len()]].values;
+                    draw(Some(parent), &mut rng)
+                })
+                .collect();
+        }
+        self.record(seed, played, Some(generations))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tuning(grid: &str) -> Tuning {
+        let config = SimConfig {
+            iterations: 20,
+            repetitions: 3,
+            ..SimConfig::default()
+        };
+        let pool = vec![
+            ParticipantConfig::new("alld", "always-defect"),
+            ParticipantConfig::new("tft", "tit-for-tat"),
+        ];
+        let axes = vec![grid.parse().unwrap()];
+        Tuning::new(
+            &config,
+            "generous-tit-for-tat",
+            axes,
+            pool,
+            &StrategyRegistry::new(),
+        )
+        .unwrap()
+    }
+
+    #[actix_rt::test]
+    async fn the_best_point_comes_with_its_confidence_interval() {
+        let record = tuning("generosity=0..1:0.25")
+            .run(&StrategyRegistry::new(), 1)
+            .await;
+        assert_eq!(record.points.len(), 5);
+        assert_eq!(record.generations, None);
+        // forgiving always defect only costs points
+        let best = &record.points[record.best.unwrap()];
+        assert_eq!(best.values, vec![0.0]);
+        let matches = &best.matches;
+        assert_eq!(matches.count, 2 * 3);
+        assert!((matches.mean - best.score).abs() < 1e-9);
+        assert!(matches.ci_low < best.score && best.score < matches.ci_high);
+    }
+
+    #[actix_rt::test]
+    async fn an_evolutionary_search_stays_in_the_box_and_replays() {
+        let registry = StrategyRegistry::new();
+        let tuning = tuning("generosity=0.2..0.6:0.2");
+        let record = tuning.evolve(&registry, 7, 4, 4).await;
+        assert_eq!(record.generations, Some(4));
+        // four points, then two mutants of the survivors each generation
+        assert_eq!(record.points.len(), 4 + 3 * 2);
+        for point in &record.points {
+            let value = point.values[0];
+            assert!((0.2..=0.6).contains(&value), "{}", value);
+            assert_eq!((value * 1000.0).round() / 1000.0, value);
+        }
+        let best = &record.points[record.best.unwrap()];
+        assert!(record.points.iter().all(|p| p.score <= best.score));
+
+        let again = tuning.evolve(&registry, 7, 4, 4).await;
+        let values = |record: &TuneRecord| -> Vec<Vec<f64>> {
+            record.points.iter().map(|p| p.values.clone()).collect()
+        };
+        assert_eq!(values(&again), values(&record));
+    }
+}
@@ -0,0 +1,207 @@
+//! Strategies compiled to WebAssembly, run in a sandbox
+//!
+//! A guest module has no imports and exports:
+//!
+//! - `choose(round: i32, own: i32, opponent: i32) -> i32`, given the round
+//!   about to be played, counting from 0, and what each side did last round.
+//!   Actions are encoded as [`COOPERATE`] and [`DEFECT`], or [`NONE`] before
+//!   the first round. It returns the action to play.
+//! - `init(seed: i64)`, optional, called once after the module is loaded.
+//! - `reset()`, optional, called between matches, when the host's strategy
+//!   is reset to play another.
+//!
+//! Each call runs on a fixed budget of fuel, and memory is capped, so a
+//! runaway guest forfeits instead of stalling the tournament. See
+//! `examples/wasm-guest` for a guest crate. Name a module in a config as
+//! `strategy = "wasm:path/to/strategy.wasm"`.
+
+use std::fs;
+use tracing::warn;
+use wasmi::{
+    Config, Engine, Linker, Memory, Module, Store, StoreLimits, StoreLimitsBuilder, TypedFunc,
+};
+
+use crate::game::Action;
+use crate::strategy::{History, Strategy};
+
+pub const COOPERATE: i32 = 0;
+pub const DEFECT: i32 = 1;
+
+/// Only seen, or worth answering, in games with a loner's payoff
+pub const ABSTAIN: i32 = 2;
+pub const NONE: i32 = -1;
+
+/// Fuel, roughly instructions, a guest may use per call
+const FUEL_PER_CALL: u64 = 10_000_000;
+
+/// Bytes of linear memory a guest may grow to
+const MAX_MEMORY: usize = 16 << 20;
+
+pub struct WasmStrategy {
+    name: String,
+    path: String,
+    store: Store<StoreLimits>,
+    choose: TypedFunc<(i32, i32, i32), i32>,
+    reset: Option<TypedFunc<(), ()>>,
+
+    /// The linear memory the guest exports as `memory`, if it does
+    memory: Option<Memory>,
+}
+
+impl WasmStrategy {
+    /// Load and instantiate the module at `path`, then call its `init`
+    pub fn load(path: &str, seed: u64) -> Result<WasmStrategy, String> {
+        let fail = |e: &dyn std::fmt::Display| format!("{}: {}", path, e);
+        let bytes = fs::read(path).map_err(|e| format!("unable to load {}: {}", path, e))?;
+
+        let mut config = Config::default();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config);
+        let module = Module::new(&engine, &bytes).map_err(|e| fail(&e))?;
+        let limits = StoreLimitsBuilder::new().memory_size(MAX_MEMORY).build();
+        let mut store = Store::new(&engine, limits);
+        store.limiter(|limits| limits);
+        let instance = Linker::<StoreLimits>::new(&engine)
+            .instantiate(&mut store, &module)
+            .and_then(|pre| pre.start(&mut store))
+            .map_err(|e| fail(&e))?;
+
+        let choose = instance
+            .get_typed_func::<(i32, i32, i32), i32>(&store, "choose")
+            .map_err(|e| format!("{} must export choose(i32, i32, i32) -> i32: {}", path, e))?;
+        let reset = instance.get_typed_func::<(), ()>(&store, "reset").ok();
+        let memory = instance.get_memory(&store, "memory");
+        if let Ok(init) = instance.get_typed_func::<i64, ()>(&store, "init") {
+            store.set_fuel(FUEL_PER_CALL).map_err(|e| fail(&e))?;
+            init.call(&mut store, seed as i64).map_err(|e| fail(&e))?;
+        }
+        Ok(WasmStrategy {
+            name: format!("wasm:{}", path),
+            path: path.to_owned(),
+            store,
+            choose,
+            reset,
+            memory,
+        })
+    }
+}
+
+fn encode(action: Option<Action>) -> i32 {
+    match action {
+        Some(action) => action.index() as i32,
+        None => NONE,
+    }
+}
+
+impl Strategy for WasmStrategy {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Panics if the guest traps, runs out of fuel, or returns an unknown
+    /// action; the prisoner turns that into a forfeit
+    fn choose(&mut self, history: &History) -> Action {
+        let round = history.round();
+        let last = history.last();
+        self.store
+            .set_fuel(FUEL_PER_CALL)
+            .expect("fuel metering is enabled");
+        let args = (
+            round as i32,
+            encode(last.map(|r| r.own)),
+            encode(last.map(|r| r.opponent)),
+        );
+        match self.choose.call(&mut self.store, args) {
+            Ok(COOPERATE) => Action::COOPERATE,
+            Ok(DEFECT) => Action::DEFECT,
+            Ok(ABSTAIN) => Action::ABSTAIN,
+            Ok(other) => panic!("{}: choose returned unknown action {}", self.path, other),
+            Err(e) => panic!("{}: choose: {}", self.path, e),
+        }
+    }
+
+    /// The guest keeps its own memory of the match
+    fn memory_depth(&self) -> Option<usize> {
+        Some(1)
+    }
+
+    /// The size of the guest's exported `memory`
+    fn memory_used(&self) -> Option<usize> {
+        self.memory.map(|memory| memory.data_size(&self.store))
+    }
+
+    /// A guest whose `reset` traps plays its next match remembering this one
+    fn reset(&mut self) {
+        let Some(reset) = &self.reset else {
+            return;
+        };
+        self.store
+            .set_fuel(FUEL_PER_CALL)
+            .expect("fuel metering is enabled");
+        if let Err(e) = reset.call(&mut self.store, ()) {
+            warn!(path = %self.path, error = %e, "guest reset failed");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A guest that cooperates once after each `reset`, then defects
+    const ONCE_PER_RESET: &[u8] = &[
+        0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, // header
+        0x01, 0x0b, 0x02, 0x60, 0x03, 0x7f, 0x7f, 0x7f, 0x01, 0x7f, 0x60, 0x00, 0x00, // types
+        0x03, 0x03, 0x02, 0x00, 0x01, // functions
+        0x06, 0x06, 0x01, 0x7f, 0x01, 0x41, 0x00, 0x0b, // mut i32 global, 0
+        0x07, 0x12, 0x02, // exports
+        0x06, b'c', b'h', b'o', b'o', b's', b'e', 0x00, 0x00, //
+        0x05, b'r', b'e', b's', b'e', b't', 0x00, 0x01, //
+        0x0a, 0x11, 0x02, // code
+        0x08, 0x00, 0x23, 0x00, 0x41, 0x01, 0x24, 0x00, 0x0b, // return global, set it to 1
+        0x06, 0x00, 0x41, 0x00, 0x24, 0x00, 0x0b, // set global to 0
+    ];
+
+    /// A guest exporting one page of memory that grows by a page each time
+    /// it cooperates
+    const HOARDER: &[u8] = &[
+        0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, // header
+        0x01, 0x08, 0x01, 0x60, 0x03, 0x7f, 0x7f, 0x7f, 0x01, 0x7f, // types
+        0x03, 0x02, 0x01, 0x00, // functions
+        0x05, 0x03, 0x01, 0x00, 0x01, // one page of memory
+        0x07, 0x13, 0x02, // exports
+        0x06, b'c', b'h', b'o', b'o', b's', b'e', 0x00, 0x00, //
+        0x06, b'm', b'e', b'm', b'o', b'r', b'y', 0x02, 0x00, //
+        0x0a, 0x0b, 0x01, // code
+        0x09, 0x00, 0x41, 0x01, 0x40, 0x00, 0x1a, 0x41, 0x00, 0x0b, // grow a page, return 0
+    ];
+
+    fn load(name: &str, bytes: &[u8]) -> WasmStrategy {
+        let path =
+            std::env::temp_dir().join(format!("actoripd-{}-{}.wasm", name, std::process::id()));
+        fs::write(&path, bytes).unwrap();
+        let strategy = WasmStrategy::load(path.to_str().unwrap(), 0).unwrap();
+        fs::remove_file(&path).unwrap();
+        strategy
+    }
+
+    #[test]
+    fn the_memory_a_guest_holds_is_measured() {
+        let mut strategy = load("hoarder", HOARDER);
+        assert_eq!(strategy.memory_used(), Some(64 << 10));
+        assert_eq!(strategy.choose(&History::new()), Action::COOPERATE);
+        assert_eq!(strategy.memory_used(), Some(128 << 10));
+        assert_eq!(load("once", ONCE_PER_RESET).memory_used(), None);
+    }
+
+    #[test]
+    fn resetting_the_strategy_resets_the_guest() {
+        let mut strategy = load("once-per-reset", ONCE_PER_RESET);
+
+        let history = History::new();
+        assert_eq!(strategy.choose(&history), Action::COOPERATE);
+        assert_eq!(strategy.choose(&history), Action::DEFECT);
+        strategy.reset();
+        assert_eq!(strategy.choose(&history), Action::COOPERATE);
+    }
+}
@@ -0,0 +1,282 @@
+//! Webhooks called when a long run finishes
+//!
+//! `--webhook URL`, given once per URL, posts a JSON document to each URL
+//! as a tournament, sweep, or evolution ends, so that a long job can post
+//! to a chat channel or start whatever comes after it:
+//!
+//! ```json
+//! {
+//!   "event": "tournament-finished",
+//!   "text": "tournament finished: 10 matches, tft first with 2298",
+//!   "content": "tournament finished: 10 matches, tft first with 2298",
+//!   "manifest": {"program": "actoripd", "version": "0.1.0", ...},
+//!   "standings": [...],
+//!   "schema_version": 2
+//! }
+//! ```
+//!
+//! - `event`: `tournament-finished`, `sweep-finished`, or
+//!   `evolution-finished`.
+//! - `text` and `content`: a line saying how the run ended, under the names
+//!   Slack and Discord show.
+//! - `manifest`: what was run, by which version of actoripd, with which
+//!   config and seed, when it finished, and whether it was interrupted.
+//! - `standings`: a tournament's standings, as `--format json` has them;
+//!   each point of a sweep with its standings; or the headcounts an
+//!   evolution ended with, by island or population if it had several.
+//!
+//! A webhook that can't be reached, or answers with an error, is logged
+//! and otherwise ignored, so it never fails the run it reports.
+
+use serde::Serialize;
+use serde_json::Value;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{info, warn};
+
+use crate::config::SimConfig;
+use crate::evolution::{CoevolutionRecord, EvolutionRecord, IslandRecord};
+use crate::interrupt;
+use crate::schema;
+use crate::sweep::SweepRecord;
+use crate::tournament::TournamentRecord;
+
+/// How long a webhook has to answer
+pub const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A run that has finished
+#[derive(Debug, Clone, Copy)]
+pub enum Finished<'a> {
+    Tournament(&'a TournamentRecord),
+    Sweep(&'a SweepRecord),
+    Evolution(&'a EvolutionRecord),
+    Islands(&'a IslandRecord),
+    Coevolution(&'a CoevolutionRecord),
+}
+
+/// What was run, and how to run it again
+#[derive(Debug, Clone, Serialize)]
+pub struct Manifest<'a> {
+    pub program: &'static str,
+    pub version: &'static str,
+
+    /// The subcommand that ran: `run`, `sweep`, or `evolve`
+    pub command: &'static str,
+    pub config: &'a SimConfig,
+    pub seed: u64,
+
+    /// Seconds since the Unix epoch
+    pub finished: u64,
+    pub interrupted: bool,
+}
+
+#[derive(Serialize)]
+struct Notification<'a> {
+    event: &'static str,
+    text: &'a str,
+    content: &'a str,
+    manifest: Manifest<'a>,
+    standings: Value,
+}
+
+fn json<T: Serialize>(value: &T) -> Value {
+    serde_json::to_value(value).expect("serializable standings")
+}
+
+impl Finished<'_> {
+    fn command(&self) -> &'static str {
+        match self {
+            Finished::Tournament(_) => "run",
+            Finished::Sweep(_) => "sweep",
+            _ => "evolve",
+        }
+    }
+
+    fn event(&self) -> &'static str {
+        match self {
+            Finished::Tournament(_) => "tournament-finished",
+            Finished::Sweep(_) => "sweep-finished",
+            _ => "evolution-finished",
+        }
+    }
+
+    fn config(&self) -> (&SimConfig, u64) {
+        match self {
+            Finished::Tournament(record) => (&record.config, record.seed),
+            Finished::Sweep(record) => (&record.config, record.seed),
+            Finished::Evolution(record) => (&record.config, record.seed),
+            Finished::Islands(record) => (&record.config, record.seed),
+            Finished::Coevolution(record) => (&record.config, record.seed),
+        }
+    }
+
+    /// A line saying how the run ended
+    pub fn summary(&self) -> String {
+        let generations = |n: usize| format!("evolution finished after {} generations", n);
+        match self {
+            Finished::Tournament(record) => {
+                let mut summary = format!("tournament finished: {} matches", record.matches.len());
+                if let Some(first) = record.standings.first() {
+                    summary += &format!(", {} first with {}", first.name, first.score);
+                }
+                summary
+            }
+            Finished::Sweep(record) => {
+                let axes: Vec<String> = record
+                    .axes
+                    .iter()
+                    .map(|axis| axis.setting.to_string())
+                    .collect();
+                format!(
+                    "sweep finished: {} points over {}",
+                    record.points.len(),
+                    axes.join(", ")
+                )
+            }
+            Finished::Evolution(record) => {
+                let mut summary = generations(record.generations.len());
+                let most = record.survivors.iter().max_by_key(|&(_, &count)| count);
+                if let Some((name, count)) = most {
+                    summary += &format!(", {} holds {} of {}", name, count, record.population);
+                }
+                summary
+            }
+            Finished::Islands(record) => generations(record.generations.len()),
+            Finished::Coevolution(record) => generations(record.generations.len()),
+        }
+    }
+
+    fn standings(&self) -> Value {
+        match self {
+            Finished::Tournament(record) => json(&record.standings),
+            Finished::Sweep(record) => json(&record.points),
+            Finished::Evolution(record) => json(&record.survivors),
+            Finished::Islands(record) => json(&record.survivors),
+            Finished::Coevolution(record) => {
+                let populations = record.labels.iter().zip(&record.survivors);
+                json(&populations.collect::<std::collections::BTreeMap<_, _>>())
+            }
+        }
+    }
+
+    /// The document posted to each webhook
+    pub fn payload(&self) -> Value {
+        let (config, seed) = self.config();
+        let finished = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs());
+        let text = self.summary();
+        schema::stamp(&Notification {
+            event: self.event(),
+            text: &text,
+            content: &text,
+            manifest: Manifest {
+                program: env!("CARGO_PKG_NAME"),
+                version: env!("CARGO_PKG_VERSION"),
+                command: self.command(),
+                config,
+                seed,
+                finished,
+                interrupted: interrupt::interrupted(),
+            },
+            standings: self.standings(),
+        })
+    }
+}
+
+/// Post `finished` to every one of `urls`, in turn
+pub fn notify(urls: &[String], finished: Finished) {
+    if urls.is_empty() {
+        return;
+    }
+    let body = finished.payload().to_string();
+    let agent = ureq::AgentBuilder::new().timeout(TIMEOUT).build();
+    for url in urls {
+        let posted = agent
+            .post(url)
+            .set("Content-Type", "application/json")
+            .send_string(&body);
+        match posted {
+            Ok(response) => info!(%url, status = response.status(), "webhook called"),
+            Err(e) => warn!(%url, error = %e, "unable to call webhook"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ParticipantConfig;
+    use crate::registry::StrategyRegistry;
+    use crate::tournament;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+
+    async fn record() -> TournamentRecord {
+        let config = SimConfig {
+            iterations: 5,
+            participants: vec![
+                ParticipantConfig::new("alld", "always-defect"),
+                ParticipantConfig::new("allc", "always-cooperate"),
+                ParticipantConfig::new("tft", "tit-for-tat"),
+            ],
+            ..SimConfig::default()
+        };
+        let registry = StrategyRegistry::new();
+        let reports = tournament::round_robin(&config, &registry, 7, None).await;
+        TournamentRecord::new(&config, 7, reports)
+    }
+
+    #[actix_rt::test]
+    async fn payloads_hold_a_manifest_and_the_standings() {
+        let record = record().await;
+        let payload = Finished::Tournament(&record).payload();
+        assert_eq!(payload["event"], "tournament-finished");
+        assert_eq!(
+            payload["text"],
+            "tournament finished: 3 matches, alld first with 32"
+        );
+        assert_eq!(payload["content"], payload["text"]);
+        assert_eq!(payload["manifest"]["command"], "run");
+        assert_eq!(payload["manifest"]["seed"], 7);
+        assert_eq!(payload["manifest"]["config"]["iterations"], 5);
+        assert_eq!(payload["standings"][1]["name"], "tft");
+        assert_eq!(payload[schema::VERSION_FIELD], schema::SCHEMA_VERSION);
+    }
+
+    #[actix_rt::test]
+    async fn each_webhook_is_posted_the_payload() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let received = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut length = 0;
+            let mut request = String::new();
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if let Some(value) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+                    length = value.trim().parse().unwrap();
+                }
+                if line == "\r\n" {
+                    break;
+                }
+                request += &line;
+            }
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+            reader
+                .get_mut()
+                .write_all(b"HTTP/1.1 204 No Content\r\nContent-Length: 0\r\n\r\n")
+                .unwrap();
+            (request, String::from_utf8(body).unwrap())
+        });
+
+        let record = record().await;
+        notify(&[url], Finished::Tournament(&record));
+        let (request, body) = received.join().unwrap();
+        assert!(request.starts_with("POST /hook HTTP/1.1\r\n"));
+        let payload: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(payload["standings"][0]["name"], "alld");
+    }
+}
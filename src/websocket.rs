@@ -0,0 +1,539 @@
+//! A WebSocket feed of game events for spectators, and seats for players
+//!
+//! [`serve`] starts an HTTP server with these routes:
+//!
+//! - `/events`, a WebSocket that streams every [`GameEvent`] published to
+//!   the bus as a JSON text message, and `/`, a page that connects to it and
+//!   draws each match as it is played.
+//! - `/spectate`, a read-only WebSocket for showing a room the run, with
+//!   every participant called by number, and `/projector`, the page drawing
+//!   it. It streams the `match-started`, `round-played`, and
+//!   `match-finished` events, with `player 1` and so on for names and
+//!   nothing of anyone's strategy, and a `standings` event after every
+//!   match: `{"event":"standings","standings":[{"name":"player 2",...}]}`.
+//!   A spectator is sent the standings so far as it connects.
+//! - `/play`, the WebSocket a [seat](crate::seats)'s player answers on,
+//!   taking the seat's token as `?token=...`, and `/player`, the page
+//!   asking them for their moves. A token no seat has is refused with 401.
+//!
+//! Every connection subscribes for itself, so any number of spectators can
+//! watch at once. Spectators only see events published after they connect.
+
+use actix::prelude::*;
+use actix_web::dev::Server;
+use actix_web::{web, App, Error, HttpRequest, HttpResponse, HttpServer};
+use actix_web_actors::ws;
+use futures::channel::oneshot;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::io;
+use std::net::ToSocketAddrs;
+use std::sync::{Arc, Mutex};
+use tracing::{info, warn};
+
+use crate::config::SimConfig;
+use crate::events::{EventBus, GameEvent, Subscribe};
+use crate::game::Action;
+use crate::referee::MatchResult;
+use crate::seats::{self, Question};
+use crate::tournament;
+
+/// The page served at `/` and `/projector`
+const SPECTATOR_PAGE: &str = include_str!("spectator.html");
+
+/// The page served at `/player`
+const PLAYER_PAGE: &str = include_str!("player.html");
+
+/// Told when the first spectator connects
+type Arrival = Arc<Mutex<Option<oneshot::Sender<()>>>>;
+
+fn arrive(arrival: &Arrival) {
+    if let Some(arrival) = arrival.lock().expect("unpoisoned arrival").take() {
+        let _ = arrival.send(());
+    }
+}
+
+/// Answer what a listening connection is sent, apart from text
+fn keep_alive<A>(msg: Result<ws::Message, ws::ProtocolError>, ctx: &mut ws::WebsocketContext<A>)
+where
+    A: Actor<Context = ws::WebsocketContext<A>>,
+{
+    match msg {
+        Ok(ws::Message::Ping(bytes)) => ctx.pong(&bytes),
+        Ok(ws::Message::Close(reason)) => {
+            ctx.close(reason);
+            ctx.stop();
+        }
+        Err(_) => ctx.stop(),
+        _ => (),
+    }
+}
+
+/// One spectator's connection, forwarding events as they are published
+struct Spectator {
+    bus: Addr<EventBus>,
+    arrival: Arrival,
+}
+
+impl Actor for Spectator {
+    type Context = ws::WebsocketContext<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        self.bus.do_send(Subscribe(ctx.address().recipient()));
+        arrive(&self.arrival);
+    }
+}
+
+impl Handler<GameEvent> for Spectator {
+    type Result = ();
+
+    fn handle(&mut self, event: GameEvent, ctx: &mut Self::Context) -> Self::Result {
+        match serde_json::to_string(&event) {
+            Ok(json) => ctx.text(json),
+            Err(e) => warn!(error = %e, "unable to serialize event"),
+        }
+    }
+}
+
+impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for Spectator {
+    fn handle(&mut self, msg: Result<ws::Message, ws::ProtocolError>, ctx: &mut Self::Context) {
+        // spectators only listen, apart from keeping the connection alive
+        keep_alive(msg, ctx);
+    }
+}
+
+/// A line shown to anonymous spectators
+struct Screen(String);
+
+impl Message for Screen {
+    type Result = ();
+}
+
+/// Show `screen` everything the projector shows from now on
+struct Watch(Recipient<Screen>);
+
+impl Message for Watch {
+    type Result = ();
+}
+
+/// Turns the events on the bus into what a room is shown, with everyone
+/// called by number
+struct Projector {
+    config: SimConfig,
+
+    /// Each participant's number, as `player 1`, in the order they came
+    aliases: BTreeMap<String, String>,
+    played: Vec<MatchResult>,
+    screens: Vec<Recipient<Screen>>,
+}
+
+impl Projector {
+    fn new(config: &SimConfig) -> Projector {
+        let mut projector = Projector {
+            config: config.clone(),
+            aliases: BTreeMap::new(),
+            played: Vec::new(),
+            screens: Vec::new(),
+        };
+        for participant in &config.participants {
+            projector.alias(&participant.name);
+        }
+        projector
+    }
+
+    fn alias(&mut self, name: &str) -> String {
+        let next = self.aliases.len() + 1;
+        self.aliases
+            .entry(name.to_owned())
+            .or_insert_with(|| format!("player {}", next))
+            .clone()
+    }
+
+    /// The standings so far, by number
+    fn standings(&mut self) -> Value {
+        let standings = tournament::standings(&self.config, &self.played);
+        let standings: Vec<Value> = standings
+            .iter()
+            .map(|standing| {
+                json!({
+                    "name": self.alias(&standing.name),
+                    "matches": standing.matches,
+                    "rounds": standing.rounds,
+                    "score": standing.score,
+                    "cooperation_rate": standing.cooperation_rate,
+                })
+            })
+            .collect();
+        json!({ "event": "standings", "standings": standings })
+    }
+
+    /// What spectators are shown of `event`, without anyone's name
+    fn project(&mut self, event: GameEvent) -> Vec<Value> {
+        match event {
+            GameEvent::MatchStarted { blue, red } => vec![json!({
+                "event": "match-started",
+                "blue": self.alias(&blue),
+                "red": self.alias(&red),
+            })],
+            GameEvent::RoundPlayed { blue, red, record } => vec![json!({
+                "event": "round-played",
+                "blue": self.alias(&blue),
+                "red": self.alias(&red),
+                "record": record,
+            })],
+            GameEvent::MatchFinished(result) => {
+                let finished = json!({
+                    "event": "match-finished",
+                    "blue": { "name": self.alias(&result.blue.name), "score": result.blue.score },
+                    "red": { "name": self.alias(&result.red.name), "score": result.red.score },
+                    "rounds": result.rounds,
+                });
+                self.played.push(*result);
+                vec![finished, self.standings()]
+            }
+            // populations are named by strategy
+            GameEvent::GenerationComplete(_) => Vec::new(),
+        }
+    }
+}
+
+impl Actor for Projector {
+    type Context = Context<Self>;
+}
+
+impl Handler<GameEvent> for Projector {
+    type Result = ();
+
+    fn handle(&mut self, event: GameEvent, _ctx: &mut Context<Self>) -> Self::Result {
+        for line in self.project(event) {
+            let line = line.to_string();
+            self.screens
+                .retain(|screen| screen.do_send(Screen(line.clone())).is_ok());
+        }
+    }
+}
+
+impl Handler<Watch> for Projector {
+    type Result = ();
+
+    fn handle(&mut self, msg: Watch, _ctx: &mut Context<Self>) -> Self::Result {
+        if msg.0.do_send(Screen(self.standings().to_string())).is_ok() {
+            self.screens.push(msg.0);
+        }
+    }
+}
+
+/// One anonymous spectator's connection
+struct Viewer {
+    projector: Addr<Projector>,
+    arrival: Arrival,
+}
+
+impl Actor for Viewer {
+    type Context = ws::WebsocketContext<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        self.projector.do_send(Watch(ctx.address().recipient()));
+        arrive(&self.arrival);
+    }
+}
+
+impl Handler<Screen> for Viewer {
+    type Result = ();
+
+    fn handle(&mut self, screen: Screen, ctx: &mut Self::Context) -> Self::Result {
+        ctx.text(screen.0);
+    }
+}
+
+impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for Viewer {
+    fn handle(&mut self, msg: Result<ws::Message, ws::ProtocolError>, ctx: &mut Self::Context) {
+        keep_alive(msg, ctx);
+    }
+}
+
+/// What a seat's player is sent
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "kebab-case")]
+enum ToPlayer<'a> {
+    Welcome { seat: &'a str },
+    Question(&'a Question),
+    Refused { reason: String },
+}
+
+/// A player's move, answering the question `id`
+#[derive(Deserialize)]
+struct Move {
+    id: u64,
+    action: char,
+}
+
+/// One player's connection to their seat
+struct PlayerConnection {
+    token: String,
+
+    /// The seat, and which connection to it this is, once seated
+    seat: Option<(String, u64)>,
+}
+
+impl PlayerConnection {
+    fn send(&self, message: &ToPlayer, ctx: &mut ws::WebsocketContext<Self>) {
+        ctx.text(serde_json::to_string(message).expect("serializable message"));
+    }
+
+    fn play(&self, text: &str) -> Result<(), String> {
+        let seat = match &self.seat {
+            Some((seat, _)) => seat,
+            None => return Err("not seated".to_owned()),
+        };
+        let played: Move = serde_json::from_str(text).map_err(|e| e.to_string())?;
+        let action = Action::from_letter(played.action)
+            .ok_or_else(|| format!("unknown action '{}'", played.action))?;
+        if seats::answer(seat, played.id, action) {
+            Ok(())
+        } else {
+            Err(format!(
+                "question {} isn't waiting for an answer",
+                played.id
+            ))
+        }
+    }
+}
+
+impl Actor for PlayerConnection {
+    type Context = ws::WebsocketContext<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        match seats::sit(&self.token, ctx.address().recipient()) {
+            Some((seat, sitting)) => {
+                info!(%seat, "player seated");
+                self.send(&ToPlayer::Welcome { seat: &seat }, ctx);
+                self.seat = Some((seat, sitting));
+            }
+            None => {
+                let reason = "the seat has been opened afresh".to_owned();
+                self.send(&ToPlayer::Refused { reason }, ctx);
+                ctx.stop();
+            }
+        }
+    }
+
+    fn stopped(&mut self, _ctx: &mut Self::Context) {
+        if let Some((seat, sitting)) = &self.seat {
+            seats::leave(seat, *sitting);
+        }
+    }
+}
+
+impl Handler<Question> for PlayerConnection {
+    type Result = ();
+
+    fn handle(&mut self, question: Question, ctx: &mut Self::Context) -> Self::Result {
+        self.send(&ToPlayer::Question(&question), ctx);
+    }
+}
+
+impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for PlayerConnection {
+    fn handle(&mut self, msg: Result<ws::Message, ws::ProtocolError>, ctx: &mut Self::Context) {
+        match msg {
+            Ok(ws::Message::Text(text)) => {
+                if let Err(reason) = self.play(&text) {
+                    self.send(&ToPlayer::Refused { reason }, ctx);
+                }
+            }
+            msg => keep_alive(msg, ctx),
+        }
+    }
+}
+
+async fn events(
+    req: HttpRequest,
+    stream: web::Payload,
+    bus: web::Data<Addr<EventBus>>,
+    arrival: web::Data<Arrival>,
+) -> Result<HttpResponse, Error> {
+    let spectator = Spectator {
+        bus: bus.get_ref().clone(),
+        arrival: arrival.get_ref().clone(),
+    };
+    ws::start(spectator, &req, stream)
+}
+
+async fn spectate(
+    req: HttpRequest,
+    stream: web::Payload,
+    projector: web::Data<Addr<Projector>>,
+    arrival: web::Data<Arrival>,
+) -> Result<HttpResponse, Error> {
+    let viewer = Viewer {
+        projector: projector.get_ref().clone(),
+        arrival: arrival.get_ref().clone(),
+    };
+    ws::start(viewer, &req, stream)
+}
+
+#[derive(Deserialize)]
+struct Ticket {
+    token: String,
+}
+
+async fn play(
+    req: HttpRequest,
+    stream: web::Payload,
+    ticket: web::Query<Ticket>,
+) -> Result<HttpResponse, Error> {
+    if seats::find(&ticket.token).is_none() {
+        return Ok(HttpResponse::Unauthorized().body("no seat has this token"));
+    }
+    let player = PlayerConnection {
+        token: ticket.into_inner().token,
+        seat: None,
+    };
+    ws::start(player, &req, stream)
+}
+
+fn html(page: &'static str) -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(page)
+}
+
+async fn page() -> HttpResponse {
+    html(SPECTATOR_PAGE)
+}
+
+async fn player_page() -> HttpResponse {
+    html(PLAYER_PAGE)
+}
+
+/// Serve the events published to `bus` at `address`, e.g. `0.0.0.0:9000`,
+/// and the seats of `config`'s players
+///
+/// Opens a seat for every `player:` participant of `config` and logs the
+/// link to it. Returns the running server, and a receiver that resolves
+/// when the first spectator connects. Must be called from inside a running
+/// `System`. The server leaves Ctrl-C to [`crate::interrupt`]; stop it with
+/// [`Server::stop`] once the run is over.
+pub fn serve<A: ToSocketAddrs>(
+    address: A,
+    bus: Addr<EventBus>,
+    config: &SimConfig,
+) -> io::Result<(Server, oneshot::Receiver<()>)> {
+    let (tx, rx) = oneshot::channel();
+    let arrival: Arrival = Arc::new(Mutex::new(Some(tx)));
+    let projector = Projector::new(config).start();
+    bus.do_send(Subscribe(projector.clone().recipient()));
+    let server = HttpServer::new(move || {
+        App::new()
+            .data(bus.clone())
+            .data(projector.clone())
+            .data(arrival.clone())
+            .route("/", web::get().to(page))
+            .route("/events", web::get().to(events))
+            .route("/projector", web::get().to(page))
+            .route("/spectate", web::get().to(spectate))
+            .route("/player", web::get().to(player_page))
+            .route("/play", web::get().to(play))
+    })
+    .workers(1)
+    .disable_signals()
+    // lets spectators take the last events before they are disconnected
+    .shutdown_timeout(1)
+    .bind(address)?;
+    for address in server.addrs() {
+        info!(%address, "serving spectators");
+    }
+    let players = config
+        .participants
+        .iter()
+        .filter_map(|participant| seats::seat_of(&participant.strategy));
+    for (seat, token) in seats::open(players) {
+        for address in server.addrs() {
+            info!(%seat, link = %format!("http://{}/player?token={}", address, token), "seat opened");
+        }
+    }
+    Ok((server.run(), rx))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ParticipantConfig;
+    use crate::registry::StrategyRegistry;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpStream;
+
+    fn config() -> SimConfig {
+        SimConfig {
+            iterations: 3,
+            participants: vec![
+                ParticipantConfig::new("alice", "always-defect"),
+                ParticipantConfig::new("bob", "tit-for-tat"),
+            ],
+            ..SimConfig::default()
+        }
+    }
+
+    #[actix_rt::test]
+    async fn spectators_see_numbers_not_names() {
+        let config = config();
+        let registry = StrategyRegistry::new();
+        let reports = tournament::round_robin(&config, &registry, 1, None).await;
+        let result = reports.into_iter().next().unwrap().result.unwrap();
+
+        let mut projector = Projector::new(&config);
+        let started = projector.project(GameEvent::MatchStarted {
+            blue: "alice".to_owned(),
+            red: "bob".to_owned(),
+        });
+        assert_eq!(started[0]["blue"], "player 1");
+        assert_eq!(started[0]["red"], "player 2");
+        let shown = projector.project(GameEvent::MatchFinished(Box::new(result)));
+        let shown = Value::Array(shown).to_string();
+        for hidden in &["alice", "bob", "always-defect", "tit-for-tat"] {
+            assert!(!shown.contains(hidden), "{} in {}", hidden, shown);
+        }
+        let standings = projector.standings();
+        assert_eq!(standings["standings"][0]["name"], "player 1");
+        assert_eq!(standings["standings"][0]["matches"], 1);
+    }
+
+    /// The status line the server answers a WebSocket handshake at `path`
+    /// with
+    async fn handshake(address: &str, path: &str) -> String {
+        let mut stream = TcpStream::connect(address).await.unwrap();
+        let request = format!(
+            "GET {} HTTP/1.1\r\nHost: {}\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+             Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n\r\n",
+            path, address
+        );
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = vec![0; 512];
+        let read = stream.read(&mut response).await.unwrap();
+        let response = String::from_utf8_lossy(&response[..read]).into_owned();
+        response.lines().next().unwrap_or_default().to_owned()
+    }
+
+    #[actix_rt::test]
+    async fn players_need_their_seats_token() {
+        let address = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .to_string();
+        let mut config = config();
+        config.participants[1].strategy = "player:bob".to_owned();
+        let bus = EventBus::default().start();
+        let (server, _) = serve(address.as_str(), bus, &config).unwrap();
+        let token = seats::open(vec!["bob"]).remove(0).1;
+
+        let refused = handshake(&address, "/play?token=guess").await;
+        assert!(refused.contains("401"), "{}", refused);
+        let seated = handshake(&address, &format!("/play?token={}", token)).await;
+        assert!(seated.contains("101"), "{}", seated);
+        let spectating = handshake(&address, "/spectate").await;
+        assert!(spectating.contains("101"), "{}", spectating);
+        server.stop(false).await;
+    }
+}